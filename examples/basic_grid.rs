@@ -17,11 +17,13 @@ fn main() -> Result<()> {
 
 /// Setup the initial scene
 fn setup_scene(world: &mut World, renderer: &mut qsi::graphics::Renderer) {
-    // Create a camera
-    world
-        .spawn()
-        .with(Transform::at_position(Vector3::new(10.0, 5.0, 10.0)))
-        .with(Camera::default());
+    // Create a camera looking at the cube; being active, it replaces the
+    // default orbit camera
+    let position = Vector3::new(10.0, 5.0, 10.0);
+    let mut camera_transform = Transform::at_position(position);
+    camera_transform.rotation =
+        qsi::camera::utils::look_at_rotation(position, Vector3::new(0.0, 0.0, 0.0));
+    world.spawn().with(camera_transform).with(Camera::default());
 
     // Create a spinning cube
     let cube_entity = world
@@ -36,7 +38,11 @@ fn setup_scene(world: &mut World, renderer: &mut qsi::graphics::Renderer) {
     world.add_component(cube_entity, cube_mesh);
 
     // Create the grid
-    let grid_entity = world.spawn().with(Transform::default()).build();
+    let grid_entity = world
+        .spawn()
+        .with(Name::new("grid"))
+        .with(Transform::default())
+        .build();
 
     let grid_mesh = create_grid_mesh(renderer, 50, 1.0);
     world.add_component(grid_entity, grid_mesh);
@@ -54,26 +60,14 @@ impl qsi::ecs::Component for SpinComponent {}
 fn physics_system(world: &mut World, _input: &qsi::input::InputState, time: &qsi::time::TimeState) {
     let dt = time.delta_seconds();
 
-    // Collect entities with both Transform and Velocity
-    let mut updates = Vec::new();
-
-    for (entity, velocity) in world.query::<qsi::math::Velocity>() {
-        if let Some(transform) = world.get_component::<Transform>(entity) {
-            let mut new_transform = transform.clone();
-
-            // Apply linear velocity
-            new_transform.position += velocity.linear * dt;
-
-            // Apply angular velocity
-            new_transform.rotation += velocity.angular * dt;
-
-            updates.push((entity, new_transform));
-        }
-    }
+    for (_, (transform, velocity)) in
+        world.query_many_mut::<(&mut Transform, &qsi::math::Velocity)>()
+    {
+        // Apply linear velocity
+        transform.position += velocity.linear * dt;
 
-    // Apply updates
-    for (entity, transform) in updates {
-        world.add_component(entity, transform);
+        // Apply angular velocity
+        transform.rotation += velocity.angular * dt;
     }
 }
 
@@ -106,39 +100,15 @@ fn create_cube_mesh(renderer: &qsi::graphics::Renderer) -> qsi::graphics::Mesh {
     let cube_color = [1.0, 0.5, 0.0]; // Orange
     let vertices = vec![
         // Front face
-        qsi::graphics::Vertex {
-            position: [-0.5, -0.5, 0.5],
-            color: cube_color,
-        },
-        qsi::graphics::Vertex {
-            position: [0.5, -0.5, 0.5],
-            color: cube_color,
-        },
-        qsi::graphics::Vertex {
-            position: [0.5, 0.5, 0.5],
-            color: cube_color,
-        },
-        qsi::graphics::Vertex {
-            position: [-0.5, 0.5, 0.5],
-            color: cube_color,
-        },
+        qsi::graphics::Vertex::new([-0.5, -0.5, 0.5], cube_color),
+        qsi::graphics::Vertex::new([0.5, -0.5, 0.5], cube_color),
+        qsi::graphics::Vertex::new([0.5, 0.5, 0.5], cube_color),
+        qsi::graphics::Vertex::new([-0.5, 0.5, 0.5], cube_color),
         // Back face
-        qsi::graphics::Vertex {
-            position: [-0.5, -0.5, -0.5],
-            color: cube_color,
-        },
-        qsi::graphics::Vertex {
-            position: [0.5, -0.5, -0.5],
-            color: cube_color,
-        },
-        qsi::graphics::Vertex {
-            position: [0.5, 0.5, -0.5],
-            color: cube_color,
-        },
-        qsi::graphics::Vertex {
-            position: [-0.5, 0.5, -0.5],
-            color: cube_color,
-        },
+        qsi::graphics::Vertex::new([-0.5, -0.5, -0.5], cube_color),
+        qsi::graphics::Vertex::new([0.5, -0.5, -0.5], cube_color),
+        qsi::graphics::Vertex::new([0.5, 0.5, -0.5], cube_color),
+        qsi::graphics::Vertex::new([-0.5, 0.5, -0.5], cube_color),
     ];
 
     let indices: Vec<u16> = vec![
@@ -176,14 +146,8 @@ fn create_grid_mesh(
             grid_color
         };
 
-        vertices.push(qsi::graphics::Vertex {
-            position: [-half_size, 0.0, z],
-            color,
-        });
-        vertices.push(qsi::graphics::Vertex {
-            position: [half_size, 0.0, z],
-            color,
-        });
+        vertices.push(qsi::graphics::Vertex::new([-half_size, 0.0, z], color));
+        vertices.push(qsi::graphics::Vertex::new([half_size, 0.0, z], color));
     }
 
     // Create vertices for vertical lines
@@ -195,14 +159,8 @@ fn create_grid_mesh(
             grid_color
         };
 
-        vertices.push(qsi::graphics::Vertex {
-            position: [x, 0.0, -half_size],
-            color,
-        });
-        vertices.push(qsi::graphics::Vertex {
-            position: [x, 0.0, half_size],
-            color,
-        });
+        vertices.push(qsi::graphics::Vertex::new([x, 0.0, -half_size], color));
+        vertices.push(qsi::graphics::Vertex::new([x, 0.0, half_size], color));
     }
 
     // Create indices for lines
@@ -0,0 +1,185 @@
+//! Shared assets referenced from entities by handle
+//!
+//! An `Assets<T>` resource owns each asset once, and entities carry a cheap
+//! `Handle<T>` component pointing at it. A thousand cubes spawned with the
+//! same `Handle<Mesh>` share one pair of GPU buffers, where a thousand `Mesh`
+//! components would allocate a thousand.
+//!
+//! ```rust,no_run
+//! use qsi::assets::Assets;
+//! use qsi::graphics::MeshSource;
+//! use qsi::prelude::*;
+//!
+//! App::new()
+//!     .add_startup_system(|world: &mut World, renderer: &mut Renderer| {
+//!         let cube = MeshSource::Cube { size: 0.5, color: [0.8; 3] }.build(renderer.device());
+//!         let cube = world.resource_mut::<Assets<Mesh>>().unwrap().add(cube);
+//!         for i in 0..1000 {
+//!             let position = Vector3::new((i % 32) as f32, 0.0, (i / 32) as f32);
+//!             world.spawn().with(Transform::at_position(position)).with(cube);
+//!         }
+//!     })
+//!     .run()
+//!     .unwrap();
+//! ```
+
+use crate::ecs::Component;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+mod server;
+#[cfg(feature = "hot-reload")]
+mod watch;
+
+pub use server::{Asset, AssetEvent, AssetServer, LoadState, update_asset_server};
+
+/// Reference to an asset in `Assets<T>`, copied freely onto entities
+pub struct Handle<T> {
+    id: u64,
+    _asset: PhantomData<fn() -> T>,
+}
+
+/// Source of handle IDs, shared by every `Assets<T>` and the `AssetServer`
+/// so handles can be made before their asset is stored
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+impl<T> Handle<T> {
+    /// Handle with an ID no other handle has
+    fn unique() -> Self {
+        Self::new(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn new(id: u64) -> Self {
+        Self {
+            id,
+            _asset: PhantomData,
+        }
+    }
+
+    /// Number identifying the asset
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+// Implemented by hand so handles are `Copy` and comparable whatever `T` is
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle<{}>({})", std::any::type_name::<T>(), self.id)
+    }
+}
+
+impl<T: 'static> Component for Handle<T> {}
+
+/// Store of shared assets of one type, kept as a resource
+///
+/// Assets live until removed, however many handles point at them; a handle
+/// to a removed asset resolves to nothing.
+///
+/// ```rust
+/// use qsi::assets::Assets;
+///
+/// let mut names = Assets::new();
+/// let handle = names.add(String::from("cube"));
+/// assert_eq!(names.get(handle).map(String::as_str), Some("cube"));
+/// names.remove(handle);
+/// assert!(names.get(handle).is_none());
+/// ```
+#[derive(Clone)]
+pub struct Assets<T> {
+    items: HashMap<u64, T>,
+}
+
+impl<T> Default for Assets<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for Assets<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Assets")
+            .field("len", &self.items.len())
+            .finish()
+    }
+}
+
+impl<T> Assets<T> {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self {
+            items: HashMap::new(),
+        }
+    }
+
+    /// Store `asset`, returning a new handle to it
+    pub fn add(&mut self, asset: T) -> Handle<T> {
+        let handle = Handle::unique();
+        self.items.insert(handle.id, asset);
+        handle
+    }
+
+    /// Replace the asset behind `handle`, so every entity holding it sees
+    /// the new one; returns the previous asset
+    pub fn set(&mut self, handle: Handle<T>, asset: T) -> Option<T> {
+        self.items.insert(handle.id, asset)
+    }
+
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        self.items.get(&handle.id)
+    }
+
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        self.items.get_mut(&handle.id)
+    }
+
+    /// Remove the asset, returning it
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        self.items.remove(&handle.id)
+    }
+
+    pub fn contains(&self, handle: Handle<T>) -> bool {
+        self.items.contains_key(&handle.id)
+    }
+
+    /// Every stored asset with its handle, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
+        self.items
+            .iter()
+            .map(|(&id, asset)| (Handle::new(id), asset))
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
@@ -0,0 +1,396 @@
+//! Loading assets from files on a background thread
+
+use super::{Assets, Handle};
+use crate::ecs::{Event, World};
+use crate::graphics::{Mesh, MeshData, Ply, Renderer, Shader, Stl, Texture, read_png_rgba8};
+use anyhow::{Context, Result, anyhow, bail};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::mpsc::{Receiver, Sender, channel};
+
+#[cfg(feature = "hot-reload")]
+use super::watch::{AssetWatcher, Reload};
+
+/// Type an `AssetServer` can load: read and parsed from a file on the loader
+/// thread, then uploaded on the main thread
+pub trait Asset: Sized + Send + Sync + 'static {
+    /// What reading the file produces, handed to `upload`
+    type Data: Send + 'static;
+
+    /// Read and parse the file; runs on the loader thread
+    fn read(path: &Path) -> Result<Self::Data>;
+
+    /// Create the asset from the parsed file; runs on the main thread
+    fn upload(data: Self::Data, renderer: &Renderer) -> Result<Self>;
+}
+
+/// STL (`.stl`) and PLY (`.ply`) files
+impl Asset for Mesh {
+    type Data = MeshData;
+
+    fn read(path: &Path) -> Result<MeshData> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        let mesh = match extension.as_deref() {
+            Some("stl") => Stl::load(path)?.build()?,
+            Some("ply") => Ply::load(path)?.build_mesh()?,
+            _ => bail!("Unsupported mesh file {}", path.display()),
+        };
+        Ok(mesh.into())
+    }
+
+    fn upload(data: MeshData, renderer: &Renderer) -> Result<Self> {
+        Ok(renderer.upload(&data))
+    }
+}
+
+/// PNG color textures
+impl Asset for Texture {
+    type Data = (u32, u32, Vec<u8>);
+
+    fn read(path: &Path) -> Result<Self::Data> {
+        read_png_rgba8(path)
+    }
+
+    fn upload((width, height, pixels): Self::Data, renderer: &Renderer) -> Result<Self> {
+        Texture::from_rgba8(renderer.device(), renderer.queue(), width, height, &pixels)
+    }
+}
+
+/// WGSL files
+impl Asset for Shader {
+    type Data = (PathBuf, String);
+
+    fn read(path: &Path) -> Result<Self::Data> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok((path.to_path_buf(), source))
+    }
+
+    fn upload((path, source): Self::Data, renderer: &Renderer) -> Result<Self> {
+        renderer.create_shader(&path.display().to_string(), source)
+    }
+}
+
+/// Progress of an asset requested from an `AssetServer`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadState {
+    /// Not requested from this server
+    NotLoaded,
+    /// Being read on the loader thread or waiting for upload
+    Loading,
+    /// Stored in its `Assets<T>` resource
+    Loaded,
+    /// Reading or uploading failed, with the error
+    Failed(String),
+}
+
+/// Sent when an asset requested from the `AssetServer` finishes loading, or
+/// is reloaded after its file changed
+#[derive(Debug)]
+pub enum AssetEvent<T> {
+    Loaded {
+        handle: Handle<T>,
+        path: PathBuf,
+    },
+    /// The file changed and the asset behind `handle` was replaced (with the
+    /// `hot-reload` feature); things built from it, like colliders, may need
+    /// rebuilding
+    Modified {
+        handle: Handle<T>,
+        path: PathBuf,
+    },
+    /// Loading failed, or reloading did, which keeps the previous asset
+    Failed {
+        handle: Handle<T>,
+        path: PathBuf,
+        error: String,
+    },
+}
+
+impl<T: 'static> Event for AssetEvent<T> {}
+
+pub(super) type ReadJob = Box<dyn FnOnce() -> Finish + Send>;
+type Finish = Box<dyn FnOnce(&mut World, &Renderer) + Send>;
+
+/// Resource loading meshes, textures, and shaders without blocking frames
+///
+/// `load` returns a handle right away and reads the file on a background
+/// thread; the asset appears in its `Assets<T>` resource a frame or so later,
+/// with an `AssetEvent::Loaded`. Entities can carry the handle in the
+/// meantime: a `Handle<Mesh>` draws nothing until its mesh arrives.
+///
+/// With the `hot-reload` feature, loaded files are watched, and an asset whose
+/// file changes is read again and replaced in place, followed by an
+/// `AssetEvent::Modified`.
+///
+/// ```rust,no_run
+/// use qsi::assets::{AssetEvent, AssetServer};
+/// use qsi::prelude::*;
+///
+/// App::new()
+///     .add_startup_system(|world: &mut World, _renderer: &mut Renderer| {
+///         let scan = world.resource_mut::<AssetServer>().unwrap().load::<Mesh>("scan.ply");
+///         world.spawn().with(Transform::default()).with(scan);
+///     })
+///     .add_system(|world: &mut World, _input: &InputState, _time: &TimeState| {
+///         for event in world.read_events::<AssetEvent<Mesh>>() {
+///             if let AssetEvent::Loaded { path, .. } = event {
+///                 log::info!("{} is ready", path.display());
+///             }
+///         }
+///     })
+///     .run()
+///     .unwrap();
+/// ```
+pub struct AssetServer {
+    root: PathBuf,
+    // Started on the first load
+    jobs: Option<Sender<ReadJob>>,
+    finished_sender: Sender<Finish>,
+    finished: Mutex<Receiver<Finish>>,
+    states: HashMap<u64, LoadState>,
+    // Handle ID of each loaded path, by asset type
+    paths: HashMap<(TypeId, PathBuf), u64>,
+    // Reloads read but not yet uploaded
+    reloading: usize,
+    // Started on the first load
+    #[cfg(feature = "hot-reload")]
+    watcher: Option<Mutex<AssetWatcher>>,
+}
+
+impl Default for AssetServer {
+    fn default() -> Self {
+        Self::new(".")
+    }
+}
+
+impl AssetServer {
+    /// Server resolving relative paths against `root`
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let (finished_sender, finished) = channel();
+        Self {
+            root: root.into(),
+            jobs: None,
+            finished_sender,
+            finished: Mutex::new(finished),
+            states: HashMap::new(),
+            paths: HashMap::new(),
+            reloading: 0,
+            #[cfg(feature = "hot-reload")]
+            watcher: None,
+        }
+    }
+
+    /// Directory relative paths are resolved against
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Start loading the file at `path` as a `T`, returning its handle
+    ///
+    /// Loading a path again returns the same handle without reading it twice.
+    pub fn load<T: Asset>(&mut self, path: impl AsRef<Path>) -> Handle<T> {
+        let path = self.root.join(path);
+        let key = (TypeId::of::<T>(), path.clone());
+        if let Some(&id) = self.paths.get(&key) {
+            return Handle::new(id);
+        }
+        let handle = Handle::unique();
+        self.paths.insert(key, handle.id);
+        self.states.insert(handle.id, LoadState::Loading);
+        #[cfg(feature = "hot-reload")]
+        self.watch(&path, handle.id, |id, path| {
+            read_job::<T>(Handle::new(id), path, true)
+        });
+        self.read(read_job(handle, path, false));
+        handle
+    }
+
+    fn read(&mut self, job: ReadJob) {
+        if let Err(error) = self.loader().send(job) {
+            // Without a loader thread, read on this one rather than never
+            let finish = (error.0)();
+            let _ = self.finished_sender.send(finish);
+        }
+    }
+
+    /// Progress of the asset behind `handle`
+    pub fn load_state<T>(&self, handle: Handle<T>) -> LoadState {
+        self.states
+            .get(&handle.id)
+            .cloned()
+            .unwrap_or(LoadState::NotLoaded)
+    }
+
+    /// Whether the asset behind `handle` is ready
+    pub fn is_loaded<T>(&self, handle: Handle<T>) -> bool {
+        self.load_state(handle) == LoadState::Loaded
+    }
+
+    /// Path `handle` was loaded from
+    pub fn path<T: 'static>(&self, handle: Handle<T>) -> Option<&Path> {
+        self.paths
+            .iter()
+            .find(|((type_id, _), id)| *type_id == TypeId::of::<T>() && **id == handle.id)
+            .map(|((_, path), _)| path.as_path())
+    }
+
+    /// Number of assets still loading or reloading
+    pub fn pending(&self) -> usize {
+        self.reloading
+            + self
+                .states
+                .values()
+                .filter(|state| **state == LoadState::Loading)
+                .count()
+    }
+
+    #[cfg(feature = "hot-reload")]
+    fn watch(&mut self, path: &Path, id: u64, reload: Reload) {
+        let watcher = match &mut self.watcher {
+            Some(watcher) => watcher,
+            None => match AssetWatcher::new() {
+                Ok(watcher) => self.watcher.insert(Mutex::new(watcher)),
+                Err(e) => {
+                    log::error!("{e:#}");
+                    return;
+                }
+            },
+        };
+        if let Ok(watcher) = watcher.get_mut()
+            && let Err(e) = watcher.watch(path, id, reload)
+        {
+            log::warn!("Not reloading {}: {e:#}", path.display());
+        }
+    }
+
+    /// Read changed files again, waking `window` on later changes
+    #[cfg(feature = "hot-reload")]
+    fn reload_changed(&mut self, window: Option<&std::sync::Arc<winit::window::Window>>) {
+        let Some(Ok(watcher)) = self.watcher.as_mut().map(Mutex::get_mut) else {
+            return;
+        };
+        if let Some(window) = window {
+            watcher.set_window(window);
+        }
+        for job in watcher.changed() {
+            self.reloading += 1;
+            self.read(job);
+        }
+    }
+
+    fn loader(&mut self) -> &Sender<ReadJob> {
+        self.jobs.get_or_insert_with(|| {
+            let (jobs, receiver) = channel::<ReadJob>();
+            let finished = self.finished_sender.clone();
+            let spawned = std::thread::Builder::new()
+                .name("qsi-asset-loader".into())
+                .spawn(move || {
+                    for job in receiver {
+                        if finished.send(job()).is_err() {
+                            break;
+                        }
+                    }
+                });
+            if let Err(e) = spawned {
+                log::error!("Failed to start the asset loader thread: {e}");
+            }
+            jobs
+        })
+    }
+
+    fn take_finished(&self) -> Vec<Finish> {
+        self.finished
+            .lock()
+            .map(|finished| finished.try_iter().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Job reading `path` on the loader thread, then uploading and storing it as
+/// the asset behind `handle`
+fn read_job<T: Asset>(handle: Handle<T>, path: PathBuf, reload: bool) -> ReadJob {
+    Box::new(move || {
+        // A panicking reader fails this asset instead of killing the loader thread
+        let data = catch_unwind(AssertUnwindSafe(|| T::read(&path)))
+            .unwrap_or_else(|_| Err(anyhow!("reader panicked")));
+        Box::new(move |world: &mut World, renderer: &Renderer| {
+            let result = data.and_then(|data| T::upload(data, renderer));
+            finish(world, handle, path, result, reload);
+        })
+    })
+}
+
+/// Store a loaded asset, record its state, and send its event
+fn finish<T: Asset>(
+    world: &mut World,
+    handle: Handle<T>,
+    path: PathBuf,
+    result: Result<T>,
+    reload: bool,
+) {
+    let (state, event) = match result {
+        Ok(asset) => {
+            if !world.has_resource::<Assets<T>>() {
+                world.insert_resource(Assets::<T>::new());
+            }
+            if let Some(assets) = world.resource_mut::<Assets<T>>() {
+                assets.set(handle, asset);
+            }
+            let event = if reload {
+                AssetEvent::Modified { handle, path }
+            } else {
+                AssetEvent::Loaded { handle, path }
+            };
+            (LoadState::Loaded, event)
+        }
+        Err(e) => {
+            let error = format!("{e:#}");
+            let action = if reload { "reload" } else { "load" };
+            log::error!("Failed to {action} {}: {error}", path.display());
+            // A failed reload leaves the previous asset in place
+            let state = if reload {
+                LoadState::Loaded
+            } else {
+                LoadState::Failed(error.clone())
+            };
+            (
+                state,
+                AssetEvent::Failed {
+                    handle,
+                    path,
+                    error,
+                },
+            )
+        }
+    };
+    if let Some(server) = world.resource_mut::<AssetServer>() {
+        server.states.insert(handle.id, state);
+        if reload {
+            server.reloading = server.reloading.saturating_sub(1);
+        }
+    }
+    world.send(event);
+}
+
+/// Upload assets the loader thread has finished reading (run once per frame
+/// by the app)
+pub fn update_asset_server(world: &mut World, renderer: &Renderer) {
+    let finished = match world.resource_mut::<AssetServer>() {
+        Some(server) => {
+            #[cfg(feature = "hot-reload")]
+            server.reload_changed(renderer.window.as_ref());
+            server.take_finished()
+        }
+        None => return,
+    };
+    for finish in finished {
+        finish(world, renderer);
+    }
+}
@@ -0,0 +1,100 @@
+//! Reloading assets when their files change on disk
+
+use super::server::ReadJob;
+use anyhow::{Context, Result};
+use notify::Watcher;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, channel};
+use std::sync::{Arc, Mutex};
+use winit::window::Window;
+
+/// Read job reloading the asset with a handle ID from a path
+pub(super) type Reload = fn(u64, PathBuf) -> ReadJob;
+
+/// File watcher collecting changed asset paths
+pub(super) struct AssetWatcher {
+    watcher: notify::RecommendedWatcher,
+    changes: Receiver<PathBuf>,
+    // Handle ID, path as loaded, and reload job of each asset, by canonical
+    // path
+    files: HashMap<PathBuf, Vec<(u64, PathBuf, Reload)>>,
+    dirs: HashSet<PathBuf>,
+    // Woken on changes, since frames are drawn on demand
+    window: Arc<Mutex<Option<Arc<Window>>>>,
+}
+
+impl AssetWatcher {
+    pub(super) fn new() -> Result<Self> {
+        let (sender, changes) = channel();
+        let window = Arc::new(Mutex::new(None::<Arc<Window>>));
+        let wake = window.clone();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            if event.kind.is_modify() || event.kind.is_create() {
+                for path in event.paths {
+                    let _ = sender.send(path);
+                }
+                if let Ok(window) = wake.lock()
+                    && let Some(window) = window.as_ref()
+                {
+                    window.request_redraw();
+                }
+            }
+        })
+        .context("Failed to start asset file watcher")?;
+        Ok(Self {
+            watcher,
+            changes,
+            files: HashMap::new(),
+            dirs: HashSet::new(),
+            window,
+        })
+    }
+
+    /// Reload the asset with handle `id` whenever the file at `path` changes
+    pub(super) fn watch(&mut self, path: &Path, id: u64, reload: Reload) -> Result<()> {
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            return Ok(());
+        };
+        // The file may not exist yet, but its directory has to
+        let dir = dir
+            .canonicalize()
+            .with_context(|| format!("Asset directory not found: {}", dir.display()))?;
+        // Watch the directory rather than the file, since many editors save by
+        // replacing the file
+        if self.dirs.insert(dir.clone()) {
+            self.watcher
+                .watch(&dir, notify::RecursiveMode::NonRecursive)
+                .with_context(|| format!("Failed to watch {}", dir.display()))?;
+        }
+        self.files
+            .entry(dir.join(name))
+            .or_default()
+            .push((id, path.to_path_buf(), reload));
+        Ok(())
+    }
+
+    /// Window to wake when a watched file changes
+    pub(super) fn set_window(&self, window: &Arc<Window>) {
+        if let Ok(mut current) = self.window.lock()
+            && current.is_none()
+        {
+            *current = Some(window.clone());
+        }
+    }
+
+    /// Reload jobs for assets whose files changed since the last call, each
+    /// listed once
+    pub(super) fn changed(&self) -> Vec<ReadJob> {
+        let paths: HashSet<PathBuf> = self.changes.try_iter().collect();
+        paths
+            .iter()
+            .filter_map(|path| self.files.get(path))
+            .flatten()
+            .map(|(id, path, reload)| reload(*id, path.clone()))
+            .collect()
+    }
+}
@@ -1,12 +1,24 @@
 //! Camera component and controller for 3D rendering
 
 use crate::ecs::{Component, EntityId, World};
-use crate::math::{Matrix4, Point3, Transform, Vector3};
-use cgmath::{Deg, EuclideanSpace, perspective};
+use crate::graphics::Viewport;
+use crate::input::InputState;
+use crate::math::{Matrix4, Point3, Ray, Transform, Vector3};
+use cgmath::{Deg, EuclideanSpace, InnerSpace, SquareMatrix, Vector4, perspective};
 use winit::event::{ElementState, MouseButton};
 
+mod presets;
+
+pub use presets::{CameraPose, CameraPreset, CameraPresets};
+
 /// Camera component that defines viewing parameters
+///
+/// The renderer draws through the active camera, with its field of view and
+/// clip planes. The app's default camera, marked `OrbitCamera`, follows the
+/// mouse-driven controller; any other active camera takes over from it and is
+/// viewed from its `Transform` (see `utils::set_active_camera`).
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Camera {
     /// Whether this camera is currently active for rendering
     pub is_active: bool,
@@ -48,6 +60,58 @@ impl Camera {
     }
 }
 
+/// Marker on the app's default camera, the one the orbit controller moves
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OrbitCamera;
+
+impl Component for OrbitCamera {}
+
+/// Resource holding the view the last frame was drawn from, so update
+/// systems can turn the cursor into a picking ray
+///
+/// ```no_run
+/// use qsi::camera::ActiveView;
+/// use qsi::prelude::*;
+/// use winit::event::MouseButton;
+///
+/// fn select(world: &mut World, input: &InputState, _: &TimeState) {
+///     if !input.mouse_button_just_pressed(MouseButton::Left) {
+///         return;
+///     }
+///     let ray = world.resource::<ActiveView>().and_then(|view| view.cursor_ray(input));
+///     if let Some(hit) = ray.and_then(|ray| world.pick(&ray)) {
+///         println!("picked {} at {:?}", hit.entity, hit.point);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActiveView {
+    /// Camera entity drawn through
+    pub camera: Option<EntityId>,
+    pub view: Matrix4<f32>,
+    pub projection: Matrix4<f32>,
+    /// Part of the window the scene was drawn into, in physical pixels
+    pub viewport: Viewport,
+}
+
+impl ActiveView {
+    /// `projection * view`
+    pub fn view_projection(&self) -> Matrix4<f32> {
+        self.projection * self.view
+    }
+
+    /// Ray through a point in physical pixels from the window's top left
+    pub fn screen_to_ray(&self, cursor: (f32, f32)) -> Option<Ray> {
+        utils::screen_to_ray(cursor, &self.view_projection(), &self.viewport)
+    }
+
+    /// Ray through the cursor
+    pub fn cursor_ray(&self, input: &InputState) -> Option<Ray> {
+        let (x, y) = input.cursor_position_physical();
+        self.screen_to_ray((x as f32, y as f32))
+    }
+}
+
 /// Camera controller for orbital movement around a target
 pub struct CameraController {
     /// Distance from the center point
@@ -70,6 +134,8 @@ pub struct CameraController {
     pub zoom_sensitivity: f32,
     /// Minimum and maximum zoom distances
     pub zoom_range: (f32, f32),
+    /// Animated move to a pose: start, target, elapsed and total seconds
+    transition: Option<(CameraPose, CameraPose, f32, f32)>,
 }
 
 impl CameraController {
@@ -87,6 +153,7 @@ impl CameraController {
             sensitivity: 0.01,
             zoom_sensitivity: 0.1,
             zoom_range: (2.0, 50.0),
+            transition: None,
         }
     }
 
@@ -105,6 +172,57 @@ impl CameraController {
         self.radius = radius.clamp(self.zoom_range.0, self.zoom_range.1);
     }
 
+    /// Current center, distance, and angles
+    pub fn pose(&self) -> CameraPose {
+        CameraPose {
+            center: self.center,
+            radius: self.radius,
+            azimuth: self.theta,
+            elevation: std::f32::consts::FRAC_PI_2 - self.phi,
+        }
+    }
+
+    /// Jump to a pose, cancelling any transition
+    pub fn set_pose(&mut self, pose: CameraPose) {
+        self.transition = None;
+        self.apply_pose(&pose);
+    }
+
+    /// Move to a pose over `duration` seconds with ease-in-out
+    pub fn animate_to(&mut self, pose: CameraPose, duration: f32) {
+        if duration <= 0.0 {
+            self.set_pose(pose);
+        } else {
+            self.transition = Some((self.pose(), pose, 0.0, duration));
+        }
+    }
+
+    /// True while an `animate_to` transition is running
+    pub fn is_animating(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    /// Advance the transition; returns true if the camera moved
+    pub fn update_transition(&mut self, delta_seconds: f32) -> bool {
+        let Some((from, to, elapsed, duration)) = self.transition else {
+            return false;
+        };
+        let elapsed = elapsed + delta_seconds;
+        let t = (elapsed / duration).min(1.0);
+        self.apply_pose(&from.lerp(&to, t * t * (3.0 - 2.0 * t)));
+        self.transition = (t < 1.0).then_some((from, to, elapsed, duration));
+        true
+    }
+
+    fn apply_pose(&mut self, pose: &CameraPose) {
+        self.center = pose.center;
+        self.radius = pose.radius.clamp(self.zoom_range.0, self.zoom_range.1);
+        self.theta = pose.azimuth;
+        // Same clamp as dragging, so looking straight up or down stays stable
+        self.phi =
+            (std::f32::consts::FRAC_PI_2 - pose.elevation).clamp(0.1, std::f32::consts::PI - 0.1);
+    }
+
     /// Get the current camera position based on spherical coordinates
     pub fn position(&self) -> Point3<f32> {
         let x = self.center.x + self.radius * self.phi.sin() * self.theta.cos();
@@ -128,6 +246,7 @@ impl CameraController {
                 ElementState::Pressed => {
                     self.is_dragging = true;
                     self.last_mouse_pos = self.cursor_pos;
+                    self.transition = None;
                 }
                 ElementState::Released => {
                     self.is_dragging = false;
@@ -158,8 +277,14 @@ impl CameraController {
         true
     }
 
+    /// Rotate horizontally around the center by `angle` radians
+    pub fn orbit(&mut self, angle: f32) {
+        self.theta = (self.theta + angle) % std::f32::consts::TAU;
+    }
+
     /// Handle mouse wheel for zoom - returns true if camera changed
     pub fn mouse_wheel(&mut self, delta: f32) -> bool {
+        self.transition = None;
         self.radius -= delta * self.zoom_sensitivity;
         self.radius = self.radius.clamp(self.zoom_range.0, self.zoom_range.1);
         true
@@ -187,6 +312,48 @@ impl Default for CameraController {
     }
 }
 
+/// Resource that slowly orbits the default camera around its center after a
+/// period without input, like a screensaver for demos and showcases
+///
+/// Any input stops the orbit; it resumes after another idle period.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoOrbit {
+    /// Seconds without input before orbiting starts
+    pub idle_after: f32,
+    /// Orbit speed in radians per second (negative for clockwise from above)
+    pub speed: f32,
+}
+
+impl Default for AutoOrbit {
+    fn default() -> Self {
+        Self {
+            idle_after: 30.0,
+            speed: 0.2,
+        }
+    }
+}
+
+impl AutoOrbit {
+    /// Start orbiting after `idle_after` seconds without input
+    pub fn new(idle_after: f32) -> Self {
+        Self {
+            idle_after,
+            ..Default::default()
+        }
+    }
+
+    /// Set the orbit speed in radians per second
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Seconds of input idleness left before orbiting starts (zero while orbiting)
+    pub fn remaining(&self, input: &InputState) -> f32 {
+        (self.idle_after - input.idle_seconds()).max(0.0)
+    }
+}
+
 /// Utility functions for camera operations
 pub mod utils {
     use super::*;
@@ -203,6 +370,53 @@ pub mod utils {
         None
     }
 
+    /// Camera the renderer draws through: the active camera with the lowest
+    /// ID, preferring any other over `orbit`, the controller's default camera
+    pub fn find_render_camera(
+        world: &World,
+        orbit: Option<EntityId>,
+    ) -> Option<(EntityId, &Camera, &Transform)> {
+        world
+            .query::<Camera>()
+            .filter(|(_, camera)| camera.is_active)
+            .filter_map(|(entity, camera)| {
+                let transform = world.get_component::<Transform>(entity)?;
+                Some((entity, camera, transform))
+            })
+            .min_by_key(|(entity, ..)| (Some(*entity) == orbit, *entity))
+    }
+
+    /// Make `entity` the only active camera; returns false, changing
+    /// nothing, if it has no `Camera`
+    pub fn set_active_camera(world: &mut World, entity: EntityId) -> bool {
+        if world.get_component::<Camera>(entity).is_none() {
+            return false;
+        }
+        let cameras: Vec<EntityId> = world.query::<Camera>().map(|(id, _)| id).collect();
+        for id in cameras {
+            if let Some(camera) = world.get_component_mut::<Camera>(id) {
+                camera.is_active = id == entity;
+            }
+        }
+        true
+    }
+
+    /// Euler rotation that points a camera at `position` toward `target`,
+    /// as `view_matrix_from_transform` reads it
+    pub fn look_at_rotation(position: Vector3<f32>, target: Vector3<f32>) -> Vector3<f32> {
+        let direction = target - position;
+        let length = direction.magnitude();
+        if length <= f32::EPSILON {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+        let direction = direction / length;
+        Vector3::new(
+            direction.y.clamp(-1.0, 1.0).asin(),
+            direction.z.atan2(direction.x),
+            0.0,
+        )
+    }
+
     /// Create a view matrix from a transform
     pub fn view_matrix_from_transform(transform: &Transform) -> Matrix4<f32> {
         // Convert position to Point3
@@ -220,4 +434,29 @@ pub mod utils {
 
         Matrix4::look_at_rh(position, target, up)
     }
+
+    /// Ray from the camera through `cursor` (physical pixels from the top
+    /// left of the target), for picking with `World::pick`
+    ///
+    /// `view_projection` is `projection * view` of a camera drawing into
+    /// `viewport`, with OpenGL depth like `Camera::projection_matrix`;
+    /// `ActiveView::cursor_ray` fills these in for the main view. `None` if
+    /// the matrix cannot be inverted.
+    pub fn screen_to_ray(
+        (x, y): (f32, f32),
+        view_projection: &Matrix4<f32>,
+        viewport: &Viewport,
+    ) -> Option<Ray> {
+        let inverse = view_projection.invert()?;
+        let ndc_x = (x - viewport.x) / viewport.width.max(1.0) * 2.0 - 1.0;
+        let ndc_y = 1.0 - (y - viewport.y) / viewport.height.max(1.0) * 2.0;
+        let unproject = |z: f32| {
+            let point = inverse * Vector4::new(ndc_x, ndc_y, z, 1.0);
+            point.truncate() / point.w
+        };
+        // From the near plane towards the far one
+        let (near, far) = (unproject(-1.0), unproject(1.0));
+        let direction = far - near;
+        (direction.magnitude2() > 0.0 && direction.x.is_finite()).then(|| Ray::new(near, direction))
+    }
 }
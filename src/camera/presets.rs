@@ -0,0 +1,230 @@
+//! Camera presets and bookmarked poses recalled from the keyboard
+
+use super::CameraController;
+use crate::input::InputState;
+use crate::math::Point3;
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+use winit::keyboard::KeyCode;
+
+/// Placement of the orbit camera: the point it looks at, its distance, and
+/// its angles around that point
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CameraPose {
+    pub center: Point3<f32>,
+    pub radius: f32,
+    /// Angle around the vertical axis in radians; 0 places the camera on +X,
+    /// π/2 on +Z
+    pub azimuth: f32,
+    /// Angle above the horizon in radians
+    pub elevation: f32,
+}
+
+impl CameraPose {
+    /// Pose looking at `center` from `radius` away
+    pub fn new(center: Point3<f32>, radius: f32, azimuth: f32, elevation: f32) -> Self {
+        Self {
+            center,
+            radius,
+            azimuth,
+            elevation,
+        }
+    }
+
+    /// Blend towards `other` by `t` (0..1), turning the short way around
+    pub fn lerp(&self, other: &CameraPose, t: f32) -> CameraPose {
+        use std::f32::consts::{PI, TAU};
+
+        let turn = (other.azimuth - self.azimuth + PI).rem_euclid(TAU) - PI;
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        CameraPose {
+            center: Point3::new(
+                mix(self.center.x, other.center.x),
+                mix(self.center.y, other.center.y),
+                mix(self.center.z, other.center.z),
+            ),
+            radius: mix(self.radius, other.radius),
+            azimuth: self.azimuth + turn * t,
+            elevation: mix(self.elevation, other.elevation),
+        }
+    }
+}
+
+/// A camera placement recalled by a key
+#[derive(Debug, Clone, PartialEq)]
+pub enum CameraPreset {
+    /// Looking along -Z
+    Front,
+    /// Looking (nearly) straight down
+    Top,
+    /// Looking along -X
+    Right,
+    /// Isometric view from the front right
+    Iso,
+    /// A named pose stored with `CameraPresets::bookmark`
+    Bookmark(String),
+}
+
+impl CameraPreset {
+    /// Pose for this preset, keeping the current center and distance
+    fn pose(
+        &self,
+        current: &CameraPose,
+        bookmarks: &HashMap<String, CameraPose>,
+    ) -> Option<CameraPose> {
+        let view = |azimuth, elevation| CameraPose {
+            azimuth,
+            elevation,
+            ..*current
+        };
+        match self {
+            CameraPreset::Front => Some(view(FRAC_PI_2, 0.0)),
+            CameraPreset::Top => Some(view(FRAC_PI_2, FRAC_PI_2)),
+            CameraPreset::Right => Some(view(0.0, 0.0)),
+            CameraPreset::Iso => Some(view(FRAC_PI_4, 0.5f32.sqrt().atan())),
+            CameraPreset::Bookmark(name) => bookmarks.get(name).copied(),
+        }
+    }
+}
+
+/// Resource enabling keyboard camera presets with animated transitions
+///
+/// By default 1–4 (top row or numpad) switch to the front, top, right, and
+/// iso views, and 5–9 recall bookmarks named "5"–"9"; holding Ctrl while
+/// pressing 5–9 stores the current pose there instead.
+pub struct CameraPresets {
+    /// Seconds an animated transition takes (0 jumps immediately)
+    pub transition_time: f32,
+    bindings: Vec<(KeyCode, CameraPreset)>,
+    bookmarks: HashMap<String, CameraPose>,
+    current: Option<CameraPose>,
+    requested: Option<CameraPreset>,
+}
+
+/// Keys for bookmark slots "5" to "9"
+const BOOKMARK_KEYS: [(KeyCode, KeyCode, &str); 5] = [
+    (KeyCode::Digit5, KeyCode::Numpad5, "5"),
+    (KeyCode::Digit6, KeyCode::Numpad6, "6"),
+    (KeyCode::Digit7, KeyCode::Numpad7, "7"),
+    (KeyCode::Digit8, KeyCode::Numpad8, "8"),
+    (KeyCode::Digit9, KeyCode::Numpad9, "9"),
+];
+
+impl Default for CameraPresets {
+    fn default() -> Self {
+        let mut presets = Self::empty();
+        for (digit, numpad, preset) in [
+            (KeyCode::Digit1, KeyCode::Numpad1, CameraPreset::Front),
+            (KeyCode::Digit2, KeyCode::Numpad2, CameraPreset::Top),
+            (KeyCode::Digit3, KeyCode::Numpad3, CameraPreset::Right),
+            (KeyCode::Digit4, KeyCode::Numpad4, CameraPreset::Iso),
+        ] {
+            presets.bind(digit, preset.clone());
+            presets.bind(numpad, preset);
+        }
+        for (digit, numpad, name) in BOOKMARK_KEYS {
+            presets.bind(digit, CameraPreset::Bookmark(name.into()));
+            presets.bind(numpad, CameraPreset::Bookmark(name.into()));
+        }
+        presets
+    }
+}
+
+impl CameraPresets {
+    /// Presets with no key bindings (use `bind` and `go_to`)
+    pub fn empty() -> Self {
+        Self {
+            transition_time: 0.4,
+            bindings: Vec::new(),
+            bookmarks: HashMap::new(),
+            current: None,
+            requested: None,
+        }
+    }
+
+    /// Set the transition time in seconds
+    pub fn with_transition_time(mut self, seconds: f32) -> Self {
+        self.transition_time = seconds;
+        self
+    }
+
+    /// Recall `preset` when `key` is pressed, replacing the key's previous binding
+    pub fn bind(&mut self, key: KeyCode, preset: CameraPreset) {
+        self.bindings.retain(|(bound, _)| *bound != key);
+        self.bindings.push((key, preset));
+    }
+
+    /// Store a named pose
+    pub fn bookmark(&mut self, name: impl Into<String>, pose: CameraPose) {
+        self.bookmarks.insert(name.into(), pose);
+    }
+
+    /// Store the camera's current pose under `name`
+    pub fn bookmark_current(&mut self, name: impl Into<String>) {
+        if let Some(pose) = self.current {
+            self.bookmark(name, pose);
+        }
+    }
+
+    /// A stored pose
+    pub fn get_bookmark(&self, name: &str) -> Option<&CameraPose> {
+        self.bookmarks.get(name)
+    }
+
+    /// Remove a stored pose
+    pub fn remove_bookmark(&mut self, name: &str) -> Option<CameraPose> {
+        self.bookmarks.remove(name)
+    }
+
+    /// Camera pose as of the last frame
+    pub fn current(&self) -> Option<CameraPose> {
+        self.current
+    }
+
+    /// Animate to `preset` on the next frame
+    pub fn go_to(&mut self, preset: CameraPreset) {
+        self.requested = Some(preset);
+    }
+
+    /// Handle preset keys and start the requested transition; returns true if
+    /// the camera is moving
+    pub(crate) fn apply(
+        &mut self,
+        input: &InputState,
+        controller: &mut CameraController,
+        delta_seconds: f32,
+    ) -> bool {
+        let current = controller.pose();
+        self.current = Some(current);
+
+        let control = input.modifiers().control_key();
+        for (digit, numpad, name) in BOOKMARK_KEYS {
+            if control && (input.key_just_pressed(digit) || input.key_just_pressed(numpad)) {
+                self.bookmark(name, current);
+                return controller.update_transition(delta_seconds.min(0.1));
+            }
+        }
+        if let Some((_, preset)) = self
+            .bindings
+            .iter()
+            .find(|(key, _)| input.key_just_pressed(*key))
+        {
+            self.requested = Some(preset.clone());
+        }
+
+        // Frames only arrive on demand, so the first one after a key press can
+        // follow a long gap; start transitions from zero and cap later steps
+        match self
+            .requested
+            .take()
+            .and_then(|preset| preset.pose(&current, &self.bookmarks))
+        {
+            Some(target) => {
+                controller.animate_to(target, self.transition_time);
+                controller.update_transition(0.0)
+            }
+            None => controller.update_transition(delta_seconds.min(0.1)),
+        }
+    }
+}
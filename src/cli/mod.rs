@@ -0,0 +1,207 @@
+//! Common command-line flags for qsi binaries
+//!
+//! `CliOptions` parses the flags most apps want (see `USAGE`): `--headless`,
+//! `--frames`, `--width`, `--height`, `--backend`, `--seed`, `--scene`, and
+//! `--run`, and applies them to an `App`. Anything it does not know is left in
+//! `CliOptions::extra` for the app. Values may follow the flag or an equals
+//! sign, as in `--frames=600`.
+//!
+//! ```rust,no_run
+//! use qsi::cli::CliOptions;
+//! use qsi::prelude::*;
+//!
+//! # fn setup(_: &mut World, _: &mut Renderer) {}
+//! let options = CliOptions::from_env()?;
+//! options.apply(App::new()).add_startup_system(setup).run()?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::App;
+use crate::commands::Commands;
+use crate::ecs::World;
+use crate::input::InputState;
+use crate::math::Rng;
+use crate::time::TimeState;
+use crate::window::DEFAULT_WINDOW_SIZE;
+use anyhow::{Context, Result, bail};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Offscreen size used by `--headless` without `--width` and `--height`
+pub const DEFAULT_HEADLESS_SIZE: (u32, u32) = (1280, 720);
+
+/// Usage text for the flags `CliOptions` understands
+pub const USAGE: &str = "\
+Options:
+  --headless           Render offscreen without a window
+  --frames N           Stop after N frames
+  --width W            Window or offscreen width in pixels
+  --height H           Window or offscreen height in pixels
+  --backend NAME       vulkan, metal, dx12, gl, primary, or all
+  --seed N             Seed of the random number generator
+  --scene FILE         Load a .ron or .json scene at startup
+  --run COMMAND        Run a command such as \"spawn Transform\" on the first frame; repeatable
+  --help               Print this help";
+
+/// Flags parsed from the command line
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CliOptions {
+    pub headless: bool,
+    pub frames: Option<u64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub backends: Option<wgpu::Backends>,
+    pub seed: Option<u64>,
+    pub scene: Option<PathBuf>,
+    /// Command lines for `Commands::execute`, in order
+    pub commands: Vec<String>,
+    pub help: bool,
+    /// Arguments not recognized above, in order
+    pub extra: Vec<String>,
+}
+
+impl CliOptions {
+    /// Parse the process arguments; prints `USAGE` and exits on `--help`
+    pub fn from_env() -> Result<Self> {
+        let options = Self::parse(std::env::args().skip(1))?;
+        if options.help {
+            let program = std::env::args().next().unwrap_or_default();
+            println!("Usage: {program} [options]\n\n{USAGE}");
+            std::process::exit(0);
+        }
+        Ok(options)
+    }
+
+    /// Parse `args`, not including the program name
+    ///
+    /// ```rust
+    /// use qsi::cli::CliOptions;
+    ///
+    /// let options =
+    ///     CliOptions::parse(["--headless", "--frames=60", "--run", "pause", "--speed", "2"])
+    ///         .unwrap();
+    /// assert!(options.headless);
+    /// assert_eq!(options.frames, Some(60));
+    /// assert_eq!(options.commands, ["pause"]);
+    /// assert_eq!(options.extra, ["--speed", "2"]);
+    /// ```
+    pub fn parse<S: Into<String>>(args: impl IntoIterator<Item = S>) -> Result<Self> {
+        let mut options = Self::default();
+        let mut args = args.into_iter().map(Into::into);
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .with_context(|| format!("{flag} needs a value"))
+            };
+            match flag.as_str() {
+                "--headless" => options.headless = true,
+                "--help" | "-h" => options.help = true,
+                "--frames" => options.frames = Some(parse_number(&flag, &value()?)?),
+                "--width" => options.width = Some(parse_number(&flag, &value()?)?),
+                "--height" => options.height = Some(parse_number(&flag, &value()?)?),
+                "--seed" => options.seed = Some(parse_number(&flag, &value()?)?),
+                "--backend" => options.backends = Some(parse_backends(&value()?)?),
+                "--scene" => options.scene = Some(value()?.into()),
+                "--run" => options.commands.push(value()?),
+                _ => options.extra.push(arg),
+            }
+        }
+        Ok(options)
+    }
+
+    /// Set up `app` as the flags ask
+    pub fn apply(&self, mut app: App) -> App {
+        if self.headless {
+            let (width, height) = DEFAULT_HEADLESS_SIZE;
+            app = app.with_headless(self.width.unwrap_or(width), self.height.unwrap_or(height));
+        } else if self.width.is_some() || self.height.is_some() {
+            let (width, height) = app.window.inner_size.unwrap_or(DEFAULT_WINDOW_SIZE);
+            app = app.with_window_size(self.width.unwrap_or(width), self.height.unwrap_or(height));
+        }
+        if let Some(frames) = self.frames {
+            app = app.with_max_frames(frames);
+        }
+        if let Some(backends) = self.backends {
+            app = app.with_backends(backends);
+        }
+        if let Some(seed) = self.seed {
+            app = app.insert_resource(Rng::new(seed));
+        }
+        if let Some(path) = self.scene.clone() {
+            app = load_scene_at_startup(app, path);
+        }
+        if !self.commands.is_empty() {
+            app = run_commands_on_first_frame(app, self.commands.clone());
+        }
+        app
+    }
+}
+
+/// Run `lines` once, after startup systems have built the world
+fn run_commands_on_first_frame(app: App, lines: Vec<String>) -> App {
+    use crate::schedule::IntoSystemDescriptor;
+
+    let pending = Mutex::new(lines);
+    app.add_system(
+        (move |world: &mut World, _: &InputState, _: &TimeState| {
+            let lines = std::mem::take(&mut *pending.lock().unwrap_or_else(|e| e.into_inner()));
+            for line in lines {
+                match Commands::execute(world, &line) {
+                    Ok(output) if !output.is_empty() => log::info!("{line}: {output}"),
+                    Ok(_) => {}
+                    Err(e) => log::error!("{line}: {e:#}"),
+                }
+            }
+        })
+        .named("qsi::cli::run_commands"),
+    )
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T> {
+    value
+        .parse()
+        .ok()
+        .with_context(|| format!("{flag} expects a whole number, got `{value}`"))
+}
+
+/// Backend set named on the command line
+fn parse_backends(name: &str) -> Result<wgpu::Backends> {
+    Ok(match name.to_ascii_lowercase().as_str() {
+        "vulkan" | "vk" => wgpu::Backends::VULKAN,
+        "metal" => wgpu::Backends::METAL,
+        "dx12" | "d3d12" => wgpu::Backends::DX12,
+        "gl" | "opengl" | "gles" => wgpu::Backends::GL,
+        "webgpu" => wgpu::Backends::BROWSER_WEBGPU,
+        "primary" => wgpu::Backends::PRIMARY,
+        "all" => wgpu::Backends::all(),
+        _ => bail!("Unknown backend `{name}`; expected vulkan, metal, dx12, gl, primary, or all"),
+    })
+}
+
+#[cfg(feature = "serde")]
+fn load_scene_at_startup(app: App, path: PathBuf) -> App {
+    app.add_startup_system(
+        move |world: &mut crate::ecs::World, _: &mut crate::graphics::Renderer| {
+            if let Err(e) = world.load_scene(&path) {
+                log::error!("{e:#}");
+            }
+        },
+    )
+}
+
+#[cfg(not(feature = "serde"))]
+fn load_scene_at_startup(app: App, path: PathBuf) -> App {
+    log::warn!(
+        "Ignoring --scene {}: scene loading needs the `serde` feature",
+        path.display()
+    );
+    app
+}
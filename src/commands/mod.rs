@@ -0,0 +1,549 @@
+//! Named commands with typed arguments, run from text
+//!
+//! The `Commands` resource is the one place tools look for things to do at
+//! runtime: the in-app console runs what is typed at its prompt through it,
+//! `--run` on the command line (see `qsi::cli`) runs a line on the first
+//! frame, and anything driving the app from outside, such as a remote-control
+//! server or a test, calls `Commands::execute`. Plugins and startup systems
+//! add their own commands next to the built-in `help`, `spawn`, `get`,
+//! `set`, and `pause`.
+//!
+//! ```rust
+//! use qsi::commands::{ArgKind, Command, Commands};
+//! use qsi::ecs::World;
+//! use qsi::math::Transform;
+//!
+//! let mut world = World::new();
+//! let mut commands = Commands::new();
+//! commands.add(
+//!     Command::new("teleport", "Move an entity", |world, args| {
+//!         let entity = args.entity("entity")?;
+//!         let position: [f32; 3] = args.get("position")?;
+//!         world.add_component(entity, Transform::at_position(position.into()));
+//!         Ok(format!("Moved {entity}"))
+//!     })
+//!     .arg("entity", ArgKind::Entity)
+//!     .arg("position", ArgKind::Vec3),
+//! );
+//! world.insert_resource(commands);
+//!
+//! let entity = world.spawn().build();
+//! Commands::execute(&mut world, &format!("teleport {entity} 1,2,3")).unwrap();
+//! assert_eq!(world.get_component::<Transform>(entity).unwrap().position.z, 3.0);
+//! assert!(Commands::execute(&mut world, "teleport").is_err());
+//!
+//! // Built-in commands edit reflected components, including new metadata keys
+//! Commands::execute(&mut world, "spawn Metadata").unwrap();
+//! let tagged = world.query::<qsi::ecs::Metadata>().next().unwrap().0;
+//! Commands::execute(&mut world, &format!("set {tagged} Metadata.label \"left arm\"")).unwrap();
+//! assert_eq!(Commands::execute(&mut world, &format!("get {tagged} Metadata.label")).unwrap(), "Metadata.label = \"left arm\"");
+//! ```
+
+use crate::ecs::{EntityId, World};
+use crate::reflect::{FieldValue, TypeRegistry};
+use crate::time::Paused;
+use anyhow::{Context, Result, anyhow, bail};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Type of a command argument and how it is read from the words of a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    /// `true`/`false`, `on`/`off`, or `1`/`0`
+    Bool,
+    Int,
+    Float,
+    /// One word; quote it to include spaces
+    String,
+    /// Three numbers, as separate words or one `x,y,z` word
+    Vec3,
+    /// An entity ID or the `Name` of a live entity
+    Entity,
+    /// Every remaining word, at least one unless optional; only valid as
+    /// the last argument
+    Rest,
+}
+
+impl ArgKind {
+    /// Kind that reads values like `value`, for arguments typed by a
+    /// component field
+    pub fn of(value: &FieldValue) -> Self {
+        match value {
+            FieldValue::Bool(_) => Self::Bool,
+            FieldValue::Int(_) => Self::Int,
+            FieldValue::Float(_) => Self::Float,
+            FieldValue::String(_) => Self::String,
+            FieldValue::Vec3(_) => Self::Vec3,
+        }
+    }
+
+    /// Parse all of `words` as one value of this kind; `Rest` joins them
+    /// into a string
+    pub fn parse(self, words: &[&str]) -> Result<FieldValue> {
+        let text = words.join(" ");
+        let number = |word: &str| {
+            word.parse::<f32>()
+                .map_err(|_| anyhow!("Expected a number, got `{word}`"))
+        };
+        Ok(match self {
+            Self::Bool => FieldValue::Bool(match text.as_str() {
+                "true" | "on" | "1" => true,
+                "false" | "off" | "0" => false,
+                _ => bail!("Expected true or false, got `{text}`"),
+            }),
+            Self::Int | Self::Entity => FieldValue::Int(
+                text.parse()
+                    .map_err(|_| anyhow!("Expected an integer, got `{text}`"))?,
+            ),
+            Self::Float => FieldValue::Float(number(&text)?),
+            Self::String | Self::Rest => FieldValue::String(text),
+            Self::Vec3 => {
+                let parts: Vec<&str> = text
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|part| !part.is_empty())
+                    .collect();
+                let [x, y, z] = parts[..] else {
+                    bail!("Expected three numbers, got `{text}`");
+                };
+                FieldValue::Vec3([number(x)?, number(y)?, number(z)?])
+            }
+        })
+    }
+
+    /// Number of words the argument takes from the front of `words`
+    fn width(self, words: &[&str]) -> usize {
+        match self {
+            Self::Rest => words.len(),
+            Self::Vec3 if words.first().is_some_and(|word| word.contains(',')) => 1,
+            Self::Vec3 => 3.min(words.len()),
+            _ => 1.min(words.len()),
+        }
+    }
+}
+
+/// One declared argument of a command
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgSpec {
+    pub name: String,
+    pub kind: ArgKind,
+    /// May be left out; only trailing arguments can be optional
+    pub optional: bool,
+}
+
+/// Value of one parsed argument
+#[derive(Debug, Clone, PartialEq)]
+enum ArgValue {
+    Value(FieldValue),
+    Entity(EntityId),
+    Words(Vec<String>),
+}
+
+/// Arguments of one command invocation, checked against its `ArgSpec`s
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Args {
+    values: Vec<(String, ArgValue)>,
+}
+
+impl Args {
+    fn value(&self, name: &str) -> Option<&ArgValue> {
+        self.values
+            .iter()
+            .find(|(arg, _)| arg == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Whether the argument was given
+    pub fn has(&self, name: &str) -> bool {
+        self.value(name).is_some()
+    }
+
+    /// Value of a `Bool`, `Int`, `Float`, `String`, or `Vec3` argument
+    pub fn get<T: TryFrom<FieldValue, Error = anyhow::Error>>(&self, name: &str) -> Result<T> {
+        match self.value(name) {
+            Some(ArgValue::Value(value)) => T::try_from(value.clone()),
+            Some(_) => bail!("Argument `{name}` is not a plain value"),
+            None => bail!("Missing argument `{name}`"),
+        }
+    }
+
+    /// Value of an optional argument, or `None` if it was left out
+    pub fn get_opt<T: TryFrom<FieldValue, Error = anyhow::Error>>(
+        &self,
+        name: &str,
+    ) -> Result<Option<T>> {
+        if self.has(name) {
+            self.get(name).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Value of an `Entity` argument
+    pub fn entity(&self, name: &str) -> Result<EntityId> {
+        match self.value(name) {
+            Some(ArgValue::Entity(entity)) => Ok(*entity),
+            Some(_) => bail!("Argument `{name}` is not an entity"),
+            None => bail!("Missing argument `{name}`"),
+        }
+    }
+
+    /// Words of a `Rest` argument, empty if none were given
+    pub fn rest(&self, name: &str) -> &[String] {
+        match self.value(name) {
+            Some(ArgValue::Words(words)) => words,
+            _ => &[],
+        }
+    }
+}
+
+type CommandFn = Box<dyn Fn(&mut World, &Args) -> Result<String> + Send + Sync>;
+
+/// A named action with typed arguments
+pub struct Command {
+    name: String,
+    help: String,
+    args: Vec<ArgSpec>,
+    run: CommandFn,
+}
+
+impl fmt::Debug for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Command")
+            .field("name", &self.name)
+            .field("args", &self.args)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Command {
+    /// Command without arguments; `run` returns text for whoever ran it
+    pub fn new(
+        name: impl Into<String>,
+        help: impl Into<String>,
+        run: impl Fn(&mut World, &Args) -> Result<String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            help: help.into(),
+            args: Vec::new(),
+            run: Box::new(run),
+        }
+    }
+
+    /// Add a required argument
+    pub fn arg(mut self, name: impl Into<String>, kind: ArgKind) -> Self {
+        self.args.push(ArgSpec {
+            name: name.into(),
+            kind,
+            optional: false,
+        });
+        self
+    }
+
+    /// Add an argument that may be left out
+    pub fn optional(mut self, name: impl Into<String>, kind: ArgKind) -> Self {
+        self.args.push(ArgSpec {
+            name: name.into(),
+            kind,
+            optional: true,
+        });
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn help(&self) -> &str {
+        &self.help
+    }
+
+    pub fn args(&self) -> &[ArgSpec] {
+        &self.args
+    }
+
+    /// One-line synopsis, e.g. `set <entity> <field> <value...>`
+    pub fn usage(&self) -> String {
+        let mut usage = self.name.clone();
+        for arg in &self.args {
+            let dots = if arg.kind == ArgKind::Rest { "..." } else { "" };
+            let (open, close) = if arg.optional { ('[', ']') } else { ('<', '>') };
+            usage.push_str(&format!(" {open}{}{dots}{close}", arg.name));
+        }
+        usage
+    }
+
+    /// Check `words` against the declared arguments
+    pub fn parse_args(&self, world: &World, words: &[&str]) -> Result<Args> {
+        let mut args = Args::default();
+        let mut rest = words;
+        for spec in &self.args {
+            if rest.is_empty() {
+                if spec.optional {
+                    break;
+                }
+                bail!("Missing <{}>; usage: {}", spec.name, self.usage());
+            }
+            let (taken, remaining) = rest.split_at(spec.kind.width(rest));
+            rest = remaining;
+            let value = match spec.kind {
+                ArgKind::Entity => ArgValue::Entity(find_entity(world, taken[0])?),
+                ArgKind::Rest => ArgValue::Words(taken.iter().map(|w| w.to_string()).collect()),
+                kind => ArgValue::Value(
+                    kind.parse(taken)
+                        .with_context(|| format!("Bad <{}>", spec.name))?,
+                ),
+            };
+            args.values.push((spec.name.clone(), value));
+        }
+        if !rest.is_empty() {
+            bail!("Too many arguments; usage: {}", self.usage());
+        }
+        Ok(args)
+    }
+}
+
+/// Resource of the commands available to consoles, flags, and other tools
+pub struct Commands {
+    commands: BTreeMap<String, Command>,
+}
+
+impl Default for Commands {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Commands {
+    /// Registry with the built-in commands
+    pub fn new() -> Self {
+        let mut commands = Self::empty();
+        commands
+            .add(
+                Command::new("spawn", "New entity with default components", spawn_command)
+                    .optional("components", ArgKind::Rest),
+            )
+            .add(
+                Command::new(
+                    "get",
+                    "Show an entity's components or one Component.field",
+                    get_command,
+                )
+                .arg("entity", ArgKind::Entity)
+                .optional("field", ArgKind::String),
+            )
+            .add(
+                Command::new("set", "Write one Component.field", set_command)
+                    .arg("entity", ArgKind::Entity)
+                    .arg("field", ArgKind::String)
+                    .arg("value", ArgKind::Rest),
+            )
+            .add(Command::new(
+                "pause",
+                "Stop or restart the clock",
+                pause_command,
+            ));
+        commands
+    }
+
+    /// Registry with no commands besides `help`
+    pub fn empty() -> Self {
+        Self {
+            commands: BTreeMap::new(),
+        }
+    }
+
+    /// Add a command, replacing any with the same name
+    pub fn add(&mut self, command: Command) -> &mut Self {
+        self.commands.insert(command.name.clone(), command);
+        self
+    }
+
+    /// Remove a command by name
+    pub fn remove(&mut self, name: &str) -> Option<Command> {
+        self.commands.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.get(name)
+    }
+
+    /// All commands, sorted by name
+    pub fn iter(&self) -> impl Iterator<Item = &Command> {
+        self.commands.values()
+    }
+
+    /// Text of `help`: every usage line, or one command's in detail
+    fn help(&self, name: Option<&str>) -> Result<String> {
+        let line = |command: &Command| format!("{} - {}", command.usage(), command.help);
+        match name {
+            Some(name) => self
+                .get(name)
+                .map(line)
+                .with_context(|| format!("Unknown command `{name}`")),
+            None => Ok(
+                std::iter::once("help [command] - List commands".to_string())
+                    .chain(self.iter().map(line))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+        }
+    }
+
+    /// Run one line, such as `set player Transform.position 0 1 0`, with the
+    /// world's `Commands` resource (or the built-in commands if there is none)
+    ///
+    /// Words are split at whitespace; double quotes keep a word with spaces
+    /// whole. An empty line does nothing.
+    pub fn execute(world: &mut World, line: &str) -> Result<String> {
+        let words = split_words(line);
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+        let Some((name, args)) = words.split_first() else {
+            return Ok(String::new());
+        };
+        let commands = world.remove_resource::<Commands>();
+        let result = Self::run_with(
+            commands.as_ref().unwrap_or(&Commands::new()),
+            world,
+            name,
+            args,
+        );
+        if let Some(commands) = commands {
+            world.insert_resource(commands);
+        }
+        result
+    }
+
+    fn run_with(&self, world: &mut World, name: &str, words: &[&str]) -> Result<String> {
+        if name == "help" {
+            return self.help(words.first().copied());
+        }
+        let command = self
+            .get(name)
+            .with_context(|| format!("Unknown command `{name}`; try `help`"))?;
+        let args = command.parse_args(world, words)?;
+        (command.run)(world, &args)
+    }
+}
+
+/// Split a command line at whitespace, keeping double-quoted words whole
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut started = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    words.push(std::mem::take(&mut word));
+                    started = false;
+                }
+            }
+            c => {
+                word.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        words.push(word);
+    }
+    words
+}
+
+/// Entity named by its ID or its `Name`
+fn find_entity(world: &World, word: &str) -> Result<EntityId> {
+    let entity = match word.parse::<EntityId>() {
+        Ok(entity) => entity,
+        Err(_) => world
+            .find_by_name(word)
+            .with_context(|| format!("No entity named `{word}`"))?,
+    };
+    if !world.is_alive(entity) {
+        bail!("Entity {entity} does not exist");
+    }
+    Ok(entity)
+}
+
+/// Run `f` with the `TypeRegistry` resource, or the built-in registry
+fn with_type_registry<R>(world: &mut World, f: impl FnOnce(&mut World, &TypeRegistry) -> R) -> R {
+    let registry = world.remove_resource::<TypeRegistry>();
+    let result = f(world, registry.as_ref().unwrap_or(&TypeRegistry::new()));
+    if let Some(registry) = registry {
+        world.insert_resource(registry);
+    }
+    result
+}
+
+/// `Component.field` split in two
+fn split_field(word: &str) -> Result<(&str, &str)> {
+    word.split_once('.')
+        .with_context(|| format!("Expected Component.field, got `{word}`"))
+}
+
+fn spawn_command(world: &mut World, args: &Args) -> Result<String> {
+    let components = args.rest("components");
+    with_type_registry(world, |world, registry| {
+        for name in components {
+            registry
+                .get(name)
+                .with_context(|| format!("Unknown component `{name}`"))?;
+        }
+        let entity = world.create_entity();
+        for name in components {
+            registry.insert_default(world, entity, name)?;
+        }
+        Ok(format!("Spawned entity {entity}"))
+    })
+}
+
+fn get_command(world: &mut World, args: &Args) -> Result<String> {
+    let entity = args.entity("entity")?;
+    let field = args.get_opt::<String>("field")?;
+    with_type_registry(world, |world, registry| match field {
+        None => Ok(format!(
+            "Entity {entity}: {}",
+            registry.components_of(world, entity).join(", ")
+        )),
+        Some(field) => {
+            let (component, field) = split_field(&field)?;
+            let value = registry
+                .get_field(world, entity, component, field)?
+                .with_context(|| format!("Entity {entity} has no {component}.{field}"))?;
+            Ok(format!("{component}.{field} = {value}"))
+        }
+    })
+}
+
+fn set_command(world: &mut World, args: &Args) -> Result<String> {
+    let entity = args.entity("entity")?;
+    let field = args.get::<String>("field")?;
+    let (component, field) = split_field(&field)?;
+    let words: Vec<&str> = args.rest("value").iter().map(String::as_str).collect();
+    with_type_registry(world, |world, registry| {
+        // Fields a component does not report yet, such as new `Metadata`
+        // keys, are strings; the component rejects names it cannot take
+        let kind = match registry.get_field(world, entity, component, field)? {
+            Some(current) => ArgKind::of(&current),
+            None if registry.components_of(world, entity).contains(&component) => ArgKind::String,
+            None => bail!("Entity {entity} has no {component}"),
+        };
+        let value = kind.parse(&words)?;
+        registry.set_field(world, entity, component, field, value.clone())?;
+        Ok(format!("{component}.{field} = {value}"))
+    })
+}
+
+fn pause_command(world: &mut World, _args: &Args) -> Result<String> {
+    if world.remove_resource::<Paused>().is_some() {
+        Ok("Resumed".to_string())
+    } else {
+        world.insert_resource(Paused);
+        Ok("Paused".to_string())
+    }
+}
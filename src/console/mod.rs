@@ -0,0 +1,428 @@
+//! In-app log console with a command prompt
+//!
+//! `ConsolePlugin` installs `ConsoleLogger` in place of `env_logger::init()`:
+//! records still go to the terminal as `RUST_LOG` says, and are also kept for
+//! an overlay opened with the backtick key. Lines typed into the prompt run
+//! through the `Commands` resource (see `qsi::commands`), which the plugin
+//! extends with `clear` and `level` for the console itself.
+//!
+//! ```rust,no_run
+//! use qsi::commands::{Command, Commands};
+//! use qsi::console::ConsolePlugin;
+//! use qsi::prelude::*;
+//!
+//! fn setup(world: &mut World, _renderer: &mut Renderer) {
+//!     world.resource_mut::<Commands>().unwrap().add(Command::new(
+//!         "count",
+//!         "Number of entities",
+//!         |world, _args| Ok(world.entities().len().to_string()),
+//!     ));
+//! }
+//!
+//! App::new()
+//!     .add_plugin(ConsolePlugin::default())
+//!     .add_startup_system(setup)
+//!     .run()
+//!     .unwrap();
+//! ```
+//!
+//! The overlay needs the `text` feature and a font set with
+//! `Renderer::set_default_font`; without them the console still takes input
+//! and echoes command results to the log.
+
+use crate::App;
+use crate::commands::{ArgKind, Command, Commands};
+#[cfg(feature = "text")]
+use crate::ecs::EntityId;
+use crate::ecs::World;
+use crate::input::InputState;
+use crate::time::TimeState;
+use anyhow::{Context, Result, anyhow};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use winit::keyboard::KeyCode;
+
+/// Records captured by `ConsoleLogger`, waiting for `console_system`
+static CAPTURED: Mutex<Vec<ConsoleLine>> = Mutex::new(Vec::new());
+
+/// One line of console output
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsoleLine {
+    /// Level of a log record, or `None` for prompt echoes and command output
+    pub level: Option<Level>,
+    /// Module path of a log record, empty otherwise
+    pub target: String,
+    pub text: String,
+}
+
+/// Logger writing to the terminal like `env_logger` and keeping records for
+/// the console
+pub struct ConsoleLogger {
+    terminal: env_logger::Logger,
+    capture: LevelFilter,
+}
+
+impl ConsoleLogger {
+    /// `env_logger` configured from `RUST_LOG`, capturing records up to `capture`
+    pub fn new(capture: LevelFilter) -> Self {
+        Self {
+            terminal: env_logger::Builder::from_default_env().build(),
+            capture,
+        }
+    }
+
+    /// Install as the global logger; fails if one is already installed
+    pub fn init(self) -> Result<(), log::SetLoggerError> {
+        let max_level = self.terminal.filter().max(self.capture);
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+}
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.capture || self.terminal.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.terminal.matches(record) {
+            self.terminal.log(record);
+        }
+        if record.level() <= self.capture
+            && let Ok(mut captured) = CAPTURED.lock()
+        {
+            captured.push(ConsoleLine {
+                level: Some(record.level()),
+                target: record.target().to_string(),
+                text: record.args().to_string(),
+            });
+        }
+    }
+
+    fn flush(&self) {
+        self.terminal.flush();
+    }
+}
+
+/// Resource holding the console's output and prompt
+pub struct Console {
+    /// Whether the overlay is shown and taking keyboard input
+    pub open: bool,
+    /// Most verbose log level shown; command output is always shown
+    pub filter: LevelFilter,
+    /// Lines kept before the oldest are dropped
+    pub capacity: usize,
+    /// Lines of the overlay, counted from the bottom of the screen
+    pub visible_lines: usize,
+    lines: VecDeque<ConsoleLine>,
+    input: String,
+    history: Vec<String>,
+    history_cursor: Option<usize>,
+    #[cfg(feature = "text")]
+    overlay: Option<(EntityId, EntityId)>,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Console {
+    /// Closed, empty console
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            filter: LevelFilter::Info,
+            capacity: 500,
+            visible_lines: 16,
+            lines: VecDeque::new(),
+            input: String::new(),
+            history: Vec::new(),
+            history_cursor: None,
+            #[cfg(feature = "text")]
+            overlay: None,
+        }
+    }
+
+    /// Add a line of command output
+    pub fn print(&mut self, text: impl Into<String>) {
+        for line in text.into().lines() {
+            self.push(ConsoleLine {
+                level: None,
+                target: String::new(),
+                text: line.to_string(),
+            });
+        }
+    }
+
+    /// Remove all output
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    /// Every kept line, oldest first
+    pub fn lines(&self) -> impl Iterator<Item = &ConsoleLine> {
+        self.lines.iter()
+    }
+
+    /// Kept lines passing `filter`, oldest first
+    pub fn visible(&self) -> impl Iterator<Item = &ConsoleLine> {
+        self.lines
+            .iter()
+            .filter(|line| line.level.is_none_or(|level| level <= self.filter))
+    }
+
+    /// Text typed at the prompt so far
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    fn push(&mut self, line: ConsoleLine) {
+        self.lines.push_back(line);
+        while self.lines.len() > self.capacity {
+            self.lines.pop_front();
+        }
+    }
+}
+
+/// Run one command line with `Commands::execute`, echoing it and its result
+/// to the `Console` resource
+///
+/// ```rust
+/// use qsi::console::{Console, execute};
+/// use qsi::ecs::World;
+/// use qsi::math::Transform;
+///
+/// let mut world = World::new();
+/// world.insert_resource(Console::new());
+/// execute(&mut world, "spawn Transform").unwrap();
+/// let entity = world.query::<Transform>().next().unwrap().0;
+/// execute(&mut world, &format!("set {entity} Transform.position 1 2 3")).unwrap();
+/// assert_eq!(world.get_component::<Transform>(entity).unwrap().position.y, 2.0);
+/// ```
+pub fn execute(world: &mut World, line: &str) -> Result<String> {
+    world
+        .resource_mut::<Console>()
+        .context("No Console resource")?
+        .print(format!("> {line}"));
+    let result = Commands::execute(world, line);
+    // A command may have removed the console
+    let Some(console) = world.resource_mut::<Console>() else {
+        return result;
+    };
+    match &result {
+        Ok(text) => console.print(text.as_str()),
+        Err(e) => console.push(ConsoleLine {
+            level: Some(Level::Error),
+            target: String::new(),
+            text: format!("{e:#}"),
+        }),
+    }
+    result
+}
+
+fn console_mut(world: &mut World) -> Result<&mut Console> {
+    world
+        .resource_mut::<Console>()
+        .context("No Console resource")
+}
+
+/// `clear` and `level`, which act on the `Console` resource
+pub fn console_commands() -> [Command; 2] {
+    [
+        Command::new("clear", "Remove all console output", |world, _| {
+            console_mut(world)?.clear();
+            Ok(String::new())
+        }),
+        Command::new(
+            "level",
+            "Show or set the most verbose log level shown (off, error, warn, info, debug, trace)",
+            |world, args| {
+                let console = console_mut(world)?;
+                if let Some(level) = args.get_opt::<String>("level")? {
+                    console.filter = level
+                        .parse()
+                        .map_err(|_| anyhow!("Unknown level `{level}`"))?;
+                }
+                Ok(format!("Showing up to {}", console.filter))
+            },
+        )
+        .optional("level", ArgKind::String),
+    ]
+}
+
+/// Run condition that is true while the console is not taking keyboard
+/// input, for gating systems that read keys
+pub fn console_closed(world: &World) -> bool {
+    !world
+        .resource::<Console>()
+        .is_some_and(|console| console.open)
+}
+
+/// Collect log records, toggle the console with backtick, and edit and run
+/// the prompt while it is open
+pub fn console_system(world: &mut World, input: &InputState, _time: &TimeState) {
+    let captured = std::mem::take(&mut *CAPTURED.lock().unwrap_or_else(|e| e.into_inner()));
+    let Some(console) = world.resource_mut::<Console>() else {
+        return;
+    };
+    for line in captured {
+        console.push(line);
+    }
+
+    if input.key_just_pressed(KeyCode::Backquote) {
+        console.open = !console.open;
+        return;
+    }
+    if !console.open {
+        return;
+    }
+    if input.key_just_pressed(KeyCode::Escape) {
+        console.open = false;
+        return;
+    }
+
+    console.input.extend(
+        input
+            .typed_text()
+            .chars()
+            .filter(|c| !c.is_control() && *c != '`'),
+    );
+    if input.key_just_pressed(KeyCode::Backspace) {
+        console.input.pop();
+    }
+    if input.key_just_pressed(KeyCode::ArrowUp) && !console.history.is_empty() {
+        let cursor = console
+            .history_cursor
+            .map_or(console.history.len() - 1, |cursor| cursor.saturating_sub(1));
+        console.history_cursor = Some(cursor);
+        console.input = console.history[cursor].clone();
+    }
+    if input.key_just_pressed(KeyCode::ArrowDown)
+        && let Some(cursor) = console.history_cursor
+    {
+        if cursor + 1 < console.history.len() {
+            console.history_cursor = Some(cursor + 1);
+            console.input = console.history[cursor + 1].clone();
+        } else {
+            console.history_cursor = None;
+            console.input.clear();
+        }
+    }
+    if input.key_just_pressed(KeyCode::Enter) || input.key_just_pressed(KeyCode::NumpadEnter) {
+        let line = std::mem::take(&mut console.input);
+        console.history_cursor = None;
+        if line.trim().is_empty() {
+            return;
+        }
+        if console.history.last() != Some(&line) {
+            console.history.push(line.clone());
+        }
+        // The result is already in the console
+        let _ = execute(world, &line);
+    }
+}
+
+/// Keep the overlay's background and text in step with the console
+#[cfg(feature = "text")]
+pub fn console_overlay_system(
+    world: &mut World,
+    renderer: &mut crate::graphics::Renderer,
+    _time: &TimeState,
+) {
+    use crate::graphics::{Sprite, Text};
+
+    const LAYER: i32 = 1000;
+    const TEXT_SIZE: f32 = 16.0;
+    const LINE_HEIGHT: f32 = TEXT_SIZE * 1.25;
+    const MARGIN: f32 = 8.0;
+
+    let Some(console) = world.resource_mut::<Console>() else {
+        return;
+    };
+    if !console.open {
+        if let Some((background, text)) = console.overlay.take() {
+            world.despawn(background);
+            world.despawn(text);
+        }
+        return;
+    }
+
+    let visible: Vec<&ConsoleLine> = console.visible().collect();
+    let shown = &visible[visible.len().saturating_sub(console.visible_lines)..];
+    let mut text = String::new();
+    for line in shown {
+        match line.level {
+            Some(level) => text.push_str(&format!("[{level:<5}] {}\n", line.text)),
+            None => text.push_str(&format!("{}\n", line.text)),
+        }
+    }
+    text.push_str(&format!("> {}_", console.input));
+    let rows = console.visible_lines + 1;
+    let first_row = rows - (shown.len() + 1);
+    let overlay = console.overlay;
+
+    let (width, _) = renderer.size();
+    let height = rows as f32 * LINE_HEIGHT + 2.0 * MARGIN;
+    let (background, label) = match overlay {
+        Some(overlay) => overlay,
+        None => {
+            let background = world.spawn().build();
+            let label = world.spawn().build();
+            if let Some(console) = world.resource_mut::<Console>() {
+                console.overlay = Some((background, label));
+            }
+            (background, label)
+        }
+    };
+    world.add_component(
+        background,
+        Sprite::screen([width as f32 / 2.0, height / 2.0], [width as f32, height])
+            .with_color([0.0, 0.0, 0.0, 0.75])
+            .with_layer(LAYER),
+    );
+    world.add_component(
+        label,
+        Text::new(text, [MARGIN, MARGIN + first_row as f32 * LINE_HEIGHT])
+            .with_size(TEXT_SIZE)
+            .with_layer(LAYER + 1),
+    );
+}
+
+/// Plugin installing `ConsoleLogger` and the console's resource and systems
+pub struct ConsolePlugin {
+    /// Most verbose level kept for the console
+    pub capture: LevelFilter,
+}
+
+impl Default for ConsolePlugin {
+    fn default() -> Self {
+        Self {
+            capture: LevelFilter::Info,
+        }
+    }
+}
+
+impl crate::plugin::Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        use crate::schedule::IntoSystemDescriptor;
+
+        if ConsoleLogger::new(self.capture).init().is_err() {
+            log::warn!("A logger is already installed; log records will not reach the console");
+        }
+        app.register_resource(Console::new());
+        app.register_startup_system(|world: &mut World, _: &mut crate::graphics::Renderer| {
+            if let Some(commands) = world.resource_mut::<Commands>() {
+                for command in console_commands() {
+                    commands.add(command);
+                }
+            }
+        });
+        app.register_system(console_system.label("console"));
+        #[cfg(feature = "text")]
+        app.register_render_system(console_overlay_system);
+    }
+}
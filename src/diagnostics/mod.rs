@@ -0,0 +1,110 @@
+//! Runtime diagnostics for finding where frame time goes, and dumps of the
+//! renderer's intermediate targets
+
+use std::fmt;
+use std::time::Duration;
+
+mod target_dump;
+
+pub use target_dump::{
+    TargetDump, TargetDumpPlugin, target_dump_command, target_dump_key_system, target_dump_system,
+};
+
+/// Accumulated timing for one system
+#[derive(Debug, Clone)]
+pub struct SystemTiming {
+    /// System name (its Rust type name unless overridden with `.named(..)`)
+    pub name: String,
+    /// Number of recorded runs
+    pub runs: u64,
+    /// Total time spent across all runs
+    pub total: Duration,
+    /// Longest single run
+    pub max: Duration,
+    /// Most recent run
+    pub last: Duration,
+}
+
+impl SystemTiming {
+    /// Average time per run
+    pub fn mean(&self) -> Duration {
+        if self.runs == 0 {
+            Duration::ZERO
+        } else {
+            self.total.div_f64(self.runs as f64)
+        }
+    }
+}
+
+/// Resource with per-system CPU timings, filled in by `App` every frame
+#[derive(Debug, Clone, Default)]
+pub struct SystemTimings {
+    entries: Vec<SystemTiming>,
+    /// Print a summary table when the app exits
+    pub print_on_exit: bool,
+}
+
+impl SystemTimings {
+    /// Record one run of the system at position `index` in the schedule
+    pub fn record(&mut self, index: usize, name: &str, elapsed: Duration) {
+        if index >= self.entries.len() {
+            self.entries.resize_with(index + 1, || SystemTiming {
+                name: String::new(),
+                runs: 0,
+                total: Duration::ZERO,
+                max: Duration::ZERO,
+                last: Duration::ZERO,
+            });
+        }
+
+        let entry = &mut self.entries[index];
+        if entry.name != name {
+            entry.name = name.to_string();
+        }
+        entry.runs += 1;
+        entry.total += elapsed;
+        entry.max = entry.max.max(elapsed);
+        entry.last = elapsed;
+    }
+
+    /// Timings in schedule order
+    pub fn entries(&self) -> &[SystemTiming] {
+        &self.entries
+    }
+
+    /// Get the timing for a system by name
+    pub fn get(&self, name: &str) -> Option<&SystemTiming> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// The system with the highest mean time
+    pub fn slowest(&self) -> Option<&SystemTiming> {
+        self.entries.iter().max_by_key(|entry| entry.mean())
+    }
+
+    /// Clear all recorded timings
+    pub fn reset(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl fmt::Display for SystemTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<48} {:>8} {:>12} {:>12}",
+            "system", "runs", "mean (ms)", "max (ms)"
+        )?;
+        for entry in &self.entries {
+            writeln!(
+                f,
+                "{:<48} {:>8} {:>12.3} {:>12.3}",
+                entry.name,
+                entry.runs,
+                entry.mean().as_secs_f64() * 1000.0,
+                entry.max.as_secs_f64() * 1000.0
+            )?;
+        }
+        Ok(())
+    }
+}
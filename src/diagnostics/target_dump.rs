@@ -0,0 +1,133 @@
+//! One-key dumps of the renderer's intermediate targets
+
+use crate::App;
+use crate::commands::{Command, Commands};
+use crate::ecs::World;
+use crate::graphics::Renderer;
+use crate::input::InputState;
+use crate::time::TimeState;
+use anyhow::Context;
+use std::path::PathBuf;
+use winit::keyboard::KeyCode;
+
+/// Resource asking for the render targets to be saved as PNGs
+///
+/// Each dump goes to its own `frame_<n>` folder under `dir`, one file per
+/// registered target: the scene depth and HDR color, plus any target a pass
+/// or plugin registered in `Renderer::render_targets` (picking IDs, shadow
+/// maps, ...).
+#[derive(Debug, Clone)]
+pub struct TargetDump {
+    /// Folder the dumps are written under
+    pub dir: PathBuf,
+    /// Key that requests a dump
+    pub key: KeyCode,
+    requested: bool,
+}
+
+impl Default for TargetDump {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("target_dumps"),
+            key: KeyCode::F9,
+            requested: false,
+        }
+    }
+}
+
+impl TargetDump {
+    /// Save the targets at the next frame
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+}
+
+/// Request a dump when the `TargetDump` key is pressed
+pub fn target_dump_key_system(world: &mut World, input: &InputState, _time: &TimeState) {
+    if let Some(dump) = world.resource_mut::<TargetDump>()
+        && input.key_just_pressed(dump.key)
+    {
+        dump.request();
+    }
+}
+
+/// Save the render targets if a dump was requested
+pub fn target_dump_system(world: &mut World, renderer: &mut Renderer, time: &TimeState) {
+    let Some(dump) = world.resource_mut::<TargetDump>() else {
+        return;
+    };
+    if !std::mem::take(&mut dump.requested) {
+        return;
+    }
+    // Render systems run before the frame is drawn, so the targets hold the
+    // previous one
+    let frame = time.frame_count().saturating_sub(1);
+    let dir = dump.dir.join(format!("frame_{frame:06}"));
+    match renderer.dump_render_targets(&dir) {
+        Ok(files) => log::info!("Saved {} render targets to {}", files.len(), dir.display()),
+        Err(e) => log::error!("Failed to dump render targets: {e:#}"),
+    }
+}
+
+/// `dump_targets`, which requests a dump from the console
+pub fn target_dump_command() -> Command {
+    Command::new(
+        "dump_targets",
+        "Save every render target (depth, HDR color, ...) as PNGs",
+        |world, _| {
+            let dump = world
+                .resource_mut::<TargetDump>()
+                .context("No TargetDump resource")?;
+            dump.request();
+            Ok(format!(
+                "Dumping render targets under {}",
+                dump.dir.display()
+            ))
+        },
+    )
+}
+
+/// Plugin saving the renderer's intermediate targets to PNGs with one key
+/// (F9 by default) or the `dump_targets` command, for debugging without a
+/// GPU debugger
+///
+/// ```rust,no_run
+/// use qsi::diagnostics::TargetDumpPlugin;
+/// use qsi::prelude::*;
+///
+/// App::new()
+///     .add_plugin(TargetDumpPlugin::default().with_dir("/tmp/qsi_dumps"))
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TargetDumpPlugin {
+    settings: TargetDump,
+}
+
+impl TargetDumpPlugin {
+    /// Write dumps under `dir` instead of `target_dumps`
+    pub fn with_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.settings.dir = dir.into();
+        self
+    }
+
+    /// Dump on `key` instead of F9
+    pub fn with_key(mut self, key: KeyCode) -> Self {
+        self.settings.key = key;
+        self
+    }
+}
+
+impl crate::plugin::Plugin for TargetDumpPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_resource(self.settings.clone());
+        app.register_startup_system(|world: &mut World, _: &mut Renderer| {
+            if let Some(commands) = world.resource_mut::<Commands>() {
+                commands.add(target_dump_command());
+            }
+        });
+        app.register_system(target_dump_key_system);
+        app.register_render_system(target_dump_system);
+    }
+}
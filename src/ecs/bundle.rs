@@ -0,0 +1,106 @@
+//! Bundles and prefabs for spawning groups of components
+
+use super::{Component, EntityId, World};
+use crate::math::{Transform, Vector3};
+
+/// A group of components inserted together
+///
+/// Implemented for tuples of components; implement it for your own structs
+/// to name common combinations:
+///
+/// ```rust,no_run
+/// use qsi::prelude::*;
+/// use qsi::ecs::Bundle;
+///
+/// struct CubeBundle {
+///     transform: Transform,
+///     velocity: qsi::math::Velocity,
+/// }
+///
+/// impl Bundle for CubeBundle {
+///     fn insert_into(self, world: &mut World, entity: EntityId) {
+///         world.add_component(entity, self.transform);
+///         world.add_component(entity, self.velocity);
+///     }
+/// }
+/// ```
+pub trait Bundle: 'static {
+    /// Add every component in the bundle to `entity`
+    fn insert_into(self, world: &mut World, entity: EntityId);
+}
+
+macro_rules! impl_bundle {
+    ($($name:ident),*) => {
+        impl<$($name: Component),*> Bundle for ($($name,)*) {
+            #[allow(non_snake_case)]
+            fn insert_into(self, world: &mut World, entity: EntityId) {
+                let ($($name,)*) = self;
+                $(world.add_component(entity, $name);)*
+            }
+        }
+    };
+}
+
+impl_bundle!(A);
+impl_bundle!(A, B);
+impl_bundle!(A, B, C);
+impl_bundle!(A, B, C, D);
+impl_bundle!(A, B, C, D, E);
+impl_bundle!(A, B, C, D, E, F);
+impl_bundle!(A, B, C, D, E, F, G);
+impl_bundle!(A, B, C, D, E, F, G, H);
+
+type Inserter = Box<dyn Fn(&mut World, EntityId) + Send + Sync>;
+
+/// A reusable entity definition that can be instantiated many times
+#[derive(Default)]
+pub struct Prefab {
+    inserters: Vec<Inserter>,
+}
+
+impl Prefab {
+    /// Create an empty prefab
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a component, cloned into each instance
+    pub fn with<T: Component + Clone>(self, component: T) -> Self {
+        self.with_fn(move |world, entity| world.add_component(entity, component.clone()))
+    }
+
+    /// Add a bundle, cloned into each instance
+    pub fn with_bundle<B: Bundle + Clone + Send + Sync>(self, bundle: B) -> Self {
+        self.with_fn(move |world, entity| bundle.clone().insert_into(world, entity))
+    }
+
+    /// Run a custom step on each instance, e.g. to build components that can't be cloned
+    pub fn with_fn(mut self, f: impl Fn(&mut World, EntityId) + Send + Sync + 'static) -> Self {
+        self.inserters.push(Box::new(f));
+        self
+    }
+
+    /// Spawn a new entity from this prefab
+    pub fn instantiate(&self, world: &mut World) -> EntityId {
+        let entity = world.create_entity();
+        self.apply(world, entity);
+        entity
+    }
+
+    /// Spawn a new entity and place it at `position`
+    pub fn instantiate_at(&self, world: &mut World, position: Vector3<f32>) -> EntityId {
+        let entity = self.instantiate(world);
+        match world.get_component_mut::<Transform>(entity) {
+            Some(transform) => transform.position = position,
+            None => world.add_component(entity, Transform::at_position(position)),
+        }
+        entity
+    }
+
+    /// Add this prefab's components to an existing entity
+    pub fn apply(&self, world: &mut World, entity: EntityId) {
+        for inserter in &self.inserters {
+            inserter(world, entity);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Double-buffered event channels stored on the `World`
+//!
+//! Systems send events with `World::send` and read them with
+//! `World::read_events` or an `EventReader`. Each event stays readable for the
+//! frame it was sent in and the following one, then `World::update_events`
+//! drops it, so unread events never pile up.
+
+use super::World;
+use std::any::Any;
+use std::marker::PhantomData;
+
+/// Event trait that all events must implement
+pub trait Event: 'static + Send + Sync {}
+
+/// Storage for one event type: this frame's and last frame's events
+pub struct Events<E: Event> {
+    previous: Vec<E>,
+    current: Vec<E>,
+    /// ID of the first event in `previous`
+    previous_start: u64,
+    /// ID of the first event in `current`
+    current_start: u64,
+}
+
+impl<E: Event> Default for Events<E> {
+    fn default() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+            previous_start: 0,
+            current_start: 0,
+        }
+    }
+}
+
+impl<E: Event> Events<E> {
+    /// Queue an event for this frame
+    pub fn send(&mut self, event: E) {
+        self.current.push(event);
+    }
+
+    /// Iterate over last frame's and this frame's events, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &E> {
+        self.previous.iter().chain(self.current.iter())
+    }
+
+    /// Total number of events ever sent (the ID the next event will get)
+    fn event_count(&self) -> u64 {
+        self.current_start + self.current.len() as u64
+    }
+
+    /// Iterate over events with an ID of at least `from`
+    fn iter_from(&self, from: u64) -> impl Iterator<Item = &E> {
+        let skip_previous = from.saturating_sub(self.previous_start) as usize;
+        let skip_current = from.saturating_sub(self.current_start) as usize;
+        self.previous
+            .iter()
+            .skip(skip_previous)
+            .chain(self.current.iter().skip(skip_current))
+    }
+
+    /// Remove and return all buffered events
+    pub fn drain(&mut self) -> impl Iterator<Item = E> + '_ {
+        self.previous_start = self.event_count();
+        self.current_start = self.previous_start;
+        self.previous.drain(..).chain(self.current.drain(..))
+    }
+
+    /// Number of buffered events
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    /// Check if there are no buffered events
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Swap buffers, dropping events that are two frames old
+    fn update(&mut self) {
+        self.previous_start = self.current_start;
+        self.current_start = self.event_count();
+        self.previous = std::mem::take(&mut self.current);
+    }
+}
+
+/// Type-erased access to an event storage
+pub(super) trait EventStorage: Send + Sync {
+    fn update(&mut self);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<E: Event> EventStorage for Events<E> {
+    fn update(&mut self) {
+        Events::update(self);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Cursor that yields each event of type `E` only once
+///
+/// Keep one per consumer (for example in a component or captured by a system)
+/// when the same events must not be handled twice across the two-frame window.
+pub struct EventReader<E: Event> {
+    next_id: u64,
+    _marker: PhantomData<fn() -> E>,
+}
+
+impl<E: Event> Default for EventReader<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Event> EventReader<E> {
+    /// Create a reader that will see every event still buffered
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Read events not yet seen by this reader
+    pub fn read<'w>(&mut self, world: &'w World) -> impl Iterator<Item = &'w E> + use<'w, E> {
+        let events = world.event_storage::<E>();
+        let from = self.next_id;
+        if let Some(events) = events {
+            self.next_id = events.event_count();
+        }
+        events
+            .into_iter()
+            .flat_map(move |events| events.iter_from(from))
+    }
+}
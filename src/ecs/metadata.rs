@@ -0,0 +1,89 @@
+//! Free-form key/value annotations on entities
+
+use super::Component;
+use std::collections::BTreeMap;
+
+/// String key/value pairs attached to an entity, such as sensor IDs or
+/// dataset labels
+///
+/// Saved with scenes and reflected under the name `Metadata`, with each key
+/// as a field, so tools can read and write it without a dedicated component
+/// type (`set sensor Metadata.id A-17` at the console).
+///
+/// ```rust
+/// use qsi::ecs::{Metadata, World};
+///
+/// let mut world = World::new();
+/// let sensor = world
+///     .spawn()
+///     .with(Metadata::new().with("sensor_id", "A-17").with("dataset", "run-3"))
+///     .build();
+/// let metadata = world.get_component::<Metadata>(sensor).unwrap();
+/// assert_eq!(metadata.get("sensor_id"), Some("A-17"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct Metadata(BTreeMap<String, String>);
+
+impl Component for Metadata {}
+
+impl Metadata {
+    /// Create empty metadata
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a key/value pair
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.insert(key, value);
+        self
+    }
+
+    /// Set `key`, returning its previous value
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.0.insert(key.into(), value.into())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Remove `key`, returning its value
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.0.remove(key)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    /// Pairs in key order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Metadata {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(pairs: I) -> Self {
+        Self(
+            pairs
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
@@ -3,20 +3,190 @@
 //! A simple but flexible ECS that allows you to build complex simulations
 //! from simple components and systems.
 
+mod bundle;
+mod event;
+mod metadata;
+mod query;
+mod stats;
+mod system;
+
+pub use bundle::{Bundle, Prefab};
+pub use event::{Event, EventReader, Events};
+pub use metadata::Metadata;
+pub use query::{ComponentAccess, QueryData, QueryFilter, ReadOnlyQueryData, With, Without};
+pub use stats::{ComponentStats, WorldStats};
+pub use system::{EventWriter, Query, Res, ResMut, SystemAccess, SystemParam};
+
+use crate::reflect::TypeRegistry;
+use event::EventStorage;
+
 use std::any::{Any, TypeId};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Entity ID - simple integer
 pub type EntityId = u32;
 
 /// Component trait that all components must implement
+///
+/// Zero-sized marker structs (`struct Player;`) make cheap tags: their storage
+/// holds only entity IDs, and they pair with the `With`/`Without` query filters.
 pub trait Component: 'static + Send + Sync {}
 
+/// Human-readable label for an entity, used by `World::find_by_name`
+///
+/// Rename an entity by inserting a new `Name` with `World::add_component`
+/// rather than through `get_component_mut`, so the lookup index stays current.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Name(String);
+
+impl Component for Name {}
+
+impl Name {
+    /// Create a new name
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    /// Get the name as a string slice
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for Name {
+    fn from(name: String) -> Self {
+        Self(name)
+    }
+}
+
+impl std::fmt::Display for Name {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Type-erased access to a component storage
+trait ComponentStorage: Send + Sync {
+    fn type_name(&self) -> &'static str;
+    fn len(&self) -> usize;
+    /// Approximate heap bytes used by the storage
+    fn allocated_bytes(&self) -> usize;
+    fn contains(&self, entity: EntityId) -> bool;
+    fn remove_entity(&mut self, entity: EntityId);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Component> ComponentStorage for HashMap<EntityId, T> {
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        // One control byte per bucket plus the key/value pair
+        self.capacity() * (std::mem::size_of::<(EntityId, T)>() + 1)
+    }
+
+    fn contains(&self, entity: EntityId) -> bool {
+        self.contains_key(&entity)
+    }
+
+    fn remove_entity(&mut self, entity: EntityId) {
+        self.remove(&entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Registered name of a component type, or its type name without module paths
+fn display_name(registry: Option<&TypeRegistry>, type_id: TypeId, type_name: &str) -> String {
+    if let Some(name) = registry.and_then(|r| r.name_of(type_id)) {
+        return name.to_string();
+    }
+    // Strip paths from every segment, including generic arguments
+    let mut short = String::new();
+    for part in type_name.split_inclusive(['<', '>', ',', ' ']) {
+        short.push_str(part.rsplit("::").next().unwrap_or(part));
+    }
+    short
+}
+
+/// Weak handle to an entity that stops resolving once the entity is despawned
+///
+/// Plain `EntityId`s stay valid as numbers after a despawn, so components that
+/// point at other entities should store an `EntityRef` instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntityRef {
+    entity: EntityId,
+    generation: u32,
+}
+
+impl EntityRef {
+    /// Resolve to the entity ID if the target is still alive
+    pub fn get(&self, world: &World) -> Option<EntityId> {
+        world
+            .entity_meta
+            .get(self.entity as usize)
+            .filter(|meta| meta.alive && meta.generation == self.generation)
+            .map(|_| self.entity)
+    }
+
+    /// Check whether the target is still alive
+    pub fn is_valid(&self, world: &World) -> bool {
+        self.get(world).is_some()
+    }
+
+    /// Get the raw entity ID without checking liveness
+    pub fn id(&self) -> EntityId {
+        self.entity
+    }
+}
+
+/// Components that hold `EntityRef`s to other entities
+///
+/// Implement this so `World::clear_dangling_refs` (or `App::track_entity_refs`)
+/// can reset references whose target has been despawned.
+pub trait EntityRefs {
+    /// Visit every entity reference held by this component
+    fn for_each_ref(&mut self, f: &mut dyn FnMut(&mut Option<EntityRef>));
+}
+
+/// Liveness bookkeeping for an entity ID
+#[derive(Debug, Clone, Copy)]
+struct EntityMeta {
+    generation: u32,
+    alive: bool,
+}
+
 /// ECS World that manages entities and components
 pub struct World {
     next_entity_id: EntityId,
     entities: Vec<EntityId>,
-    components: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    /// Generation and liveness per entity ID, indexed by ID
+    entity_meta: Vec<EntityMeta>,
+    components: HashMap<TypeId, Box<dyn ComponentStorage>>,
+    /// Name -> entities index, kept in sync by the `Name` insert/remove paths
+    name_index: HashMap<String, Vec<EntityId>>,
+    events: HashMap<TypeId, Box<dyn EventStorage>>,
+    /// Global singletons shared between systems
+    resources: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
 }
 
 impl Default for World {
@@ -31,7 +201,11 @@ impl World {
         Self {
             next_entity_id: 0,
             entities: Vec::new(),
+            entity_meta: Vec::new(),
             components: HashMap::new(),
+            name_index: HashMap::new(),
+            events: HashMap::new(),
+            resources: HashMap::new(),
         }
     }
 
@@ -40,9 +214,29 @@ impl World {
         let id = self.next_entity_id;
         self.next_entity_id += 1;
         self.entities.push(id);
+        self.entity_meta.push(EntityMeta {
+            generation: 0,
+            alive: true,
+        });
         id
     }
 
+    /// Check if an entity exists and has not been despawned
+    pub fn is_alive(&self, entity: EntityId) -> bool {
+        self.entity_meta
+            .get(entity as usize)
+            .is_some_and(|meta| meta.alive)
+    }
+
+    /// Create a weak reference to a live entity
+    pub fn entity_ref(&self, entity: EntityId) -> Option<EntityRef> {
+        let meta = self.entity_meta.get(entity as usize)?;
+        meta.alive.then_some(EntityRef {
+            entity,
+            generation: meta.generation,
+        })
+    }
+
     /// Create an entity with a builder pattern
     pub fn spawn(&mut self) -> EntityBuilder<'_> {
         let id = self.create_entity();
@@ -52,42 +246,59 @@ impl World {
         }
     }
 
+    /// Create an entity with all components in `bundle`
+    pub fn spawn_bundle<B: Bundle>(&mut self, bundle: B) -> EntityId {
+        let entity = self.create_entity();
+        bundle.insert_into(self, entity);
+        entity
+    }
+
+    /// Add all components in `bundle` to an existing entity
+    pub fn insert_bundle<B: Bundle>(&mut self, entity: EntityId, bundle: B) {
+        bundle.insert_into(self, entity);
+    }
+
     /// Add a component to an entity
     pub fn add_component<T: Component>(&mut self, entity: EntityId, component: T) {
+        if let Some(name) = (&component as &dyn Any).downcast_ref::<Name>() {
+            self.unindex_name(entity);
+            self.name_index
+                .entry(name.as_str().to_string())
+                .or_default()
+                .push(entity);
+        }
+
         let type_id = TypeId::of::<T>();
         let storage = self
             .components
             .entry(type_id)
             .or_insert_with(|| Box::new(HashMap::<EntityId, T>::new()));
 
-        if let Some(storage) = storage.downcast_mut::<HashMap<EntityId, T>>() {
+        if let Some(storage) = storage.as_any_mut().downcast_mut::<HashMap<EntityId, T>>() {
             storage.insert(entity, component);
         }
     }
 
     /// Get a component from an entity
     pub fn get_component<T: Component>(&self, entity: EntityId) -> Option<&T> {
-        let type_id = TypeId::of::<T>();
-        self.components
-            .get(&type_id)?
-            .downcast_ref::<HashMap<EntityId, T>>()?
-            .get(&entity)
+        self.storage::<T>()?.get(&entity)
     }
 
     /// Get a mutable component from an entity
     pub fn get_component_mut<T: Component>(&mut self, entity: EntityId) -> Option<&mut T> {
-        let type_id = TypeId::of::<T>();
-        self.components
-            .get_mut(&type_id)?
-            .downcast_mut::<HashMap<EntityId, T>>()?
-            .get_mut(&entity)
+        self.storage_mut::<T>()?.get_mut(&entity)
     }
 
     /// Remove a component from an entity
     pub fn remove_component<T: Component>(&mut self, entity: EntityId) -> Option<T> {
+        if TypeId::of::<T>() == TypeId::of::<Name>() {
+            self.unindex_name(entity);
+        }
+
         let type_id = TypeId::of::<T>();
         self.components
             .get_mut(&type_id)?
+            .as_any_mut()
             .downcast_mut::<HashMap<EntityId, T>>()?
             .remove(&entity)
     }
@@ -97,7 +308,7 @@ impl World {
         let type_id = TypeId::of::<T>();
         self.components
             .get(&type_id)
-            .and_then(|storage| storage.downcast_ref::<HashMap<EntityId, T>>())
+            .and_then(|storage| storage.as_any().downcast_ref::<HashMap<EntityId, T>>())
             .map(|storage| storage.iter().map(|(&id, component)| (id, component)))
             .into_iter()
             .flatten()
@@ -108,26 +319,172 @@ impl World {
         let type_id = TypeId::of::<T>();
         self.components
             .get_mut(&type_id)
-            .and_then(|storage| storage.downcast_mut::<HashMap<EntityId, T>>())
+            .and_then(|storage| storage.as_any_mut().downcast_mut::<HashMap<EntityId, T>>())
             .map(|storage| storage.iter_mut().map(|(&id, component)| (id, component)))
             .into_iter()
             .flatten()
     }
 
+    /// Query for entities matching a tuple of component accesses
+    ///
+    /// `Option<&T>` entries match whether or not the entity has `T`:
+    /// `world.query_many::<(&Transform, Option<&Velocity>)>()`
+    pub fn query_many<Q: ReadOnlyQueryData>(
+        &self,
+    ) -> impl Iterator<Item = (EntityId, Q::Item<'_>)> {
+        // SAFETY: read-only query data never writes through the pointer
+        let state = unsafe { Q::init(self as *const World as *mut World) };
+        self.entities
+            .iter()
+            // SAFETY: storages are borrowed from `self` for the iterator's lifetime
+            .filter_map(move |&entity| {
+                unsafe { Q::fetch(state, entity) }.map(|item| (entity, item))
+            })
+    }
+
+    /// Query for entities matching a tuple of component accesses, including `&mut T`
+    ///
+    /// Panics if the same component is requested mutably more than once.
+    pub fn query_many_mut<Q: QueryData>(
+        &mut self,
+    ) -> impl Iterator<Item = (EntityId, Q::Item<'_>)> {
+        let mut access = Vec::new();
+        Q::add_access(&mut access);
+        if let Err(conflict) = query::validate_access(&access) {
+            panic!("Invalid query: {conflict}");
+        }
+
+        let entities = self.entities.clone();
+        // SAFETY: `self` is exclusively borrowed for the iterator's lifetime and
+        // access validation rules out aliasing between storages
+        let state = unsafe { Q::init(self as *mut World) };
+        entities
+            .into_iter()
+            .filter_map(move |entity| unsafe { Q::fetch(state, entity) }.map(|item| (entity, item)))
+    }
+
+    /// Query for entities with component `T` that also pass filter `F`
+    pub fn query_filtered<T: Component, F: QueryFilter>(
+        &self,
+    ) -> impl Iterator<Item = (EntityId, &T)> {
+        self.query::<T>()
+            .filter(|&(entity, _)| F::matches(self, entity))
+    }
+
+    /// Query for entities with component `T` that also pass filter `F` (mutable)
+    pub fn query_filtered_mut<T: Component, F: QueryFilter>(
+        &mut self,
+    ) -> impl Iterator<Item = (EntityId, &mut T)> {
+        // Filters need shared access to the world, so resolve them up front
+        let matching: HashSet<EntityId> = self
+            .query::<T>()
+            .map(|(entity, _)| entity)
+            .filter(|&entity| F::matches(self, entity))
+            .collect();
+
+        self.query_mut::<T>()
+            .filter(move |(entity, _)| matching.contains(entity))
+    }
+
     /// Check if an entity has a specific component
     pub fn has_component<T: Component>(&self, entity: EntityId) -> bool {
         self.get_component::<T>(entity).is_some()
     }
 
+    /// Type IDs of all components on an entity
+    pub fn component_types(&self, entity: EntityId) -> Vec<TypeId> {
+        self.components
+            .iter()
+            .filter(|(_, storage)| storage.contains(entity))
+            .map(|(&type_id, _)| type_id)
+            .collect()
+    }
+
+    /// Spawn a copy of `entity` with all of its cloneable components
+    ///
+    /// Cloning goes through the `TypeRegistry` resource (or the built-in
+    /// registry if none is inserted); components it doesn't know are skipped.
+    pub fn clone_entity(&mut self, entity: EntityId) -> EntityId {
+        let clone = self.create_entity();
+        let registry = self.remove_resource::<TypeRegistry>();
+        registry
+            .as_ref()
+            .unwrap_or(&TypeRegistry::new())
+            .clone_components(self, entity, clone);
+        if let Some(registry) = registry {
+            self.insert_resource(registry);
+        }
+        clone
+    }
+
+    /// Count entities, components per type, and approximate memory use
+    pub fn stats(&self) -> WorldStats {
+        let registry = self.resource::<TypeRegistry>();
+        let mut components: Vec<ComponentStats> = self
+            .components
+            .iter()
+            .filter(|(_, storage)| storage.len() > 0)
+            .map(|(&type_id, storage)| ComponentStats {
+                name: display_name(registry, type_id, storage.type_name()),
+                type_name: storage.type_name(),
+                count: storage.len(),
+                bytes: storage.allocated_bytes(),
+            })
+            .collect();
+        components.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+
+        WorldStats {
+            entity_count: self.entities.len(),
+            resource_count: self.resources.len(),
+            event_types: self.events.len(),
+            components,
+        }
+    }
+
+    /// Describe every entity and the names of its components, one per line
+    pub fn dump_to_string(&self) -> String {
+        use std::fmt::Write;
+
+        let registry = self.resource::<TypeRegistry>();
+        let mut out = String::new();
+        for &entity in &self.entities {
+            let mut names: Vec<String> = self
+                .components
+                .iter()
+                .filter(|(_, storage)| storage.contains(entity))
+                .map(|(&type_id, storage)| display_name(registry, type_id, storage.type_name()))
+                .collect();
+            names.sort();
+            let _ = match self.name(entity) {
+                Some(name) => writeln!(out, "{entity} \"{name}\": [{}]", names.join(", ")),
+                None => writeln!(out, "{entity}: [{}]", names.join(", ")),
+            };
+        }
+        out
+    }
+
+    /// Print every entity and the names of its components to stdout
+    pub fn dump(&self) {
+        print!("{}", self.dump_to_string());
+    }
+
     /// Remove an entity and all its components
     pub fn despawn(&mut self, entity: EntityId) {
+        let Some(meta) = self.entity_meta.get_mut(entity as usize) else {
+            return;
+        };
+        if !meta.alive {
+            return;
+        }
+        meta.alive = false;
+        meta.generation += 1;
+
         self.entities.retain(|&e| e != entity);
+        self.unindex_name(entity);
 
         // Remove from all component storages
-        for _storage in self.components.values_mut() {
-            // This is a bit of a hack since we can't know the exact type
-            // In a more sophisticated ECS, you'd track which components an entity has
-            // For now, we'll just leave orphaned components (they won't be accessible)
+        for storage in self.components.values_mut() {
+            storage.remove_entity(entity);
         }
     }
 
@@ -135,6 +492,165 @@ impl World {
     pub fn entities(&self) -> &[EntityId] {
         &self.entities
     }
+
+    /// Reset every `EntityRef` in components of type `T` whose target is gone
+    pub fn clear_dangling_refs<T: Component + EntityRefs>(&mut self) {
+        let Some(storage) = self
+            .components
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_any_mut().downcast_mut::<HashMap<EntityId, T>>())
+        else {
+            return;
+        };
+
+        let meta = &self.entity_meta;
+        for component in storage.values_mut() {
+            component.for_each_ref(&mut |slot| {
+                let dangling = slot.is_some_and(|entity_ref| {
+                    meta.get(entity_ref.entity as usize)
+                        .is_none_or(|m| !m.alive || m.generation != entity_ref.generation)
+                });
+                if dangling {
+                    *slot = None;
+                }
+            });
+        }
+    }
+
+    /// Find the first entity with the given `Name`
+    pub fn find_by_name(&self, name: &str) -> Option<EntityId> {
+        self.name_index.get(name)?.first().copied()
+    }
+
+    /// Find all entities with the given `Name`
+    pub fn find_all_by_name(&self, name: &str) -> &[EntityId] {
+        self.name_index.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Get the name of an entity, if it has one
+    pub fn name(&self, entity: EntityId) -> Option<&str> {
+        self.get_component::<Name>(entity).map(Name::as_str)
+    }
+
+    fn storage<T: Component>(&self) -> Option<&HashMap<EntityId, T>> {
+        self.components
+            .get(&TypeId::of::<T>())?
+            .as_any()
+            .downcast_ref::<HashMap<EntityId, T>>()
+    }
+
+    fn storage_mut<T: Component>(&mut self) -> Option<&mut HashMap<EntityId, T>> {
+        self.components
+            .get_mut(&TypeId::of::<T>())?
+            .as_any_mut()
+            .downcast_mut::<HashMap<EntityId, T>>()
+    }
+
+    /// Empty world with the same entities (IDs, generations, and liveness)
+    pub(crate) fn with_same_entities(&self) -> World {
+        World {
+            next_entity_id: self.next_entity_id,
+            entities: self.entities.clone(),
+            entity_meta: self.entity_meta.clone(),
+            ..World::new()
+        }
+    }
+
+    /// Take the entities of `snapshot`, its components of the given types
+    /// (dropping ours of those types it lacks), and its resources; other
+    /// components and resources are kept
+    pub(crate) fn absorb(&mut self, snapshot: World, types: &[TypeId]) {
+        self.next_entity_id = snapshot.next_entity_id;
+        self.entities = snapshot.entities;
+        self.entity_meta = snapshot.entity_meta;
+        for type_id in types {
+            self.components.remove(type_id);
+        }
+        self.components.extend(snapshot.components);
+        self.resources.extend(snapshot.resources);
+    }
+
+    /// Insert a resource, replacing any existing one of the same type
+    pub fn insert_resource<T: 'static + Send + Sync>(&mut self, resource: T) {
+        self.resources.insert(TypeId::of::<T>(), Box::new(resource));
+    }
+
+    /// Get a resource
+    pub fn resource<T: 'static + Send + Sync>(&self) -> Option<&T> {
+        self.resources.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// Get a mutable resource
+    pub fn resource_mut<T: 'static + Send + Sync>(&mut self) -> Option<&mut T> {
+        self.resources.get_mut(&TypeId::of::<T>())?.downcast_mut()
+    }
+
+    /// Remove a resource and return it
+    pub fn remove_resource<T: 'static + Send + Sync>(&mut self) -> Option<T> {
+        self.resources
+            .remove(&TypeId::of::<T>())?
+            .downcast()
+            .ok()
+            .map(|resource| *resource)
+    }
+
+    /// Check if a resource exists
+    pub fn has_resource<T: 'static + Send + Sync>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Send an event, readable this frame and next frame
+    pub fn send<E: Event>(&mut self, event: E) {
+        self.events_mut::<E>().send(event);
+    }
+
+    /// Read all buffered events of type `E` without consuming them
+    pub fn read_events<E: Event>(&self) -> impl Iterator<Item = &E> {
+        self.event_storage::<E>().into_iter().flat_map(Events::iter)
+    }
+
+    /// Remove and return all buffered events of type `E`
+    pub fn drain_events<E: Event>(&mut self) -> Vec<E> {
+        self.events_mut::<E>().drain().collect()
+    }
+
+    /// Get mutable access to the event storage for `E`, creating it if needed
+    pub fn events_mut<E: Event>(&mut self) -> &mut Events<E> {
+        self.events
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::new(Events::<E>::default()))
+            .as_any_mut()
+            .downcast_mut::<Events<E>>()
+            .expect("event storage type mismatch")
+    }
+
+    /// Advance all event buffers by one frame (called by `App` before systems run)
+    pub fn update_events(&mut self) {
+        for events in self.events.values_mut() {
+            events.update();
+        }
+    }
+
+    fn event_storage<E: Event>(&self) -> Option<&Events<E>> {
+        self.events
+            .get(&TypeId::of::<E>())?
+            .as_any()
+            .downcast_ref::<Events<E>>()
+    }
+
+    /// Drop an entity from the name index (before its `Name` is replaced or removed)
+    fn unindex_name(&mut self, entity: EntityId) {
+        let Some(old) = self.get_component::<Name>(entity) else {
+            return;
+        };
+        let old = old.as_str().to_string();
+        if let Some(entities) = self.name_index.get_mut(&old) {
+            entities.retain(|&e| e != entity);
+            if entities.is_empty() {
+                self.name_index.remove(&old);
+            }
+        }
+    }
 }
 
 /// Builder pattern for creating entities with components
@@ -150,6 +666,12 @@ impl<'a> EntityBuilder<'a> {
         self
     }
 
+    /// Add all components in a bundle to this entity
+    pub fn with_bundle<B: Bundle>(self, bundle: B) -> Self {
+        bundle.insert_into(self.world, self.entity);
+        self
+    }
+
     /// Get the entity ID
     pub fn id(&self) -> EntityId {
         self.entity
@@ -0,0 +1,226 @@
+//! Tuple queries and query filters
+//!
+//! `QueryData` describes what a query fetches per entity: `&T`, `&mut T`,
+//! `Option<&T>`, `Option<&mut T>`, or a tuple of those. Filters (`With<T>`,
+//! `Without<T>`) only narrow which entities match.
+
+use super::{Component, EntityId, World};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// One component access made by a query, used to reject aliasing `&mut`
+#[derive(Debug, Clone, Copy)]
+pub struct ComponentAccess {
+    pub type_id: TypeId,
+    pub type_name: &'static str,
+    pub mutable: bool,
+}
+
+/// Data fetched for each entity by `World::query_many`
+///
+/// # Safety
+///
+/// Implementations must report every component they touch in `add_access`,
+/// and only create mutable references for accesses reported as mutable.
+pub unsafe trait QueryData {
+    /// The value yielded per entity
+    type Item<'w>;
+    /// Storage pointers resolved once per query
+    type State: Copy;
+
+    /// Record the components this query reads or writes
+    fn add_access(access: &mut Vec<ComponentAccess>);
+
+    /// Resolve component storages
+    ///
+    /// # Safety
+    ///
+    /// `world` must be valid for the lifetime of the returned state. Mutable
+    /// implementations require it to come from a `&mut World`.
+    unsafe fn init(world: *mut World) -> Self::State;
+
+    /// Fetch the item for an entity, or `None` if a required component is missing
+    ///
+    /// # Safety
+    ///
+    /// The state must come from `init` on a world that outlives `'w`, and no
+    /// other live reference may alias the mutable components of this entity.
+    unsafe fn fetch<'w>(state: Self::State, entity: EntityId) -> Option<Self::Item<'w>>;
+}
+
+/// Query data that only reads, and so can run against `&World`
+///
+/// # Safety
+///
+/// Implementations must never create mutable references.
+pub unsafe trait ReadOnlyQueryData: QueryData {}
+
+/// Check that a set of accesses contains no conflicting `&mut`
+pub(crate) fn validate_access(access: &[ComponentAccess]) -> Result<(), String> {
+    for (i, a) in access.iter().enumerate() {
+        for b in &access[i + 1..] {
+            if a.type_id == b.type_id && (a.mutable || b.mutable) {
+                return Err(format!(
+                    "`{}` is accessed mutably more than once or both mutably and immutably",
+                    a.type_name
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn access_of<T: Component>(mutable: bool) -> ComponentAccess {
+    ComponentAccess {
+        type_id: TypeId::of::<T>(),
+        type_name: std::any::type_name::<T>(),
+        mutable,
+    }
+}
+
+unsafe impl<T: Component> QueryData for &T {
+    type Item<'w> = &'w T;
+    type State = *const HashMap<EntityId, T>;
+
+    fn add_access(access: &mut Vec<ComponentAccess>) {
+        access.push(access_of::<T>(false));
+    }
+
+    unsafe fn init(world: *mut World) -> Self::State {
+        // SAFETY: caller guarantees `world` is valid; only a shared borrow is taken
+        let world = unsafe { &*world };
+        world
+            .storage::<T>()
+            .map_or(std::ptr::null(), |storage| storage as *const _)
+    }
+
+    unsafe fn fetch<'w>(state: Self::State, entity: EntityId) -> Option<Self::Item<'w>> {
+        // SAFETY: pointer is null or points at a storage that outlives 'w
+        unsafe { state.as_ref()?.get(&entity) }
+    }
+}
+
+unsafe impl<T: Component> ReadOnlyQueryData for &T {}
+
+unsafe impl<T: Component> QueryData for &mut T {
+    type Item<'w> = &'w mut T;
+    type State = *mut HashMap<EntityId, T>;
+
+    fn add_access(access: &mut Vec<ComponentAccess>) {
+        access.push(access_of::<T>(true));
+    }
+
+    unsafe fn init(world: *mut World) -> Self::State {
+        // SAFETY: caller guarantees `world` came from a `&mut World`
+        let world = unsafe { &mut *world };
+        world
+            .storage_mut::<T>()
+            .map_or(std::ptr::null_mut(), |storage| storage as *mut _)
+    }
+
+    unsafe fn fetch<'w>(state: Self::State, entity: EntityId) -> Option<Self::Item<'w>> {
+        // SAFETY: access validation guarantees this storage is not aliased, and
+        // each entity is fetched at most once per query
+        unsafe { state.as_mut()?.get_mut(&entity) }
+    }
+}
+
+unsafe impl<T: QueryData> QueryData for Option<T> {
+    type Item<'w> = Option<T::Item<'w>>;
+    type State = T::State;
+
+    fn add_access(access: &mut Vec<ComponentAccess>) {
+        T::add_access(access);
+    }
+
+    unsafe fn init(world: *mut World) -> Self::State {
+        unsafe { T::init(world) }
+    }
+
+    unsafe fn fetch<'w>(state: Self::State, entity: EntityId) -> Option<Self::Item<'w>> {
+        Some(unsafe { T::fetch(state, entity) })
+    }
+}
+
+unsafe impl<T: ReadOnlyQueryData> ReadOnlyQueryData for Option<T> {}
+
+macro_rules! impl_query_data_tuple {
+    ($($name:ident),+) => {
+        #[allow(non_snake_case)]
+        unsafe impl<$($name: QueryData),+> QueryData for ($($name,)+) {
+            type Item<'w> = ($($name::Item<'w>,)+);
+            type State = ($($name::State,)+);
+
+            fn add_access(access: &mut Vec<ComponentAccess>) {
+                $($name::add_access(access);)+
+            }
+
+            unsafe fn init(world: *mut World) -> Self::State {
+                ($(unsafe { $name::init(world) },)+)
+            }
+
+            unsafe fn fetch<'w>(state: Self::State, entity: EntityId) -> Option<Self::Item<'w>> {
+                let ($($name,)+) = state;
+                Some(($(unsafe { $name::fetch($name, entity) }?,)+))
+            }
+        }
+
+        unsafe impl<$($name: ReadOnlyQueryData),+> ReadOnlyQueryData for ($($name,)+) {}
+    };
+}
+
+impl_query_data_tuple!(A);
+impl_query_data_tuple!(A, B);
+impl_query_data_tuple!(A, B, C);
+impl_query_data_tuple!(A, B, C, D);
+impl_query_data_tuple!(A, B, C, D, E);
+impl_query_data_tuple!(A, B, C, D, E, F);
+
+/// Filter deciding whether an entity is included in a filtered query
+pub trait QueryFilter {
+    /// Check whether the entity passes this filter
+    fn matches(world: &World, entity: EntityId) -> bool;
+}
+
+/// Only match entities that have component `T`
+///
+/// Works well with zero-sized marker components (`struct Player;`), which are
+/// stored without any per-entity payload.
+pub struct With<T>(PhantomData<T>);
+
+/// Only match entities that do not have component `T`
+pub struct Without<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for With<T> {
+    fn matches(world: &World, entity: EntityId) -> bool {
+        world.has_component::<T>(entity)
+    }
+}
+
+impl<T: Component> QueryFilter for Without<T> {
+    fn matches(world: &World, entity: EntityId) -> bool {
+        !world.has_component::<T>(entity)
+    }
+}
+
+impl QueryFilter for () {
+    fn matches(_world: &World, _entity: EntityId) -> bool {
+        true
+    }
+}
+
+macro_rules! impl_filter_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: QueryFilter),+> QueryFilter for ($($name,)+) {
+            fn matches(world: &World, entity: EntityId) -> bool {
+                $($name::matches(world, entity))&&+
+            }
+        }
+    };
+}
+
+impl_filter_tuple!(A);
+impl_filter_tuple!(A, B);
+impl_filter_tuple!(A, B, C);
+impl_filter_tuple!(A, B, C, D);
@@ -0,0 +1,66 @@
+//! World statistics for debugging and profiling
+
+use std::fmt;
+
+/// Storage statistics for one component type
+#[derive(Debug, Clone)]
+pub struct ComponentStats {
+    /// Registered name, or the short type name
+    pub name: String,
+    pub type_name: &'static str,
+    /// Number of entities with this component
+    pub count: usize,
+    /// Approximate heap bytes used by the storage
+    pub bytes: usize,
+}
+
+/// Snapshot returned by `World::stats`
+#[derive(Debug, Clone, Default)]
+pub struct WorldStats {
+    pub entity_count: usize,
+    pub resource_count: usize,
+    pub event_types: usize,
+    /// Per-type counts, most common first
+    pub components: Vec<ComponentStats>,
+}
+
+impl WorldStats {
+    /// Approximate heap bytes used by all component storages
+    pub fn component_bytes(&self) -> usize {
+        self.components.iter().map(|c| c.bytes).sum()
+    }
+
+    /// Look up the stats for a component by name
+    pub fn get(&self, name: &str) -> Option<&ComponentStats> {
+        self.components.iter().find(|c| c.name == name)
+    }
+}
+
+impl fmt::Display for WorldStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} entities, {} resources, {} event types, ~{} KiB of components",
+            self.entity_count,
+            self.resource_count,
+            self.event_types,
+            self.component_bytes().div_ceil(1024)
+        )?;
+        let width = self
+            .components
+            .iter()
+            .map(|c| c.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(9);
+        writeln!(f, "{:<width$} {:>8} {:>10}", "Component", "Count", "Bytes")?;
+        for component in &self.components {
+            writeln!(
+                f,
+                "{:<width$} {:>8} {:>10}",
+                component.name, component.count, component.bytes
+            )?;
+        }
+        Ok(())
+    }
+}
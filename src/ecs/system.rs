@@ -0,0 +1,310 @@
+//! Function-parameter systems
+//!
+//! Besides the classic `fn(&mut World, &InputState, &TimeState)` signature,
+//! systems can declare exactly what they need and have it injected:
+//!
+//! ```rust,no_run
+//! use qsi::prelude::*;
+//! use qsi::math::Velocity;
+//!
+//! fn integrate(mut query: Query<(&mut Transform, &Velocity)>, time: Res<TimeState>) {
+//!     let dt = time.delta_seconds();
+//!     for (_, (transform, velocity)) in query.iter_mut() {
+//!         transform.position += velocity.linear * dt;
+//!     }
+//! }
+//!
+//! App::new().add_system(integrate).run().unwrap();
+//! ```
+//!
+//! Parameters are checked when the system is added: requesting the same
+//! component or resource mutably twice panics with the system's name.
+
+use super::query::{ComponentAccess, QueryData, QueryFilter, ReadOnlyQueryData, validate_access};
+use super::{EntityId, Event, Events, World};
+use crate::input::InputState;
+use crate::time::TimeState;
+use std::any::{Any, TypeId};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+/// Everything a system touches, used to reject conflicting parameters
+#[derive(Debug, Default)]
+pub struct SystemAccess {
+    pub components: Vec<ComponentAccess>,
+    pub resources: Vec<ComponentAccess>,
+}
+
+impl SystemAccess {
+    /// Check that no component or resource is aliased mutably
+    pub fn validate(&self) -> Result<(), String> {
+        validate_access(&self.components)?;
+        validate_access(&self.resources)
+    }
+}
+
+/// A value that can be injected as a system function parameter
+///
+/// # Safety
+///
+/// Implementations must report all accesses in `add_access` and create
+/// mutable references only for accesses reported as mutable.
+pub unsafe trait SystemParam {
+    /// The parameter type handed to the system for a given world borrow
+    type Item<'w>;
+
+    /// Record the components and resources this parameter uses
+    fn add_access(access: &mut SystemAccess);
+
+    /// Fetch the parameter
+    ///
+    /// # Safety
+    ///
+    /// `world` must be valid and exclusively borrowed for `'w`, and the
+    /// accesses of all parameters fetched together must have been validated.
+    unsafe fn fetch<'w>(
+        world: *mut World,
+        input: &'w InputState,
+        time: &'w TimeState,
+    ) -> Self::Item<'w>;
+}
+
+fn resource_access<T: 'static>(mutable: bool) -> ComponentAccess {
+    ComponentAccess {
+        type_id: TypeId::of::<T>(),
+        type_name: std::any::type_name::<T>(),
+        mutable,
+    }
+}
+
+/// Shared access to a resource
+///
+/// `Res<InputState>` and `Res<TimeState>` are always available.
+pub struct Res<'w, T: 'static>(&'w T);
+
+impl<T: 'static> Deref for Res<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+
+unsafe impl<T: 'static + Send + Sync> SystemParam for Res<'_, T> {
+    type Item<'w> = Res<'w, T>;
+
+    fn add_access(access: &mut SystemAccess) {
+        access.resources.push(resource_access::<T>(false));
+    }
+
+    unsafe fn fetch<'w>(
+        world: *mut World,
+        input: &'w InputState,
+        time: &'w TimeState,
+    ) -> Self::Item<'w> {
+        if let Some(input) = (input as &dyn Any).downcast_ref::<T>() {
+            return Res(input);
+        }
+        if let Some(time) = (time as &dyn Any).downcast_ref::<T>() {
+            return Res(time);
+        }
+        // SAFETY: caller guarantees `world` is valid for 'w
+        let world = unsafe { &*world };
+        Res(world
+            .resource::<T>()
+            .unwrap_or_else(|| panic!("Resource `{}` does not exist", std::any::type_name::<T>())))
+    }
+}
+
+/// Exclusive access to a resource
+pub struct ResMut<'w, T: 'static>(&'w mut T);
+
+impl<T: 'static> Deref for ResMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+
+impl<T: 'static> DerefMut for ResMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.0
+    }
+}
+
+unsafe impl<T: 'static + Send + Sync> SystemParam for ResMut<'_, T> {
+    type Item<'w> = ResMut<'w, T>;
+
+    fn add_access(access: &mut SystemAccess) {
+        access.resources.push(resource_access::<T>(true));
+    }
+
+    unsafe fn fetch<'w>(
+        world: *mut World,
+        _input: &'w InputState,
+        _time: &'w TimeState,
+    ) -> Self::Item<'w> {
+        // SAFETY: access validation guarantees no other parameter borrows `T`
+        let world = unsafe { &mut *world };
+        ResMut(
+            world.resource_mut::<T>().unwrap_or_else(|| {
+                panic!("Resource `{}` does not exist", std::any::type_name::<T>())
+            }),
+        )
+    }
+}
+
+unsafe impl<T: 'static + Send + Sync> SystemParam for Option<Res<'_, T>> {
+    type Item<'w> = Option<Res<'w, T>>;
+
+    fn add_access(access: &mut SystemAccess) {
+        access.resources.push(resource_access::<T>(false));
+    }
+
+    unsafe fn fetch<'w>(
+        world: *mut World,
+        _input: &'w InputState,
+        _time: &'w TimeState,
+    ) -> Self::Item<'w> {
+        // SAFETY: caller guarantees `world` is valid for 'w
+        let world = unsafe { &*world };
+        world.resource::<T>().map(Res)
+    }
+}
+
+unsafe impl<T: 'static + Send + Sync> SystemParam for Option<ResMut<'_, T>> {
+    type Item<'w> = Option<ResMut<'w, T>>;
+
+    fn add_access(access: &mut SystemAccess) {
+        access.resources.push(resource_access::<T>(true));
+    }
+
+    unsafe fn fetch<'w>(
+        world: *mut World,
+        _input: &'w InputState,
+        _time: &'w TimeState,
+    ) -> Self::Item<'w> {
+        // SAFETY: access validation guarantees no other parameter borrows `T`
+        let world = unsafe { &mut *world };
+        world.resource_mut::<T>().map(ResMut)
+    }
+}
+
+/// Sends events of type `E`
+pub struct EventWriter<'w, E: Event>(&'w mut Events<E>);
+
+impl<E: Event> EventWriter<'_, E> {
+    /// Send an event, readable this frame and next frame
+    pub fn send(&mut self, event: E) {
+        self.0.send(event);
+    }
+}
+
+unsafe impl<E: Event> SystemParam for EventWriter<'_, E> {
+    type Item<'w> = EventWriter<'w, E>;
+
+    fn add_access(access: &mut SystemAccess) {
+        // Event storages are tracked alongside resources
+        access.resources.push(resource_access::<Events<E>>(true));
+    }
+
+    unsafe fn fetch<'w>(
+        world: *mut World,
+        _input: &'w InputState,
+        _time: &'w TimeState,
+    ) -> Self::Item<'w> {
+        // SAFETY: access validation guarantees no other parameter borrows these events
+        let world = unsafe { &mut *world };
+        EventWriter(world.events_mut::<E>())
+    }
+}
+
+/// Iterates entities matching `Q` that pass filter `F`
+pub struct Query<'w, Q: QueryData, F: QueryFilter = ()> {
+    world: *mut World,
+    state: Q::State,
+    entities: Vec<EntityId>,
+    _marker: PhantomData<(&'w World, F)>,
+}
+
+impl<'w, Q: QueryData, F: QueryFilter> Query<'w, Q, F> {
+    /// Iterate with mutable access to the queried components
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (EntityId, Q::Item<'_>)> {
+        let (world, state) = (self.world, self.state);
+        self.entities
+            .iter()
+            // SAFETY: `&mut self` makes this the only live iteration; each entity is fetched once
+            .filter(move |&&entity| F::matches(unsafe { &*world }, entity))
+            .filter_map(move |&entity| {
+                unsafe { Q::fetch(state, entity) }.map(|item| (entity, item))
+            })
+    }
+
+    /// Get the queried components of one entity mutably
+    pub fn get_mut(&mut self, entity: EntityId) -> Option<Q::Item<'_>> {
+        // SAFETY: `&mut self` guarantees no other item from this query is alive
+        if !F::matches(unsafe { &*self.world }, entity) {
+            return None;
+        }
+        unsafe { Q::fetch(self.state, entity) }
+    }
+
+    /// Number of matching entities
+    pub fn count(&self) -> usize {
+        // SAFETY: only checks for presence; no references escape
+        let world = unsafe { &*self.world };
+        let state = self.state;
+        self.entities
+            .iter()
+            .filter(|&&entity| F::matches(world, entity))
+            .filter(|&&entity| unsafe { Q::fetch(state, entity) }.is_some())
+            .count()
+    }
+}
+
+impl<'w, Q: ReadOnlyQueryData, F: QueryFilter> Query<'w, Q, F> {
+    /// Iterate over the queried components
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, Q::Item<'_>)> {
+        let (world, state) = (self.world, self.state);
+        self.entities
+            .iter()
+            // SAFETY: read-only query data never creates mutable references
+            .filter(move |&&entity| F::matches(unsafe { &*world }, entity))
+            .filter_map(move |&entity| {
+                unsafe { Q::fetch(state, entity) }.map(|item| (entity, item))
+            })
+    }
+
+    /// Get the queried components of one entity
+    pub fn get(&self, entity: EntityId) -> Option<Q::Item<'_>> {
+        // SAFETY: read-only query data never creates mutable references
+        if !F::matches(unsafe { &*self.world }, entity) {
+            return None;
+        }
+        unsafe { Q::fetch(self.state, entity) }
+    }
+}
+
+unsafe impl<Q: QueryData, F: QueryFilter> SystemParam for Query<'_, Q, F> {
+    type Item<'w> = Query<'w, Q, F>;
+
+    fn add_access(access: &mut SystemAccess) {
+        Q::add_access(&mut access.components);
+    }
+
+    unsafe fn fetch<'w>(
+        world: *mut World,
+        _input: &'w InputState,
+        _time: &'w TimeState,
+    ) -> Self::Item<'w> {
+        // SAFETY: caller guarantees `world` is valid and accesses were validated
+        let entities = unsafe { (*world).entities.clone() };
+        Query {
+            world,
+            state: unsafe { Q::init(world) },
+            entities,
+            _marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! Frame sequence and video export
+//!
+//! Insert a `FrameRecorder` resource to capture every rendered frame (or every
+//! Nth) to numbered PNG files or to an `ffmpeg` process. While recording, the
+//! simulation advances by a fixed step per frame, so the exported video plays
+//! back at the same speed however long each frame took to render.
+//!
+//! ```no_run
+//! use qsi::frame_export::FrameRecorder;
+//! use qsi::prelude::*;
+//!
+//! App::new()
+//!     .with_headless(1280, 720)
+//!     .with_max_frames(600)
+//!     .insert_resource(FrameRecorder::png_sequence("frames").with_fps(60.0))
+//!     .run()
+//!     .unwrap();
+//! ```
+
+use crate::ecs::World;
+use crate::graphics::{CapturedImage, Renderer};
+use anyhow::{Context, Result, bail};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::time::Duration;
+
+/// Where recorded frames go
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameOutput {
+    /// Numbered PNG files (`frame_00000.png`, ...) in a directory
+    PngSequence { dir: PathBuf },
+    /// Raw frames piped to `ffmpeg`, which encodes them to `path`
+    Ffmpeg { path: PathBuf },
+}
+
+/// Resource capturing rendered frames while recording
+pub struct FrameRecorder {
+    output: FrameOutput,
+    /// Frame rate of the export; also sets the fixed simulation step
+    pub fps: f32,
+    /// Capture every Nth rendered frame (1 captures all)
+    pub every: u32,
+    /// Advance the simulation by a fixed step per rendered frame, so each
+    /// exported frame covers exactly `1 / fps` seconds
+    pub fixed_step: bool,
+    recording: bool,
+    rendered: u64,
+    written: u64,
+    ffmpeg: Option<(Child, ChildStdin, (u32, u32))>,
+}
+
+impl FrameRecorder {
+    /// Record to numbered PNGs in `dir` (created if missing)
+    pub fn png_sequence(dir: impl Into<PathBuf>) -> Self {
+        Self::new(FrameOutput::PngSequence { dir: dir.into() })
+    }
+
+    /// Record to a video file by piping frames to `ffmpeg`, which must be on
+    /// the `PATH`; the container and codec follow the file extension
+    pub fn ffmpeg(path: impl Into<PathBuf>) -> Self {
+        Self::new(FrameOutput::Ffmpeg { path: path.into() })
+    }
+
+    fn new(output: FrameOutput) -> Self {
+        Self {
+            output,
+            fps: 60.0,
+            every: 1,
+            fixed_step: true,
+            recording: true,
+            rendered: 0,
+            written: 0,
+            ffmpeg: None,
+        }
+    }
+
+    /// Set the export frame rate
+    pub fn with_fps(mut self, fps: f32) -> Self {
+        self.fps = fps.max(1e-3);
+        self
+    }
+
+    /// Capture only every Nth rendered frame
+    pub fn with_every(mut self, every: u32) -> Self {
+        self.every = every.max(1);
+        self
+    }
+
+    /// Choose between a fixed simulation step (the default) and real time
+    pub fn with_fixed_step(mut self, fixed_step: bool) -> Self {
+        self.fixed_step = fixed_step;
+        self
+    }
+
+    /// Start the recorder paused (see `start`)
+    pub fn paused(mut self) -> Self {
+        self.recording = false;
+        self
+    }
+
+    /// Where frames are written
+    pub fn output(&self) -> &FrameOutput {
+        &self.output
+    }
+
+    /// Resume capturing frames
+    pub fn start(&mut self) {
+        self.recording = true;
+    }
+
+    /// Stop capturing and finish the video file, if any; a later `start`
+    /// continues a PNG sequence but begins a new ffmpeg encode
+    pub fn stop(&mut self) {
+        self.recording = false;
+        self.finish_ffmpeg();
+    }
+
+    /// True while frames are being captured
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// Number of frames written so far
+    pub fn frames_written(&self) -> u64 {
+        self.written
+    }
+
+    /// Simulation step to use while recording, if any
+    pub fn fixed_delta(&self) -> Option<Duration> {
+        (self.recording && self.fixed_step)
+            .then(|| Duration::from_secs_f32(1.0 / (self.fps * self.every as f32)))
+    }
+
+    /// Count a rendered frame; true if it should be captured
+    fn should_capture(&mut self) -> bool {
+        if !self.recording {
+            return false;
+        }
+        let capture = self.rendered.is_multiple_of(self.every as u64);
+        self.rendered += 1;
+        capture
+    }
+
+    fn write(&mut self, image: &CapturedImage) -> Result<()> {
+        match &self.output {
+            FrameOutput::PngSequence { dir } => {
+                std::fs::create_dir_all(dir)
+                    .with_context(|| format!("Failed to create {}", dir.display()))?;
+                image.save_png(dir.join(format!("frame_{:05}.png", self.written)))?;
+            }
+            FrameOutput::Ffmpeg { path } => {
+                let size = (image.width, image.height);
+                if self.ffmpeg.is_none() {
+                    self.ffmpeg = Some(spawn_ffmpeg(path, size, self.fps)?);
+                }
+                if let Some((_, stdin, expected)) = &mut self.ffmpeg {
+                    if *expected != size {
+                        bail!(
+                            "Frame size changed from {}x{} to {}x{} during video export",
+                            expected.0,
+                            expected.1,
+                            size.0,
+                            size.1
+                        );
+                    }
+                    stdin
+                        .write_all(&image.pixels)
+                        .context("Failed to write frame to ffmpeg")?;
+                }
+            }
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    fn finish_ffmpeg(&mut self) {
+        if let Some((mut child, stdin, _)) = self.ffmpeg.take() {
+            // Closing stdin ends the input so ffmpeg can finalize the file
+            drop(stdin);
+            match child.wait() {
+                Ok(status) if !status.success() => log::error!("ffmpeg exited with {status}"),
+                Err(e) => log::error!("Failed to wait for ffmpeg: {e}"),
+                Ok(_) => {}
+            }
+        }
+    }
+}
+
+impl Drop for FrameRecorder {
+    fn drop(&mut self) {
+        self.finish_ffmpeg();
+    }
+}
+
+fn spawn_ffmpeg(
+    path: &std::path::Path,
+    (width, height): (u32, u32),
+    fps: f32,
+) -> Result<(Child, ChildStdin, (u32, u32))> {
+    let mut child = Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pixel_format",
+            "rgba",
+        ])
+        .args(["-video_size", &format!("{width}x{height}")])
+        .args(["-framerate", &fps.to_string(), "-i", "-"])
+        .args(["-pix_fmt", "yuv420p"])
+        .arg(path)
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("Failed to start ffmpeg; is it installed and on the PATH?")?;
+    let stdin = child.stdin.take().context("ffmpeg has no stdin")?;
+    Ok((child, stdin, (width, height)))
+}
+
+/// Capture the frame just rendered if a `FrameRecorder` wants it
+///
+/// Called by the app after each frame. Errors are logged and stop the recording.
+pub(crate) fn capture_frame(world: &mut World, renderer: &mut Renderer) {
+    let Some(recorder) = world.resource_mut::<FrameRecorder>() else {
+        return;
+    };
+    if !recorder.is_recording() {
+        return;
+    }
+    // Frames are drawn on demand, so keep them coming while recording
+    renderer.request_redraw();
+    if !recorder.should_capture() {
+        return;
+    }
+
+    let result = renderer.capture_frame(world).and_then(|image| {
+        match world.resource_mut::<FrameRecorder>() {
+            Some(recorder) => recorder.write(&image),
+            None => Ok(()),
+        }
+    });
+    if let (Err(e), Some(recorder)) = (result, world.resource_mut::<FrameRecorder>()) {
+        log::error!("Frame export stopped: {e:#}");
+        recorder.stop();
+    }
+}
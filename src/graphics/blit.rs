@@ -0,0 +1,239 @@
+//! Texture blits and region copies
+
+use super::Renderer;
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+
+/// Fullscreen-triangle pass sampling one texture into another, created on
+/// the first blit
+pub(crate) struct Blitter {
+    shader: wgpu::ShaderModule,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    nearest: wgpu::Sampler,
+    linear: wgpu::Sampler,
+    pipelines: HashMap<wgpu::TextureFormat, wgpu::RenderPipeline>,
+}
+
+impl Blitter {
+    fn new(device: &wgpu::Device) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("blit_bind_group_layout"),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blit Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Blit Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/blit.wgsl").into()),
+        });
+        let sampler = |label, filter| {
+            device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some(label),
+                mag_filter: filter,
+                min_filter: filter,
+                ..Default::default()
+            })
+        };
+        Self {
+            shader,
+            bind_group_layout,
+            pipeline_layout,
+            nearest: sampler("Blit Nearest Sampler", wgpu::FilterMode::Nearest),
+            linear: sampler("Blit Linear Sampler", wgpu::FilterMode::Linear),
+            pipelines: HashMap::new(),
+        }
+    }
+
+    fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        (source, target): (&wgpu::TextureView, &wgpu::TextureView),
+        filter: wgpu::FilterMode,
+    ) {
+        let format = target.texture().format();
+        let pipeline = self.pipelines.entry(format).or_insert_with(|| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Blit Pipeline"),
+                layout: Some(&self.pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &self.shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &self.shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        });
+        let sampler = match filter {
+            wgpu::FilterMode::Nearest => &self.nearest,
+            wgpu::FilterMode::Linear => &self.linear,
+        };
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+            label: Some("blit_bind_group"),
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Blit Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+impl Renderer {
+    /// Stretch `source` over all of `target`, converting between formats and
+    /// sizes, and submit it
+    ///
+    /// `source` must be a filterable float texture with `TEXTURE_BINDING`
+    /// usage; `target` a single-sampled view with `RENDER_ATTACHMENT` usage.
+    pub fn blit(
+        &mut self,
+        source: &wgpu::TextureView,
+        target: &wgpu::TextureView,
+        filter: wgpu::FilterMode,
+    ) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Blit Encoder"),
+            });
+        self.encode_blit(&mut encoder, source, target, filter);
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Record a `blit` into an existing encoder, for custom passes
+    pub fn encode_blit(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::TextureView,
+        target: &wgpu::TextureView,
+        filter: wgpu::FilterMode,
+    ) {
+        let device = &self.device;
+        self.blitter
+            .get_or_insert_with(|| Blitter::new(device))
+            .draw(device, encoder, (source, target), filter);
+        self.frame_stats
+            .record_draw(wgpu::PrimitiveTopology::TriangleList, 3, 1);
+    }
+
+    /// Copy a `size` region of `source` at `source_origin` into `target` at
+    /// `target_origin`, texel for texel, and submit it
+    ///
+    /// Both textures need the same format (ignoring sRGB) and sample count,
+    /// with `COPY_SRC` and `COPY_DST` usage respectively.
+    pub fn copy_texture_region(
+        &self,
+        source: &wgpu::Texture,
+        source_origin: [u32; 2],
+        target: &wgpu::Texture,
+        target_origin: [u32; 2],
+        size: [u32; 2],
+    ) -> Result<()> {
+        if source.format().remove_srgb_suffix() != target.format().remove_srgb_suffix() {
+            bail!(
+                "Cannot copy {:?} texels into a {:?} texture",
+                source.format(),
+                target.format()
+            );
+        }
+        let fits = |texture: &wgpu::Texture, [x, y]: [u32; 2]| {
+            x + size[0] <= texture.width() && y + size[1] <= texture.height()
+        };
+        if !fits(source, source_origin) || !fits(target, target_origin) {
+            bail!(
+                "Region of {}×{} does not fit in a {}×{} source at {source_origin:?} \
+                 or a {}×{} target at {target_origin:?}",
+                size[0],
+                size[1],
+                source.width(),
+                source.height(),
+                target.width(),
+                target.height()
+            );
+        }
+
+        let copy = |texture, [x, y]: [u32; 2]| wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d { x, y, z: 0 },
+            aspect: wgpu::TextureAspect::All,
+        };
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Copy Encoder"),
+            });
+        encoder.copy_texture_to_texture(
+            copy(source, source_origin),
+            copy(target, target_origin),
+            wgpu::Extent3d {
+                width: size[0],
+                height: size[1],
+                depth_or_array_layers: 1,
+            },
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+        Ok(())
+    }
+}
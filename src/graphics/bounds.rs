@@ -0,0 +1,187 @@
+//! Bounding boxes of mesh entities as components
+//!
+//! `BoundsPlugin` gives every mesh entity an `Aabb` component with its
+//! mesh's current bounds, and keeps a `WorldAabb` with those bounds placed by its
+//! `Transform` and `Instances` each frame, for culling, picking, and
+//! collision tests that work on entities rather than meshes.
+//!
+//! ```no_run
+//! use qsi::graphics::{BoundsDebug, BoundsPlugin, WorldAabb};
+//! use qsi::prelude::*;
+//! use winit::keyboard::KeyCode;
+//!
+//! App::new()
+//!     .add_plugin(BoundsPlugin)
+//!     .add_system(|world: &mut World, input: &InputState, _: &TimeState| {
+//!         if input.key_just_pressed(KeyCode::KeyB)
+//!             && let Some(debug) = world.resource_mut::<BoundsDebug>()
+//!         {
+//!             debug.toggle();
+//!         }
+//!         for (entity, bounds) in world.query::<WorldAabb>() {
+//!             if bounds.0.min.y < 0.0 {
+//!                 println!("{entity} reaches below the ground");
+//!             }
+//!         }
+//!     })
+//!     .run()
+//!     .unwrap();
+//! ```
+
+use super::{DebugDraw, Instances, Mesh};
+use crate::App;
+use crate::assets::{Assets, Handle};
+use crate::ecs::{Component, EntityId, Without, World};
+use crate::input::InputState;
+use crate::math::{Aabb, Matrix4, Transform};
+use crate::time::TimeState;
+use cgmath::SquareMatrix;
+
+/// World-space bounds of an entity's `Aabb`, after its `Transform` and
+/// around all of its `Instances`
+///
+/// Kept up to date by `bounds_system`; rotated entities get the looser box
+/// enclosing their rotated bounds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldAabb(pub Aabb);
+
+impl Component for WorldAabb {}
+
+/// The `Aabb` `bounds_system` last computed for an entity, so it can tell its
+/// own boxes (refreshed from the mesh) from ones set by hand (kept)
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AutoAabb(Aabb);
+
+impl Component for AutoAabb {}
+
+/// Resource switching the outlines of every `WorldAabb` on and off
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundsDebug {
+    /// Draw the bounds through `DebugDraw` each frame
+    pub enabled: bool,
+    pub color: [f32; 3],
+}
+
+impl Default for BoundsDebug {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: [1.0, 1.0, 0.0],
+        }
+    }
+}
+
+impl BoundsDebug {
+    /// Show the bounds if hidden, otherwise hide them
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+}
+
+/// Keep mesh entities' `Aabb`s in step with their meshes, update every
+/// `WorldAabb`, and draw them while `BoundsDebug` is enabled
+///
+/// Computed boxes follow meshes updated in place or replaced. An `Aabb` set
+/// by hand (e.g. larger, for a mesh a shader animates) is kept; removing it
+/// has it computed from the mesh again.
+pub fn bounds_system(world: &mut World, _input: &InputState, _time: &TimeState) {
+    let meshes = world.resource::<Assets<Mesh>>();
+    let shared = world
+        .query_filtered::<Handle<Mesh>, Without<Mesh>>()
+        .filter_map(|(entity, &handle)| Some((entity, meshes?.get(handle)?)));
+    let mut stale: Vec<(EntityId, Option<Aabb>)> = world
+        .query::<Mesh>()
+        .chain(shared)
+        .filter_map(|(entity, mesh)| {
+            let current = world.get_component::<Aabb>(entity);
+            let computed = world.get_component::<AutoAabb>(entity).map(|auto| auto.0);
+            let own = current.is_none() || current == computed.as_ref();
+            (own && current != Some(&mesh.bounds)).then_some((entity, Some(mesh.bounds)))
+        })
+        .collect();
+    // Computed boxes of entities that lost their mesh
+    stale.extend(
+        world
+            .query::<AutoAabb>()
+            .filter(|&(entity, _)| {
+                !world.has_component::<Mesh>(entity) && !world.has_component::<Handle<Mesh>>(entity)
+            })
+            .map(|(entity, _)| (entity, None)),
+    );
+    for (entity, bounds) in stale {
+        match bounds {
+            Some(bounds) => {
+                world.add_component(entity, bounds);
+                world.add_component(entity, AutoAabb(bounds));
+            }
+            None => {
+                if world.get_component::<Aabb>(entity)
+                    == world.get_component::<AutoAabb>(entity).map(|auto| &auto.0)
+                {
+                    world.remove_component::<Aabb>(entity);
+                }
+                world.remove_component::<AutoAabb>(entity);
+            }
+        }
+    }
+
+    let placed: Vec<(EntityId, Option<Aabb>)> = world
+        .query::<Aabb>()
+        .map(|(entity, bounds)| {
+            let model = world
+                .get_component::<Transform>(entity)
+                .map_or_else(Matrix4::identity, Transform::matrix);
+            let placed = match world.get_component::<Instances>(entity) {
+                Some(instances) => instances
+                    .instances
+                    .iter()
+                    .map(|instance| bounds.transformed(&(model * instance.transform.matrix())))
+                    .reduce(|a, b| a.union(&b)),
+                None => Some(bounds.transformed(&model)),
+            };
+            (entity, placed)
+        })
+        .collect();
+    for (entity, placed) in placed {
+        match placed {
+            Some(bounds) => world.add_component(entity, WorldAabb(bounds)),
+            // No instances, nothing to bound
+            None => {
+                world.remove_component::<WorldAabb>(entity);
+            }
+        }
+    }
+
+    let Some(debug) = world
+        .resource::<BoundsDebug>()
+        .copied()
+        .filter(|debug| debug.enabled)
+    else {
+        return;
+    };
+    let boxes: Vec<Aabb> = world
+        .query::<WorldAabb>()
+        .map(|(_, bounds)| bounds.0)
+        .collect();
+    if let Some(lines) = world.resource_mut::<DebugDraw>() {
+        for bounds in &boxes {
+            lines.aabb(bounds, debug.color);
+        }
+    }
+}
+
+/// Plugin keeping `Aabb` and `WorldAabb` components on mesh entities, with a
+/// `BoundsDebug` resource to draw them
+///
+/// `bounds_system` runs under the label `"bounds"`; systems moving entities
+/// go `.before("bounds")` for this frame's bounds to see the move.
+pub struct BoundsPlugin;
+
+impl crate::plugin::Plugin for BoundsPlugin {
+    fn build(&self, app: &mut App) {
+        use crate::schedule::IntoSystemDescriptor;
+
+        app.register_resource(BoundsDebug::default());
+        app.register_system(bounds_system.label("bounds"));
+    }
+}
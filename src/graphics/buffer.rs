@@ -0,0 +1,254 @@
+//! Typed GPU buffers for custom shaders and materials
+//!
+//! Both keep a CPU copy of their contents, note when it changes, and write it
+//! to the GPU on `upload`.
+//!
+//! ```rust,ignore
+//! #[repr(C)]
+//! #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+//! struct Params { tint: [f32; 4] }
+//!
+//! let mut params = UniformBuffer::new(renderer.device(), Params { tint: [1.0; 4] });
+//! let layout = [UniformBuffer::<Params>::layout_entry(0, wgpu::ShaderStages::FRAGMENT)];
+//! renderer.register_material::<Glow>(include_str!("glow.wgsl"), &layout)?;
+//!
+//! params.get_mut().tint = [1.0, 0.5, 0.0, 1.0];
+//! params.upload(renderer.queue());
+//! ```
+
+use bytemuck::Pod;
+use wgpu::util::DeviceExt;
+
+/// Single value of `T` in a uniform buffer
+///
+/// The buffer is padded to a multiple of 16 bytes; `T` itself must match
+/// WGSL's uniform layout rules (vec3s padded to 16 bytes, and so on).
+#[derive(Debug)]
+pub struct UniformBuffer<T: Pod> {
+    value: T,
+    buffer: wgpu::Buffer,
+    dirty: bool,
+}
+
+impl<T: Pod> UniformBuffer<T> {
+    /// Uniform buffer holding `value`, already uploaded
+    pub fn new(device: &wgpu::Device, value: T) -> Self {
+        let mut contents = bytemuck::bytes_of(&value).to_vec();
+        contents.resize(contents.len().next_multiple_of(16).max(16), 0);
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(std::any::type_name::<T>()),
+            contents: &contents,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        Self {
+            value,
+            buffer,
+            dirty: false,
+        }
+    }
+
+    /// Current value
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    /// Value to modify, marking the buffer for upload
+    pub fn get_mut(&mut self) -> &mut T {
+        self.dirty = true;
+        &mut self.value
+    }
+
+    /// Replace the value, marking the buffer for upload
+    pub fn set(&mut self, value: T) {
+        self.value = value;
+        self.dirty = true;
+    }
+
+    /// Whether the value changed since the last upload
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Write the value to the GPU if it changed; returns whether it did
+    pub fn upload(&mut self, queue: &wgpu::Queue) -> bool {
+        if !self.dirty {
+            return false;
+        }
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&self.value));
+        self.dirty = false;
+        true
+    }
+
+    /// The GPU buffer
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// The whole buffer, for a bind group entry
+    pub fn binding(&self) -> wgpu::BindingResource<'_> {
+        self.buffer.as_entire_binding()
+    }
+
+    /// Bind group layout entry for a uniform buffer at `binding`
+    pub fn layout_entry(
+        binding: u32,
+        visibility: wgpu::ShaderStages,
+    ) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }
+    }
+}
+
+/// Growable array of `T` in a storage buffer, for `array<T>` in WGSL
+///
+/// The GPU buffer grows to the next power of two when the array outgrows it.
+/// A new buffer means new bind groups, so `upload` reports when that happens.
+/// WGSL's `arrayLength` counts the capacity, so pass the length separately.
+#[derive(Debug)]
+pub struct StorageBuffer<T: Pod> {
+    items: Vec<T>,
+    buffer: wgpu::Buffer,
+    capacity: usize,
+    dirty: bool,
+}
+
+impl<T: Pod> StorageBuffer<T> {
+    /// Empty storage buffer with room for `capacity` items (at least one)
+    pub fn with_capacity(device: &wgpu::Device, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            items: Vec::new(),
+            buffer: create_storage_buffer::<T>(device, capacity),
+            capacity,
+            dirty: false,
+        }
+    }
+
+    /// Storage buffer holding `items`, uploaded on the first `upload`
+    pub fn from_slice(device: &wgpu::Device, items: &[T]) -> Self {
+        let mut buffer = Self::with_capacity(device, items.len());
+        buffer.extend_from_slice(items);
+        buffer
+    }
+
+    /// Current items
+    pub fn as_slice(&self) -> &[T] {
+        &self.items
+    }
+
+    /// Items to modify in place, marking the buffer for upload
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        self.dirty = true;
+        &mut self.items
+    }
+
+    /// Append an item
+    pub fn push(&mut self, item: T) {
+        self.items.push(item);
+        self.dirty = true;
+    }
+
+    /// Append several items
+    pub fn extend_from_slice(&mut self, items: &[T]) {
+        self.items.extend_from_slice(items);
+        self.dirty = true;
+    }
+
+    /// Replace all items
+    pub fn set(&mut self, items: Vec<T>) {
+        self.items = items;
+        self.dirty = true;
+    }
+
+    /// Remove all items, keeping the GPU buffer
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.dirty = true;
+    }
+
+    /// Number of items
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether there are no items
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Items the GPU buffer holds before it has to grow
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Whether the items changed since the last upload
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Write the items to the GPU if they changed, growing the buffer if
+    /// needed; returns `true` if the buffer was replaced and bind groups
+    /// using it must be recreated
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> bool {
+        let resized = self.items.len() > self.capacity;
+        if resized {
+            self.capacity = self.items.len().next_power_of_two();
+            self.buffer = create_storage_buffer::<T>(device, self.capacity);
+        }
+        if self.dirty && !self.items.is_empty() {
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&self.items));
+        }
+        self.dirty = false;
+        resized
+    }
+
+    /// The GPU buffer
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// The whole buffer, for a bind group entry
+    pub fn binding(&self) -> wgpu::BindingResource<'_> {
+        self.buffer.as_entire_binding()
+    }
+
+    /// Bind group layout entry for a storage buffer at `binding`
+    pub fn layout_entry(
+        binding: u32,
+        visibility: wgpu::ShaderStages,
+        read_only: bool,
+    ) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }
+    }
+}
+
+fn create_storage_buffer<T>(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    let size = (capacity * std::mem::size_of::<T>())
+        .next_multiple_of(16)
+        .max(16);
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(std::any::type_name::<T>()),
+        size: size as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    })
+}
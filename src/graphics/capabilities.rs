@@ -0,0 +1,94 @@
+//! Optional GPU features, detected once per adapter
+
+/// What the adapter supports beyond the baseline the renderer needs
+///
+/// Detected when the renderer is created. Features the adapter lacks are
+/// turned off with a log message instead of failing, so the same binary runs
+/// on discrete GPUs, weak integrated ones, and CI software adapters. Check
+/// `Renderer::capabilities` before relying on one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuCapabilities {
+    /// Name, backend, and type of the adapter in use
+    pub adapter: wgpu::AdapterInfo,
+    /// Highest MSAA sample count usable for captures, 1 without MSAA
+    pub max_samples: u32,
+    /// Triangles can be drawn as edges (`Renderer::set_wireframe`)
+    pub wireframe: bool,
+    /// Compute shaders and storage textures are available
+    pub compute: bool,
+    /// GPU timestamps can be written by passes and encoders
+    pub timestamp_queries: bool,
+}
+
+impl GpuCapabilities {
+    /// Check `adapter`, returning what it supports and the device features to
+    /// request for it; `sampled_formats` must all support a sample count for
+    /// it to count towards `max_samples`
+    pub(crate) fn detect(
+        adapter: &wgpu::Adapter,
+        sampled_formats: &[wgpu::TextureFormat],
+    ) -> (Self, wgpu::Features) {
+        let available = adapter.features();
+        let wanted = wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+            | wgpu::Features::POLYGON_MODE_LINE
+            | wgpu::Features::TIMESTAMP_QUERY
+            | wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS;
+        let features = available & wanted;
+
+        // Adapter-specific format features unlock counts above 4; without
+        // them 4 is the most WebGPU allows, and downlevel adapters may not
+        // even reach that
+        let counts: &[u32] =
+            if features.contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
+                &[16, 8, 4, 2]
+            } else {
+                &[4]
+            };
+        let flags: Vec<_> = sampled_formats
+            .iter()
+            .map(|&format| adapter.get_texture_format_features(format).flags)
+            .collect();
+        let max_samples = counts
+            .iter()
+            .copied()
+            .find(|&n| flags.iter().all(|flags| flags.sample_count_supported(n)))
+            .unwrap_or(1);
+
+        let capabilities = Self {
+            adapter: adapter.get_info(),
+            max_samples,
+            wireframe: features.contains(wgpu::Features::POLYGON_MODE_LINE),
+            compute: adapter
+                .get_downlevel_capabilities()
+                .flags
+                .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS),
+            timestamp_queries: features.contains(wgpu::Features::TIMESTAMP_QUERY),
+        };
+        capabilities.log();
+        (capabilities, features)
+    }
+
+    /// Report the adapter, and each optional feature turned off
+    fn log(&self) {
+        let info = &self.adapter;
+        log::info!(
+            "Using {} ({:?}, {:?}), MSAA up to {}x",
+            info.name,
+            info.backend,
+            info.device_type,
+            self.max_samples
+        );
+        if self.max_samples == 1 {
+            log::info!("MSAA disabled: the adapter cannot multisample the render formats");
+        }
+        if !self.wireframe {
+            log::info!("Wireframe disabled: the adapter has no line polygon mode");
+        }
+        if !self.compute {
+            log::info!("Compute paths disabled: the adapter has no compute shaders");
+        }
+        if !self.timestamp_queries {
+            log::info!("GPU timing disabled: the adapter has no timestamp queries");
+        }
+    }
+}
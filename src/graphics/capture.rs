@@ -0,0 +1,353 @@
+//! Offscreen frame capture and PNG export
+
+use super::color::{linear_to_srgb, srgb_to_linear};
+use super::post::PostOutput;
+use super::{HDR_FORMAT, Renderer, create_depth_view};
+use crate::ecs::World;
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+
+/// Color format of captured images
+pub const CAPTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// RGBA8 (sRGB) image read back from the GPU
+#[derive(Debug, Clone)]
+pub struct CapturedImage {
+    pub width: u32,
+    pub height: u32,
+    /// Tightly packed rows of RGBA bytes, top row first
+    pub pixels: Vec<u8>,
+}
+
+impl CapturedImage {
+    /// Write the image as a PNG file
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+        let mut writer = encoder
+            .write_header()
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        writer
+            .write_image_data(&self.pixels)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Shrink by an integer factor, averaging each block in linear color
+    pub fn downsample(&self, factor: u32) -> CapturedImage {
+        let factor = factor.max(1);
+        if factor == 1 {
+            return self.clone();
+        }
+        let width = self.width / factor;
+        let height = self.height / factor;
+        let to_linear: Vec<f32> = (0..=255u8)
+            .map(|v| srgb_to_linear(v as f32 / 255.0))
+            .collect();
+        let samples = (factor * factor) as f32;
+
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0f32; 4];
+                for sy in 0..factor {
+                    let row = ((y * factor + sy) * self.width + x * factor) as usize * 4;
+                    for sx in 0..factor as usize {
+                        let texel = &self.pixels[row + sx * 4..row + sx * 4 + 4];
+                        for c in 0..3 {
+                            sum[c] += to_linear[texel[c] as usize];
+                        }
+                        sum[3] += texel[3] as f32 / 255.0;
+                    }
+                }
+                for (c, total) in sum.into_iter().enumerate() {
+                    let value = if c < 3 {
+                        linear_to_srgb(total / samples)
+                    } else {
+                        total / samples
+                    };
+                    pixels.push((value * 255.0).round().clamp(0.0, 255.0) as u8);
+                }
+            }
+        }
+
+        CapturedImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+impl Renderer {
+    /// Highest MSAA sample count available for offscreen captures
+    pub fn max_capture_samples(&self) -> u32 {
+        self.capabilities.max_samples
+    }
+
+    /// Re-render the current frame offscreen at `scale` times the window size
+    /// (clamped to 1–4 and the device's texture limit) with maximum MSAA, then
+    /// downsample to the window size
+    ///
+    /// Memory use grows with `scale² × samples`, so 4x at high resolutions can
+    /// need several hundred megabytes of GPU memory for the duration of the call.
+    pub fn capture_high_quality(&mut self, world: &World, scale: u32) -> Result<CapturedImage> {
+        let (width, height) = (self.config.width, self.config.height);
+        if width == 0 || height == 0 {
+            bail!("Cannot capture a zero-sized window");
+        }
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+        let scale = scale
+            .clamp(1, 4)
+            .min(max_dimension / width.max(height))
+            .max(1);
+        let size = wgpu::Extent3d {
+            width: width * scale,
+            height: height * scale,
+            depth_or_array_layers: 1,
+        };
+        let samples = self.capabilities.max_samples;
+
+        let resolve = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: CAPTURE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let resolve_view = resolve.create_view(&wgpu::TextureViewDescriptor::default());
+        // The scene resolves into an HDR texture, tonemapped into `resolve`
+        let scene_view = self
+            .post_effects
+            .scene_view(&self.device, (size.width, size.height));
+        let msaa_view = (samples > 1).then(|| {
+            self.device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("Capture MSAA Texture"),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: samples,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: HDR_FORMAT,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+        let depth_view = create_depth_view(
+            &self.device,
+            size.width,
+            size.height,
+            self.depth_format,
+            samples,
+        );
+
+        self.extract(world);
+        let draws = self.prepare_scene(
+            &[(
+                self.current_view_matrix,
+                self.scene_projection((size.width, size.height)),
+            )],
+            (size.width, size.height),
+        );
+        let pipelines =
+            self.bucket_pipelines(&draws, (HDR_FORMAT, samples, wgpu::ColorWrites::ALL));
+        let extras = self.extra_pipelines((HDR_FORMAT, samples, wgpu::ColorWrites::ALL));
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Capture Encoder"),
+            });
+        {
+            let (view, resolve_target) = match &msaa_view {
+                Some(msaa_view) => (msaa_view, Some(&scene_view)),
+                None => (&scene_view, None),
+            };
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Capture Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.scene_clear_color()),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            self.apply_scene_region(
+                &mut render_pass,
+                (size.width, size.height),
+                scale as f32,
+                None,
+            );
+            let drawn = self.draw_scene(&mut render_pass, &draws, 0, &pipelines, &extras);
+            self.frame_stats += drawn;
+            // GL resolves multisampled targets with a blit that honors the
+            // scissor, so reopen it to the whole target first
+            render_pass.set_scissor_rect(0, 0, size.width, size.height);
+        }
+        let drawn = self.post_effects.run(
+            &self.device,
+            &self.queue,
+            &mut encoder,
+            &scene_view,
+            PostOutput {
+                view: &resolve_view,
+                format: CAPTURE_FORMAT,
+                viewport: self.scene_viewport(scale as f32),
+                keep_outside: false,
+            },
+            self.render_list.exposure,
+        );
+        self.frame_stats += drawn;
+        self.draw_sprites(&mut encoder, (&resolve_view, CAPTURE_FORMAT));
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let image = self.read_texture(&resolve)?;
+        Ok(image.downsample(scale))
+    }
+
+    /// Capture with `capture_high_quality` and save the result as a PNG
+    pub fn save_high_quality_screenshot(
+        &mut self,
+        world: &World,
+        path: impl AsRef<Path>,
+        scale: u32,
+    ) -> Result<()> {
+        self.capture_high_quality(world, scale)?.save_png(path)
+    }
+
+    /// Image of the current frame: the last rendered frame when headless,
+    /// otherwise a re-render of the window's view at its size
+    pub fn capture_frame(&mut self, world: &World) -> Result<CapturedImage> {
+        if self.offscreen.is_some() {
+            self.read_frame()
+        } else {
+            self.capture_high_quality(world, 1)
+        }
+    }
+
+    /// Read back the last frame drawn by a headless renderer
+    ///
+    /// Render systems run before the frame is drawn, so calling this from one
+    /// returns the previous frame.
+    pub fn read_frame(&self) -> Result<CapturedImage> {
+        let Some(target) = &self.offscreen else {
+            bail!("read_frame needs a headless renderer; use capture_high_quality for windows");
+        };
+        self.read_texture(target)
+    }
+
+    /// Copy an RGBA8 or BGRA8 texture with `COPY_SRC` usage back to the CPU,
+    /// blocking until the GPU is done
+    pub fn read_texture(&self, texture: &wgpu::Texture) -> Result<CapturedImage> {
+        let bgra = match texture.format().remove_srgb_suffix() {
+            wgpu::TextureFormat::Rgba8Unorm => false,
+            wgpu::TextureFormat::Bgra8Unorm => true,
+            format => bail!("Cannot read back {format:?} textures; blit to RGBA8 first"),
+        };
+        let (width, height) = (texture.width(), texture.height());
+        let mut pixels = self.read_texels(texture, wgpu::TextureAspect::All)?;
+        if bgra {
+            pixels
+                .chunks_exact_mut(4)
+                .for_each(|pixel| pixel.swap(0, 2));
+        }
+
+        Ok(CapturedImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Raw texels of `aspect` of a texture with `COPY_SRC` usage, in tightly
+    /// packed rows, blocking until the GPU is done
+    pub(crate) fn read_texels(
+        &self,
+        texture: &wgpu::Texture,
+        aspect: wgpu::TextureAspect,
+    ) -> Result<Vec<u8>> {
+        let format = texture.format();
+        let Some(texel_bytes) = format.block_copy_size(Some(aspect)) else {
+            bail!("{format:?} textures cannot be copied to the CPU");
+        };
+        let (width, height) = (texture.width(), texture.height());
+        let row_bytes = width * texel_bytes;
+        let padded_row_bytes = row_bytes.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (padded_row_bytes * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Readback Encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                aspect,
+                ..texture.as_image_copy()
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                depth_or_array_layers: 1,
+                ..texture.size()
+            },
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device
+            .poll(wgpu::PollType::Wait)
+            .context("Failed to wait for the GPU")?;
+        receiver
+            .recv()
+            .context("Readback was cancelled")?
+            .context("Failed to map readback buffer")?;
+
+        let mut texels = Vec::with_capacity((row_bytes * height) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(padded_row_bytes as usize) {
+                texels.extend_from_slice(&row[..row_bytes as usize]);
+            }
+        }
+        buffer.unmap();
+        Ok(texels)
+    }
+}
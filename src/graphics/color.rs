@@ -0,0 +1,197 @@
+//! Colors and the sRGB transfer function
+//!
+//! Every color the renderer stores — vertex colors, `Material` colors,
+//! lights, sprites, text, and the clear color — is linear RGB, the space
+//! lighting and blending happen in. sRGB render targets encode it on write,
+//! so values picked in an image editor or given as hex codes have to be
+//! converted first; `Color` does that conversion once, where the color is
+//! written down.
+
+use anyhow::{Result, bail};
+
+/// Linear RGBA color, built from sRGB or linear components
+///
+/// Converts into the `[f32; 3]` and `[f32; 4]` arrays component fields and
+/// builders take, and into `wgpu::Color` for `Renderer::set_clear_color`.
+///
+/// ```rust
+/// use qsi::graphics::{Color, Material};
+///
+/// let orange = Color::hex("#ff8000").unwrap();
+/// assert_eq!(orange.to_srgb_u8(), [255, 128, 0, 255]);
+/// // Mid-gray in sRGB is about a fifth of white in linear light
+/// assert!((Color::srgb(0.5, 0.5, 0.5).r - 0.214).abs() < 0.001);
+/// let material = Material::new(orange.into());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Self::WHITE
+    }
+}
+
+impl Color {
+    pub const WHITE: Self = Self::linear(1.0, 1.0, 1.0);
+    pub const BLACK: Self = Self::linear(0.0, 0.0, 0.0);
+    pub const TRANSPARENT: Self = Self::linear_rgba(0.0, 0.0, 0.0, 0.0);
+
+    /// Opaque color from linear components
+    pub const fn linear(r: f32, g: f32, b: f32) -> Self {
+        Self::linear_rgba(r, g, b, 1.0)
+    }
+
+    /// Color from linear components and alpha
+    pub const fn linear_rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Opaque color from sRGB components in 0..1, as color pickers show them
+    pub fn srgb(r: f32, g: f32, b: f32) -> Self {
+        Self::srgba(r, g, b, 1.0)
+    }
+
+    /// Color from sRGB components and (linear) alpha
+    pub fn srgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self::linear_rgba(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a)
+    }
+
+    /// Opaque color from 8-bit sRGB components
+    pub fn srgb_u8(r: u8, g: u8, b: u8) -> Self {
+        Self::srgb(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
+    }
+
+    /// Color from a `#rgb`, `#rrggbb`, or `#rrggbbaa` sRGB hex code; the `#`
+    /// is optional
+    pub fn hex(code: &str) -> Result<Self> {
+        let digits = code.strip_prefix('#').unwrap_or(code);
+        let expanded = match digits.len() {
+            3 => digits
+                .chars()
+                .flat_map(|c| [c, c])
+                .chain("ff".chars())
+                .collect(),
+            6 => format!("{digits}ff"),
+            _ => digits.to_string(),
+        };
+        let bytes: Option<Vec<u8>> = (expanded.len() == 8)
+            .then(|| {
+                (0..4)
+                    .map(|i| {
+                        let pair = expanded.get(i * 2..i * 2 + 2)?;
+                        u8::from_str_radix(pair, 16).ok()
+                    })
+                    .collect()
+            })
+            .flatten();
+        let Some([r, g, b, a]) = bytes.and_then(|bytes| <[u8; 4]>::try_from(bytes).ok()) else {
+            bail!("Invalid hex color `{code}`, expected #rgb, #rrggbb, or #rrggbbaa");
+        };
+        Ok(Self::srgb_u8(r, g, b).with_alpha(a as f32 / 255.0))
+    }
+
+    /// Same color with a different alpha
+    pub fn with_alpha(mut self, a: f32) -> Self {
+        self.a = a;
+        self
+    }
+
+    /// Linear RGB components
+    pub fn rgb(&self) -> [f32; 3] {
+        [self.r, self.g, self.b]
+    }
+
+    /// Linear RGB components and alpha
+    pub fn rgba(&self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+
+    /// sRGB components and alpha in 0..1
+    pub fn to_srgb(&self) -> [f32; 4] {
+        [
+            linear_to_srgb(self.r),
+            linear_to_srgb(self.g),
+            linear_to_srgb(self.b),
+            self.a,
+        ]
+    }
+
+    /// 8-bit sRGB components and alpha
+    pub fn to_srgb_u8(&self) -> [u8; 4] {
+        self.to_srgb()
+            .map(|c| (c * 255.0).round().clamp(0.0, 255.0) as u8)
+    }
+}
+
+impl From<Color> for [f32; 3] {
+    fn from(color: Color) -> Self {
+        color.rgb()
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        color.rgba()
+    }
+}
+
+impl From<[f32; 3]> for Color {
+    /// Linear components, as stored in component fields
+    fn from([r, g, b]: [f32; 3]) -> Self {
+        Self::linear(r, g, b)
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from([r, g, b, a]: [f32; 4]) -> Self {
+        Self::linear_rgba(r, g, b, a)
+    }
+}
+
+impl From<Color> for wgpu::Color {
+    fn from(color: Color) -> Self {
+        Self {
+            r: color.r as f64,
+            g: color.g as f64,
+            b: color.b as f64,
+            a: color.a as f64,
+        }
+    }
+}
+
+impl From<wgpu::Color> for Color {
+    /// Linear components, as wgpu treats them
+    fn from(color: wgpu::Color) -> Self {
+        Self::linear_rgba(
+            color.r as f32,
+            color.g as f32,
+            color.b as f32,
+            color.a as f32,
+        )
+    }
+}
+
+/// Decode one sRGB component to linear
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encode one linear component to sRGB
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,185 @@
+//! GPU setup choices made when a renderer is created
+
+use super::DEFAULT_DEPTH_FORMAT;
+
+/// How a renderer picks its adapter and device and presents frames
+///
+/// The defaults suit most apps: vsync, the fastest adapter, the primary
+/// backends of the platform (plus OpenGL when headless), and wgpu's default
+/// limits.
+///
+/// ```rust,no_run
+/// use qsi::graphics::RendererConfig;
+/// use qsi::prelude::*;
+///
+/// // Save battery on laptops and draw as fast as the display can take frames
+/// App::new()
+///     .with_renderer_config(
+///         RendererConfig::default()
+///             .low_power()
+///             .with_present_mode(wgpu::PresentMode::Mailbox),
+///     )
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RendererConfig {
+    /// How frames are queued for the display; modes the surface does not
+    /// support fall back to `Fifo`
+    pub present_mode: wgpu::PresentMode,
+    /// Whether to prefer a discrete or an integrated GPU
+    pub power_preference: wgpu::PowerPreference,
+    /// Backends to look for adapters on, or `None` for the primary ones
+    /// (plus OpenGL when headless)
+    pub backends: Option<wgpu::Backends>,
+    /// Limits the device must support; creating the renderer fails if the
+    /// adapter falls short
+    pub limits: wgpu::Limits,
+    /// Depth buffer format
+    pub depth_format: wgpu::TextureFormat,
+    /// How the surface blends with the desktop, or `None` for the surface's
+    /// preferred mode; transparent windows use `PreMultiplied`
+    pub alpha_mode: Option<wgpu::CompositeAlphaMode>,
+    /// Adapter and device to draw with instead of requesting them, for a GPU
+    /// set up elsewhere; `backends` and `power_preference` then do not apply
+    pub gpu: Option<SharedGpu>,
+}
+
+/// Instance, adapter, device, and queue created outside the renderer, such
+/// as the Vulkan device an OpenXR runtime picks (see `qsi::xr::OpenXrPlugin`)
+///
+/// The device must have been created with the features
+/// `RendererConfig::device_features` asks of its adapter.
+#[derive(Debug, Clone)]
+pub struct SharedGpu {
+    pub instance: wgpu::Instance,
+    pub adapter: wgpu::Adapter,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            present_mode: wgpu::PresentMode::Fifo,
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            backends: None,
+            limits: wgpu::Limits::default(),
+            depth_format: DEFAULT_DEPTH_FORMAT,
+            alpha_mode: None,
+            gpu: None,
+        }
+    }
+}
+
+impl RendererConfig {
+    /// Set how frames are presented
+    pub fn with_present_mode(mut self, mode: wgpu::PresentMode) -> Self {
+        self.present_mode = mode;
+        self
+    }
+
+    /// Wait for vertical blank (`Fifo`) or present as soon as possible
+    /// (`Immediate`, or `Mailbox` where tearing is unsupported)
+    pub fn with_vsync(self, vsync: bool) -> Self {
+        self.with_present_mode(if vsync {
+            wgpu::PresentMode::AutoVsync
+        } else {
+            wgpu::PresentMode::AutoNoVsync
+        })
+    }
+
+    /// Set the adapter power preference
+    pub fn with_power_preference(mut self, preference: wgpu::PowerPreference) -> Self {
+        self.power_preference = preference;
+        self
+    }
+
+    /// Prefer an integrated GPU, which draws less power
+    pub fn low_power(self) -> Self {
+        self.with_power_preference(wgpu::PowerPreference::LowPower)
+    }
+
+    /// Only use these backends (e.g. only Vulkan, for debugging)
+    pub fn with_backends(mut self, backends: wgpu::Backends) -> Self {
+        self.backends = Some(backends);
+        self
+    }
+
+    /// Require these device limits instead of wgpu's defaults
+    pub fn with_limits(mut self, limits: wgpu::Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set the depth buffer format
+    pub fn with_depth_format(mut self, format: wgpu::TextureFormat) -> Self {
+        self.depth_format = format;
+        self
+    }
+
+    /// Set how the surface blends with the desktop
+    pub fn with_alpha_mode(mut self, mode: wgpu::CompositeAlphaMode) -> Self {
+        self.alpha_mode = Some(mode);
+        self
+    }
+
+    /// Draw with an existing device instead of requesting one
+    pub fn with_gpu(mut self, gpu: SharedGpu) -> Self {
+        self.gpu = Some(gpu);
+        self
+    }
+
+    /// Device features the renderer uses on `adapter`, for creating a
+    /// `SharedGpu` device
+    pub fn device_features(&self, adapter: &wgpu::Adapter) -> wgpu::Features {
+        super::GpuCapabilities::detect(adapter, &self.sampled_formats()).1
+    }
+
+    /// Formats the renderer multisamples, which decide `max_samples`
+    pub(crate) fn sampled_formats(&self) -> [wgpu::TextureFormat; 3] {
+        [super::CAPTURE_FORMAT, super::HDR_FORMAT, self.depth_format]
+    }
+
+    /// Instance to create the renderer's surface and adapter from: the shared
+    /// GPU's, or a new one on `backends`
+    pub(crate) fn instance(&self, headless: bool) -> wgpu::Instance {
+        match &self.gpu {
+            Some(gpu) => gpu.instance.clone(),
+            None => wgpu::Instance::new(&wgpu::InstanceDescriptor {
+                backends: self.backends(headless),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Backends to use, filling in the default for windowed or headless
+    /// renderers
+    pub(crate) fn backends(&self, headless: bool) -> wgpu::Backends {
+        self.backends.unwrap_or(if headless {
+            // Servers often only have a software OpenGL driver, so allow GL too
+            wgpu::Backends::PRIMARY | wgpu::Backends::GL
+        } else {
+            wgpu::Backends::PRIMARY
+        })
+    }
+}
+
+impl super::Renderer {
+    /// Change how frames are presented, e.g. to toggle vsync from a settings
+    /// menu; modes the surface does not support fall back to `Fifo`
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) {
+        let mode = super::supported_present_mode(mode, &self.present_modes);
+        if mode != self.config.present_mode {
+            self.config.present_mode = mode;
+            if let (Some(surface), true) = (&self.surface, self.is_surface_configured) {
+                surface.configure(&self.device, &self.config);
+            }
+        }
+    }
+
+    /// Present mode frames are shown with
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.config.present_mode
+    }
+}
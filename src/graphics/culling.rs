@@ -0,0 +1,62 @@
+//! CPU frustum culling of meshes before draw submission
+
+use crate::math::{Aabb, Frustum};
+use cgmath::Matrix4;
+
+/// How many objects the last rendered frame drew and skipped
+///
+/// Each instance of an instanced mesh counts as one object.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CullingStats {
+    /// Objects inside at least one view frustum
+    pub visible: u32,
+    /// Objects outside every view frustum, not drawn
+    pub culled: u32,
+}
+
+impl CullingStats {
+    /// Objects considered, drawn or not
+    pub fn total(&self) -> u32 {
+        self.visible + self.culled
+    }
+}
+
+/// Visibility test against all views of a frame, counting the results
+pub(crate) struct Culler {
+    // Empty when culling is disabled, so everything passes
+    frustums: Vec<Frustum>,
+    pub stats: CullingStats,
+}
+
+impl Culler {
+    pub fn new(enabled: bool, views: &[(Matrix4<f32>, Matrix4<f32>)]) -> Self {
+        let frustums = if enabled {
+            views
+                .iter()
+                .map(|(view, proj)| Frustum::from_view_projection(&(proj * view)))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Self {
+            frustums,
+            stats: CullingStats::default(),
+        }
+    }
+
+    /// Whether `bounds` transformed by `model` may be seen by any view
+    pub fn is_visible(&mut self, bounds: &Aabb, model: &Matrix4<f32>) -> bool {
+        let visible = self.frustums.is_empty() || {
+            let bounds = bounds.transformed(model);
+            self.frustums
+                .iter()
+                .any(|frustum| frustum.intersects(&bounds))
+        };
+        if visible {
+            self.stats.visible += 1;
+        } else {
+            self.stats.culled += 1;
+        }
+        visible
+    }
+}
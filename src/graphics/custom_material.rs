@@ -0,0 +1,163 @@
+//! User-supplied WGSL shaders for meshes with a custom material component
+
+use super::Renderer;
+use crate::ecs::{Component, EntityId, World};
+use anyhow::{Result, anyhow};
+use std::any::TypeId;
+
+/// Component that draws its entity with a shader registered through
+/// `Renderer::register_material`
+///
+/// The shader must provide `vs_main` and `fs_main` entry points taking the
+/// same vertex layout as the default shader (see `Vertex::desc`). Groups 0
+/// (globals, objects, light) and 1 (normal map) match `default.wgsl` and may be
+/// declared as needed; group 2 is the material's own layout.
+///
+/// ```rust,ignore
+/// struct Glow {
+///     bind_group: wgpu::BindGroup,
+/// }
+///
+/// impl Component for Glow {}
+///
+/// impl CustomMaterial for Glow {
+///     fn bind_group(&self) -> Option<&wgpu::BindGroup> {
+///         Some(&self.bind_group)
+///     }
+/// }
+///
+/// renderer.register_material::<Glow>(include_str!("glow.wgsl"), &[uniform_entry])?;
+/// let layout = renderer.material_bind_group_layout::<Glow>().unwrap();
+/// ```
+pub trait CustomMaterial: Component {
+    /// Bind group for the material's layout (group 2); entities whose material
+    /// has a layout but returns `None` here are not drawn
+    fn bind_group(&self) -> Option<&wgpu::BindGroup> {
+        None
+    }
+}
+
+type FetchFn = fn(&World, EntityId) -> Option<Option<wgpu::BindGroup>>;
+
+/// A registered material type with its shader and layouts
+pub(crate) struct RegisteredMaterial {
+    pub(crate) type_id: TypeId,
+    pub(crate) shader: wgpu::ShaderModule,
+    pub(crate) pipeline_layout: wgpu::PipelineLayout,
+    pub(crate) bind_group_layout: Option<wgpu::BindGroupLayout>,
+    /// `Some` if the entity has the component, holding its bind group
+    pub(crate) fetch: FetchFn,
+}
+
+fn fetch_material<M: CustomMaterial>(
+    world: &World,
+    entity: EntityId,
+) -> Option<Option<wgpu::BindGroup>> {
+    world
+        .get_component::<M>(entity)
+        .map(|material| material.bind_group().cloned())
+}
+
+/// Compile WGSL, returning validation errors instead of panicking
+pub(crate) fn create_shader_module(
+    device: &wgpu::Device,
+    label: &str,
+    source: &str,
+) -> Result<wgpu::ShaderModule> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(error) => Err(anyhow!("Failed to compile {label}: {error}")),
+        None => Ok(module),
+    }
+}
+
+/// Compiled WGSL with the source it came from
+///
+/// The source is kept so it can be registered as a material
+/// (`Renderer::register_material`) or recompiled after edits.
+#[derive(Debug, Clone)]
+pub struct Shader {
+    pub source: String,
+    pub module: wgpu::ShaderModule,
+}
+
+impl Renderer {
+    /// Compile `source`, returning validation errors instead of panicking
+    pub fn create_shader(&self, label: &str, source: impl Into<String>) -> Result<Shader> {
+        let source = source.into();
+        let module = create_shader_module(&self.device, label, &source)?;
+        Ok(Shader { source, module })
+    }
+
+    /// Draw entities with an `M` component using `wgsl_source`, with
+    /// `bind_layout` describing the material's own bind group (group 2, may
+    /// be empty)
+    ///
+    /// Registering the same type again replaces its shader. Fails without
+    /// changing anything if the shader does not compile.
+    pub fn register_material<M: CustomMaterial>(
+        &mut self,
+        wgsl_source: &str,
+        bind_layout: &[wgpu::BindGroupLayoutEntry],
+    ) -> Result<()> {
+        let label = std::any::type_name::<M>();
+        let shader = create_shader_module(&self.device, label, wgsl_source)?;
+
+        let bind_group_layout = (!bind_layout.is_empty()).then(|| {
+            self.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    entries: bind_layout,
+                    label: Some(label),
+                })
+        });
+        let mut layouts = vec![
+            &self.uniform_bind_group_layout,
+            &self.texture_bind_group_layout,
+        ];
+        layouts.extend(bind_group_layout.as_ref());
+        let pipeline_layout = self
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &layouts,
+                push_constant_ranges: &[],
+            });
+
+        let material = RegisteredMaterial {
+            type_id: TypeId::of::<M>(),
+            shader,
+            pipeline_layout,
+            bind_group_layout,
+            fetch: fetch_material::<M>,
+        };
+        match self.material_index::<M>() {
+            Some(index) => {
+                self.materials[index] = material;
+                self.clear_material_pipelines(Some(index));
+            }
+            None => self.materials.push(material),
+        }
+        Ok(())
+    }
+
+    /// Layout of `M`'s bind group, for creating the group its components return
+    pub fn material_bind_group_layout<M: CustomMaterial>(&self) -> Option<&wgpu::BindGroupLayout> {
+        let index = self.material_index::<M>()?;
+        self.materials[index].bind_group_layout.as_ref()
+    }
+
+    /// True if `M` has been registered
+    pub fn has_material<M: CustomMaterial>(&self) -> bool {
+        self.material_index::<M>().is_some()
+    }
+
+    pub(super) fn material_index<M: CustomMaterial>(&self) -> Option<usize> {
+        self.materials
+            .iter()
+            .position(|material| material.type_id == TypeId::of::<M>())
+    }
+}
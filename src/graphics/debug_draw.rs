@@ -0,0 +1,363 @@
+//! Immediate-mode debug lines collected each frame
+
+use super::{PipelineKey, RenderStats, Renderer};
+use crate::ecs::World;
+use crate::math::{Aabb, Matrix4, Vector3};
+use cgmath::InnerSpace;
+use std::collections::HashMap;
+
+/// Segments per circle, including each of a sphere's three
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// Resource collecting lines drawn over the scene for one frame, for
+/// debugging physics, AI, and cameras
+///
+/// Any system can add shapes; the renderer draws them all in one batch and
+/// they are cleared after every frame, so shapes meant to stay visible are
+/// added again each frame. The app inserts one by default.
+///
+/// ```rust,ignore
+/// let debug = world.resource_mut::<DebugDraw>().unwrap();
+/// debug.line(start, end, [1.0, 0.0, 0.0]);
+/// debug.aabb(&mesh.bounds.transformed(&transform.matrix()), [0.0, 1.0, 0.0]);
+/// debug.arrow(position, position + velocity, [1.0, 1.0, 0.0]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugDraw {
+    /// Hide lines behind scene geometry; off draws them on top of everything
+    pub depth_test: bool,
+    vertices: Vec<DebugVertex>,
+}
+
+impl Default for DebugDraw {
+    fn default() -> Self {
+        Self {
+            depth_test: true,
+            vertices: Vec::new(),
+        }
+    }
+}
+
+impl DebugDraw {
+    /// Line from `a` to `b`
+    pub fn line(&mut self, a: Vector3<f32>, b: Vector3<f32>, color: [f32; 3]) {
+        self.vertices.push(DebugVertex {
+            position: a.into(),
+            color,
+        });
+        self.vertices.push(DebugVertex {
+            position: b.into(),
+            color,
+        });
+    }
+
+    /// Line from `origin` along `direction`, including its length
+    pub fn ray(&mut self, origin: Vector3<f32>, direction: Vector3<f32>, color: [f32; 3]) {
+        self.line(origin, origin + direction, color);
+    }
+
+    /// Line from `from` to `to` with a head at `to`
+    pub fn arrow(&mut self, from: Vector3<f32>, to: Vector3<f32>, color: [f32; 3]) {
+        self.line(from, to, color);
+        let along = to - from;
+        let length = along.magnitude();
+        if length <= f32::EPSILON {
+            return;
+        }
+        let (side, up) = perpendiculars(along / length);
+        let back = to - along * 0.2;
+        let spread = length * 0.08;
+        for offset in [side, -side, up, -up] {
+            self.line(to, back + offset * spread, color);
+        }
+    }
+
+    /// The 12 edges of an axis-aligned box
+    pub fn aabb(&mut self, aabb: &Aabb, color: [f32; 3]) {
+        self.cuboid(
+            &Matrix4::from_translation(aabb.center()),
+            aabb.half_extents(),
+            color,
+        );
+    }
+
+    /// The 12 edges of a box with `half_extents` around the origin of
+    /// `transform`, for oriented boxes
+    pub fn cuboid(
+        &mut self,
+        transform: &Matrix4<f32>,
+        half_extents: Vector3<f32>,
+        color: [f32; 3],
+    ) {
+        let corner = |i: usize| {
+            let sign = |bit: usize| if i & bit == 0 { -1.0 } else { 1.0 };
+            let local = Vector3::new(
+                sign(1) * half_extents.x,
+                sign(2) * half_extents.y,
+                sign(4) * half_extents.z,
+            );
+            (transform * local.extend(1.0)).truncate()
+        };
+        // Corners differing in exactly one axis bit share an edge
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+
+    /// Circle of `radius` around `center` in the plane facing `normal`
+    pub fn circle(
+        &mut self,
+        center: Vector3<f32>,
+        normal: Vector3<f32>,
+        radius: f32,
+        color: [f32; 3],
+    ) {
+        if normal.magnitude2() <= f32::EPSILON {
+            return;
+        }
+        let (u, v) = perpendiculars(normal.normalize());
+        let point = |i: usize| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + (u * angle.cos() + v * angle.sin()) * radius
+        };
+        for i in 0..CIRCLE_SEGMENTS {
+            self.line(point(i), point(i + 1), color);
+        }
+    }
+
+    /// Wire sphere as three circles, one around each axis
+    pub fn sphere(&mut self, center: Vector3<f32>, radius: f32, color: [f32; 3]) {
+        for axis in [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()] {
+            self.circle(center, axis, radius, color);
+        }
+    }
+
+    /// Three short lines crossing at `point`, `size` long
+    pub fn cross(&mut self, point: Vector3<f32>, size: f32, color: [f32; 3]) {
+        let half = size / 2.0;
+        for axis in [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()] {
+            self.line(point - axis * half, point + axis * half, color);
+        }
+    }
+
+    /// The X, Y, and Z axes of `transform` in red, green, and blue
+    pub fn axes(&mut self, transform: &Matrix4<f32>, length: f32) {
+        let origin = transform.w.truncate();
+        self.line(
+            origin,
+            origin + transform.x.truncate() * length,
+            [1.0, 0.0, 0.0],
+        );
+        self.line(
+            origin,
+            origin + transform.y.truncate() * length,
+            [0.0, 1.0, 0.0],
+        );
+        self.line(
+            origin,
+            origin + transform.z.truncate() * length,
+            [0.0, 0.0, 1.0],
+        );
+    }
+
+    /// Number of lines added this frame
+    pub fn line_count(&self) -> usize {
+        self.vertices.len() / 2
+    }
+
+    /// Whether nothing has been added this frame
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    /// Remove every shape; the app does this after each frame
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+}
+
+/// Two unit vectors perpendicular to `direction` and each other
+fn perpendiculars(direction: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    let helper = if direction.y.abs() < 0.9 {
+        Vector3::unit_y()
+    } else {
+        Vector3::unit_x()
+    };
+    let side = direction.cross(helper).normalize();
+    (side, side.cross(direction))
+}
+
+/// Line endpoint
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct DebugVertex {
+    position: [f32; 3],
+    color: [f32; 3],
+}
+
+impl DebugVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+}
+
+/// Pipelines and the vertex buffer for `DebugDraw` lines, created once lines
+/// appear
+pub(crate) struct DebugRenderer {
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipelines: HashMap<(PipelineKey, bool), wgpu::RenderPipeline>,
+    vertices: wgpu::Buffer,
+    capacity: usize,
+    vertex_count: u32,
+    depth_test: bool,
+}
+
+impl DebugRenderer {
+    fn new(device: &wgpu::Device, uniform_layout: &wgpu::BindGroupLayout) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Debug Line Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/debug_draw.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Debug Line Pipeline Layout"),
+            bind_group_layouts: &[uniform_layout],
+            push_constant_ranges: &[],
+        });
+        let capacity = 1024;
+        Self {
+            shader,
+            pipeline_layout,
+            pipelines: HashMap::new(),
+            vertices: create_vertex_buffer(device, capacity),
+            capacity,
+            vertex_count: 0,
+            depth_test: true,
+        }
+    }
+
+    /// Pipeline for the given target and the current depth test setting,
+    /// created on first use
+    pub(crate) fn pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        key: PipelineKey,
+        depth_format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        let (format, samples, write_mask) = key;
+        let depth_test = self.depth_test;
+        self.pipelines
+            .entry((key, depth_test))
+            .or_insert_with(|| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("Debug Line Pipeline"),
+                    layout: Some(&self.pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &self.shader,
+                        entry_point: Some("vs_main"),
+                        buffers: &[wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<DebugVertex>() as wgpu::BufferAddress,
+                            step_mode: wgpu::VertexStepMode::Vertex,
+                            attributes: &DebugVertex::ATTRIBUTES,
+                        }],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &self.shader,
+                        entry_point: Some("fs_main"),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: Some(wgpu::BlendState::REPLACE),
+                            write_mask,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::LineList,
+                        ..Default::default()
+                    },
+                    // Lines never hide anything, including each other
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: depth_format,
+                        depth_write_enabled: false,
+                        depth_compare: if depth_test {
+                            wgpu::CompareFunction::LessEqual
+                        } else {
+                            wgpu::CompareFunction::Always
+                        },
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        count: samples,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    multiview: None,
+                    cache: None,
+                })
+            })
+            .clone()
+    }
+
+    /// Draw the lines uploaded by `Renderer::prepare_debug_lines`, with the
+    /// scene bind group already set
+    pub(crate) fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        pipeline: &wgpu::RenderPipeline,
+        stats: &mut RenderStats,
+    ) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_vertex_buffer(0, self.vertices.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+        stats.record_draw(wgpu::PrimitiveTopology::LineList, self.vertex_count, 1);
+    }
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Debug Line Vertices"),
+        size: (capacity * std::mem::size_of::<DebugVertex>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+impl Renderer {
+    /// Upload this frame's `DebugDraw` lines
+    pub(crate) fn prepare_debug_lines(&mut self, world: &World) {
+        let debug = world
+            .resource::<DebugDraw>()
+            .filter(|debug| !debug.is_empty());
+        if debug.is_none() && self.debug_lines.is_none() {
+            return;
+        }
+        let renderer = self.debug_lines.get_or_insert_with(|| {
+            DebugRenderer::new(&self.device, &self.uniform_bind_group_layout)
+        });
+        let Some(debug) = debug else {
+            renderer.vertex_count = 0;
+            return;
+        };
+
+        if debug.vertices.len() > renderer.capacity {
+            renderer.capacity = debug.vertices.len().next_power_of_two();
+            renderer.vertices = create_vertex_buffer(&self.device, renderer.capacity);
+        }
+        self.frame_stats.write_buffer(
+            &self.queue,
+            &renderer.vertices,
+            0,
+            bytemuck::cast_slice(&debug.vertices),
+        );
+        renderer.vertex_count = debug.vertices.len() as u32;
+        renderer.depth_test = debug.depth_test;
+    }
+}
@@ -0,0 +1,376 @@
+//! Meshes swept from 2D profiles: extrusions along a path and lathe surfaces
+
+use super::{Vertex, mesh_utils};
+use crate::math::Vector3;
+use anyhow::{Result, bail};
+use cgmath::InnerSpace;
+use std::f32::consts::TAU;
+
+/// Mesh made by sweeping a 2D profile along a 3D path, like a pipe or a rail
+///
+/// Profile points are in the plane across the path, with x to the right and
+/// y up; counter-clockwise profiles and clockwise ones both face outwards.
+///
+/// ```rust,ignore
+/// let square = [[-0.5, -0.5], [0.5, -0.5], [0.5, 0.5], [-0.5, 0.5]];
+/// let path = [[0.0, 0.0, 0.0], [0.0, 1.0, -2.0], [2.0, 1.0, -4.0]];
+/// let (vertices, indices) = Extrusion::new(&square, &path).with_subdivisions(8).build()?;
+/// let mesh = renderer.create_mesh(&vertices, &indices);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extrusion {
+    pub profile: Vec<[f32; 2]>,
+    pub path: Vec<[f32; 3]>,
+    /// Join the last profile point back to the first
+    pub closed_profile: bool,
+    /// Close the ends of a closed profile with fans around its centroid
+    /// (suits convex or star-shaped profiles)
+    pub caps: bool,
+    /// Points inserted between path points along a Catmull-Rom spline (0
+    /// keeps straight segments)
+    pub subdivisions: u32,
+    /// Surfaces meeting at less than this angle (radians) are shaded smoothly
+    pub smoothing_angle: f32,
+    pub color: [f32; 3],
+}
+
+impl Extrusion {
+    /// Sweep `profile` along `path` with capped ends
+    pub fn new(profile: &[[f32; 2]], path: &[[f32; 3]]) -> Self {
+        Self {
+            profile: profile.to_vec(),
+            path: path.to_vec(),
+            closed_profile: true,
+            caps: true,
+            subdivisions: 0,
+            smoothing_angle: 60f32.to_radians(),
+            color: [1.0, 1.0, 1.0],
+        }
+    }
+
+    /// Leave the profile open (a ribbon or half-pipe), which also drops the caps
+    pub fn with_open_profile(mut self) -> Self {
+        self.closed_profile = false;
+        self
+    }
+
+    /// Choose whether to cap the ends
+    pub fn with_caps(mut self, caps: bool) -> Self {
+        self.caps = caps;
+        self
+    }
+
+    /// Smooth the path with a Catmull-Rom spline through its points
+    pub fn with_subdivisions(mut self, subdivisions: u32) -> Self {
+        self.subdivisions = subdivisions;
+        self
+    }
+
+    /// Set the smoothing angle in radians (0 gives flat shading)
+    pub fn with_smoothing_angle(mut self, angle: f32) -> Self {
+        self.smoothing_angle = angle;
+        self
+    }
+
+    /// Set the vertex color
+    pub fn with_color(mut self, color: [f32; 3]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Generate vertices (with normals, UVs, and tangents) and triangle indices
+    pub fn build(&self) -> Result<(Vec<Vertex>, Vec<u16>)> {
+        if self.profile.len() < 2 || self.path.len() < 2 {
+            bail!("An extrusion needs at least 2 profile points and 2 path points");
+        }
+        let path = catmull_rom(&self.path, self.subdivisions);
+        let frames = path_frames(&path);
+        let profile = oriented(&self.profile, self.closed_profile);
+        let rings: Vec<Vec<Vector3<f32>>> = path
+            .iter()
+            .zip(&frames)
+            .map(|(&center, &(right, up))| {
+                profile
+                    .iter()
+                    .map(|&[x, y]| center + right * x + up * y)
+                    .collect()
+            })
+            .collect();
+
+        let mut sheet = Sheet::new(&rings, self.closed_profile, self.color);
+        if self.caps && self.closed_profile {
+            let forward = path[1] - path[0];
+            sheet.cap(&rings[0], -forward);
+            let last = path.len() - 1;
+            sheet.cap(&rings[last], path[last] - path[last - 1]);
+        }
+        sheet.finish(self.smoothing_angle)
+    }
+}
+
+/// Mesh made by revolving a 2D profile around the Y axis, like a vase or a bowl
+///
+/// Profile points are `[radius, height]`; list them from bottom to top so the
+/// surface faces outwards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lathe {
+    pub profile: Vec<[f32; 2]>,
+    /// Steps around the axis
+    pub segments: u32,
+    /// Sweep angle in radians (a full turn by default)
+    pub angle: f32,
+    /// Surfaces meeting at less than this angle (radians) are shaded smoothly
+    pub smoothing_angle: f32,
+    pub color: [f32; 3],
+}
+
+impl Lathe {
+    /// Revolve `profile` a full turn in 32 segments
+    pub fn new(profile: &[[f32; 2]]) -> Self {
+        Self {
+            profile: profile.to_vec(),
+            segments: 32,
+            angle: TAU,
+            smoothing_angle: 60f32.to_radians(),
+            color: [1.0, 1.0, 1.0],
+        }
+    }
+
+    /// Set the number of steps around the axis
+    pub fn with_segments(mut self, segments: u32) -> Self {
+        self.segments = segments.max(3);
+        self
+    }
+
+    /// Revolve only part of a turn (radians)
+    pub fn with_angle(mut self, angle: f32) -> Self {
+        self.angle = angle.clamp(0.0, TAU);
+        self
+    }
+
+    /// Set the smoothing angle in radians (0 gives flat shading)
+    pub fn with_smoothing_angle(mut self, angle: f32) -> Self {
+        self.smoothing_angle = angle;
+        self
+    }
+
+    /// Set the vertex color
+    pub fn with_color(mut self, color: [f32; 3]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Generate vertices (with normals, UVs, and tangents) and triangle indices
+    pub fn build(&self) -> Result<(Vec<Vertex>, Vec<u16>)> {
+        if self.profile.len() < 2 {
+            bail!("A lathe needs at least 2 profile points");
+        }
+        // One ring per step around the axis, each holding the whole profile;
+        // a full turn repeats the first ring so the texture seam has its own UVs
+        let segments = self.segments.max(3);
+        let rings: Vec<Vec<Vector3<f32>>> = (0..=segments)
+            .map(|step| {
+                let angle = self.angle * step as f32 / segments as f32;
+                let (sin, cos) = angle.sin_cos();
+                self.profile
+                    .iter()
+                    .map(|&[radius, height]| Vector3::new(radius * cos, height, -radius * sin))
+                    .collect()
+            })
+            .collect();
+        Sheet::new(&rings, false, self.color).finish(self.smoothing_angle)
+    }
+}
+
+/// Triangles joining consecutive rings of points, plus optional caps
+struct Sheet {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+}
+
+impl Sheet {
+    fn new(rings: &[Vec<Vector3<f32>>], closed: bool, color: [f32; 3]) -> Self {
+        let width = rings[0].len() + closed as usize;
+        let ring_lengths = arc_lengths(rings.iter().map(|ring| centroid(ring)));
+        let across = arc_lengths(
+            rings[0]
+                .iter()
+                .chain(closed.then_some(&rings[0][0]))
+                .copied(),
+        );
+
+        let mut vertices = Vec::with_capacity(rings.len() * width);
+        for (ring, &v) in rings.iter().zip(&ring_lengths) {
+            for (i, &u) in across.iter().enumerate() {
+                let position = ring[i % ring.len()];
+                vertices.push(Vertex::new(position.into(), color).with_uv([u, v]));
+            }
+        }
+        let mut indices = Vec::new();
+        for row in 0..rings.len() as u32 - 1 {
+            for column in 0..width as u32 - 1 {
+                let a = row * width as u32 + column;
+                let b = a + 1;
+                let (c, d) = (b + width as u32, a + width as u32);
+                indices.extend_from_slice(&[a, d, c, a, c, b]);
+            }
+        }
+        Self { vertices, indices }
+    }
+
+    /// Fan of triangles closing `ring`, facing `outward`
+    fn cap(&mut self, ring: &[Vector3<f32>], outward: Vector3<f32>) {
+        let color = self.vertices[0].color;
+        let center = centroid(ring);
+        let base = self.vertices.len() as u32;
+        self.vertices
+            .push(Vertex::new(center.into(), color).with_uv([0.5, 0.5]));
+        let radius = ring
+            .iter()
+            .map(|&p| (p - center).magnitude())
+            .fold(1e-6, f32::max);
+        for &point in ring {
+            let offset = (point - center) / radius;
+            let uv = [0.5 + offset.x * 0.5, 0.5 - offset.y * 0.5];
+            self.vertices
+                .push(Vertex::new(point.into(), color).with_uv(uv));
+        }
+        for i in 0..ring.len() as u32 {
+            let (a, b) = (base + 1 + i, base + 1 + (i + 1) % ring.len() as u32);
+            let normal =
+                (ring[(i as usize + 1) % ring.len()] - center).cross(ring[i as usize] - center);
+            if normal.dot(outward) >= 0.0 {
+                self.indices.extend_from_slice(&[base, b, a]);
+            } else {
+                self.indices.extend_from_slice(&[base, a, b]);
+            }
+        }
+    }
+
+    fn finish(self, smoothing_angle: f32) -> Result<(Vec<Vertex>, Vec<u16>)> {
+        finish_mesh(self.vertices, self.indices, smoothing_angle)
+    }
+}
+
+/// Convert to 16-bit indices and fill in normals (split at edges sharper
+/// than `smoothing_angle`) and tangents
+pub(super) fn finish_mesh(
+    mut vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    smoothing_angle: f32,
+) -> Result<(Vec<Vertex>, Vec<u16>)> {
+    if vertices.len() > u16::MAX as usize + 1 {
+        bail!(
+            "Mesh needs {} vertices, more than 16-bit indices allow",
+            vertices.len()
+        );
+    }
+    let mut indices: Vec<u16> = indices.into_iter().map(|i| i as u16).collect();
+    mesh_utils::recompute_normals(&mut vertices, &mut indices, smoothing_angle);
+    mesh_utils::generate_tangents(&mut vertices, &indices);
+    Ok((vertices, indices))
+}
+
+/// Profile points ordered counter-clockwise so extrusions face outwards
+fn oriented(profile: &[[f32; 2]], closed: bool) -> Vec<[f32; 2]> {
+    let area: f32 = profile
+        .iter()
+        .zip(profile.iter().cycle().skip(1))
+        .map(|(a, b)| a[0] * b[1] - b[0] * a[1])
+        .sum();
+    let mut profile = profile.to_vec();
+    if closed && area < 0.0 {
+        profile.reverse();
+    }
+    profile
+}
+
+/// Points along a Catmull-Rom spline through `points`, with `subdivisions`
+/// extra points per segment
+fn catmull_rom(points: &[[f32; 3]], subdivisions: u32) -> Vec<Vector3<f32>> {
+    let points: Vec<Vector3<f32>> = points.iter().map(|&p| p.into()).collect();
+    if subdivisions == 0 {
+        return points;
+    }
+    let last = points.len() - 1;
+    let mut result = Vec::with_capacity(last * (subdivisions as usize + 1) + 1);
+    for i in 0..last {
+        let p0 = points[i.saturating_sub(1)];
+        let (p1, p2) = (points[i], points[i + 1]);
+        let p3 = points[(i + 2).min(last)];
+        for step in 0..=subdivisions {
+            let t = step as f32 / (subdivisions + 1) as f32;
+            let (t2, t3) = (t * t, t * t * t);
+            result.push(
+                (p1 * 2.0
+                    + (p2 - p0) * t
+                    + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+                    + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+                    * 0.5,
+            );
+        }
+    }
+    result.push(points[last]);
+    result
+}
+
+/// `(right, up)` axes across the path at each point, carried along without
+/// twisting (parallel transport) from an initial up of +Y where possible
+fn path_frames(path: &[Vector3<f32>]) -> Vec<(Vector3<f32>, Vector3<f32>)> {
+    let tangent = |i: usize| {
+        let before = path[i.saturating_sub(1)];
+        let after = path[(i + 1).min(path.len() - 1)];
+        let direction = after - before;
+        if direction.magnitude2() > 1e-12 {
+            direction.normalize()
+        } else {
+            Vector3::unit_z()
+        }
+    };
+
+    let first = tangent(0);
+    let mut up = if first.y.abs() > 0.99 {
+        Vector3::unit_z()
+    } else {
+        Vector3::unit_y()
+    };
+    let mut previous = first;
+    (0..path.len())
+        .map(|i| {
+            let forward = tangent(i);
+            // Rotate `up` by the turn between the previous and current tangent
+            let axis = previous.cross(forward);
+            if axis.magnitude2() > 1e-12 {
+                let angle = previous.angle(forward);
+                up = rotate(up, axis.normalize(), angle.0);
+            }
+            previous = forward;
+            up = (up - forward * forward.dot(up)).normalize();
+            (forward.cross(up), up)
+        })
+        .collect()
+}
+
+/// Rodrigues rotation of `v` around unit `axis`
+fn rotate(v: Vector3<f32>, axis: Vector3<f32>, angle: f32) -> Vector3<f32> {
+    let (sin, cos) = angle.sin_cos();
+    v * cos + axis.cross(v) * sin + axis * axis.dot(v) * (1.0 - cos)
+}
+
+fn centroid(points: &[Vector3<f32>]) -> Vector3<f32> {
+    points
+        .iter()
+        .fold(Vector3::new(0.0, 0.0, 0.0), |sum, &p| sum + p)
+        / points.len() as f32
+}
+
+/// Cumulative distance along `points`, normalized to 0..1
+fn arc_lengths(points: impl Iterator<Item = Vector3<f32>>) -> Vec<f32> {
+    let points: Vec<_> = points.collect();
+    let mut lengths = vec![0.0];
+    for pair in points.windows(2) {
+        lengths.push(lengths.last().unwrap() + (pair[1] - pair[0]).magnitude());
+    }
+    let total = lengths.last().copied().unwrap_or(0.0).max(1e-6);
+    lengths.iter().map(|length| length / total).collect()
+}
@@ -0,0 +1,194 @@
+//! Terrain meshes from grayscale height images
+
+use super::{Vertex, mesh_utils, procedural, texture::read_png_rgba8};
+use crate::math::Vector3;
+use anyhow::{Result, bail};
+use cgmath::InnerSpace;
+use std::path::Path;
+
+/// Terrain grid with one height per pixel, black lowest and white highest
+///
+/// The mesh is centred on the origin with image x along +X and the top row of
+/// the image at -Z, so the image reads like a map seen from above.
+///
+/// ```rust,ignore
+/// let (vertices, indices) = Heightmap::load("assets/valley.png")?
+///     .with_cell_size(0.5)
+///     .with_height_scale(12.0)
+///     .build()?;
+/// let terrain = renderer.create_mesh(&vertices, &indices);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightmap {
+    width: u32,
+    depth: u32,
+    // Row-major, 0..1
+    heights: Vec<f32>,
+    /// Horizontal distance between neighbouring samples
+    pub cell_size: f32,
+    /// Height of a white pixel above a black one
+    pub height_scale: f32,
+    /// Use every `step`th pixel, to fit large images in 16-bit indices (0 acts as 1)
+    pub step: u32,
+    pub color: [f32; 3],
+}
+
+impl Heightmap {
+    /// Heights from a PNG's luminance
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let (width, height, pixels) = read_png_rgba8(path.as_ref())?;
+        Self::from_rgba8(width, height, &pixels)
+    }
+
+    /// Heights from the luminance of tightly packed RGBA8 pixels, top row first
+    pub fn from_rgba8(width: u32, height: u32, pixels: &[u8]) -> Result<Self> {
+        let heights = pixels
+            .chunks_exact(4)
+            .map(|p| (0.2126 * p[0] as f32 + 0.7152 * p[1] as f32 + 0.0722 * p[2] as f32) / 255.0)
+            .collect();
+        Self::from_heights(width, height, heights)
+    }
+
+    /// `size`×`size` fractal noise terrain (see `procedural::noise`)
+    pub fn from_noise(size: u32, period: u32, seed: u32) -> Result<Self> {
+        Self::from_heights(size, size, procedural::noise_heights(size, period, seed))
+    }
+
+    /// Heights in 0..1, `width` per row, `depth` rows
+    pub fn from_heights(width: u32, depth: u32, heights: Vec<f32>) -> Result<Self> {
+        if width < 2 || depth < 2 {
+            bail!("A heightmap needs at least 2×2 samples, got {width}×{depth}");
+        }
+        if heights.len() != (width * depth) as usize {
+            bail!(
+                "Expected {} heights for {width}×{depth}, got {}",
+                width * depth,
+                heights.len()
+            );
+        }
+        Ok(Self {
+            width,
+            depth,
+            heights,
+            cell_size: 1.0,
+            height_scale: 1.0,
+            step: 1,
+            color: [1.0, 1.0, 1.0],
+        })
+    }
+
+    /// Set the horizontal spacing between samples
+    pub fn with_cell_size(mut self, cell_size: f32) -> Self {
+        self.cell_size = cell_size;
+        self
+    }
+
+    /// Set the height of a white pixel
+    pub fn with_height_scale(mut self, height_scale: f32) -> Self {
+        self.height_scale = height_scale;
+        self
+    }
+
+    /// Sample every `step`th pixel (at least 1), keeping the same overall size
+    pub fn with_step(mut self, step: u32) -> Self {
+        self.step = step.max(1);
+        self
+    }
+
+    /// Set the vertex color
+    pub fn with_color(mut self, color: [f32; 3]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Number of samples across and down the image
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.depth)
+    }
+
+    /// Scaled terrain height at local `(x, z)`, interpolated between samples,
+    /// or `None` outside the terrain
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        let column = x / self.cell_size + (self.width - 1) as f32 * 0.5;
+        let row = z / self.cell_size + (self.depth - 1) as f32 * 0.5;
+        if !(0.0..=(self.width - 1) as f32).contains(&column)
+            || !(0.0..=(self.depth - 1) as f32).contains(&row)
+        {
+            return None;
+        }
+        let (x0, z0) = (
+            (column as u32).min(self.width - 2),
+            (row as u32).min(self.depth - 2),
+        );
+        let (fx, fz) = (column - x0 as f32, row - z0 as f32);
+        let sample = |x, z| self.sample(x, z);
+        let near = sample(x0, z0) * (1.0 - fx) + sample(x0 + 1, z0) * fx;
+        let far = sample(x0, z0 + 1) * (1.0 - fx) + sample(x0 + 1, z0 + 1) * fx;
+        Some((near * (1.0 - fz) + far * fz) * self.height_scale)
+    }
+
+    /// Generate vertices (with smooth normals, UVs, and tangents) and
+    /// triangle indices
+    pub fn build(&self) -> Result<(Vec<Vertex>, Vec<u16>)> {
+        let columns = self.sampled(self.width);
+        let rows = self.sampled(self.depth);
+        let count = columns.len() * rows.len();
+        if count > u16::MAX as usize + 1 {
+            bail!(
+                "Heightmap needs {count} vertices, more than 16-bit indices allow; \
+                 raise the step with `with_step`"
+            );
+        }
+
+        let (u_span, v_span) = ((self.width - 1) as f32, (self.depth - 1) as f32);
+        let height = |i: usize, j: usize| self.sample(columns[i], rows[j]) * self.height_scale;
+        let mut vertices = Vec::with_capacity(count);
+        for (j, &z) in rows.iter().enumerate() {
+            for (i, &x) in columns.iter().enumerate() {
+                // Central differences, one-sided at the borders
+                let (left, right) = (i.saturating_sub(1), (i + 1).min(columns.len() - 1));
+                let (back, front) = (j.saturating_sub(1), (j + 1).min(rows.len() - 1));
+                let dx = (height(right, j) - height(left, j))
+                    / ((columns[right] - columns[left]) as f32 * self.cell_size);
+                let dz = (height(i, front) - height(i, back))
+                    / ((rows[front] - rows[back]) as f32 * self.cell_size);
+                let normal = Vector3::new(-dx, 1.0, -dz).normalize();
+
+                let position = Vector3::new(
+                    (x as f32 - u_span * 0.5) * self.cell_size,
+                    height(i, j),
+                    (z as f32 - v_span * 0.5) * self.cell_size,
+                );
+                let mut vertex = Vertex::new(position.into(), self.color);
+                vertex.normal = normal.into();
+                vertex.uv = [x as f32 / u_span, z as f32 / v_span];
+                vertices.push(vertex);
+            }
+        }
+
+        let stride = columns.len() as u16;
+        let mut indices = Vec::with_capacity((columns.len() - 1) * (rows.len() - 1) * 6);
+        for j in 0..rows.len() as u16 - 1 {
+            for i in 0..stride - 1 {
+                let a = j * stride + i;
+                let (b, c) = (a + 1, a + stride);
+                indices.extend_from_slice(&[a, c, b, b, c, c + 1]);
+            }
+        }
+        mesh_utils::generate_tangents(&mut vertices, &indices);
+        Ok((vertices, indices))
+    }
+
+    fn sample(&self, x: u32, z: u32) -> f32 {
+        self.heights[(z * self.width + x) as usize]
+    }
+
+    /// Every `step`th index up to `size`, always ending on the last one
+    fn sampled(&self, size: u32) -> Vec<u32> {
+        let mut indices: Vec<u32> = (0..size).step_by(self.step.max(1) as usize).collect();
+        if indices.last() != Some(&(size - 1)) {
+            indices.push(size - 1);
+        }
+        indices
+    }
+}
@@ -0,0 +1,463 @@
+//! Entity IDs rendered per pixel, for exact GPU picking
+//!
+//! Ray picking tests bounds or triangles, which is awkward for lines and
+//! points and slow in dense scenes. The ID pass instead draws every mesh with
+//! its entity ID as the color into an `R32Uint` target and reads back the
+//! pixel under the cursor a frame or two later, without stalling the GPU.
+//!
+//! ```no_run
+//! use qsi::graphics::{GpuPicking, GpuPickingPlugin};
+//! use qsi::prelude::*;
+//!
+//! App::new()
+//!     .add_plugin(GpuPickingPlugin)
+//!     .add_system(|world: &mut World, _: &InputState, _: &TimeState| {
+//!         if let Some(entity) = world.resource::<GpuPicking>().and_then(GpuPicking::hovered) {
+//!             println!("hovering {entity}");
+//!         }
+//!     })
+//!     .run()
+//!     .unwrap();
+//! ```
+
+use super::{RenderTarget, RenderTargets, Renderer, SceneDraws, Vertex};
+use crate::App;
+use crate::ecs::{EntityId, World};
+use crate::input::InputState;
+use crate::time::TimeState;
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, TryRecvError, channel};
+
+const ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+
+/// Entity the ID pass found under a pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdPick {
+    /// Pixel asked about, in physical pixels from the top left
+    pub position: (u32, u32),
+    /// Mesh entity drawn nearest at that pixel, if any
+    pub entity: Option<EntityId>,
+}
+
+/// Pixel copy waiting for the GPU
+struct Readback {
+    position: (u32, u32),
+    mapped: Receiver<Result<(), wgpu::BufferAsyncError>>,
+}
+
+/// Pipelines, buffers, and readback state of the ID pass
+pub(crate) struct IdBuffer {
+    layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    pipelines: HashMap<wgpu::PrimitiveTopology, wgpu::RenderPipeline>,
+    ids_layout: wgpu::BindGroupLayout,
+    // Entity ID plus one of each object slot (0 for nothing)
+    ids: Vec<u32>,
+    ids_buffer: wgpu::Buffer,
+    ids_bind_group: wgpu::BindGroup,
+    depth: Option<((u32, u32), wgpu::TextureView)>,
+    readback_buffer: wgpu::Buffer,
+    request: Option<(u32, u32)>,
+    // Copy recorded this frame, mapped once the frame is submitted
+    recorded: Option<(u32, u32)>,
+    readback: Option<Readback>,
+    result: Option<IdPick>,
+}
+
+impl IdBuffer {
+    fn new(device: &wgpu::Device, uniform_layout: &wgpu::BindGroupLayout) -> Self {
+        let ids_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("id_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("ID Pipeline Layout"),
+            bind_group_layouts: &[uniform_layout, &ids_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("ID Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/id_buffer.wgsl").into()),
+        });
+        let (ids_buffer, ids_bind_group) = create_ids_buffer(device, &ids_layout, 64);
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ID Readback Buffer"),
+            size: std::mem::size_of::<u32>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Self {
+            layout,
+            shader,
+            pipelines: HashMap::new(),
+            ids_layout,
+            ids: Vec::new(),
+            ids_buffer,
+            ids_bind_group,
+            depth: None,
+            readback_buffer,
+            request: None,
+            recorded: None,
+            readback: None,
+            result: None,
+        }
+    }
+
+    fn pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        topology: wgpu::PrimitiveTopology,
+        depth_format: wgpu::TextureFormat,
+    ) -> &wgpu::RenderPipeline {
+        self.pipelines.entry(topology).or_insert_with(|| {
+            // Same culling and strip restarts as the scene pipelines
+            let cull_mode = match topology {
+                wgpu::PrimitiveTopology::TriangleList | wgpu::PrimitiveTopology::TriangleStrip => {
+                    Some(wgpu::Face::Back)
+                }
+                _ => None,
+            };
+            let strip_index_format = (topology == wgpu::PrimitiveTopology::LineStrip)
+                .then_some(wgpu::IndexFormat::Uint16);
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("ID Pipeline"),
+                layout: Some(&self.layout),
+                vertex: wgpu::VertexState {
+                    module: &self.shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[Vertex::desc()],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &self.shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(ID_FORMAT.into())],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology,
+                    strip_index_format,
+                    cull_mode,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: depth_format,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        })
+    }
+}
+
+/// Storage buffer for `capacity` object IDs and its bind group
+fn create_ids_buffer(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    capacity: usize,
+) -> (wgpu::Buffer, wgpu::BindGroup) {
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("ID Buffer"),
+        size: (capacity * std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("id_bind_group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+    });
+    (buffer, bind_group)
+}
+
+impl Renderer {
+    /// Turn the ID pass on or off; while on, `request_id_pick` reads back
+    /// the entity under a pixel and the IDs are registered as the
+    /// `RenderTargets::PICKING_IDS` target
+    pub fn set_id_picking(&mut self, enabled: bool) {
+        if !enabled {
+            self.id_buffer = None;
+            self.render_targets.remove(RenderTargets::PICKING_IDS);
+        } else if self.id_buffer.is_none() {
+            self.id_buffer = Some(Box::new(IdBuffer::new(
+                &self.device,
+                &self.uniform_bind_group_layout,
+            )));
+        }
+    }
+
+    /// Whether the ID pass is on
+    pub fn id_picking(&self) -> bool {
+        self.id_buffer.is_some()
+    }
+
+    /// Find the entity under a pixel (physical pixels from the top left of
+    /// the window) when the next frame is drawn; collect the answer with
+    /// `take_id_pick` once the GPU has it
+    ///
+    /// Does nothing while the ID pass is off. A newer request replaces one
+    /// not yet drawn.
+    pub fn request_id_pick(&mut self, x: u32, y: u32) {
+        if let Some(ids) = &mut self.id_buffer {
+            ids.request = Some((x, y));
+        }
+    }
+
+    /// The most recent pick the GPU finished since the last call, if any
+    pub fn take_id_pick(&mut self) -> Option<IdPick> {
+        let ids = self.id_buffer.as_mut()?;
+        if let Some(readback) = &ids.readback {
+            let _ = self.device.poll(wgpu::PollType::Poll);
+            match readback.mapped.try_recv() {
+                Err(TryRecvError::Empty) => {}
+                result => {
+                    let position = readback.position;
+                    ids.readback = None;
+                    if let Ok(Ok(())) = result {
+                        let slice = ids.readback_buffer.slice(..);
+                        let id =
+                            bytemuck::pod_read_unaligned::<u32>(&slice.get_mapped_range()[..4]);
+                        ids.readback_buffer.unmap();
+                        ids.result = Some(IdPick {
+                            position,
+                            entity: id.checked_sub(1),
+                        });
+                    }
+                }
+            }
+        }
+        ids.result.take()
+    }
+
+    /// Draw the IDs of this frame's meshes and copy the requested pixel, if
+    /// a pick was requested and the last one has been read
+    pub(super) fn draw_ids(&mut self, encoder: &mut wgpu::CommandEncoder, draws: &SceneDraws) {
+        let size = (self.config.width, self.config.height);
+        let Some(ids) = self.id_buffer.as_deref_mut() else {
+            return;
+        };
+        if ids.readback.is_some() {
+            return;
+        }
+        let Some(position) = ids.request.take() else {
+            return;
+        };
+        if position.0 >= size.0 || position.1 >= size.1 {
+            ids.result = Some(IdPick {
+                position,
+                entity: None,
+            });
+            return;
+        }
+
+        // One ID per object slot, as the scene draws assigned them
+        ids.ids.clear();
+        ids.ids.resize(self.objects.len().max(1), 0);
+        for bucket in draws.in_order() {
+            for draw in &bucket.draws {
+                let id = self.render_list.items[draw.item].entity + 1;
+                ids.ids[draw.index as usize..(draw.index + draw.count) as usize].fill(id);
+            }
+        }
+        if ids.ids.len() * std::mem::size_of::<u32>() > ids.ids_buffer.size() as usize {
+            (ids.ids_buffer, ids.ids_bind_group) = create_ids_buffer(
+                &self.device,
+                &ids.ids_layout,
+                ids.ids.len().next_power_of_two(),
+            );
+        }
+        self.frame_stats.write_buffer(
+            &self.queue,
+            &ids.ids_buffer,
+            0,
+            bytemuck::cast_slice(&ids.ids),
+        );
+
+        let target = match self.render_targets.get(RenderTargets::PICKING_IDS) {
+            Some(target) if target.size() == size && target.format() == ID_FORMAT => target.clone(),
+            _ => {
+                let target = RenderTarget::new(&self.device, "ID Target", size, ID_FORMAT);
+                self.render_targets
+                    .insert(RenderTargets::PICKING_IDS, target.clone());
+                target
+            }
+        };
+        if ids.depth.as_ref().is_none_or(|(cached, _)| *cached != size) {
+            let view = super::create_depth_view(&self.device, size.0, size.1, self.depth_format, 1);
+            ids.depth = Some((size, view));
+        }
+        for bucket in draws.in_order() {
+            let topology = bucket.pipeline.topology;
+            ids.pipeline(&self.device, topology, self.depth_format);
+        }
+
+        // Borrowed again immutably for the pass
+        let Some(ids) = self.id_buffer.as_deref() else {
+            return;
+        };
+        let Some((_, depth)) = &ids.depth else {
+            return;
+        };
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("ID Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            self.apply_scene_region(&mut render_pass, size, 1.0, None);
+            // The first view's globals, as the mono scene pass uses
+            render_pass.set_bind_group(0, &self.uniform_bind_group, &[0]);
+            render_pass.set_bind_group(1, &ids.ids_bind_group, &[]);
+            for bucket in draws.in_order() {
+                let Some(pipeline) = ids.pipelines.get(&bucket.pipeline.topology) else {
+                    continue;
+                };
+                render_pass.set_pipeline(pipeline);
+                for draw in &bucket.draws {
+                    let mesh = &self.render_list.items[draw.item].mesh;
+                    render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                    render_pass
+                        .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                    render_pass.draw_indexed(
+                        0..mesh.num_indices,
+                        0,
+                        draw.index..draw.index + draw.count,
+                    );
+                }
+            }
+        }
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                origin: wgpu::Origin3d {
+                    x: position.0,
+                    y: position.1,
+                    z: 0,
+                },
+                ..target.texture.as_image_copy()
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &ids.readback_buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: None,
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+        if let Some(ids) = self.id_buffer.as_deref_mut() {
+            ids.recorded = Some(position);
+        }
+    }
+
+    /// Start mapping the pixel copied by `draw_ids`, after the frame holding
+    /// it was submitted
+    pub(super) fn map_id_readback(&mut self) {
+        let Some(ids) = self.id_buffer.as_deref_mut() else {
+            return;
+        };
+        let Some(position) = ids.recorded.take() else {
+            return;
+        };
+        let (sender, mapped) = channel();
+        ids.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        ids.readback = Some(Readback { position, mapped });
+    }
+}
+
+/// Resource exposing the entity under the cursor, found by the ID pass
+///
+/// The answer trails the cursor by a frame or two, as it waits for the GPU.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GpuPicking {
+    cursor: Option<(u32, u32)>,
+    last: Option<IdPick>,
+}
+
+impl GpuPicking {
+    /// Entity under the cursor as of the latest finished pick
+    pub fn hovered(&self) -> Option<EntityId> {
+        self.last.and_then(|pick| pick.entity)
+    }
+
+    /// Latest finished pick, with the pixel it was taken at
+    pub fn last_pick(&self) -> Option<IdPick> {
+        self.last
+    }
+}
+
+/// Follow the cursor with ID picks
+pub fn gpu_picking_cursor_system(world: &mut World, input: &InputState, _time: &TimeState) {
+    if let Some(picking) = world.resource_mut::<GpuPicking>() {
+        let (x, y) = input.cursor_position_physical();
+        picking.cursor = (x >= 0.0 && y >= 0.0).then_some((x as u32, y as u32));
+    }
+}
+
+/// Collect finished picks and request one at the cursor
+pub fn gpu_picking_system(world: &mut World, renderer: &mut Renderer, _time: &TimeState) {
+    let Some(picking) = world.resource_mut::<GpuPicking>() else {
+        return;
+    };
+    renderer.set_id_picking(true);
+    if let Some(pick) = renderer.take_id_pick() {
+        picking.last = Some(pick);
+    }
+    if let Some((x, y)) = picking.cursor {
+        renderer.request_id_pick(x, y);
+    }
+}
+
+/// Plugin keeping `GpuPicking` up to date with the mesh entity under the
+/// cursor, found per pixel on the GPU
+pub struct GpuPickingPlugin;
+
+impl crate::plugin::Plugin for GpuPickingPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_resource(GpuPicking::default());
+        app.register_system(gpu_picking_cursor_system);
+        app.register_render_system(gpu_picking_system);
+    }
+}
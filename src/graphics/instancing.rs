@@ -0,0 +1,95 @@
+//! Many copies of one mesh drawn with a single draw call
+
+use crate::ecs::Component;
+use crate::math::Transform;
+
+/// One copy of an entity's mesh in its `Instances`
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Instance {
+    /// Placement relative to the entity's own `Transform`
+    pub transform: Transform,
+    /// Multiplies the material's base color (RGBA)
+    pub color: [f32; 4],
+    /// Free scalar for custom shaders, read as `objects[instance].params.y`
+    pub data: f32,
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Self::new(Transform::default())
+    }
+}
+
+impl Instance {
+    /// White instance with the given placement
+    pub fn new(transform: Transform) -> Self {
+        Self {
+            transform,
+            color: [1.0; 4],
+            data: 0.0,
+        }
+    }
+
+    /// Set the color tint (RGB, opaque)
+    pub fn with_color(mut self, color: [f32; 3]) -> Self {
+        let [r, g, b] = color;
+        self.color = [r, g, b, 1.0];
+        self
+    }
+
+    /// Set the custom scalar
+    pub fn with_data(mut self, data: f32) -> Self {
+        self.data = data;
+        self
+    }
+}
+
+/// Draws the entity's `Mesh` once per instance in a single draw call
+///
+/// Each instance gets its own transform, color tint, and scalar, so large
+/// datasets (agents, molecules) can be colored by state with one material.
+/// An entity with an empty list draws nothing.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Instances {
+    pub instances: Vec<Instance>,
+}
+
+impl Component for Instances {}
+
+impl Instances {
+    /// No instances
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an instance
+    pub fn with(mut self, instance: Instance) -> Self {
+        self.instances.push(instance);
+        self
+    }
+
+    /// Add an instance
+    pub fn push(&mut self, instance: Instance) {
+        self.instances.push(instance);
+    }
+
+    /// Number of instances
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// True if there are no instances
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+}
+
+impl FromIterator<Instance> for Instances {
+    fn from_iter<I: IntoIterator<Item = Instance>>(iter: I) -> Self {
+        Self {
+            instances: iter.into_iter().collect(),
+        }
+    }
+}
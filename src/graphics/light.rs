@@ -0,0 +1,349 @@
+//! Scene lighting
+
+use crate::ecs::{Component, World};
+use crate::math::{Transform, Vector3};
+use cgmath::{Deg, InnerSpace, Rad, Zero};
+
+/// Directional light such as the sun; the renderer uses the first one it finds
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DirectionalLight {
+    /// Direction the light travels in (towards the lit surfaces)
+    pub direction: Vector3<f32>,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Fraction of the base color visible on unlit faces
+    pub ambient: f32,
+}
+
+impl Component for DirectionalLight {}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            direction: Vector3::new(-1.0, -1.0, -1.0),
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+            ambient: 0.2,
+        }
+    }
+}
+
+impl DirectionalLight {
+    /// White light travelling in `direction`
+    pub fn new(direction: Vector3<f32>) -> Self {
+        Self {
+            direction,
+            ..Default::default()
+        }
+    }
+
+    /// Set the color
+    pub fn with_color(mut self, color: [f32; 3]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Set the intensity
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Set the ambient fraction
+    pub fn with_ambient(mut self, ambient: f32) -> Self {
+        self.ambient = ambient;
+        self
+    }
+}
+
+/// Maximum number of point and spot lights sent to the shader (`MAX_LIGHTS` in
+/// `default.wgsl`); beyond this the lights nearest the camera are used
+pub const MAX_LIGHTS: usize = 16;
+
+/// Distance falloff `1 / (constant + linear·d + quadratic·d²)`, faded to zero at the range
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Self {
+            constant: 1.0,
+            linear: 0.09,
+            quadratic: 0.032,
+        }
+    }
+}
+
+impl Attenuation {
+    /// Physically based inverse-square falloff
+    pub fn inverse_square() -> Self {
+        Self {
+            constant: 0.0,
+            linear: 0.0,
+            quadratic: 1.0,
+        }
+    }
+
+    /// No falloff until the range cutoff
+    pub fn none() -> Self {
+        Self {
+            constant: 1.0,
+            linear: 0.0,
+            quadratic: 0.0,
+        }
+    }
+}
+
+/// Light radiating in all directions from the entity's `Transform` position
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PointLight {
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Distance at which the light has faded out completely
+    pub range: f32,
+    pub attenuation: Attenuation,
+}
+
+impl Component for PointLight {}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        Self {
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+            range: 10.0,
+            attenuation: Attenuation::default(),
+        }
+    }
+}
+
+impl PointLight {
+    /// Point light with the given color and range
+    pub fn new(color: [f32; 3], range: f32) -> Self {
+        Self {
+            color,
+            range,
+            ..Default::default()
+        }
+    }
+
+    /// Set the intensity
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Set the attenuation
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> Self {
+        self.attenuation = attenuation;
+        self
+    }
+}
+
+/// Cone of light from the entity's `Transform` position
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpotLight {
+    /// World-space direction the cone points in
+    pub direction: Vector3<f32>,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub range: f32,
+    pub attenuation: Attenuation,
+    /// Half-angle of the fully lit inner cone
+    pub inner_angle: Rad<f32>,
+    /// Half-angle where the light reaches zero
+    pub outer_angle: Rad<f32>,
+}
+
+impl Component for SpotLight {}
+
+impl Default for SpotLight {
+    fn default() -> Self {
+        Self {
+            direction: Vector3::new(0.0, -1.0, 0.0),
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+            range: 10.0,
+            attenuation: Attenuation::default(),
+            inner_angle: Deg(20.0).into(),
+            outer_angle: Deg(30.0).into(),
+        }
+    }
+}
+
+impl SpotLight {
+    /// White spot light pointing in `direction`
+    pub fn new(direction: Vector3<f32>) -> Self {
+        Self {
+            direction,
+            ..Default::default()
+        }
+    }
+
+    /// Set the color
+    pub fn with_color(mut self, color: [f32; 3]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Set the intensity
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Set the range
+    pub fn with_range(mut self, range: f32) -> Self {
+        self.range = range;
+        self
+    }
+
+    /// Set the inner and outer cone half-angles
+    pub fn with_cone(mut self, inner: impl Into<Rad<f32>>, outer: impl Into<Rad<f32>>) -> Self {
+        self.inner_angle = inner.into();
+        self.outer_angle = outer.into();
+        self
+    }
+
+    /// Set the attenuation
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> Self {
+        self.attenuation = attenuation;
+        self
+    }
+}
+
+/// One point or spot light as laid out in the shader
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuLight {
+    /// Position in xyz, range in w
+    position: [f32; 4],
+    /// Color times intensity in rgb, cosine of the inner cone angle in w
+    color: [f32; 4],
+    /// Spot direction in xyz, cosine of the outer cone angle in w
+    direction: [f32; 4],
+    /// Constant, linear, and quadratic attenuation
+    attenuation: [f32; 4],
+}
+
+impl GpuLight {
+    fn new(
+        position: Vector3<f32>,
+        color: [f32; 3],
+        intensity: f32,
+        range: f32,
+        attenuation: Attenuation,
+    ) -> Self {
+        let [r, g, b] = color.map(|c| c * intensity);
+        Self {
+            position: [position.x, position.y, position.z, range.max(1e-3)],
+            // Point lights: a cone covering every direction
+            color: [r, g, b, -1.0],
+            direction: [0.0, 0.0, 0.0, -2.0],
+            attenuation: [
+                attenuation.constant,
+                attenuation.linear,
+                attenuation.quadratic,
+                0.0,
+            ],
+        }
+    }
+}
+
+/// Light data uploaded to the shader
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct LightUniform {
+    /// Unit vector pointing towards the light
+    to_light: [f32; 4],
+    /// Color times intensity in rgb, ambient in w
+    color: [f32; 4],
+    /// Number of used entries in `lights` (x)
+    count: [u32; 4],
+    lights: [GpuLight; MAX_LIGHTS],
+}
+
+impl LightUniform {
+    /// Uniform for the first `DirectionalLight` in the world (or the default
+    /// light) plus the point and spot lights nearest to `eye`
+    pub(crate) fn from_world(world: &World, eye: Vector3<f32>) -> Self {
+        let light = world
+            .query::<DirectionalLight>()
+            .next()
+            .map(|(_, light)| *light)
+            .unwrap_or_default();
+        let mut uniform = Self::from_light(&light);
+
+        let position = |entity| {
+            world
+                .get_component::<Transform>(entity)
+                .map_or(Vector3::zero(), |transform| transform.position)
+        };
+        let mut lights: Vec<GpuLight> = world
+            .query::<PointLight>()
+            .map(|(entity, light)| {
+                GpuLight::new(
+                    position(entity),
+                    light.color,
+                    light.intensity,
+                    light.range,
+                    light.attenuation,
+                )
+            })
+            .collect();
+        lights.extend(world.query::<SpotLight>().map(|(entity, light)| {
+            let mut gpu = GpuLight::new(
+                position(entity),
+                light.color,
+                light.intensity,
+                light.range,
+                light.attenuation,
+            );
+            let direction = if light.direction.magnitude2() > 0.0 {
+                light.direction.normalize()
+            } else {
+                -Vector3::unit_y()
+            };
+            let outer = light.outer_angle.0.max(1e-3);
+            let inner = light.inner_angle.0.clamp(0.0, outer - 1e-4);
+            gpu.color[3] = inner.cos();
+            gpu.direction = [direction.x, direction.y, direction.z, outer.cos()];
+            gpu
+        }));
+
+        if lights.len() > MAX_LIGHTS {
+            let distance = |light: &GpuLight| {
+                let [x, y, z, _] = light.position;
+                (Vector3::new(x, y, z) - eye).magnitude2()
+            };
+            lights.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+            lights.truncate(MAX_LIGHTS);
+        }
+        uniform.count[0] = lights.len() as u32;
+        uniform.lights[..lights.len()].copy_from_slice(&lights);
+        uniform
+    }
+
+    pub(crate) fn from_light(light: &DirectionalLight) -> Self {
+        let to_light = if light.direction.magnitude2() > 0.0 {
+            -light.direction.normalize()
+        } else {
+            Vector3::unit_y()
+        };
+        let [r, g, b] = light.color.map(|c| c * light.intensity);
+        Self {
+            to_light: [to_light.x, to_light.y, to_light.z, 0.0],
+            color: [r, g, b, light.ambient],
+            count: [0; 4],
+            lights: [GpuLight::default(); MAX_LIGHTS],
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Per-entity surface appearance
+
+use super::Texture;
+use crate::ecs::Component;
+
+/// How a material responds to lighting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShadingMode {
+    /// Color is output as-is, ignoring lights
+    Unlit,
+    /// Diffuse lighting from the face normal (the default look)
+    #[default]
+    Flat,
+    /// Diffuse plus a specular highlight towards the camera
+    Shaded,
+}
+
+impl ShadingMode {
+    fn index(self) -> f32 {
+        match self {
+            ShadingMode::Unlit => 0.0,
+            ShadingMode::Flat => 1.0,
+            ShadingMode::Shaded => 2.0,
+        }
+    }
+}
+
+/// Material component tinting a mesh without touching its vertex data
+///
+/// The base color multiplies the vertex colors, so shared meshes with white
+/// vertices can be colored per entity. Entities without a material render with
+/// `Material::default()`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Material {
+    /// Linear RGBA; convert picked sRGB values with `Color`
+    pub base_color: [f32; 4],
+    /// Linear RGB
+    pub emissive: [f32; 3],
+    pub shading: ShadingMode,
+    /// Tangent-space normal map perturbing the lit normal; needs mesh UVs and
+    /// tangents. GPU textures are not saved in scenes.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub normal_map: Option<Texture>,
+    /// Scale of the normal map's bumps (0 flattens, 1 as authored)
+    #[cfg_attr(feature = "serde", serde(default = "default_normal_strength"))]
+    pub normal_strength: f32,
+    /// Blend by `base_color`'s alpha in a pass after opaque meshes, sorted
+    /// back to front and without writing depth
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub transparent: bool,
+}
+
+#[cfg(feature = "serde")]
+fn default_normal_strength() -> f32 {
+    1.0
+}
+
+impl Component for Material {}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            base_color: [1.0, 1.0, 1.0, 1.0],
+            emissive: [0.0, 0.0, 0.0],
+            shading: ShadingMode::Flat,
+            normal_map: None,
+            normal_strength: 1.0,
+            transparent: false,
+        }
+    }
+}
+
+impl Material {
+    /// Flat-shaded material with the given base color
+    pub fn new(color: [f32; 3]) -> Self {
+        Self {
+            base_color: [color[0], color[1], color[2], 1.0],
+            ..Default::default()
+        }
+    }
+
+    /// Material that ignores lighting
+    pub fn unlit(color: [f32; 3]) -> Self {
+        Self::new(color).with_shading(ShadingMode::Unlit)
+    }
+
+    /// Set the shading mode
+    pub fn with_shading(mut self, shading: ShadingMode) -> Self {
+        self.shading = shading;
+        self
+    }
+
+    /// Set the emissive color, added after lighting; values above 1.0 stay
+    /// bright through tonemapping and feed `Bloom`
+    pub fn with_emissive(mut self, emissive: [f32; 3]) -> Self {
+        self.emissive = emissive;
+        self
+    }
+
+    /// Render see-through with the given opacity (see `transparent`)
+    pub fn with_alpha(mut self, alpha: f32) -> Self {
+        self.base_color[3] = alpha;
+        self.with_transparent(true)
+    }
+
+    /// Choose whether to render in the transparent pass
+    pub fn with_transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    /// Set the normal map and its strength
+    pub fn with_normal_map(mut self, normal_map: Texture, strength: f32) -> Self {
+        self.normal_map = Some(normal_map);
+        self.normal_strength = strength;
+        self
+    }
+
+    /// Pack into the `(base_color, emissive + shading mode, normal strength)`
+    /// layout used by the shader
+    pub(crate) fn gpu_data(&self) -> ([f32; 4], [f32; 4], [f32; 4]) {
+        let [r, g, b] = self.emissive;
+        let strength = if self.normal_map.is_some() {
+            self.normal_strength
+        } else {
+            0.0
+        };
+        (
+            self.base_color,
+            [r, g, b, self.shading.index()],
+            [strength, 0.0, 0.0, 0.0],
+        )
+    }
+}
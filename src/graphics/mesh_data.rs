@@ -0,0 +1,207 @@
+//! CPU-side geometry that can be edited before it becomes a GPU `Mesh`
+
+use super::{Mesh, Vertex, mesh_utils};
+use crate::ecs::Component;
+use crate::math::{Aabb, Matrix4, Transform, Vector3};
+use anyhow::{Result, bail};
+use cgmath::{InnerSpace, Matrix, Matrix3, SquareMatrix};
+
+/// Vertices and indices kept in memory, to build, transform, merge, and
+/// inspect geometry before uploading it with `Renderer::upload`
+///
+/// Loaders and generators return `(vertices, indices)` pairs, which convert
+/// with `MeshData::from`.
+///
+/// ```rust
+/// use qsi::graphics::{MeshData, MeshSource};
+/// use qsi::math::{Transform, Vector3};
+///
+/// let cube = MeshSource::Cube { size: 1.0, color: [1.0; 3] }.mesh_data();
+/// let mut pair = cube.clone();
+/// pair.merge(&cube.with_transform(&Transform::at_position(Vector3::new(2.0, 0.0, 0.0))))
+///     .unwrap();
+/// assert_eq!(pair.triangle_count(), 24);
+/// assert_eq!(pair.bounds().unwrap().max.x, 2.5);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u16>,
+    pub topology: wgpu::PrimitiveTopology,
+}
+
+// Kept on an entity next to its `Mesh`, lets `World::pick` test the
+// triangles rather than the bounds
+impl Component for MeshData {}
+
+impl Default for MeshData {
+    fn default() -> Self {
+        Self::new(Vec::new(), Vec::new())
+    }
+}
+
+impl From<(Vec<Vertex>, Vec<u16>)> for MeshData {
+    fn from((vertices, indices): (Vec<Vertex>, Vec<u16>)) -> Self {
+        Self::new(vertices, indices)
+    }
+}
+
+impl MeshData {
+    /// Triangle list from vertices and indices
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u16>) -> Self {
+        Self {
+            vertices,
+            indices,
+            topology: wgpu::PrimitiveTopology::TriangleList,
+        }
+    }
+
+    /// Set how indices form primitives
+    pub fn with_topology(mut self, topology: wgpu::PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    /// Number of triangles, or 0 for point and line topologies
+    pub fn triangle_count(&self) -> usize {
+        match self.topology {
+            wgpu::PrimitiveTopology::TriangleList => self.indices.len() / 3,
+            wgpu::PrimitiveTopology::TriangleStrip => self.indices.len().saturating_sub(2),
+            _ => 0,
+        }
+    }
+
+    /// Whether there is nothing to draw
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Bounds of the vertex positions, or `None` without vertices
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices.iter().map(|vertex| vertex.position))
+    }
+
+    /// Corner positions of each triangle of a triangle list, skipping
+    /// triangles with out-of-range indices
+    pub fn triangles(&self) -> impl Iterator<Item = [[f32; 3]; 3]> + '_ {
+        let list = self.topology == wgpu::PrimitiveTopology::TriangleList;
+        self.indices
+            .chunks_exact(3)
+            .take_while(move |_| list)
+            .filter_map(|triangle| {
+                let corner = |i: usize| self.vertices.get(triangle[i] as usize);
+                Some([
+                    corner(0)?.position,
+                    corner(1)?.position,
+                    corner(2)?.position,
+                ])
+            })
+    }
+
+    /// Transform positions by `matrix`, and normals and tangents to match
+    ///
+    /// A mirroring matrix also reverses triangle winding so faces keep
+    /// pointing outwards.
+    pub fn transform(&mut self, matrix: &Matrix4<f32>) {
+        let linear = Matrix3::from_cols(
+            matrix.x.truncate(),
+            matrix.y.truncate(),
+            matrix.z.truncate(),
+        );
+        // Normals follow the inverse transpose so non-uniform scale keeps
+        // them perpendicular to the surface
+        let normal_matrix = linear
+            .invert()
+            .map_or(linear, |inverse| inverse.transpose());
+        let direction = |matrix: &Matrix3<f32>, v: [f32; 3]| {
+            let v = Vector3::from(v);
+            if v.magnitude2() == 0.0 {
+                return v;
+            }
+            let v = matrix * v;
+            if v.magnitude2() > 0.0 {
+                v.normalize()
+            } else {
+                v
+            }
+        };
+        for vertex in &mut self.vertices {
+            vertex.position = (matrix * Vector3::from(vertex.position).extend(1.0))
+                .truncate()
+                .into();
+            vertex.normal = direction(&normal_matrix, vertex.normal).into();
+            let [x, y, z, w] = vertex.tangent;
+            let tangent = direction(&linear, [x, y, z]);
+            vertex.tangent = [tangent.x, tangent.y, tangent.z, w];
+        }
+        if linear.determinant() < 0.0 && self.topology == wgpu::PrimitiveTopology::TriangleList {
+            for triangle in self.indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+    }
+
+    /// Builder form of `transform` taking a `Transform`
+    pub fn with_transform(mut self, transform: &Transform) -> Self {
+        self.transform(&transform.matrix());
+        self
+    }
+
+    /// Set every vertex color
+    pub fn set_color(&mut self, color: [f32; 3]) {
+        for vertex in &mut self.vertices {
+            vertex.color = color;
+        }
+    }
+
+    /// Append `other`'s geometry, offsetting its indices
+    ///
+    /// Both must be the same list topology, and the result must stay within
+    /// 16-bit indices.
+    pub fn merge(&mut self, other: &MeshData) -> Result<()> {
+        if other.topology != self.topology {
+            bail!(
+                "Cannot merge {:?} geometry into {:?}",
+                other.topology,
+                self.topology
+            );
+        }
+        if matches!(
+            self.topology,
+            wgpu::PrimitiveTopology::LineStrip | wgpu::PrimitiveTopology::TriangleStrip
+        ) {
+            bail!("Cannot merge strips; use a list topology");
+        }
+        let offset = self.vertices.len();
+        if offset + other.vertices.len() > u16::MAX as usize + 1 {
+            bail!(
+                "Merged mesh would have {} vertices, more than 16-bit indices can address",
+                offset + other.vertices.len()
+            );
+        }
+        self.vertices.extend_from_slice(&other.vertices);
+        self.indices
+            .extend(other.indices.iter().map(|&index| index + offset as u16));
+        Ok(())
+    }
+
+    /// Recompute normals, smoothed across shared positions or one per face
+    /// (see `Mesh::compute_normals`)
+    pub fn compute_normals(&mut self, smooth: bool) {
+        Mesh::compute_normals(&mut self.vertices, &mut self.indices, smooth);
+    }
+
+    /// Fill in tangents from normals and UVs, for normal maps
+    pub fn generate_tangents(&mut self) {
+        mesh_utils::generate_tangents(&mut self.vertices, &self.indices);
+    }
+
+    /// Create the GPU mesh
+    pub fn to_mesh(&self, device: &wgpu::Device) -> Mesh {
+        Mesh::new_with_topology(device, &self.vertices, &self.indices, self.topology)
+    }
+}
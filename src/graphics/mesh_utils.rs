@@ -0,0 +1,174 @@
+//! CPU-side helpers for building vertex data
+
+use super::Vertex;
+use crate::math::Vector3;
+use cgmath::InnerSpace;
+use std::collections::HashMap;
+
+/// Fill in `tangent` for every vertex of an indexed triangle list from its
+/// normals and UVs, for use with normal maps
+///
+/// Tangents follow the direction of increasing U; `w` holds the handedness of
+/// the bitangent (towards decreasing V, i.e. up the image). Vertices without a
+/// normal, or whose triangles all have degenerate UVs, get a zero tangent,
+/// which the default shader treats as "no normal mapping".
+pub fn generate_tangents(vertices: &mut [Vertex], indices: &[u16]) {
+    let zero = Vector3::new(0.0, 0.0, 0.0);
+    let mut tangents = vec![zero; vertices.len()];
+    let mut bitangents = vec![zero; vertices.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+        if a.max(b).max(c) >= vertices.len() {
+            continue;
+        }
+        let position = |i: usize| Vector3::from(vertices[i].position);
+        let edge1 = position(b) - position(a);
+        let edge2 = position(c) - position(a);
+        let [u0, v0] = vertices[a].uv;
+        let [u1, v1] = vertices[b].uv;
+        let [u2, v2] = vertices[c].uv;
+        let (du1, dv1, du2, dv2) = (u1 - u0, v1 - v0, u2 - u0, v2 - v0);
+
+        let det = du1 * dv2 - du2 * dv1;
+        if det.abs() < 1e-12 {
+            continue;
+        }
+        let r = 1.0 / det;
+        // Weighting by the unnormalized vectors favours larger triangles
+        let tangent = (edge1 * dv2 - edge2 * dv1) * r;
+        let bitangent = -(edge2 * du1 - edge1 * du2) * r;
+        for i in [a, b, c] {
+            tangents[i] += tangent;
+            bitangents[i] += bitangent;
+        }
+    }
+
+    for (vertex, (tangent, bitangent)) in vertices
+        .iter_mut()
+        .zip(tangents.into_iter().zip(bitangents))
+    {
+        let normal = Vector3::from(vertex.normal);
+        // Gram-Schmidt: make the tangent perpendicular to the normal
+        let tangent = tangent - normal * normal.dot(tangent);
+        if normal.magnitude2() < 1e-12 || tangent.magnitude2() < 1e-12 {
+            vertex.tangent = [0.0; 4];
+            continue;
+        }
+        let tangent = tangent.normalize();
+        let handedness = if normal.cross(tangent).dot(bitangent) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+        vertex.tangent = [tangent.x, tangent.y, tangent.z, handedness];
+    }
+}
+
+/// Recompute vertex normals of an indexed triangle list from its faces
+///
+/// Corners at the same position are smoothed together when their faces meet
+/// at less than `angle_threshold` radians, so 0 gives flat shading and π
+/// smooths everything. Vertices shared across a sharper edge are split, which
+/// appends vertices and rewrites `indices`; corners that would need an index
+/// past `u16::MAX` keep the first normal computed for their vertex.
+///
+/// Run `generate_tangents` afterwards if the mesh uses a normal map.
+pub fn recompute_normals(vertices: &mut Vec<Vertex>, indices: &mut [u16], angle_threshold: f32) {
+    let zero = Vector3::new(0.0, 0.0, 0.0);
+    let count = vertices.len();
+    let triangles: Vec<[usize; 3]> = indices
+        .chunks_exact(3)
+        .map(|t| [0, 1, 2].map(|i| t[i] as usize))
+        .collect();
+    let valid = |triangle: &[usize; 3]| triangle.iter().all(|&i| i < count);
+    let position = |i: usize| Vector3::from(vertices[i].position);
+
+    // Unit face normals (zero for degenerate triangles) and the interior angle
+    // at each corner, used as weights so thin slivers don't dominate
+    let mut face_normals = vec![zero; triangles.len()];
+    let mut corner_angles = vec![[0.0f32; 3]; triangles.len()];
+    for (face, triangle) in triangles.iter().enumerate().filter(|(_, t)| valid(t)) {
+        let [a, b, c] = triangle.map(position);
+        let normal = (b - a).cross(c - a);
+        if normal.magnitude2() > 1e-20 {
+            face_normals[face] = normal.normalize();
+        }
+        for corner in 0..3 {
+            let origin = position(triangle[corner]);
+            let to_next = position(triangle[(corner + 1) % 3]) - origin;
+            let to_prev = position(triangle[(corner + 2) % 3]) - origin;
+            if to_next.magnitude2() > 1e-20 && to_prev.magnitude2() > 1e-20 {
+                corner_angles[face][corner] = to_next.angle(to_prev).0;
+            }
+        }
+    }
+
+    // Corners grouped by exact position, so unwelded meshes smooth too
+    let key = |i: usize| vertices[i].position.map(|c| (c + 0.0).to_bits());
+    let mut corners_at: HashMap<[u32; 3], Vec<(usize, usize)>> = HashMap::new();
+    for (face, triangle) in triangles.iter().enumerate().filter(|(_, t)| valid(t)) {
+        for (corner, &index) in triangle.iter().enumerate() {
+            corners_at
+                .entry(key(index))
+                .or_default()
+                .push((face, corner));
+        }
+    }
+    let corner_normal = |face: usize, index: usize| {
+        let own = face_normals[face];
+        let min_cos = angle_threshold.clamp(0.0, std::f32::consts::PI).cos() - 1e-6;
+        let mut normal = zero;
+        for &(other, other_corner) in &corners_at[&key(index)] {
+            // Degenerate faces have no direction of their own and join any group
+            if own == zero || own.dot(face_normals[other]) >= min_cos {
+                normal += face_normals[other] * corner_angles[other][other_corner];
+            }
+        }
+        if normal.magnitude2() > 1e-20 {
+            normal.normalize()
+        } else {
+            own
+        }
+    };
+    let normals: Vec<[Vector3<f32>; 3]> = triangles
+        .iter()
+        .enumerate()
+        .map(|(face, triangle)| {
+            if valid(triangle) {
+                [0, 1, 2].map(|corner| corner_normal(face, triangle[corner]))
+            } else {
+                [zero; 3]
+            }
+        })
+        .collect();
+
+    // Give each corner its normal, splitting vertices whose corners disagree
+    let mut variants: Vec<Vec<(Vector3<f32>, u16)>> = vec![Vec::new(); count];
+    for (face, triangle) in triangles.iter().enumerate().filter(|(_, t)| valid(t)) {
+        for (corner, &index) in triangle.iter().enumerate() {
+            let normal = normals[face][corner];
+            let existing = &variants[index];
+            let target = match existing
+                .iter()
+                .find(|(n, _)| (n - normal).magnitude2() < 1e-8)
+            {
+                Some(&(_, target)) => target,
+                None if existing.is_empty() => {
+                    vertices[index].normal = normal.into();
+                    variants[index].push((normal, index as u16));
+                    index as u16
+                }
+                None => match u16::try_from(vertices.len()) {
+                    Ok(new_index) => {
+                        vertices.push(vertices[index].with_normal(normal.into()));
+                        variants[index].push((normal, new_index));
+                        new_index
+                    }
+                    Err(_) => existing[0].1,
+                },
+            };
+            indices[face * 3 + corner] = target;
+        }
+    }
+}
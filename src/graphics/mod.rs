@@ -1,23 +1,166 @@
 //! Graphics rendering system built on wgpu
 
 // use crate::camera::{utils as camera_utils, Camera};
-use crate::ecs::{Component, World};
-use crate::math::{Matrix4, Transform};
+use crate::ecs::{Component, Without, World};
+use crate::math::{Aabb, Matrix4, Vector3};
+use crate::time::TimeState;
 use anyhow::{Context, Result};
-use cgmath::{Deg, SquareMatrix, perspective};
+use cgmath::{Deg, MetricSpace, SquareMatrix, perspective};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::sync::Arc;
 use wgpu::util::DeviceExt;
 use winit::window::Window;
 
+mod blit;
+mod bounds;
+mod buffer;
+mod capabilities;
+mod capture;
+mod color;
+mod config;
+mod culling;
+mod custom_material;
+mod debug_draw;
+mod extrude;
+mod heightmap;
+mod id_buffer;
+mod instancing;
+mod light;
+mod material;
+mod mesh_data;
+pub mod mesh_utils;
+mod outline;
+mod pacing;
+mod picking;
+mod ply;
+mod point_cloud;
+mod post;
+pub mod procedural;
+mod render_list;
+mod ribbon;
+#[cfg(feature = "shader-reload")]
+mod shader_reload;
+mod skybox;
+mod sprite;
+mod stats;
+mod stereo;
+mod stl;
+mod target_dump;
+mod targets;
+#[cfg(feature = "text")]
+mod text;
+#[cfg(feature = "text-mesh")]
+mod text_mesh;
+mod texture;
+mod viewport;
+
+pub use bounds::{BoundsDebug, BoundsPlugin, WorldAabb, bounds_system};
+pub use buffer::{StorageBuffer, UniformBuffer};
+pub use capabilities::GpuCapabilities;
+pub use capture::{CAPTURE_FORMAT, CapturedImage};
+pub use color::{Color, linear_to_srgb, srgb_to_linear};
+pub use config::{RendererConfig, SharedGpu};
+pub use culling::CullingStats;
+pub use custom_material::{CustomMaterial, Shader};
+pub use debug_draw::DebugDraw;
+pub use extrude::{Extrusion, Lathe};
+pub use heightmap::Heightmap;
+pub use id_buffer::{
+    GpuPicking, GpuPickingPlugin, IdPick, gpu_picking_cursor_system, gpu_picking_system,
+};
+pub use instancing::{Instance, Instances};
+pub use light::{Attenuation, DirectionalLight, MAX_LIGHTS, PointLight, SpotLight};
+pub use material::{Material, ShadingMode};
+pub use mesh_data::MeshData;
+pub use outline::{MAX_OUTLINE_WIDTH, Outlined};
+pub use pacing::{FramePacing, LatencyStats};
+pub use picking::PickHit;
+pub use ply::Ply;
+pub use point_cloud::{Point, PointCloud};
+pub(crate) use post::swap_camera_post_effects;
+pub use post::{
+    Bloom, Exposure, HDR_FORMAT, PostEffect, PostEffects, PostFrame, TonemapOperator, Vignette,
+};
+pub use render_list::Visibility;
+pub use ribbon::Ribbon;
+pub use skybox::{Cubemap, Skybox};
+pub use sprite::{Sprite, SpriteAnchor};
+pub use stats::RenderStats;
+pub use stereo::{StereoConfig, StereoMode};
+pub use stl::Stl;
+pub use targets::{RenderTarget, RenderTargets};
+#[cfg(feature = "text")]
+pub use text::{Text, Text3d};
+#[cfg(feature = "text-mesh")]
+pub use text_mesh::{Font, TextAlign, TextMesh};
+pub use texture::Texture;
+pub(crate) use texture::read_png_rgba8;
+pub use viewport::{ScissorRect, Viewport};
+
+use blit::Blitter;
+use culling::Culler;
+use custom_material::RegisteredMaterial;
+use debug_draw::DebugRenderer;
+use point_cloud::PointCloudRenderer;
+use post::PostOutput;
+use render_list::{RenderItem, RenderList};
+use ribbon::RibbonRenderer;
+use skybox::SkyRenderer;
+use sprite::SpriteRenderer;
+use stereo::EyePass;
+#[cfg(feature = "text")]
+use text::TextRenderer;
+
+use light::LightUniform;
+use pacing::FramePacer;
+
 /// Vertex structure for rendering
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
     pub position: [f32; 3],
+    /// Linear RGB, multiplied by the material color (see `Color`)
     pub color: [f32; 3],
+    /// Surface normal; all zeros means "use the face normal" in the default shader
+    pub normal: [f32; 3],
+    /// Texture coordinates, origin at the top left of the image
+    pub uv: [f32; 2],
+    /// Tangent along increasing U with bitangent handedness in w; all zeros
+    /// disables normal mapping (see `mesh_utils::generate_tangents`)
+    pub tangent: [f32; 4],
 }
 
 impl Vertex {
+    /// Create a vertex without a normal, UVs, or tangent
+    pub fn new(position: [f32; 3], color: [f32; 3]) -> Self {
+        Self {
+            position,
+            color,
+            normal: [0.0; 3],
+            uv: [0.0; 2],
+            tangent: [0.0; 4],
+        }
+    }
+
+    /// Set the normal
+    pub fn with_normal(mut self, normal: [f32; 3]) -> Self {
+        self.normal = normal;
+        self
+    }
+
+    /// Set the texture coordinates
+    pub fn with_uv(mut self, uv: [f32; 2]) -> Self {
+        self.uv = uv;
+        self
+    }
+
+    /// Set the tangent
+    pub fn with_tangent(mut self, tangent: [f32; 4]) -> Self {
+        self.tangent = tangent;
+        self
+    }
+
     /// Get the vertex buffer layout descriptor
     pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         wgpu::VertexBufferLayout {
@@ -36,18 +179,40 @@ impl Vertex {
                     shader_location: 1,
                     format: wgpu::VertexFormat::Float32x3,
                 },
+                // Normal
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                // UV
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 9]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                // Tangent
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 11]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
             ],
         }
     }
 }
 
 /// Mesh component containing GPU buffers for rendering
-#[derive(Debug)]
+///
+/// Cloning is cheap and shares the underlying buffers.
+#[derive(Debug, Clone)]
 pub struct Mesh {
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
     pub num_indices: u32,
     pub primitive_topology: wgpu::PrimitiveTopology,
+    /// Local-space bounds of the vertices, used for frustum culling
+    pub bounds: Aabb,
 }
 
 impl Component for Mesh {}
@@ -87,146 +252,1151 @@ impl Mesh {
             index_buffer,
             num_indices: indices.len() as u32,
             primitive_topology: topology,
+            bounds: Aabb::from_points(vertices.iter().map(|vertex| vertex.position))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Create a triangle mesh whose buffers can be rewritten with `update`,
+    /// for geometry that changes every frame
+    ///
+    /// ```rust,ignore
+    /// let mut mesh = renderer.create_dynamic_mesh(&vertices, &indices);
+    /// // Each frame, after moving the vertices:
+    /// renderer.update_mesh(&mut mesh, &vertices, &indices);
+    /// ```
+    pub fn new_dynamic(device: &wgpu::Device, vertices: &[Vertex], indices: &[u16]) -> Self {
+        let indices = padded_indices(indices);
+        Self {
+            vertex_buffer: create_dynamic_buffer(
+                device,
+                "Dynamic Vertex Buffer",
+                bytemuck::cast_slice(vertices),
+                wgpu::BufferUsages::VERTEX,
+            ),
+            index_buffer: create_dynamic_buffer(
+                device,
+                "Dynamic Index Buffer",
+                bytemuck::cast_slice(&indices.0),
+                wgpu::BufferUsages::INDEX,
+            ),
+            num_indices: indices.1,
+            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+            bounds: Aabb::from_points(vertices.iter().map(|vertex| vertex.position))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Whether the buffers can be rewritten in place (see `new_dynamic`)
+    pub fn is_dynamic(&self) -> bool {
+        self.vertex_buffer
+            .usage()
+            .contains(wgpu::BufferUsages::COPY_DST)
+            && self
+                .index_buffer
+                .usage()
+                .contains(wgpu::BufferUsages::COPY_DST)
+    }
+
+    /// Replace the vertices and indices, writing into the existing buffers
+    /// when they fit
+    ///
+    /// A buffer that is too small, or a mesh not created with `new_dynamic`,
+    /// gets a new dynamic buffer with room to grow. Clones of the mesh share
+    /// the old buffers, so they see writes made in place but not new buffers.
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        vertices: &[Vertex],
+        indices: &[u16],
+    ) {
+        let (indices, num_indices) = padded_indices(indices);
+        write_dynamic_buffer(
+            device,
+            queue,
+            &mut self.vertex_buffer,
+            "Dynamic Vertex Buffer",
+            bytemuck::cast_slice(vertices),
+            wgpu::BufferUsages::VERTEX,
+        );
+        write_dynamic_buffer(
+            device,
+            queue,
+            &mut self.index_buffer,
+            "Dynamic Index Buffer",
+            bytemuck::cast_slice(&indices),
+            wgpu::BufferUsages::INDEX,
+        );
+        self.num_indices = num_indices;
+        self.bounds =
+            Aabb::from_points(vertices.iter().map(|vertex| vertex.position)).unwrap_or_default();
+    }
+
+    /// Fill in the normals of triangle-list vertex data before creating a
+    /// mesh from it, smoothed across shared positions or one per face
+    ///
+    /// Flat normals split vertices shared between faces, which appends
+    /// vertices and rewrites `indices` (see `mesh_utils::recompute_normals`
+    /// for an angle threshold between the two). Tangents are regenerated if
+    /// any vertex had one, so normal maps keep working.
+    ///
+    /// ```rust
+    /// use qsi::graphics::{Mesh, Vertex};
+    ///
+    /// // Two triangles folded along a shared edge
+    /// let mut vertices = vec![
+    ///     Vertex::new([0.0, 0.0, 0.0], [1.0; 3]),
+    ///     Vertex::new([1.0, 0.0, 0.0], [1.0; 3]),
+    ///     Vertex::new([0.0, 1.0, 0.0], [1.0; 3]),
+    ///     Vertex::new([0.0, 0.0, 1.0], [1.0; 3]),
+    /// ];
+    /// let mut indices = vec![0, 1, 2, 0, 3, 1];
+    /// Mesh::compute_normals(&mut vertices, &mut indices, false);
+    /// assert_eq!(vertices.len(), 6);
+    /// assert_eq!(vertices[indices[0] as usize].normal, [0.0, 0.0, 1.0]);
+    /// ```
+    pub fn compute_normals(vertices: &mut Vec<Vertex>, indices: &mut [u16], smooth: bool) {
+        let had_tangents = vertices.iter().any(|vertex| vertex.tangent != [0.0; 4]);
+        let angle_threshold = if smooth { std::f32::consts::PI } else { 0.0 };
+        mesh_utils::recompute_normals(vertices, indices, angle_threshold);
+        if had_tangents {
+            mesh_utils::generate_tangents(vertices, indices);
         }
     }
+
+    /// Create a line strip through `points` in order, one vertex per point
+    ///
+    /// Suited to trajectories and function plots. Index `u16::MAX` restarts
+    /// the strip, so at most 65 535 points are used.
+    pub fn polyline(device: &wgpu::Device, points: &[Vector3<f32>], color: [f32; 3]) -> Self {
+        let max_points = u16::MAX as usize;
+        if points.len() > max_points {
+            log::warn!(
+                "Polyline has {} points, keeping the first {max_points}",
+                points.len()
+            );
+        }
+        let vertices: Vec<_> = points
+            .iter()
+            .take(max_points)
+            .map(|point| Vertex::new((*point).into(), color))
+            .collect();
+        let indices: Vec<u16> = (0..vertices.len() as u16).collect();
+        Self::new_with_topology(
+            device,
+            &vertices,
+            &indices,
+            wgpu::PrimitiveTopology::LineStrip,
+        )
+    }
 }
 
-/// Uniform buffer data for shaders
+/// Indices padded to a whole number of 4-byte words, as buffer writes need,
+/// and the count of real ones
+fn padded_indices(indices: &[u16]) -> (Cow<'_, [u16]>, u32) {
+    let count = indices.len() as u32;
+    if indices.len().is_multiple_of(2) {
+        (Cow::Borrowed(indices), count)
+    } else {
+        let mut padded = indices.to_vec();
+        padded.push(0);
+        (Cow::Owned(padded), count)
+    }
+}
+
+/// Writable buffer holding `contents`, at least one word long
+fn create_dynamic_buffer(
+    device: &wgpu::Device,
+    label: &str,
+    contents: &[u8],
+    usage: wgpu::BufferUsages,
+) -> wgpu::Buffer {
+    let size = (contents.len() as wgpu::BufferAddress).max(wgpu::COPY_BUFFER_ALIGNMENT);
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size,
+        usage: usage | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: true,
+    });
+    buffer.slice(..).get_mapped_range_mut()[..contents.len()].copy_from_slice(contents);
+    buffer.unmap();
+    buffer
+}
+
+/// Write `contents` into `buffer`, replacing it with a writable buffer of
+/// twice the needed size if it is too small or read-only
+fn write_dynamic_buffer(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &mut wgpu::Buffer,
+    label: &str,
+    contents: &[u8],
+    usage: wgpu::BufferUsages,
+) {
+    let size = contents.len() as wgpu::BufferAddress;
+    if size > buffer.size() || !buffer.usage().contains(wgpu::BufferUsages::COPY_DST) {
+        *buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: (size * 2)
+                .next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+                .max(wgpu::COPY_BUFFER_ALIGNMENT),
+            usage: usage | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+    }
+    if size > 0 {
+        queue.write_buffer(buffer, 0, contents);
+    }
+}
+
+/// Serializable description of a mesh, built into a `Mesh` by `build_mesh_sources`
+///
+/// Scenes store this instead of GPU buffers.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MeshSource {
+    /// Axis-aligned cube centred on the origin
+    Cube { size: f32, color: [f32; 3] },
+    /// Line grid on the XZ plane
+    Grid {
+        cells: u32,
+        spacing: f32,
+        color: [f32; 3],
+    },
+}
+
+impl Component for MeshSource {}
+
+impl MeshSource {
+    /// Create the GPU mesh described by this source
+    pub fn build(&self, device: &wgpu::Device) -> Mesh {
+        self.mesh_data().to_mesh(device)
+    }
+
+    /// The geometry described by this source, on the CPU
+    pub fn mesh_data(&self) -> MeshData {
+        match *self {
+            MeshSource::Cube { size, color } => {
+                let h = size * 0.5;
+                // Four vertices per face so each face has its own normal
+                let faces: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+                    ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+                    ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+                    ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+                    ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+                    ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+                    ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+                ];
+                let mut vertices = Vec::with_capacity(24);
+                let mut indices = Vec::with_capacity(36);
+                for (normal, u, v) in faces {
+                    let base = vertices.len() as u16;
+                    for (su, sv) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                        let position = [0, 1, 2].map(|i| (normal[i] + su * u[i] + sv * v[i]) * h);
+                        let uv = [(1.0 + su) * 0.5, (1.0 - sv) * 0.5];
+                        vertices.push(Vertex::new(position, color).with_normal(normal).with_uv(uv));
+                    }
+                    indices.extend_from_slice(&[
+                        base,
+                        base + 1,
+                        base + 2,
+                        base + 2,
+                        base + 3,
+                        base,
+                    ]);
+                }
+                mesh_utils::generate_tangents(&mut vertices, &indices);
+                MeshData::new(vertices, indices)
+            }
+            MeshSource::Grid {
+                cells,
+                spacing,
+                color,
+            } => {
+                let half = cells as f32 * spacing * 0.5;
+                let mut vertices = Vec::new();
+                for i in 0..=cells {
+                    let offset = i as f32 * spacing - half;
+                    vertices.push(Vertex::new([-half, 0.0, offset], color));
+                    vertices.push(Vertex::new([half, 0.0, offset], color));
+                    vertices.push(Vertex::new([offset, 0.0, -half], color));
+                    vertices.push(Vertex::new([offset, 0.0, half], color));
+                }
+                let indices: Vec<u16> = (0..vertices.len() as u16).collect();
+                MeshData::new(vertices, indices).with_topology(wgpu::PrimitiveTopology::LineList)
+            }
+        }
+    }
+}
+
+/// Build a `Mesh` for every entity that has a `MeshSource` but no mesh yet
+pub fn build_mesh_sources(world: &mut World, renderer: &Renderer) {
+    let pending: Vec<_> = world
+        .query_filtered::<MeshSource, Without<Mesh>>()
+        .map(|(entity, source)| (entity, source.build(renderer.device())))
+        .collect();
+    for (entity, mesh) in pending {
+        world.add_component(entity, mesh);
+    }
+}
+
+/// Per-view data shared by every scene shader (group 0, binding 0); see
+/// `Globals` in `default.wgsl` for the WGSL side
+///
+/// `view_proj` and `position` come first so shaders may declare only those.
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-struct Uniforms {
+struct GlobalsUniform {
     view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+    view: [[f32; 4]; 4],
+    proj: [[f32; 4]; 4],
+    inverse_view: [[f32; 4]; 4],
+    inverse_proj: [[f32; 4]; 4],
+    inverse_view_proj: [[f32; 4]; 4],
+    /// Elapsed seconds, last frame's duration in seconds, and frame count
+    time: [f32; 4],
+    /// Target width and height in pixels, then their reciprocals
+    resolution: [f32; 4],
+}
+
+impl GlobalsUniform {
+    fn new(
+        view: Matrix4<f32>,
+        proj: Matrix4<f32>,
+        time: [f32; 3],
+        (width, height): (u32, u32),
+    ) -> Self {
+        let invert = |matrix: Matrix4<f32>| matrix.invert().unwrap_or_else(Matrix4::identity);
+        let inverse_view = invert(view);
+        let eye = inverse_view.w;
+        let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+        Self {
+            view_proj: (proj * view).into(),
+            position: [eye.x, eye.y, eye.z, 1.0],
+            view: view.into(),
+            proj: proj.into(),
+            inverse_view: inverse_view.into(),
+            inverse_proj: invert(proj).into(),
+            inverse_view_proj: invert(proj * view).into(),
+            time: [time[0], time[1], time[2], 0.0],
+            resolution: [width, height, 1.0 / width, 1.0 / height],
+        }
+    }
+}
+
+/// Per-object data stored in the object storage buffer, indexed by instance
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ObjectUniform {
     model: [[f32; 4]; 4],
+    base_color: [f32; 4],
+    /// Emissive color in xyz, shading mode in w
+    emissive: [f32; 4],
+    /// Normal map strength in x, per-instance scalar in y
+    params: [f32; 4],
+}
+
+/// Depth buffer format used when none is configured
+pub const DEFAULT_DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// One item of the render list to draw with its slots in the object buffer
+struct MeshDraw {
+    item: usize,
+    index: u32,
+    // Number of consecutive object slots, one per instance
+    count: u32,
+}
+
+/// Shader, topology, and blending a mesh is drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct PipelineId {
+    /// Index of a registered custom material, or `None` for the default shader
+    material: Option<usize>,
+    topology: wgpu::PrimitiveTopology,
+    /// Alpha blended without depth writes (see `Material::transparent`)
+    transparent: bool,
+    /// Triangles drawn as edges (see `Renderer::set_wireframe`)
+    wireframe: bool,
+}
+
+/// Draws sharing one pipeline
+struct DrawBucket {
+    pipeline: PipelineId,
+    draws: Vec<MeshDraw>,
+}
+
+/// Meshes to draw this frame: opaque ones grouped by pipeline, then
+/// transparent ones back to front
+#[derive(Default)]
+struct SceneDraws {
+    buckets: Vec<DrawBucket>,
+    // Runs of consecutive transparent draws sharing a pipeline, in draw order
+    transparent: Vec<DrawBucket>,
+}
+
+impl SceneDraws {
+    /// All buckets in the order they are drawn
+    fn in_order(&self) -> impl Iterator<Item = &DrawBucket> {
+        self.buckets.iter().chain(&self.transparent)
+    }
+
+    /// Append a transparent draw after the previous ones
+    fn push_transparent(&mut self, pipeline: PipelineId, draw: MeshDraw) {
+        match self.transparent.last_mut() {
+            Some(bucket) if bucket.pipeline == pipeline => bucket.draws.push(draw),
+            _ => self.transparent.push(DrawBucket {
+                pipeline,
+                draws: vec![draw],
+            }),
+        }
+    }
+
+    /// Append an opaque draw; the render list already groups them by pipeline
+    fn push(&mut self, pipeline: PipelineId, draw: MeshDraw) {
+        match self.buckets.last_mut() {
+            Some(bucket) if bucket.pipeline == pipeline => bucket.draws.push(draw),
+            _ => self.buckets.push(DrawBucket {
+                pipeline,
+                draws: vec![draw],
+            }),
+        }
+    }
+}
+
+/// Color target rendered from its own camera, such as one eye of a headset
+pub struct ViewTarget<'a> {
+    pub view: Matrix4<f32>,
+    pub projection: Matrix4<f32>,
+    pub target: &'a wgpu::TextureView,
+    pub format: wgpu::TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    /// Part of `target` to draw into, keeping the rest; the whole target
+    /// (cleared first) when `None`. Targets sharing a texture with different
+    /// viewports make a split screen, each with a `projection` built for its
+    /// `Viewport::aspect` (see `Renderer::projection`).
+    pub viewport: Option<Viewport>,
 }
 
-impl Uniforms {
-    fn new() -> Self {
+/// Field of view and clip planes the camera projection is built from
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Lens {
+    /// Vertical field of view in degrees
+    fov: f32,
+    near: f32,
+    far: f32,
+}
+
+impl Default for Lens {
+    fn default() -> Self {
         Self {
-            view_proj: Matrix4::identity().into(),
-            model: Matrix4::identity().into(),
+            fov: 45.0,
+            near: 0.1,
+            far: 100.0,
         }
     }
+}
 
-    fn update_view_proj(&mut self, view: Matrix4<f32>, proj: Matrix4<f32>) {
-        self.view_proj = (proj * view).into();
+impl Lens {
+    fn projection(&self, aspect: f32) -> Matrix4<f32> {
+        let aspect = if aspect.is_finite() && aspect > 0.0 {
+            aspect
+        } else {
+            1.0
+        };
+        perspective(Deg(self.fov), aspect, self.near, self.far)
     }
+}
+
+/// Color format, sample count, and write mask of a render target
+type PipelineKey = (wgpu::TextureFormat, u32, wgpu::ColorWrites);
+
+/// Pipelines for what `draw_scene` draws besides meshes, for one target
+struct ExtraPipelines {
+    sky: Option<wgpu::RenderPipeline>,
+    point_clouds: Option<wgpu::RenderPipeline>,
+    debug_lines: Option<wgpu::RenderPipeline>,
+    ribbons: Option<wgpu::RenderPipeline>,
+    #[cfg(feature = "text")]
+    text: Option<wgpu::RenderPipeline>,
+}
+
+/// Number of view slots in the globals buffer (one per eye in stereo)
+const MAX_VIEWS: usize = 2;
 
-    fn update_model(&mut self, model: Matrix4<f32>) {
-        self.model = model.into();
+/// Initial number of object slots in the object buffer
+const INITIAL_OBJECT_CAPACITY: usize = 256;
+
+fn create_object_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Object Buffer"),
+        size: (capacity * std::mem::size_of::<ObjectUniform>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Scene pipeline for a shader with the given topology, blending, and target formats
+fn create_scene_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    (color_format, sample_count, write_mask): PipelineKey,
+    depth_format: wgpu::TextureFormat,
+    PipelineId {
+        topology,
+        transparent,
+        wireframe,
+        ..
+    }: PipelineId,
+) -> wgpu::RenderPipeline {
+    let (label, cull_mode) = match topology {
+        wgpu::PrimitiveTopology::LineList => ("Line Pipeline", None), // No culling for lines
+        wgpu::PrimitiveTopology::LineStrip => ("Line Strip Pipeline", None),
+        _ => ("Triangle Pipeline", Some(wgpu::Face::Back)),
+    };
+    // Strips are drawn indexed, with u16::MAX restarting them
+    let strip_index_format =
+        (topology == wgpu::PrimitiveTopology::LineStrip).then_some(wgpu::IndexFormat::Uint16);
+    let blend = if transparent {
+        wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::OVER,
+        }
+    } else {
+        wgpu::BlendState::REPLACE
+    };
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[Vertex::desc()],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: color_format,
+                blend: Some(blend),
+                write_mask,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology,
+            strip_index_format,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode,
+            polygon_mode: if wireframe {
+                wgpu::PolygonMode::Line
+            } else {
+                wgpu::PolygonMode::Fill
+            },
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: depth_format,
+            depth_write_enabled: !transparent,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// sRGB view of a surface `format` that has none, so shaders' linear output
+/// is encoded on write; without one, colors come out too dark
+fn srgb_view_formats(
+    adapter: &wgpu::Adapter,
+    format: wgpu::TextureFormat,
+) -> Vec<wgpu::TextureFormat> {
+    let srgb = format.add_srgb_suffix();
+    if srgb == format {
+        return Vec::new();
+    }
+    if adapter
+        .get_downlevel_capabilities()
+        .flags
+        .contains(wgpu::DownlevelFlags::SURFACE_VIEW_FORMATS)
+    {
+        vec![srgb]
+    } else {
+        log::warn!(
+            "Surface format {format:?} is not sRGB and cannot be viewed as sRGB; colors will look too dark"
+        );
+        Vec::new()
     }
 }
 
+/// `requested` if the surface supports it, otherwise `Fifo`, which every
+/// surface does
+fn supported_present_mode(
+    requested: wgpu::PresentMode,
+    supported: &[wgpu::PresentMode],
+) -> wgpu::PresentMode {
+    // The automatic modes resolve to a supported one when configured
+    let automatic = matches!(
+        requested,
+        wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
+    );
+    if automatic || supported.contains(&requested) {
+        requested
+    } else {
+        log::warn!("Present mode {requested:?} is not supported; using Fifo");
+        wgpu::PresentMode::Fifo
+    }
+}
+
+/// Fail, naming every limit the adapter falls short of, if `required` asks
+/// more than `allowed`
+fn check_limits(required: &wgpu::Limits, allowed: &wgpu::Limits) -> Result<()> {
+    let mut exceeded = Vec::new();
+    required.check_limits_with_fail_fn(allowed, false, |name, required, allowed| {
+        exceeded.push(format!(
+            "{name} (asked {required}, adapter allows {allowed})"
+        ));
+    });
+    if !exceeded.is_empty() {
+        anyhow::bail!(
+            "GPU adapter does not support the requested limits: {}",
+            exceeded.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Scene brightness multiplier from the `Exposure` resource
+fn exposure(world: &World) -> f32 {
+    world
+        .resource::<Exposure>()
+        .map_or(1.0, |exposure| exposure.value)
+}
+
+/// Color target for a headless renderer, readable with `read_frame`
+fn create_offscreen_texture(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Headless Target"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::COPY_SRC
+            | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    })
+}
+
+fn create_depth_view(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        size: wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        // Sampleable so passes can read it through `RenderTargets::DEPTH`;
+        // multisampled depth cannot be bound on every backend
+        usage: if sample_count == 1 {
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+        } else {
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+        },
+        label: Some("depth_texture"),
+        view_formats: &[],
+    });
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn create_texture_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    normal_map: &Texture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&normal_map.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&normal_map.sampler),
+            },
+        ],
+        label: Some("material_texture_bind_group"),
+    })
+}
+
+fn create_uniform_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    globals_buffer: &wgpu::Buffer,
+    object_buffer: &wgpu::Buffer,
+    light_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: globals_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<GlobalsUniform>() as u64),
+                }),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: object_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: light_buffer.as_entire_binding(),
+            },
+        ],
+        label: Some("uniform_bind_group"),
+    })
+}
+
 /// Main renderer that handles all GPU resources and rendering
 pub struct Renderer {
     // GPU resources
     device: wgpu::Device,
     queue: wgpu::Queue,
-    surface: wgpu::Surface<'static>,
+    // Window surface, or `None` for a headless renderer
+    surface: Option<wgpu::Surface<'static>>,
     config: wgpu::SurfaceConfiguration,
-    pub window: Arc<Window>,
+    pub window: Option<Arc<Window>>,
     is_surface_configured: bool,
+    // Present modes the surface supports
+    present_modes: Vec<wgpu::PresentMode>,
+    // Color target rendered into instead of a surface when headless
+    offscreen: Option<wgpu::Texture>,
 
     // Rendering resources
-    triangle_pipeline: wgpu::RenderPipeline,
-    line_pipeline: wgpu::RenderPipeline,
-    uniform_buffer: wgpu::Buffer,
+    scene_shader: wgpu::ShaderModule,
+    scene_pipeline_layout: wgpu::PipelineLayout,
+    // Optional features found on the adapter
+    capabilities: GpuCapabilities,
+    // Triangle meshes drawn as edges
+    wireframe: bool,
+    pipeline_cache: HashMap<(PipelineId, PipelineKey), wgpu::RenderPipeline>,
+    // Shaders registered with `register_material`
+    materials: Vec<RegisteredMaterial>,
+    // Watches shader files for `watch_default_shader` and `register_material_file`
+    #[cfg(feature = "shader-reload")]
+    shader_watcher: Option<shader_reload::ShaderWatcher>,
+    target_depth: Option<((u32, u32), wgpu::TextureView)>,
+    globals_buffer: wgpu::Buffer,
+    globals_stride: wgpu::BufferAddress,
+    object_buffer: wgpu::Buffer,
+    light_buffer: wgpu::Buffer,
+    object_capacity: usize,
+    objects: Vec<ObjectUniform>,
+    uniform_bind_group_layout: wgpu::BindGroupLayout,
     uniform_bind_group: wgpu::BindGroup,
-    uniforms: Uniforms,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    // Bound for materials without a normal map
+    default_texture_bind_group: wgpu::BindGroup,
+    // Per normal map, kept while some drawn material uses it
+    texture_bind_groups: HashMap<u64, wgpu::BindGroup>,
+    // What the current frame draws, extracted from the world
+    render_list: RenderList,
+
+    // Depth buffer, reused across frames and recreated on resize
+    depth_format: wgpu::TextureFormat,
+    depth_view: wgpu::TextureView,
 
-    // Camera matrices (stored separately for proper orbital camera support)
+    // Camera view matrix (stored separately for proper orbital camera
+    // support), and what projections are built from for each target's aspect
     current_view_matrix: Matrix4<f32>,
-    current_proj_matrix: Matrix4<f32>,
+    lens: Lens,
+
+    // Background color as displayed, after tonemapping
+    clear_color: Color,
+
+    // Part of the window the scene is drawn into, and the rectangle it is
+    // clipped to, in window pixels
+    viewport: Option<Viewport>,
+    scissor: Option<ScissorRect>,
+
+    // Two-view rendering, when enabled
+    stereo: Option<StereoConfig>,
+
+    // Background drawn instead of the clear color, when set
+    sky: Option<SkyRenderer>,
+
+    // Effects and tonemapping between the HDR scene texture and the output
+    post_effects: PostEffects,
+
+    // Skip meshes outside every view frustum, and what that skipped last frame
+    frustum_culling: bool,
+    culling_stats: CullingStats,
+
+    // Work submitted so far this frame, and by the last rendered frame
+    frame_stats: RenderStats,
+    render_stats: RenderStats,
 
-    // Clear color
-    clear_color: wgpu::Color,
+    // Pass for `blit`, created on first use
+    blitter: Option<Blitter>,
+
+    // Overlay pass for `Sprite`s, created once there are any
+    sprites: Option<SpriteRenderer>,
+    point_clouds: Option<PointCloudRenderer>,
+    debug_lines: Option<DebugRenderer>,
+    ribbons: Option<RibbonRenderer>,
+    #[cfg(feature = "text")]
+    text: Option<TextRenderer>,
+
+    // Built-in and user textures by name
+    render_targets: RenderTargets,
+
+    // Elapsed seconds, frame seconds, and frame count passed to shaders
+    shader_time: [f32; 3],
+
+    // Frame pacing policy and latency of recent frames
+    pacer: FramePacer,
+
+    // Entity ID pass for GPU picking, while turned on
+    id_buffer: Option<Box<id_buffer::IdBuffer>>,
+    outlines: Option<Box<outline::OutlineRenderer>>,
 }
 
 impl Renderer {
     /// Create a new renderer
     pub async fn new(window: Arc<Window>) -> Result<Self> {
-        let size = window.inner_size();
+        Self::with_config(window, &RendererConfig::default()).await
+    }
 
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::PRIMARY,
-            ..Default::default()
-        });
+    /// Create a new renderer using the given depth buffer format
+    pub async fn with_depth_format(
+        window: Arc<Window>,
+        depth_format: wgpu::TextureFormat,
+    ) -> Result<Self> {
+        let config = RendererConfig::default().with_depth_format(depth_format);
+        Self::with_config(window, &config).await
+    }
 
+    /// Create a renderer on one of `backends` (e.g. only Vulkan, for debugging)
+    pub async fn with_backends(
+        window: Arc<Window>,
+        depth_format: wgpu::TextureFormat,
+        backends: wgpu::Backends,
+    ) -> Result<Self> {
+        let config = RendererConfig::default()
+            .with_depth_format(depth_format)
+            .with_backends(backends);
+        Self::with_config(window, &config).await
+    }
+
+    /// Create a renderer set up as `config` asks
+    pub async fn with_config(window: Arc<Window>, config: &RendererConfig) -> Result<Self> {
+        let size = window.inner_size();
+        let instance = config.instance(false);
         let surface = instance.create_surface(window.clone())?;
+        Self::create(
+            instance,
+            Some((surface, window)),
+            (size.width, size.height),
+            config,
+        )
+        .await
+    }
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .context("Failed to find a suitable GPU adapter")?;
-
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                label: Some("Main Device"),
-                required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::default(),
-                memory_hints: Default::default(),
-                trace: Default::default(),
-            })
-            .await
-            .context("Failed to create logical device and command queue")?;
+    /// Create a renderer without a window that draws into an offscreen
+    /// `width`×`height` texture (read it back with `read_frame`)
+    ///
+    /// Falls back to a software adapter when no GPU is available, so this
+    /// also works on CI machines and servers.
+    pub async fn new_headless(width: u32, height: u32) -> Result<Self> {
+        Self::headless_with_config(width, height, &RendererConfig::default()).await
+    }
 
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = surface_caps
-            .formats
-            .iter()
-            .find(|f| f.is_srgb())
-            .copied()
-            .unwrap_or(surface_caps.formats[0]);
+    /// Create a headless renderer using the given depth buffer format
+    pub async fn headless_with_depth_format(
+        width: u32,
+        height: u32,
+        depth_format: wgpu::TextureFormat,
+    ) -> Result<Self> {
+        let config = RendererConfig::default().with_depth_format(depth_format);
+        Self::headless_with_config(width, height, &config).await
+    }
+
+    /// Create a headless renderer on one of `backends`
+    pub async fn headless_with_backends(
+        width: u32,
+        height: u32,
+        depth_format: wgpu::TextureFormat,
+        backends: wgpu::Backends,
+    ) -> Result<Self> {
+        let config = RendererConfig::default()
+            .with_depth_format(depth_format)
+            .with_backends(backends);
+        Self::headless_with_config(width, height, &config).await
+    }
+
+    /// Create a headless renderer set up as `config` asks; the present mode
+    /// does not apply without a surface
+    pub async fn headless_with_config(
+        width: u32,
+        height: u32,
+        config: &RendererConfig,
+    ) -> Result<Self> {
+        if width == 0 || height == 0 {
+            anyhow::bail!("Headless size must be non-zero, got {width}x{height}");
+        }
+        Self::create(config.instance(true), None, (width, height), config).await
+    }
+
+    /// Shared setup for windowed and headless renderers
+    async fn create(
+        instance: wgpu::Instance,
+        surface: Option<(wgpu::Surface<'static>, Arc<Window>)>,
+        (width, height): (u32, u32),
+        renderer_config: &RendererConfig,
+    ) -> Result<Self> {
+        let (surface, window) = surface.unzip();
+
+        let shared = renderer_config.gpu.as_ref();
+        let adapter = match shared {
+            Some(gpu) => gpu.adapter.clone(),
+            None => {
+                let mut adapter = instance
+                    .request_adapter(&wgpu::RequestAdapterOptions {
+                        power_preference: renderer_config.power_preference,
+                        compatible_surface: surface.as_ref(),
+                        force_fallback_adapter: false,
+                    })
+                    .await;
+                if adapter.is_err() && surface.is_none() {
+                    adapter = instance
+                        .request_adapter(&wgpu::RequestAdapterOptions {
+                            power_preference: renderer_config.power_preference,
+                            compatible_surface: None,
+                            force_fallback_adapter: true,
+                        })
+                        .await;
+                }
+                adapter.context("Failed to find a suitable GPU adapter")?
+            }
+        };
+        let depth_format = renderer_config.depth_format;
+        check_limits(&renderer_config.limits, &adapter.limits())?;
+
+        // Post effects capture through the HDR format, so it must multisample too
+        let (capabilities, required_features) =
+            GpuCapabilities::detect(&adapter, &renderer_config.sampled_formats());
+
+        let (device, queue) = match shared {
+            Some(gpu) => {
+                if !gpu.device.features().contains(required_features) {
+                    anyhow::bail!(
+                        "Shared GPU device lacks features {:?}",
+                        required_features - gpu.device.features()
+                    );
+                }
+                if let Some(surface) = &surface
+                    && surface.get_capabilities(&adapter).formats.is_empty()
+                {
+                    anyhow::bail!("Shared GPU adapter cannot present to this window");
+                }
+                (gpu.device.clone(), gpu.queue.clone())
+            }
+            None => adapter
+                .request_device(&wgpu::DeviceDescriptor {
+                    label: Some("Main Device"),
+                    required_features,
+                    required_limits: renderer_config.limits.clone(),
+                    memory_hints: Default::default(),
+                    trace: Default::default(),
+                })
+                .await
+                .context("Failed to create logical device and command queue")?,
+        };
+
+        // Headless renderers use the capture format so frames read back directly
+        let mut present_modes = vec![wgpu::PresentMode::Fifo];
+        let (surface_format, alpha_mode, present_mode) = match &surface {
+            Some(surface) => {
+                let surface_caps = surface.get_capabilities(&adapter);
+                let format = surface_caps
+                    .formats
+                    .iter()
+                    .find(|f| f.is_srgb())
+                    .copied()
+                    .unwrap_or(surface_caps.formats[0]);
+                let present_mode = supported_present_mode(
+                    renderer_config.present_mode,
+                    &surface_caps.present_modes,
+                );
+                let alpha_mode = match renderer_config.alpha_mode {
+                    Some(mode) if surface_caps.alpha_modes.contains(&mode) => mode,
+                    Some(mode) => {
+                        log::warn!("Surface alpha mode {mode:?} is not supported");
+                        surface_caps.alpha_modes[0]
+                    }
+                    None => surface_caps.alpha_modes[0],
+                };
+                present_modes = surface_caps.present_modes;
+                (format, alpha_mode, present_mode)
+            }
+            None => (
+                CAPTURE_FORMAT,
+                wgpu::CompositeAlphaMode::Opaque,
+                wgpu::PresentMode::Fifo,
+            ),
+        };
 
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
+            width,
+            height,
+            present_mode,
+            alpha_mode,
+            view_formats: srgb_view_formats(&adapter, surface_format),
             desired_maximum_frame_latency: 2,
         };
+        let offscreen = surface
+            .is_none()
+            .then(|| create_offscreen_texture(&device, &config));
 
-        // Initialize uniforms
-        let uniforms = Uniforms::new();
-
-        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Uniform Buffer"),
-            contents: bytemuck::cast_slice(&[uniforms]),
+        // Per-view globals selected by dynamic offset, plus a storage buffer holding
+        // one model matrix per object
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let globals_stride = (std::mem::size_of::<GlobalsUniform>() as wgpu::BufferAddress)
+            .div_ceil(alignment)
+            * alignment;
+        let globals_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Globals Buffer"),
+            size: globals_stride * MAX_VIEWS as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let object_buffer = create_object_buffer(&device, INITIAL_OBJECT_CAPACITY);
+        let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Buffer"),
+            contents: bytemuck::cast_slice(&[LightUniform::from_light(
+                &DirectionalLight::default(),
+            )]),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
         // Create bind group layout
         let uniform_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<
+                                GlobalsUniform,
+                            >()
+                                as u64),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
                     },
-                    count: None,
-                }],
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
                 label: Some("uniform_bind_group_layout"),
             });
 
-        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &uniform_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: uniform_buffer.as_entire_binding(),
-            }],
-            label: Some("uniform_bind_group"),
-        });
+        let uniform_bind_group = create_uniform_bind_group(
+            &device,
+            &uniform_bind_group_layout,
+            &globals_buffer,
+            &object_buffer,
+            &light_buffer,
+        );
+
+        // Material textures (group 1), with a flat normal map as the fallback
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+                label: Some("material_texture_bind_group_layout"),
+            });
+        let default_texture_bind_group = create_texture_bind_group(
+            &device,
+            &texture_bind_group_layout,
+            &Texture::flat_normal_map(&device, &queue),
+        );
 
-        // Create shader and pipelines
+        // Create shader and pipeline layout; pipelines are created on first use
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Default Shader"),
             source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/default.wgsl").into()),
@@ -235,108 +1405,18 @@ impl Renderer {
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&uniform_bind_group_layout],
+                bind_group_layouts: &[&uniform_bind_group_layout, &texture_bind_group_layout],
                 push_constant_ranges: &[],
             });
 
-        // Triangle pipeline
-        let triangle_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Triangle Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[Vertex::desc()],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-            cache: None,
-        });
+        let depth_view = create_depth_view(&device, config.width, config.height, depth_format, 1);
 
-        // Line pipeline
-        let line_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Line Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[Vertex::desc()],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::LineList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None, // No culling for lines
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-            cache: None,
-        });
-
-        // Initialize view and projection matrices
-        let aspect = config.width as f32 / config.height as f32;
+        // Initialize the view matrix
         let current_view_matrix = Matrix4::look_at_rh(
             cgmath::Point3::new(10.0, 5.0, 10.0),
             cgmath::Point3::new(0.0, 0.0, 0.0),
             cgmath::Vector3::new(0.0, 1.0, 0.0),
         );
-        let current_proj_matrix = perspective(Deg(45.0), aspect, 0.1, 100.0);
 
         Ok(Self {
             device,
@@ -344,20 +1424,58 @@ impl Renderer {
             surface,
             config,
             window,
-            is_surface_configured: false,
-            triangle_pipeline,
-            line_pipeline,
-            uniform_buffer,
+            // A headless target is usable right away; a surface waits for the
+            // first resize
+            is_surface_configured: offscreen.is_some(),
+            present_modes,
+            offscreen,
+            scene_shader: shader,
+            scene_pipeline_layout: render_pipeline_layout,
+            capabilities,
+            wireframe: false,
+            pipeline_cache: HashMap::new(),
+            materials: Vec::new(),
+            #[cfg(feature = "shader-reload")]
+            shader_watcher: None,
+            target_depth: None,
+            globals_buffer,
+            globals_stride,
+            object_buffer,
+            light_buffer,
+            object_capacity: INITIAL_OBJECT_CAPACITY,
+            objects: Vec::with_capacity(INITIAL_OBJECT_CAPACITY),
+            uniform_bind_group_layout,
             uniform_bind_group,
-            uniforms,
+            texture_bind_group_layout,
+            default_texture_bind_group,
+            texture_bind_groups: HashMap::new(),
+            render_list: RenderList::default(),
+            depth_format,
+            depth_view,
             current_view_matrix,
-            current_proj_matrix,
-            clear_color: wgpu::Color {
-                r: 0.05,
-                g: 0.05,
-                b: 0.1,
-                a: 1.0,
-            },
+            lens: Lens::default(),
+            clear_color: Color::srgb(0.23, 0.23, 0.38),
+            viewport: None,
+            scissor: None,
+            stereo: None,
+            sky: None,
+            post_effects: PostEffects::default(),
+            frustum_culling: true,
+            culling_stats: CullingStats::default(),
+            frame_stats: RenderStats::default(),
+            render_stats: RenderStats::default(),
+            blitter: None,
+            sprites: None,
+            point_clouds: None,
+            debug_lines: None,
+            ribbons: None,
+            #[cfg(feature = "text")]
+            text: None,
+            render_targets: RenderTargets::default(),
+            shader_time: [0.0; 3],
+            pacer: FramePacer::default(),
+            id_buffer: None,
+            outlines: None,
         })
     }
 
@@ -366,18 +1484,267 @@ impl Renderer {
         if width > 0 && height > 0 {
             self.config.width = width;
             self.config.height = height;
-            self.surface.configure(&self.device, &self.config);
+            match &self.surface {
+                Some(surface) => surface.configure(&self.device, &self.config),
+                None => self.offscreen = Some(create_offscreen_texture(&self.device, &self.config)),
+            }
             self.is_surface_configured = true;
+            self.depth_view = create_depth_view(&self.device, width, height, self.depth_format, 1);
+        }
+    }
+
+    /// Camera projection for a target or viewport `aspect` (width / height)
+    /// wide, such as `Viewport::aspect` of a split-screen `ViewTarget`
+    pub fn projection(&self, aspect: f32) -> Matrix4<f32> {
+        self.lens.projection(aspect)
+    }
+
+    /// Projection for the scene in a target of `size` pixels, fitted to the
+    /// viewport when one is set
+    fn scene_projection(&self, size: (u32, u32)) -> Matrix4<f32> {
+        let viewport = self.viewport.unwrap_or(Viewport::full(size));
+        self.projection(viewport.aspect())
+    }
 
-            // Update projection matrix for new aspect ratio
-            let aspect = width as f32 / height as f32;
-            self.current_proj_matrix = perspective(Deg(45.0), aspect, 0.1, 100.0);
+    /// Format frames are drawn to the window (or headless target) in: an
+    /// sRGB format, or a view of one, whenever the adapter allows
+    pub fn output_format(&self) -> wgpu::TextureFormat {
+        self.config
+            .view_formats
+            .first()
+            .copied()
+            .unwrap_or(self.config.format)
+    }
+
+    /// Format of the depth buffer
+    pub fn depth_format(&self) -> wgpu::TextureFormat {
+        self.depth_format
+    }
+
+    /// Set the background color, as it should appear on screen (linear, like
+    /// every color; build it with `Color::srgb` or `Color::hex` from picked
+    /// values)
+    ///
+    /// The scene is cleared to whatever exposure and tonemapping turn into
+    /// this color, so the background is not tinted by them.
+    pub fn set_clear_color(&mut self, color: impl Into<Color>) {
+        self.clear_color = color.into();
+    }
+
+    /// Background color as it appears on screen
+    pub fn clear_color(&self) -> Color {
+        self.clear_color
+    }
+
+    /// HDR value to clear the scene to so `clear_color` survives exposure
+    /// and tonemapping
+    fn scene_clear_color(&self) -> wgpu::Color {
+        let tonemap = self.post_effects.tonemap();
+        let exposure = self.render_list.exposure.max(f32::EPSILON);
+        let [r, g, b, a] = self.clear_color.rgba();
+        let [r, g, b] = [r, g, b].map(|c| tonemap.invert(c) / exposure);
+        Color::linear_rgba(r, g, b, a).into()
+    }
+
+    /// Draw a cubemap or gradient sky behind all geometry instead of the clear color
+    pub fn set_skybox(&mut self, skybox: impl Into<Skybox>) {
+        let skybox = skybox.into();
+        // Cheap enough to call every frame for animated gradients
+        if let Some(sky) = &mut self.sky
+            && sky.update_gradient(&skybox)
+        {
+            return;
         }
+        self.sky = Some(SkyRenderer::new(
+            &self.device,
+            &self.queue,
+            skybox,
+            MAX_VIEWS,
+        ));
+    }
+
+    /// Remove the skybox, going back to the clear color
+    pub fn clear_skybox(&mut self) {
+        self.sky = None;
+    }
+
+    /// Current skybox
+    pub fn skybox(&self) -> Option<&Skybox> {
+        self.sky.as_ref().map(SkyRenderer::skybox)
+    }
+
+    /// Load an equirectangular PNG panorama as a cubemap for `set_skybox`
+    pub fn load_equirectangular_cubemap(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        face_size: u32,
+    ) -> Result<Cubemap> {
+        Cubemap::load_equirectangular(&self.device, &self.queue, path, face_size)
+    }
+
+    /// Enable two-view rendering with per-eye cameras, or `None` for mono
+    pub fn set_stereo(&mut self, stereo: Option<StereoConfig>) {
+        self.stereo = stereo;
+    }
+
+    /// Current stereo settings
+    pub fn stereo(&self) -> Option<&StereoConfig> {
+        self.stereo.as_ref()
+    }
+
+    /// Draw the scene into part of the window only, in window pixels, or
+    /// `None` for all of it; the rest shows the clear color
+    ///
+    /// The projection takes the viewport's aspect ratio, so a
+    /// `Viewport::letterboxed` scene keeps its framing at any window size.
+    /// Screen-anchored sprites and text still cover the whole window.
+    pub fn set_viewport(&mut self, viewport: Option<Viewport>) {
+        self.viewport = viewport;
+    }
+
+    /// Part of the window the scene is drawn into, if limited
+    pub fn viewport(&self) -> Option<Viewport> {
+        self.viewport
     }
 
-    /// Set the clear color
-    pub fn set_clear_color(&mut self, color: wgpu::Color) {
-        self.clear_color = color;
+    /// Clip the scene to a rectangle of the window, in window pixels, or
+    /// `None` to clip only to the viewport
+    ///
+    /// Unlike a viewport, a scissor rectangle cuts the image without
+    /// rescaling it, e.g. to leave a HUD region untouched.
+    pub fn set_scissor(&mut self, scissor: Option<ScissorRect>) {
+        self.scissor = scissor;
+    }
+
+    /// Rectangle the scene is clipped to, if any
+    pub fn scissor(&self) -> Option<ScissorRect> {
+        self.scissor
+    }
+
+    /// Scene viewport in the pixels of a target `scale` times the window's
+    fn scene_viewport(&self, scale: f32) -> Option<Viewport> {
+        self.viewport.map(|viewport| Viewport {
+            x: viewport.x * scale,
+            y: viewport.y * scale,
+            width: viewport.width * scale,
+            height: viewport.height * scale,
+            ..viewport
+        })
+    }
+
+    /// Apply the scene viewport and scissor to a pass drawing into a target
+    /// of `size` pixels, `scale` times the window's, narrowed to the `(x,
+    /// width)` fractions of a stereo eye
+    fn apply_scene_region(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        size: (u32, u32),
+        scale: f32,
+        eye: Option<(f32, f32)>,
+    ) {
+        let viewport = self.scene_viewport(scale);
+        if viewport.is_some() || eye.is_some() {
+            let viewport = viewport.unwrap_or(Viewport::full(size));
+            let (x, width) = eye.unwrap_or((0.0, 1.0));
+            Viewport {
+                x: viewport.x + x * viewport.width,
+                width: width * viewport.width,
+                ..viewport
+            }
+            .apply(render_pass);
+        }
+        if let Some(scissor) = self.scissor {
+            let scale = |v: u32| (v as f32 * scale).round() as u32;
+            ScissorRect::new(
+                scale(scissor.x),
+                scale(scissor.y),
+                scale(scissor.width),
+                scale(scissor.height),
+            )
+            .apply(render_pass, size);
+        }
+    }
+
+    /// Post-processing effects applied to each frame
+    pub fn post_effects(&self) -> &PostEffects {
+        &self.post_effects
+    }
+
+    /// Post-processing effects, to add, remove, or adjust them
+    pub fn post_effects_mut(&mut self) -> &mut PostEffects {
+        &mut self.post_effects
+    }
+
+    /// Pass the app clock to shaders through the globals block; `App` calls
+    /// this before each frame
+    pub fn update_time(&mut self, time: &TimeState) {
+        self.shader_time = [
+            time.elapsed_seconds(),
+            time.delta_seconds(),
+            time.frame_count() as f32,
+        ];
+    }
+
+    /// Skip drawing meshes whose bounds are outside the camera's view (on by
+    /// default); disable for custom shaders that move vertices far from the mesh
+    pub fn set_frustum_culling(&mut self, enabled: bool) {
+        self.frustum_culling = enabled;
+    }
+
+    /// Whether frustum culling is enabled
+    pub fn frustum_culling(&self) -> bool {
+        self.frustum_culling
+    }
+
+    /// Objects drawn and culled by the last render or capture
+    pub fn culling_stats(&self) -> CullingStats {
+        self.culling_stats
+    }
+
+    /// Draw calls, geometry, and uploads of the last rendered frame
+    pub fn render_stats(&self) -> RenderStats {
+        self.render_stats
+    }
+
+    /// Optional features the adapter supports, detected at startup
+    pub fn capabilities(&self) -> &GpuCapabilities {
+        &self.capabilities
+    }
+
+    /// Draw triangle meshes as their edges; stays off with a warning when
+    /// the adapter has no line polygon mode. Returns whether it is now on.
+    pub fn set_wireframe(&mut self, enabled: bool) -> bool {
+        if enabled && !self.capabilities.wireframe {
+            log::warn!(
+                "Wireframe is not supported by {}",
+                self.capabilities.adapter.name
+            );
+        }
+        self.wireframe = enabled && self.capabilities.wireframe;
+        self.wireframe
+    }
+
+    /// Whether triangle meshes are drawn as edges
+    pub fn wireframe(&self) -> bool {
+        self.wireframe
+    }
+
+    /// Views rendered this frame into a target of `size` pixels: the camera,
+    /// or one `(view, projection)` per eye
+    fn frame_views(&self, size: (u32, u32)) -> Vec<(Matrix4<f32>, Matrix4<f32>)> {
+        let view = self.current_view_matrix;
+        let viewport = self.viewport.unwrap_or(Viewport::full(size));
+        match &self.stereo {
+            None => vec![(view, self.projection(viewport.aspect()))],
+            Some(stereo) => {
+                // Each eye gets half the viewport width
+                let aspect = match stereo.mode.is_split() {
+                    true => viewport.aspect() / 2.0,
+                    false => viewport.aspect(),
+                };
+                stereo.eye_matrices(view, self.projection(aspect)).to_vec()
+            }
+        }
     }
 
     /// Create a mesh from vertices and indices
@@ -385,6 +1752,44 @@ impl Renderer {
         Mesh::new(&self.device, vertices, indices)
     }
 
+    /// Create the GPU mesh for CPU-side geometry
+    pub fn upload(&self, data: &MeshData) -> Mesh {
+        data.to_mesh(&self.device)
+    }
+
+    /// Create a mesh whose buffers can be rewritten (see `Mesh::new_dynamic`)
+    pub fn create_dynamic_mesh(&self, vertices: &[Vertex], indices: &[u16]) -> Mesh {
+        Mesh::new_dynamic(&self.device, vertices, indices)
+    }
+
+    /// Replace a mesh's geometry, reusing its buffers when the data fits
+    /// (see `Mesh::update`)
+    pub fn update_mesh(&self, mesh: &mut Mesh, vertices: &[Vertex], indices: &[u16]) {
+        mesh.update(&self.device, &self.queue, vertices, indices);
+    }
+
+    /// Create a line strip through `points` (see `Mesh::polyline`)
+    pub fn create_polyline(&self, points: &[Vector3<f32>], color: [f32; 3]) -> Mesh {
+        Mesh::polyline(&self.device, points, color)
+    }
+
+    /// Load a binary or ASCII STL file as a mesh with recomputed normals,
+    /// keeping its units and axes (see `Stl` for scaling and Z-up)
+    pub fn load_stl(&self, path: impl AsRef<std::path::Path>) -> Result<Mesh> {
+        let (vertices, indices) = Stl::load(path)?.build()?;
+        Ok(self.create_mesh(&vertices, &indices))
+    }
+
+    /// Load a color texture from a PNG file
+    pub fn load_texture(&self, path: impl AsRef<std::path::Path>) -> Result<Texture> {
+        Texture::load_png(&self.device, &self.queue, path)
+    }
+
+    /// Load a tangent-space normal map from a PNG file
+    pub fn load_normal_map(&self, path: impl AsRef<std::path::Path>) -> Result<Texture> {
+        Texture::load_normal_map(&self.device, &self.queue, path)
+    }
+
     /// Create a line mesh (useful for grids, wireframes, etc.)
     pub fn create_line_mesh(&self, vertices: &[Vertex], indices: &[u16]) -> Mesh {
         Mesh::new_with_topology(
@@ -400,9 +1805,27 @@ impl Renderer {
         self.current_view_matrix = view;
     }
 
-    /// Request a redraw
+    /// Set the field of view (vertical, in degrees) and clip planes
+    /// projections are built with (called with the active camera's)
+    pub fn update_lens(&mut self, fov: f32, near: f32, far: f32) {
+        self.lens = Lens { fov, near, far };
+    }
+
+    /// Request a redraw (headless renderers draw every frame regardless)
     pub fn request_redraw(&self) {
-        self.window.request_redraw();
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
+
+    /// True if this renderer draws into an offscreen texture instead of a window
+    pub fn is_headless(&self) -> bool {
+        self.surface.is_none()
+    }
+
+    /// Size of the window surface or headless target in pixels
+    pub fn size(&self) -> (u32, u32) {
+        (self.config.width, self.config.height)
     }
 
     /// Render the current frame
@@ -410,33 +1833,40 @@ impl Renderer {
         if !self.is_surface_configured {
             return Ok(());
         }
+        #[cfg(feature = "shader-reload")]
+        self.reload_changed_shaders();
 
-        // Use the stored view and projection matrices from the camera controller
-        let view_matrix = self.current_view_matrix;
-        let proj_matrix = self.current_proj_matrix;
-
-        let output = self.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-
-        // Create depth texture
-        let depth_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-            size: wgpu::Extent3d {
-                width: self.config.width,
-                height: self.config.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            label: Some("depth_texture"),
-            view_formats: &[],
+        self.ensure_frame_begun();
+        let acquire_start = std::time::Instant::now();
+        let output = match &self.surface {
+            Some(surface) => Some(surface.get_current_texture()?),
+            None => None,
+        };
+        self.record_acquire(acquire_start.elapsed());
+        let Some(target) = output
+            .as_ref()
+            .map(|output| &output.texture)
+            .or(self.offscreen.as_ref())
+        else {
+            return Ok(());
+        };
+        let output_format = self.output_format();
+        let view = target.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(output_format),
+            ..Default::default()
         });
+        let scene_view = self
+            .post_effects
+            .scene_view(&self.device, (self.config.width, self.config.height));
 
-        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.extract(world);
+        let views = self.frame_views((self.config.width, self.config.height));
+        let draws = self.prepare_scene(&views, (self.config.width, self.config.height));
+
+        let passes = match &self.stereo {
+            Some(stereo) => stereo.mode.passes().to_vec(),
+            None => vec![EyePass::mono()],
+        };
 
         let mut encoder = self
             .device
@@ -444,20 +1874,29 @@ impl Renderer {
                 label: Some("Render Encoder"),
             });
 
-        {
+        // One pass per eye; later eyes keep the color written so far but get
+        // a fresh depth buffer
+        for (pass_index, pass) in passes.iter().enumerate() {
+            let pipelines = self.bucket_pipelines(&draws, (HDR_FORMAT, 1, pass.write_mask));
+            let extras = self.extra_pipelines((HDR_FORMAT, 1, pass.write_mask));
+            let load = if pass_index == 0 {
+                wgpu::LoadOp::Clear(self.scene_clear_color())
+            } else {
+                wgpu::LoadOp::Load
+            };
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view: &scene_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(self.clear_color),
+                        load,
                         store: wgpu::StoreOp::Store,
                     },
                     depth_slice: None,
                 })],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &depth_view,
+                    view: &self.depth_view,
                     depth_ops: Some(wgpu::Operations {
                         load: wgpu::LoadOp::Clear(1.0),
                         store: wgpu::StoreOp::Store,
@@ -468,79 +1907,409 @@ impl Renderer {
                 timestamp_writes: None,
             });
 
-            // Group meshes by topology to minimize pipeline changes
-            let mut triangle_meshes = Vec::new();
-            let mut line_meshes = Vec::new();
-
-            for (entity_id, mesh) in world.query::<Mesh>() {
-                let model_matrix =
-                    if let Some(transform) = world.get_component::<Transform>(entity_id) {
-                        transform.matrix()
-                    } else {
-                        Matrix4::identity()
-                    };
-
-                match mesh.primitive_topology {
-                    wgpu::PrimitiveTopology::TriangleList => {
-                        triangle_meshes.push((mesh, model_matrix));
-                    }
-                    wgpu::PrimitiveTopology::LineList => {
-                        line_meshes.push((mesh, model_matrix));
-                    }
-                    _ => {
-                        // Handle other topologies as triangles for now
-                        triangle_meshes.push((mesh, model_matrix));
-                    }
-                }
+            self.apply_scene_region(
+                &mut render_pass,
+                (self.config.width, self.config.height),
+                1.0,
+                pass.viewport,
+            );
+            let drawn = self.draw_scene(
+                &mut render_pass,
+                &draws,
+                pass.view_index.min(views.len() - 1),
+                &pipelines,
+                &extras,
+            );
+            self.frame_stats += drawn;
+        }
+
+        // Stereo eyes have no single view to pick in
+        if self.stereo.is_none() {
+            self.draw_ids(&mut encoder, &draws);
+        }
+
+        let drawn = self.post_effects.run(
+            &self.device,
+            &self.queue,
+            &mut encoder,
+            &scene_view,
+            PostOutput {
+                view: &view,
+                format: output_format,
+                viewport: self.scene_viewport(1.0),
+                keep_outside: false,
+            },
+            self.render_list.exposure,
+        );
+        self.frame_stats += drawn;
+        if self.stereo.is_none() {
+            self.draw_outlines(&mut encoder, (&view, output_format), &draws);
+        }
+        self.draw_sprites(&mut encoder, (&view, output_format));
+        self.update_builtin_targets(&scene_view);
+
+        let submission = self.queue.submit(std::iter::once(encoder.finish()));
+        self.track_frame(submission);
+        self.map_id_readback();
+        self.render_stats = std::mem::take(&mut self.frame_stats);
+        if let Some(output) = output {
+            output.present();
+        }
+
+        Ok(())
+    }
+
+    /// Render the scene into each target from its own camera (at most two
+    /// targets, e.g. the eyes of a headset), without touching the window surface
+    pub fn render_to_targets(&mut self, world: &World, targets: &[ViewTarget<'_>]) {
+        let targets = &targets[..targets.len().min(MAX_VIEWS)];
+        if targets.is_empty() {
+            return;
+        }
+        let views: Vec<_> = targets
+            .iter()
+            .map(|target| (target.view, target.projection))
+            .collect();
+        self.extract(world);
+        let draws = self.prepare_scene(&views, (targets[0].width, targets[0].height));
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("View Target Encoder"),
+            });
+        // Both targets share one HDR scene texture, tonemapped into each in turn
+        let pipelines = self.bucket_pipelines(&draws, (HDR_FORMAT, 1, wgpu::ColorWrites::ALL));
+        let extras = self.extra_pipelines((HDR_FORMAT, 1, wgpu::ColorWrites::ALL));
+        for (view_index, target) in targets.iter().enumerate() {
+            let size = (target.width, target.height);
+            let scene_view = self.post_effects.scene_view(&self.device, size);
+            if self
+                .target_depth
+                .as_ref()
+                .is_none_or(|(cached, _)| *cached != size)
+            {
+                let view = create_depth_view(&self.device, size.0, size.1, self.depth_format, 1);
+                self.target_depth = Some((size, view));
             }
+            let Some((_, depth_view)) = &self.target_depth else {
+                continue;
+            };
 
-            // Render triangles
-            if !triangle_meshes.is_empty() {
-                render_pass.set_pipeline(&self.triangle_pipeline);
-
-                for (mesh, model_matrix) in triangle_meshes {
-                    self.uniforms.update_view_proj(view_matrix, proj_matrix);
-                    self.uniforms.update_model(model_matrix);
-                    self.queue.write_buffer(
-                        &self.uniform_buffer,
-                        0,
-                        bytemuck::cast_slice(&[self.uniforms]),
-                    );
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("View Target Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &scene_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.scene_clear_color()),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            if let Some(viewport) = target.viewport {
+                viewport.apply(&mut render_pass);
+            }
+            let drawn = self.draw_scene(&mut render_pass, &draws, view_index, &pipelines, &extras);
+            drop(render_pass);
+            self.frame_stats += drawn;
+            let drawn = self.post_effects.run(
+                &self.device,
+                &self.queue,
+                &mut encoder,
+                &scene_view,
+                PostOutput {
+                    view: target.target,
+                    format: target.format,
+                    viewport: target.viewport,
+                    keep_outside: target.viewport.is_some(),
+                },
+                self.render_list.exposure,
+            );
+            self.frame_stats += drawn;
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
 
-                    render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
-                    render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-                    render_pass
-                        .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-                    render_pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
-                }
+    /// Pipeline for each bucket in `draws`, in draw order
+    fn bucket_pipelines(
+        &mut self,
+        draws: &SceneDraws,
+        key: PipelineKey,
+    ) -> Vec<wgpu::RenderPipeline> {
+        let wireframe = self.wireframe;
+        draws
+            .in_order()
+            .map(|bucket| {
+                let id = PipelineId {
+                    wireframe: wireframe
+                        && bucket.pipeline.topology == wgpu::PrimitiveTopology::TriangleList,
+                    ..bucket.pipeline
+                };
+                self.pipeline(id, key)
+            })
+            .collect()
+    }
+
+    /// Scene pipeline for a shader, topology, and render target, created on first use
+    fn pipeline(&mut self, id: PipelineId, key: PipelineKey) -> wgpu::RenderPipeline {
+        self.pipeline_cache
+            .entry((id, key))
+            .or_insert_with(|| {
+                let (layout, shader) = match id.material {
+                    Some(index) => (
+                        &self.materials[index].pipeline_layout,
+                        &self.materials[index].shader,
+                    ),
+                    None => (&self.scene_pipeline_layout, &self.scene_shader),
+                };
+                create_scene_pipeline(&self.device, layout, shader, key, self.depth_format, id)
+            })
+            .clone()
+    }
+
+    /// Drop cached pipelines built from a material's shader (`None` for the default one)
+    fn clear_material_pipelines(&mut self, material: Option<usize>) {
+        self.pipeline_cache
+            .retain(|(id, _), _| id.material != material);
+    }
+
+    /// Skybox, `PointCloud`, `DebugDraw`, `Ribbon`, and `Text3d` pipelines
+    /// for a render target, for those in use
+    fn extra_pipelines(&mut self, key: PipelineKey) -> ExtraPipelines {
+        let device = &self.device;
+        let depth_format = self.depth_format;
+        ExtraPipelines {
+            sky: self
+                .sky
+                .as_mut()
+                .map(|sky| sky.pipeline(device, key, depth_format)),
+            point_clouds: self
+                .point_clouds
+                .as_mut()
+                .map(|clouds| clouds.pipeline(device, key, depth_format)),
+            debug_lines: self
+                .debug_lines
+                .as_mut()
+                .map(|lines| lines.pipeline(device, key, depth_format)),
+            ribbons: self
+                .ribbons
+                .as_mut()
+                .map(|ribbons| ribbons.pipeline(device, key, depth_format)),
+            #[cfg(feature = "text")]
+            text: self
+                .text
+                .as_mut()
+                .map(|text| text.pipeline(device, key, depth_format)),
+        }
+    }
+
+    /// Cull the extracted meshes against `views`, order transparent ones,
+    /// and upload per-view globals, light, and per-object data for a target of
+    /// `resolution` pixels
+    fn prepare_scene(
+        &mut self,
+        views: &[(Matrix4<f32>, Matrix4<f32>)],
+        resolution: (u32, u32),
+    ) -> SceneDraws {
+        // Each draw keeps the index of its first model matrix in the object buffer
+        self.objects.clear();
+        let mut draws = SceneDraws::default();
+        let mut culler = Culler::new(self.frustum_culling, views);
+        // Transparent meshes are sorted by distance from the first view's eye
+        let eye = views.first().map_or_else(
+            || self.camera_position(),
+            |(view, _)| view.invert().unwrap_or_else(Matrix4::identity).w.truncate(),
+        );
+        let distance = |model: &Matrix4<f32>, center: Vector3<f32>| {
+            (model * center.extend(1.0)).truncate().distance2(eye)
+        };
+        let mut transparent = Vec::new();
+
+        for (item_index, item) in self.render_list.items.iter().enumerate() {
+            let bounds = &item.bounds;
+            let objects = &self.render_list.objects[item.objects.clone()];
+            // Instanced meshes get one object slot per instance, in consecutive slots
+            let index = self.objects.len() as u32;
+            if item.instanced {
+                self.objects.extend(
+                    objects
+                        .iter()
+                        .filter(|object| culler.is_visible(bounds, &object.model.into())),
+                );
+            } else if culler.is_visible(bounds, &item.model) {
+                self.objects.extend_from_slice(objects);
             }
+            let count = self.objects.len() as u32 - index;
+            if count == 0 {
+                continue;
+            }
+            let draw = MeshDraw {
+                item: item_index,
+                index,
+                count,
+            };
+            if item.pipeline.transparent {
+                // Also order the instances within the draw
+                let center = bounds.center();
+                self.objects[index as usize..].sort_by(|a, b| {
+                    let (a, b) = (Matrix4::from(a.model), Matrix4::from(b.model));
+                    distance(&b, center).total_cmp(&distance(&a, center))
+                });
+                transparent.push((distance(&item.model, center), item.pipeline, draw));
+            } else {
+                draws.push(item.pipeline, draw);
+            }
+        }
+        // Farthest first, so nearer surfaces blend over them
+        transparent.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (_, id, draw) in transparent {
+            draws.push_transparent(id, draw);
+        }
 
-            // Render lines
-            if !line_meshes.is_empty() {
-                render_pass.set_pipeline(&self.line_pipeline);
-
-                for (mesh, model_matrix) in line_meshes {
-                    self.uniforms.update_view_proj(view_matrix, proj_matrix);
-                    self.uniforms.update_model(model_matrix);
-                    self.queue.write_buffer(
-                        &self.uniform_buffer,
-                        0,
-                        bytemuck::cast_slice(&[self.uniforms]),
-                    );
+        self.culling_stats = culler.stats;
+        self.frame_stats.write_buffer(
+            &self.queue,
+            &self.light_buffer,
+            0,
+            bytemuck::cast_slice(&[self.render_list.lights]),
+        );
+        self.upload_views(views, resolution);
+        self.upload_objects();
+        if let Some(sky) = &self.sky {
+            sky.upload(&self.queue, &mut self.frame_stats, views);
+        }
+        draws
+    }
 
-                    render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
-                    render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-                    render_pass
-                        .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-                    render_pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
-                }
+    /// Record the sky, the draws prepared by `prepare_scene`, point clouds,
+    /// debug lines, ribbons, and any `Text3d` into a render pass
+    fn draw_scene(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        draws: &SceneDraws,
+        view_index: usize,
+        pipelines: &[wgpu::RenderPipeline],
+        extras: &ExtraPipelines,
+    ) -> RenderStats {
+        let mut stats = RenderStats::default();
+        if let (Some(sky), Some(pipeline)) = (&self.sky, &extras.sky) {
+            sky.draw(
+                render_pass,
+                pipeline,
+                view_index.min(MAX_VIEWS - 1),
+                &mut stats,
+            );
+        }
+
+        let globals_offset = (view_index.min(MAX_VIEWS - 1) as wgpu::BufferAddress
+            * self.globals_stride) as wgpu::DynamicOffset;
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[globals_offset]);
+
+        for (bucket, pipeline) in draws.in_order().zip(pipelines) {
+            render_pass.set_pipeline(pipeline);
+            for draw in &bucket.draws {
+                Self::draw_mesh(
+                    render_pass,
+                    &self.render_list.items[draw.item],
+                    draw,
+                    &mut stats,
+                );
             }
         }
+        if let (Some(clouds), Some(pipeline)) = (&self.point_clouds, &extras.point_clouds) {
+            clouds.draw(render_pass, pipeline, &mut stats);
+        }
+        if let (Some(lines), Some(pipeline)) = (&self.debug_lines, &extras.debug_lines) {
+            lines.draw(render_pass, pipeline, &mut stats);
+        }
+        if let (Some(ribbons), Some(pipeline)) = (&self.ribbons, &extras.ribbons) {
+            ribbons.draw(render_pass, pipeline, &mut stats);
+        }
+        #[cfg(feature = "text")]
+        if let (Some(text), Some(pipeline)) = (&self.text, &extras.text) {
+            text.draw(render_pass, pipeline, &mut stats);
+        }
+        stats
+    }
 
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+    /// World-space camera position derived from the current view matrix
+    fn camera_position(&self) -> Vector3<f32> {
+        self.current_view_matrix
+            .invert()
+            .unwrap_or_else(Matrix4::identity)
+            .w
+            .truncate()
+    }
 
-        Ok(())
+    /// Write one globals slot per view, for a target of `resolution` pixels
+    fn upload_views(&mut self, views: &[(Matrix4<f32>, Matrix4<f32>)], resolution: (u32, u32)) {
+        for (index, &(view, proj)) in views.iter().take(MAX_VIEWS).enumerate() {
+            self.frame_stats.write_buffer(
+                &self.queue,
+                &self.globals_buffer,
+                index as wgpu::BufferAddress * self.globals_stride,
+                bytemuck::cast_slice(&[GlobalsUniform::new(
+                    view,
+                    proj,
+                    self.shader_time,
+                    resolution,
+                )]),
+            );
+        }
+    }
+
+    /// Write all queued object data, growing the object buffer if needed
+    fn upload_objects(&mut self) {
+        if self.objects.len() > self.object_capacity {
+            self.object_capacity = self.objects.len().next_power_of_two();
+            self.object_buffer = create_object_buffer(&self.device, self.object_capacity);
+            self.uniform_bind_group = create_uniform_bind_group(
+                &self.device,
+                &self.uniform_bind_group_layout,
+                &self.globals_buffer,
+                &self.object_buffer,
+                &self.light_buffer,
+            );
+        }
+
+        if !self.objects.is_empty() {
+            self.frame_stats.write_buffer(
+                &self.queue,
+                &self.object_buffer,
+                0,
+                bytemuck::cast_slice(&self.objects),
+            );
+        }
+    }
+
+    /// Issue an indexed draw whose instance index selects its slot in the object buffer
+    fn draw_mesh(
+        render_pass: &mut wgpu::RenderPass<'_>,
+        item: &RenderItem,
+        draw: &MeshDraw,
+        stats: &mut RenderStats,
+    ) {
+        let (mesh, index, count) = (&item.mesh, draw.index, draw.count);
+        render_pass.set_bind_group(1, &item.textures, &[]);
+        if let Some(material) = &item.material {
+            render_pass.set_bind_group(2, material, &[]);
+        }
+        render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..mesh.num_indices, 0, index..index + count);
+        stats.record_indexed(item.pipeline.topology, mesh.num_indices, count);
     }
 
     /// Get the wgpu device (for advanced users)
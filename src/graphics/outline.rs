@@ -0,0 +1,445 @@
+//! Colored outlines around selected entities
+//!
+//! Meshes of entities with an `Outlined` component are drawn into a mask,
+//! without depth testing so hidden parts still count, and a full-screen pass
+//! colors the pixels around each silhouette over the tonemapped frame,
+//! before sprites.
+//!
+//! ```no_run
+//! use qsi::graphics::{GpuPicking, GpuPickingPlugin, Outlined};
+//! use qsi::prelude::*;
+//! use winit::event::MouseButton;
+//!
+//! App::new()
+//!     .add_plugin(GpuPickingPlugin)
+//!     .add_system(|world: &mut World, input: &InputState, _: &TimeState| {
+//!         if !input.mouse_button_just_pressed(MouseButton::Left) {
+//!             return;
+//!         }
+//!         let selected: Vec<_> = world.query::<Outlined>().map(|(entity, _)| entity).collect();
+//!         for entity in selected {
+//!             world.remove_component::<Outlined>(entity);
+//!         }
+//!         if let Some(entity) = world.resource::<GpuPicking>().and_then(GpuPicking::hovered) {
+//!             world.add_component(entity, Outlined::default());
+//!         }
+//!     })
+//!     .run()
+//!     .unwrap();
+//! ```
+
+use super::{RenderTarget, Renderer, SceneDraws, Vertex, post::HDR_FORMAT};
+use crate::ecs::Component;
+use std::collections::HashMap;
+
+/// Widest outline drawn, in pixels; the composite searches this far around
+/// each pixel
+pub const MAX_OUTLINE_WIDTH: f32 = 16.0;
+
+/// Outline drawn around an entity's mesh, such as to mark it selected
+///
+/// Shown through other geometry, and around every instance of an entity with
+/// `Instances`. Only the window's frame gets outlines, not stereo eyes or
+/// `Renderer::render_to_targets`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outlined {
+    /// Linear RGB color
+    pub color: [f32; 3],
+    /// Thickness in physical pixels, up to `MAX_OUTLINE_WIDTH`
+    pub width: f32,
+}
+
+impl Component for Outlined {}
+
+impl Default for Outlined {
+    /// A 3 pixel orange outline
+    fn default() -> Self {
+        Self {
+            color: [1.0, 0.35, 0.0],
+            width: 3.0,
+        }
+    }
+}
+
+impl Outlined {
+    /// Outline of `color`, 3 pixels wide
+    pub fn new(color: [f32; 3]) -> Self {
+        Self {
+            color,
+            ..Self::default()
+        }
+    }
+
+    /// Set the thickness in pixels
+    pub fn with_width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+}
+
+/// Pipelines, buffers, and the mask of the outline passes
+pub(crate) struct OutlineRenderer {
+    mask_layout: wgpu::PipelineLayout,
+    mask_shader: wgpu::ShaderModule,
+    mask_pipelines: HashMap<wgpu::PrimitiveTopology, wgpu::RenderPipeline>,
+    outlines_layout: wgpu::BindGroupLayout,
+    // Color and width of each object slot (zero width for none)
+    outlines: Vec<[f32; 4]>,
+    outlines_buffer: wgpu::Buffer,
+    outlines_bind_group: wgpu::BindGroup,
+    mask: Option<RenderTarget>,
+    composite_layout: wgpu::BindGroupLayout,
+    composite_pipeline_layout: wgpu::PipelineLayout,
+    composite_shader: wgpu::ShaderModule,
+    composite_pipelines: HashMap<wgpu::TextureFormat, wgpu::RenderPipeline>,
+    params_buffer: wgpu::Buffer,
+    // Recreated with the mask
+    composite_bind_group: Option<wgpu::BindGroup>,
+}
+
+impl OutlineRenderer {
+    fn new(device: &wgpu::Device, uniform_layout: &wgpu::BindGroupLayout) -> Self {
+        let outlines_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("outline_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let mask_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Outline Mask Pipeline Layout"),
+            bind_group_layouts: &[uniform_layout, &outlines_layout],
+            push_constant_ranges: &[],
+        });
+        let mask_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Outline Mask Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/outline_mask.wgsl").into()),
+        });
+        let (outlines_buffer, outlines_bind_group) =
+            create_outlines_buffer(device, &outlines_layout, 64);
+
+        let composite_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("outline_composite_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let composite_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Outline Pipeline Layout"),
+                bind_group_layouts: &[&composite_layout],
+                push_constant_ranges: &[],
+            });
+        let composite_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Outline Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/outline.wgsl").into()),
+        });
+        // Padded to 16 bytes for uniform layout on every backend
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Outline Params Buffer"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            mask_layout,
+            mask_shader,
+            mask_pipelines: HashMap::new(),
+            outlines_layout,
+            outlines: Vec::new(),
+            outlines_buffer,
+            outlines_bind_group,
+            mask: None,
+            composite_layout,
+            composite_pipeline_layout,
+            composite_shader,
+            composite_pipelines: HashMap::new(),
+            params_buffer,
+            composite_bind_group: None,
+        }
+    }
+
+    fn mask_pipeline(&mut self, device: &wgpu::Device, topology: wgpu::PrimitiveTopology) {
+        self.mask_pipelines.entry(topology).or_insert_with(|| {
+            // Strips restart like the scene's; no culling, as only coverage counts
+            let strip_index_format = (topology == wgpu::PrimitiveTopology::LineStrip)
+                .then_some(wgpu::IndexFormat::Uint16);
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Outline Mask Pipeline"),
+                layout: Some(&self.mask_layout),
+                vertex: wgpu::VertexState {
+                    module: &self.mask_shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[Vertex::desc()],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &self.mask_shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(HDR_FORMAT.into())],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology,
+                    strip_index_format,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        });
+    }
+
+    fn composite_pipeline(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        self.composite_pipelines.entry(format).or_insert_with(|| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Outline Pipeline"),
+                layout: Some(&self.composite_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &self.composite_shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &self.composite_shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        });
+    }
+}
+
+/// Storage buffer for `capacity` object outlines and its bind group
+fn create_outlines_buffer(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    capacity: usize,
+) -> (wgpu::Buffer, wgpu::BindGroup) {
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Outline Buffer"),
+        size: (capacity * std::mem::size_of::<[f32; 4]>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("outline_bind_group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+    });
+    (buffer, bind_group)
+}
+
+impl Renderer {
+    /// Draw the outlines of this frame's `Outlined` meshes over `target`
+    pub(super) fn draw_outlines(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        (target, format): (&wgpu::TextureView, wgpu::TextureFormat),
+        draws: &SceneDraws,
+    ) {
+        let outlined = |item: usize| self.render_list.items[item].outline;
+        if !draws.in_order().any(|bucket| {
+            bucket
+                .draws
+                .iter()
+                .any(|draw| outlined(draw.item).is_some())
+        }) {
+            return;
+        }
+        let size = (self.config.width, self.config.height);
+        let device = &self.device;
+        let renderer = self.outlines.get_or_insert_with(|| {
+            Box::new(OutlineRenderer::new(
+                device,
+                &self.uniform_bind_group_layout,
+            ))
+        });
+
+        // Outline of each object slot the scene draws assigned
+        renderer.outlines.clear();
+        renderer
+            .outlines
+            .resize(self.objects.len().max(1), [0.0; 4]);
+        let mut widest = 0.0f32;
+        for bucket in draws.in_order() {
+            for draw in &bucket.draws {
+                let Some(outline) = self.render_list.items[draw.item].outline else {
+                    continue;
+                };
+                let width = outline.width.clamp(0.0, MAX_OUTLINE_WIDTH);
+                let [r, g, b] = outline.color;
+                renderer.outlines[draw.index as usize..(draw.index + draw.count) as usize]
+                    .fill([r, g, b, width]);
+                widest = widest.max(width);
+            }
+            renderer.mask_pipeline(device, bucket.pipeline.topology);
+        }
+        if widest <= 0.0 {
+            return;
+        }
+        if renderer.outlines.len() * std::mem::size_of::<[f32; 4]>()
+            > renderer.outlines_buffer.size() as usize
+        {
+            (renderer.outlines_buffer, renderer.outlines_bind_group) = create_outlines_buffer(
+                device,
+                &renderer.outlines_layout,
+                renderer.outlines.len().next_power_of_two(),
+            );
+        }
+        self.frame_stats.write_buffer(
+            &self.queue,
+            &renderer.outlines_buffer,
+            0,
+            bytemuck::cast_slice(&renderer.outlines),
+        );
+        self.frame_stats.write_buffer(
+            &self.queue,
+            &renderer.params_buffer,
+            0,
+            bytemuck::cast_slice(&[widest.ceil() as i32, 0, 0, 0]),
+        );
+        if renderer
+            .mask
+            .as_ref()
+            .is_none_or(|mask| mask.size() != size)
+        {
+            let mask = RenderTarget::new(device, "Outline Mask", size, HDR_FORMAT);
+            renderer.composite_bind_group =
+                Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("outline_composite_bind_group"),
+                    layout: &renderer.composite_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&mask.view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: renderer.params_buffer.as_entire_binding(),
+                        },
+                    ],
+                }));
+            renderer.mask = Some(mask);
+        }
+        renderer.composite_pipeline(device, format);
+
+        // Borrowed again immutably for the passes
+        let Some(renderer) = self.outlines.as_deref() else {
+            return;
+        };
+        let (Some(mask), Some(composite_bind_group), Some(composite_pipeline)) = (
+            &renderer.mask,
+            &renderer.composite_bind_group,
+            renderer.composite_pipelines.get(&format),
+        ) else {
+            return;
+        };
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Outline Mask Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &mask.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            self.apply_scene_region(&mut render_pass, size, 1.0, None);
+            // The first view's globals, as the mono scene pass uses
+            render_pass.set_bind_group(0, &self.uniform_bind_group, &[0]);
+            render_pass.set_bind_group(1, &renderer.outlines_bind_group, &[]);
+            for bucket in draws.in_order() {
+                let Some(pipeline) = renderer.mask_pipelines.get(&bucket.pipeline.topology) else {
+                    continue;
+                };
+                render_pass.set_pipeline(pipeline);
+                for draw in &bucket.draws {
+                    let item = &self.render_list.items[draw.item];
+                    if item.outline.is_none() {
+                        continue;
+                    }
+                    render_pass.set_vertex_buffer(0, item.mesh.vertex_buffer.slice(..));
+                    render_pass.set_index_buffer(
+                        item.mesh.index_buffer.slice(..),
+                        wgpu::IndexFormat::Uint16,
+                    );
+                    render_pass.draw_indexed(
+                        0..item.mesh.num_indices,
+                        0,
+                        draw.index..draw.index + draw.count,
+                    );
+                }
+            }
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Outline Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        // Fragment positions stay in target pixels, so the viewport only clips
+        self.apply_scene_region(&mut render_pass, size, 1.0, None);
+        render_pass.set_pipeline(composite_pipeline);
+        render_pass.set_bind_group(0, composite_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
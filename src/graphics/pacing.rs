@@ -0,0 +1,224 @@
+//! Frame pacing and frame latency measurement
+//!
+//! With the default pacing the CPU may queue a couple of frames ahead of the
+//! display, which keeps the GPU busy but lets input wait behind those frames.
+//! `FramePacing::low_latency` trades some throughput for responsiveness: one
+//! queued frame, and the CPU waits for the GPU before starting the next one.
+//!
+//! ```rust,no_run
+//! use qsi::graphics::FramePacing;
+//! use qsi::prelude::*;
+//!
+//! App::new()
+//!     .with_frame_pacing(FramePacing::low_latency().with_max_frame_rate(Some(120.0)))
+//!     .add_render_system(|_: &mut World, renderer: &mut Renderer, _: &TimeState| {
+//!         let stats = renderer.latency_stats();
+//!         println!("latency {:.1} ms", stats.mean.as_secs_f64() * 1000.0);
+//!     })
+//!     .run()
+//!     .unwrap();
+//! ```
+
+use super::Renderer;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Frames kept for `LatencyStats::mean` and `LatencyStats::max`
+const LATENCY_WINDOW: usize = 120;
+
+/// How far the CPU may run ahead of the display
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FramePacing {
+    /// Frames the surface may queue before presenting (1–3); fewer frames
+    /// means lower latency but less overlap between CPU and GPU work
+    pub max_frame_latency: u32,
+    /// Wait until the GPU finished the previous frame before acquiring the
+    /// next surface texture, so no frame queues behind another
+    pub wait_for_gpu: bool,
+    /// Sleep before acquiring a surface texture to cap the frame rate
+    pub max_frame_rate: Option<f64>,
+}
+
+impl Default for FramePacing {
+    fn default() -> Self {
+        Self {
+            max_frame_latency: 2,
+            wait_for_gpu: false,
+            max_frame_rate: None,
+        }
+    }
+}
+
+impl FramePacing {
+    /// One queued frame, started only once the GPU is done with the last one
+    pub fn low_latency() -> Self {
+        Self {
+            max_frame_latency: 1,
+            wait_for_gpu: true,
+            max_frame_rate: None,
+        }
+    }
+
+    /// Set the number of frames the surface may queue
+    pub fn with_max_frame_latency(mut self, frames: u32) -> Self {
+        self.max_frame_latency = frames;
+        self
+    }
+
+    /// Set whether to wait for the GPU before each frame
+    pub fn with_wait_for_gpu(mut self, wait: bool) -> Self {
+        self.wait_for_gpu = wait;
+        self
+    }
+
+    /// Set the frame rate cap (`None` for uncapped)
+    pub fn with_max_frame_rate(mut self, frame_rate: Option<f64>) -> Self {
+        self.max_frame_rate = frame_rate;
+        self
+    }
+}
+
+/// Frame latency over the last frames the GPU finished
+///
+/// Latency runs from the start of a frame (`Renderer::begin_frame`) until the
+/// GPU finished its work. Completion is noticed
+/// when the renderer next checks the device, so readings can run up to a frame
+/// long unless `FramePacing::wait_for_gpu` is on.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LatencyStats {
+    /// Frames finished since the renderer was created
+    pub frames: u64,
+    /// Latency of the most recently finished frame
+    pub latest: Duration,
+    /// Mean latency over the last 120 finished frames
+    pub mean: Duration,
+    /// Highest latency over the last 120 finished frames
+    pub max: Duration,
+    /// Time the last frame spent waiting for the GPU or the frame rate cap
+    pub throttle: Duration,
+    /// Time the last frame blocked acquiring a surface texture
+    pub acquire: Duration,
+}
+
+/// Pacing settings and latency bookkeeping of a renderer
+#[derive(Default)]
+pub(crate) struct FramePacer {
+    pacing: FramePacing,
+    frame_start: Option<Instant>,
+    last_acquire: Option<Instant>,
+    last_submission: Option<wgpu::SubmissionIndex>,
+    /// Latencies reported by GPU completion callbacks, not yet counted
+    finished: Arc<Mutex<Vec<Duration>>>,
+    window: VecDeque<Duration>,
+    stats: LatencyStats,
+}
+
+impl Renderer {
+    /// Change how frames are paced; takes effect on the next frame
+    pub fn set_frame_pacing(&mut self, pacing: FramePacing) {
+        let latency = pacing.max_frame_latency.clamp(1, 3);
+        if latency != self.config.desired_maximum_frame_latency {
+            self.config.desired_maximum_frame_latency = latency;
+            if let (Some(surface), true) = (&self.surface, self.is_surface_configured) {
+                surface.configure(&self.device, &self.config);
+            }
+        }
+        self.pacer.pacing = pacing;
+    }
+
+    /// Current frame pacing
+    pub fn frame_pacing(&self) -> FramePacing {
+        self.pacer.pacing
+    }
+
+    /// Latency of recently finished frames
+    pub fn latency_stats(&self) -> LatencyStats {
+        self.pacer.stats
+    }
+
+    /// Start a frame before reading input: wait as the pacing policy asks,
+    /// then mark the start for `latency_stats`
+    ///
+    /// `App` calls this each frame; otherwise `render` does.
+    pub fn begin_frame(&mut self) {
+        self.pace_frame();
+        self.pacer.frame_start = Some(Instant::now());
+    }
+
+    /// `begin_frame`, unless it was called since the last `render`
+    pub(crate) fn ensure_frame_begun(&mut self) {
+        if self.pacer.frame_start.is_none() {
+            self.begin_frame();
+        }
+    }
+
+    /// Apply the pacing policy and count frames the GPU finished since the
+    /// last call
+    fn pace_frame(&mut self) {
+        let start = Instant::now();
+        if self.pacer.pacing.wait_for_gpu
+            && let Some(index) = self.pacer.last_submission.take()
+        {
+            let _ = self
+                .device
+                .poll(wgpu::PollType::WaitForSubmissionIndex(index));
+        } else {
+            let _ = self.device.poll(wgpu::PollType::Poll);
+        }
+        if let (Some(rate), Some(last)) =
+            (self.pacer.pacing.max_frame_rate, self.pacer.last_acquire)
+            && rate > 0.0
+        {
+            let next = last + Duration::from_secs_f64(1.0 / rate);
+            let now = Instant::now();
+            if next > now {
+                std::thread::sleep(next - now);
+            }
+        }
+        self.pacer.stats.throttle = start.elapsed();
+        self.pacer.last_acquire = Some(Instant::now());
+        self.pacer.count_finished();
+    }
+
+    /// Record how long acquiring the surface texture blocked
+    pub(crate) fn record_acquire(&mut self, waited: Duration) {
+        self.pacer.stats.acquire = waited;
+    }
+
+    /// Track a submitted frame until the GPU finishes it
+    pub(crate) fn track_frame(&mut self, index: wgpu::SubmissionIndex) {
+        let start = self.pacer.frame_start.take().unwrap_or_else(Instant::now);
+        let finished = self.pacer.finished.clone();
+        self.queue.on_submitted_work_done(move || {
+            if let Ok(mut finished) = finished.lock() {
+                finished.push(start.elapsed());
+            }
+        });
+        self.pacer.last_submission = Some(index);
+    }
+}
+
+impl FramePacer {
+    /// Fold latencies reported since the last call into the stats
+    fn count_finished(&mut self) {
+        let finished = match self.finished.lock() {
+            Ok(mut finished) => std::mem::take(&mut *finished),
+            Err(_) => return,
+        };
+        if finished.is_empty() {
+            return;
+        }
+        for latency in finished {
+            if self.window.len() == LATENCY_WINDOW {
+                self.window.pop_front();
+            }
+            self.window.push_back(latency);
+            self.stats.frames += 1;
+            self.stats.latest = latency;
+        }
+        let total: Duration = self.window.iter().sum();
+        self.stats.mean = total / self.window.len() as u32;
+        self.stats.max = self.window.iter().copied().max().unwrap_or_default();
+    }
+}
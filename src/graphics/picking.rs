@@ -0,0 +1,100 @@
+//! Finding the mesh entity under the cursor by casting rays
+
+use super::{Instances, Mesh, MeshData, Visibility};
+use crate::assets::{Assets, Handle};
+use crate::ecs::{EntityId, Without, World};
+use crate::math::{Aabb, Matrix4, Ray, Transform, Vector3};
+use cgmath::SquareMatrix;
+
+/// Where a picking ray met a mesh entity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PickHit {
+    pub entity: EntityId,
+    /// Index into the entity's `Instances`, if it has them
+    pub instance: Option<usize>,
+    /// World-space hit point
+    pub point: Vector3<f32>,
+    /// Distance from the ray origin along its direction
+    pub distance: f32,
+}
+
+impl World {
+    /// Nearest visible mesh entity along `ray`
+    ///
+    /// Meshes are tested by their bounds (the entity's `Aabb` if it has one),
+    /// transformed with the entity and each of its instances. Entities that also keep their geometry as a
+    /// `MeshData` component are tested triangle by triangle.
+    pub fn pick(&self, ray: &Ray) -> Option<PickHit> {
+        self.pick_all(ray).into_iter().next()
+    }
+
+    /// Every visible mesh entity along `ray`, nearest first
+    pub fn pick_all(&self, ray: &Ray) -> Vec<PickHit> {
+        self.pick_where(ray, |_, _| true)
+    }
+
+    /// Every visible mesh entity along `ray` that `filter` accepts, nearest
+    /// first; rejected entities cost no intersection tests
+    pub(crate) fn pick_where(
+        &self,
+        ray: &Ray,
+        filter: impl Fn(EntityId, &Mesh) -> bool,
+    ) -> Vec<PickHit> {
+        let meshes = self.resource::<Assets<Mesh>>();
+        let shared = self
+            .query_filtered::<Handle<Mesh>, Without<Mesh>>()
+            .filter_map(|(entity, &handle)| Some((entity, meshes?.get(handle)?)));
+        let mut hits = Vec::new();
+        for (entity, mesh) in self.query::<Mesh>().chain(shared) {
+            if !filter(entity, mesh)
+                || self.get_component::<Visibility>(entity) == Some(&Visibility::Hidden)
+            {
+                continue;
+            }
+            let model = self
+                .get_component::<Transform>(entity)
+                .map_or_else(Matrix4::identity, Transform::matrix);
+            let bounds = self.get_component::<Aabb>(entity).unwrap_or(&mesh.bounds);
+            let data = self.get_component::<MeshData>(entity);
+            let hit = |model: Matrix4<f32>, instance| {
+                let distance = intersect(ray, bounds, data, &model.invert()?)?;
+                Some(PickHit {
+                    entity,
+                    instance,
+                    point: ray.at(distance),
+                    distance,
+                })
+            };
+            match self.get_component::<Instances>(entity) {
+                Some(instances) => {
+                    hits.extend(instances.instances.iter().enumerate().filter_map(
+                        |(index, instance)| hit(model * instance.transform.matrix(), Some(index)),
+                    ));
+                }
+                None => hits.extend(hit(model, None)),
+            }
+        }
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        hits
+    }
+}
+
+/// Distance along `ray` to a mesh with `bounds` placed by the inverse of
+/// `inverse_model`
+fn intersect(
+    ray: &Ray,
+    bounds: &Aabb,
+    data: Option<&MeshData>,
+    inverse_model: &Matrix4<f32>,
+) -> Option<f32> {
+    // Distances along the untransformed direction match the world ray's
+    let local = ray.transformed(inverse_model);
+    let entry = local.intersect_aabb(bounds)?;
+    match data.filter(|data| data.topology == wgpu::PrimitiveTopology::TriangleList) {
+        Some(data) => data
+            .triangles()
+            .filter_map(|triangle| local.intersect_triangle(triangle))
+            .min_by(f32::total_cmp),
+        None => Some(entry),
+    }
+}
@@ -0,0 +1,611 @@
+//! Meshes and point clouds from PLY files, as written by scanners and
+//! photogrammetry tools
+
+use super::extrude::finish_mesh;
+use super::{Mesh, Point, PointCloud, Renderer, Vertex};
+use anyhow::{Context, Result, bail};
+use std::f32::consts::PI;
+use std::path::Path;
+
+/// Vertices, optional per-vertex normals and colors, and faces read from a
+/// PLY file
+///
+/// ASCII and both binary variants are read. Files with faces become meshes;
+/// files with only vertices, typical of scans, become point clouds. Elements
+/// and properties other than positions, normals, colors, and face indices are
+/// skipped, and polygons are split into triangle fans.
+///
+/// ```rust,ignore
+/// let scan = Ply::load("scans/statue.ply")?;
+/// let cloud = renderer.create_point_cloud(&scan.points()).with_point_size(2.0);
+/// world.spawn().with(Transform::default()).with(cloud);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ply {
+    positions: Vec<[f32; 3]>,
+    normals: Option<Vec<[f32; 3]>>,
+    colors: Option<Vec<[u8; 4]>>,
+    triangles: Vec<[u32; 3]>,
+    /// Faces meeting at less than this angle in radians are shaded smoothly
+    /// when the file has no normals; the default π smooths everything
+    pub smoothing_angle: f32,
+    /// Color of vertices when the file has none
+    pub color: [u8; 4],
+}
+
+impl Ply {
+    /// Read a PLY file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_bytes(&bytes).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Parse the contents of a PLY file
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (header, body) = parse_header(bytes)?;
+        let mut body = match header.format {
+            Format::Ascii => Body::Ascii {
+                words: std::str::from_utf8(body)
+                    .context("ASCII PLY body is not valid UTF-8")?
+                    .split_ascii_whitespace(),
+                len: body.len(),
+            },
+            Format::Binary { big_endian } => Body::Binary {
+                bytes: body,
+                offset: 0,
+                big_endian,
+            },
+        };
+
+        let mut ply = Self {
+            positions: Vec::new(),
+            normals: None,
+            colors: None,
+            triangles: Vec::new(),
+            smoothing_angle: PI,
+            color: [255; 4],
+        };
+        for element in &header.elements {
+            match element.name.as_str() {
+                "vertex" => ply.read_vertices(element, &mut body)?,
+                "face" => ply.read_faces(element, &mut body)?,
+                // Elements without properties take no space, whatever their count
+                _ if element.properties.is_empty() => {}
+                _ => {
+                    for _ in 0..element.count {
+                        for property in &element.properties {
+                            body.skip(property)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        let count = ply.positions.len() as u32;
+        if let Some(triangle) = ply.triangles.iter().find(|t| t.iter().any(|&i| i >= count)) {
+            bail!("Face {triangle:?} refers past the {count} vertices");
+        }
+        Ok(ply)
+    }
+
+    /// Set the angle below which faces are shaded smoothly (0 for flat)
+    pub fn with_smoothing_angle(mut self, radians: f32) -> Self {
+        self.smoothing_angle = radians;
+        self
+    }
+
+    /// Set the color of vertices when the file has none
+    pub fn with_color(mut self, color: [u8; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Number of vertices
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Number of triangles after splitting polygons; 0 for point clouds
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    /// Whether the file has faces, and so describes a mesh
+    pub fn is_mesh(&self) -> bool {
+        !self.triangles.is_empty()
+    }
+
+    /// Whether the file has per-vertex colors
+    pub fn has_colors(&self) -> bool {
+        self.colors.is_some()
+    }
+
+    /// Whether the file has per-vertex normals
+    pub fn has_normals(&self) -> bool {
+        self.normals.is_some()
+    }
+
+    /// Every vertex as a point with its color, ignoring faces
+    pub fn points(&self) -> Vec<Point> {
+        self.positions
+            .iter()
+            .enumerate()
+            .map(|(i, &position)| Point::new(position, self.vertex_color(i)))
+            .collect()
+    }
+
+    /// Generate vertices and triangle indices, using the file's normals or
+    /// computing them when it has none
+    ///
+    /// Fails without faces, or with more than 65 536 vertices; show large
+    /// scans as point clouds instead.
+    pub fn build_mesh(&self) -> Result<(Vec<Vertex>, Vec<u16>)> {
+        if !self.is_mesh() {
+            bail!("PLY file has no faces; load it as a point cloud instead");
+        }
+        let vertices = self
+            .positions
+            .iter()
+            .enumerate()
+            .map(|(i, &position)| {
+                let color = self.vertex_color(i);
+                Vertex::new(position, [0, 1, 2].map(|c| color[c] as f32 / 255.0))
+            })
+            .collect();
+        let indices = self.triangles.iter().flatten().copied().collect();
+        let Some(normals) = &self.normals else {
+            return finish_mesh(vertices, indices, self.smoothing_angle);
+        };
+        if self.positions.len() > u16::MAX as usize + 1 {
+            bail!(
+                "Mesh has {} vertices, more than 16-bit indices allow",
+                self.positions.len()
+            );
+        }
+        let vertices = vertices
+            .into_iter()
+            .zip(normals)
+            .map(|(vertex, &normal)| vertex.with_normal(normal))
+            .collect();
+        Ok((vertices, indices.into_iter().map(|i| i as u16).collect()))
+    }
+
+    fn vertex_color(&self, index: usize) -> [u8; 4] {
+        self.colors
+            .as_ref()
+            .map_or(self.color, |colors| colors[index])
+    }
+
+    fn read_vertices(&mut self, element: &Element, body: &mut Body) -> Result<()> {
+        let slots: Vec<_> = element
+            .properties
+            .iter()
+            .map(|property| match property {
+                Property::Scalar { name, kind } => Slot::of(name, *kind),
+                Property::List { .. } => None,
+            })
+            .collect();
+        let count =
+            |wanted: fn(&Slot) -> bool| slots.iter().flatten().filter(|s| wanted(s)).count();
+        if count(|slot| matches!(slot, Slot::Position(_))) < 3 {
+            bail!("PLY vertices need x, y, and z properties");
+        }
+        let with_normals = count(|slot| matches!(slot, Slot::Normal(_))) > 0;
+        let with_colors = count(|slot| matches!(slot, Slot::Color(..))) > 0;
+
+        // The count comes from the file, so reserve no more than the data can hold
+        self.positions
+            .reserve(element.count.min(body.max_records(&element.properties)));
+        let mut normals = Vec::new();
+        let mut colors = Vec::new();
+        for _ in 0..element.count {
+            let mut position = [0.0; 3];
+            let mut normal = [0.0; 3];
+            let mut color = [255; 4];
+            for (property, slot) in element.properties.iter().zip(&slots) {
+                let Some(slot) = slot else {
+                    body.skip(property)?;
+                    continue;
+                };
+                let Property::Scalar { kind, .. } = property else {
+                    unreachable!("list properties have no slot");
+                };
+                let value = body.read(*kind)?;
+                match *slot {
+                    Slot::Position(axis) => position[axis] = value as f32,
+                    Slot::Normal(axis) => normal[axis] = value as f32,
+                    Slot::Color(channel, scale) => {
+                        color[channel] = (value * scale).round().clamp(0.0, 255.0) as u8;
+                    }
+                }
+            }
+            self.positions.push(position);
+            if with_normals {
+                normals.push(normal);
+            }
+            if with_colors {
+                colors.push(color);
+            }
+        }
+        self.normals = with_normals.then_some(normals);
+        self.colors = with_colors.then_some(colors);
+        Ok(())
+    }
+
+    fn read_faces(&mut self, element: &Element, body: &mut Body) -> Result<()> {
+        if element.properties.is_empty() {
+            return Ok(());
+        }
+        let mut polygon = Vec::new();
+        for _ in 0..element.count {
+            for property in &element.properties {
+                match property {
+                    Property::List { name, count, item }
+                        if name == "vertex_indices" || name == "vertex_index" =>
+                    {
+                        let length = body.read_index(*count)? as usize;
+                        polygon.clear();
+                        for _ in 0..length {
+                            polygon.push(body.read_index(*item)?);
+                        }
+                        for i in 1..length.saturating_sub(1) {
+                            self.triangles
+                                .push([polygon[0], polygon[i], polygon[i + 1]]);
+                        }
+                    }
+                    _ => body.skip(property)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Renderer {
+    /// Load a PLY file with faces as a mesh (see `Ply::build_mesh`)
+    pub fn load_ply_mesh(&self, path: impl AsRef<Path>) -> Result<Mesh> {
+        let (vertices, indices) = Ply::load(path)?.build_mesh()?;
+        Ok(self.create_mesh(&vertices, &indices))
+    }
+
+    /// Load the vertices of a PLY file as a point cloud, ignoring any faces
+    pub fn load_ply_point_cloud(&self, path: impl AsRef<Path>) -> Result<PointCloud> {
+        Ok(self.create_point_cloud(&Ply::load(path)?.points()))
+    }
+}
+
+enum Format {
+    Ascii,
+    Binary { big_endian: bool },
+}
+
+/// Scalar property type
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Kind {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => bail!("Unknown PLY property type `{name}`"),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+}
+
+enum Property {
+    Scalar {
+        name: String,
+        kind: Kind,
+    },
+    List {
+        name: String,
+        count: Kind,
+        item: Kind,
+    },
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+}
+
+/// Where a vertex property goes
+enum Slot {
+    Position(usize),
+    Normal(usize),
+    /// Channel and factor to 0–255
+    Color(usize, f64),
+}
+
+impl Slot {
+    fn of(name: &str, kind: Kind) -> Option<Self> {
+        let channel = |channel| {
+            let scale = match kind {
+                Kind::F32 | Kind::F64 => 255.0,
+                Kind::U16 => 255.0 / 65535.0,
+                _ => 1.0,
+            };
+            Some(Self::Color(channel, scale))
+        };
+        match name {
+            "x" => Some(Self::Position(0)),
+            "y" => Some(Self::Position(1)),
+            "z" => Some(Self::Position(2)),
+            "nx" => Some(Self::Normal(0)),
+            "ny" => Some(Self::Normal(1)),
+            "nz" => Some(Self::Normal(2)),
+            "red" | "diffuse_red" | "r" => channel(0),
+            "green" | "diffuse_green" | "g" => channel(1),
+            "blue" | "diffuse_blue" | "b" => channel(2),
+            "alpha" | "a" => channel(3),
+            _ => None,
+        }
+    }
+}
+
+/// Split the header from the data after `end_header`
+fn parse_header(bytes: &[u8]) -> Result<(Header, &[u8])> {
+    if !bytes.starts_with(b"ply") {
+        bail!("Not a PLY file: missing `ply` magic");
+    }
+    let marker = b"end_header";
+    let end = bytes
+        .windows(marker.len())
+        .position(|window| window == marker)
+        .context("PLY header has no `end_header`")?;
+    let body_start = bytes[end..]
+        .iter()
+        .position(|&byte| byte == b'\n')
+        .map_or(bytes.len(), |newline| end + newline + 1);
+    let text = std::str::from_utf8(&bytes[..end]).context("PLY header is not valid UTF-8")?;
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for (number, line) in text.lines().enumerate().skip(1) {
+        let words: Vec<_> = line.split_whitespace().collect();
+        let context = || format!("Line {}: `{}`", number + 1, line.trim());
+        match words.as_slice() {
+            ["format", "ascii", ..] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", ..] => {
+                format = Some(Format::Binary { big_endian: false })
+            }
+            ["format", "binary_big_endian", ..] => {
+                format = Some(Format::Binary { big_endian: true })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().with_context(context)?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => elements
+                .last_mut()
+                .with_context(context)?
+                .properties
+                .push(Property::List {
+                    name: name.to_string(),
+                    count: Kind::parse(count).with_context(context)?,
+                    item: Kind::parse(item).with_context(context)?,
+                }),
+            ["property", kind, name] => {
+                elements
+                    .last_mut()
+                    .with_context(context)?
+                    .properties
+                    .push(Property::Scalar {
+                        name: name.to_string(),
+                        kind: Kind::parse(kind).with_context(context)?,
+                    })
+            }
+            [] | ["comment", ..] | ["obj_info", ..] => {}
+            _ => bail!("{}: not a PLY header line", context()),
+        }
+    }
+    let format = format.context("PLY header has no format line")?;
+    Ok((Header { format, elements }, &bytes[body_start..]))
+}
+
+/// Data after the header
+enum Body<'a> {
+    Ascii {
+        words: std::str::SplitAsciiWhitespace<'a>,
+        /// Length of the whole body in bytes
+        len: usize,
+    },
+    Binary {
+        bytes: &'a [u8],
+        offset: usize,
+        big_endian: bool,
+    },
+}
+
+impl Body<'_> {
+    fn read(&mut self, kind: Kind) -> Result<f64> {
+        match self {
+            Self::Ascii { words, .. } => words
+                .next()
+                .context("PLY data ends early")?
+                .parse()
+                .context("PLY data has a value that is not a number"),
+            Self::Binary {
+                bytes,
+                offset,
+                big_endian,
+            } => {
+                let size = kind.size();
+                let mut raw = [0; 8];
+                raw[..size].copy_from_slice(
+                    bytes
+                        .get(*offset..*offset + size)
+                        .context("PLY data ends early")?,
+                );
+                *offset += size;
+                if *big_endian {
+                    raw[..size].reverse();
+                }
+                let [a, b, c, d, ..] = raw;
+                Ok(match kind {
+                    Kind::I8 => a as i8 as f64,
+                    Kind::U8 => a as f64,
+                    Kind::I16 => i16::from_le_bytes([a, b]) as f64,
+                    Kind::U16 => u16::from_le_bytes([a, b]) as f64,
+                    Kind::I32 => i32::from_le_bytes([a, b, c, d]) as f64,
+                    Kind::U32 => u32::from_le_bytes([a, b, c, d]) as f64,
+                    Kind::F32 => f32::from_le_bytes([a, b, c, d]) as f64,
+                    Kind::F64 => f64::from_le_bytes(raw),
+                })
+            }
+        }
+    }
+
+    /// Read a list length or vertex index, which must be a whole,
+    /// non-negative 32-bit number
+    fn read_index(&mut self, kind: Kind) -> Result<u32> {
+        let value = self.read(kind)?;
+        if value < 0.0 || value > u32::MAX as f64 || value.fract() != 0.0 {
+            bail!("PLY data has {value} where an index or count belongs");
+        }
+        Ok(value as u32)
+    }
+
+    /// Most records with these properties the rest of the data could hold
+    fn max_records(&self, properties: &[Property]) -> usize {
+        match self {
+            // At least one character and a separator per value
+            Self::Ascii { len, .. } => (len + 1) / (2 * properties.len().max(1)),
+            Self::Binary { bytes, offset, .. } => {
+                let record: usize = properties
+                    .iter()
+                    .map(|property| match property {
+                        Property::Scalar { kind, .. } => kind.size(),
+                        Property::List { count, .. } => count.size(),
+                    })
+                    .sum();
+                bytes.len().saturating_sub(*offset) / record.max(1)
+            }
+        }
+    }
+
+    fn skip(&mut self, property: &Property) -> Result<()> {
+        match property {
+            Property::Scalar { kind, .. } => {
+                self.read(*kind)?;
+            }
+            Property::List { count, item, .. } => {
+                for _ in 0..self.read_index(*count)? {
+                    self.read(*item)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIANGLE_HEADER: &str = "ply\nformat ascii 1.0\nelement vertex 3\n\
+        property float x\nproperty float y\nproperty float z\n\
+        element face 1\nproperty list uchar int vertex_indices\nend_header\n";
+
+    fn binary_header(vertices: &str) -> Vec<u8> {
+        format!(
+            "ply\nformat binary_little_endian 1.0\nelement vertex {vertices}\n\
+             property float x\nproperty float y\nproperty float z\nend_header\n"
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn reads_ascii_triangle() {
+        let text = format!("{TRIANGLE_HEADER}0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n");
+        let ply = Ply::from_bytes(text.as_bytes()).unwrap();
+        assert_eq!(ply.vertex_count(), 3);
+        assert_eq!(ply.triangle_count(), 1);
+    }
+
+    #[test]
+    fn truncated_ascii_body_is_an_error() {
+        let text = format!("{TRIANGLE_HEADER}0 0 0\n1 0 0\n0 1\n");
+        assert!(Ply::from_bytes(text.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn truncated_binary_body_is_an_error() {
+        let mut bytes = binary_header("2");
+        for value in [0.0f32, 0.0, 0.0, 1.0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        assert!(Ply::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn negative_face_index_is_an_error() {
+        let text = format!("{TRIANGLE_HEADER}0 0 0\n1 0 0\n0 1 0\n3 0 -1 2\n");
+        assert!(Ply::from_bytes(text.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn out_of_range_face_index_is_an_error() {
+        let text = format!("{TRIANGLE_HEADER}0 0 0\n1 0 0\n0 1 0\n3 0 1 3\n");
+        assert!(Ply::from_bytes(text.as_bytes()).is_err());
+        let text = format!("{TRIANGLE_HEADER}0 0 0\n1 0 0\n0 1 0\n3 0 1 1e12\n");
+        assert!(Ply::from_bytes(text.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn huge_declared_count_is_an_error_not_an_abort() {
+        let mut bytes = binary_header("99999999999");
+        bytes.extend_from_slice(&[0; 12]);
+        assert!(Ply::from_bytes(&bytes).is_err());
+
+        let text = "ply\nformat ascii 1.0\nelement vertex 99999999999\n\
+            property float x\nproperty float y\nproperty float z\nend_header\n0 0 0\n";
+        assert!(Ply::from_bytes(text.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn huge_count_of_empty_elements_is_skipped() {
+        let text = format!(
+            "ply\nformat ascii 1.0\nelement junk 99999999999\n{}0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n",
+            TRIANGLE_HEADER.trim_start_matches("ply\nformat ascii 1.0\n")
+        );
+        assert_eq!(
+            Ply::from_bytes(text.as_bytes()).unwrap().triangle_count(),
+            1
+        );
+    }
+}
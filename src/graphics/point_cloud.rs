@@ -0,0 +1,336 @@
+//! Point clouds drawn as screen-space squares or dots
+
+use super::{PipelineKey, RenderStats, Renderer};
+use crate::ecs::{Component, World};
+use crate::math::{Aabb, Matrix4, Transform};
+use cgmath::SquareMatrix;
+use std::collections::HashMap;
+use wgpu::util::DeviceExt;
+
+/// One point of a `PointCloud`: a position and an 8-bit RGBA color
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Point {
+    pub position: [f32; 3],
+    pub color: [u8; 4],
+}
+
+impl Point {
+    /// Point at `position` with color `color`
+    pub fn new(position: [f32; 3], color: [u8; 4]) -> Self {
+        Self { position, color }
+    }
+
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Unorm8x4];
+}
+
+/// Component drawing a large set of points, such as a LIDAR scan, each a
+/// fixed number of pixels across
+///
+/// The points live in a GPU buffer uploaded once, 16 bytes each, so clouds of
+/// millions of points cost nothing per frame until they change. Points are in
+/// the entity's local space when it has a `Transform`. Cloning is cheap and
+/// shares the buffer.
+///
+/// ```rust,ignore
+/// let points: Vec<Point> = scan.iter().map(|p| Point::new(p.xyz, p.rgba)).collect();
+/// let cloud = renderer.create_point_cloud(&points).with_point_size(3.0);
+/// world.spawn().with(Transform::default()).with(cloud);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PointCloud {
+    pub buffer: wgpu::Buffer,
+    pub num_points: u32,
+    /// Width of each point in pixels
+    pub point_size: f32,
+    /// Draw points as circles instead of squares
+    pub round: bool,
+    /// Local-space bounds of the points
+    pub bounds: Aabb,
+}
+
+impl Component for PointCloud {}
+
+impl PointCloud {
+    /// Upload `points` as square points 2 pixels wide
+    pub fn new(device: &wgpu::Device, points: &[Point]) -> Self {
+        Self {
+            buffer: create_point_buffer(device, points),
+            num_points: points.len() as u32,
+            point_size: 2.0,
+            round: false,
+            bounds: point_bounds(points),
+        }
+    }
+
+    /// Set the width of each point in pixels
+    pub fn with_point_size(mut self, point_size: f32) -> Self {
+        self.point_size = point_size;
+        self
+    }
+
+    /// Draw points as circles instead of squares
+    pub fn with_round(mut self, round: bool) -> Self {
+        self.round = round;
+        self
+    }
+
+    /// Replace the points, reusing the buffer when they fit
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, points: &[Point]) {
+        let size = std::mem::size_of_val(points) as wgpu::BufferAddress;
+        if size > self.buffer.size() {
+            self.buffer = create_point_buffer(device, points);
+        } else {
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(points));
+        }
+        self.num_points = points.len() as u32;
+        self.bounds = point_bounds(points);
+    }
+}
+
+fn create_point_buffer(device: &wgpu::Device, points: &[Point]) -> wgpu::Buffer {
+    // An empty cloud still needs a bindable buffer
+    let placeholder = [Point::new([0.0; 3], [0; 4])];
+    let contents = if points.is_empty() {
+        &placeholder[..]
+    } else {
+        points
+    };
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Point Cloud Buffer"),
+        contents: bytemuck::cast_slice(contents),
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+fn point_bounds(points: &[Point]) -> Aabb {
+    Aabb::from_points(points.iter().map(|point| point.position)).unwrap_or_default()
+}
+
+/// Per-cloud settings, one slot per cloud selected by dynamic offset
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct CloudUniform {
+    model: [[f32; 4]; 4],
+    /// x: point size in pixels, y: 1 for round points
+    params: [f32; 4],
+}
+
+/// Pipelines and per-cloud uniforms for every `PointCloud`, created once
+/// clouds appear
+pub(crate) struct PointCloudRenderer {
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipelines: HashMap<PipelineKey, wgpu::RenderPipeline>,
+    uniforms: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    stride: wgpu::BufferAddress,
+    capacity: usize,
+    // Buffer and point count of each cloud drawn this frame, in slot order
+    clouds: Vec<(wgpu::Buffer, u32)>,
+}
+
+impl PointCloudRenderer {
+    fn new(device: &wgpu::Device, uniform_layout: &wgpu::BindGroupLayout) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Point Cloud Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/point_cloud.wgsl").into()),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<CloudUniform>() as u64
+                    ),
+                },
+                count: None,
+            }],
+            label: Some("point_cloud_bind_group_layout"),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Point Cloud Pipeline Layout"),
+            bind_group_layouts: &[uniform_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let stride = (std::mem::size_of::<CloudUniform>() as wgpu::BufferAddress)
+            .div_ceil(alignment)
+            * alignment;
+        let capacity = 16;
+        let (uniforms, bind_group) = create_uniforms(device, &bind_group_layout, stride, capacity);
+        Self {
+            shader,
+            pipeline_layout,
+            bind_group_layout,
+            pipelines: HashMap::new(),
+            uniforms,
+            bind_group,
+            stride,
+            capacity,
+            clouds: Vec::new(),
+        }
+    }
+
+    /// Pipeline for the given target, created on first use
+    pub(crate) fn pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        (format, samples, write_mask): PipelineKey,
+        depth_format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        self.pipelines
+            .entry((format, samples, write_mask))
+            .or_insert_with(|| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("Point Cloud Pipeline"),
+                    layout: Some(&self.pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &self.shader,
+                        entry_point: Some("vs_main"),
+                        // One quad per point
+                        buffers: &[wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<Point>() as wgpu::BufferAddress,
+                            step_mode: wgpu::VertexStepMode::Instance,
+                            attributes: &Point::ATTRIBUTES,
+                        }],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &self.shader,
+                        entry_point: Some("fs_main"),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: Some(wgpu::BlendState::REPLACE),
+                            write_mask,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: depth_format,
+                        depth_write_enabled: true,
+                        depth_compare: wgpu::CompareFunction::Less,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        count: samples,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    multiview: None,
+                    cache: None,
+                })
+            })
+            .clone()
+    }
+
+    /// Draw the clouds gathered by `Renderer::prepare_point_clouds`, with the
+    /// scene bind group already set
+    pub(crate) fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        pipeline: &wgpu::RenderPipeline,
+        stats: &mut RenderStats,
+    ) {
+        if self.clouds.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(pipeline);
+        for (slot, (buffer, count)) in self.clouds.iter().enumerate() {
+            let offset = (slot as wgpu::BufferAddress * self.stride) as wgpu::DynamicOffset;
+            render_pass.set_bind_group(1, &self.bind_group, &[offset]);
+            render_pass.set_vertex_buffer(0, buffer.slice(..));
+            render_pass.draw(0..6, 0..*count);
+            stats.record_draw(wgpu::PrimitiveTopology::TriangleList, 6, *count);
+        }
+    }
+}
+
+fn create_uniforms(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    stride: wgpu::BufferAddress,
+    capacity: usize,
+) -> (wgpu::Buffer, wgpu::BindGroup) {
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Point Cloud Uniforms"),
+        size: stride * capacity as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer: &buffer,
+                offset: 0,
+                size: wgpu::BufferSize::new(std::mem::size_of::<CloudUniform>() as u64),
+            }),
+        }],
+        label: Some("point_cloud_bind_group"),
+    });
+    (buffer, bind_group)
+}
+
+impl Renderer {
+    /// Upload `points` into a new `PointCloud`
+    pub fn create_point_cloud(&self, points: &[Point]) -> PointCloud {
+        PointCloud::new(&self.device, points)
+    }
+
+    /// Replace the points of `cloud`, reusing its buffer when they fit
+    pub fn update_point_cloud(&self, cloud: &mut PointCloud, points: &[Point]) {
+        cloud.update(&self.device, &self.queue, points);
+    }
+
+    /// Gather this frame's `PointCloud`s and upload their settings
+    pub(crate) fn prepare_point_clouds(&mut self, world: &World) {
+        let clouds: Vec<_> = world
+            .query::<PointCloud>()
+            .filter(|(_, cloud)| cloud.num_points > 0)
+            .collect();
+        if clouds.is_empty() && self.point_clouds.is_none() {
+            return;
+        }
+        let renderer = self.point_clouds.get_or_insert_with(|| {
+            PointCloudRenderer::new(&self.device, &self.uniform_bind_group_layout)
+        });
+        if clouds.len() > renderer.capacity {
+            renderer.capacity = clouds.len().next_power_of_two();
+            (renderer.uniforms, renderer.bind_group) = create_uniforms(
+                &self.device,
+                &renderer.bind_group_layout,
+                renderer.stride,
+                renderer.capacity,
+            );
+        }
+
+        renderer.clouds.clear();
+        for (slot, (entity, cloud)) in clouds.into_iter().enumerate() {
+            let model = world
+                .get_component::<Transform>(entity)
+                .map_or_else(Matrix4::identity, Transform::matrix);
+            let uniform = CloudUniform {
+                model: model.into(),
+                params: [cloud.point_size, f32::from(u8::from(cloud.round)), 0.0, 0.0],
+            };
+            self.frame_stats.write_buffer(
+                &self.queue,
+                &renderer.uniforms,
+                slot as wgpu::BufferAddress * renderer.stride,
+                bytemuck::bytes_of(&uniform),
+            );
+            renderer
+                .clouds
+                .push((cloud.buffer.clone(), cloud.num_points));
+        }
+    }
+}
@@ -0,0 +1,723 @@
+//! HDR output and post-processing
+//!
+//! The scene is drawn into an `HDR_FORMAT` texture, so colors may exceed 1.0.
+//! Each post effect reads the previous result, then a tonemapping pass scaled
+//! by the `Exposure` resource maps it into the window (or capture target).
+//!
+//! ```rust,ignore
+//! renderer.post_effects_mut().push(Bloom::default());
+//! renderer.post_effects_mut().push(Vignette::default());
+//! world.insert_resource(Exposure::from_stops(-1.0));
+//! ```
+//!
+//! A camera entity carrying its own `PostEffects` component renders with that
+//! stack instead of the renderer's:
+//!
+//! ```rust,ignore
+//! let mut post = PostEffects::default();
+//! post.push(Vignette::default());
+//! world.add_component(camera_entity, post);
+//! ```
+
+use super::{RenderStats, Renderer, ScissorRect, Viewport};
+use crate::ecs::{Component, EntityId, World};
+use std::any::Any;
+use std::collections::HashMap;
+use wgpu::util::DeviceExt;
+
+/// Format of the offscreen scene texture that post effects read
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Resource scaling scene brightness before tonemapping; 1.0 when absent
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Exposure {
+    pub value: f32,
+}
+
+impl Default for Exposure {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl Exposure {
+    /// Multiply scene colors by `value`
+    pub fn new(value: f32) -> Self {
+        Self { value }
+    }
+
+    /// Exposure in photographic stops: each stop doubles the brightness
+    pub fn from_stops(stops: f32) -> Self {
+        Self::new(stops.exp2())
+    }
+}
+
+/// One step of the post-processing stack
+///
+/// Implement this for custom effects: record render passes on
+/// `frame.encoder` that read `frame.input` and fill `frame.output`.
+pub trait PostEffect: Any + Send + Sync {
+    fn apply(&mut self, frame: &mut PostFrame<'_>);
+}
+
+/// What an effect reads and writes for the current frame
+pub struct PostFrame<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub encoder: &'a mut wgpu::CommandEncoder,
+    /// Result so far, in `HDR_FORMAT`, bindable as a texture
+    pub input: &'a wgpu::TextureView,
+    /// Where the effect writes, in `HDR_FORMAT`; every pixel must be covered
+    pub output: &'a wgpu::TextureView,
+    /// Size of `input` and `output` in pixels
+    pub size: (u32, u32),
+    /// Part of the frame the scene was drawn into, when it does not fill it
+    /// (see `Renderer::set_viewport`); effects can `apply` it, or its
+    /// `scissor`, to their passes to work on that region alone
+    pub viewport: Option<Viewport>,
+    passes: &'a mut PostPasses,
+}
+
+impl PostFrame<'_> {
+    fn draw(&mut self, pass: Pass<'_>) {
+        self.passes.draw(self.device, self.encoder, pass);
+    }
+}
+
+/// Ordered list of effects applied to every frame, followed by tonemapping
+///
+/// The renderer owns the default stack; added to the active camera entity as a
+/// component, it replaces that stack for the camera's view.
+#[derive(Default)]
+pub struct PostEffects {
+    effects: Vec<Box<dyn PostEffect>>,
+    tonemap: TonemapOperator,
+    passes: Option<PostPasses>,
+    // Scene texture and two ping-pong textures, at the last size used
+    targets: Option<((u32, u32), Vec<wgpu::TextureView>)>,
+}
+
+impl Component for PostEffects {}
+
+impl PostEffects {
+    /// Append an effect to the end of the stack
+    pub fn push(&mut self, effect: impl PostEffect) {
+        self.effects.push(Box::new(effect));
+    }
+
+    /// Insert an effect at `index`
+    pub fn insert(&mut self, index: usize, effect: impl PostEffect) {
+        self.effects.insert(index, Box::new(effect));
+    }
+
+    /// Remove and return the effect at `index`
+    pub fn remove(&mut self, index: usize) -> Option<Box<dyn PostEffect>> {
+        (index < self.effects.len()).then(|| self.effects.remove(index))
+    }
+
+    /// Remove all effects (tonemapping still applies)
+    pub fn clear(&mut self) {
+        self.effects.clear();
+    }
+
+    /// Number of effects
+    pub fn len(&self) -> usize {
+        self.effects.len()
+    }
+
+    /// True if no effects are applied
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    /// First effect of type `T`
+    pub fn get<T: PostEffect>(&self) -> Option<&T> {
+        self.effects
+            .iter()
+            .find_map(|effect| (effect.as_ref() as &dyn Any).downcast_ref())
+    }
+
+    /// First effect of type `T`, mutably (e.g. to animate its settings)
+    pub fn get_mut<T: PostEffect>(&mut self) -> Option<&mut T> {
+        self.effects
+            .iter_mut()
+            .find_map(|effect| (effect.as_mut() as &mut dyn Any).downcast_mut())
+    }
+
+    /// Curve used to map HDR colors to the display
+    pub fn tonemap(&self) -> TonemapOperator {
+        self.tonemap
+    }
+
+    /// Choose the tonemapping curve
+    pub fn set_tonemap(&mut self, operator: TonemapOperator) {
+        self.tonemap = operator;
+    }
+
+    /// HDR texture the scene is drawn into before the effects run
+    pub(crate) fn scene_view(
+        &mut self,
+        device: &wgpu::Device,
+        size: (u32, u32),
+    ) -> wgpu::TextureView {
+        self.targets(device, size)[0].clone()
+    }
+
+    /// Run every effect on the HDR scene in `input`, then tonemap the
+    /// result into `output`; returns what the built-in passes drew
+    pub(crate) fn run(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &wgpu::TextureView,
+        output: PostOutput<'_>,
+        exposure: f32,
+    ) -> RenderStats {
+        let size = (input.texture().width(), input.texture().height());
+        let targets = self.targets(device, size).to_vec();
+        let Self {
+            effects,
+            tonemap,
+            passes,
+            ..
+        } = self;
+        let passes = passes.get_or_insert_with(|| PostPasses::new(device));
+        let mut input = input.clone();
+        for (index, effect) in effects.iter_mut().enumerate() {
+            let target = targets[1 + index % 2].clone();
+            effect.apply(&mut PostFrame {
+                device,
+                queue,
+                encoder,
+                input: &input,
+                output: &target,
+                size,
+                viewport: output.viewport,
+                passes,
+            });
+            input = target;
+        }
+
+        let operator = match tonemap {
+            TonemapOperator::Aces => 0.0,
+            TonemapOperator::Reinhard => 1.0,
+            TonemapOperator::Clamp => 2.0,
+        };
+        passes.draw(
+            device,
+            encoder,
+            Pass {
+                entry_point: "fs_tonemap",
+                params: [[exposure, operator, 0.0, 0.0], [0.0; 4]],
+                source: &input,
+                extra: None,
+                target: output.view,
+                format: output.format,
+                additive: false,
+                region: output
+                    .viewport
+                    .filter(|_| output.keep_outside)
+                    .map(|viewport| viewport.scissor()),
+            },
+        );
+        std::mem::take(&mut passes.drawn)
+    }
+
+    fn targets(&mut self, device: &wgpu::Device, size: (u32, u32)) -> &[wgpu::TextureView] {
+        if self
+            .targets
+            .as_ref()
+            .is_none_or(|(cached, _)| *cached != size)
+        {
+            let views = ["Post Scene", "Post Ping", "Post Pong"].map(|label| {
+                create_hdr_texture(device, label, size, 1).create_view(&Default::default())
+            });
+            self.targets = Some((size, views.to_vec()));
+        }
+        self.targets.as_ref().map_or(&[], |(_, views)| views)
+    }
+}
+
+/// Where `PostEffects::run` tonemaps the frame to
+pub(crate) struct PostOutput<'a> {
+    pub view: &'a wgpu::TextureView,
+    pub format: wgpu::TextureFormat,
+    // Part of the frame holding the scene, passed on to effects
+    pub viewport: Option<Viewport>,
+    // Write only inside `viewport`, keeping what `view` holds elsewhere
+    pub keep_outside: bool,
+}
+
+/// Exchange the renderer's post stack with the one on `camera`, if it has
+/// one; calling it again swaps them back
+pub(crate) fn swap_camera_post_effects(
+    world: &mut World,
+    renderer: &mut Renderer,
+    camera: Option<EntityId>,
+) {
+    if let Some(post) = camera.and_then(|camera| world.get_component_mut::<PostEffects>(camera)) {
+        std::mem::swap(post, renderer.post_effects_mut());
+    }
+}
+
+fn create_hdr_texture(
+    device: &wgpu::Device,
+    label: &str,
+    (width, height): (u32, u32),
+    mip_level_count: u32,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: HDR_FORMAT,
+        // Copyable for `Renderer::dump_render_targets`
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+/// One fullscreen draw of the built-in post shader
+struct Pass<'v> {
+    entry_point: &'static str,
+    params: [[f32; 4]; 2],
+    source: &'v wgpu::TextureView,
+    // Second texture for passes combining two inputs; `source` if unused
+    extra: Option<&'v wgpu::TextureView>,
+    target: &'v wgpu::TextureView,
+    format: wgpu::TextureFormat,
+    // Add to the target instead of replacing it
+    additive: bool,
+    // Pixels written, keeping the rest of the target; all of it if `None`
+    region: Option<ScissorRect>,
+}
+
+/// Shader, layouts, and pipelines shared by the built-in effects
+struct PostPasses {
+    shader: wgpu::ShaderModule,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    sampler: wgpu::Sampler,
+    pipelines: HashMap<(&'static str, wgpu::TextureFormat, bool), wgpu::RenderPipeline>,
+    // Passes drawn since `PostEffects::run` last collected them
+    drawn: RenderStats,
+}
+
+impl PostPasses {
+    fn new(device: &wgpu::Device) -> Self {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                texture_entry(0),
+                texture_entry(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("post_bind_group_layout"),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Post Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/post.wgsl").into()),
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Post Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        Self {
+            shader,
+            bind_group_layout,
+            pipeline_layout,
+            sampler,
+            pipelines: HashMap::new(),
+            drawn: RenderStats::default(),
+        }
+    }
+
+    fn draw(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, pass: Pass<'_>) {
+        let pipeline = self
+            .pipelines
+            .entry((pass.entry_point, pass.format, pass.additive))
+            .or_insert_with(|| {
+                let blend = if pass.additive {
+                    let add = wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    };
+                    wgpu::BlendState {
+                        color: add,
+                        alpha: add,
+                    }
+                } else {
+                    wgpu::BlendState::REPLACE
+                };
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(pass.entry_point),
+                    layout: Some(&self.pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &self.shader,
+                        entry_point: Some("vs_main"),
+                        buffers: &[],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &self.shader,
+                        entry_point: Some(pass.entry_point),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: pass.format,
+                            blend: Some(blend),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                    cache: None,
+                })
+            });
+
+        // A small buffer per pass, since all passes of a frame are submitted
+        // together and would otherwise see the last write
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Post Params"),
+            contents: bytemuck::cast_slice(&pass.params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(pass.source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(pass.extra.unwrap_or(pass.source)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: params.as_entire_binding(),
+                },
+            ],
+            label: Some("post_bind_group"),
+        });
+
+        let load = if pass.additive || pass.region.is_some() {
+            wgpu::LoadOp::Load
+        } else {
+            wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(pass.entry_point),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: pass.target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        if let Some(region) = pass.region {
+            let target = pass.target.texture();
+            region.apply(&mut render_pass, (target.width(), target.height()));
+        }
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        self.drawn
+            .record_draw(wgpu::PrimitiveTopology::TriangleList, 3, 1);
+        self.drawn.bytes_written += std::mem::size_of_val(&pass.params) as u64;
+    }
+}
+
+/// Curve mapping HDR colors into displayable 0..1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TonemapOperator {
+    /// Filmic curve with soft highlight rolloff
+    #[default]
+    Aces,
+    /// `c / (1 + c)`, gentle and desaturating
+    Reinhard,
+    /// Cut off at 1.0, as without HDR
+    Clamp,
+}
+
+impl TonemapOperator {
+    /// HDR value this curve maps to `mapped`, for colors that must come out
+    /// of tonemapping unchanged
+    pub fn invert(self, mapped: f32) -> f32 {
+        match self {
+            // Positive root of the fitted curve solved for x
+            Self::Aces => {
+                let y = mapped.clamp(0.0, 1.0);
+                let (a, b, c) = (2.43 * y - 2.51, 0.59 * y - 0.03, 0.14 * y);
+                (-b - (b * b - 4.0 * a * c).sqrt()) / (2.0 * a)
+            }
+            Self::Reinhard => {
+                let y = mapped.clamp(0.0, 0.999);
+                y / (1.0 - y)
+            }
+            Self::Clamp => mapped.clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Darkens (or tints) the edges of the frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vignette {
+    /// How strongly the corners take on `color`, 0..1
+    pub intensity: f32,
+    /// Distance from the center (1 at the corners) where darkening starts
+    pub radius: f32,
+    /// Distance over which darkening fades in
+    pub smoothness: f32,
+    pub color: [f32; 3],
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Self {
+            intensity: 0.5,
+            radius: 0.5,
+            smoothness: 0.6,
+            color: [0.0, 0.0, 0.0],
+        }
+    }
+}
+
+impl Vignette {
+    /// Set how strongly the corners are darkened
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Set where darkening starts (0 center, 1 corners)
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+}
+
+impl PostEffect for Vignette {
+    fn apply(&mut self, frame: &mut PostFrame<'_>) {
+        let [r, g, b] = self.color;
+        let (input, output) = (frame.input, frame.output);
+        frame.draw(Pass {
+            entry_point: "fs_vignette",
+            params: [
+                [self.intensity, self.radius, self.smoothness.max(1e-3), 0.0],
+                [r, g, b, 0.0],
+            ],
+            source: input,
+            extra: None,
+            target: output,
+            format: HDR_FORMAT,
+            additive: false,
+            region: None,
+        });
+    }
+}
+
+/// Glow around bright areas: pixels above `threshold` are blurred through a
+/// chain of half-size textures and added back onto the frame
+#[derive(Debug)]
+pub struct Bloom {
+    /// Brightness above which pixels glow
+    pub threshold: f32,
+    /// Width of the soft transition around the threshold
+    pub knee: f32,
+    /// How much of the blurred highlights is added
+    pub intensity: f32,
+    /// Number of half-size blur steps; more gives a wider glow
+    pub levels: u32,
+    /// Spread of each upsampling step, in texels
+    pub radius: f32,
+    // Blur texture views, one per mip, with the frame size they were made for
+    chain: Option<((u32, u32), Vec<wgpu::TextureView>)>,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.5,
+            levels: 6,
+            radius: 1.0,
+            chain: None,
+        }
+    }
+}
+
+impl Bloom {
+    /// Set the brightness threshold
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Set how much glow is added
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Set the number of blur steps
+    pub fn with_levels(mut self, levels: u32) -> Self {
+        self.levels = levels.max(1);
+        self
+    }
+
+    fn chain(&mut self, device: &wgpu::Device, size: (u32, u32)) -> &[wgpu::TextureView] {
+        let base = ((size.0 / 2).max(1), (size.1 / 2).max(1));
+        // Stop before the smallest side would drop below one pixel
+        let levels = self
+            .levels
+            .clamp(1, u32::BITS - base.0.min(base.1).leading_zeros());
+        if self
+            .chain
+            .as_ref()
+            .is_none_or(|(cached, views)| *cached != size || views.len() != levels as usize)
+        {
+            let texture = create_hdr_texture(device, "Bloom Texture", base, levels);
+            let views = (0..levels)
+                .map(|level| {
+                    texture.create_view(&wgpu::TextureViewDescriptor {
+                        base_mip_level: level,
+                        mip_level_count: Some(1),
+                        ..Default::default()
+                    })
+                })
+                .collect();
+            self.chain = Some((size, views));
+        }
+        self.chain.as_ref().map_or(&[], |(_, views)| views)
+    }
+}
+
+impl PostEffect for Bloom {
+    fn apply(&mut self, frame: &mut PostFrame<'_>) {
+        let (threshold, knee, intensity, radius) = (
+            self.threshold,
+            self.knee.max(0.0),
+            self.intensity,
+            self.radius,
+        );
+        let size = frame.size;
+        let views = self.chain(frame.device, size);
+        let texel = |level: i32| {
+            // Level -1 is the full-size input
+            let (width, height) = match level {
+                -1 => size,
+                _ => (
+                    ((size.0 / 2) >> level).max(1),
+                    ((size.1 / 2) >> level).max(1),
+                ),
+            };
+            [1.0 / width as f32, 1.0 / height as f32]
+        };
+        let blur_pass = |entry_point, params: [f32; 4], source, target, additive| Pass {
+            entry_point,
+            params: [params, [0.0; 4]],
+            source,
+            extra: None,
+            target,
+            format: HDR_FORMAT,
+            additive,
+            region: None,
+        };
+
+        let [x, y] = texel(-1);
+        frame.draw(blur_pass(
+            "fs_bloom_prefilter",
+            [x, y, threshold, knee],
+            frame.input,
+            &views[0],
+            false,
+        ));
+        for level in 1..views.len() {
+            let [x, y] = texel(level as i32 - 1);
+            frame.draw(blur_pass(
+                "fs_bloom_downsample",
+                [x, y, 0.0, 0.0],
+                &views[level - 1],
+                &views[level],
+                false,
+            ));
+        }
+        for level in (0..views.len() - 1).rev() {
+            let [x, y] = texel(level as i32 + 1);
+            frame.draw(blur_pass(
+                "fs_bloom_upsample",
+                [x, y, radius, 0.0],
+                &views[level + 1],
+                &views[level],
+                true,
+            ));
+        }
+
+        let (input, output) = (frame.input, frame.output);
+        frame.draw(Pass {
+            entry_point: "fs_bloom_composite",
+            params: [[intensity, 0.0, 0.0, 0.0], [0.0; 4]],
+            source: input,
+            extra: Some(&views[0]),
+            target: output,
+            format: HDR_FORMAT,
+            additive: false,
+            region: None,
+        });
+    }
+}
@@ -0,0 +1,202 @@
+//! Generated textures: checkerboards, gradients, grids, and noise
+//!
+//! The pixel functions return tightly packed RGBA8 data, top row first, ready
+//! for `Texture::from_rgba8`; the `Texture` constructors below wrap them.
+//! Colors are sRGB RGBA.
+//!
+//! ```rust,ignore
+//! let bumps = Texture::noise_normal_map(renderer.device(), renderer.queue(), 256, 32, 2.0, 7)?;
+//! world.spawn().with(Material::default().with_normal_map(bumps, 1.0));
+//! ```
+
+use super::Texture;
+use anyhow::Result;
+
+/// Octaves summed by the noise generators
+const NOISE_OCTAVES: u32 = 4;
+
+/// Checkerboard of `cells`×`cells` squares alternating between `a` and `b`
+pub fn checker(size: u32, cells: u32, a: [u8; 4], b: [u8; 4]) -> Vec<u8> {
+    let cells = cells.max(1);
+    pixels(size, size, |x, y| {
+        let cell = x * cells / size + y * cells / size;
+        if cell.is_multiple_of(2) { a } else { b }
+    })
+}
+
+/// Vertical gradient from `top` to `bottom`
+pub fn gradient(width: u32, height: u32, top: [u8; 4], bottom: [u8; 4]) -> Vec<u8> {
+    let span = height.saturating_sub(1).max(1) as f32;
+    pixels(width, height, |_, y| mix(top, bottom, y as f32 / span))
+}
+
+/// Lines `line_width` pixels wide on `background`, splitting the texture into
+/// `cells`×`cells` squares; tiles seamlessly
+pub fn grid(size: u32, cells: u32, line_width: u32, line: [u8; 4], background: [u8; 4]) -> Vec<u8> {
+    let cells = cells.max(1);
+    // Each line runs along the top or left edge of its cell
+    let on_line = |coordinate: u32| (coordinate * cells) % size < line_width * cells;
+    pixels(size, size, |x, y| {
+        if on_line(x) || on_line(y) {
+            line
+        } else {
+            background
+        }
+    })
+}
+
+/// Grayscale fractal value noise whose largest features are `period` pixels
+/// apart; tiles seamlessly when `period` divides `size`
+pub fn noise(size: u32, period: u32, seed: u32) -> Vec<u8> {
+    let heights = noise_heights(size, period, seed);
+    pixels(size, size, |x, y| {
+        let value = (heights[(y * size + x) as usize] * 255.0).round() as u8;
+        [value, value, value, 255]
+    })
+}
+
+/// Tangent-space normal map of bumps shaped by `noise`, with `strength`
+/// scaling their slope (green points up the image)
+pub fn noise_normals(size: u32, period: u32, strength: f32, seed: u32) -> Vec<u8> {
+    let heights = noise_heights(size, period, seed);
+    let height = |x: u32, y: u32| heights[((y % size) * size + x % size) as usize];
+    // Scale slopes so `strength` is independent of the texture size
+    let scale = strength * period.max(1) as f32 / 4.0;
+    pixels(size, size, |x, y| {
+        let dx = (height(x + 1, y) - height(x + size - 1, y)) * scale;
+        let dy = (height(x, y + size - 1) - height(x, y + 1)) * scale;
+        let length = (dx * dx + dy * dy + 1.0).sqrt();
+        let encode = |c: f32| ((c / length * 0.5 + 0.5) * 255.0).round() as u8;
+        [encode(-dx), encode(-dy), encode(1.0), 255]
+    })
+}
+
+/// Fractal value noise in 0..1, one value per pixel
+pub(super) fn noise_heights(size: u32, period: u32, seed: u32) -> Vec<f32> {
+    let mut heights = vec![0.0; (size * size) as usize];
+    let mut total = 0.0;
+    for octave in 0..NOISE_OCTAVES {
+        let cell = (period >> octave).max(1);
+        let lattice = size.div_ceil(cell).max(1);
+        let amplitude = 0.5f32.powi(octave as i32);
+        total += amplitude;
+        for y in 0..size {
+            for x in 0..size {
+                heights[(y * size + x) as usize] +=
+                    amplitude * value_noise(x, y, cell, lattice, seed.wrapping_add(octave));
+            }
+        }
+    }
+    heights.iter_mut().for_each(|height| *height /= total);
+    heights
+}
+
+/// Smoothly interpolated random values on a lattice of `cell`-pixel squares,
+/// wrapping every `lattice` points
+fn value_noise(x: u32, y: u32, cell: u32, lattice: u32, seed: u32) -> f32 {
+    let (cx, cy) = (x / cell, y / cell);
+    let fade = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, ty) = (
+        fade((x % cell) as f32 / cell as f32),
+        fade((y % cell) as f32 / cell as f32),
+    );
+    let corner = |i: u32, j: u32| hash(i % lattice, j % lattice, seed);
+    let top = corner(cx, cy) + (corner(cx + 1, cy) - corner(cx, cy)) * tx;
+    let bottom = corner(cx, cy + 1) + (corner(cx + 1, cy + 1) - corner(cx, cy + 1)) * tx;
+    top + (bottom - top) * ty
+}
+
+/// Pseudo-random value in 0..1 for a lattice point
+fn hash(x: u32, y: u32, seed: u32) -> f32 {
+    let mut h = x
+        .wrapping_mul(0x8da6_b343)
+        .wrapping_add(y.wrapping_mul(0xd816_3841))
+        .wrapping_add(seed.wrapping_mul(0xcb1a_b31f));
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297a_2d39);
+    h ^= h >> 15;
+    h as f32 / u32::MAX as f32
+}
+
+fn mix(a: [u8; 4], b: [u8; 4], t: f32) -> [u8; 4] {
+    [0, 1, 2, 3].map(|i| (a[i] as f32 + (b[i] as f32 - a[i] as f32) * t).round() as u8)
+}
+
+fn pixels(width: u32, height: u32, color: impl Fn(u32, u32) -> [u8; 4]) -> Vec<u8> {
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .flat_map(|(x, y)| color(x, y))
+        .collect()
+}
+
+impl Texture {
+    /// Checkerboard texture (see `procedural::checker`)
+    pub fn checker(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: u32,
+        cells: u32,
+        a: [u8; 4],
+        b: [u8; 4],
+    ) -> Result<Self> {
+        Self::from_rgba8(device, queue, size, size, &checker(size, cells, a, b))
+    }
+
+    /// Vertical gradient texture (see `procedural::gradient`)
+    pub fn gradient(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        top: [u8; 4],
+        bottom: [u8; 4],
+    ) -> Result<Self> {
+        Self::from_rgba8(
+            device,
+            queue,
+            width,
+            height,
+            &gradient(width, height, top, bottom),
+        )
+    }
+
+    /// Grid line texture (see `procedural::grid`)
+    pub fn grid(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: u32,
+        cells: u32,
+        line_width: u32,
+        line: [u8; 4],
+        background: [u8; 4],
+    ) -> Result<Self> {
+        let pixels = grid(size, cells, line_width, line, background);
+        Self::from_rgba8(device, queue, size, size, &pixels)
+    }
+
+    /// Grayscale noise texture (see `procedural::noise`), stored linearly
+    pub fn noise(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: u32,
+        period: u32,
+        seed: u32,
+    ) -> Result<Self> {
+        Self::from_rgba8_linear(device, queue, size, size, &noise(size, period, seed))
+    }
+
+    /// Bumpy normal map from noise (see `procedural::noise_normals`)
+    pub fn noise_normal_map(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: u32,
+        period: u32,
+        strength: f32,
+        seed: u32,
+    ) -> Result<Self> {
+        let pixels = noise_normals(size, period, strength, seed);
+        Self::from_rgba8_linear(device, queue, size, size, &pixels)
+    }
+}
@@ -0,0 +1,213 @@
+//! Render data extracted from the world once per frame
+//!
+//! `Renderer::extract` copies what drawing needs out of the `World` into a
+//! `RenderList`: meshes, world-space transforms and material data,
+//! lights, and sprites. Everything after it — culling, sorting, uploads, and
+//! passes — reads only the list, so a frame never borrows the world while
+//! drawing, and meshes arrive already grouped by pipeline.
+
+use super::light::LightUniform;
+use super::sprite::SpriteQuad;
+use super::{
+    Instances, Material, Mesh, ObjectUniform, Outlined, PipelineId, Renderer,
+    create_texture_bind_group, exposure,
+};
+use crate::assets::{Assets, Handle};
+use crate::ecs::{Component, EntityId, Without, World};
+use crate::math::{Aabb, Matrix4, Transform};
+use cgmath::SquareMatrix;
+use std::ops::Range;
+
+/// Whether an entity's `Mesh` or `Handle<Mesh>` is drawn
+///
+/// Entities without the component are visible.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Visibility {
+    #[default]
+    Visible,
+    Hidden,
+}
+
+impl Component for Visibility {}
+
+/// One mesh entity as rendering sees it
+pub(crate) struct RenderItem {
+    pub(crate) entity: EntityId,
+    pub(crate) mesh: Mesh,
+    /// Mesh-space bounds for culling: the entity's `Aabb`, else the mesh's
+    pub(crate) bounds: Aabb,
+    /// The entity's own model matrix; instances are placed relative to it
+    pub(crate) model: Matrix4<f32>,
+    /// Slots in `RenderList::objects`, one per instance or one for the mesh
+    pub(crate) objects: Range<usize>,
+    /// Culled per instance rather than as a whole
+    pub(crate) instanced: bool,
+    pub(crate) pipeline: PipelineId,
+    pub(crate) textures: wgpu::BindGroup,
+    // Custom material bind group (group 2), if its material has one
+    pub(crate) material: Option<wgpu::BindGroup>,
+    pub(crate) outline: Option<Outlined>,
+}
+
+/// Everything a frame draws, copied out of the `World` by `Renderer::extract`
+pub(crate) struct RenderList {
+    /// Opaque meshes grouped by pipeline, then transparent ones
+    pub(crate) items: Vec<RenderItem>,
+    /// World-space object data of every item, before culling
+    pub(crate) objects: Vec<ObjectUniform>,
+    pub(crate) lights: LightUniform,
+    pub(crate) exposure: f32,
+    /// Screen-space quads by layer, in draw order
+    pub(crate) sprites: Vec<((i32, EntityId), SpriteQuad)>,
+}
+
+impl Default for RenderList {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            objects: Vec::new(),
+            lights: bytemuck::Zeroable::zeroed(),
+            exposure: 1.0,
+            sprites: Vec::new(),
+        }
+    }
+}
+
+impl Renderer {
+    /// Copy this frame's render data out of `world`, replacing the previous
+    /// frame's, and upload point clouds, debug lines, ribbons, and 3D text
+    pub(crate) fn extract(&mut self, world: &World) {
+        let mut list = std::mem::take(&mut self.render_list);
+        list.items.clear();
+        list.objects.clear();
+        let mut unused_textures = std::mem::take(&mut self.texture_bind_groups);
+
+        // Entities with their own `Mesh`, then those sharing one by handle
+        let meshes = world.resource::<Assets<Mesh>>();
+        let shared = world
+            .query_filtered::<Handle<Mesh>, Without<Mesh>>()
+            .filter_map(|(entity, &handle)| Some((entity, meshes?.get(handle)?)));
+        for (entity_id, mesh) in world.query::<Mesh>().chain(shared) {
+            if world.get_component::<Visibility>(entity_id) == Some(&Visibility::Hidden) {
+                continue;
+            }
+            let model = world
+                .get_component::<Transform>(entity_id)
+                .map_or_else(Matrix4::identity, Transform::matrix);
+
+            // The first registered custom material the entity has picks its shader
+            let custom = self
+                .materials
+                .iter()
+                .enumerate()
+                .find_map(|(index, registered)| {
+                    (registered.fetch)(world, entity_id)
+                        .map(|group| (index, registered.bind_group_layout.is_some(), group))
+                });
+            let (custom_index, material_group) = match custom {
+                // The shader expects a material bind group that isn't ready yet
+                Some((_, true, None)) => continue,
+                Some((index, _, group)) => (Some(index), group),
+                None => (None, None),
+            };
+
+            let material = world.get_component::<Material>(entity_id);
+            let (base_color, emissive, params) = material
+                .map(Material::gpu_data)
+                .unwrap_or_else(|| Material::default().gpu_data());
+            let textures = match material.and_then(|material| material.normal_map.as_ref()) {
+                Some(normal_map) => {
+                    let id = normal_map.id();
+                    let group = match self.texture_bind_groups.get(&id) {
+                        Some(group) => group.clone(),
+                        None => unused_textures.remove(&id).unwrap_or_else(|| {
+                            create_texture_bind_group(
+                                &self.device,
+                                &self.texture_bind_group_layout,
+                                normal_map,
+                            )
+                        }),
+                    };
+                    self.texture_bind_groups.insert(id, group.clone());
+                    group
+                }
+                None => self.default_texture_bind_group.clone(),
+            };
+
+            let start = list.objects.len();
+            let instances = world.get_component::<Instances>(entity_id);
+            match instances {
+                Some(instances) => {
+                    list.objects
+                        .extend(instances.instances.iter().map(|instance| {
+                            let tint = instance.color;
+                            ObjectUniform {
+                                model: (model * instance.transform.matrix()).into(),
+                                base_color: [0, 1, 2, 3].map(|i| base_color[i] * tint[i]),
+                                emissive,
+                                params: [params[0], instance.data, params[2], params[3]],
+                            }
+                        }));
+                }
+                None => list.objects.push(ObjectUniform {
+                    model: model.into(),
+                    base_color,
+                    emissive,
+                    params,
+                }),
+            }
+
+            let topology = match mesh.primitive_topology {
+                topology @ (wgpu::PrimitiveTopology::LineList
+                | wgpu::PrimitiveTopology::LineStrip) => topology,
+                // Handle other topologies as triangles for now
+                _ => wgpu::PrimitiveTopology::TriangleList,
+            };
+            list.items.push(RenderItem {
+                entity: entity_id,
+                mesh: mesh.clone(),
+                bounds: world
+                    .get_component::<Aabb>(entity_id)
+                    .copied()
+                    .unwrap_or(mesh.bounds),
+                model,
+                objects: start..list.objects.len(),
+                instanced: instances.is_some(),
+                pipeline: PipelineId {
+                    material: custom_index,
+                    topology,
+                    transparent: material.is_some_and(|material| material.transparent),
+                    wireframe: false,
+                },
+                textures,
+                material: material_group,
+                outline: world.get_component::<Outlined>(entity_id).copied(),
+            });
+        }
+
+        // Batch by pipeline once here, in first-seen order, so the frame only
+        // culls and sorts transparent meshes by distance
+        let mut order: Vec<PipelineId> = Vec::new();
+        for item in &list.items {
+            if !order.contains(&item.pipeline) {
+                order.push(item.pipeline);
+            }
+        }
+        list.items.sort_by_key(|item| {
+            let rank = order.iter().position(|id| *id == item.pipeline);
+            (item.pipeline.transparent, rank)
+        });
+
+        list.lights = LightUniform::from_world(world, self.camera_position());
+        list.exposure = exposure(world);
+        self.render_list = list;
+
+        self.extract_sprites(world);
+        self.prepare_point_clouds(world);
+        self.prepare_debug_lines(world);
+        self.prepare_ribbons(world);
+        #[cfg(feature = "text")]
+        self.prepare_world_text(world);
+    }
+}
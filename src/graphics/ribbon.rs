@@ -0,0 +1,393 @@
+//! Camera-facing ribbons along point sequences
+
+use super::{PipelineKey, RenderStats, Renderer};
+use crate::ecs::{Component, World};
+use crate::math::{Matrix4, Transform, Vector3};
+use cgmath::{InnerSpace, SquareMatrix};
+use std::collections::HashMap;
+
+/// Flat strip through a sequence of points that always turns its face to the
+/// camera, for orbits, trajectories, and motion trails
+///
+/// Points are in the entity's local space when it has a `Transform`. The
+/// color fades through `colors`, spread evenly along the ribbon's length, and
+/// alpha below 1 blends with the scene behind.
+///
+/// ```rust,ignore
+/// let orbit = (0..=64).map(|i| {
+///     let angle = i as f32 / 64.0 * std::f32::consts::TAU;
+///     Vector3::new(angle.cos() * 5.0, 0.0, angle.sin() * 5.0)
+/// });
+/// world.spawn().with(Ribbon::new(orbit).with_width(0.1).with_gradient(vec![
+///     [0.2, 0.6, 1.0, 0.0],
+///     [0.2, 0.6, 1.0, 1.0],
+/// ]));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ribbon {
+    pub points: Vec<Vector3<f32>>,
+    /// Width in world units
+    pub width: f32,
+    /// Colors from the first point to the last, alpha included; white if empty
+    pub colors: Vec<[f32; 4]>,
+    /// Extra points per segment on a Catmull-Rom curve through the points;
+    /// 0 joins them with straight segments
+    pub subdivisions: u32,
+    /// Oldest points dropped by `push` beyond this many; 0 keeps all
+    pub max_points: usize,
+}
+
+impl Component for Ribbon {}
+
+impl Ribbon {
+    /// White ribbon 0.1 units wide through `points`
+    pub fn new(points: impl IntoIterator<Item = Vector3<f32>>) -> Self {
+        Self {
+            points: points.into_iter().collect(),
+            width: 0.1,
+            colors: vec![[1.0, 1.0, 1.0, 1.0]],
+            subdivisions: 4,
+            max_points: 0,
+        }
+    }
+
+    /// Set the width in world units
+    pub fn with_width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Use one color along the whole ribbon
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.colors = vec![color];
+        self
+    }
+
+    /// Fade through `colors` from the first point to the last
+    pub fn with_gradient(mut self, colors: Vec<[f32; 4]>) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Set the extra points per segment of the smoothed curve
+    pub fn with_subdivisions(mut self, subdivisions: u32) -> Self {
+        self.subdivisions = subdivisions;
+        self
+    }
+
+    /// Keep at most `max_points` points when using `push`
+    pub fn with_max_points(mut self, max_points: usize) -> Self {
+        self.max_points = max_points;
+        self
+    }
+
+    /// Append a point, dropping the oldest beyond `max_points`
+    pub fn push(&mut self, point: Vector3<f32>) {
+        self.points.push(point);
+        if self.max_points > 0 && self.points.len() > self.max_points {
+            let excess = self.points.len() - self.max_points;
+            self.points.drain(..excess);
+        }
+    }
+
+    /// Color at `t` from 0 at the first point to 1 at the last
+    fn color_at(&self, t: f32) -> [f32; 4] {
+        match self.colors.as_slice() {
+            [] => [1.0; 4],
+            [color] => *color,
+            colors => {
+                let scaled = t.clamp(0.0, 1.0) * (colors.len() - 1) as f32;
+                let index = (scaled.floor() as usize).min(colors.len() - 2);
+                let fraction = scaled - index as f32;
+                let (a, b) = (colors[index], colors[index + 1]);
+                [0, 1, 2, 3].map(|i| a[i] + (b[i] - a[i]) * fraction)
+            }
+        }
+    }
+
+    /// Points along the smoothed curve, without repeats, and whether it is a
+    /// closed loop
+    fn curve(&self) -> (Vec<Vector3<f32>>, bool) {
+        let mut points = self.points.clone();
+        points.dedup_by(|a, b| is_near(*a, *b));
+        let closed = points.len() > 3 && is_near(points[0], points[points.len() - 1]);
+        if self.subdivisions == 0 || points.len() < 3 {
+            return (points, closed);
+        }
+        let steps = self.subdivisions + 1;
+        let last = points.len() - 1;
+        // Neighbours past either end wrap around loops and repeat the end
+        // point otherwise
+        let neighbour = |i: isize| {
+            if closed {
+                points[i.rem_euclid(last as isize) as usize]
+            } else {
+                points[i.clamp(0, last as isize) as usize]
+            }
+        };
+        let mut curve = Vec::with_capacity(last * steps as usize + 1);
+        for i in 0..last {
+            let p0 = neighbour(i as isize - 1);
+            let (p1, p2) = (points[i], points[i + 1]);
+            let p3 = neighbour(i as isize + 2);
+            for step in 0..steps {
+                curve.push(catmull_rom(p0, p1, p2, p3, step as f32 / steps as f32));
+            }
+        }
+        curve.push(points[last]);
+        (curve, closed)
+    }
+}
+
+fn is_near(a: Vector3<f32>, b: Vector3<f32>) -> bool {
+    (a - b).magnitude2() <= 1e-8
+}
+
+/// Uniform Catmull-Rom spline between `p1` and `p2`
+fn catmull_rom(
+    p0: Vector3<f32>,
+    p1: Vector3<f32>,
+    p2: Vector3<f32>,
+    p3: Vector3<f32>,
+    t: f32,
+) -> Vector3<f32> {
+    let (t2, t3) = (t * t, t * t * t);
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
+/// One edge of the ribbon at a curve point, pushed sideways in the shader
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct RibbonVertex {
+    position: [f32; 3],
+    /// Signed half width: negative on one edge, positive on the other
+    offset: f32,
+    tangent: [f32; 3],
+    color: [f32; 4],
+}
+
+impl RibbonVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
+        0 => Float32x3, 1 => Float32, 2 => Float32x3, 3 => Float32x4
+    ];
+}
+
+/// Pipelines and buffers for every `Ribbon`, created once ribbons appear
+pub(crate) struct RibbonRenderer {
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipelines: HashMap<PipelineKey, wgpu::RenderPipeline>,
+    vertices: wgpu::Buffer,
+    indices: wgpu::Buffer,
+    vertex_capacity: usize,
+    index_capacity: usize,
+    index_count: u32,
+}
+
+impl RibbonRenderer {
+    fn new(device: &wgpu::Device, uniform_layout: &wgpu::BindGroupLayout) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Ribbon Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/ribbon.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Ribbon Pipeline Layout"),
+            bind_group_layouts: &[uniform_layout],
+            push_constant_ranges: &[],
+        });
+        let (vertex_capacity, index_capacity) = (1024, 3072);
+        Self {
+            shader,
+            pipeline_layout,
+            pipelines: HashMap::new(),
+            vertices: create_buffer::<RibbonVertex>(device, vertex_capacity, true),
+            indices: create_buffer::<u32>(device, index_capacity, false),
+            vertex_capacity,
+            index_capacity,
+            index_count: 0,
+        }
+    }
+
+    /// Pipeline for the given target, created on first use
+    pub(crate) fn pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        (format, samples, write_mask): PipelineKey,
+        depth_format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        self.pipelines
+            .entry((format, samples, write_mask))
+            .or_insert_with(|| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("Ribbon Pipeline"),
+                    layout: Some(&self.pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &self.shader,
+                        entry_point: Some("vs_main"),
+                        buffers: &[wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<RibbonVertex>()
+                                as wgpu::BufferAddress,
+                            step_mode: wgpu::VertexStepMode::Vertex,
+                            attributes: &RibbonVertex::ATTRIBUTES,
+                        }],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &self.shader,
+                        entry_point: Some("fs_main"),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: Some(wgpu::BlendState {
+                                color: wgpu::BlendComponent {
+                                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                                    operation: wgpu::BlendOperation::Add,
+                                },
+                                alpha: wgpu::BlendComponent::OVER,
+                            }),
+                            write_mask,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    // Ribbons face the camera, so neither side is culled
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: depth_format,
+                        depth_write_enabled: false,
+                        depth_compare: wgpu::CompareFunction::Less,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        count: samples,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    multiview: None,
+                    cache: None,
+                })
+            })
+            .clone()
+    }
+
+    /// Draw the ribbons uploaded by `Renderer::prepare_ribbons`, with the
+    /// scene bind group already set
+    pub(crate) fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        pipeline: &wgpu::RenderPipeline,
+        stats: &mut RenderStats,
+    ) {
+        if self.index_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_vertex_buffer(0, self.vertices.slice(..));
+        render_pass.set_index_buffer(self.indices.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.index_count, 0, 0..1);
+        stats.record_indexed(wgpu::PrimitiveTopology::TriangleList, self.index_count, 1);
+    }
+}
+
+fn create_buffer<T>(device: &wgpu::Device, capacity: usize, vertex: bool) -> wgpu::Buffer {
+    let (label, usage) = if vertex {
+        ("Ribbon Vertices", wgpu::BufferUsages::VERTEX)
+    } else {
+        ("Ribbon Indices", wgpu::BufferUsages::INDEX)
+    };
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: (capacity * std::mem::size_of::<T>()) as wgpu::BufferAddress,
+        usage: usage | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+impl Renderer {
+    /// Build and upload this frame's `Ribbon` geometry
+    pub(crate) fn prepare_ribbons(&mut self, world: &World) {
+        let ribbons: Vec<_> = world.query::<Ribbon>().collect();
+        if ribbons.is_empty() && self.ribbons.is_none() {
+            return;
+        }
+
+        let mut vertices: Vec<RibbonVertex> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        for (entity, ribbon) in ribbons {
+            let model = world
+                .get_component::<Transform>(entity)
+                .map_or_else(Matrix4::identity, Transform::matrix);
+            let (curve, closed) = ribbon.curve();
+            let points: Vec<Vector3<f32>> = curve
+                .into_iter()
+                .map(|point| (model * point.extend(1.0)).truncate())
+                .collect();
+            if points.len() < 2 {
+                continue;
+            }
+            // Distance along the ribbon at each point, for the gradient
+            let mut distances = vec![0.0];
+            for pair in points.windows(2) {
+                distances.push(distances[distances.len() - 1] + (pair[1] - pair[0]).magnitude());
+            }
+            let length = distances[distances.len() - 1].max(f32::EPSILON);
+
+            let base = vertices.len() as u32;
+            let last = points.len() - 1;
+            for (i, &point) in points.iter().enumerate() {
+                // Averaging both neighbours bends the edges smoothly at joints,
+                // including where a loop meets itself
+                let (before, after) = if closed && (i == 0 || i == last) {
+                    (points[last - 1], points[1])
+                } else {
+                    (points[i.saturating_sub(1)], points[(i + 1).min(last)])
+                };
+                let tangent = (after - before).normalize();
+                let color = ribbon.color_at(distances[i] / length);
+                for side in [-0.5, 0.5] {
+                    vertices.push(RibbonVertex {
+                        position: point.into(),
+                        offset: side * ribbon.width,
+                        tangent: tangent.into(),
+                        color,
+                    });
+                }
+                if i < last {
+                    let a = base + 2 * i as u32;
+                    indices.extend_from_slice(&[a, a + 1, a + 2, a + 2, a + 1, a + 3]);
+                }
+            }
+        }
+
+        let renderer = self.ribbons.get_or_insert_with(|| {
+            RibbonRenderer::new(&self.device, &self.uniform_bind_group_layout)
+        });
+        if vertices.len() > renderer.vertex_capacity {
+            renderer.vertex_capacity = vertices.len().next_power_of_two();
+            renderer.vertices =
+                create_buffer::<RibbonVertex>(&self.device, renderer.vertex_capacity, true);
+        }
+        if indices.len() > renderer.index_capacity {
+            renderer.index_capacity = indices.len().next_power_of_two();
+            renderer.indices = create_buffer::<u32>(&self.device, renderer.index_capacity, false);
+        }
+        let stats = &mut self.frame_stats;
+        stats.write_buffer(
+            &self.queue,
+            &renderer.vertices,
+            0,
+            bytemuck::cast_slice(&vertices),
+        );
+        stats.write_buffer(
+            &self.queue,
+            &renderer.indices,
+            0,
+            bytemuck::cast_slice(&indices),
+        );
+        renderer.index_count = indices.len() as u32;
+    }
+}
@@ -0,0 +1,184 @@
+//! Rebuild pipelines when watched WGSL files change on disk
+
+use super::custom_material::{CustomMaterial, create_shader_module};
+use super::{PipelineId, Renderer, create_scene_pipeline};
+use anyhow::{Context, Result};
+use notify::Watcher;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, channel};
+
+/// Which shader a watched file provides
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShaderSlot {
+    Default,
+    Material(usize),
+}
+
+/// File watcher feeding changed shader paths to the renderer
+pub(crate) struct ShaderWatcher {
+    watcher: notify::RecommendedWatcher,
+    changes: Receiver<PathBuf>,
+    files: Vec<(PathBuf, ShaderSlot)>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl ShaderWatcher {
+    fn new(window: Option<std::sync::Arc<winit::window::Window>>) -> Result<Self> {
+        let (sender, changes) = channel();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            if event.kind.is_modify() || event.kind.is_create() {
+                for path in event.paths {
+                    let _ = sender.send(path);
+                }
+                // Frames are drawn on demand, so ask for one to pick up the change
+                if let Some(window) = &window {
+                    window.request_redraw();
+                }
+            }
+        })
+        .context("Failed to start shader file watcher")?;
+        Ok(Self {
+            watcher,
+            changes,
+            files: Vec::new(),
+            dirs: HashSet::new(),
+        })
+    }
+
+    /// Watch `path` for `slot`; returns its canonical path
+    fn watch(&mut self, path: &Path, slot: ShaderSlot) -> Result<PathBuf> {
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("Shader not found: {}", path.display()))?;
+        // Watch the directory rather than the file, since many editors save by
+        // replacing the file
+        if let Some(dir) = path.parent()
+            && self.dirs.insert(dir.to_path_buf())
+        {
+            self.watcher
+                .watch(dir, notify::RecursiveMode::NonRecursive)
+                .with_context(|| format!("Failed to watch {}", dir.display()))?;
+        }
+        self.files.retain(|(_, watched)| *watched != slot);
+        self.files.push((path.clone(), slot));
+        Ok(path)
+    }
+
+    /// Watched files changed since the last call, each listed once
+    fn changed(&self) -> Vec<(PathBuf, ShaderSlot)> {
+        let paths: HashSet<PathBuf> = self.changes.try_iter().collect();
+        self.files
+            .iter()
+            .filter(|(path, _)| paths.contains(path))
+            .cloned()
+            .collect()
+    }
+}
+
+fn read_shader(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read shader {}", path.display()))
+}
+
+impl Renderer {
+    /// Load the default mesh shader from `path` and rebuild its pipelines
+    /// whenever the file changes
+    ///
+    /// The file must keep the bindings and entry points of the built-in
+    /// `default.wgsl`; a copy of it is a good starting point.
+    pub fn watch_default_shader(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = self
+            .shader_watcher()?
+            .watch(path.as_ref(), ShaderSlot::Default)?;
+        self.reload_shader(&path, ShaderSlot::Default)
+    }
+
+    /// Register `M` with the shader at `path` (see `register_material`) and
+    /// rebuild its pipelines whenever the file changes
+    pub fn register_material_file<M: CustomMaterial>(
+        &mut self,
+        path: impl AsRef<Path>,
+        bind_layout: &[wgpu::BindGroupLayoutEntry],
+    ) -> Result<()> {
+        let path = path.as_ref();
+        self.register_material::<M>(&read_shader(path)?, bind_layout)?;
+        let index = self
+            .material_index::<M>()
+            .context("Material was not registered")?;
+        self.shader_watcher()?
+            .watch(path, ShaderSlot::Material(index))?;
+        Ok(())
+    }
+
+    /// Recompile watched shaders whose files changed; `render` calls this
+    /// before each frame
+    ///
+    /// A shader that fails to compile, or whose pipelines fail to build, is
+    /// logged and the previous version stays in use.
+    pub fn reload_changed_shaders(&mut self) {
+        let Some(watcher) = &self.shader_watcher else {
+            return;
+        };
+        for (path, slot) in watcher.changed() {
+            match self.reload_shader(&path, slot) {
+                Ok(()) => log::info!("Reloaded shader {}", path.display()),
+                Err(e) => log::error!("Shader reload failed, keeping previous version: {e:#}"),
+            }
+        }
+    }
+
+    fn shader_watcher(&mut self) -> Result<&mut ShaderWatcher> {
+        if self.shader_watcher.is_none() {
+            self.shader_watcher = Some(ShaderWatcher::new(self.window.clone())?);
+        }
+        Ok(self.shader_watcher.as_mut().unwrap())
+    }
+
+    /// Compile the shader at `path` and rebuild every cached pipeline that
+    /// uses `slot`, swapping them in only if all of them build
+    fn reload_shader(&mut self, path: &Path, slot: ShaderSlot) -> Result<()> {
+        let source = read_shader(path)?;
+        let shader = create_shader_module(&self.device, &path.display().to_string(), &source)?;
+        let material = match slot {
+            ShaderSlot::Default => None,
+            ShaderSlot::Material(index) => Some(index),
+        };
+        let layout = match material {
+            Some(index) => &self.materials[index].pipeline_layout,
+            None => &self.scene_pipeline_layout,
+        };
+
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let pipelines: Vec<_> = self
+            .pipeline_cache
+            .keys()
+            .filter(|(id, _)| id.material == material)
+            .map(|&(id, key): &(PipelineId, _)| {
+                let pipeline = create_scene_pipeline(
+                    &self.device,
+                    layout,
+                    &shader,
+                    key,
+                    self.depth_format,
+                    id,
+                );
+                ((id, key), pipeline)
+            })
+            .collect();
+        if let Some(error) = pollster::block_on(self.device.pop_error_scope()) {
+            anyhow::bail!("Failed to build pipelines for {}: {error}", path.display());
+        }
+
+        match material {
+            Some(index) => self.materials[index].shader = shader,
+            None => self.scene_shader = shader,
+        }
+        self.pipeline_cache.extend(pipelines);
+        self.request_redraw();
+        Ok(())
+    }
+}
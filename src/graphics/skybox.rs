@@ -0,0 +1,463 @@
+//! Environment backgrounds drawn behind all geometry
+
+use super::texture::{next_texture_id, read_png_rgba8};
+use super::{PipelineKey, RenderStats};
+use crate::math::Matrix4;
+use anyhow::{Result, bail};
+use cgmath::SquareMatrix;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Six-sided sRGB cube texture, looked up with world-space directions
+///
+/// Faces are ordered +X, -X, +Y, -Y, +Z, -Z using the wgpu (Vulkan/D3D) cube
+/// map convention. Cloning is cheap and shares the GPU texture.
+#[derive(Debug, Clone)]
+pub struct Cubemap {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    id: u64,
+}
+
+impl PartialEq for Cubemap {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Cubemap {
+    /// Cubemap from six square faces of tightly packed RGBA8 pixels
+    pub fn from_faces(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: u32,
+        faces: [&[u8]; 6],
+    ) -> Result<Self> {
+        let face_bytes = size as usize * size as usize * 4;
+        if size == 0 {
+            bail!("Cubemap face size must be non-zero");
+        }
+        if let Some(face) = faces.iter().find(|face| face.len() != face_bytes) {
+            bail!(
+                "Expected {face_bytes} bytes per {size}x{size} cubemap face, got {}",
+                face.len()
+            );
+        }
+
+        let extent = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Cubemap Texture"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            texture.as_image_copy(),
+            &faces.concat(),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(size * 4),
+                rows_per_image: Some(size),
+            },
+            extent,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Cubemap View"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Cubemap Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+            id: next_texture_id(),
+        })
+    }
+
+    /// Cubemap resampled from an equirectangular (latitude/longitude) RGBA8
+    /// panorama whose center column faces -Z
+    pub fn from_equirectangular(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+        face_size: u32,
+    ) -> Result<Self> {
+        if width == 0 || height == 0 || pixels.len() != width as usize * height as usize * 4 {
+            bail!("Invalid {width}x{height} equirectangular image data");
+        }
+        let faces = equirectangular_to_faces(width, height, pixels, face_size.max(1));
+        Self::from_faces(
+            device,
+            queue,
+            face_size.max(1),
+            faces.each_ref().map(Vec::as_slice),
+        )
+    }
+
+    /// Load an equirectangular PNG panorama as a cubemap
+    pub fn load_equirectangular(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: impl AsRef<Path>,
+        face_size: u32,
+    ) -> Result<Self> {
+        let (width, height, pixels) = read_png_rgba8(path.as_ref())?;
+        Self::from_equirectangular(device, queue, width, height, &pixels, face_size)
+    }
+
+    /// Width and height of each face
+    pub fn face_size(&self) -> u32 {
+        self.texture.width()
+    }
+}
+
+/// World-space direction through texel `(s, t)` (0..1) of cube face `face`
+fn face_direction(face: usize, s: f32, t: f32) -> [f32; 3] {
+    let (sc, tc) = (s * 2.0 - 1.0, t * 2.0 - 1.0);
+    match face {
+        0 => [1.0, -tc, -sc],
+        1 => [-1.0, -tc, sc],
+        2 => [sc, 1.0, tc],
+        3 => [sc, -1.0, -tc],
+        4 => [sc, -tc, 1.0],
+        _ => [-sc, -tc, -1.0],
+    }
+}
+
+fn equirectangular_to_faces(width: u32, height: u32, pixels: &[u8], size: u32) -> [Vec<u8>; 6] {
+    use std::f32::consts::{PI, TAU};
+
+    // Bilinear lookup, wrapping horizontally and clamping vertically
+    let sample = |u: f32, v: f32| -> [u8; 4] {
+        let x = u * width as f32 - 0.5;
+        let y = (v * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let column = |x: f32| (x as i64).rem_euclid(width as i64) as usize;
+        let row = |y: f32| (y as usize).min(height as usize - 1);
+        let texel = |x: usize, y: usize| &pixels[(y * width as usize + x) * 4..][..4];
+        let (a, b) = (texel(column(x0), row(y0)), texel(column(x0 + 1.0), row(y0)));
+        let (c, d) = (
+            texel(column(x0), row(y0 + 1.0)),
+            texel(column(x0 + 1.0), row(y0 + 1.0)),
+        );
+        [0, 1, 2, 3].map(|i| {
+            let top = a[i] as f32 * (1.0 - fx) + b[i] as f32 * fx;
+            let bottom = c[i] as f32 * (1.0 - fx) + d[i] as f32 * fx;
+            (top * (1.0 - fy) + bottom * fy).round() as u8
+        })
+    };
+
+    std::array::from_fn(|face| {
+        let mut data = Vec::with_capacity((size * size * 4) as usize);
+        for j in 0..size {
+            for i in 0..size {
+                let s = (i as f32 + 0.5) / size as f32;
+                let t = (j as f32 + 0.5) / size as f32;
+                let [x, y, z] = face_direction(face, s, t);
+                let length = (x * x + y * y + z * z).sqrt();
+                let longitude = x.atan2(-z);
+                let latitude = (y / length).clamp(-1.0, 1.0).asin();
+                data.extend_from_slice(&sample(0.5 + longitude / TAU, 0.5 - latitude / PI));
+            }
+        }
+        data
+    })
+}
+
+/// Background drawn where no geometry covers the screen
+#[derive(Debug, Clone, PartialEq)]
+pub enum Skybox {
+    /// Environment cubemap
+    Cubemap(Cubemap),
+    /// Procedural sky blending from the horizon up to the zenith and down to
+    /// the ground (linear colors)
+    Gradient {
+        zenith: [f32; 3],
+        horizon: [f32; 3],
+        ground: [f32; 3],
+    },
+}
+
+impl Default for Skybox {
+    /// Clear daytime sky
+    fn default() -> Self {
+        Self::gradient([0.15, 0.35, 0.8], [0.7, 0.8, 0.95], [0.25, 0.22, 0.2])
+    }
+}
+
+impl Skybox {
+    /// Procedural gradient sky
+    pub fn gradient(zenith: [f32; 3], horizon: [f32; 3], ground: [f32; 3]) -> Self {
+        Self::Gradient {
+            zenith,
+            horizon,
+            ground,
+        }
+    }
+}
+
+impl From<Cubemap> for Skybox {
+    fn from(cubemap: Cubemap) -> Self {
+        Self::Cubemap(cubemap)
+    }
+}
+
+/// Per-view sky data
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SkyUniform {
+    inv_view_proj: [[f32; 4]; 4],
+    zenith: [f32; 4],
+    horizon: [f32; 4],
+    ground: [f32; 4],
+}
+
+/// GPU state for drawing the active skybox
+pub(crate) struct SkyRenderer {
+    skybox: Skybox,
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    uniform_buffer: wgpu::Buffer,
+    stride: wgpu::BufferAddress,
+    bind_group: wgpu::BindGroup,
+    pipelines: HashMap<PipelineKey, wgpu::RenderPipeline>,
+}
+
+impl SkyRenderer {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        skybox: Skybox,
+        max_views: usize,
+    ) -> Self {
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let stride = (std::mem::size_of::<SkyUniform>() as wgpu::BufferAddress).div_ceil(alignment)
+            * alignment;
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sky Buffer"),
+            size: stride * max_views as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<SkyUniform>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("sky_bind_group_layout"),
+        });
+
+        // Gradient skies still need a cube texture bound
+        let cubemap = match &skybox {
+            Skybox::Cubemap(cubemap) => cubemap.clone(),
+            Skybox::Gradient { .. } => Cubemap::from_faces(device, queue, 1, [&[0, 0, 0, 255]; 6])
+                .expect("1x1 cubemap data is valid"),
+        };
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &uniform_buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(std::mem::size_of::<SkyUniform>() as u64),
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&cubemap.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&cubemap.sampler),
+                },
+            ],
+            label: Some("sky_bind_group"),
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Skybox Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/skybox.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Skybox Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        Self {
+            skybox,
+            shader,
+            pipeline_layout,
+            uniform_buffer,
+            stride,
+            bind_group,
+            pipelines: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn skybox(&self) -> &Skybox {
+        &self.skybox
+    }
+
+    /// Swap in new gradient colors without recreating GPU resources; false if
+    /// either sky is a cubemap
+    pub(crate) fn update_gradient(&mut self, skybox: &Skybox) -> bool {
+        match (&self.skybox, skybox) {
+            (Skybox::Gradient { .. }, Skybox::Gradient { .. }) => {
+                self.skybox = skybox.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Pipeline for the given target, created on first use
+    pub(crate) fn pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        (format, samples, write_mask): PipelineKey,
+        depth_format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        self.pipelines
+            .entry((format, samples, write_mask))
+            .or_insert_with(|| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("Skybox Pipeline"),
+                    layout: Some(&self.pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &self.shader,
+                        entry_point: Some("vs_main"),
+                        buffers: &[],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &self.shader,
+                        entry_point: Some("fs_main"),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: Some(wgpu::BlendState::REPLACE),
+                            write_mask,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    // Drawn first, leaving the cleared depth for the scene
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: depth_format,
+                        depth_write_enabled: false,
+                        depth_compare: wgpu::CompareFunction::Always,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        count: samples,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    multiview: None,
+                    cache: None,
+                })
+            })
+            .clone()
+    }
+
+    /// Write one sky slot per `(view, projection)`
+    pub(crate) fn upload(
+        &self,
+        queue: &wgpu::Queue,
+        stats: &mut RenderStats,
+        views: &[(Matrix4<f32>, Matrix4<f32>)],
+    ) {
+        let (zenith, horizon, ground, cubemap) = match &self.skybox {
+            Skybox::Cubemap(_) => ([0.0; 3], [0.0; 3], [0.0; 3], 1.0),
+            Skybox::Gradient {
+                zenith,
+                horizon,
+                ground,
+            } => (*zenith, *horizon, *ground, 0.0),
+        };
+        let rgba = |[r, g, b]: [f32; 3], w| [r, g, b, w];
+        let slots = (self.uniform_buffer.size() / self.stride) as usize;
+        for (index, &(view, proj)) in views.iter().take(slots).enumerate() {
+            // Only the camera's rotation matters for the sky
+            let mut rotation = view;
+            rotation.w = cgmath::Vector4::new(0.0, 0.0, 0.0, 1.0);
+            let inv_view_proj = (proj * rotation).invert().unwrap_or_else(Matrix4::identity);
+            let uniform = SkyUniform {
+                inv_view_proj: inv_view_proj.into(),
+                zenith: rgba(zenith, cubemap),
+                horizon: rgba(horizon, 0.0),
+                ground: rgba(ground, 0.0),
+            };
+            stats.write_buffer(
+                queue,
+                &self.uniform_buffer,
+                index as wgpu::BufferAddress * self.stride,
+                bytemuck::cast_slice(&[uniform]),
+            );
+        }
+    }
+
+    /// Draw the sky for camera slot `view_index`
+    pub(crate) fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        pipeline: &wgpu::RenderPipeline,
+        view_index: usize,
+        stats: &mut RenderStats,
+    ) {
+        let offset = (view_index as wgpu::BufferAddress * self.stride) as wgpu::DynamicOffset;
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[offset]);
+        render_pass.draw(0..3, 0..1);
+        stats.record_draw(wgpu::PrimitiveTopology::TriangleList, 3, 1);
+    }
+}
@@ -0,0 +1,349 @@
+//! Screen-space sprites drawn over the 3D scene
+
+use super::{Renderer, Texture, Viewport, create_texture_bind_group};
+use crate::ecs::{Component, World};
+use crate::math::{Matrix4, Transform};
+use std::collections::HashMap;
+
+/// Where a sprite is placed on screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpriteAnchor {
+    /// Centred on a pixel position, measured from the top left
+    Screen([f32; 2]),
+    /// Centred on the entity's `Transform` position as seen by the camera,
+    /// and hidden when that is behind it
+    World,
+}
+
+/// Textured or solid rectangle drawn over the scene, for HUD icons and 2D
+/// overlays
+///
+/// Sizes are in pixels. Sprites are drawn after post-processing in an
+/// orthographic pass, lowest `layer` first, and ignore scene depth.
+///
+/// ```rust,ignore
+/// let icon = Texture::load_png(renderer.device(), renderer.queue(), "assets/icon.png")?;
+/// world.spawn().with(Sprite::screen([40.0, 40.0], [48.0, 48.0]).with_texture(icon));
+/// world.spawn().with(Transform::at_position(target)).with(Sprite::world([12.0, 12.0]));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sprite {
+    /// Image to draw, or `None` for a solid rectangle
+    pub texture: Option<Texture>,
+    pub size: [f32; 2],
+    /// Linear tint multiplying the texture, alpha included
+    pub color: [f32; 4],
+    /// Part of the texture shown, as UV min and max
+    pub region: [f32; 4],
+    pub anchor: SpriteAnchor,
+    pub layer: i32,
+}
+
+impl Component for Sprite {}
+
+impl Sprite {
+    /// White sprite of `size` centred at a pixel `position`
+    pub fn screen(position: [f32; 2], size: [f32; 2]) -> Self {
+        Self {
+            texture: None,
+            size,
+            color: [1.0, 1.0, 1.0, 1.0],
+            region: [0.0, 0.0, 1.0, 1.0],
+            anchor: SpriteAnchor::Screen(position),
+            layer: 0,
+        }
+    }
+
+    /// White sprite of `size` that follows the entity's `Transform`
+    pub fn world(size: [f32; 2]) -> Self {
+        Self {
+            anchor: SpriteAnchor::World,
+            ..Self::screen([0.0, 0.0], size)
+        }
+    }
+
+    /// Set the texture
+    pub fn with_texture(mut self, texture: Texture) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    /// Set the tint color
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Show only part of the texture, such as one frame of a sprite sheet
+    pub fn with_region(mut self, min: [f32; 2], max: [f32; 2]) -> Self {
+        self.region = [min[0], min[1], max[0], max[1]];
+        self
+    }
+
+    /// Set the draw order; higher layers are drawn on top
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+}
+
+/// Rectangle queued for the sprite pass
+pub(crate) struct SpriteQuad {
+    /// Top left and bottom right in pixels
+    pub(crate) rect: [f32; 4],
+    pub(crate) region: [f32; 4],
+    pub(crate) color: [f32; 4],
+    pub(crate) texture: Option<Texture>,
+}
+
+/// Per-sprite vertex data
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SpriteInstance {
+    rect: [f32; 4],
+    region: [f32; 4],
+    color: [f32; 4],
+}
+
+impl SpriteInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4];
+}
+
+/// Pipelines and buffers for drawing sprites, created once sprites appear
+pub(crate) struct SpriteRenderer {
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    texture_layout: wgpu::BindGroupLayout,
+    pipelines: HashMap<wgpu::TextureFormat, wgpu::RenderPipeline>,
+    // Bound for sprites without a texture
+    white: wgpu::BindGroup,
+    // Per sprite texture, kept while some sprite uses it
+    bind_groups: HashMap<u64, wgpu::BindGroup>,
+    instances: wgpu::Buffer,
+    capacity: usize,
+}
+
+impl SpriteRenderer {
+    fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Sprite Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/sprite.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Sprite Pipeline Layout"),
+            bind_group_layouts: &[texture_layout],
+            push_constant_ranges: &[],
+        });
+        let white =
+            Texture::from_rgba8(device, queue, 1, 1, &[255; 4]).expect("1x1 texture data is valid");
+        let capacity = 64;
+        Self {
+            shader,
+            pipeline_layout,
+            texture_layout: texture_layout.clone(),
+            pipelines: HashMap::new(),
+            white: create_texture_bind_group(device, texture_layout, &white),
+            bind_groups: HashMap::new(),
+            instances: create_instance_buffer(device, capacity),
+            capacity,
+        }
+    }
+
+    /// Pipeline for a target format, created on first use
+    fn pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        self.pipelines
+            .entry(format)
+            .or_insert_with(|| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("Sprite Pipeline"),
+                    layout: Some(&self.pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &self.shader,
+                        entry_point: Some("vs_main"),
+                        buffers: &[wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<SpriteInstance>()
+                                as wgpu::BufferAddress,
+                            step_mode: wgpu::VertexStepMode::Instance,
+                            attributes: &SpriteInstance::ATTRIBUTES,
+                        }],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &self.shader,
+                        entry_point: Some("fs_main"),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                    cache: None,
+                })
+            })
+            .clone()
+    }
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Sprite Instances"),
+        size: (capacity * std::mem::size_of::<SpriteInstance>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+impl Renderer {
+    /// Lay out every `Sprite` (and `Text`, with the `text` feature) for the
+    /// window size into the render list
+    pub(crate) fn extract_sprites(&mut self, world: &World) {
+        let size = (self.config.width, self.config.height);
+        // World anchors land where the scene draws them, inside its viewport
+        let viewport = self.viewport.unwrap_or(Viewport::full(size));
+        let view_proj: Matrix4<f32> = self.scene_projection(size) * self.current_view_matrix;
+        let mut quads = std::mem::take(&mut self.render_list.sprites);
+        quads.clear();
+        for (entity, sprite) in world.query::<Sprite>() {
+            let center = match sprite.anchor {
+                SpriteAnchor::Screen(position) => position,
+                SpriteAnchor::World => {
+                    let Some(transform) = world.get_component::<Transform>(entity) else {
+                        continue;
+                    };
+                    let clip = view_proj * transform.position.extend(1.0);
+                    if clip.w <= 0.0 {
+                        continue;
+                    }
+                    [
+                        viewport.x + (clip.x / clip.w + 1.0) * 0.5 * viewport.width,
+                        viewport.y + (1.0 - clip.y / clip.w) * 0.5 * viewport.height,
+                    ]
+                }
+            };
+            let half = [sprite.size[0] * 0.5, sprite.size[1] * 0.5];
+            quads.push((
+                (sprite.layer, entity),
+                SpriteQuad {
+                    rect: [
+                        center[0] - half[0],
+                        center[1] - half[1],
+                        center[0] + half[0],
+                        center[1] + half[1],
+                    ],
+                    region: sprite.region,
+                    color: sprite.color,
+                    texture: sprite.texture.clone(),
+                },
+            ));
+        }
+        #[cfg(feature = "text")]
+        self.layout_screen_text(world, &mut quads);
+        // Stable, so glyphs of one text keep their order
+        quads.sort_by_key(|&(key, _)| key);
+        self.render_list.sprites = quads;
+    }
+
+    /// Draw the extracted sprites over `target`, stretched from the window
+    /// size to the target's
+    pub(crate) fn draw_sprites(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        (target, format): (&wgpu::TextureView, wgpu::TextureFormat),
+    ) {
+        let quads = &self.render_list.sprites;
+        if quads.is_empty() {
+            return;
+        }
+        let (width, height) = (self.config.width as f32, self.config.height as f32);
+
+        let renderer = self.sprites.get_or_insert_with(|| {
+            SpriteRenderer::new(&self.device, &self.queue, &self.texture_bind_group_layout)
+        });
+
+        // Consecutive quads sharing a texture are drawn together
+        let mut instances = Vec::with_capacity(quads.len());
+        let mut batches: Vec<(Option<u64>, u32)> = Vec::new();
+        let mut unused = std::mem::take(&mut renderer.bind_groups);
+        for (_, quad) in quads {
+            let ndc = |x: f32, y: f32| [x / width * 2.0 - 1.0, 1.0 - y / height * 2.0];
+            let [left, top] = ndc(quad.rect[0], quad.rect[1]);
+            let [right, bottom] = ndc(quad.rect[2], quad.rect[3]);
+            instances.push(SpriteInstance {
+                rect: [left, bottom, right, top],
+                region: quad.region,
+                color: quad.color,
+            });
+
+            let texture = quad.texture.as_ref().map(|texture| {
+                let id = texture.id();
+                if !renderer.bind_groups.contains_key(&id) {
+                    let group = unused.remove(&id).unwrap_or_else(|| {
+                        create_texture_bind_group(&self.device, &renderer.texture_layout, texture)
+                    });
+                    renderer.bind_groups.insert(id, group);
+                }
+                id
+            });
+            match batches.last_mut() {
+                Some((last, count)) if *last == texture => *count += 1,
+                _ => batches.push((texture, 1)),
+            }
+        }
+        if instances.is_empty() {
+            return;
+        }
+
+        if instances.len() > renderer.capacity {
+            renderer.capacity = instances.len().next_power_of_two();
+            renderer.instances = create_instance_buffer(&self.device, renderer.capacity);
+        }
+        self.frame_stats.write_buffer(
+            &self.queue,
+            &renderer.instances,
+            0,
+            bytemuck::cast_slice(&instances),
+        );
+
+        let pipeline = renderer.pipeline(&self.device, format);
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Sprite Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&pipeline);
+        render_pass.set_vertex_buffer(0, renderer.instances.slice(..));
+        let mut first = 0;
+        for (texture, count) in batches {
+            let group = texture.map_or(&renderer.white, |id| &renderer.bind_groups[&id]);
+            render_pass.set_bind_group(0, group, &[]);
+            render_pass.draw(0..6, first..first + count);
+            self.frame_stats
+                .record_draw(wgpu::PrimitiveTopology::TriangleList, 6, count);
+            first += count;
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Per-frame counts of the work the renderer submits
+
+/// Draw calls, geometry, and uploads of one rendered frame
+///
+/// The app keeps the last frame's counts as a resource; `Renderer::render_stats`
+/// returns them too. Every pass counts, post effects and overlays included.
+///
+/// ```no_run
+/// use qsi::graphics::RenderStats;
+/// use qsi::prelude::*;
+///
+/// App::new()
+///     .add_system(|world: &mut World, _: &InputState, time: &TimeState| {
+///         if time.frame_count() % 60 == 0
+///             && let Some(stats) = world.resource::<RenderStats>()
+///         {
+///             println!(
+///                 "{} draws, {} triangles, {} KiB uploaded",
+///                 stats.draw_calls,
+///                 stats.triangles,
+///                 stats.bytes_written / 1024
+///             );
+///         }
+///     })
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    /// Instances over all draw calls, one for a draw without instancing
+    pub instances: u32,
+    /// Vertices of non-indexed draws, times their instances
+    pub vertices: u64,
+    /// Indices of indexed draws, times their instances
+    pub indices: u64,
+    /// Triangles of triangle list and strip draws, times their instances
+    pub triangles: u64,
+    /// Bytes the renderer wrote to buffers and textures; uploads made
+    /// directly through the queue (such as `Mesh::update` or
+    /// `UniformBuffer::upload`) are not seen
+    pub bytes_written: u64,
+}
+
+impl RenderStats {
+    /// Count a draw of `vertices` vertices per instance
+    pub(crate) fn record_draw(
+        &mut self,
+        topology: wgpu::PrimitiveTopology,
+        vertices: u32,
+        instances: u32,
+    ) {
+        self.record(topology, vertices, instances);
+        self.vertices += u64::from(vertices) * u64::from(instances);
+    }
+
+    /// Count an indexed draw of `indices` indices per instance
+    pub(crate) fn record_indexed(
+        &mut self,
+        topology: wgpu::PrimitiveTopology,
+        indices: u32,
+        instances: u32,
+    ) {
+        self.record(topology, indices, instances);
+        self.indices += u64::from(indices) * u64::from(instances);
+    }
+
+    fn record(&mut self, topology: wgpu::PrimitiveTopology, count: u32, instances: u32) {
+        let triangles = match topology {
+            wgpu::PrimitiveTopology::TriangleList => count / 3,
+            wgpu::PrimitiveTopology::TriangleStrip => count.saturating_sub(2),
+            _ => 0,
+        };
+        self.draw_calls += 1;
+        self.instances += instances;
+        self.triangles += u64::from(triangles) * u64::from(instances);
+    }
+
+    /// Write `data` into `buffer` at `offset`, counting the bytes
+    pub(crate) fn write_buffer(
+        &mut self,
+        queue: &wgpu::Queue,
+        buffer: &wgpu::Buffer,
+        offset: wgpu::BufferAddress,
+        data: &[u8],
+    ) {
+        queue.write_buffer(buffer, offset, data);
+        self.bytes_written += data.len() as u64;
+    }
+}
+
+impl std::ops::AddAssign for RenderStats {
+    fn add_assign(&mut self, other: Self) {
+        self.draw_calls += other.draw_calls;
+        self.instances += other.instances;
+        self.vertices += other.vertices;
+        self.indices += other.indices;
+        self.triangles += other.triangles;
+        self.bytes_written += other.bytes_written;
+    }
+}
@@ -0,0 +1,144 @@
+//! Two-view (stereo) rendering
+
+use crate::math::{Matrix4, Vector3};
+
+/// How the two eye views are presented
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StereoMode {
+    /// Left eye in the left half of the window, right eye in the right half
+    /// (parallel viewing, or for headsets and 3D displays)
+    #[default]
+    SideBySide,
+    /// Right eye on the left, left eye on the right, for cross-eyed free viewing
+    CrossEye,
+    /// Both eyes full-window, left in the red channel and right in green and
+    /// blue, for red/cyan glasses
+    Anaglyph,
+}
+
+impl StereoMode {
+    /// The next mode, for cycling through the previews at runtime
+    pub fn next(self) -> Self {
+        match self {
+            StereoMode::SideBySide => StereoMode::CrossEye,
+            StereoMode::CrossEye => StereoMode::Anaglyph,
+            StereoMode::Anaglyph => StereoMode::SideBySide,
+        }
+    }
+
+    /// True if each eye gets half of the window
+    pub fn is_split(self) -> bool {
+        matches!(self, StereoMode::SideBySide | StereoMode::CrossEye)
+    }
+
+    /// Render passes for this mode, in order
+    pub(crate) fn passes(self) -> [EyePass; 2] {
+        let eye = |view_index, viewport, write_mask| EyePass {
+            view_index,
+            viewport,
+            write_mask,
+        };
+        let all = wgpu::ColorWrites::ALL;
+        match self {
+            StereoMode::SideBySide => {
+                [eye(0, Some((0.0, 0.5)), all), eye(1, Some((0.5, 0.5)), all)]
+            }
+            StereoMode::CrossEye => [eye(1, Some((0.0, 0.5)), all), eye(0, Some((0.5, 0.5)), all)],
+            StereoMode::Anaglyph => [
+                eye(0, None, wgpu::ColorWrites::RED),
+                eye(1, None, wgpu::ColorWrites::GREEN | wgpu::ColorWrites::BLUE),
+            ],
+        }
+    }
+}
+
+/// One eye's render pass: which camera slot, which part of the window (x
+/// offset and width as fractions), and which color channels it writes
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EyePass {
+    pub view_index: usize,
+    pub viewport: Option<(f32, f32)>,
+    pub write_mask: wgpu::ColorWrites,
+}
+
+impl EyePass {
+    /// Whole window, all channels, first camera slot
+    pub(crate) fn mono() -> Self {
+        Self {
+            view_index: 0,
+            viewport: None,
+            write_mask: wgpu::ColorWrites::ALL,
+        }
+    }
+}
+
+/// Stereo camera settings: both eyes are derived from the active camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StereoConfig {
+    pub mode: StereoMode,
+    /// Interpupillary distance in world units
+    pub ipd: f32,
+    /// Distance at which the eyes' images coincide (zero parallax)
+    pub convergence: f32,
+}
+
+impl Default for StereoConfig {
+    fn default() -> Self {
+        Self {
+            mode: StereoMode::SideBySide,
+            ipd: 0.064,
+            convergence: 5.0,
+        }
+    }
+}
+
+impl StereoConfig {
+    /// Side-by-side stereo with the given eye separation
+    pub fn side_by_side(ipd: f32) -> Self {
+        Self {
+            ipd,
+            ..Default::default()
+        }
+    }
+
+    /// Red/cyan anaglyph with the given eye separation
+    pub fn anaglyph(ipd: f32) -> Self {
+        Self {
+            mode: StereoMode::Anaglyph,
+            ipd,
+            ..Default::default()
+        }
+    }
+
+    /// Set the presentation mode
+    pub fn with_mode(mut self, mode: StereoMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set the zero-parallax distance
+    pub fn with_convergence(mut self, convergence: f32) -> Self {
+        self.convergence = convergence;
+        self
+    }
+
+    /// Left and right `(view, projection)` pairs for a mono camera
+    ///
+    /// Eyes are offset by ±IPD/2 along the camera's right axis and use parallel
+    /// view directions with off-axis projections converging at `convergence`.
+    pub fn eye_matrices(
+        &self,
+        view: Matrix4<f32>,
+        proj: Matrix4<f32>,
+    ) -> [(Matrix4<f32>, Matrix4<f32>); 2] {
+        let half = self.ipd * 0.5;
+        // NDC shift that cancels the eye offset at the convergence distance
+        let shift = proj.x.x * half / self.convergence.max(1e-3);
+        [-1.0, 1.0].map(|side| {
+            let eye_view = Matrix4::from_translation(Vector3::new(-side * half, 0.0, 0.0)) * view;
+            // A clip-space translation is scaled by w, i.e. a constant NDC offset
+            let eye_proj = Matrix4::from_translation(Vector3::new(side * shift, 0.0, 0.0)) * proj;
+            (eye_view, eye_proj)
+        })
+    }
+}
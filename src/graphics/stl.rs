@@ -0,0 +1,239 @@
+//! Triangle meshes from binary and ASCII STL files
+
+use super::Vertex;
+use super::extrude::finish_mesh;
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Triangles read from an STL file, as exported by most CAD tools
+///
+/// Both the binary and the ASCII variant are read. Normals stored in the file
+/// are ignored: corners at the same position are welded and normals are
+/// recomputed, smooth across shallow edges and split at sharp ones, so
+/// machined parts keep crisp edges while curved faces shade smoothly.
+///
+/// ```rust,ignore
+/// let (vertices, indices) = Stl::load("parts/bracket.stl")?
+///     .with_scale(0.001) // millimeters to meters
+///     .with_z_up(true)
+///     .build()?;
+/// let bracket = renderer.create_mesh(&vertices, &indices);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stl {
+    triangles: Vec<[[f32; 3]; 3]>,
+    /// Factor applied to every coordinate, e.g. 0.001 for parts in millimeters
+    pub scale: f32,
+    /// Treat +Z as up, as most CAD tools do, and turn it into +Y
+    pub z_up: bool,
+    /// Faces meeting at less than this angle in radians are shaded smoothly
+    pub smoothing_angle: f32,
+    pub color: [f32; 3],
+}
+
+impl Stl {
+    /// Read an STL file, binary or ASCII
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_bytes(&bytes).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Parse the contents of an STL file, binary or ASCII
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        // Binary files may also start with "solid", so trust the size first
+        let binary_len = binary_triangle_count(bytes)
+            .and_then(|count| Some((count, count.checked_mul(50)?.checked_add(84)?)));
+        let triangles = match binary_len {
+            Some((count, len)) if bytes.len() == len => parse_binary(bytes, count),
+            _ if bytes.trim_ascii_start().starts_with(b"solid") => parse_ascii(bytes)?,
+            Some((count, len)) if bytes.len() > len => parse_binary(bytes, count),
+            _ => bail!("Not an STL file: too short for its triangle count and not ASCII"),
+        };
+        Ok(Self::from_triangles(triangles))
+    }
+
+    /// Use triangles given as three corners each, counter-clockwise seen
+    /// from outside
+    pub fn from_triangles(triangles: Vec<[[f32; 3]; 3]>) -> Self {
+        Self {
+            triangles,
+            scale: 1.0,
+            z_up: false,
+            smoothing_angle: 30f32.to_radians(),
+            color: [1.0, 1.0, 1.0],
+        }
+    }
+
+    /// Set the factor applied to every coordinate
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Set whether the file uses +Z as up
+    pub fn with_z_up(mut self, z_up: bool) -> Self {
+        self.z_up = z_up;
+        self
+    }
+
+    /// Set the angle below which faces are shaded smoothly (0 for flat)
+    pub fn with_smoothing_angle(mut self, radians: f32) -> Self {
+        self.smoothing_angle = radians;
+        self
+    }
+
+    /// Set the vertex color
+    pub fn with_color(mut self, color: [f32; 3]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Triangles as read from the file, before scaling
+    pub fn triangles(&self) -> &[[[f32; 3]; 3]] {
+        &self.triangles
+    }
+
+    /// Generate welded vertices with normals and triangle indices, skipping
+    /// triangles that collapse to a line or point
+    ///
+    /// Fails if the part has more than 65 536 distinct corners.
+    pub fn build(&self) -> Result<(Vec<Vertex>, Vec<u16>)> {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::with_capacity(self.triangles.len() * 3);
+        let mut welded: HashMap<[u32; 3], u32> = HashMap::new();
+        for triangle in &self.triangles {
+            let corners = triangle.map(|corner| {
+                let [x, y, z] = corner.map(|c| c * self.scale);
+                // Adding 0.0 turns -0.0 into 0.0, so both weld together
+                let position = if self.z_up { [x, z, -y] } else { [x, y, z] }.map(|c| c + 0.0);
+                *welded.entry(position.map(f32::to_bits)).or_insert_with(|| {
+                    vertices.push(Vertex::new(position, self.color));
+                    vertices.len() as u32 - 1
+                })
+            });
+            if corners[0] != corners[1] && corners[1] != corners[2] && corners[0] != corners[2] {
+                indices.extend(corners);
+            }
+        }
+        finish_mesh(vertices, indices, self.smoothing_angle)
+    }
+}
+
+/// Triangle count from a binary header, if the data is long enough to have one
+fn binary_triangle_count(bytes: &[u8]) -> Option<usize> {
+    let count = bytes.get(80..84)?;
+    Some(u32::from_le_bytes(count.try_into().ok()?) as usize)
+}
+
+/// 80-byte header, triangle count, then per triangle a normal, three corners,
+/// and a 2-byte attribute
+fn parse_binary(bytes: &[u8], count: usize) -> Vec<[[f32; 3]; 3]> {
+    bytes[84..84 + 50 * count]
+        .chunks_exact(50)
+        .map(|record| {
+            let float = |i: usize| {
+                let start = 12 + 4 * i;
+                f32::from_le_bytes(record[start..start + 4].try_into().unwrap())
+            };
+            [0, 1, 2].map(|corner| [0, 1, 2].map(|axis| float(corner * 3 + axis)))
+        })
+        .collect()
+}
+
+/// `facet normal … outer loop vertex x y z (×3) endloop endfacet`, repeated
+fn parse_ascii(bytes: &[u8]) -> Result<Vec<[[f32; 3]; 3]>> {
+    let text = std::str::from_utf8(bytes).context("ASCII STL is not valid UTF-8")?;
+    let mut corners = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let mut words = line.split_whitespace();
+        if words.next() != Some("vertex") {
+            continue;
+        }
+        let mut corner = [0.0; 3];
+        for value in &mut corner {
+            *value = words
+                .next()
+                .and_then(|word| word.parse().ok())
+                .with_context(|| format!("Line {}: expected `vertex x y z`", number + 1))?;
+        }
+        corners.push(corner);
+    }
+    if corners.len() % 3 != 0 {
+        bail!(
+            "ASCII STL has {} vertices, not a whole number of triangles",
+            corners.len()
+        );
+    }
+    Ok(corners
+        .chunks_exact(3)
+        .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Binary STL with an 80-byte `header` and one triangle per entry
+    fn binary(header: &[u8], triangles: &[[[f32; 3]; 3]]) -> Vec<u8> {
+        let mut bytes = header.to_vec();
+        bytes.resize(80, b' ');
+        bytes.extend_from_slice(&(triangles.len() as u32).to_le_bytes());
+        for triangle in triangles {
+            bytes.extend_from_slice(&[0; 12]);
+            for value in triangle.iter().flatten() {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes.extend_from_slice(&[0; 2]);
+        }
+        bytes
+    }
+
+    const TRIANGLE: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+
+    #[test]
+    fn reads_binary() {
+        let stl = Stl::from_bytes(&binary(b"part", &[TRIANGLE, TRIANGLE])).unwrap();
+        assert_eq!(stl.triangles(), &[TRIANGLE, TRIANGLE]);
+    }
+
+    #[test]
+    fn binary_starting_with_solid_is_read_as_binary() {
+        let stl = Stl::from_bytes(&binary(b"solid exported by CAD", &[TRIANGLE])).unwrap();
+        assert_eq!(stl.triangles(), &[TRIANGLE]);
+    }
+
+    #[test]
+    fn reads_ascii() {
+        let text = "solid part\nfacet normal 0 0 1\nouter loop\n\
+            vertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid part\n";
+        assert_eq!(
+            Stl::from_bytes(text.as_bytes()).unwrap().triangles(),
+            &[TRIANGLE]
+        );
+    }
+
+    #[test]
+    fn truncated_binary_is_an_error() {
+        let mut bytes = binary(b"part", &[TRIANGLE, TRIANGLE]);
+        bytes.truncate(bytes.len() - 10);
+        assert!(Stl::from_bytes(&bytes).is_err());
+        assert!(Stl::from_bytes(&bytes[..40]).is_err());
+    }
+
+    #[test]
+    fn truncated_ascii_is_an_error() {
+        let text = "solid part\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0";
+        assert!(Stl::from_bytes(text.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn huge_declared_count_is_an_error() {
+        let mut bytes = binary(b"part", &[TRIANGLE]);
+        bytes[80..84].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Stl::from_bytes(&bytes).is_err());
+    }
+}
@@ -0,0 +1,346 @@
+//! Viewable PNGs of intermediate render targets, for debugging
+
+use super::{CapturedImage, RenderTarget, Renderer, linear_to_srgb};
+use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
+
+/// Format depth is copied into for readback: renderable and copyable on
+/// every backend, unlike depth and 32-bit float formats, and precise enough
+/// near 0 to hold `1 - depth`
+const DEPTH_COPY_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// How the texels of a format turn into a viewable image
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Decode {
+    /// 8-bit color, stored as displayed
+    Rgba8 { bgra: bool },
+    /// Linear float color, clamped to 0..1
+    Float { channels: usize, half: bool },
+    /// First float channel, stretched over the range present
+    Gray {
+        channels: usize,
+        half: bool,
+        depth: bool,
+    },
+    /// Integer IDs in the first channel, one color per ID
+    Ids { bytes: usize, channels: usize },
+}
+
+impl Decode {
+    fn of(format: wgpu::TextureFormat) -> Option<Self> {
+        use wgpu::TextureFormat as F;
+        Some(match format {
+            F::Rgba8Unorm | F::Rgba8UnormSrgb => Self::Rgba8 { bgra: false },
+            F::Bgra8Unorm | F::Bgra8UnormSrgb => Self::Rgba8 { bgra: true },
+            F::Rgba16Float => Self::Float {
+                channels: 4,
+                half: true,
+            },
+            F::Rg16Float => Self::Float {
+                channels: 2,
+                half: true,
+            },
+            F::Rgba32Float => Self::Float {
+                channels: 4,
+                half: false,
+            },
+            F::Rg32Float => Self::Float {
+                channels: 2,
+                half: false,
+            },
+            F::R16Float => Self::Gray {
+                channels: 1,
+                half: true,
+                depth: false,
+            },
+            F::R32Float => Self::Gray {
+                channels: 1,
+                half: false,
+                depth: false,
+            },
+            // Depth is first copied into a `DEPTH_COPY_FORMAT` texture
+            F::Depth16Unorm
+            | F::Depth24Plus
+            | F::Depth24PlusStencil8
+            | F::Depth32Float
+            | F::Depth32FloatStencil8 => Self::Gray {
+                channels: 4,
+                half: true,
+                depth: true,
+            },
+            F::R8Uint => Self::Ids {
+                bytes: 1,
+                channels: 1,
+            },
+            F::R16Uint => Self::Ids {
+                bytes: 2,
+                channels: 1,
+            },
+            F::R32Uint => Self::Ids {
+                bytes: 4,
+                channels: 1,
+            },
+            F::Rg32Uint => Self::Ids {
+                bytes: 4,
+                channels: 2,
+            },
+            F::Rgba32Uint => Self::Ids {
+                bytes: 4,
+                channels: 4,
+            },
+            _ => return None,
+        })
+    }
+
+    /// Opaque sRGB RGBA8 pixels from tightly packed texels
+    fn pixels(self, texels: &[u8]) -> Vec<u8> {
+        let float = |bytes: &[u8], half: bool| {
+            if half {
+                f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]]))
+            } else {
+                f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+            }
+        };
+        let encode = |value: f32| (linear_to_srgb(value.clamp(0.0, 1.0)) * 255.0).round() as u8;
+        match self {
+            Self::Rgba8 { bgra } => texels
+                .chunks_exact(4)
+                .flat_map(|texel| match bgra {
+                    true => [texel[2], texel[1], texel[0], 255],
+                    false => [texel[0], texel[1], texel[2], 255],
+                })
+                .collect(),
+            Self::Float { channels, half } => {
+                let size = if half { 2 } else { 4 };
+                texels
+                    .chunks_exact(size * channels)
+                    .flat_map(|texel| {
+                        let mut rgb = [0; 3];
+                        for (c, bytes) in texel.chunks_exact(size).take(3).enumerate() {
+                            rgb[c] = encode(float(bytes, half));
+                        }
+                        [rgb[0], rgb[1], rgb[2], 255]
+                    })
+                    .collect()
+            }
+            Self::Gray {
+                channels,
+                half,
+                depth,
+            } => {
+                let values: Vec<f32> = texels
+                    .chunks_exact(channels * if half { 2 } else { 4 })
+                    .map(|bytes| float(bytes, half))
+                    .collect();
+                // Depth copies hold distance from the far plane, so cleared
+                // depth stays black, and the range of what was drawn is
+                // stretched so near geometry is white
+                let drawn = |value: &&f32| value.is_finite() && !(depth && **value <= 0.0);
+                let min = values
+                    .iter()
+                    .filter(drawn)
+                    .copied()
+                    .fold(f32::MAX, f32::min);
+                let max = values
+                    .iter()
+                    .filter(drawn)
+                    .copied()
+                    .fold(f32::MIN, f32::max);
+                let range = (max - min).max(f32::EPSILON);
+                values
+                    .iter()
+                    .flat_map(|value| {
+                        let level = match drawn(&value) {
+                            true => (255.0 * (value - min) / range).round() as u8,
+                            false => 0,
+                        };
+                        [level, level, level, 255]
+                    })
+                    .collect()
+            }
+            Self::Ids { bytes, channels } => texels
+                .chunks_exact(bytes * channels)
+                .flat_map(|texel| {
+                    let mut id = [0; 4];
+                    id[..bytes].copy_from_slice(&texel[..bytes]);
+                    id_color(u32::from_le_bytes(id))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Distinct color for an ID, black for 0 (nothing)
+fn id_color(id: u32) -> [u8; 4] {
+    if id == 0 {
+        return [0, 0, 0, 255];
+    }
+    // Integer hash, so neighboring IDs get unrelated colors
+    let mut hash = id.wrapping_mul(0x9e37_79b9);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    let [r, g, b, _] = hash.to_le_bytes();
+    // Kept away from black
+    [r | 0x40, g | 0x40, b | 0x40, 255]
+}
+
+/// Half-precision float bits to `f32`
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        31 if mantissa == 0.0 => f32::INFINITY,
+        31 => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+impl Renderer {
+    /// Read a render target back as a viewable image
+    ///
+    /// Color comes out as it is stored, with HDR values clamped; depth and
+    /// single-channel float targets as grayscale stretched over the values
+    /// present (near depth white, cleared depth black); integer targets, such
+    /// as object IDs, as one distinct color per value with 0 black. Color
+    /// textures need `COPY_SRC` usage and depth textures `TEXTURE_BINDING`,
+    /// which the built-in and `create_render_target` targets have.
+    pub fn read_render_target(&self, target: &RenderTarget) -> Result<CapturedImage> {
+        let format = target.format();
+        let Some(decode) = Decode::of(format) else {
+            bail!("Cannot turn {format:?} texels into an image");
+        };
+        let (width, height) = target.size();
+        let texels = if format.is_depth_stencil_format() {
+            self.read_texels(&self.copy_depth(target), wgpu::TextureAspect::All)?
+        } else {
+            self.read_texels(&target.texture, wgpu::TextureAspect::All)?
+        };
+        Ok(CapturedImage {
+            width,
+            height,
+            pixels: decode.pixels(&texels),
+        })
+    }
+
+    /// `1 - depth` of a depth target in `DEPTH_COPY_FORMAT`, since backends
+    /// like GL cannot copy depth to a buffer
+    fn copy_depth(&self, target: &RenderTarget) -> wgpu::Texture {
+        let device = &self.device;
+        let copy = RenderTarget::new(device, "Depth Copy", target.size(), DEPTH_COPY_FORMAT);
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("depth_copy_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    // Bound as plain floats, since GL cannot load texels
+                    // from depth textures
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let depth_view = target.texture.create_view(&wgpu::TextureViewDescriptor {
+            aspect: wgpu::TextureAspect::DepthOnly,
+            ..Default::default()
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("depth_copy_bind_group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&depth_view),
+            }],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Depth Copy Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/depth_copy.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Depth Copy Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Depth Copy Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(DEPTH_COPY_FORMAT.into())],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Depth Copy Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Depth Copy Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &copy.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_pipeline(&pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+        copy.texture
+    }
+
+    /// Save every registered render target (see `render_targets`) as
+    /// `<name>.png` in `dir`, created if missing, returning the files written
+    ///
+    /// Targets that cannot be read back are skipped with a warning. Render
+    /// systems run before the frame is drawn, so calling this from one saves
+    /// the previous frame's buffers.
+    pub fn dump_render_targets(&self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let mut names: Vec<&str> = self.render_targets.names().collect();
+        names.sort_unstable();
+        let mut written = Vec::new();
+        for name in names {
+            let Some(target) = self.render_targets.get(name) else {
+                continue;
+            };
+            let path = dir.join(format!("{name}.png"));
+            match self
+                .read_render_target(target)
+                .and_then(|image| image.save_png(&path))
+            {
+                Ok(()) => written.push(path),
+                Err(e) => log::warn!("Skipped render target `{name}`: {e:#}"),
+            }
+        }
+        Ok(written)
+    }
+}
@@ -0,0 +1,147 @@
+//! Named textures shared between the renderer, systems, and custom passes
+
+use super::Renderer;
+use std::collections::HashMap;
+
+/// Texture and its default view
+#[derive(Debug, Clone)]
+pub struct RenderTarget {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl RenderTarget {
+    /// Texture usable as an attachment, a shader input, and a copy source or
+    /// destination
+    pub fn new(
+        device: &wgpu::Device,
+        label: &str,
+        (width, height): (u32, u32),
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self { texture, view }
+    }
+
+    /// Target for an existing view and the texture it belongs to
+    pub fn from_view(view: &wgpu::TextureView) -> Self {
+        Self {
+            texture: view.texture().clone(),
+            view: view.clone(),
+        }
+    }
+
+    /// Size in pixels
+    pub fn size(&self) -> (u32, u32) {
+        (self.texture.width(), self.texture.height())
+    }
+
+    /// Texel format
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.texture.format()
+    }
+}
+
+/// Registry of named render targets, read with `Renderer::render_targets`
+///
+/// The renderer keeps `DEPTH` and `HDR_COLOR` pointing at the last frame's
+/// buffers, replacing anything registered under those names. Other names are
+/// free for user targets.
+///
+/// ```rust,ignore
+/// renderer.create_render_target("minimap", (256, 256), wgpu::TextureFormat::Rgba8UnormSrgb);
+/// let depth = renderer.render_targets().get(RenderTargets::DEPTH).unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct RenderTargets {
+    targets: HashMap<String, RenderTarget>,
+}
+
+impl RenderTargets {
+    /// Scene depth buffer of the last frame
+    pub const DEPTH: &'static str = "depth";
+    /// Scene color in HDR before post-processing and tonemapping
+    pub const HDR_COLOR: &'static str = "hdr_color";
+    /// Entity ID plus one per pixel, while `Renderer::set_id_picking` is on
+    pub const PICKING_IDS: &'static str = "picking_ids";
+
+    /// Target registered under `name`
+    pub fn get(&self, name: &str) -> Option<&RenderTarget> {
+        self.targets.get(name)
+    }
+
+    /// Whether a target is registered under `name`
+    pub fn contains(&self, name: &str) -> bool {
+        self.targets.contains_key(name)
+    }
+
+    /// Register `target` under `name`, returning the one it replaces
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        target: RenderTarget,
+    ) -> Option<RenderTarget> {
+        self.targets.insert(name.into(), target)
+    }
+
+    /// Unregister the target under `name`
+    pub fn remove(&mut self, name: &str) -> Option<RenderTarget> {
+        self.targets.remove(name)
+    }
+
+    /// Registered names, in no particular order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.targets.keys().map(String::as_str)
+    }
+}
+
+impl Renderer {
+    /// Named textures, including the built-in `RenderTargets::DEPTH` and
+    /// `RenderTargets::HDR_COLOR`
+    pub fn render_targets(&self) -> &RenderTargets {
+        &self.render_targets
+    }
+
+    /// Named textures, to register or remove user targets
+    pub fn render_targets_mut(&mut self) -> &mut RenderTargets {
+        &mut self.render_targets
+    }
+
+    /// Create a `width`×`height` target in `format` and register it under
+    /// `name`, replacing any previous one
+    pub fn create_render_target(
+        &mut self,
+        name: &str,
+        size: (u32, u32),
+        format: wgpu::TextureFormat,
+    ) -> &RenderTarget {
+        let target = RenderTarget::new(&self.device, name, size, format);
+        self.render_targets.insert(name, target);
+        &self.render_targets.targets[name]
+    }
+
+    /// Point the built-in targets at this frame's buffers
+    pub(crate) fn update_builtin_targets(&mut self, hdr_color: &wgpu::TextureView) {
+        let depth = RenderTarget::from_view(&self.depth_view);
+        self.render_targets.insert(RenderTargets::DEPTH, depth);
+        self.render_targets
+            .insert(RenderTargets::HDR_COLOR, RenderTarget::from_view(hdr_color));
+    }
+}
@@ -0,0 +1,679 @@
+//! Screen-space and world-space text drawn from a glyph atlas
+
+use super::sprite::SpriteQuad;
+use super::text_mesh::layout;
+use super::{
+    Font, PipelineKey, RenderStats, Renderer, TextAlign, Texture, create_texture_bind_group,
+};
+use crate::ecs::{Component, EntityId, World};
+use crate::math::{Matrix4, SquareMatrix, Transform};
+use ab_glyph_rasterizer::{Point, Rasterizer, point};
+use std::collections::HashMap;
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+/// Width and height of the glyph atlas in pixels
+const ATLAS_SIZE: u32 = 1024;
+/// Pixels per em that `Text3d` glyphs are rasterized at
+const WORLD_GLYPH_PIXELS: u32 = 64;
+
+/// Text drawn over the scene, for FPS counters, HUD readouts, and debug
+/// output
+///
+/// Sizes are in pixels. Text is drawn with the sprites after
+/// post-processing, ordered by `layer` together with them. Lines split at
+/// `\n`.
+///
+/// ```rust,ignore
+/// renderer.set_default_font(Font::load("assets/DejaVuSans.ttf")?);
+/// world.spawn().with(Text::new("FPS: 60", [10.0, 10.0]).with_size(18.0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Text {
+    pub text: String,
+    /// Font to draw with, or `None` for the renderer's default font
+    pub font: Option<Font>,
+    /// Em size in pixels
+    pub size: f32,
+    pub color: [f32; 4],
+    /// Pixel position of the top of the first line, measured from the top
+    /// left; `align` places each line to the right of, around, or left of it
+    pub position: [f32; 2],
+    pub align: TextAlign,
+    pub layer: i32,
+}
+
+impl Component for Text {}
+
+impl Text {
+    /// White 16-pixel text with its top left at `position`
+    pub fn new(text: impl Into<String>, position: [f32; 2]) -> Self {
+        Self {
+            text: text.into(),
+            font: None,
+            size: 16.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            position,
+            align: TextAlign::Left,
+            layer: 0,
+        }
+    }
+
+    /// Set the font
+    pub fn with_font(mut self, font: Font) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    /// Set the em size in pixels
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Set the color, alpha included
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Set the horizontal alignment
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Set the draw order; higher layers are drawn on top
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+}
+
+/// Flat text placed in the scene at the entity's `Transform`, for axis
+/// labels and entity annotations
+///
+/// The text reads along the transform's +X and faces +Z, with the first
+/// line's baseline through its origin, like `TextMesh`. It is hidden behind
+/// scene geometry but does not hide anything itself.
+///
+/// ```rust,ignore
+/// world
+///     .spawn()
+///     .with(Transform::at_position(Vector3::new(0.0, 2.0, 0.0)))
+///     .with(Text3d::new("Player").with_size(0.4).with_align(TextAlign::Center).with_billboard(true));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Text3d {
+    pub text: String,
+    /// Font to draw with, or `None` for the renderer's default font
+    pub font: Option<Font>,
+    /// Em size in world units
+    pub size: f32,
+    pub color: [f32; 4],
+    pub align: TextAlign,
+    /// Turn to face the camera, keeping only the transform's position and
+    /// scale
+    pub billboard: bool,
+}
+
+impl Component for Text3d {}
+
+impl Text3d {
+    /// White one-unit text
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            font: None,
+            size: 1.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            align: TextAlign::Left,
+            billboard: false,
+        }
+    }
+
+    /// Set the font
+    pub fn with_font(mut self, font: Font) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    /// Set the em size in world units
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Set the color, alpha included
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Set the horizontal alignment
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Set whether the text turns to face the camera
+    pub fn with_billboard(mut self, billboard: bool) -> Self {
+        self.billboard = billboard;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font: u64,
+    glyph: u16,
+    pixels: u32,
+}
+
+/// Where a rasterized glyph sits in the atlas
+#[derive(Debug, Clone, Copy)]
+struct AtlasGlyph {
+    /// Pixel offset of the top left corner from the pen position, y down
+    offset: [f32; 2],
+    size: [f32; 2],
+    /// UV min and max
+    region: [f32; 4],
+}
+
+/// Glyph coverage packed into rows of one texture, kept until it fills up
+struct GlyphAtlas {
+    texture: Texture,
+    // `None` for glyphs with nothing to draw, such as spaces
+    glyphs: HashMap<GlyphKey, Option<AtlasGlyph>>,
+    // Top left of the free space on the current row
+    cursor: [u32; 2],
+    row_height: u32,
+    // Set when a glyph did not fit; the atlas is emptied before the next frame
+    full: bool,
+}
+
+impl GlyphAtlas {
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        // White everywhere so filtering at glyph edges only fades alpha
+        let pixels = [255, 255, 255, 0].repeat((ATLAS_SIZE * ATLAS_SIZE) as usize);
+        let texture = Texture::from_rgba8_linear(device, queue, ATLAS_SIZE, ATLAS_SIZE, &pixels)
+            .expect("atlas data matches its size");
+        Self {
+            texture,
+            glyphs: HashMap::new(),
+            cursor: [0, 0],
+            row_height: 0,
+            full: false,
+        }
+    }
+
+    /// Forget every glyph if the atlas ran out of room last frame
+    fn begin_frame(&mut self) {
+        if self.full {
+            self.glyphs.clear();
+            self.cursor = [0, 0];
+            self.row_height = 0;
+            self.full = false;
+        }
+    }
+
+    /// Glyph rasterized at `pixels` per em, added on first use; `None` if it
+    /// has no outline or no longer fits
+    fn glyph(
+        &mut self,
+        (queue, stats): (&wgpu::Queue, &mut RenderStats),
+        (font, face): (&Font, &Face<'_>),
+        glyph: GlyphId,
+        pixels: u32,
+    ) -> Option<AtlasGlyph> {
+        let key = GlyphKey {
+            font: font.id(),
+            glyph: glyph.0,
+            pixels,
+        };
+        if let Some(&cached) = self.glyphs.get(&key) {
+            return cached;
+        }
+        let Some((offset, [width, height], coverage)) = rasterize(face, glyph, pixels) else {
+            self.glyphs.insert(key, None);
+            return None;
+        };
+        let Some([x, y]) = self.allocate(width, height) else {
+            self.full = true;
+            return None;
+        };
+
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.texture.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &coverage,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width * 4),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        stats.bytes_written += coverage.len() as u64;
+        let uv = |texels: u32| texels as f32 / ATLAS_SIZE as f32;
+        let placed = AtlasGlyph {
+            offset,
+            size: [width as f32, height as f32],
+            region: [uv(x), uv(y), uv(x + width), uv(y + height)],
+        };
+        self.glyphs.insert(key, Some(placed));
+        Some(placed)
+    }
+
+    /// Top left of a free `width`×`height` area, one texel away from its
+    /// neighbours and the atlas edges
+    fn allocate(&mut self, width: u32, height: u32) -> Option<[u32; 2]> {
+        if self.cursor[0] + width + 2 > ATLAS_SIZE {
+            self.cursor = [0, self.cursor[1] + self.row_height];
+            self.row_height = 0;
+        }
+        if self.cursor[0] + width + 2 > ATLAS_SIZE || self.cursor[1] + height + 2 > ATLAS_SIZE {
+            return None;
+        }
+        let position = [self.cursor[0] + 1, self.cursor[1] + 1];
+        self.cursor[0] += width + 1;
+        self.row_height = self.row_height.max(height + 1);
+        Some(position)
+    }
+}
+
+/// White RGBA8 pixels with glyph coverage in alpha, along with the top left
+/// offset from the pen position and the size
+fn rasterize(
+    face: &Face<'_>,
+    glyph: GlyphId,
+    pixels: u32,
+) -> Option<([f32; 2], [u32; 2], Vec<u8>)> {
+    let bounds = face.glyph_bounding_box(glyph)?;
+    let scale = pixels as f32 / face.units_per_em() as f32;
+    // Pixel rows run down while font units run up
+    let left = (bounds.x_min as f32 * scale).floor();
+    let top = (-bounds.y_max as f32 * scale).floor();
+    let width = ((bounds.x_max as f32 * scale).ceil() - left).max(1.0) as u32;
+    let height = ((-bounds.y_min as f32 * scale).ceil() - top).max(1.0) as u32;
+
+    let mut outline = Coverage {
+        rasterizer: Rasterizer::new(width as usize, height as usize),
+        scale,
+        origin: [left, top],
+        start: point(0.0, 0.0),
+        last: point(0.0, 0.0),
+    };
+    face.outline_glyph(glyph, &mut outline)?;
+    let mut rgba = [255, 255, 255, 0].repeat((width * height) as usize);
+    outline.rasterizer.for_each_pixel_2d(|x, y, alpha| {
+        rgba[((y * width + x) * 4 + 3) as usize] = (alpha.clamp(0.0, 1.0) * 255.0).round() as u8;
+    });
+    Some(([left, top], [width, height], rgba))
+}
+
+/// Glyph outline drawn into a coverage rasterizer, in pixels from the top
+/// left of the glyph's box
+struct Coverage {
+    rasterizer: Rasterizer,
+    scale: f32,
+    origin: [f32; 2],
+    start: Point,
+    last: Point,
+}
+
+impl Coverage {
+    fn point(&self, x: f32, y: f32) -> Point {
+        point(
+            x * self.scale - self.origin[0],
+            -y * self.scale - self.origin[1],
+        )
+    }
+}
+
+impl OutlineBuilder for Coverage {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = self.point(x, y);
+        self.last = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let next = self.point(x, y);
+        self.rasterizer.draw_line(self.last, next);
+        self.last = next;
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let next = self.point(x, y);
+        self.rasterizer
+            .draw_quad(self.last, self.point(x1, y1), next);
+        self.last = next;
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let next = self.point(x, y);
+        self.rasterizer
+            .draw_cubic(self.last, self.point(x1, y1), self.point(x2, y2), next);
+        self.last = next;
+    }
+
+    fn close(&mut self) {
+        if self.last != self.start {
+            self.rasterizer.draw_line(self.last, self.start);
+        }
+        self.last = self.start;
+    }
+}
+
+/// Per-glyph vertex data for `Text3d`
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct GlyphInstance {
+    origin: [f32; 4],
+    right: [f32; 4],
+    up: [f32; 4],
+    rect: [f32; 4],
+    region: [f32; 4],
+    color: [f32; 4],
+}
+
+impl GlyphInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+        0 => Float32x4, 1 => Float32x4, 2 => Float32x4,
+        3 => Float32x4, 4 => Float32x4, 5 => Float32x4,
+    ];
+}
+
+/// Glyph atlas, default font, and GPU state for `Text3d`, created once text
+/// appears
+pub(crate) struct TextRenderer {
+    atlas: GlyphAtlas,
+    default_font: Option<Font>,
+    warned_no_font: bool,
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipelines: HashMap<PipelineKey, wgpu::RenderPipeline>,
+    atlas_bind_group: wgpu::BindGroup,
+    instances: wgpu::Buffer,
+    capacity: usize,
+    glyph_count: u32,
+}
+
+impl TextRenderer {
+    fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        (uniform_layout, texture_layout): (&wgpu::BindGroupLayout, &wgpu::BindGroupLayout),
+    ) -> Self {
+        let atlas = GlyphAtlas::new(device, queue);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Text Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/text.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Text Pipeline Layout"),
+            bind_group_layouts: &[uniform_layout, texture_layout],
+            push_constant_ranges: &[],
+        });
+        let capacity = 256;
+        Self {
+            atlas_bind_group: create_texture_bind_group(device, texture_layout, &atlas.texture),
+            atlas,
+            default_font: None,
+            warned_no_font: false,
+            shader,
+            pipeline_layout,
+            pipelines: HashMap::new(),
+            instances: create_instance_buffer(device, capacity),
+            capacity,
+            glyph_count: 0,
+        }
+    }
+
+    /// `font`, or the default font if it is `None`
+    fn font(&mut self, font: Option<&Font>) -> Option<Font> {
+        let font = font.or(self.default_font.as_ref()).cloned();
+        if font.is_none() && !self.warned_no_font {
+            log::warn!("Skipping text with no font; set one with `Renderer::set_default_font`");
+            self.warned_no_font = true;
+        }
+        font
+    }
+
+    /// `Text3d` pipeline for the given target, created on first use
+    pub(crate) fn pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        (format, samples, write_mask): PipelineKey,
+        depth_format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        self.pipelines
+            .entry((format, samples, write_mask))
+            .or_insert_with(|| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("Text Pipeline"),
+                    layout: Some(&self.pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &self.shader,
+                        entry_point: Some("vs_main"),
+                        buffers: &[wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<GlyphInstance>()
+                                as wgpu::BufferAddress,
+                            step_mode: wgpu::VertexStepMode::Instance,
+                            attributes: &GlyphInstance::ATTRIBUTES,
+                        }],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &self.shader,
+                        entry_point: Some("fs_main"),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: Some(wgpu::BlendState {
+                                color: wgpu::BlendComponent {
+                                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                                    operation: wgpu::BlendOperation::Add,
+                                },
+                                alpha: wgpu::BlendComponent::OVER,
+                            }),
+                            write_mask,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    // Both sides are drawn so text stays visible from behind
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: depth_format,
+                        depth_write_enabled: false,
+                        depth_compare: wgpu::CompareFunction::Less,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        count: samples,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    multiview: None,
+                    cache: None,
+                })
+            })
+            .clone()
+    }
+
+    /// Draw the glyphs prepared by `Renderer::prepare_world_text`, with the
+    /// scene bind group already set
+    pub(crate) fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        pipeline: &wgpu::RenderPipeline,
+        stats: &mut RenderStats,
+    ) {
+        if self.glyph_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(1, &self.atlas_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instances.slice(..));
+        render_pass.draw(0..6, 0..self.glyph_count);
+        stats.record_draw(wgpu::PrimitiveTopology::TriangleList, 6, self.glyph_count);
+    }
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Text Glyph Instances"),
+        size: (capacity * std::mem::size_of::<GlyphInstance>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+impl Renderer {
+    /// Font used by `Text` and `Text3d` components that do not set one
+    pub fn set_default_font(&mut self, font: Font) {
+        self.text_renderer().default_font = Some(font);
+    }
+
+    /// Font used by text components that do not set one
+    pub fn default_font(&self) -> Option<&Font> {
+        self.text.as_ref()?.default_font.as_ref()
+    }
+
+    fn text_renderer(&mut self) -> &mut TextRenderer {
+        self.text.get_or_insert_with(|| {
+            TextRenderer::new(
+                &self.device,
+                &self.queue,
+                (
+                    &self.uniform_bind_group_layout,
+                    &self.texture_bind_group_layout,
+                ),
+            )
+        })
+    }
+
+    /// Rasterize and upload the glyphs of every `Text3d` for this frame
+    pub(crate) fn prepare_world_text(&mut self, world: &World) {
+        let texts: Vec<_> = world.query::<Text3d>().collect();
+        if !texts.is_empty() {
+            self.text_renderer();
+        }
+        let Some(renderer) = &mut self.text else {
+            return;
+        };
+        let (device, queue, stats) = (&self.device, &self.queue, &mut self.frame_stats);
+        renderer.atlas.begin_frame();
+
+        let mut instances = Vec::new();
+        for (entity, text) in texts {
+            let Some(font) = renderer.font(text.font.as_ref()) else {
+                continue;
+            };
+            let face = font.face();
+            let model = world
+                .get_component::<Transform>(entity)
+                .map_or_else(Matrix4::identity, Transform::matrix);
+            let axis = |column: cgmath::Vector4<f32>| [column.x, column.y, column.z, 0.0];
+            let units = text.size / face.units_per_em() as f32;
+            let texel = text.size / WORLD_GLYPH_PIXELS as f32;
+            for (glyph, [x, y]) in layout(&face, &text.text, text.align, 1.0) {
+                let Some(placed) =
+                    renderer
+                        .atlas
+                        .glyph((queue, stats), (&font, &face), glyph, WORLD_GLYPH_PIXELS)
+                else {
+                    continue;
+                };
+                let left = x * units + placed.offset[0] * texel;
+                let top = y * units - placed.offset[1] * texel;
+                instances.push(GlyphInstance {
+                    origin: [
+                        model.w.x,
+                        model.w.y,
+                        model.w.z,
+                        if text.billboard { 1.0 } else { 0.0 },
+                    ],
+                    right: axis(model.x),
+                    up: axis(model.y),
+                    rect: [
+                        left,
+                        top - placed.size[1] * texel,
+                        left + placed.size[0] * texel,
+                        top,
+                    ],
+                    region: placed.region,
+                    color: text.color,
+                });
+            }
+        }
+
+        if instances.len() > renderer.capacity {
+            renderer.capacity = instances.len().next_power_of_two();
+            renderer.instances = create_instance_buffer(device, renderer.capacity);
+        }
+        stats.write_buffer(
+            queue,
+            &renderer.instances,
+            0,
+            bytemuck::cast_slice(&instances),
+        );
+        renderer.glyph_count = instances.len() as u32;
+    }
+
+    /// Add one quad per glyph of every `Text` to the sprite pass, keyed by
+    /// layer and entity
+    pub(crate) fn layout_screen_text(
+        &mut self,
+        world: &World,
+        quads: &mut Vec<((i32, EntityId), SpriteQuad)>,
+    ) {
+        let texts: Vec<_> = world.query::<Text>().collect();
+        if texts.is_empty() {
+            return;
+        }
+        self.text_renderer();
+        let Some(renderer) = &mut self.text else {
+            return;
+        };
+        let (queue, stats) = (&self.queue, &mut self.frame_stats);
+        for (entity, text) in texts {
+            let Some(font) = renderer.font(text.font.as_ref()) else {
+                continue;
+            };
+            let face = font.face();
+            // Whole pixel sizes and pen positions keep glyphs crisp
+            let pixels = text.size.round().max(1.0) as u32;
+            let scale = pixels as f32 / face.units_per_em() as f32;
+            let baseline = text.position[1] + (face.ascender() as f32 * scale).round();
+            for (glyph, [x, y]) in layout(&face, &text.text, text.align, 1.0) {
+                let Some(placed) =
+                    renderer
+                        .atlas
+                        .glyph((queue, stats), (&font, &face), glyph, pixels)
+                else {
+                    continue;
+                };
+                let left = (text.position[0] + x * scale).round() + placed.offset[0];
+                let top = (baseline - y * scale).round() + placed.offset[1];
+                quads.push((
+                    (text.layer, entity),
+                    SpriteQuad {
+                        rect: [left, top, left + placed.size[0], top + placed.size[1]],
+                        region: placed.region,
+                        color: text.color,
+                        texture: Some(renderer.atlas.texture.clone()),
+                    },
+                ));
+            }
+        }
+    }
+}
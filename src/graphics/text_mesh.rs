@@ -0,0 +1,522 @@
+//! Extruded 3D text built from TrueType/OpenType glyph outlines
+
+use super::Vertex;
+use super::extrude::finish_mesh;
+use anyhow::{Context, Result, anyhow};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+type Point = [f32; 2];
+
+static NEXT_FONT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Font file for building `TextMesh`es
+///
+/// Cloning is cheap and shares the font data.
+#[derive(Clone)]
+pub struct Font {
+    data: Arc<[u8]>,
+    id: u64,
+}
+
+impl std::fmt::Debug for Font {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Font")
+            .field("bytes", &self.data.len())
+            .finish()
+    }
+}
+
+impl Font {
+    /// Font from the bytes of a TTF or OTF file (the first face of a collection)
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        Face::parse(&data, 0).map_err(|e| anyhow!("Failed to parse font: {e}"))?;
+        Ok(Self {
+            data: data.into(),
+            id: NEXT_FONT_ID.fetch_add(1, Ordering::Relaxed),
+        })
+    }
+
+    /// Load a TTF or OTF file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_bytes(data).with_context(|| format!("Failed to load {}", path.display()))
+    }
+
+    pub(super) fn face(&self) -> Face<'_> {
+        Face::parse(&self.data, 0).expect("font was validated when loaded")
+    }
+
+    /// Identifier shared by clones of this font
+    #[cfg_attr(not(feature = "text"), allow(dead_code))]
+    pub(super) fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+/// Horizontal placement of each line relative to the origin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Text as solid geometry: glyph outlines filled front and back and joined
+/// by side walls
+///
+/// The text reads along +X and faces +Z, with the first line's baseline on
+/// y = 0 and later lines (split at `\n`) below it.
+///
+/// ```rust,ignore
+/// let font = Font::load("assets/DejaVuSans.ttf")?;
+/// let (vertices, indices) = TextMesh::new("qsi").with_depth(0.2).build(&font)?;
+/// world.spawn().with(renderer.create_mesh(&vertices, &indices));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextMesh {
+    pub text: String,
+    /// Em size (roughly the height of a line) in world units
+    pub size: f32,
+    /// Thickness along Z, centered on z = 0; 0 gives a single front face
+    pub depth: f32,
+    pub align: TextAlign,
+    /// Multiplier on the font's line height
+    pub line_spacing: f32,
+    /// Straight segments per curve of the outline
+    pub curve_segments: u32,
+    /// Side walls meeting at less than this angle (radians) are shaded smoothly
+    pub smoothing_angle: f32,
+    pub color: [f32; 3],
+}
+
+impl TextMesh {
+    /// One-unit text, 0.2 deep
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            size: 1.0,
+            depth: 0.2,
+            align: TextAlign::Left,
+            line_spacing: 1.0,
+            curve_segments: 8,
+            smoothing_angle: 30f32.to_radians(),
+            color: [1.0, 1.0, 1.0],
+        }
+    }
+
+    /// Set the em size in world units
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Set the extrusion depth
+    pub fn with_depth(mut self, depth: f32) -> Self {
+        self.depth = depth.max(0.0);
+        self
+    }
+
+    /// Set the horizontal alignment
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Set the number of segments per outline curve
+    pub fn with_curve_segments(mut self, segments: u32) -> Self {
+        self.curve_segments = segments.max(1);
+        self
+    }
+
+    /// Set the vertex color
+    pub fn with_color(mut self, color: [f32; 3]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Generate vertices (with normals, UVs, and tangents) and triangle indices
+    pub fn build(&self, font: &Font) -> Result<(Vec<Vertex>, Vec<u16>)> {
+        let face = font.face();
+        let scale = self.size / face.units_per_em() as f32;
+        let mut mesh = GlyphMesh {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            depth: self.depth,
+            size: self.size,
+            color: self.color,
+        };
+        for (glyph, [x, y]) in layout(&face, &self.text, self.align, self.line_spacing) {
+            let mut outline = Outline {
+                contours: Vec::new(),
+                current: Vec::new(),
+                segments: self.curve_segments.max(1),
+            };
+            if face.outline_glyph(glyph, &mut outline).is_none() {
+                continue;
+            }
+            let contours = outline
+                .contours
+                .into_iter()
+                .map(|contour| {
+                    contour
+                        .into_iter()
+                        .map(|[px, py]| [(x + px) * scale, (y + py) * scale])
+                        .collect()
+                })
+                .collect();
+            mesh.add_glyph(contours);
+        }
+        finish_mesh(mesh.vertices, mesh.indices, self.smoothing_angle)
+    }
+}
+
+/// Glyphs of `text` with their pen positions in font units
+///
+/// Lines split at `\n` are placed by `align` around x = 0, with the first
+/// baseline on y = 0 and later ones below it.
+pub(super) fn layout(
+    face: &Face<'_>,
+    text: &str,
+    align: TextAlign,
+    line_spacing: f32,
+) -> Vec<(GlyphId, Point)> {
+    let line_height =
+        (face.ascender() as f32 - face.descender() as f32 + face.line_gap() as f32) * line_spacing;
+    let mut placed = Vec::new();
+    for (row, line) in text.lines().enumerate() {
+        let glyphs: Vec<GlyphId> = line
+            .chars()
+            .map(|c| face.glyph_index(c).unwrap_or(GlyphId(0)))
+            .collect();
+        // Pen position of each glyph, with pair kerning
+        let mut pen = 0.0;
+        let start = placed.len();
+        for (i, &glyph) in glyphs.iter().enumerate() {
+            if i > 0 {
+                pen += kerning(face, glyphs[i - 1], glyph);
+            }
+            placed.push((glyph, [pen, -(row as f32) * line_height]));
+            pen += face.glyph_hor_advance(glyph).unwrap_or(0) as f32;
+        }
+        let offset = match align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => -pen / 2.0,
+            TextAlign::Right => -pen,
+        };
+        for (_, position) in &mut placed[start..] {
+            position[0] += offset;
+        }
+    }
+    placed
+}
+
+/// Horizontal kerning between two glyphs from the `kern` table, in font units
+fn kerning(face: &Face<'_>, left: GlyphId, right: GlyphId) -> f32 {
+    face.tables()
+        .kern
+        .and_then(|kern| {
+            kern.subtables
+                .into_iter()
+                .filter(|table| table.horizontal && !table.variable)
+                .find_map(|table| table.glyphs_kerning(left, right))
+        })
+        .unwrap_or(0) as f32
+}
+
+/// Glyph outline flattened into closed polylines
+struct Outline {
+    contours: Vec<Vec<Point>>,
+    current: Vec<Point>,
+    segments: u32,
+}
+
+impl Outline {
+    fn last(&self) -> Point {
+        self.current.last().copied().unwrap_or([0.0, 0.0])
+    }
+
+    fn flatten(&mut self, point: impl Fn(f32) -> Point) {
+        for step in 1..=self.segments {
+            self.current.push(point(step as f32 / self.segments as f32));
+        }
+    }
+}
+
+impl OutlineBuilder for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.close();
+        self.current.push([x, y]);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.current.push([x, y]);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let p0 = self.last();
+        self.flatten(|t| {
+            let u = 1.0 - t;
+            [0, 1].map(|i| u * u * p0[i] + 2.0 * u * t * [x1, y1][i] + t * t * [x, y][i])
+        });
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let p0 = self.last();
+        self.flatten(|t| {
+            let u = 1.0 - t;
+            [0, 1].map(|i| {
+                u * u * u * p0[i]
+                    + 3.0 * u * u * t * [x1, y1][i]
+                    + 3.0 * u * t * t * [x2, y2][i]
+                    + t * t * t * [x, y][i]
+            })
+        });
+    }
+
+    fn close(&mut self) {
+        let mut contour = std::mem::take(&mut self.current);
+        contour.dedup();
+        if contour.len() > 1 && contour.first() == contour.last() {
+            contour.pop();
+        }
+        if contour.len() >= 3 {
+            self.contours.push(contour);
+        }
+    }
+}
+
+/// Vertices and triangles accumulated across glyphs
+struct GlyphMesh {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    depth: f32,
+    size: f32,
+    color: [f32; 3],
+}
+
+impl GlyphMesh {
+    /// Fill and extrude one glyph's contours (already in world units)
+    fn add_glyph(&mut self, mut contours: Vec<Vec<Point>>) {
+        // Contours inside an even number of others are filled; the rest are holes
+        let nesting: Vec<usize> = contours
+            .iter()
+            .enumerate()
+            .map(|(i, contour)| {
+                contours
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, other)| j != i && contains(other, contour[0]))
+                    .count()
+            })
+            .collect();
+        for (contour, &level) in contours.iter_mut().zip(&nesting) {
+            // Filled contours run counter-clockwise, holes clockwise
+            if (signed_area(contour) > 0.0) != level.is_multiple_of(2) {
+                contour.reverse();
+            }
+        }
+
+        for (i, outer) in contours.iter().enumerate() {
+            if !nesting[i].is_multiple_of(2) {
+                continue;
+            }
+            let holes: Vec<&[Point]> = contours
+                .iter()
+                .enumerate()
+                .filter(|&(j, hole)| nesting[j] == nesting[i] + 1 && contains(outer, hole[0]))
+                .map(|(_, hole)| hole.as_slice())
+                .collect();
+            let polygon = bridge_holes(outer, &holes);
+            let triangles = triangulate(&polygon);
+            if self.depth > 0.0 {
+                self.add_cap(&polygon, &triangles, self.depth / 2.0, true);
+                self.add_cap(&polygon, &triangles, -self.depth / 2.0, false);
+            } else {
+                self.add_cap(&polygon, &triangles, 0.0, true);
+            }
+        }
+        if self.depth > 0.0 {
+            for contour in &contours {
+                self.add_wall(contour);
+            }
+        }
+    }
+
+    /// Flat face at height `z`, facing +Z if `front` and -Z otherwise
+    fn add_cap(&mut self, polygon: &[Point], triangles: &[[usize; 3]], z: f32, front: bool) {
+        let base = self.vertices.len() as u32;
+        for &[x, y] in polygon {
+            let uv = [x / self.size, -y / self.size];
+            self.vertices
+                .push(Vertex::new([x, y, z], self.color).with_uv(uv));
+        }
+        for &[a, b, c] in triangles {
+            let [a, b, c] = [a, b, c].map(|index| base + index as u32);
+            if front {
+                self.indices.extend_from_slice(&[a, b, c]);
+            } else {
+                self.indices.extend_from_slice(&[a, c, b]);
+            }
+        }
+    }
+
+    /// Side wall along a contour, facing away from the filled area
+    fn add_wall(&mut self, contour: &[Point]) {
+        let (front, back) = (self.depth / 2.0, -self.depth / 2.0);
+        let base = self.vertices.len() as u32;
+        let mut distance = 0.0;
+        // The first point is repeated at the end so the texture seam has its own UVs
+        for k in 0..=contour.len() {
+            let [x, y] = contour[k % contour.len()];
+            if k > 0 {
+                let [px, py] = contour[k - 1];
+                distance += ((x - px).powi(2) + (y - py).powi(2)).sqrt();
+            }
+            let u = distance / self.size;
+            self.vertices
+                .push(Vertex::new([x, y, front], self.color).with_uv([u, 0.0]));
+            self.vertices
+                .push(Vertex::new([x, y, back], self.color).with_uv([u, 1.0]));
+        }
+        for k in 0..contour.len() as u32 {
+            let (a_front, a_back) = (base + 2 * k, base + 2 * k + 1);
+            let (b_front, b_back) = (a_front + 2, a_back + 2);
+            self.indices
+                .extend_from_slice(&[a_front, b_back, b_front, a_front, a_back, b_back]);
+        }
+    }
+}
+
+fn cross(o: Point, a: Point, b: Point) -> f32 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+/// Twice the signed area; positive for counter-clockwise polygons
+fn signed_area(polygon: &[Point]) -> f32 {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| a[0] * b[1] - b[0] * a[1])
+        .sum()
+}
+
+/// Even-odd point in polygon test
+fn contains(polygon: &[Point], [x, y]: Point) -> bool {
+    let mut inside = false;
+    for (a, b) in polygon.iter().zip(polygon.iter().cycle().skip(1)) {
+        if (a[1] > y) != (b[1] > y) && x < a[0] + (y - a[1]) / (b[1] - a[1]) * (b[0] - a[0]) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// True if segments `p1 p2` and `q1 q2` cross at a point inside both
+fn segments_cross(p1: Point, p2: Point, q1: Point, q2: Point) -> bool {
+    let d1 = cross(q1, q2, p1);
+    let d2 = cross(q1, q2, p2);
+    let d3 = cross(p1, p2, q1);
+    let d4 = cross(p1, p2, q2);
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+/// Join clockwise `holes` into the counter-clockwise `outer` ring with
+/// zero-width cuts, giving one polygon that ear clipping can fill
+fn bridge_holes(outer: &[Point], holes: &[&[Point]]) -> Vec<Point> {
+    let mut ring = outer.to_vec();
+    let mut holes = holes.to_vec();
+    // Rightmost holes first, so later cuts can't be blocked by earlier ones
+    let rightmost = |hole: &[Point]| hole.iter().map(|p| p[0]).fold(f32::MIN, f32::max);
+    holes.sort_by(|a, b| rightmost(b).total_cmp(&rightmost(a)));
+
+    for (index, hole) in holes.iter().enumerate() {
+        let start = (0..hole.len())
+            .max_by(|&a, &b| hole[a][0].total_cmp(&hole[b][0]))
+            .unwrap_or(0);
+        let from = hole[start];
+        // Nearest ring vertex whose cut crosses no edge of the ring or any hole
+        let edges = |polygon: &[Point]| {
+            (0..polygon.len())
+                .map(|k| (polygon[k], polygon[(k + 1) % polygon.len()]))
+                .collect::<Vec<_>>()
+        };
+        let mut blockers = edges(&ring);
+        for other in &holes[index..] {
+            blockers.extend(edges(other));
+        }
+        let distance = |p: Point| (p[0] - from[0]).powi(2) + (p[1] - from[1]).powi(2);
+        let mut candidates: Vec<usize> = (0..ring.len()).collect();
+        candidates.sort_by(|&a, &b| distance(ring[a]).total_cmp(&distance(ring[b])));
+        let target = candidates
+            .iter()
+            .copied()
+            .find(|&k| {
+                let to = ring[k];
+                blockers.iter().all(|&(a, b)| {
+                    [a, b].iter().any(|&p| p == to || p == from) || !segments_cross(from, to, a, b)
+                })
+            })
+            .unwrap_or(candidates[0]);
+
+        // ring up to the target, around the hole and back to the target
+        let mut bridged = Vec::with_capacity(ring.len() + hole.len() + 2);
+        bridged.extend_from_slice(&ring[..=target]);
+        bridged.extend_from_slice(&hole[start..]);
+        bridged.extend_from_slice(&hole[..=start]);
+        bridged.extend_from_slice(&ring[target..]);
+        ring = bridged;
+    }
+    ring
+}
+
+/// Ear clipping of a counter-clockwise polygon into index triangles
+fn triangulate(polygon: &[Point]) -> Vec<[usize; 3]> {
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+    let mut triangles = Vec::with_capacity(polygon.len().saturating_sub(2));
+    let mut i = 0;
+    let mut misses = 0;
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let (a, b, c) = (
+            remaining[(i + n - 1) % n],
+            remaining[i % n],
+            remaining[(i + 1) % n],
+        );
+        let (pa, pb, pc) = (polygon[a], polygon[b], polygon[c]);
+        let area = cross(pa, pb, pc);
+        let is_ear = area > 0.0
+            && remaining.iter().all(|&k| {
+                let p = polygon[k];
+                p == pa
+                    || p == pb
+                    || p == pc
+                    || cross(pa, pb, p) < 0.0
+                    || cross(pb, pc, p) < 0.0
+                    || cross(pc, pa, p) < 0.0
+            });
+        // Collinear points and zero-width spikes are dropped without a triangle;
+        // if no ear is left (bad input), clip anyway so the loop ends
+        if is_ear || area == 0.0 || misses > n {
+            if area != 0.0 {
+                triangles.push([a, b, c]);
+            }
+            remaining.remove(i % n);
+            misses = 0;
+        } else {
+            i += 1;
+            misses += 1;
+        }
+    }
+    if let [a, b, c] = remaining[..]
+        && cross(polygon[a], polygon[b], polygon[c]) != 0.0
+    {
+        triangles.push([a, b, c]);
+    }
+    triangles
+}
@@ -0,0 +1,199 @@
+//! GPU textures for materials
+
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_TEXTURE_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Unique identifier for a newly created GPU texture wrapper
+pub(crate) fn next_texture_id() -> u64 {
+    NEXT_TEXTURE_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Sampled 2D texture with its view and sampler
+///
+/// Cloning is cheap and shares the GPU texture; clones compare equal.
+#[derive(Debug, Clone)]
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    id: u64,
+}
+
+impl PartialEq for Texture {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Texture {}
+
+impl Texture {
+    /// Color texture from tightly packed sRGB RGBA8 pixels, top row first
+    pub fn from_rgba8(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<Self> {
+        Self::from_pixels(
+            device,
+            queue,
+            width,
+            height,
+            pixels,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+
+    /// Data texture (such as a normal map) from linear RGBA8 pixels, top row first
+    pub fn from_rgba8_linear(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<Self> {
+        Self::from_pixels(
+            device,
+            queue,
+            width,
+            height,
+            pixels,
+            wgpu::TextureFormat::Rgba8Unorm,
+        )
+    }
+
+    /// Load a color texture from a PNG file
+    pub fn load_png(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let (width, height, pixels) = read_png_rgba8(path.as_ref())?;
+        Self::from_rgba8(device, queue, width, height, &pixels)
+    }
+
+    /// Load a tangent-space normal map from a PNG file (green pointing up the image)
+    pub fn load_normal_map(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let (width, height, pixels) = read_png_rgba8(path.as_ref())?;
+        Self::from_rgba8_linear(device, queue, width, height, &pixels)
+    }
+
+    /// 1×1 normal map pointing straight out of the surface
+    pub fn flat_normal_map(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        Self::from_rgba8_linear(device, queue, 1, 1, &[128, 128, 255, 255])
+            .expect("1x1 texture data is valid")
+    }
+
+    /// Size in pixels
+    pub fn size(&self) -> (u32, u32) {
+        (self.texture.width(), self.texture.height())
+    }
+
+    /// Identifier shared by clones of this texture
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn from_pixels(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+        format: wgpu::TextureFormat,
+    ) -> Result<Self> {
+        if width == 0 || height == 0 {
+            bail!("Texture size must be non-zero, got {width}x{height}");
+        }
+        let expected = width as usize * height as usize * 4;
+        if pixels.len() != expected {
+            bail!(
+                "Expected {expected} bytes of RGBA8 data for a {width}x{height} texture, got {}",
+                pixels.len()
+            );
+        }
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Material Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            texture.as_image_copy(),
+            pixels,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width * 4),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Material Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+            id: next_texture_id(),
+        })
+    }
+}
+
+/// Decode a PNG file into `(width, height, RGBA8 pixels)`
+pub(crate) fn read_png_rgba8(path: &Path) -> Result<(u32, u32, Vec<u8>)> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut decoder = png::Decoder::new(std::io::BufReader::new(file));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .with_context(|| format!("Failed to decode {}", path.display()))?;
+    buffer.truncate(info.buffer_size());
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        png::ColorType::Indexed => bail!("Unexpanded palette in {}", path.display()),
+    };
+    Ok((info.width, info.height, pixels))
+}
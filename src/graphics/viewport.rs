@@ -0,0 +1,154 @@
+//! Drawing into part of a render target
+
+/// Rectangle of a render target that a pass draws into, in pixels from the
+/// top left, with the depth range it maps to
+///
+/// Geometry is scaled to fit the rectangle, so a scene drawn into a
+/// letterboxed or split-screen viewport keeps its framing.
+///
+/// ```rust
+/// use qsi::graphics::Viewport;
+///
+/// // 16:9 content centered in a 4:3 window
+/// let viewport = Viewport::letterboxed((1024, 768), 16.0 / 9.0);
+/// assert_eq!((viewport.width, viewport.height), (1024.0, 576.0));
+/// assert_eq!(viewport.y, 96.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub min_depth: f32,
+    pub max_depth: f32,
+}
+
+impl Viewport {
+    /// Rectangle with the full depth range
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        }
+    }
+
+    /// Whole target of `size` pixels
+    pub fn full((width, height): (u32, u32)) -> Self {
+        Self::new(0.0, 0.0, width as f32, height as f32)
+    }
+
+    /// Part of a target of `size` pixels given in fractions of it, such as
+    /// `[0.5, 0.0, 0.5, 1.0]` for the right half
+    pub fn fraction((width, height): (u32, u32), [x, y, w, h]: [f32; 4]) -> Self {
+        let (width, height) = (width as f32, height as f32);
+        Self::new(x * width, y * height, w * width, h * height)
+    }
+
+    /// Largest centered rectangle of `aspect` (width / height) inside a
+    /// target of `size` pixels, leaving bars above and below or at the sides
+    pub fn letterboxed(size: (u32, u32), aspect: f32) -> Self {
+        let full = Self::full(size);
+        if aspect <= 0.0 || full.height <= 0.0 {
+            return full;
+        }
+        if full.width / full.height > aspect {
+            let width = (full.height * aspect).round();
+            Self::new(
+                ((full.width - width) / 2.0).floor(),
+                0.0,
+                width,
+                full.height,
+            )
+        } else {
+            let height = (full.width / aspect).round();
+            Self::new(
+                0.0,
+                ((full.height - height) / 2.0).floor(),
+                full.width,
+                height,
+            )
+        }
+    }
+
+    /// Map depth into `min..max` instead of `0..1`
+    pub fn with_depth_range(mut self, min: f32, max: f32) -> Self {
+        self.min_depth = min;
+        self.max_depth = max;
+        self
+    }
+
+    /// Width divided by height, or 1 for an empty rectangle
+    pub fn aspect(&self) -> f32 {
+        if self.height > 0.0 {
+            self.width / self.height
+        } else {
+            1.0
+        }
+    }
+
+    /// Pixels the viewport covers, for clipping other passes to it
+    pub fn scissor(&self) -> ScissorRect {
+        let x = self.x.max(0.0).floor();
+        let y = self.y.max(0.0).floor();
+        ScissorRect::new(
+            x as u32,
+            y as u32,
+            ((self.x + self.width).ceil() - x).max(0.0) as u32,
+            ((self.y + self.height).ceil() - y).max(0.0) as u32,
+        )
+    }
+
+    /// Set this viewport on `pass`
+    pub fn apply(&self, pass: &mut wgpu::RenderPass<'_>) {
+        pass.set_viewport(
+            self.x,
+            self.y,
+            self.width,
+            self.height,
+            self.min_depth,
+            self.max_depth,
+        );
+    }
+}
+
+/// Rectangle of a render target outside of which a pass writes nothing, in
+/// pixels from the top left
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScissorRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ScissorRect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Overlap with `other`, empty if they do not meet
+    pub fn intersect(&self, other: &ScissorRect) -> ScissorRect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        ScissorRect::new(x, y, right.saturating_sub(x), bottom.saturating_sub(y))
+    }
+
+    /// Set this rectangle on `pass`, cut to a target of `size` pixels as
+    /// wgpu requires
+    pub fn apply(&self, pass: &mut wgpu::RenderPass<'_>, (width, height): (u32, u32)) {
+        let rect = self.intersect(&ScissorRect::new(0, 0, width, height));
+        pass.set_scissor_rect(rect.x, rect.y, rect.width, rect.height);
+    }
+}
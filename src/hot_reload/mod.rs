@@ -0,0 +1,166 @@
+//! Hot-reloadable update systems loaded from a dynamic library
+//!
+//! Put simulation logic in a separate crate built as a `cdylib` that exports
+//! an update function with the usual system signature:
+//!
+//! ```rust,ignore
+//! #[unsafe(no_mangle)]
+//! pub fn qsi_update(world: &mut World, input: &InputState, time: &TimeState) {
+//!     // ...
+//! }
+//! ```
+//!
+//! Register it with `App::add_hot_reload_system("target/debug/libsim.so", "qsi_update")`.
+//! Each frame the file's modification time is checked; after a rebuild the
+//! library is reloaded while the `World` keeps all its entities.
+//!
+//! The library goes through the Rust ABI, so it must be built with the same
+//! compiler and `qsi` version as the app. Component types used by both sides
+//! should live in a crate shared by the app and the library, not in the
+//! library itself, so their `TypeId`s stay stable across reloads.
+
+use crate::ecs::World;
+use crate::input::InputState;
+use crate::time::TimeState;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+type SystemFn = fn(&mut World, &InputState, &TimeState);
+
+/// A dynamic library exporting one update system, reloaded when the file changes
+pub struct HotReloadLibrary {
+    path: PathBuf,
+    symbol: String,
+    modified: Option<SystemTime>,
+    reload_count: u32,
+    /// Loads attempted, numbering each temporary copy so none is overwritten
+    /// while mapped
+    load_count: u32,
+    // Declared before `library` so the function pointer is dropped first
+    system: Option<SystemFn>,
+    library: Option<libloading::Library>,
+    loaded_copy: Option<PathBuf>,
+}
+
+impl HotReloadLibrary {
+    /// Load the library at `path` and look up `symbol`
+    pub fn new(path: impl Into<PathBuf>, symbol: impl Into<String>) -> Result<Self> {
+        let mut library = Self {
+            path: path.into(),
+            symbol: symbol.into(),
+            modified: None,
+            reload_count: 0,
+            load_count: 0,
+            system: None,
+            library: None,
+            loaded_copy: None,
+        };
+        library.reload()?;
+        Ok(library)
+    }
+
+    /// Reload if the library file changed since it was last loaded
+    ///
+    /// Returns `true` if a reload happened. On failure the previous version
+    /// stays active and the error is returned.
+    pub fn reload_if_changed(&mut self) -> Result<bool> {
+        let modified = modified_time(&self.path)?;
+        if Some(modified) == self.modified {
+            return Ok(false);
+        }
+        self.reload()?;
+        Ok(true)
+    }
+
+    /// Run the loaded system
+    pub fn run(&self, world: &mut World, input: &InputState, time: &TimeState) {
+        if let Some(system) = self.system {
+            system(world, input, time);
+        }
+    }
+
+    /// Number of times the library has been reloaded after the initial load
+    pub fn reload_count(&self) -> u32 {
+        self.reload_count
+    }
+
+    /// Path of the library being watched
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        let modified = modified_time(&self.path)?;
+
+        // Load from a copy so the build can overwrite the original, and so the
+        // OS does not hand back the cached old image for the same path
+        self.load_count += 1;
+        let copy = std::env::temp_dir().join(format!(
+            "qsi-hot-{}-{}-{}",
+            std::process::id(),
+            self.load_count,
+            self.path
+                .file_name()
+                .map_or("lib".into(), |name| name.to_string_lossy())
+        ));
+        let loaded = std::fs::copy(&self.path, &copy)
+            .with_context(|| format!("Failed to copy {}", self.path.display()))
+            .and_then(|_| self.load(&copy));
+        let (library, system) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                let _ = std::fs::remove_file(&copy);
+                return Err(e);
+            }
+        };
+
+        let first_load = self.library.is_none();
+        self.system = None;
+        self.library = None;
+        if let Some(old_copy) = self.loaded_copy.take() {
+            let _ = std::fs::remove_file(old_copy);
+        }
+
+        self.system = Some(system);
+        self.library = Some(library);
+        self.loaded_copy = Some(copy);
+        self.modified = Some(modified);
+        if !first_load {
+            self.reload_count += 1;
+            log::info!("Reloaded {}", self.path.display());
+        }
+        Ok(())
+    }
+
+    /// Open the copied library and look up the system in it
+    fn load(&self, copy: &Path) -> Result<(libloading::Library, SystemFn)> {
+        // SAFETY: loading runs the library's initializers; the caller opted into
+        // hot reload and is responsible for building a compatible library
+        let library = unsafe { libloading::Library::new(copy) }
+            .with_context(|| format!("Failed to load {}", self.path.display()))?;
+        // SAFETY: the exported symbol must have the `SystemFn` signature (see module docs)
+        let system: SystemFn = unsafe {
+            *library
+                .get::<SystemFn>(self.symbol.as_bytes())
+                .with_context(|| format!("Symbol `{}` not found", self.symbol))?
+        };
+        Ok((library, system))
+    }
+}
+
+impl Drop for HotReloadLibrary {
+    fn drop(&mut self) {
+        self.system = None;
+        self.library = None;
+        if let Some(copy) = self.loaded_copy.take() {
+            let _ = std::fs::remove_file(copy);
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Result<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .with_context(|| format!("Failed to read {}", path.display()))
+}
@@ -0,0 +1,409 @@
+//! Inverse kinematics for articulated chains
+//!
+//! A chain is a list of joint entities, root first, each with a `Transform`
+//! holding its world position. `ik_system` moves the joints so the last one
+//! reaches toward the chain's target entity while bone lengths stay fixed.
+//!
+//! ```rust,no_run
+//! use qsi::prelude::*;
+//! use qsi::ik::{IkChain, IkPlugin};
+//!
+//! fn setup(world: &mut World, _renderer: &mut Renderer) {
+//!     let joints: Vec<EntityId> = (0..4)
+//!         .map(|i| {
+//!             world
+//!                 .spawn()
+//!                 .with(Transform::at_position(Vector3::new(0.0, i as f32, 0.0)))
+//!                 .build()
+//!         })
+//!         .collect();
+//!     let target = world
+//!         .spawn()
+//!         .with(Transform::at_position(Vector3::new(2.0, 1.0, 0.0)))
+//!         .build();
+//!     let target = world.entity_ref(target);
+//!     world.spawn().with(IkChain::fabrik(joints, target));
+//! }
+//!
+//! App::new().add_plugin(IkPlugin).add_startup_system(setup).run().unwrap();
+//! ```
+
+use crate::App;
+use crate::ecs::{Component, EntityId, EntityRef, EntityRefs, World};
+use crate::input::InputState;
+use crate::math::{Transform, Vector3};
+use crate::time::TimeState;
+use cgmath::InnerSpace;
+
+/// Which algorithm an `IkChain` uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IkSolver {
+    /// Analytic solver for exactly three joints (e.g. shoulder, elbow, wrist)
+    TwoBone,
+    /// Iterative FABRIK solver for chains of any length
+    Fabrik,
+}
+
+/// Component describing an IK chain and its goal
+#[derive(Debug, Clone)]
+pub struct IkChain {
+    /// Joint entities, root first; the root never moves
+    pub joints: Vec<EntityId>,
+    /// Entity whose position the end joint reaches for
+    pub target: Option<EntityRef>,
+    /// Point the middle of the chain bends toward (e.g. where an elbow points)
+    pub pole: Option<Vector3<f32>>,
+    pub solver: IkSolver,
+    /// Maximum FABRIK iterations per frame
+    pub iterations: u32,
+    /// Distance to the target considered close enough
+    pub tolerance: f32,
+    /// Rotate each joint so its local +Z axis points at the next joint
+    pub orient_joints: bool,
+    /// Bone lengths, captured from the joint positions on the first solve
+    lengths: Vec<f32>,
+}
+
+impl Component for IkChain {}
+
+impl EntityRefs for IkChain {
+    fn for_each_ref(&mut self, f: &mut dyn FnMut(&mut Option<EntityRef>)) {
+        f(&mut self.target);
+    }
+}
+
+impl IkChain {
+    /// Create a FABRIK chain
+    pub fn fabrik(joints: Vec<EntityId>, target: Option<EntityRef>) -> Self {
+        Self {
+            joints,
+            target,
+            pole: None,
+            solver: IkSolver::Fabrik,
+            iterations: 10,
+            tolerance: 0.001,
+            orient_joints: true,
+            lengths: Vec::new(),
+        }
+    }
+
+    /// Create a two-bone chain from root, middle and end joints
+    pub fn two_bone(
+        root: EntityId,
+        middle: EntityId,
+        end: EntityId,
+        target: Option<EntityRef>,
+    ) -> Self {
+        Self {
+            solver: IkSolver::TwoBone,
+            ..Self::fabrik(vec![root, middle, end], target)
+        }
+    }
+
+    /// Set the pole point the chain bends toward
+    pub fn with_pole(mut self, pole: Vector3<f32>) -> Self {
+        self.pole = Some(pole);
+        self
+    }
+
+    /// Forget the captured bone lengths so they are re-measured on the next solve
+    pub fn reset_lengths(&mut self) {
+        self.lengths.clear();
+    }
+}
+
+/// Analytic two-bone IK
+///
+/// Returns new positions for the middle and end joints so the end reaches
+/// `target` (or points straight at it when out of reach). The chain bends in
+/// the plane containing `pole`.
+pub fn solve_two_bone(
+    root: Vector3<f32>,
+    middle: Vector3<f32>,
+    end: Vector3<f32>,
+    target: Vector3<f32>,
+    pole: Option<Vector3<f32>>,
+) -> (Vector3<f32>, Vector3<f32>) {
+    let upper = (middle - root).magnitude();
+    let lower = (end - middle).magnitude();
+    // Degenerate bones have no reach or bend to solve for
+    if upper < 1e-6 || lower < 1e-6 {
+        return (middle, end);
+    }
+    let to_target = target - root;
+    let min_reach = (upper - lower).abs() + 1e-5;
+    let max_reach = (upper + lower - 1e-5).max(min_reach);
+    let distance = to_target.magnitude().max(min_reach).min(max_reach);
+    let direction = if to_target.magnitude2() > 1e-12 {
+        to_target.normalize()
+    } else if (end - root).magnitude2() > 1e-12 {
+        (end - root).normalize()
+    } else {
+        (middle - root).normalize()
+    };
+
+    // Bend direction: component of (pole or current middle) perpendicular to the reach axis
+    let hint = pole.unwrap_or(middle) - root;
+    let mut bend = hint - direction * hint.dot(direction);
+    if bend.magnitude2() < 1e-12 {
+        bend = any_perpendicular(direction);
+    }
+    let bend = bend.normalize();
+
+    // Law of cosines for the angle at the root
+    let cos_root = ((upper * upper + distance * distance - lower * lower)
+        / (2.0 * upper * distance))
+        .clamp(-1.0, 1.0);
+    let sin_root = (1.0 - cos_root * cos_root).sqrt();
+
+    let new_middle = root + (direction * cos_root + bend * sin_root) * upper;
+    let new_end = root + direction * distance;
+    (new_middle, new_end)
+}
+
+/// FABRIK solve in place
+///
+/// `lengths[i]` is the distance between `positions[i]` and `positions[i + 1]`.
+/// Returns `true` if the end joint got within `tolerance` of the target.
+pub fn solve_fabrik(
+    positions: &mut [Vector3<f32>],
+    lengths: &[f32],
+    target: Vector3<f32>,
+    tolerance: f32,
+    iterations: u32,
+) -> bool {
+    let count = positions.len();
+    if count < 2 || lengths.len() + 1 != count {
+        return false;
+    }
+
+    let root = positions[0];
+    let reach: f32 = lengths.iter().sum();
+
+    // Zero-length chains cannot move
+    if reach < 1e-6 {
+        return (positions[count - 1] - target).magnitude() <= tolerance;
+    }
+
+    // Out of reach: stretch straight toward the target
+    if (target - root).magnitude() >= reach {
+        let direction = direction_or(target - root, Vector3::unit_y());
+        for i in 1..count {
+            positions[i] = positions[i - 1] + direction * lengths[i - 1];
+        }
+        return false;
+    }
+
+    for _ in 0..iterations {
+        if (positions[count - 1] - target).magnitude() <= tolerance {
+            return true;
+        }
+
+        // Backward pass: pin the end to the target
+        positions[count - 1] = target;
+        for i in (0..count - 1).rev() {
+            let fallback = direction_or(root - positions[i + 1], -Vector3::unit_y());
+            let direction = direction_or(positions[i] - positions[i + 1], fallback);
+            positions[i] = positions[i + 1] + direction * lengths[i];
+        }
+
+        // Forward pass: pin the root back in place
+        positions[0] = root;
+        for i in 1..count {
+            let fallback = direction_or(target - positions[i - 1], Vector3::unit_y());
+            let direction = direction_or(positions[i] - positions[i - 1], fallback);
+            positions[i] = positions[i - 1] + direction * lengths[i - 1];
+        }
+    }
+
+    (positions[count - 1] - target).magnitude() <= tolerance
+}
+
+/// Solve every `IkChain` in the world
+pub fn ik_system(world: &mut World, _input: &InputState, _time: &TimeState) {
+    let chains: Vec<EntityId> = world.query::<IkChain>().map(|(entity, _)| entity).collect();
+    for entity in chains {
+        solve_chain(world, entity);
+    }
+}
+
+fn solve_chain(world: &mut World, entity: EntityId) {
+    let Some(chain) = world.get_component::<IkChain>(entity) else {
+        return;
+    };
+    let Some(target) = chain
+        .target
+        .and_then(|target| target.get(world))
+        .and_then(|target| world.get_component::<Transform>(target))
+        .map(|transform| transform.position)
+    else {
+        return;
+    };
+    let Some(mut positions) = chain
+        .joints
+        .iter()
+        .map(|&joint| world.get_component::<Transform>(joint).map(|t| t.position))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return;
+    };
+
+    let (solver, pole, tolerance, iterations, orient) = (
+        chain.solver,
+        chain.pole,
+        chain.tolerance,
+        chain.iterations,
+        chain.orient_joints,
+    );
+    let joints = chain.joints.clone();
+    let mut lengths = chain.lengths.clone();
+    if lengths.len() + 1 != positions.len() {
+        lengths = positions
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).magnitude())
+            .collect();
+        if let Some(chain) = world.get_component_mut::<IkChain>(entity) {
+            chain.lengths = lengths.clone();
+        }
+    }
+
+    match solver {
+        IkSolver::TwoBone if positions.len() == 3 => {
+            // Restore rest lengths before solving so drift never accumulates
+            let upper = direction_or(positions[1] - positions[0], Vector3::unit_y());
+            let lower = direction_or(positions[2] - positions[1], upper);
+            let middle = positions[0] + upper * lengths[0];
+            let end = middle + lower * lengths[1];
+            let (middle, end) = solve_two_bone(positions[0], middle, end, target, pole);
+            positions[1] = middle;
+            positions[2] = end;
+        }
+        IkSolver::TwoBone => {
+            log::warn!("Two-bone IK chain needs exactly 3 joints, using FABRIK");
+            solve_fabrik(&mut positions, &lengths, target, tolerance, iterations);
+        }
+        IkSolver::Fabrik => {
+            solve_fabrik(&mut positions, &lengths, target, tolerance, iterations);
+        }
+    }
+
+    for (i, (&joint, &position)) in joints.iter().zip(&positions).enumerate() {
+        let Some(transform) = world.get_component_mut::<Transform>(joint) else {
+            continue;
+        };
+        transform.position = position;
+        if orient && let Some(&next) = positions.get(i + 1) {
+            let direction = next - position;
+            if direction.magnitude2() > 1e-12 {
+                let direction = direction.normalize();
+                transform.rotation.x = -direction.y.asin();
+                transform.rotation.y = direction.x.atan2(direction.z);
+                transform.rotation.z = 0.0;
+            }
+        }
+    }
+}
+
+/// `v` normalized, or `fallback` when `v` is too short to have a direction
+fn direction_or(v: Vector3<f32>, fallback: Vector3<f32>) -> Vector3<f32> {
+    if v.magnitude2() > 1e-12 {
+        v.normalize()
+    } else {
+        fallback
+    }
+}
+
+fn any_perpendicular(v: Vector3<f32>) -> Vector3<f32> {
+    let axis = if v.x.abs() < 0.9 {
+        Vector3::unit_x()
+    } else {
+        Vector3::unit_y()
+    };
+    v.cross(axis).normalize()
+}
+
+/// Registers `ik_system` and clears chain targets that get despawned
+pub struct IkPlugin;
+
+impl crate::plugin::Plugin for IkPlugin {
+    fn build(&self, app: &mut App) {
+        use crate::schedule::IntoSystemDescriptor;
+
+        app.register_system(ik_system.label("ik")).register_system(
+            (|world: &mut World, _: &InputState, _: &TimeState| {
+                world.clear_dangling_refs::<IkChain>();
+            })
+            .before("ik"),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finite(v: Vector3<f32>) -> bool {
+        v.x.is_finite() && v.y.is_finite() && v.z.is_finite()
+    }
+
+    #[test]
+    fn two_bone_zero_length_bones_leave_joints_in_place() {
+        let zero = Vector3::new(0.0, 0.0, 0.0);
+        let (middle, end) = solve_two_bone(zero, zero, zero, Vector3::unit_x(), None);
+        assert_eq!(middle, zero);
+        assert_eq!(end, zero);
+    }
+
+    #[test]
+    fn two_bone_reaches_target_in_range() {
+        let root = Vector3::new(0.0, 0.0, 0.0);
+        let (middle, end) = solve_two_bone(
+            root,
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, 2.0, 0.0),
+            Vector3::new(1.5, 0.0, 0.0),
+            None,
+        );
+        assert!(finite(middle) && finite(end));
+        assert!(((middle - root).magnitude() - 1.0).abs() < 1e-4);
+        assert!((end - Vector3::new(1.5, 0.0, 0.0)).magnitude() < 1e-3);
+    }
+
+    #[test]
+    fn fabrik_zero_length_bones_stay_finite() {
+        let mut positions = [Vector3::new(0.0, 0.0, 0.0); 3];
+        solve_fabrik(&mut positions, &[0.0, 0.0], Vector3::unit_x(), 0.001, 10);
+        assert!(positions.iter().all(|&p| finite(p)));
+    }
+
+    #[test]
+    fn fabrik_coincident_joints_stay_finite() {
+        let mut positions = [Vector3::new(0.0, 0.0, 0.0); 3];
+        solve_fabrik(
+            &mut positions,
+            &[1.0, 1.0],
+            Vector3::new(0.5, 0.5, 0.0),
+            0.001,
+            10,
+        );
+        assert!(positions.iter().all(|&p| finite(p)));
+        assert!(((positions[1] - positions[0]).magnitude() - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn fabrik_target_at_root_stays_finite() {
+        let mut positions = [
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, 2.0, 0.0),
+        ];
+        solve_fabrik(
+            &mut positions,
+            &[1.0, 1.0],
+            Vector3::new(0.0, 0.0, 0.0),
+            0.001,
+            10,
+        );
+        assert!(positions.iter().all(|&p| finite(p)));
+    }
+}
@@ -1,27 +1,34 @@
 //! Input handling system for keyboard and mouse events
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 use winit::event::{ElementState, MouseButton};
 use winit::keyboard::{KeyCode, ModifiersState};
 
 /// Input state that tracks keyboard and mouse state
+#[derive(Clone)]
 pub struct InputState {
     // Keyboard state
     pressed_keys: HashSet<KeyCode>,
     just_pressed_keys: HashSet<KeyCode>,
     just_released_keys: HashSet<KeyCode>,
     modifiers: ModifiersState,
+    // Characters typed this frame
+    text: String,
 
     // Mouse state
     pressed_buttons: HashSet<MouseButton>,
     just_pressed_buttons: HashSet<MouseButton>,
     just_released_buttons: HashSet<MouseButton>,
-    cursor_position: (f32, f32),
-    cursor_delta: (f32, f32),
+    // Physical pixels, unrounded; converted to logical pixels on access
+    cursor_position: (f64, f64),
+    cursor_delta: (f64, f64),
     scroll_delta: f32,
+    scale_factor: f64,
 
     // Internal state
     needs_redraw: bool,
+    last_activity: Instant,
 }
 
 impl InputState {
@@ -32,13 +39,16 @@ impl InputState {
             just_pressed_keys: HashSet::new(),
             just_released_keys: HashSet::new(),
             modifiers: ModifiersState::default(),
+            text: String::new(),
             pressed_buttons: HashSet::new(),
             just_pressed_buttons: HashSet::new(),
             just_released_buttons: HashSet::new(),
             cursor_position: (0.0, 0.0),
             cursor_delta: (0.0, 0.0),
             scroll_delta: 0.0,
+            scale_factor: 1.0,
             needs_redraw: false,
+            last_activity: Instant::now(),
         }
     }
 
@@ -46,6 +56,7 @@ impl InputState {
     pub fn update(&mut self) {
         self.just_pressed_keys.clear();
         self.just_released_keys.clear();
+        self.text.clear();
         self.just_pressed_buttons.clear();
         self.just_released_buttons.clear();
         self.cursor_delta = (0.0, 0.0);
@@ -70,6 +81,20 @@ impl InputState {
             }
         }
         self.needs_redraw = true;
+        self.last_activity = Instant::now();
+    }
+
+    /// Handle text produced by a key press, after keyboard layout and
+    /// modifiers
+    pub fn text_input(&mut self, text: &str) {
+        self.text.push_str(text);
+        self.last_activity = Instant::now();
+    }
+
+    /// Text typed this frame, for text fields; may hold control characters
+    /// such as backspace, depending on the platform
+    pub fn typed_text(&self) -> &str {
+        &self.text
     }
 
     /// Check if a key is currently pressed
@@ -119,6 +144,7 @@ impl InputState {
             }
         }
         self.needs_redraw = true;
+        self.last_activity = Instant::now();
     }
 
     /// Check if a mouse button is currently pressed
@@ -136,27 +162,77 @@ impl InputState {
         self.just_released_buttons.contains(&button)
     }
 
-    /// Set cursor position
+    /// Set the window's DPI scale factor (physical pixels per logical pixel)
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        if scale_factor > 0.0 {
+            self.scale_factor = scale_factor;
+        }
+    }
+
+    /// Window DPI scale factor
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Set cursor position in logical pixels
     pub fn set_cursor_position(&mut self, x: f32, y: f32) {
-        let old_pos = self.cursor_position;
+        self.set_cursor_position_physical(
+            x as f64 * self.scale_factor,
+            y as f64 * self.scale_factor,
+        );
+    }
+
+    /// Set cursor position in physical pixels, as reported by the window;
+    /// movement accumulates into this frame's delta
+    pub fn set_cursor_position_physical(&mut self, x: f64, y: f64) {
+        let (old_x, old_y) = self.cursor_position;
+        if (x, y) != (old_x, old_y) {
+            self.last_activity = Instant::now();
+        }
         self.cursor_position = (x, y);
-        self.cursor_delta = (x - old_pos.0, y - old_pos.1);
+        self.cursor_delta.0 += x - old_x;
+        self.cursor_delta.1 += y - old_y;
     }
 
-    /// Get current cursor position
+    /// Get current cursor position in logical pixels (the same on any DPI)
     pub fn cursor_position(&self) -> (f32, f32) {
+        self.to_logical(self.cursor_position)
+    }
+
+    /// Get current cursor position in physical pixels, with subpixel precision
+    pub fn cursor_position_physical(&self) -> (f64, f64) {
         self.cursor_position
     }
 
-    /// Get cursor movement delta for this frame
+    /// Get cursor movement this frame in logical pixels
     pub fn cursor_delta(&self) -> (f32, f32) {
-        self.cursor_delta
+        self.to_logical(self.cursor_delta)
+    }
+
+    /// Cursor in normalized device coordinates (-1..1, y up) for a surface of
+    /// the given physical size, for picking
+    pub fn cursor_ndc(&self, width: u32, height: u32) -> (f32, f32) {
+        let (x, y) = self.cursor_position;
+        let width = width.max(1) as f64;
+        let height = height.max(1) as f64;
+        (
+            (x / width * 2.0 - 1.0) as f32,
+            (1.0 - y / height * 2.0) as f32,
+        )
+    }
+
+    fn to_logical(&self, (x, y): (f64, f64)) -> (f32, f32) {
+        (
+            (x / self.scale_factor) as f32,
+            (y / self.scale_factor) as f32,
+        )
     }
 
     /// Set scroll delta
     pub fn set_scroll_delta(&mut self, delta: f32) {
         self.scroll_delta = delta;
         self.needs_redraw = true;
+        self.last_activity = Instant::now();
     }
 
     /// Get scroll delta for this frame
@@ -173,6 +249,43 @@ impl InputState {
     pub fn request_redraw(&mut self) {
         self.needs_redraw = true;
     }
+
+    /// Time since the last key, button, cursor, or scroll input
+    pub fn idle_time(&self) -> Duration {
+        self.last_activity.elapsed()
+    }
+
+    /// Seconds since the last input
+    pub fn idle_seconds(&self) -> f32 {
+        self.idle_time().as_secs_f32()
+    }
+
+    /// True once there has been no input for `seconds`
+    pub fn is_idle(&self, seconds: f32) -> bool {
+        self.idle_seconds() >= seconds
+    }
+
+    /// Fold in one frame of `frame`'s input, so a consumer running at another
+    /// rate (the simulation thread) sees every press and all movement
+    pub(crate) fn accumulate(&mut self, frame: &InputState) {
+        self.just_pressed_keys.extend(&frame.just_pressed_keys);
+        self.just_released_keys.extend(&frame.just_released_keys);
+        self.pressed_keys.clone_from(&frame.pressed_keys);
+        self.modifiers = frame.modifiers;
+        self.text.push_str(&frame.text);
+        self.just_pressed_buttons
+            .extend(&frame.just_pressed_buttons);
+        self.just_released_buttons
+            .extend(&frame.just_released_buttons);
+        self.pressed_buttons.clone_from(&frame.pressed_buttons);
+        self.cursor_position = frame.cursor_position;
+        self.cursor_delta.0 += frame.cursor_delta.0;
+        self.cursor_delta.1 += frame.cursor_delta.1;
+        self.scroll_delta += frame.scroll_delta;
+        self.scale_factor = frame.scale_factor;
+        self.needs_redraw |= frame.needs_redraw;
+        self.last_activity = self.last_activity.max(frame.last_activity);
+    }
 }
 
 impl Default for InputState {
@@ -181,6 +294,70 @@ impl Default for InputState {
     }
 }
 
+/// Named actions bound to keys, stored as a world resource
+///
+/// Systems query actions ("accelerate", "jump") instead of hard-coded keys so
+/// bindings can be changed in one place.
+#[derive(Debug, Clone, Default)]
+pub struct ActionMap {
+    bindings: HashMap<String, Vec<KeyCode>>,
+}
+
+impl ActionMap {
+    /// Create an empty action map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind a key to an action (an action can have several keys)
+    pub fn bind(mut self, action: impl Into<String>, key: KeyCode) -> Self {
+        self.bind_key(action, key);
+        self
+    }
+
+    /// In-place form of `bind`
+    pub fn bind_key(&mut self, action: impl Into<String>, key: KeyCode) {
+        let keys = self.bindings.entry(action.into()).or_default();
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    /// Remove all keys bound to an action
+    pub fn unbind(&mut self, action: &str) {
+        self.bindings.remove(action);
+    }
+
+    /// Keys bound to an action
+    pub fn keys(&self, action: &str) -> &[KeyCode] {
+        self.bindings.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Check if any key bound to the action is held
+    pub fn pressed(&self, input: &InputState, action: &str) -> bool {
+        self.keys(action).iter().any(|&key| input.key_pressed(key))
+    }
+
+    /// Check if any key bound to the action was pressed this frame
+    pub fn just_pressed(&self, input: &InputState, action: &str) -> bool {
+        self.keys(action)
+            .iter()
+            .any(|&key| input.key_just_pressed(key))
+    }
+
+    /// -1, 0, or 1 from a pair of opposing actions
+    pub fn axis(&self, input: &InputState, negative: &str, positive: &str) -> f32 {
+        let mut value = 0.0;
+        if self.pressed(input, negative) {
+            value -= 1.0;
+        }
+        if self.pressed(input, positive) {
+            value += 1.0;
+        }
+        value
+    }
+}
+
 /// Utility functions for common input patterns
 pub mod utils {
     use cgmath::num_traits::Float as _;
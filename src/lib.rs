@@ -24,9 +24,11 @@
 //! }
 //!
 //! fn setup_scene(world: &mut World, renderer: &mut Renderer) {
+//!     // The app starts with an orbit camera; spawning an active `Camera`
+//!     // would draw through that one instead
 //!     world.spawn()
 //!         .with(Transform::default())
-//!         .with(Camera::default());
+//!         .with(DirectionalLight::default());
 //! }
 //!
 //! fn update_system(world: &mut World, input: &InputState, time: &TimeState) {
@@ -34,17 +36,48 @@
 //! }
 //! ```
 
+pub mod assets;
 pub mod camera;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod commands;
+pub mod console;
+pub mod diagnostics;
 pub mod ecs;
+pub mod frame_export;
 pub mod graphics;
+#[cfg(feature = "dylib-reload")]
+pub mod hot_reload;
+pub mod ik;
 pub mod input;
 pub mod math;
+pub mod navigation;
+pub mod physics;
+pub mod plugin;
 pub mod prelude;
+pub mod reflect;
+pub mod replay;
+#[cfg(feature = "serde")]
+pub mod scene;
+pub mod schedule;
+#[cfg(feature = "serde")]
+pub mod session;
+pub mod simulation;
+pub mod state;
+pub mod streaming;
+pub mod tasks;
+pub mod testing;
 pub mod time;
+pub mod time_of_day;
+pub mod window;
+#[cfg(feature = "xr")]
+pub mod xr;
 
 // Core re-exports
 pub use anyhow::{Context, Result};
 pub use cgmath;
+#[cfg(feature = "dylib-reload")]
+use schedule::IntoSystemDescriptor;
 pub use wgpu;
 pub use winit;
 use winit::keyboard::{KeyCode, PhysicalKey};
@@ -53,22 +86,58 @@ use winit::keyboard::{KeyCode, PhysicalKey};
 pub type StartupSystem = Box<dyn FnOnce(&mut ecs::World, &mut graphics::Renderer)>;
 
 /// Update system function type  
-pub type UpdateSystem = Box<dyn Fn(&mut ecs::World, &input::InputState, &time::TimeState)>;
+///
+/// Update systems must be `Send`, since `App::with_simulation_thread` runs them
+/// on another thread.
+pub type UpdateSystem = Box<dyn Fn(&mut ecs::World, &input::InputState, &time::TimeState) + Send>;
+
+/// Render system function type, for systems that need the renderer every frame
+///
+/// Render systems always run on the main thread, so they need not be `Send`.
+pub type RenderSystem = Box<dyn Fn(&mut ecs::World, &mut graphics::Renderer, &time::TimeState)>;
+
+/// Deferred resource insertion, applied once the world exists
+type ResourceInsert = Box<dyn FnOnce(&mut ecs::World)>;
+
+/// Resource that closes the app at the end of the frame it is inserted in
+pub struct AppExit;
 
 /// Main application struct that ties everything together
 pub struct App {
     state: Option<AppState>,
     startup_systems: Vec<StartupSystem>,
-    update_systems: Vec<UpdateSystem>,
-    title: String,
+    schedule: schedule::Schedule,
+    /// Systems with renderer access, run after all update systems
+    render_systems: Vec<RenderSystem>,
+    resources: Vec<ResourceInsert>,
+    window: window::WindowConfig,
+    renderer_config: graphics::RendererConfig,
+    print_system_timings: bool,
+    plugins: std::collections::HashSet<String>,
+    headless: Option<(u32, u32)>,
+    max_frames: Option<u64>,
+    simulation_rate: Option<f64>,
+    extractor: simulation::Extractor,
+    frame_pacing: Option<graphics::FramePacing>,
+    /// Session restored after startup systems
+    #[cfg(feature = "serde")]
+    load_session_from: Option<std::path::PathBuf>,
+    /// Session saved on exit
+    #[cfg(feature = "serde")]
+    save_session_to: Option<std::path::PathBuf>,
 }
 
 struct AppState {
     world: ecs::World,
     renderer: graphics::Renderer,
     camera_controller: camera::CameraController,
+    /// Camera drawn through this frame and where it is
+    active_camera: Option<ecs::EntityId>,
+    camera_position: math::Point3<f32>,
     input_state: input::InputState,
     time: time::TimeState,
+    /// Thread running update systems; `world` then holds render snapshots
+    simulation: Option<simulation::SimulationThread>,
 }
 
 impl Default for App {
@@ -83,14 +152,137 @@ impl App {
         Self {
             state: None,
             startup_systems: Vec::new(),
-            update_systems: Vec::new(),
-            title: "QSi App".to_string(),
+            schedule: schedule::Schedule::default(),
+            render_systems: Vec::new(),
+            resources: Vec::new(),
+            window: window::WindowConfig::default(),
+            renderer_config: graphics::RendererConfig::default(),
+            print_system_timings: false,
+            plugins: std::collections::HashSet::new(),
+            headless: None,
+            max_frames: None,
+            simulation_rate: None,
+            extractor: simulation::Extractor::new(),
+            frame_pacing: None,
+            #[cfg(feature = "serde")]
+            load_session_from: None,
+            #[cfg(feature = "serde")]
+            save_session_to: None,
         }
     }
 
     /// Set the window title
     pub fn with_title(mut self, title: impl Into<String>) -> Self {
-        self.title = title.into();
+        self.window.title = title.into();
+        self
+    }
+
+    /// Open the window with these settings (see `window::WindowConfig`)
+    pub fn with_window(mut self, config: window::WindowConfig) -> Self {
+        self.window = config;
+        self
+    }
+
+    /// Set the window's initial inner size in logical pixels
+    pub fn with_window_size(mut self, width: u32, height: u32) -> Self {
+        self.window.inner_size = Some((width, height));
+        self
+    }
+
+    /// Set how the renderer picks its GPU and presents frames (see
+    /// `graphics::RendererConfig`)
+    pub fn with_renderer_config(mut self, mut config: graphics::RendererConfig) -> Self {
+        // Keep a device a plugin set up (e.g. `xr::OpenXrPlugin`)
+        config.gpu = config.gpu.or(self.renderer_config.gpu.take());
+        self.renderer_config = config;
+        self
+    }
+
+    /// Only use these graphics backends (by default the primary ones, plus
+    /// OpenGL when headless)
+    pub fn with_backends(mut self, backends: wgpu::Backends) -> Self {
+        self.renderer_config = self.renderer_config.with_backends(backends);
+        self
+    }
+
+    /// Set the depth buffer format (defaults to `Depth32Float`)
+    pub fn with_depth_format(mut self, format: wgpu::TextureFormat) -> Self {
+        self.renderer_config = self.renderer_config.with_depth_format(format);
+        self
+    }
+
+    /// Run without a window or event loop, rendering every frame into an
+    /// offscreen `width`×`height` target (see `Renderer::new_headless`)
+    ///
+    /// There is no input, so stop the app with `with_max_frames` or by inserting
+    /// an `AppExit` resource.
+    pub fn with_headless(mut self, width: u32, height: u32) -> Self {
+        self.headless = Some((width, height));
+        self
+    }
+
+    /// Stop after `frames` frames; a window then draws continuously instead
+    /// of waiting for input
+    pub fn with_max_frames(mut self, frames: u64) -> Self {
+        self.max_frames = Some(frames);
+        self
+    }
+
+    /// Run update systems on a dedicated thread, `tick_rate` times per second,
+    /// while the main thread renders the newest snapshot at its own pace (see
+    /// the `simulation` module)
+    pub fn with_simulation_thread(mut self, tick_rate: f64) -> Self {
+        self.simulation_rate = Some(tick_rate);
+        self
+    }
+
+    /// Copy `T` components into render snapshots when using
+    /// `with_simulation_thread`, for components read by custom materials or
+    /// render systems
+    pub fn extract_component<T: ecs::Component + Clone>(mut self) -> Self {
+        self.register_extract_component::<T>();
+        self
+    }
+
+    /// Copy the `T` resource into render snapshots when using
+    /// `with_simulation_thread`
+    pub fn extract_resource<T: Clone + Send + Sync + 'static>(mut self) -> Self {
+        self.register_extract_resource::<T>();
+        self
+    }
+
+    /// Pace frames with `pacing`, e.g. `FramePacing::low_latency()` for
+    /// interactive simulations (see `Renderer::latency_stats`)
+    pub fn with_frame_pacing(mut self, pacing: graphics::FramePacing) -> Self {
+        self.frame_pacing = Some(pacing);
+        self
+    }
+
+    /// Resume the session saved in `path` once startup systems have run
+    /// (see `qsi::session`)
+    #[cfg(feature = "serde")]
+    pub fn load_session(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.load_session_from = Some(path.into());
+        self
+    }
+
+    /// Save the session to `path` when the app exits (see `qsi::session`)
+    #[cfg(feature = "serde")]
+    pub fn save_session(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.save_session_to = Some(path.into());
+        self
+    }
+
+    /// Print per-system timings (see `diagnostics::SystemTimings`) when the app exits
+    pub fn with_system_timings_report(mut self, enabled: bool) -> Self {
+        self.print_system_timings = enabled;
+        self
+    }
+
+    /// Catch panics in update systems, log them with the system name, and disable
+    /// the offending system instead of shutting down the app
+    pub fn with_panic_safe_systems(mut self, enabled: bool) -> Self {
+        self.schedule.panic_safe = enabled;
         self
     }
 
@@ -99,40 +291,317 @@ impl App {
     where
         F: FnOnce(&mut ecs::World, &mut graphics::Renderer) + 'static,
     {
-        self.startup_systems.push(Box::new(system));
+        self.register_startup_system(system);
         self
     }
 
     /// Add a system that runs every frame
-    pub fn add_system<F>(mut self, system: F) -> Self
+    ///
+    /// Use `.label(..)`, `.before(..)` and `.after(..)` on the system to control
+    /// ordering; otherwise systems run in registration order.
+    pub fn add_system<M>(mut self, system: impl schedule::IntoSystemDescriptor<M>) -> Self {
+        self.register_system(system);
+        self
+    }
+
+    /// Add a state machine of type `S`, starting in `initial`
+    ///
+    /// The current state lives in the `state::State<S>` resource.
+    pub fn add_state<S: state::States>(mut self, initial: S) -> Self {
+        self.register_state(initial);
+        self
+    }
+
+    /// Add a system that only runs while `State<S>` is `state`
+    pub fn add_system_in_state<S: state::States, M>(
+        mut self,
+        state: S,
+        system: impl schedule::IntoSystemDescriptor<M>,
+    ) -> Self {
+        self.register_system_in_state(state, system);
+        self
+    }
+
+    /// Add a system that runs once each time `state` is entered
+    pub fn add_on_enter<S: state::States, M>(
+        mut self,
+        state: S,
+        system: impl schedule::IntoSystemDescriptor<M>,
+    ) -> Self {
+        self.register_on_enter(state, system);
+        self
+    }
+
+    /// Add a system that runs once each time `state` is exited
+    pub fn add_on_exit<S: state::States, M>(
+        mut self,
+        state: S,
+        system: impl schedule::IntoSystemDescriptor<M>,
+    ) -> Self {
+        self.register_on_exit(state, system);
+        self
+    }
+
+    /// Add a plugin, which registers its own systems and resources
+    pub fn add_plugin(mut self, plugin: impl plugin::Plugin) -> Self {
+        self.register_plugin(plugin);
+        self
+    }
+
+    /// Add an update system loaded from a dynamic library that is reloaded when rebuilt
+    ///
+    /// See `hot_reload` for how to build the library.
+    #[cfg(feature = "dylib-reload")]
+    pub fn add_hot_reload_system(
+        self,
+        path: impl Into<std::path::PathBuf>,
+        symbol: impl Into<String>,
+    ) -> Result<Self> {
+        let library = std::cell::RefCell::new(hot_reload::HotReloadLibrary::new(path, symbol)?);
+        let name = format!("hot_reload({})", library.borrow().path().display());
+
+        let system =
+            move |world: &mut ecs::World, input: &input::InputState, time: &time::TimeState| {
+                let mut library = library.borrow_mut();
+                if let Err(e) = library.reload_if_changed() {
+                    log::error!("Hot reload failed, keeping previous version: {e:#}");
+                }
+                library.run(world, input, time);
+            };
+        Ok(self.add_system(system.named(name)))
+    }
+
+    /// Add a system with renderer access that runs every frame after the update systems
+    ///
+    /// Use this to create or replace meshes at runtime (e.g. spawning projectiles).
+    pub fn add_render_system<F>(mut self, system: F) -> Self
+    where
+        F: Fn(&mut ecs::World, &mut graphics::Renderer, &time::TimeState) + 'static,
+    {
+        self.register_render_system(system);
+        self
+    }
+
+    /// Add a maintenance system that clears dangling `EntityRef`s in components of type `T`
+    pub fn track_entity_refs<T>(self) -> Self
+    where
+        T: ecs::Component + ecs::EntityRefs,
+    {
+        self.add_system(
+            |world: &mut ecs::World, _: &input::InputState, _: &time::TimeState| {
+                world.clear_dangling_refs::<T>();
+            },
+        )
+    }
+
+    /// Insert a resource that can be accessed by systems through `World::resource`
+    pub fn insert_resource<T: 'static + Send + Sync>(mut self, resource: T) -> Self {
+        self.register_resource(resource);
+        self
+    }
+
+    // In-place registration, used by plugins which only get `&mut App`
+
+    /// Register a startup system (in-place form of `add_startup_system`)
+    pub fn register_startup_system<F>(&mut self, system: F) -> &mut Self
+    where
+        F: FnOnce(&mut ecs::World, &mut graphics::Renderer) + 'static,
+    {
+        self.startup_systems.push(Box::new(system));
+        self
+    }
+
+    /// Register an update system (in-place form of `add_system`)
+    pub fn register_system<M>(
+        &mut self,
+        system: impl schedule::IntoSystemDescriptor<M>,
+    ) -> &mut Self {
+        self.schedule.systems.push(system.into_descriptor());
+        self
+    }
+
+    /// Register a state machine (in-place form of `add_state`)
+    pub fn register_state<S: state::States>(&mut self, initial: S) -> &mut Self {
+        self.schedule
+            .state_transitions
+            .push(state::transition_driver::<S>());
+        self.register_resource(state::State::new(initial))
+    }
+
+    /// Register a state-gated system (in-place form of `add_system_in_state`)
+    pub fn register_system_in_state<S: state::States, M>(
+        &mut self,
+        state: S,
+        system: impl schedule::IntoSystemDescriptor<M>,
+    ) -> &mut Self {
+        self.register_system(system.run_if(state::in_state(state)))
+    }
+
+    /// Register an enter hook (in-place form of `add_on_enter`)
+    pub fn register_on_enter<S: state::States, M>(
+        &mut self,
+        state: S,
+        system: impl schedule::IntoSystemDescriptor<M>,
+    ) -> &mut Self {
+        let hook = state::StateHook::new(
+            state::HookKind::Enter,
+            state,
+            system.into_descriptor().system,
+        );
+        self.schedule.state_hooks.push(hook);
+        self
+    }
+
+    /// Register an exit hook (in-place form of `add_on_exit`)
+    pub fn register_on_exit<S: state::States, M>(
+        &mut self,
+        state: S,
+        system: impl schedule::IntoSystemDescriptor<M>,
+    ) -> &mut Self {
+        let hook = state::StateHook::new(
+            state::HookKind::Exit,
+            state,
+            system.into_descriptor().system,
+        );
+        self.schedule.state_hooks.push(hook);
+        self
+    }
+
+    /// Register a render system (in-place form of `add_render_system`)
+    pub fn register_render_system<F>(&mut self, system: F) -> &mut Self
     where
-        F: Fn(&mut ecs::World, &input::InputState, &time::TimeState) + 'static,
+        F: Fn(&mut ecs::World, &mut graphics::Renderer, &time::TimeState) + 'static,
     {
-        self.update_systems.push(Box::new(system));
+        self.render_systems.push(Box::new(system));
+        self
+    }
+
+    /// Register an extracted component (in-place form of `extract_component`)
+    pub fn register_extract_component<T: ecs::Component + Clone>(&mut self) -> &mut Self {
+        self.extractor.component::<T>();
+        self
+    }
+
+    /// Register an extracted resource (in-place form of `extract_resource`)
+    pub fn register_extract_resource<T: Clone + Send + Sync + 'static>(&mut self) -> &mut Self {
+        self.extractor.resource::<T>();
         self
     }
 
-    /// Insert a resource that can be accessed by systems
-    /// Note: This is a simplified version - full ECS would have better resource management
-    pub fn insert_resource<T: 'static + Send + Sync>(self, _resource: T) -> Self {
-        // For now, resources would need to be stored in World or handled differently
-        // This is here for API compatibility
+    /// Register a resource (in-place form of `insert_resource`)
+    pub fn register_resource<T: 'static + Send + Sync>(&mut self, resource: T) -> &mut Self {
+        self.resources
+            .push(Box::new(move |world| world.insert_resource(resource)));
+        self
+    }
+
+    /// Register a plugin (in-place form of `add_plugin`)
+    ///
+    /// A plugin with the same name as one already added is skipped, so plugins
+    /// can safely add shared dependencies.
+    pub fn register_plugin(&mut self, plugin: impl plugin::Plugin) -> &mut Self {
+        if self.plugins.insert(plugin.name().to_string()) {
+            plugin.build(self);
+        } else {
+            log::debug!("Plugin `{}` already added, skipping", plugin.name());
+        }
         self
     }
 
     /// Run the application
-    pub fn run(self) -> Result<()> {
+    pub fn run(mut self) -> Result<()> {
+        self.schedule.sort()?;
+        if let Some((width, height)) = self.headless {
+            return self.run_headless(width, height);
+        }
+
         let event_loop = winit::event_loop::EventLoop::new()?;
         event_loop.set_control_flow(winit::event_loop::ControlFlow::Wait);
 
         let mut handler = AppHandler {
             app: self,
             systems_executed: false,
+            frames: 0,
         };
         event_loop.run_app(&mut handler)?;
         Ok(())
     }
 
+    /// Update and render frames back to back until `AppExit` or the frame limit
+    fn run_headless(mut self, width: u32, height: u32) -> Result<()> {
+        let renderer = pollster::block_on(graphics::Renderer::headless_with_config(
+            width,
+            height,
+            &self.renderer_config,
+        ))?;
+        let mut state = AppState::new(renderer, 1.0);
+        self.init_state(&mut state);
+        for system in self.startup_systems.drain(..) {
+            system(&mut state.world, &mut state.renderer);
+        }
+        #[cfg(feature = "serde")]
+        state.resume_session(self.load_session_from.as_deref())?;
+        self.start_simulation(&mut state);
+
+        let mut frames = 0;
+        let result = loop {
+            if self.max_frames.is_some_and(|max| frames >= max) {
+                break Ok(());
+            }
+            state.update(&mut self.schedule, &self.render_systems);
+            if let Err(e) = state.render() {
+                break Err(e);
+            }
+            frames += 1;
+            if state.exit_requested() {
+                break Ok(());
+            }
+        };
+        state.stop_simulation(&mut self.schedule);
+        self.state = Some(state);
+        result?;
+        #[cfg(feature = "serde")]
+        if let Some(state) = &self.state {
+            state.checkpoint_session(self.save_session_to.as_deref())?;
+        }
+        self.report_timings();
+        Ok(())
+    }
+
+    /// Hand the world and update systems to a simulation thread, if enabled
+    fn start_simulation(&mut self, state: &mut AppState) {
+        if let Some(tick_rate) = self.simulation_rate {
+            state.start_simulation(&mut self.schedule, self.extractor.clone(), tick_rate);
+        }
+    }
+
+    /// Apply app settings and deferred resources to a freshly created state
+    fn init_state(&mut self, state: &mut AppState) {
+        if let Some(timings) = state.world.resource_mut::<diagnostics::SystemTimings>() {
+            timings.print_on_exit = self.print_system_timings;
+        }
+        if let Some(pacing) = self.frame_pacing {
+            state.renderer.set_frame_pacing(pacing);
+        }
+        state
+            .world
+            .insert_resource(window::WindowControl::new(&self.window));
+        for insert in self.resources.drain(..) {
+            insert(&mut state.world);
+        }
+    }
+
+    /// Print system timings if `with_system_timings_report` asked for them
+    fn report_timings(&self) {
+        if let Some(timings) = self
+            .world()
+            .and_then(|world| world.resource::<diagnostics::SystemTimings>())
+            && timings.print_on_exit
+        {
+            println!("{timings}");
+        }
+    }
+
     /// Get immutable access to the ECS world (only available after startup)
     pub fn world(&self) -> Option<&ecs::World> {
         self.state.as_ref().map(|s| &s.world)
@@ -147,19 +616,30 @@ impl App {
 struct AppHandler {
     app: App,
     systems_executed: bool,
+    /// Frames drawn, for `with_max_frames`
+    frames: u64,
 }
 
 impl winit::application::ApplicationHandler for AppHandler {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         let window = std::sync::Arc::new(
             event_loop
-                .create_window(
-                    winit::window::Window::default_attributes().with_title(&self.app.title),
-                )
+                .create_window(self.app.window.attributes())
                 .expect("Failed to create window"),
         );
 
-        let state = pollster::block_on(AppState::new(window)).expect("Failed to create app state");
+        // A transparent window needs a surface that blends with the desktop
+        let mut renderer_config = self.app.renderer_config.clone();
+        if self.app.window.transparent && renderer_config.alpha_mode.is_none() {
+            renderer_config.alpha_mode = Some(wgpu::CompositeAlphaMode::PreMultiplied);
+        }
+        let renderer = pollster::block_on(graphics::Renderer::with_config(
+            window.clone(),
+            &renderer_config,
+        ))
+        .expect("Failed to create renderer");
+        let mut state = AppState::new(renderer, window.scale_factor());
+        self.app.init_state(&mut state);
         self.app.state = Some(state);
     }
 
@@ -175,44 +655,120 @@ impl winit::application::ApplicationHandler for AppHandler {
                 for system in self.app.startup_systems.drain(..) {
                     system(&mut state.world, &mut state.renderer);
                 }
+                #[cfg(feature = "serde")]
+                if let Err(e) = state.resume_session(self.app.load_session_from.as_deref()) {
+                    log::error!("{e:#}");
+                }
+                if let Some(tick_rate) = self.app.simulation_rate {
+                    state.start_simulation(
+                        &mut self.app.schedule,
+                        self.app.extractor.clone(),
+                        tick_rate,
+                    );
+                    state.renderer.request_redraw();
+                }
                 self.systems_executed = true;
             }
 
-            state.handle_event(event_loop, event, &self.app.update_systems);
+            if matches!(event, winit::event::WindowEvent::RedrawRequested)
+                && let Some(max) = self.app.max_frames
+            {
+                if self.frames >= max {
+                    event_loop.exit();
+                    return;
+                }
+                self.frames += 1;
+                // Run to the frame limit rather than waiting for input
+                state.renderer.request_redraw();
+            }
+            state.handle_event(
+                event_loop,
+                event,
+                &mut self.app.schedule,
+                &self.app.render_systems,
+            );
         }
     }
+
+    fn about_to_wait(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        // Wake up when an `AutoOrbit` is due to start, since nothing else will
+        // request a frame while input is idle
+        let Some(state) = &self.app.state else {
+            return;
+        };
+        if let Some(auto_orbit) = state.world.resource::<camera::AutoOrbit>() {
+            let remaining = auto_orbit.remaining(&state.input_state);
+            if remaining <= 0.0 {
+                state.renderer.request_redraw();
+            } else {
+                event_loop.set_control_flow(winit::event_loop::ControlFlow::WaitUntil(
+                    std::time::Instant::now() + std::time::Duration::from_secs_f32(remaining),
+                ));
+            }
+        }
+    }
+
+    fn exiting(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
+        if let Some(state) = &mut self.app.state {
+            state.stop_simulation(&mut self.app.schedule);
+            #[cfg(feature = "serde")]
+            if let Err(e) = state.checkpoint_session(self.app.save_session_to.as_deref()) {
+                log::error!("{e:#}");
+            }
+        }
+        self.app.report_timings();
+    }
 }
 
 impl AppState {
-    async fn new(window: std::sync::Arc<winit::window::Window>) -> Result<Self> {
+    fn new(renderer: graphics::Renderer, scale_factor: f64) -> Self {
         let mut world = ecs::World::new();
-        let renderer = graphics::Renderer::new(window.clone()).await?;
         let mut camera_controller = camera::CameraController::new();
-        let input_state = input::InputState::new();
+        let mut input_state = input::InputState::new();
+        input_state.set_scale_factor(scale_factor);
         let time = time::TimeState::new();
 
         // Create default camera entity
         let camera_entity = world.create_entity();
         world.add_component(camera_entity, math::Transform::default());
         world.add_component(camera_entity, camera::Camera::default());
+        world.add_component(camera_entity, camera::OrbitCamera);
+        // The controller owns its pose (sessions store it), and a loaded copy
+        // would be another active camera
+        #[cfg(feature = "serde")]
+        world.add_component(camera_entity, scene::SkipSave::entity());
 
         // Set up the camera controller with the camera entity
         camera_controller.set_camera_entity(camera_entity);
 
-        Ok(Self {
+        // Default resources (user resources inserted later replace these)
+        world.insert_resource(tasks::TaskPool::default());
+        world.insert_resource(diagnostics::SystemTimings::default());
+        world.insert_resource(reflect::TypeRegistry::default());
+        world.insert_resource(graphics::DebugDraw::default());
+        world.insert_resource(math::Rng::default());
+        world.insert_resource(commands::Commands::new());
+        world.insert_resource(assets::Assets::<graphics::Mesh>::new());
+        world.insert_resource(assets::AssetServer::default());
+
+        Self {
             world,
             renderer,
             camera_controller,
+            active_camera: Some(camera_entity),
+            camera_position: math::Point3::new(0.0, 0.0, 0.0),
             input_state,
             time,
-        })
+            simulation: None,
+        }
     }
 
     fn handle_event(
         &mut self,
         event_loop: &winit::event_loop::ActiveEventLoop,
         event: winit::event::WindowEvent,
-        update_systems: &[UpdateSystem],
+        schedule: &mut schedule::Schedule,
+        render_systems: &[RenderSystem],
     ) {
         use winit::event::*;
 
@@ -224,17 +780,22 @@ impl AppState {
             }
 
             WindowEvent::RedrawRequested => {
-                self.update(update_systems);
+                self.update(schedule, render_systems);
                 if let Err(e) = self.render() {
                     match e {
                         wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated => {
-                            let size = self.renderer.window.inner_size();
-                            self.renderer.resize(size.width, size.height);
+                            if let Some(window) = &self.renderer.window {
+                                let size = window.inner_size();
+                                self.renderer.resize(size.width, size.height);
+                            }
                         }
                         wgpu::SurfaceError::OutOfMemory => event_loop.exit(),
                         _ => log::error!("Render error: {e}"),
                     }
                 }
+                if self.exit_requested() {
+                    event_loop.exit();
+                }
             }
 
             WindowEvent::MouseInput { button, state, .. } => {
@@ -243,13 +804,16 @@ impl AppState {
                 self.renderer.request_redraw();
             }
 
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.input_state.set_scale_factor(scale_factor);
+            }
+
             WindowEvent::CursorMoved { position, .. } => {
                 self.input_state
-                    .set_cursor_position(position.x as f32, position.y as f32);
-                if self
-                    .camera_controller
-                    .mouse_motion(position.x as f32, position.y as f32)
-                {
+                    .set_cursor_position_physical(position.x, position.y);
+                // Orbit in logical pixels so drag speed is the same on any DPI
+                let (x, y) = self.input_state.cursor_position();
+                if self.camera_controller.mouse_motion(x, y) {
                     self.renderer.request_redraw();
                 }
             }
@@ -257,7 +821,9 @@ impl AppState {
             WindowEvent::MouseWheel { delta, .. } => {
                 let scroll_delta = match delta {
                     MouseScrollDelta::LineDelta(_, y) => y,
-                    MouseScrollDelta::PixelDelta(pos) => pos.y as f32 * 0.1,
+                    MouseScrollDelta::PixelDelta(pos) => {
+                        (pos.y / self.input_state.scale_factor()) as f32 * 0.1
+                    }
                 };
                 self.input_state.set_scroll_delta(scroll_delta);
                 if self.camera_controller.mouse_wheel(scroll_delta) {
@@ -274,14 +840,25 @@ impl AppState {
                     KeyEvent {
                         physical_key: PhysicalKey::Code(code),
                         state: key_state,
+                        text,
                         ..
                     },
                 ..
             } => {
                 self.input_state.key_input(code, key_state);
+                if key_state == ElementState::Pressed
+                    && let Some(text) = text
+                {
+                    self.input_state.text_input(&text);
+                }
 
-                // Built-in exit with Escape or Ctrl+C
-                if (code == KeyCode::Escape && key_state == ElementState::Pressed)
+                // Built-in exit with Escape or Ctrl+C; Escape closes an open
+                // console instead
+                let console_open = self
+                    .world
+                    .resource::<console::Console>()
+                    .is_some_and(|console| console.open);
+                if (code == KeyCode::Escape && key_state == ElementState::Pressed && !console_open)
                     || (code == KeyCode::KeyC
                         && key_state == ElementState::Pressed
                         && self.input_state.modifiers().control_key())
@@ -296,25 +873,235 @@ impl AppState {
         }
     }
 
-    fn update(&mut self, update_systems: &[UpdateSystem]) {
+    /// Move the world and update systems onto a simulation thread, keeping
+    /// render snapshots and what the main thread owns here
+    fn start_simulation(
+        &mut self,
+        schedule: &mut schedule::Schedule,
+        extractor: simulation::Extractor,
+        tick_rate: f64,
+    ) {
+        let mut world = std::mem::take(&mut self.world);
+        self.world = extractor.extract(&world);
+        simulation::hand_over(&mut world, &mut self.world);
+        let updates = std::mem::take(schedule);
+        self.simulation = Some(simulation::SimulationThread::spawn(
+            world, updates, extractor, tick_rate,
+        ));
+    }
+
+    /// Stop the simulation thread and take back its world and update systems
+    fn stop_simulation(&mut self, schedule: &mut schedule::Schedule) {
+        let Some(simulation) = self.simulation.take() else {
+            return;
+        };
+        if let Some((mut world, updates)) = simulation.join() {
+            simulation::hand_over(&mut self.world, &mut world);
+            self.world = world;
+            *schedule = updates;
+        }
+    }
+
+    /// True once `AppExit` has been inserted, on either thread
+    fn exit_requested(&self) -> bool {
+        self.world.resource::<AppExit>().is_some()
+            || self
+                .simulation
+                .as_ref()
+                .is_some_and(simulation::SimulationThread::exit_requested)
+    }
+
+    fn update(&mut self, schedule: &mut schedule::Schedule, render_systems: &[RenderSystem]) {
+        self.renderer.begin_frame();
+        if self.simulation.is_some() {
+            self.update_threaded(render_systems);
+            return;
+        }
+        // Frame export steps the clock by a fixed amount while recording
+        if let Some(recorder) = self.world.resource::<frame_export::FrameRecorder>() {
+            self.time.set_fixed_delta(recorder.fixed_delta());
+        }
+        self.time
+            .set_paused(self.world.has_resource::<time::Paused>());
         self.time.update();
-        self.input_state.update();
+        self.world.update_events();
+        tasks::apply_completed(&mut self.world);
 
         // Run user-defined update systems
-        for system in update_systems {
-            system(&mut self.world, &self.input_state, &self.time);
+        schedule.run(&mut self.world, &self.input_state, &self.time);
+        for system in render_systems {
+            system(&mut self.world, &mut self.renderer, &self.time);
         }
+        window::apply_window_control(&mut self.world, &self.renderer);
+        #[cfg(feature = "serde")]
+        self.apply_session_request();
+        graphics::build_mesh_sources(&mut self.world, &self.renderer);
+        assets::update_asset_server(&mut self.world, &self.renderer);
+
+        self.animate_camera();
 
         // Update camera from controller
         self.camera_controller
             .update_camera_transform(&mut self.world);
+        self.update_render_camera();
+
+        // Stream world chunks around the camera
+        streaming::update_streaming(&mut self.world, &self.renderer, self.camera_position);
+
+        // Keep frames coming while background jobs, asset loads, or chunk
+        // uploads are in flight
+        let tasks_pending = self
+            .world
+            .resource::<tasks::TaskPool>()
+            .is_some_and(|pool| pool.pending() > 0);
+        let chunks_pending = self
+            .world
+            .resource::<streaming::ChunkStreamer>()
+            .is_some_and(|streamer| streamer.pending_count() > 0);
+        let assets_pending = self
+            .world
+            .resource::<assets::AssetServer>()
+            .is_some_and(|server| server.pending() > 0);
+        if tasks_pending || assets_pending || chunks_pending {
+            self.renderer.request_redraw();
+        }
+
+        // Systems have seen this frame's presses and typed text
+        self.input_state.update();
+    }
+
+    /// Main-thread frame while update systems run on the simulation thread
+    fn update_threaded(&mut self, render_systems: &[RenderSystem]) {
+        self.time.update();
+        if let Some(simulation) = &self.simulation {
+            simulation.send_input(&self.input_state);
+            simulation.update_render_world(&mut self.world);
+        }
+        self.input_state.update();
+
+        // The camera follows input at the frame rate, whatever the tick rate
+        self.animate_camera();
+        self.camera_controller
+            .update_camera_transform(&mut self.world);
+        self.update_render_camera();
 
-        // Update renderer matrices using the camera controller's view matrix directly
-        self.renderer
-            .update_view_matrix(self.camera_controller.view_matrix());
+        // Render systems and uploads need the real world, so they wait for a
+        // gap between ticks
+        if let Some(simulation) = &self.simulation
+            && let Some(mut world) = simulation.try_world()
+        {
+            self.camera_controller.update_camera_transform(&mut world);
+            if let Some(view) = self.world.resource::<camera::ActiveView>() {
+                world.insert_resource(*view);
+            }
+            world.insert_resource(self.renderer.render_stats());
+            #[cfg(feature = "serde")]
+            if world.remove_resource::<session::SessionRequest>().is_some() {
+                log::warn!("Session requests are ignored with a simulation thread");
+            }
+            for system in render_systems {
+                system(&mut world, &mut self.renderer, &self.time);
+            }
+            window::apply_window_control(&mut world, &self.renderer);
+            graphics::build_mesh_sources(&mut world, &self.renderer);
+            assets::update_asset_server(&mut world, &self.renderer);
+            streaming::update_streaming(&mut world, &self.renderer, self.camera_position);
+            simulation.release_world(world);
+        }
+
+        // Snapshots keep arriving, so keep drawing
+        self.renderer.request_redraw();
+    }
+
+    /// Point the renderer through the active camera: the controller's orbit
+    /// camera unless another camera is active
+    fn update_render_camera(&mut self) {
+        use cgmath::EuclideanSpace;
+
+        let orbit = self.camera_controller.camera_entity();
+        let found = camera::utils::find_render_camera(&self.world, orbit).map(
+            |(entity, camera, transform)| {
+                let view = (Some(entity) != orbit).then(|| {
+                    (
+                        camera::utils::view_matrix_from_transform(transform),
+                        math::Point3::from_vec(transform.position),
+                    )
+                });
+                (entity, camera.clone(), view)
+            },
+        );
+        let (entity, lens, view) = match found {
+            Some((entity, camera, view)) => (Some(entity), camera, view),
+            None => (orbit, camera::Camera::default(), None),
+        };
+        let (view, position) = view.unwrap_or_else(|| {
+            (
+                self.camera_controller.view_matrix(),
+                self.camera_controller.position(),
+            )
+        });
+        self.active_camera = entity;
+        self.camera_position = position;
+        self.renderer.update_view_matrix(view);
+        self.renderer.update_lens(lens.fov, lens.near, lens.far);
+
+        // For picking in next frame's systems, which see this frame
+        let viewport = self
+            .renderer
+            .viewport()
+            .unwrap_or(graphics::Viewport::full(self.renderer.size()));
+        self.world.insert_resource(camera::ActiveView {
+            camera: entity,
+            view,
+            projection: self.renderer.projection(viewport.aspect()),
+            viewport,
+        });
+    }
+
+    /// Move the camera for presets and the idle orbit
+    fn animate_camera(&mut self) {
+        // Keyboard camera presets and their animated transitions
+        let delta_seconds = self.time.delta_seconds();
+        if let Some(presets) = self.world.resource_mut::<camera::CameraPresets>()
+            && presets.apply(
+                &self.input_state,
+                &mut self.camera_controller,
+                delta_seconds,
+            )
+        {
+            self.renderer.request_redraw();
+        }
+
+        // Screensaver orbit once input has been idle long enough
+        let auto_orbit = self.world.resource::<camera::AutoOrbit>().copied();
+        if let Some(auto_orbit) = auto_orbit
+            && auto_orbit.remaining(&self.input_state) <= 0.0
+        {
+            // The first orbiting frame follows a long idle gap, so cap the step
+            let dt = self.time.delta_seconds().min(0.1);
+            self.camera_controller.orbit(auto_orbit.speed * dt);
+            self.renderer.request_redraw();
+        }
     }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        self.renderer.render(&self.world)
+        self.renderer.update_time(&self.time);
+        // The camera's own post stack, if any, stands in for this frame
+        let camera = self.active_camera;
+        graphics::swap_camera_post_effects(&mut self.world, &mut self.renderer, camera);
+        let result = self.renderer.render(&self.world);
+        if result.is_ok() {
+            self.world.insert_resource(self.renderer.render_stats());
+            frame_export::capture_frame(&mut self.world, &mut self.renderer);
+        }
+        graphics::swap_camera_post_effects(&mut self.world, &mut self.renderer, camera);
+        result?;
+        // Snapshots bring their own lines, which stay until the next tick
+        if self.simulation.is_none()
+            && let Some(debug) = self.world.resource_mut::<graphics::DebugDraw>()
+        {
+            debug.clear();
+        }
+        Ok(())
     }
 }
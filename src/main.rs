@@ -860,13 +860,12 @@ impl ApplicationHandler for App {
                 state.input.needs_redraw = true;
             }
 
-            WindowEvent::CursorMoved { position, .. } => {
+            WindowEvent::CursorMoved { position, .. }
                 if state
                     .camera_controller
-                    .mouse_motion(position.x as f32, position.y as f32)
-                {
-                    state.input.needs_redraw = true;
-                }
+                    .mouse_motion(position.x as f32, position.y as f32) =>
+            {
+                state.input.needs_redraw = true;
             }
 
             WindowEvent::MouseWheel { delta, .. } => {
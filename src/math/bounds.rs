@@ -0,0 +1,120 @@
+//! Bounding boxes and view frustums for visibility tests
+
+use crate::ecs::Component;
+use cgmath::{Matrix4, Vector3, Vector4};
+
+/// Axis-aligned bounding box
+///
+/// As a component it holds an entity's bounds in its own space (see
+/// `graphics::BoundsPlugin`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Component for Aabb {}
+
+impl Default for Aabb {
+    /// Empty box at the origin
+    fn default() -> Self {
+        Self::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0))
+    }
+}
+
+impl Aabb {
+    /// Box spanning `min` to `max`
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Self {
+        Self { min, max }
+    }
+
+    /// Smallest box containing every point, or `None` if there are none
+    pub fn from_points(points: impl IntoIterator<Item = [f32; 3]>) -> Option<Self> {
+        points.into_iter().fold(None, |bounds, [x, y, z]| {
+            let point = Vector3::new(x, y, z);
+            Some(match bounds {
+                Some(Self { min, max }) => Self::new(
+                    Vector3::new(min.x.min(x), min.y.min(y), min.z.min(z)),
+                    Vector3::new(max.x.max(x), max.y.max(y), max.z.max(z)),
+                ),
+                None => Self::new(point, point),
+            })
+        })
+    }
+
+    /// Midpoint of the box
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Half the size along each axis
+    pub fn half_extents(&self) -> Vector3<f32> {
+        (self.max - self.min) * 0.5
+    }
+
+    /// Smallest box containing both boxes
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self::new(
+            Vector3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            Vector3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        )
+    }
+
+    /// Box enclosing this one after `matrix` is applied (rotation makes it
+    /// looser than the transformed shape)
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        let center = matrix * self.center().extend(1.0);
+        let extents = self.half_extents();
+        // Each output axis gathers the absolute contribution of every input axis
+        let radius = |row: usize| {
+            matrix.x[row].abs() * extents.x
+                + matrix.y[row].abs() * extents.y
+                + matrix.z[row].abs() * extents.z
+        };
+        let radius = Vector3::new(radius(0), radius(1), radius(2));
+        let center = center.truncate();
+        Self::new(center - radius, center + radius)
+    }
+}
+
+/// The six planes bounding what a camera can see
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    /// `(normal, distance)` per plane, with normals pointing inwards
+    pub planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Frustum of a `projection * view` matrix
+    ///
+    /// The near plane assumes OpenGL depth (-1..1), which also keeps the test
+    /// conservative for 0..1 projections.
+    pub fn from_view_projection(matrix: &Matrix4<f32>) -> Self {
+        let row = |i: usize| Vector4::new(matrix.x[i], matrix.y[i], matrix.z[i], matrix.w[i]);
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        Self {
+            planes: [w + x, w - x, w + y, w - y, w + z, w - z],
+        }
+    }
+
+    /// False only if `bounds` lies entirely outside one of the planes
+    pub fn intersects(&self, bounds: &Aabb) -> bool {
+        let center = bounds.center();
+        let extents = bounds.half_extents();
+        self.planes.iter().all(|plane| {
+            let distance = plane.x * center.x + plane.y * center.y + plane.z * center.z + plane.w;
+            let radius =
+                plane.x.abs() * extents.x + plane.y.abs() * extents.y + plane.z.abs() * extents.z;
+            distance >= -radius
+        })
+    }
+}
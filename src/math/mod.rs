@@ -2,9 +2,18 @@
 
 use crate::ecs::Component;
 pub use cgmath::{Deg, EuclideanSpace, Matrix4, Point3, Rad, SquareMatrix, Vector3, perspective};
+use cgmath::{InnerSpace, Matrix3, Quaternion};
+
+mod bounds;
+mod random;
+mod ray;
+pub use bounds::{Aabb, Frustum};
+pub use random::Rng;
+pub use ray::Ray;
 
 /// Transform component for position, rotation, and scale
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Transform {
     pub position: Vector3<f32>,
     pub rotation: Vector3<f32>, // Euler angles in radians
@@ -71,6 +80,16 @@ impl Transform {
         self.rotation = rotation;
     }
 
+    /// Set rotation from a quaternion, converting to this transform's Y-X-Z Euler order
+    pub fn set_rotation_from_quaternion(&mut self, rotation: Quaternion<f32>) {
+        let m = Matrix3::from(rotation.normalize());
+        self.rotation = Vector3::new(
+            (-m.z.y).clamp(-1.0, 1.0).asin(),
+            m.z.x.atan2(m.z.z),
+            m.x.y.atan2(m.y.y),
+        );
+    }
+
     /// Set scale
     pub fn set_scale(&mut self, scale: Vector3<f32>) {
         self.scale = scale;
@@ -97,6 +116,7 @@ impl Transform {
 
 /// Velocity component for physics simulations
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Velocity {
     pub linear: Vector3<f32>,
     pub angular: Vector3<f32>, // Radians per second
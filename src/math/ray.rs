@@ -0,0 +1,97 @@
+//! Rays for picking and line-of-sight tests
+
+use super::Aabb;
+use cgmath::{InnerSpace, Matrix4, Vector3};
+
+/// Half-line from `origin` along `direction`
+///
+/// Hit distances are in units of `direction`, which is a unit vector unless
+/// the ray was built or transformed with a scaled one.
+///
+/// ```rust
+/// use qsi::math::{Aabb, Ray, Vector3};
+///
+/// let ray = Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+/// let unit_box = Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+/// assert_eq!(ray.intersect_aabb(&unit_box), Some(4.0));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Vector3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    /// Ray from `origin` along `direction`, normalized
+    pub fn new(origin: Vector3<f32>, direction: Vector3<f32>) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    /// Point `distance` along the ray
+    pub fn at(&self, distance: f32) -> Vector3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    /// Ray with `matrix` applied to its origin and direction, keeping
+    /// distances comparable along both (the direction is not renormalized)
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        Self {
+            origin: (matrix * self.origin.extend(1.0)).truncate(),
+            direction: (matrix * self.direction.extend(0.0)).truncate(),
+        }
+    }
+
+    /// Distance to where the ray enters `bounds`, 0 if it starts inside, or
+    /// `None` if it misses
+    pub fn intersect_aabb(&self, bounds: &Aabb) -> Option<f32> {
+        let mut near = 0.0f32;
+        let mut far = f32::INFINITY;
+        for axis in 0..3 {
+            let (origin, direction) = (self.origin[axis], self.direction[axis]);
+            let (min, max) = (bounds.min[axis], bounds.max[axis]);
+            if direction.abs() <= f32::EPSILON {
+                // Parallel to this slab: inside it or never
+                if origin < min || origin > max {
+                    return None;
+                }
+                continue;
+            }
+            let (a, b) = ((min - origin) / direction, (max - origin) / direction);
+            near = near.max(a.min(b));
+            far = far.min(a.max(b));
+            if near > far {
+                return None;
+            }
+        }
+        Some(near)
+    }
+
+    /// Distance to where the ray crosses the triangle, from either side, or
+    /// `None` if it misses
+    pub fn intersect_triangle(&self, [a, b, c]: [[f32; 3]; 3]) -> Option<f32> {
+        // Möller–Trumbore
+        let (a, b, c) = (Vector3::from(a), Vector3::from(b), Vector3::from(c));
+        let (edge1, edge2) = (b - a, c - a);
+        let p = self.direction.cross(edge2);
+        let determinant = edge1.dot(p);
+        if determinant.abs() <= f32::EPSILON * edge1.magnitude() * edge2.magnitude() {
+            return None;
+        }
+        let inverse = 1.0 / determinant;
+        let offset = self.origin - a;
+        let u = offset.dot(p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = offset.cross(edge1);
+        let v = self.direction.dot(q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let distance = edge2.dot(q) * inverse;
+        (distance >= 0.0).then_some(distance)
+    }
+}
@@ -0,0 +1,233 @@
+//! Local collision avoidance between path agents
+
+use super::PathAgent;
+use crate::ecs::{Component, EntityId, World};
+use crate::input::InputState;
+use crate::math::{Transform, Vector3};
+use crate::time::TimeState;
+use cgmath::{InnerSpace, Zero};
+use std::collections::HashMap;
+
+type Bucket<T> = Vec<(Vector3<f32>, T)>;
+
+/// Uniform hash grid on the XZ plane for neighbour queries
+#[derive(Debug, Clone)]
+pub struct SpatialHash<T> {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Bucket<T>>,
+}
+
+impl<T: Copy> SpatialHash<T> {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size: cell_size.max(1e-3),
+            cells: HashMap::new(),
+        }
+    }
+
+    fn key(&self, position: Vector3<f32>) -> (i32, i32) {
+        (
+            (position.x / self.cell_size).floor() as i32,
+            (position.z / self.cell_size).floor() as i32,
+        )
+    }
+
+    pub fn insert(&mut self, position: Vector3<f32>, value: T) {
+        let key = self.key(position);
+        self.cells.entry(key).or_default().push((position, value));
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    /// Items within `radius` of `center` on the XZ plane
+    pub fn query(
+        &self,
+        center: Vector3<f32>,
+        radius: f32,
+    ) -> impl Iterator<Item = (Vector3<f32>, T)> + '_ {
+        let (cx, cz) = self.key(center);
+        let reach = (radius / self.cell_size).ceil() as i32;
+        (cx - reach..=cx + reach)
+            .flat_map(move |x| (cz - reach..=cz + reach).map(move |z| (x, z)))
+            .filter_map(|key| self.cells.get(&key))
+            .flatten()
+            .filter(move |(position, _)| {
+                let (dx, dz) = (position.x - center.x, position.z - center.z);
+                dx * dx + dz * dz <= radius * radius
+            })
+            .copied()
+    }
+}
+
+/// Steers a `PathAgent` around other avoiding agents
+///
+/// Uses sampled reciprocal velocity obstacles: each frame the agent picks the
+/// velocity closest to its path direction that won't collide with neighbours
+/// within `time_horizon` seconds, assuming they share the work of avoiding.
+#[derive(Debug, Clone)]
+pub struct Avoidance {
+    pub radius: f32,
+    pub max_speed: f32,
+    /// Only agents closer than this are considered
+    pub neighbor_distance: f32,
+    /// How far ahead collisions are predicted, in seconds
+    pub time_horizon: f32,
+    /// Velocity chosen for this frame
+    pub velocity: Vector3<f32>,
+}
+
+impl Component for Avoidance {}
+
+impl Avoidance {
+    pub fn new(radius: f32, max_speed: f32) -> Self {
+        Self {
+            radius,
+            max_speed,
+            neighbor_distance: radius * 10.0,
+            time_horizon: 2.0,
+            velocity: Vector3::zero(),
+        }
+    }
+}
+
+struct AgentState {
+    entity: EntityId,
+    position: Vector3<f32>,
+    velocity: Vector3<f32>,
+    preferred: Vector3<f32>,
+    avoidance: Avoidance,
+}
+
+/// Choose avoidance velocities for all agents; runs before `path_agent_system`
+pub fn avoidance_system(world: &mut World, _input: &InputState, time: &TimeState) {
+    let dt = time.delta_seconds();
+    if dt <= 0.0 {
+        return;
+    }
+
+    let agents: Vec<AgentState> = world
+        .query::<Avoidance>()
+        .filter_map(|(entity, avoidance)| {
+            let position = world.get_component::<Transform>(entity)?.position;
+            let preferred = world
+                .get_component::<PathAgent>(entity)
+                .map(|agent| {
+                    let desired = agent.desired_velocity(position);
+                    // Slow down for the final waypoint instead of overshooting
+                    let remaining = match agent.path() {
+                        [last] => {
+                            ((last.x - position.x).powi(2) + (last.z - position.z).powi(2)).sqrt()
+                        }
+                        _ => f32::MAX,
+                    };
+                    let speed = desired
+                        .magnitude()
+                        .min(avoidance.max_speed)
+                        .min(remaining / dt);
+                    if desired.magnitude2() > 1e-12 {
+                        desired.normalize() * speed
+                    } else {
+                        desired
+                    }
+                })
+                .unwrap_or_else(Vector3::zero);
+            Some(AgentState {
+                entity,
+                position,
+                velocity: avoidance.velocity,
+                preferred,
+                avoidance: avoidance.clone(),
+            })
+        })
+        .collect();
+
+    let max_reach = agents
+        .iter()
+        .map(|a| a.avoidance.neighbor_distance)
+        .fold(1.0, f32::max);
+    let mut grid = SpatialHash::new(max_reach);
+    for (index, agent) in agents.iter().enumerate() {
+        grid.insert(agent.position, index);
+    }
+
+    let chosen: Vec<(EntityId, Vector3<f32>)> = agents
+        .iter()
+        .enumerate()
+        .map(|(index, agent)| {
+            let neighbors: Vec<&AgentState> = grid
+                .query(agent.position, agent.avoidance.neighbor_distance)
+                .filter(|&(_, other)| other != index)
+                .map(|(_, other)| &agents[other])
+                .collect();
+            (agent.entity, choose_velocity(agent, &neighbors))
+        })
+        .collect();
+
+    for (entity, velocity) in chosen {
+        if let Some(avoidance) = world.get_component_mut::<Avoidance>(entity) {
+            avoidance.velocity = velocity;
+        }
+    }
+}
+
+fn choose_velocity(agent: &AgentState, neighbors: &[&AgentState]) -> Vector3<f32> {
+    if neighbors.is_empty() {
+        return agent.preferred;
+    }
+
+    const DIRECTIONS: usize = 16;
+    const SPEEDS: [f32; 3] = [1.0, 0.6, 0.3];
+    let max_speed = agent.avoidance.max_speed;
+    let candidates = std::iter::once(agent.preferred)
+        .chain(std::iter::once(Vector3::zero()))
+        .chain((0..DIRECTIONS).flat_map(|i| {
+            let angle = i as f32 / DIRECTIONS as f32 * std::f32::consts::TAU;
+            SPEEDS.map(|s| Vector3::new(angle.cos(), 0.0, angle.sin()) * max_speed * s)
+        }));
+
+    let mut best = agent.preferred;
+    let mut best_penalty = f32::MAX;
+    for candidate in candidates {
+        let mut soonest = f32::MAX;
+        for other in neighbors {
+            // Reciprocal: each agent takes half the responsibility
+            let relative_velocity = candidate * 2.0 - agent.velocity - other.velocity;
+            let combined = agent.avoidance.radius + other.avoidance.radius;
+            let t = time_to_collision(other.position - agent.position, relative_velocity, combined);
+            soonest = soonest.min(t);
+        }
+        let avoidance = if soonest < agent.avoidance.time_horizon {
+            2.0 / soonest.max(1e-3)
+        } else {
+            0.0
+        };
+        let penalty = avoidance + (candidate - agent.preferred).magnitude();
+        if penalty < best_penalty {
+            best_penalty = penalty;
+            best = candidate;
+        }
+    }
+    best
+}
+
+/// Seconds until two discs touch, `f32::MAX` if never, `0` if already overlapping
+fn time_to_collision(offset: Vector3<f32>, relative_velocity: Vector3<f32>, radius: f32) -> f32 {
+    let offset = Vector3::new(offset.x, 0.0, offset.z);
+    let velocity = Vector3::new(relative_velocity.x, 0.0, relative_velocity.z);
+    let c = offset.magnitude2() - radius * radius;
+    if c < 0.0 {
+        return 0.0;
+    }
+    let a = velocity.magnitude2();
+    let b = offset.dot(velocity);
+    if a < 1e-12 || b <= 0.0 {
+        return f32::MAX;
+    }
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+        return f32::MAX;
+    }
+    (b - discriminant.sqrt()) / a
+}
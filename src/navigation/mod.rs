@@ -0,0 +1,491 @@
+//! Grid navigation with A* pathfinding
+//!
+//! A `NavGrid` resource marks which cells on the XZ plane are walkable.
+//! `PathAgent`s ask it for paths and `path_agent_system` walks them there.
+//! Agents that also have an `Avoidance` component steer around each other.
+//!
+//! ```rust,no_run
+//! use qsi::prelude::*;
+//! use qsi::navigation::{NavGrid, NavigationPlugin, PathAgent};
+//!
+//! fn setup(world: &mut World, _renderer: &mut Renderer) {
+//!     let mut grid = NavGrid::new(Vector3::new(-10.0, 0.0, -10.0), 0.5, 40, 40);
+//!     grid.block_circle(Vector3::new(0.0, 0.0, 0.0), 2.0);
+//!     world.insert_resource(grid);
+//!
+//!     world
+//!         .spawn()
+//!         .with(Transform::at_position(Vector3::new(-8.0, 0.0, 0.0)))
+//!         .with(PathAgent::new(3.0).with_goal(Vector3::new(8.0, 0.0, 0.0)));
+//! }
+//!
+//! App::new().add_plugin(NavigationPlugin).add_startup_system(setup).run().unwrap();
+//! ```
+
+mod avoidance;
+
+pub use avoidance::{Avoidance, SpatialHash, avoidance_system};
+
+use crate::App;
+use crate::ecs::{Component, EntityId, World};
+use crate::input::InputState;
+use crate::math::{Transform, Vector3};
+use crate::physics::RigidBody;
+use crate::time::TimeState;
+use cgmath::InnerSpace;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Cell coordinates in a `NavGrid`
+pub type Cell = (u32, u32);
+
+/// Walkability grid on the XZ plane
+#[derive(Debug, Clone)]
+pub struct NavGrid {
+    /// World position of the grid's minimum corner; `y` is the walking height
+    pub origin: Vector3<f32>,
+    pub cell_size: f32,
+    width: u32,
+    depth: u32,
+    walkable: Vec<bool>,
+}
+
+impl NavGrid {
+    /// Create a fully walkable grid of `width` x `depth` cells
+    pub fn new(origin: Vector3<f32>, cell_size: f32, width: u32, depth: u32) -> Self {
+        Self {
+            origin,
+            cell_size,
+            width,
+            depth,
+            walkable: vec![true; (width * depth) as usize],
+        }
+    }
+
+    /// Create a grid whose walkability comes from a function of each cell
+    pub fn from_fn(
+        origin: Vector3<f32>,
+        cell_size: f32,
+        width: u32,
+        depth: u32,
+        walkable: impl Fn(Cell) -> bool,
+    ) -> Self {
+        let mut grid = Self::new(origin, cell_size, width, depth);
+        for z in 0..depth {
+            for x in 0..width {
+                grid.set_walkable((x, z), walkable((x, z)));
+            }
+        }
+        grid
+    }
+
+    /// Create a grid with cells blocked by static rigid bodies, using their radius
+    pub fn from_world(
+        world: &World,
+        origin: Vector3<f32>,
+        cell_size: f32,
+        width: u32,
+        depth: u32,
+    ) -> Self {
+        let mut grid = Self::new(origin, cell_size, width, depth);
+        for (entity, body) in world.query::<RigidBody>() {
+            if !body.is_static() {
+                continue;
+            }
+            if let Some(transform) = world.get_component::<Transform>(entity) {
+                grid.block_circle(transform.position, body.radius);
+            }
+        }
+        grid
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Check if a cell exists and can be walked on
+    pub fn is_walkable(&self, cell: Cell) -> bool {
+        self.index(cell).is_some_and(|i| self.walkable[i])
+    }
+
+    /// Mark a cell walkable or blocked; out-of-range cells are ignored
+    pub fn set_walkable(&mut self, cell: Cell, walkable: bool) {
+        if let Some(i) = self.index(cell) {
+            self.walkable[i] = walkable;
+        }
+    }
+
+    /// Block every cell whose centre lies within `radius` of `center` on the XZ plane
+    pub fn block_circle(&mut self, center: Vector3<f32>, radius: f32) {
+        let reach = radius + self.cell_size * 0.5;
+        for z in 0..self.depth {
+            for x in 0..self.width {
+                let c = self.cell_center((x, z));
+                let (dx, dz) = (c.x - center.x, c.z - center.z);
+                if dx * dx + dz * dz <= reach * reach {
+                    self.set_walkable((x, z), false);
+                }
+            }
+        }
+    }
+
+    /// Cell containing a world position, if it is inside the grid
+    pub fn world_to_cell(&self, position: Vector3<f32>) -> Option<Cell> {
+        let x = ((position.x - self.origin.x) / self.cell_size).floor();
+        let z = ((position.z - self.origin.z) / self.cell_size).floor();
+        if x < 0.0 || z < 0.0 || x >= self.width as f32 || z >= self.depth as f32 {
+            return None;
+        }
+        Some((x as u32, z as u32))
+    }
+
+    /// World position of a cell's centre at the grid's height
+    pub fn cell_center(&self, cell: Cell) -> Vector3<f32> {
+        Vector3::new(
+            self.origin.x + (cell.0 as f32 + 0.5) * self.cell_size,
+            self.origin.y,
+            self.origin.z + (cell.1 as f32 + 0.5) * self.cell_size,
+        )
+    }
+
+    /// Find a path between two world positions
+    ///
+    /// The result starts near `start`, ends exactly at `goal`, and has
+    /// redundant waypoints removed. Returns `None` if either end is blocked
+    /// or outside the grid, or no route exists.
+    pub fn find_path(&self, start: Vector3<f32>, goal: Vector3<f32>) -> Option<Vec<Vector3<f32>>> {
+        let start_cell = self.world_to_cell(start)?;
+        let goal_cell = self.world_to_cell(goal)?;
+        let cells = self.find_cell_path(start_cell, goal_cell)?;
+
+        let mut path = self.smooth(&cells);
+        path.remove(0);
+        path.pop();
+        path.push(Vector3::new(goal.x, self.origin.y, goal.z));
+        Some(path)
+    }
+
+    /// A* over cells with 8-way movement; diagonals may not cut blocked corners
+    pub fn find_cell_path(&self, start: Cell, goal: Cell) -> Option<Vec<Cell>> {
+        if !self.is_walkable(start) || !self.is_walkable(goal) {
+            return None;
+        }
+
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<Cell, Cell> = HashMap::new();
+        let mut cost: HashMap<Cell, f32> = HashMap::new();
+        cost.insert(start, 0.0);
+        open.push(OpenCell {
+            cell: start,
+            estimate: octile(start, goal),
+        });
+
+        while let Some(OpenCell { cell, .. }) = open.pop() {
+            if cell == goal {
+                let mut path = vec![cell];
+                let mut current = cell;
+                while let Some(&previous) = came_from.get(&current) {
+                    path.push(previous);
+                    current = previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let current_cost = cost[&cell];
+            for (neighbor, step) in self.neighbors(cell) {
+                let next_cost = current_cost + step;
+                if cost.get(&neighbor).is_none_or(|&c| next_cost < c) {
+                    cost.insert(neighbor, next_cost);
+                    came_from.insert(neighbor, cell);
+                    open.push(OpenCell {
+                        cell: neighbor,
+                        estimate: next_cost + octile(neighbor, goal),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Check whether a straight line between two cells crosses only walkable cells
+    pub fn line_of_sight(&self, from: Cell, to: Cell) -> bool {
+        let (a, b) = (self.cell_center(from), self.cell_center(to));
+        let distance = ((b.x - a.x).powi(2) + (b.z - a.z).powi(2)).sqrt();
+        let steps = (distance / (self.cell_size * 0.25)).ceil().max(1.0) as u32;
+        (0..=steps).all(|i| {
+            let t = i as f32 / steps as f32;
+            self.world_to_cell(a + (b - a) * t)
+                .is_some_and(|cell| self.is_walkable(cell))
+        })
+    }
+
+    fn neighbors(&self, (x, z): Cell) -> impl Iterator<Item = (Cell, f32)> + '_ {
+        const DIRECTIONS: [(i32, i32); 8] = [
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+        ];
+        DIRECTIONS.iter().filter_map(move |&(dx, dz)| {
+            let nx = x.checked_add_signed(dx)?;
+            let nz = z.checked_add_signed(dz)?;
+            if !self.is_walkable((nx, nz)) {
+                return None;
+            }
+            if dx != 0 && dz != 0 {
+                // Don't squeeze diagonally between two blocked cells
+                if !self.is_walkable((nx, z)) || !self.is_walkable((x, nz)) {
+                    return None;
+                }
+                Some(((nx, nz), std::f32::consts::SQRT_2))
+            } else {
+                Some(((nx, nz), 1.0))
+            }
+        })
+    }
+
+    /// Drop waypoints that the previous kept waypoint can see past
+    fn smooth(&self, cells: &[Cell]) -> Vec<Vector3<f32>> {
+        let mut kept = vec![cells[0]];
+        for window in 1..cells.len() {
+            let last = *kept.last().unwrap();
+            let next = cells.get(window + 1).copied();
+            match next {
+                Some(next) if self.line_of_sight(last, next) => {}
+                _ => kept.push(cells[window]),
+            }
+        }
+        kept.into_iter()
+            .map(|cell| self.cell_center(cell))
+            .collect()
+    }
+
+    fn index(&self, (x, z): Cell) -> Option<usize> {
+        (x < self.width && z < self.depth).then(|| (z * self.width + x) as usize)
+    }
+}
+
+fn octile(a: Cell, b: Cell) -> f32 {
+    let dx = a.0.abs_diff(b.0) as f32;
+    let dz = a.1.abs_diff(b.1) as f32;
+    dx.max(dz) + (std::f32::consts::SQRT_2 - 1.0) * dx.min(dz)
+}
+
+#[derive(PartialEq)]
+struct OpenCell {
+    cell: Cell,
+    estimate: f32,
+}
+
+impl Eq for OpenCell {}
+
+impl Ord for OpenCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so the heap pops the lowest estimate first
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+impl PartialOrd for OpenCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Progress of a `PathAgent`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathStatus {
+    Idle,
+    Moving,
+    Arrived,
+    Unreachable,
+}
+
+/// Component that walks an entity along paths from the `NavGrid`
+#[derive(Debug, Clone)]
+pub struct PathAgent {
+    pub speed: f32,
+    /// Distance at which a waypoint counts as reached
+    pub arrival_radius: f32,
+    goal: Option<Vector3<f32>>,
+    path: Vec<Vector3<f32>>,
+    needs_path: bool,
+    status: PathStatus,
+}
+
+impl Component for PathAgent {}
+
+impl PathAgent {
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            arrival_radius: 0.1,
+            goal: None,
+            path: Vec::new(),
+            needs_path: false,
+            status: PathStatus::Idle,
+        }
+    }
+
+    /// Builder form of `set_goal`
+    pub fn with_goal(mut self, goal: Vector3<f32>) -> Self {
+        self.set_goal(goal);
+        self
+    }
+
+    /// Head for `goal`; the path is computed on the next update
+    pub fn set_goal(&mut self, goal: Vector3<f32>) {
+        self.goal = Some(goal);
+        self.needs_path = true;
+    }
+
+    /// Stop moving and forget the goal
+    pub fn stop(&mut self) {
+        self.goal = None;
+        self.path.clear();
+        self.needs_path = false;
+        self.status = PathStatus::Idle;
+    }
+
+    /// Recompute the path to the current goal, e.g. after the grid changed
+    pub fn repath(&mut self) {
+        self.needs_path = self.goal.is_some();
+    }
+
+    pub fn goal(&self) -> Option<Vector3<f32>> {
+        self.goal
+    }
+
+    /// Remaining waypoints
+    pub fn path(&self) -> &[Vector3<f32>] {
+        &self.path
+    }
+
+    pub fn status(&self) -> PathStatus {
+        self.status
+    }
+
+    /// Direction and speed the agent wants to move at this frame
+    pub fn desired_velocity(&self, position: Vector3<f32>) -> Vector3<f32> {
+        let Some(&target) = self.path.first() else {
+            return Vector3::new(0.0, 0.0, 0.0);
+        };
+        let mut offset = target - position;
+        offset.y = 0.0;
+        if offset.magnitude2() < 1e-12 {
+            return offset;
+        }
+        offset.normalize() * self.speed
+    }
+}
+
+/// Plan paths for agents with new goals and move agents along their paths
+pub fn path_agent_system(world: &mut World, _input: &InputState, time: &TimeState) {
+    let dt = time.delta_seconds();
+    let agents: Vec<EntityId> = world
+        .query::<PathAgent>()
+        .map(|(entity, _)| entity)
+        .collect();
+    let steering: HashMap<EntityId, Vector3<f32>> = world
+        .query::<Avoidance>()
+        .map(|(entity, avoidance)| (entity, avoidance.velocity))
+        .collect();
+
+    for entity in agents {
+        let Some(position) = world.get_component::<Transform>(entity).map(|t| t.position) else {
+            continue;
+        };
+        let planned = match world.get_component::<PathAgent>(entity) {
+            Some(agent) if agent.needs_path => Some(agent.goal.and_then(|goal| {
+                world
+                    .resource::<NavGrid>()
+                    .and_then(|grid| grid.find_path(position, goal))
+            })),
+            _ => None,
+        };
+        let Some(agent) = world.get_component_mut::<PathAgent>(entity) else {
+            continue;
+        };
+        if let Some(planned) = planned {
+            agent.needs_path = false;
+            match planned {
+                Some(path) => {
+                    agent.path = path;
+                    agent.status = PathStatus::Moving;
+                }
+                None => {
+                    agent.path.clear();
+                    agent.status = PathStatus::Unreachable;
+                }
+            }
+        }
+
+        let mut position = position;
+        if let Some(velocity) = steering.get(&entity) {
+            // Steered by `Avoidance`: move freely and tick off waypoints as they pass
+            position += velocity * dt;
+            while let Some(&waypoint) = agent.path.first() {
+                let (dx, dz) = (waypoint.x - position.x, waypoint.z - position.z);
+                let reach = agent.arrival_radius.max(agent.speed * dt);
+                if dx * dx + dz * dz > reach * reach {
+                    break;
+                }
+                agent.path.remove(0);
+            }
+        }
+        let mut budget = if steering.contains_key(&entity) {
+            0.0
+        } else {
+            agent.speed * dt
+        };
+        while budget > 0.0 {
+            let Some(&waypoint) = agent.path.first() else {
+                break;
+            };
+            let mut offset = waypoint - position;
+            offset.y = 0.0;
+            let distance = offset.magnitude();
+            if distance <= agent.arrival_radius.max(budget) {
+                position.x = waypoint.x;
+                position.z = waypoint.z;
+                budget -= distance;
+                agent.path.remove(0);
+            } else {
+                position += offset / distance * budget;
+                budget = 0.0;
+            }
+        }
+        if agent.status == PathStatus::Moving && agent.path.is_empty() {
+            agent.status = PathStatus::Arrived;
+        }
+
+        if let Some(transform) = world.get_component_mut::<Transform>(entity) {
+            let moved = position - transform.position;
+            if moved.x * moved.x + moved.z * moved.z > 1e-12 {
+                transform.rotation.y = moved.x.atan2(moved.z);
+            }
+            transform.position = position;
+        }
+    }
+}
+
+/// Registers `path_agent_system` under the "navigation" label, after `avoidance_system`
+pub struct NavigationPlugin;
+
+impl crate::plugin::Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        use crate::schedule::IntoSystemDescriptor;
+
+        app.register_system(avoidance_system.before("navigation"))
+            .register_system(path_agent_system.label("navigation"));
+    }
+}
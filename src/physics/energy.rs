@@ -0,0 +1,180 @@
+//! Energy and momentum totals for checking integrator behavior
+
+use super::{PhysicsSettings, RigidBody};
+use crate::ecs::World;
+use crate::input::InputState;
+use crate::math::{Transform, Vector3, Velocity};
+use crate::time::TimeState;
+use anyhow::{Context, Result};
+use cgmath::{InnerSpace, Zero};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::Path;
+
+/// Totals over all dynamic `RigidBody` entities after one physics step
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnergySample {
+    /// Elapsed simulation time in seconds
+    pub time: f32,
+    /// Sum of ½mv²
+    pub kinetic: f32,
+    /// Gravitational potential energy relative to the origin, -m·g·p
+    pub potential: f32,
+    /// Sum of m·v
+    pub momentum: Vector3<f32>,
+    /// Sum of m·(p × v) about the origin
+    pub angular_momentum: Vector3<f32>,
+}
+
+impl EnergySample {
+    /// Kinetic plus potential energy
+    pub fn total(&self) -> f32 {
+        self.kinetic + self.potential
+    }
+}
+
+/// Resource recording an `EnergySample` every physics step
+///
+/// Insert it to turn on `energy_system`, which `PhysicsPlugin` registers
+/// after `physics_step`. A stable integrator without damping, ground, or
+/// water keeps `drift` near zero; damping and collisions make energy fall.
+/// Static bodies and bodies without a `Velocity` count as at rest.
+#[derive(Debug, Clone)]
+pub struct EnergyDiagnostics {
+    history: VecDeque<EnergySample>,
+    /// Samples kept; the oldest are dropped first
+    pub capacity: usize,
+}
+
+impl Default for EnergyDiagnostics {
+    fn default() -> Self {
+        Self::new(1000)
+    }
+}
+
+impl EnergyDiagnostics {
+    /// Keep the last `capacity` samples
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            history: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Add a sample, dropping the oldest past `capacity`
+    pub fn record(&mut self, sample: EnergySample) {
+        while self.history.len() >= self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back(sample);
+    }
+
+    /// Recorded samples, oldest first
+    pub fn history(&self) -> impl Iterator<Item = &EnergySample> {
+        self.history.iter()
+    }
+
+    /// The most recent sample
+    pub fn latest(&self) -> Option<&EnergySample> {
+        self.history.back()
+    }
+
+    /// Change in total energy from the oldest kept sample to the latest,
+    /// relative to the oldest's magnitude
+    pub fn drift(&self) -> f32 {
+        match (self.history.front(), self.history.back()) {
+            (Some(first), Some(last)) => {
+                (last.total() - first.total()) / first.total().abs().max(f32::EPSILON)
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Largest change in momentum magnitude between the oldest kept sample
+    /// and any later one
+    pub fn momentum_drift(&self) -> f32 {
+        let Some(first) = self.history.front() else {
+            return 0.0;
+        };
+        self.history
+            .iter()
+            .map(|sample| (sample.momentum - first.momentum).magnitude())
+            .fold(0.0, f32::max)
+    }
+
+    /// Forget all samples
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+
+    /// Write the history as CSV, one row per sample, for plotting
+    pub fn write_csv(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut file = std::io::BufWriter::new(
+            std::fs::File::create(path)
+                .with_context(|| format!("Failed to create {}", path.display()))?,
+        );
+        writeln!(file, "time,kinetic,potential,total,px,py,pz,lx,ly,lz")?;
+        for sample in &self.history {
+            writeln!(
+                file,
+                "{},{},{},{},{},{},{},{},{},{}",
+                sample.time,
+                sample.kinetic,
+                sample.potential,
+                sample.total(),
+                sample.momentum.x,
+                sample.momentum.y,
+                sample.momentum.z,
+                sample.angular_momentum.x,
+                sample.angular_momentum.y,
+                sample.angular_momentum.z
+            )?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// Energy and momentum of every dynamic body in `world`, under `gravity`
+pub fn measure_energy(world: &World, gravity: Vector3<f32>, time: f32) -> EnergySample {
+    let mut sample = EnergySample {
+        time,
+        kinetic: 0.0,
+        potential: 0.0,
+        momentum: Vector3::zero(),
+        angular_momentum: Vector3::zero(),
+    };
+    for (entity, body) in world.query::<RigidBody>() {
+        if body.is_static() {
+            continue;
+        }
+        let Some(transform) = world.get_component::<Transform>(entity) else {
+            continue;
+        };
+        let velocity = world
+            .get_component::<Velocity>(entity)
+            .map_or_else(Vector3::zero, |velocity| velocity.linear);
+        let position = transform.position;
+        sample.kinetic += 0.5 * body.mass * velocity.magnitude2();
+        sample.potential -= body.mass * body.gravity_scale * gravity.dot(position);
+        sample.momentum += velocity * body.mass;
+        sample.angular_momentum += position.cross(velocity) * body.mass;
+    }
+    sample
+}
+
+/// Record a sample into `EnergyDiagnostics`, if that resource exists
+pub fn energy_system(world: &mut World, _input: &InputState, time: &TimeState) {
+    if !world.has_resource::<EnergyDiagnostics>() {
+        return;
+    }
+    let gravity = world.resource::<PhysicsSettings>().map_or_else(
+        || PhysicsSettings::new().gravity,
+        |settings| settings.gravity,
+    );
+    let sample = measure_energy(world, gravity, time.elapsed_seconds());
+    if let Some(diagnostics) = world.resource_mut::<EnergyDiagnostics>() {
+        diagnostics.record(sample);
+    }
+}
@@ -0,0 +1,540 @@
+//! Point-mass physics with articulated joints
+//!
+//! Bodies are simulated as point masses using position-based dynamics:
+//! `physics_step` applies gravity, then solves each `Joint` as a fixed-length
+//! link with optional angular limits. That is enough for pendulum chains,
+//! rope, and simple ragdolls. Bodies inside a `WaterVolume` also float.
+//! Insert an `EnergyDiagnostics` resource to track energy and momentum per step.
+//!
+//! ```rust,no_run
+//! use qsi::prelude::*;
+//! use qsi::physics::{self, JointKind, PhysicsPlugin};
+//!
+//! fn setup(world: &mut World, _renderer: &mut Renderer) {
+//!     physics::spawn_chain(
+//!         world,
+//!         Vector3::new(0.0, 5.0, 0.0),
+//!         Vector3::new(1.0, 0.0, 0.0),
+//!         8,
+//!         0.5,
+//!         1.0,
+//!         JointKind::Ball { cone_limit: None },
+//!     );
+//!     physics::spawn_ragdoll(world, Vector3::new(3.0, 4.0, 0.0), 1.0);
+//! }
+//!
+//! App::new().add_plugin(PhysicsPlugin).add_startup_system(setup).run().unwrap();
+//! ```
+
+mod energy;
+mod projectile;
+mod vehicle;
+mod water;
+
+pub use energy::{EnergyDiagnostics, EnergySample, energy_system, measure_energy};
+pub use projectile::{
+    Projectile, ProjectileImpact, predict_trajectory, projectile_system, spawn_projectile,
+};
+pub use vehicle::{
+    Ground, Vehicle, VehicleControls, VehicleInput, Wheel, spawn_car, vehicle_system,
+};
+pub use water::{WaterShape, WaterSurface, WaterVolume, water_surface_system};
+
+use crate::App;
+use crate::ecs::{Component, EntityId, EntityRef, EntityRefs, World};
+use crate::input::InputState;
+use crate::math::{Transform, Vector3, Velocity};
+use crate::time::TimeState;
+use cgmath::{InnerSpace, Quaternion, Rad, Rotation, Zero};
+use std::collections::HashMap;
+
+/// Simulation settings resource
+#[derive(Debug, Clone)]
+pub struct PhysicsSettings {
+    pub gravity: Vector3<f32>,
+    /// Sub-steps per frame; more gives stiffer joints
+    pub substeps: u32,
+    /// Constraint iterations per sub-step
+    pub iterations: u32,
+    /// Bodies are kept above this height when set
+    pub ground_height: Option<f32>,
+    /// Longest frame time simulated in one update, in seconds
+    pub max_delta: f32,
+}
+
+impl PhysicsSettings {
+    pub fn new() -> Self {
+        Self {
+            gravity: Vector3::new(0.0, -9.81, 0.0),
+            substeps: 4,
+            iterations: 4,
+            ground_height: None,
+            max_delta: 1.0 / 30.0,
+        }
+    }
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Point mass driven by `physics_step`; a mass of zero makes the body static
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RigidBody {
+    pub mass: f32,
+    pub gravity_scale: f32,
+    /// Fraction of velocity lost per second
+    pub linear_damping: f32,
+    /// Approximate body size, used for buoyancy
+    #[cfg_attr(feature = "serde", serde(default = "default_radius"))]
+    pub radius: f32,
+}
+
+fn default_radius() -> f32 {
+    0.1
+}
+
+impl Component for RigidBody {}
+
+impl RigidBody {
+    /// Create a dynamic body with the given mass
+    pub fn dynamic(mass: f32) -> Self {
+        Self {
+            mass,
+            gravity_scale: 1.0,
+            linear_damping: 0.05,
+            radius: default_radius(),
+        }
+    }
+
+    /// Create an immovable body, useful as an anchor
+    pub fn fixed() -> Self {
+        Self::dynamic(0.0)
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.mass <= 0.0
+    }
+
+    pub fn inverse_mass(&self) -> f32 {
+        if self.is_static() {
+            0.0
+        } else {
+            1.0 / self.mass
+        }
+    }
+}
+
+/// Angular behaviour of a joint
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JointKind {
+    /// Free rotation, optionally limited to a cone around the rest direction
+    Ball { cone_limit: Option<Rad<f32>> },
+    /// Rotation about a single axis, limited to `[min, max]` from the rest direction
+    Hinge {
+        axis: Vector3<f32>,
+        min: Rad<f32>,
+        max: Rad<f32>,
+    },
+}
+
+/// Connects a body to its parent body at a fixed distance
+///
+/// Lives on the child entity. Limits are measured against `rest_direction`
+/// (parent to child, world space at rest), which follows the parent's own
+/// bone when the parent is itself jointed.
+#[derive(Debug, Clone)]
+pub struct Joint {
+    pub parent: Option<EntityRef>,
+    pub length: f32,
+    pub kind: JointKind,
+    pub rest_direction: Vector3<f32>,
+}
+
+impl Component for Joint {}
+
+impl EntityRefs for Joint {
+    fn for_each_ref(&mut self, f: &mut dyn FnMut(&mut Option<EntityRef>)) {
+        f(&mut self.parent);
+    }
+}
+
+impl Joint {
+    /// Create a joint from the current positions of parent and child
+    pub fn between(world: &World, parent: EntityId, child: EntityId, kind: JointKind) -> Self {
+        let position = |entity| {
+            world
+                .get_component::<Transform>(entity)
+                .map(|t| t.position)
+                .unwrap_or_else(Vector3::zero)
+        };
+        let offset = position(child) - position(parent);
+        let length = offset.magnitude();
+        let rest_direction = if length > 1e-6 {
+            offset / length
+        } else {
+            -Vector3::unit_y()
+        };
+        Self {
+            parent: world.entity_ref(parent),
+            length,
+            kind,
+            rest_direction,
+        }
+    }
+}
+
+struct Body {
+    entity: EntityId,
+    position: Vector3<f32>,
+    previous: Vector3<f32>,
+    velocity: Vector3<f32>,
+    inverse_mass: f32,
+    gravity_scale: f32,
+    damping: f32,
+    radius: f32,
+}
+
+struct Link {
+    child: usize,
+    parent: usize,
+    /// Parent's own link: (grandparent index, parent rest direction)
+    parent_link: Option<(usize, Vector3<f32>)>,
+    length: f32,
+    kind: JointKind,
+    rest_direction: Vector3<f32>,
+}
+
+/// Advance all rigid bodies and solve joints
+pub fn physics_step(world: &mut World, _input: &InputState, time: &TimeState) {
+    let settings = world
+        .resource::<PhysicsSettings>()
+        .cloned()
+        .unwrap_or_default();
+    let dt = time.delta_seconds().min(settings.max_delta);
+    if dt <= 0.0 {
+        return;
+    }
+
+    let mut bodies = Vec::new();
+    let mut index = HashMap::new();
+    for (entity, body) in world.query::<RigidBody>() {
+        let Some(transform) = world.get_component::<Transform>(entity) else {
+            continue;
+        };
+        let velocity = world
+            .get_component::<Velocity>(entity)
+            .map(|v| v.linear)
+            .unwrap_or_else(Vector3::zero);
+        index.insert(entity, bodies.len());
+        bodies.push(Body {
+            entity,
+            position: transform.position,
+            previous: transform.position,
+            velocity,
+            inverse_mass: body.inverse_mass(),
+            gravity_scale: body.gravity_scale,
+            damping: body.linear_damping,
+            radius: body.radius,
+        });
+    }
+
+    let parent_of = |entity: EntityId| {
+        world
+            .get_component::<Joint>(entity)
+            .and_then(|joint| joint.parent.and_then(|p| p.get(world)).map(|p| (p, joint)))
+    };
+    let links: Vec<Link> = world
+        .query::<Joint>()
+        .filter_map(|(entity, joint)| {
+            let child = *index.get(&entity)?;
+            let parent_entity = joint.parent?.get(world)?;
+            let parent = *index.get(&parent_entity)?;
+            let parent_link = parent_of(parent_entity).and_then(|(grandparent, parent_joint)| {
+                Some((*index.get(&grandparent)?, parent_joint.rest_direction))
+            });
+            Some(Link {
+                child,
+                parent,
+                parent_link,
+                length: joint.length,
+                kind: joint.kind,
+                rest_direction: joint.rest_direction,
+            })
+        })
+        .collect();
+
+    let water: Vec<WaterVolume> = world
+        .query::<WaterVolume>()
+        .map(|(_, water)| water.clone())
+        .collect();
+    let elapsed = time.elapsed_seconds();
+
+    let substeps = settings.substeps.max(1);
+    let h = dt / substeps as f32;
+    for _ in 0..substeps {
+        for body in bodies.iter_mut().filter(|b| b.inverse_mass > 0.0) {
+            body.velocity += settings.gravity * body.gravity_scale * h;
+            for volume in &water {
+                if let Some((buoyancy, drag)) = volume.forces(
+                    body.position,
+                    body.radius,
+                    body.inverse_mass,
+                    settings.gravity,
+                    elapsed,
+                ) {
+                    body.velocity += buoyancy * h;
+                    body.velocity *= (1.0 - drag * h).max(0.0);
+                }
+            }
+            body.previous = body.position;
+            body.position += body.velocity * h;
+        }
+
+        for _ in 0..settings.iterations.max(1) {
+            for link in &links {
+                solve_link(&mut bodies, link);
+            }
+            if let Some(ground) = settings.ground_height {
+                for body in bodies.iter_mut().filter(|b| b.inverse_mass > 0.0) {
+                    body.position.y = body.position.y.max(ground);
+                }
+            }
+        }
+
+        for body in bodies.iter_mut().filter(|b| b.inverse_mass > 0.0) {
+            body.velocity = (body.position - body.previous) / h * (1.0 - body.damping * h).max(0.0);
+        }
+    }
+
+    for body in &bodies {
+        if body.inverse_mass == 0.0 {
+            continue;
+        }
+        if let Some(transform) = world.get_component_mut::<Transform>(body.entity) {
+            transform.position = body.position;
+        }
+        if let Some(velocity) = world.get_component_mut::<Velocity>(body.entity) {
+            velocity.linear = body.velocity;
+        }
+    }
+}
+
+fn solve_link(bodies: &mut [Body], link: &Link) {
+    let (wa, wb) = (
+        bodies[link.parent].inverse_mass,
+        bodies[link.child].inverse_mass,
+    );
+    if wa + wb == 0.0 {
+        return;
+    }
+    let pa = bodies[link.parent].position;
+    let pb = bodies[link.child].position;
+    let offset = pb - pa;
+    if offset.magnitude2() < 1e-12 {
+        return;
+    }
+
+    // Rotate the rest frame along with the parent's bone
+    let frame = link
+        .parent_link
+        .map(|(grandparent, parent_rest)| {
+            let bone = pa - bodies[grandparent].position;
+            if bone.magnitude2() > 1e-12 {
+                Quaternion::from_arc(parent_rest, bone.normalize(), None)
+            } else {
+                Quaternion::new(1.0, 0.0, 0.0, 0.0)
+            }
+        })
+        .unwrap_or(Quaternion::new(1.0, 0.0, 0.0, 0.0));
+    let rest = frame.rotate_vector(link.rest_direction);
+
+    let direction = limit_direction(offset.normalize(), rest, link.kind, frame);
+    let correction = pa + direction * link.length - pb;
+    bodies[link.child].position += correction * (wb / (wa + wb));
+    bodies[link.parent].position -= correction * (wa / (wa + wb));
+}
+
+fn limit_direction(
+    direction: Vector3<f32>,
+    rest: Vector3<f32>,
+    kind: JointKind,
+    frame: Quaternion<f32>,
+) -> Vector3<f32> {
+    match kind {
+        JointKind::Ball { cone_limit: None } => direction,
+        JointKind::Ball {
+            cone_limit: Some(limit),
+        } => {
+            let angle = direction.dot(rest).clamp(-1.0, 1.0).acos();
+            if angle <= limit.0 {
+                return direction;
+            }
+            let side = direction - rest * direction.dot(rest);
+            if side.magnitude2() < 1e-12 {
+                return rest;
+            }
+            rest * limit.0.cos() + side.normalize() * limit.0.sin()
+        }
+        JointKind::Hinge { axis, min, max } => {
+            let axis = frame.rotate_vector(axis.normalize());
+            let reference = rest - axis * rest.dot(axis);
+            let projected = direction - axis * direction.dot(axis);
+            if reference.magnitude2() < 1e-12 || projected.magnitude2() < 1e-12 {
+                return rest;
+            }
+            let reference = reference.normalize();
+            let projected = projected.normalize();
+            let angle = axis
+                .dot(reference.cross(projected))
+                .atan2(reference.dot(projected))
+                .clamp(min.0, max.0);
+            reference * angle.cos() + axis.cross(reference) * angle.sin()
+        }
+    }
+}
+
+/// Spawn a static anchor followed by `links` bodies joined end to end
+///
+/// Returns the anchor first, then each link in order.
+pub fn spawn_chain(
+    world: &mut World,
+    anchor: Vector3<f32>,
+    direction: Vector3<f32>,
+    links: usize,
+    link_length: f32,
+    mass: f32,
+    kind: JointKind,
+) -> Vec<EntityId> {
+    let direction = direction.normalize();
+    let mut chain = vec![
+        world
+            .spawn()
+            .with(Transform::at_position(anchor))
+            .with(RigidBody::fixed())
+            .build(),
+    ];
+    for i in 1..=links {
+        let position = anchor + direction * link_length * i as f32;
+        let entity = world
+            .spawn()
+            .with(Transform::at_position(position))
+            .with(Velocity::default())
+            .with(RigidBody::dynamic(mass))
+            .build();
+        let joint = Joint::between(world, chain[i - 1], entity, kind);
+        world.add_component(entity, joint);
+        chain.push(entity);
+    }
+    chain
+}
+
+/// Bodies making up a ragdoll spawned by `spawn_ragdoll`
+#[derive(Debug, Clone, Copy)]
+pub struct Ragdoll {
+    pub pelvis: EntityId,
+    pub chest: EntityId,
+    pub head: EntityId,
+    pub left_elbow: EntityId,
+    pub left_hand: EntityId,
+    pub right_elbow: EntityId,
+    pub right_hand: EntityId,
+    pub left_knee: EntityId,
+    pub left_foot: EntityId,
+    pub right_knee: EntityId,
+    pub right_foot: EntityId,
+}
+
+/// Spawn a stick-figure ragdoll standing with its pelvis at `position`
+pub fn spawn_ragdoll(world: &mut World, position: Vector3<f32>, scale: f32) -> Ragdoll {
+    let body = |world: &mut World, offset: Vector3<f32>, mass: f32| {
+        world
+            .spawn()
+            .with(Transform::at_position(position + offset * scale))
+            .with(Velocity::default())
+            .with(RigidBody::dynamic(mass))
+            .build()
+    };
+
+    let pelvis = body(world, Vector3::new(0.0, 0.0, 0.0), 4.0);
+    let chest = body(world, Vector3::new(0.0, 0.6, 0.0), 4.0);
+    let head = body(world, Vector3::new(0.0, 0.9, 0.0), 1.5);
+    let left_elbow = body(world, Vector3::new(-0.35, 0.35, 0.0), 1.0);
+    let left_hand = body(world, Vector3::new(-0.45, 0.05, 0.0), 0.5);
+    let right_elbow = body(world, Vector3::new(0.35, 0.35, 0.0), 1.0);
+    let right_hand = body(world, Vector3::new(0.45, 0.05, 0.0), 0.5);
+    let left_knee = body(world, Vector3::new(-0.15, -0.45, 0.0), 2.0);
+    let left_foot = body(world, Vector3::new(-0.15, -0.9, 0.0), 1.0);
+    let right_knee = body(world, Vector3::new(0.15, -0.45, 0.0), 2.0);
+    let right_foot = body(world, Vector3::new(0.15, -0.9, 0.0), 1.0);
+
+    let deg = |degrees: f32| Rad(degrees.to_radians());
+    let ball = |limit: f32| JointKind::Ball {
+        cone_limit: Some(deg(limit)),
+    };
+    let knee = JointKind::Hinge {
+        axis: Vector3::unit_x(),
+        min: deg(0.0),
+        max: deg(140.0),
+    };
+    let elbow = JointKind::Hinge {
+        axis: Vector3::unit_z(),
+        min: deg(-140.0),
+        max: deg(140.0),
+    };
+
+    let joints = [
+        (pelvis, chest, ball(30.0)),
+        (chest, head, ball(40.0)),
+        (chest, left_elbow, ball(120.0)),
+        (left_elbow, left_hand, elbow),
+        (chest, right_elbow, ball(120.0)),
+        (right_elbow, right_hand, elbow),
+        (pelvis, left_knee, ball(70.0)),
+        (left_knee, left_foot, knee),
+        (pelvis, right_knee, ball(70.0)),
+        (right_knee, right_foot, knee),
+    ];
+    for (parent, child, kind) in joints {
+        let joint = Joint::between(world, parent, child, kind);
+        world.add_component(child, joint);
+    }
+
+    Ragdoll {
+        pelvis,
+        chest,
+        head,
+        left_elbow,
+        left_hand,
+        right_elbow,
+        right_hand,
+        left_knee,
+        left_foot,
+        right_knee,
+        right_foot,
+    }
+}
+
+/// Registers `physics_step`, vehicles, projectiles, energy diagnostics, and the water surface renderer; insert a `PhysicsSettings` resource to change its defaults
+pub struct PhysicsPlugin;
+
+impl crate::plugin::Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        use crate::schedule::IntoSystemDescriptor;
+
+        app.register_render_system(water_surface_system);
+        app.register_system(vehicle_system.before("physics"))
+            .register_system(physics_step.label("physics"))
+            .register_system(projectile_system.after("physics"))
+            .register_system(energy_system.after("physics"))
+            .register_system(
+                (|world: &mut World, _: &InputState, _: &TimeState| {
+                    world.clear_dangling_refs::<Joint>();
+                })
+                .before("physics"),
+            );
+    }
+}
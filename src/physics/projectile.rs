@@ -0,0 +1,280 @@
+//! Ballistic projectiles with swept impact detection
+
+use super::{PhysicsSettings, RigidBody};
+use crate::ecs::{Component, EntityId, Event, World};
+use crate::input::InputState;
+use crate::math::{Transform, Vector3, Velocity};
+use crate::time::TimeState;
+use cgmath::{InnerSpace, Zero};
+
+/// A ballistic projectile moved by `projectile_system`
+///
+/// Projectiles are swept from their previous to their new position each
+/// step, so fast ones can't tunnel through the ground or rigid bodies.
+#[derive(Debug, Clone)]
+pub struct Projectile {
+    /// Quadratic drag coefficient (acceleration = -drag * |v| * v)
+    pub drag: f32,
+    pub gravity_scale: f32,
+    pub radius: f32,
+    /// Seconds left before the projectile despawns
+    pub lifetime: f32,
+    pub despawn_on_impact: bool,
+    /// Entity that fired the projectile; never hit by it
+    pub owner: Option<EntityId>,
+    /// What the projectile hit and still touches (`None` for the ground); these
+    /// are not hit again until it moves clear of them
+    pub touching: Vec<Option<EntityId>>,
+}
+
+impl Component for Projectile {}
+
+impl Default for Projectile {
+    fn default() -> Self {
+        Self {
+            drag: 0.0,
+            gravity_scale: 1.0,
+            radius: 0.05,
+            lifetime: 10.0,
+            despawn_on_impact: true,
+            owner: None,
+            touching: Vec::new(),
+        }
+    }
+}
+
+impl Projectile {
+    pub fn with_drag(mut self, drag: f32) -> Self {
+        self.drag = drag;
+        self
+    }
+
+    pub fn with_owner(mut self, owner: EntityId) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+}
+
+/// Sent when a projectile hits the ground or a rigid body
+#[derive(Debug, Clone)]
+pub struct ProjectileImpact {
+    pub projectile: EntityId,
+    /// Rigid body that was hit, or `None` for the ground
+    pub target: Option<EntityId>,
+    pub point: Vector3<f32>,
+    pub normal: Vector3<f32>,
+    pub velocity: Vector3<f32>,
+}
+
+impl Event for ProjectileImpact {}
+
+/// Spawn a projectile at `position` moving with `velocity`
+pub fn spawn_projectile(
+    world: &mut World,
+    position: Vector3<f32>,
+    velocity: Vector3<f32>,
+    projectile: Projectile,
+) -> EntityId {
+    world
+        .spawn()
+        .with(Transform::at_position(position))
+        .with(Velocity::linear(velocity))
+        .with(projectile)
+        .build()
+}
+
+/// Advance one integration step; shared by the system and `predict_trajectory`
+fn step(
+    position: Vector3<f32>,
+    velocity: Vector3<f32>,
+    gravity: Vector3<f32>,
+    drag: f32,
+    dt: f32,
+) -> (Vector3<f32>, Vector3<f32>) {
+    let acceleration = gravity - velocity * (drag * velocity.magnitude());
+    let velocity = velocity + acceleration * dt;
+    (position + velocity * dt, velocity)
+}
+
+/// Sample future positions of a projectile, e.g. for an aiming arc
+///
+/// Returns `samples` points spaced `interval` seconds apart, starting at `origin`.
+pub fn predict_trajectory(
+    origin: Vector3<f32>,
+    velocity: Vector3<f32>,
+    gravity: Vector3<f32>,
+    drag: f32,
+    interval: f32,
+    samples: usize,
+) -> Vec<Vector3<f32>> {
+    const SUBSTEPS: u32 = 4;
+    let dt = interval / SUBSTEPS as f32;
+    let (mut position, mut velocity) = (origin, velocity);
+    let mut points = Vec::with_capacity(samples);
+    for _ in 0..samples {
+        points.push(position);
+        for _ in 0..SUBSTEPS {
+            (position, velocity) = step(position, velocity, gravity, drag, dt);
+        }
+    }
+    points
+}
+
+/// Distance within which a projectile still counts as touching what it hit
+const CONTACT_SLOP: f32 = 1e-3;
+
+/// Move projectiles, emit `ProjectileImpact` events, and despawn expired ones
+pub fn projectile_system(world: &mut World, _input: &InputState, time: &TimeState) {
+    let dt = time.delta_seconds();
+    if dt <= 0.0 {
+        return;
+    }
+    let settings = world
+        .resource::<PhysicsSettings>()
+        .cloned()
+        .unwrap_or_default();
+
+    let targets: Vec<(EntityId, Vector3<f32>, f32)> = world
+        .query::<RigidBody>()
+        .filter_map(|(entity, body)| {
+            let position = world.get_component::<Transform>(entity)?.position;
+            Some((entity, position, body.radius))
+        })
+        .collect();
+    let projectiles: Vec<EntityId> = world
+        .query::<Projectile>()
+        .map(|(entity, _)| entity)
+        .collect();
+
+    let mut impacts = Vec::new();
+    let mut expired = Vec::new();
+    for entity in projectiles {
+        let Some(projectile) = world.get_component_mut::<Projectile>(entity) else {
+            continue;
+        };
+        projectile.lifetime -= dt;
+        let projectile = projectile.clone();
+        if projectile.lifetime <= 0.0 {
+            expired.push(entity);
+            continue;
+        }
+        let (Some(start), Some(velocity)) = (
+            world.get_component::<Transform>(entity).map(|t| t.position),
+            world.get_component::<Velocity>(entity).map(|v| v.linear),
+        ) else {
+            continue;
+        };
+
+        let gravity = settings.gravity * projectile.gravity_scale;
+        let (end, velocity) = step(start, velocity, gravity, projectile.drag, dt);
+
+        // Resting against or lodged in something it already hit: stay quiet
+        // until it moves clear
+        let mut touching = Vec::new();
+        let mut hit: Option<(f32, Option<EntityId>, Vector3<f32>)> = None;
+        if let Some(ground) = settings.ground_height {
+            let (above, below) = (start.y - ground, end.y - ground);
+            if projectile.touching.contains(&None) && above <= projectile.radius + CONTACT_SLOP {
+                touching.push(None);
+            } else if above >= projectile.radius && below < projectile.radius {
+                let t = (above - projectile.radius) / (above - below);
+                hit = Some((t, None, Vector3::unit_y()));
+            }
+        }
+        for &(target, center, radius) in &targets {
+            if Some(target) == projectile.owner || target == entity {
+                continue;
+            }
+            let reach = radius + projectile.radius;
+            if projectile.touching.contains(&Some(target))
+                && (start - center).magnitude() <= reach + CONTACT_SLOP
+            {
+                touching.push(Some(target));
+                continue;
+            }
+            if let Some(t) = sweep_sphere(start, end, center, reach)
+                && hit.is_none_or(|(best, ..)| t < best)
+            {
+                let point = start + (end - start) * t;
+                let offset = point - center;
+                // Starting at the centre leaves no direction to push out along
+                let normal = if offset.magnitude2() > 1e-12 {
+                    offset.normalize()
+                } else if velocity.magnitude2() > 1e-12 {
+                    -velocity.normalize()
+                } else {
+                    Vector3::unit_y()
+                };
+                hit = Some((t, Some(target), normal));
+            }
+        }
+        if let Some((_, target, _)) = hit {
+            touching.push(target);
+        }
+        if let Some(projectile) = world.get_component_mut::<Projectile>(entity) {
+            projectile.touching = touching;
+        }
+
+        let position = match hit {
+            Some((t, target, normal)) => {
+                let point = start + (end - start) * t;
+                impacts.push(ProjectileImpact {
+                    projectile: entity,
+                    target,
+                    point,
+                    normal,
+                    velocity,
+                });
+                if projectile.despawn_on_impact {
+                    expired.push(entity);
+                }
+                point
+            }
+            None => end,
+        };
+        if let Some(transform) = world.get_component_mut::<Transform>(entity) {
+            transform.position = position;
+        }
+        if let Some(v) = world.get_component_mut::<Velocity>(entity) {
+            v.linear = if hit.is_some() {
+                Vector3::zero()
+            } else {
+                velocity
+            };
+        }
+    }
+
+    for impact in impacts {
+        world.send(impact);
+    }
+    for entity in expired {
+        world.despawn(entity);
+    }
+}
+
+/// Fraction along `start..end` where the segment first enters a sphere
+fn sweep_sphere(
+    start: Vector3<f32>,
+    end: Vector3<f32>,
+    center: Vector3<f32>,
+    radius: f32,
+) -> Option<f32> {
+    let direction = end - start;
+    let offset = start - center;
+    let a = direction.magnitude2();
+    let c = offset.magnitude2() - radius * radius;
+    if c <= 0.0 {
+        // Already overlapping at the start
+        return Some(0.0);
+    }
+    if a < 1e-12 {
+        return None;
+    }
+    let b = offset.dot(direction);
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t = (-b - discriminant.sqrt()) / a;
+    (0.0..=1.0).contains(&t).then_some(t)
+}
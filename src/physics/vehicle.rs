@@ -0,0 +1,341 @@
+//! Raycast vehicles driven by the physics step
+
+use super::{PhysicsSettings, RigidBody};
+use crate::ecs::{Component, EntityId, World};
+use crate::input::{ActionMap, InputState};
+use crate::math::{Ray, Transform, Vector3, Velocity};
+use crate::time::TimeState;
+use cgmath::InnerSpace;
+use winit::keyboard::KeyCode;
+
+/// A wheel attached to a `Vehicle`
+#[derive(Debug, Clone)]
+pub struct Wheel {
+    /// Suspension mount point in chassis space (+Z forward, +Y up)
+    pub offset: Vector3<f32>,
+    /// Whether the wheel turns with steering input
+    pub steered: bool,
+    /// Whether engine force is applied through this wheel
+    pub driven: bool,
+    /// Current suspension compression in metres
+    pub compression: f32,
+    /// Whether the wheel touched the ground last step
+    pub grounded: bool,
+}
+
+impl Wheel {
+    pub fn new(offset: Vector3<f32>, steered: bool, driven: bool) -> Self {
+        Self {
+            offset,
+            steered,
+            driven,
+            compression: 0.0,
+            grounded: false,
+        }
+    }
+}
+
+/// Marker for entities vehicles drive on, such as terrain, roads, and ramps
+///
+/// Suspension rays test only these, by their triangles when the entity keeps
+/// a `MeshData`, otherwise by their bounds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Ground;
+
+impl Component for Ground {}
+
+/// Driver input for a `Vehicle`, each in -1 to 1 (brake 0 to 1)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VehicleInput {
+    pub throttle: f32,
+    pub brake: f32,
+    pub steer: f32,
+}
+
+/// Raycast vehicle on a `RigidBody` chassis
+///
+/// Each wheel casts a ray straight down from its mount against the triangle
+/// meshes of `Ground` entities and the ground plane
+/// (`PhysicsSettings::ground_height`, or y = 0 when unset), and pushes the
+/// chassis up from the nearest hit with a spring-damper.
+/// Heading is the chassis `Transform`'s yaw.
+#[derive(Debug, Clone)]
+pub struct Vehicle {
+    pub wheels: Vec<Wheel>,
+    pub wheel_radius: f32,
+    /// Suspension length at rest
+    pub suspension_length: f32,
+    /// Spring force per metre of compression, per wheel
+    pub stiffness: f32,
+    /// Damping force per m/s of vertical speed, per wheel
+    pub damping: f32,
+    /// Forward force at full throttle
+    pub engine_force: f32,
+    /// Deceleration at full brake in m/s²
+    pub brake_force: f32,
+    /// Maximum steering angle in radians
+    pub max_steer: f32,
+    /// Fraction of sideways velocity removed per second when grounded
+    pub grip: f32,
+    pub input: VehicleInput,
+}
+
+impl Component for Vehicle {}
+
+impl Vehicle {
+    /// Four-wheeled car with front steering and rear drive
+    pub fn car(width: f32, length: f32) -> Self {
+        let (x, z) = (width * 0.5, length * 0.5);
+        Self {
+            wheels: vec![
+                Wheel::new(Vector3::new(-x, 0.0, z), true, false),
+                Wheel::new(Vector3::new(x, 0.0, z), true, false),
+                Wheel::new(Vector3::new(-x, 0.0, -z), false, true),
+                Wheel::new(Vector3::new(x, 0.0, -z), false, true),
+            ],
+            wheel_radius: 0.35,
+            suspension_length: 0.4,
+            stiffness: 8000.0,
+            damping: 2000.0,
+            engine_force: 4000.0,
+            brake_force: 12.0,
+            max_steer: 0.6,
+            grip: 8.0,
+            input: VehicleInput::default(),
+        }
+    }
+
+    /// Distance between the frontmost and rearmost wheels
+    pub fn wheelbase(&self) -> f32 {
+        let (min, max) = self
+            .wheels
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), w| {
+                (min.min(w.offset.z), max.max(w.offset.z))
+            });
+        (max - min).max(0.1)
+    }
+}
+
+/// Maps actions from the `ActionMap` resource onto a `Vehicle`'s input
+#[derive(Debug, Clone)]
+pub struct VehicleControls {
+    pub accelerate: String,
+    pub reverse: String,
+    pub brake: String,
+    pub steer_left: String,
+    pub steer_right: String,
+}
+
+impl Component for VehicleControls {}
+
+impl Default for VehicleControls {
+    fn default() -> Self {
+        Self {
+            accelerate: "accelerate".into(),
+            reverse: "reverse".into(),
+            brake: "brake".into(),
+            steer_left: "steer_left".into(),
+            steer_right: "steer_right".into(),
+        }
+    }
+}
+
+impl VehicleControls {
+    /// WASD/arrow key bindings for the default action names
+    pub fn default_bindings() -> ActionMap {
+        ActionMap::new()
+            .bind("accelerate", KeyCode::KeyW)
+            .bind("accelerate", KeyCode::ArrowUp)
+            .bind("reverse", KeyCode::KeyS)
+            .bind("reverse", KeyCode::ArrowDown)
+            .bind("brake", KeyCode::Space)
+            .bind("steer_left", KeyCode::KeyA)
+            .bind("steer_left", KeyCode::ArrowLeft)
+            .bind("steer_right", KeyCode::KeyD)
+            .bind("steer_right", KeyCode::ArrowRight)
+    }
+}
+
+/// Read controls and apply suspension, drive, and steering to vehicles
+///
+/// Runs before `physics_step`, which then integrates gravity and position.
+pub fn vehicle_system(world: &mut World, input: &InputState, time: &TimeState) {
+    let dt = time.delta_seconds();
+    let settings = world
+        .resource::<PhysicsSettings>()
+        .cloned()
+        .unwrap_or_default();
+    let dt = dt.min(settings.max_delta);
+    if dt <= 0.0 {
+        return;
+    }
+
+    let default_bindings;
+    let actions = match world.resource::<ActionMap>() {
+        Some(actions) => actions,
+        None => {
+            default_bindings = VehicleControls::default_bindings();
+            &default_bindings
+        }
+    };
+    let inputs: Vec<(EntityId, VehicleInput)> = world
+        .query::<VehicleControls>()
+        .map(|(entity, controls)| {
+            let throttle = actions.axis(input, &controls.reverse, &controls.accelerate);
+            let steer = actions.axis(input, &controls.steer_right, &controls.steer_left);
+            let brake = if actions.pressed(input, &controls.brake) {
+                1.0
+            } else {
+                0.0
+            };
+            (
+                entity,
+                VehicleInput {
+                    throttle,
+                    brake,
+                    steer,
+                },
+            )
+        })
+        .collect();
+    for (entity, vehicle_input) in inputs {
+        if let Some(vehicle) = world.get_component_mut::<Vehicle>(entity) {
+            vehicle.input = vehicle_input;
+        }
+    }
+
+    let ground = settings.ground_height.unwrap_or(0.0);
+    let vehicles: Vec<EntityId> = world.query::<Vehicle>().map(|(entity, _)| entity).collect();
+    for entity in vehicles {
+        let Some(mass) = world
+            .get_component::<RigidBody>(entity)
+            .filter(|body| !body.is_static())
+            .map(|body| body.mass)
+        else {
+            continue;
+        };
+        let (Some(transform), Some(velocity)) = (
+            world.get_component::<Transform>(entity).cloned(),
+            world.get_component::<Velocity>(entity).cloned(),
+        ) else {
+            continue;
+        };
+
+        let yaw = transform.rotation.y;
+        let forward = Vector3::new(yaw.sin(), 0.0, yaw.cos());
+        let right = Vector3::new(-yaw.cos(), 0.0, yaw.sin());
+        let mut linear = velocity.linear;
+
+        // Ground distance under each wheel, cast before borrowing the vehicle
+        let Some(distances) = world.get_component::<Vehicle>(entity).map(|vehicle| {
+            vehicle
+                .wheels
+                .iter()
+                .map(|wheel| {
+                    let mount = transform.position
+                        + right * -wheel.offset.x
+                        + Vector3::unit_y() * wheel.offset.y
+                        + forward * wheel.offset.z;
+                    ground_distance(world, entity, mount, ground)
+                })
+                .collect::<Vec<_>>()
+        }) else {
+            continue;
+        };
+        let Some(vehicle) = world.get_component_mut::<Vehicle>(entity) else {
+            continue;
+        };
+
+        // Suspension
+        let mut lift = 0.0;
+        let mut grounded = 0;
+        let reach = vehicle.suspension_length + vehicle.wheel_radius;
+        for (wheel, distance) in vehicle.wheels.iter_mut().zip(distances) {
+            wheel.grounded = distance < reach;
+            wheel.compression = if wheel.grounded {
+                (reach - distance).min(vehicle.suspension_length)
+            } else {
+                0.0
+            };
+            if wheel.grounded {
+                grounded += 1;
+                let force = vehicle.stiffness * wheel.compression - vehicle.damping * linear.y;
+                lift += force.max(0.0);
+            }
+        }
+        linear.y += lift / mass * dt;
+
+        let wheel_count = vehicle.wheels.len().max(1) as f32;
+        let traction = grounded as f32 / wheel_count;
+        let mut yaw_rate = 0.0;
+        if grounded > 0 {
+            let driven = vehicle
+                .wheels
+                .iter()
+                .filter(|w| w.driven && w.grounded)
+                .count() as f32;
+            let driven_total = vehicle.wheels.iter().filter(|w| w.driven).count().max(1) as f32;
+            linear += forward
+                * (vehicle.input.throttle * vehicle.engine_force * driven / driven_total / mass
+                    * dt);
+
+            let mut forward_speed = linear.dot(forward);
+            let braking = vehicle.input.brake * vehicle.brake_force * traction * dt;
+            let braked = (forward_speed.abs() - braking)
+                .max(0.0)
+                .copysign(forward_speed);
+            linear += forward * (braked - forward_speed);
+            forward_speed = braked;
+
+            let sideways = linear.dot(right);
+            linear -= right * sideways * (vehicle.grip * traction * dt).min(1.0);
+
+            let steer_angle = vehicle.input.steer.clamp(-1.0, 1.0) * vehicle.max_steer;
+            if vehicle.wheels.iter().any(|w| w.steered && w.grounded) {
+                yaw_rate = forward_speed * steer_angle.tan() / vehicle.wheelbase();
+            }
+        }
+
+        if let Some(velocity) = world.get_component_mut::<Velocity>(entity) {
+            velocity.linear = linear;
+        }
+        if let Some(transform) = world.get_component_mut::<Transform>(entity) {
+            transform.rotation.y += yaw_rate * dt;
+        }
+    }
+}
+
+/// Distance straight down from `mount` to the nearest `Ground` mesh other
+/// than `chassis`, or to the ground plane at `ground` if that is closer
+///
+/// Hits at or behind the mount (the wheel sunk into the mesh) are skipped.
+fn ground_distance(world: &World, chassis: EntityId, mount: Vector3<f32>, ground: f32) -> f32 {
+    let ray = Ray::new(mount, -Vector3::unit_y());
+    let plane = mount.y - ground;
+    world
+        .pick_where(&ray, |entity, mesh| {
+            entity != chassis
+                && mesh.primitive_topology == wgpu::PrimitiveTopology::TriangleList
+                && world.has_component::<Ground>(entity)
+        })
+        .into_iter()
+        .find(|hit| hit.distance > 0.0)
+        .map_or(plane, |hit| hit.distance.min(plane))
+}
+
+/// Spawn a car chassis with rigid body, vehicle, and default controls
+pub fn spawn_car(world: &mut World, position: Vector3<f32>, mass: f32) -> EntityId {
+    let body = RigidBody {
+        linear_damping: 0.1,
+        ..RigidBody::dynamic(mass)
+    };
+    world
+        .spawn()
+        .with(Transform::at_position(position))
+        .with(Velocity::default())
+        .with(body)
+        .with(Vehicle::car(1.6, 2.6))
+        .with(VehicleControls::default())
+        .build()
+}
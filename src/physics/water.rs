@@ -0,0 +1,227 @@
+//! Water volumes with buoyancy, drag, and an animated surface
+
+use crate::ecs::{Component, World};
+use crate::graphics::{Mesh, Renderer, Vertex};
+use crate::math::{Transform, Vector3};
+use crate::time::TimeState;
+use cgmath::InnerSpace;
+use std::f32::consts::{PI, TAU};
+
+/// Region of water a `WaterVolume` occupies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WaterShape {
+    /// Infinite water below `height`
+    Plane { height: f32 },
+    /// Water filling an axis-aligned box; the surface is the top face
+    Box {
+        min: Vector3<f32>,
+        max: Vector3<f32>,
+    },
+}
+
+/// Applies buoyancy and drag to rigid bodies inside it
+#[derive(Debug, Clone)]
+pub struct WaterVolume {
+    pub shape: WaterShape,
+    /// Fluid density in kg/m³ (water is 1000)
+    pub density: f32,
+    /// Fraction of velocity lost per second while fully submerged
+    pub drag: f32,
+    pub wave_amplitude: f32,
+    pub wave_length: f32,
+    /// Wave travel speed in m/s
+    pub wave_speed: f32,
+}
+
+impl Component for WaterVolume {}
+
+impl WaterVolume {
+    /// Create calm water below `height`
+    pub fn plane(height: f32) -> Self {
+        Self {
+            shape: WaterShape::Plane { height },
+            density: 1000.0,
+            drag: 8.0,
+            wave_amplitude: 0.0,
+            wave_length: 4.0,
+            wave_speed: 1.0,
+        }
+    }
+
+    /// Create calm water filling a box
+    pub fn bounded(min: Vector3<f32>, max: Vector3<f32>) -> Self {
+        Self {
+            shape: WaterShape::Box { min, max },
+            ..Self::plane(max.y)
+        }
+    }
+
+    /// Add travelling waves to the surface
+    pub fn with_waves(mut self, amplitude: f32, length: f32, speed: f32) -> Self {
+        self.wave_amplitude = amplitude;
+        self.wave_length = length;
+        self.wave_speed = speed;
+        self
+    }
+
+    /// Height of the undisturbed surface
+    pub fn base_height(&self) -> f32 {
+        match self.shape {
+            WaterShape::Plane { height } => height,
+            WaterShape::Box { max, .. } => max.y,
+        }
+    }
+
+    /// Surface height at `(x, z)` after `time` seconds
+    pub fn surface_height(&self, x: f32, z: f32, time: f32) -> f32 {
+        if self.wave_amplitude == 0.0 || self.wave_length <= 0.0 {
+            return self.base_height();
+        }
+        let k = TAU / self.wave_length;
+        let omega = k * self.wave_speed;
+        let wave = (k * x + omega * time).sin() + (0.8 * k * z + 1.3 * omega * time).sin();
+        self.base_height() + self.wave_amplitude * 0.5 * wave
+    }
+
+    /// Fraction (0 to 1) of a sphere at `position` that is under water
+    pub fn submerged_fraction(&self, position: Vector3<f32>, radius: f32, time: f32) -> f32 {
+        if let WaterShape::Box { min, max } = self.shape {
+            let outside = position.x < min.x
+                || position.x > max.x
+                || position.z < min.z
+                || position.z > max.z
+                || position.y + radius < min.y;
+            if outside {
+                return 0.0;
+            }
+        }
+
+        let surface = self.surface_height(position.x, position.z, time);
+        if radius <= 0.0 {
+            return if position.y < surface { 1.0 } else { 0.0 };
+        }
+        // Spherical cap volume over sphere volume
+        let depth = (surface - (position.y - radius)).clamp(0.0, 2.0 * radius);
+        depth * depth * (3.0 * radius - depth) / (4.0 * radius * radius * radius)
+    }
+
+    /// Upward acceleration and drag rate for a body, or `None` when it is dry
+    pub(super) fn forces(
+        &self,
+        position: Vector3<f32>,
+        radius: f32,
+        inverse_mass: f32,
+        gravity: Vector3<f32>,
+        time: f32,
+    ) -> Option<(Vector3<f32>, f32)> {
+        let submerged = self.submerged_fraction(position, radius, time);
+        if submerged <= 0.0 {
+            return None;
+        }
+        let volume = 4.0 / 3.0 * PI * radius * radius * radius;
+        let buoyancy = -gravity * self.density * volume * submerged * inverse_mass;
+        Some((buoyancy, self.drag * submerged))
+    }
+}
+
+/// Animated surface mesh for the `WaterVolume` on the same entity
+#[derive(Debug, Clone)]
+pub struct WaterSurface {
+    /// Grid cells along each side
+    pub resolution: u32,
+    /// Side length of a plane's surface; boxes use their own extents
+    pub size: f32,
+    pub color: [f32; 3],
+}
+
+impl Component for WaterSurface {}
+
+impl Default for WaterSurface {
+    fn default() -> Self {
+        Self {
+            resolution: 32,
+            size: 20.0,
+            color: [0.1, 0.35, 0.6],
+        }
+    }
+}
+
+/// Move water surface vertices to the current wave state
+///
+/// Each surface keeps one dynamic `Mesh` whose buffers are rewritten in place.
+pub fn water_surface_system(world: &mut World, renderer: &mut Renderer, time: &TimeState) {
+    let t = time.elapsed_seconds();
+    let surfaces: Vec<_> = world
+        .query::<WaterSurface>()
+        .filter_map(|(entity, surface)| {
+            let water = world.get_component::<WaterVolume>(entity)?;
+            Some((entity, surface_geometry(water, surface, t)))
+        })
+        .collect();
+
+    for (entity, (vertices, indices)) in surfaces {
+        match world.get_component_mut::<Mesh>(entity) {
+            Some(mesh) if mesh.is_dynamic() => renderer.update_mesh(mesh, &vertices, &indices),
+            _ => {
+                let mesh = renderer.create_dynamic_mesh(&vertices, &indices);
+                world.add_component(entity, mesh);
+            }
+        }
+        if !world.has_component::<Transform>(entity) {
+            world.add_component(entity, Transform::default());
+        }
+    }
+}
+
+fn surface_geometry(
+    water: &WaterVolume,
+    surface: &WaterSurface,
+    time: f32,
+) -> (Vec<Vertex>, Vec<u16>) {
+    let (min, max) = match water.shape {
+        WaterShape::Plane { .. } => {
+            let half = surface.size * 0.5;
+            ((-half, -half), (half, half))
+        }
+        WaterShape::Box { min, max } => ((min.x, min.z), (max.x, max.z)),
+    };
+    let cells = surface.resolution.clamp(1, 254);
+    let step = (
+        (max.0 - min.0) / cells as f32,
+        (max.1 - min.1) / cells as f32,
+    );
+    let base = water.base_height();
+    let amplitude = water.wave_amplitude.max(1e-6);
+
+    let mut vertices = Vec::with_capacity(((cells + 1) * (cells + 1)) as usize);
+    for row in 0..=cells {
+        for col in 0..=cells {
+            let x = min.0 + col as f32 * step.0;
+            let z = min.1 + row as f32 * step.1;
+            let y = water.surface_height(x, z, time);
+            // Lighter crests, darker troughs
+            let shade = 0.85 + 0.3 * ((y - base) / amplitude).clamp(-1.0, 1.0);
+            // Normal from central differences of the wave height
+            let e = 0.05;
+            let dx = water.surface_height(x + e, z, time) - water.surface_height(x - e, z, time);
+            let dz = water.surface_height(x, z + e, time) - water.surface_height(x, z - e, time);
+            let normal = Vector3::new(-dx, 2.0 * e, -dz).normalize();
+            vertices.push(
+                Vertex::new([x, y, z], surface.color.map(|c| (c * shade).min(1.0)))
+                    .with_normal(normal.into()),
+            );
+        }
+    }
+
+    let width = cells + 1;
+    let mut indices = Vec::with_capacity((cells * cells * 6) as usize);
+    for row in 0..cells {
+        for col in 0..cells {
+            let i = (row * width + col) as u16;
+            let w = width as u16;
+            indices.extend_from_slice(&[i, i + w, i + 1, i + 1, i + w, i + w + 1]);
+        }
+    }
+
+    (vertices, indices)
+}
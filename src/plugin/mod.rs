@@ -0,0 +1,44 @@
+//! Plugins bundle systems and resources into reusable features
+//!
+//! ```rust,no_run
+//! use qsi::prelude::*;
+//!
+//! struct Gravity(f32);
+//!
+//! struct PhysicsPlugin;
+//!
+//! impl Plugin for PhysicsPlugin {
+//!     fn build(&self, app: &mut App) {
+//!         app.register_resource(Gravity(-9.81))
+//!             .register_system(step_physics.label("physics"));
+//!     }
+//! }
+//!
+//! fn step_physics(world: &mut World, _input: &InputState, time: &TimeState) {
+//!     // ...
+//! }
+//!
+//! App::new().add_plugin(PhysicsPlugin).run().unwrap();
+//! ```
+
+use crate::App;
+
+/// A reusable bundle of systems, resources and configuration
+pub trait Plugin: 'static {
+    /// Register this plugin's systems and resources on the app
+    fn build(&self, app: &mut App);
+
+    /// Name used to detect duplicate registrations (defaults to the type name)
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+impl<F> Plugin for F
+where
+    F: Fn(&mut App) + 'static,
+{
+    fn build(&self, app: &mut App) {
+        self(app)
+    }
+}
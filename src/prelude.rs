@@ -6,10 +6,18 @@
 //! ```
 
 // Core app
-pub use crate::App;
+pub use crate::plugin::Plugin;
+pub use crate::{App, AppExit};
 
 // ECS
-pub use crate::ecs::{Component, EntityBuilder, EntityId, World};
+pub use crate::ecs::{
+    Bundle, Component, EntityBuilder, EntityId, EntityRef, Event, EventReader, EventWriter,
+    Metadata, Name, Prefab, Query, Res, ResMut, With, Without, World,
+};
+
+// Scheduling
+pub use crate::schedule::IntoSystemDescriptor;
+pub use crate::state::State;
 
 // Input
 pub use crate::input::InputState;
@@ -24,8 +32,14 @@ pub use crate::time::TimeState;
 pub use crate::graphics::Renderer;
 
 // Components
-pub use crate::camera::Camera;
-pub use crate::graphics::Mesh;
+pub use crate::assets::{Assets, Handle};
+pub use crate::camera::{AutoOrbit, Camera, CameraPresets};
+pub use crate::graphics::{
+    Color, DebugDraw, DirectionalLight, Instance, Instances, Material, Mesh, PointLight, SpotLight,
+    Sprite, Visibility,
+};
+#[cfg(feature = "text")]
+pub use crate::graphics::{Text, Text3d};
 
 // Common cgmath types
 pub use cgmath::{Deg, Rad};
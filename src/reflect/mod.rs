@@ -0,0 +1,569 @@
+//! Runtime reflection for components
+//!
+//! Components implementing `Reflect` expose their fields by name. Registering
+//! them in a `TypeRegistry` (available as a world resource) lets tools such as
+//! inspectors, consoles, and scripts create and edit components from strings.
+//! With the `serde` feature the registry also lists the components saved in
+//! scene files (see `TypeRegistry::register_saved`), which `SceneRegistry` is
+//! built from.
+//!
+//! ```rust,no_run
+//! use qsi::prelude::*;
+//! use qsi::reflect::{FieldValue, TypeRegistry};
+//!
+//! #[derive(Clone, Default)]
+//! struct Health {
+//!     current: f32,
+//!     max: f32,
+//! }
+//!
+//! impl qsi::ecs::Component for Health {}
+//! qsi::impl_reflect!(Health { current, max });
+//!
+//! fn setup(world: &mut World, _renderer: &mut Renderer) {
+//!     let entity = world.spawn().build();
+//!     let mut registry = world.remove_resource::<TypeRegistry>().unwrap();
+//!     registry.register::<Health>("Health");
+//!     registry.insert_default(world, entity, "Health").unwrap();
+//!     registry
+//!         .set_field(world, entity, "Health", "max", FieldValue::Float(100.0))
+//!         .unwrap();
+//!     world.insert_resource(registry);
+//! }
+//! ```
+
+use crate::assets::Handle;
+use crate::camera::Camera;
+use crate::ecs::{Component, EntityId, Metadata, Name, World};
+use crate::graphics::{Mesh, MeshSource};
+use crate::ik::IkChain;
+use crate::math::{Transform, Vector3, Velocity};
+use crate::physics::{Joint, RigidBody, WaterSurface, WaterVolume};
+use anyhow::{Result, anyhow, bail};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A dynamically typed field value
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Bool(bool),
+    Int(i64),
+    Float(f32),
+    String(String),
+    Vec3([f32; 3]),
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Bool(v) => write!(f, "{}", v),
+            FieldValue::Int(v) => write!(f, "{}", v),
+            FieldValue::Float(v) => write!(f, "{}", v),
+            FieldValue::String(v) => write!(f, "{:?}", v),
+            FieldValue::Vec3([x, y, z]) => write!(f, "({}, {}, {})", x, y, z),
+        }
+    }
+}
+
+impl From<bool> for FieldValue {
+    fn from(value: bool) -> Self {
+        FieldValue::Bool(value)
+    }
+}
+
+impl From<i32> for FieldValue {
+    fn from(value: i32) -> Self {
+        FieldValue::Int(value.into())
+    }
+}
+
+impl From<u32> for FieldValue {
+    fn from(value: u32) -> Self {
+        FieldValue::Int(value.into())
+    }
+}
+
+impl From<f32> for FieldValue {
+    fn from(value: f32) -> Self {
+        FieldValue::Float(value)
+    }
+}
+
+impl From<String> for FieldValue {
+    fn from(value: String) -> Self {
+        FieldValue::String(value)
+    }
+}
+
+impl From<[f32; 3]> for FieldValue {
+    fn from(value: [f32; 3]) -> Self {
+        FieldValue::Vec3(value)
+    }
+}
+
+impl From<Vector3<f32>> for FieldValue {
+    fn from(value: Vector3<f32>) -> Self {
+        FieldValue::Vec3(value.into())
+    }
+}
+
+impl TryFrom<FieldValue> for bool {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FieldValue) -> Result<Self> {
+        match value {
+            FieldValue::Bool(v) => Ok(v),
+            other => bail!("Expected a bool, got {}", other),
+        }
+    }
+}
+
+impl TryFrom<FieldValue> for i32 {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FieldValue) -> Result<Self> {
+        match value {
+            FieldValue::Int(v) => Ok(i32::try_from(v)?),
+            other => bail!("Expected an integer, got {}", other),
+        }
+    }
+}
+
+impl TryFrom<FieldValue> for u32 {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FieldValue) -> Result<Self> {
+        match value {
+            FieldValue::Int(v) => Ok(u32::try_from(v)?),
+            other => bail!("Expected an integer, got {}", other),
+        }
+    }
+}
+
+impl TryFrom<FieldValue> for f32 {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FieldValue) -> Result<Self> {
+        match value {
+            FieldValue::Float(v) => Ok(v),
+            FieldValue::Int(v) => Ok(v as f32),
+            other => bail!("Expected a number, got {}", other),
+        }
+    }
+}
+
+impl TryFrom<FieldValue> for String {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FieldValue) -> Result<Self> {
+        match value {
+            FieldValue::String(v) => Ok(v),
+            other => bail!("Expected a string, got {}", other),
+        }
+    }
+}
+
+impl TryFrom<FieldValue> for [f32; 3] {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FieldValue) -> Result<Self> {
+        match value {
+            FieldValue::Vec3(v) => Ok(v),
+            other => bail!("Expected a vector, got {}", other),
+        }
+    }
+}
+
+impl TryFrom<FieldValue> for Vector3<f32> {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FieldValue) -> Result<Self> {
+        <[f32; 3]>::try_from(value).map(Into::into)
+    }
+}
+
+/// A component whose fields can be read and written by name
+pub trait Reflect: Component {
+    /// Names of the reflected fields, in declaration order
+    fn field_names() -> &'static [&'static str]
+    where
+        Self: Sized;
+
+    /// Read a field, or `None` if there is no such field
+    fn field(&self, name: &str) -> Option<FieldValue>;
+
+    /// Write a field, failing on unknown names or mismatched types
+    fn set_field(&mut self, name: &str, value: FieldValue) -> Result<()>;
+}
+
+/// Implement `Reflect` for a struct from a list of its fields
+///
+/// Each field type must convert to and from `FieldValue`.
+#[macro_export]
+macro_rules! impl_reflect {
+    ($ty:ty { $($field:ident),* $(,)? }) => {
+        impl $crate::reflect::Reflect for $ty {
+            fn field_names() -> &'static [&'static str] {
+                &[$(stringify!($field)),*]
+            }
+
+            fn field(&self, name: &str) -> Option<$crate::reflect::FieldValue> {
+                match name {
+                    $(stringify!($field) => Some(self.$field.clone().into()),)*
+                    _ => None,
+                }
+            }
+
+            fn set_field(
+                &mut self,
+                name: &str,
+                value: $crate::reflect::FieldValue,
+            ) -> anyhow::Result<()> {
+                match name {
+                    $(stringify!($field) => {
+                        self.$field = value.try_into()?;
+                        Ok(())
+                    })*
+                    _ => anyhow::bail!("{} has no field `{}`", stringify!($ty), name),
+                }
+            }
+        }
+    };
+}
+
+impl_reflect!(Transform {
+    position,
+    rotation,
+    scale
+});
+impl_reflect!(Velocity { linear, angular });
+impl_reflect!(Camera {
+    is_active,
+    fov,
+    near,
+    far
+});
+impl_reflect!(RigidBody {
+    mass,
+    gravity_scale,
+    linear_damping,
+    radius
+});
+
+/// Every key is a string field; setting a new key adds it
+impl Reflect for Metadata {
+    fn field_names() -> &'static [&'static str] {
+        &[]
+    }
+
+    fn field(&self, name: &str) -> Option<FieldValue> {
+        self.get(name)
+            .map(|value| FieldValue::String(value.to_string()))
+    }
+
+    fn set_field(&mut self, name: &str, value: FieldValue) -> Result<()> {
+        let value = match value {
+            FieldValue::String(value) => value,
+            other => other.to_string(),
+        };
+        self.insert(name, value);
+        Ok(())
+    }
+}
+
+type InsertFn = Box<dyn Fn(&mut World, EntityId) + Send + Sync>;
+type CloneFn = fn(&mut World, EntityId, EntityId);
+#[cfg(feature = "serde")]
+pub(crate) type SaveFn = fn(&World, EntityId) -> Option<Result<serde_json::Value>>;
+#[cfg(feature = "serde")]
+pub(crate) type LoadFn = fn(&mut World, EntityId, serde_json::Value) -> Result<()>;
+
+/// How to save and load one component in scene files
+#[cfg(feature = "serde")]
+pub(crate) struct SavedType {
+    pub(crate) name: String,
+    pub(crate) save: SaveFn,
+    pub(crate) load: LoadFn,
+}
+
+/// Type-erased operations for one registered component
+pub struct TypeRegistration {
+    name: String,
+    type_id: TypeId,
+    type_name: &'static str,
+    field_names: &'static [&'static str],
+    insert_default: InsertFn,
+    has: fn(&World, EntityId) -> bool,
+    remove: fn(&mut World, EntityId),
+    get_field: fn(&World, EntityId, &str) -> Option<FieldValue>,
+    set_field: fn(&mut World, EntityId, &str, FieldValue) -> Result<()>,
+}
+
+impl TypeRegistration {
+    /// Registered name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Rust type name
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// Reflected field names
+    pub fn field_names(&self) -> &'static [&'static str] {
+        self.field_names
+    }
+}
+
+/// Registry of reflectable components, looked up by name
+///
+/// Also records how to clone components for `World::clone_entity`, including
+/// cloneable components that are not reflected, and (with the `serde`
+/// feature) which components scene files save.
+pub struct TypeRegistry {
+    registrations: Vec<TypeRegistration>,
+    cloners: HashMap<TypeId, CloneFn>,
+    #[cfg(feature = "serde")]
+    saved: Vec<SavedType>,
+}
+
+impl TypeRegistry {
+    /// Create a registry with the built-in reflectable components
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        registry.register::<Transform>("Transform");
+        registry.register::<Velocity>("Velocity");
+        registry.register::<Camera>("Camera");
+        registry.register_with::<RigidBody>("RigidBody", || RigidBody::dynamic(1.0));
+        registry.register::<Metadata>("Metadata");
+        registry
+            .register_clone::<Name>()
+            .register_clone::<Mesh>()
+            .register_clone::<Handle<Mesh>>()
+            .register_clone::<MeshSource>()
+            .register_clone::<Joint>()
+            .register_clone::<IkChain>()
+            .register_clone::<WaterVolume>()
+            .register_clone::<WaterSurface>();
+        #[cfg(feature = "serde")]
+        {
+            use crate::graphics::{DirectionalLight, Instances, Material, PointLight, SpotLight};
+            registry
+                .register_saved::<Name>("Name")
+                .register_saved::<Metadata>("Metadata")
+                .register_saved::<Transform>("Transform")
+                .register_saved::<Velocity>("Velocity")
+                .register_saved::<Camera>("Camera")
+                .register_saved::<RigidBody>("RigidBody")
+                .register_saved::<MeshSource>("MeshSource")
+                .register_saved::<Material>("Material")
+                .register_saved::<Instances>("Instances")
+                .register_saved::<DirectionalLight>("DirectionalLight")
+                .register_saved::<PointLight>("PointLight")
+                .register_saved::<SpotLight>("SpotLight");
+        }
+        registry
+    }
+
+    /// Create a registry with no components
+    pub fn empty() -> Self {
+        Self {
+            registrations: Vec::new(),
+            cloners: HashMap::new(),
+            #[cfg(feature = "serde")]
+            saved: Vec::new(),
+        }
+    }
+
+    /// Register a component using its `Default` value as the constructor
+    pub fn register<T: Reflect + Clone + Default>(&mut self, name: impl Into<String>) -> &mut Self {
+        self.register_with::<T>(name, T::default)
+    }
+
+    /// Register a component with a custom constructor
+    pub fn register_with<T: Reflect + Clone>(
+        &mut self,
+        name: impl Into<String>,
+        constructor: impl Fn() -> T + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.register_clone::<T>();
+        let name = name.into();
+        self.registrations.retain(|r| r.name != name);
+        self.registrations.push(TypeRegistration {
+            name,
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+            field_names: T::field_names(),
+            insert_default: Box::new(move |world, entity| {
+                world.add_component(entity, constructor())
+            }),
+            has: |world, entity| world.has_component::<T>(entity),
+            remove: |world, entity| {
+                world.remove_component::<T>(entity);
+            },
+            get_field: |world, entity, field| world.get_component::<T>(entity)?.field(field),
+            set_field: |world, entity, field, value| {
+                world
+                    .get_component_mut::<T>(entity)
+                    .ok_or_else(|| anyhow!("Entity has no {}", std::any::type_name::<T>()))?
+                    .set_field(field, value)
+            },
+        });
+        self
+    }
+
+    /// Allow `World::clone_entity` to copy a component without reflecting it
+    pub fn register_clone<T: Component + Clone>(&mut self) -> &mut Self {
+        self.cloners
+            .insert(TypeId::of::<T>(), |world, source, target| {
+                if let Some(component) = world.get_component::<T>(source).cloned() {
+                    world.add_component(target, component);
+                }
+            });
+        self
+    }
+
+    /// Save and load a component in scene files under `name`, replacing any
+    /// previous component saved under it
+    #[cfg(feature = "serde")]
+    pub fn register_saved<T>(&mut self, name: impl Into<String>) -> &mut Self
+    where
+        T: Component + serde::Serialize + serde::de::DeserializeOwned,
+    {
+        let name = name.into();
+        self.saved.retain(|saved| saved.name != name);
+        self.saved.push(SavedType {
+            name,
+            save: save_component::<T>,
+            load: load_component::<T>,
+        });
+        self
+    }
+
+    /// Names of the components saved in scene files
+    #[cfg(feature = "serde")]
+    pub fn saved_names(&self) -> impl Iterator<Item = &str> {
+        self.saved.iter().map(|saved| saved.name.as_str())
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn saved_types(&self) -> &[SavedType] {
+        &self.saved
+    }
+
+    /// Copy every cloneable component from `source` to `target`
+    pub fn clone_components(&self, world: &mut World, source: EntityId, target: EntityId) {
+        for type_id in world.component_types(source) {
+            match self.cloners.get(&type_id) {
+                Some(clone) => clone(world, source, target),
+                None => log::debug!("Skipping uncloneable component {:?}", type_id),
+            }
+        }
+    }
+
+    /// Look up a registration by name
+    pub fn get(&self, name: &str) -> Option<&TypeRegistration> {
+        self.registrations.iter().find(|r| r.name == name)
+    }
+
+    /// Look up a registration by Rust type
+    pub fn get_by_type<T: 'static>(&self) -> Option<&TypeRegistration> {
+        self.registrations
+            .iter()
+            .find(|r| r.type_id == TypeId::of::<T>())
+    }
+
+    /// Registered name of a component type, if it has one
+    pub fn name_of(&self, type_id: TypeId) -> Option<&str> {
+        self.registrations
+            .iter()
+            .find(|r| r.type_id == type_id)
+            .map(|r| r.name.as_str())
+    }
+
+    /// All registrations, in registration order
+    pub fn iter(&self) -> impl Iterator<Item = &TypeRegistration> {
+        self.registrations.iter()
+    }
+
+    /// Names of the registered components present on `entity`
+    pub fn components_of(&self, world: &World, entity: EntityId) -> Vec<&str> {
+        self.registrations
+            .iter()
+            .filter(|r| (r.has)(world, entity))
+            .map(|r| r.name.as_str())
+            .collect()
+    }
+
+    /// Add the named component with its default value, replacing any existing one
+    pub fn insert_default(&self, world: &mut World, entity: EntityId, name: &str) -> Result<()> {
+        (self.lookup(name)?.insert_default)(world, entity);
+        Ok(())
+    }
+
+    /// Remove the named component from `entity`
+    pub fn remove(&self, world: &mut World, entity: EntityId, name: &str) -> Result<()> {
+        (self.lookup(name)?.remove)(world, entity);
+        Ok(())
+    }
+
+    /// Read one field of the named component
+    pub fn get_field(
+        &self,
+        world: &World,
+        entity: EntityId,
+        component: &str,
+        field: &str,
+    ) -> Result<Option<FieldValue>> {
+        Ok((self.lookup(component)?.get_field)(world, entity, field))
+    }
+
+    /// Write one field of the named component
+    pub fn set_field(
+        &self,
+        world: &mut World,
+        entity: EntityId,
+        component: &str,
+        field: &str,
+        value: FieldValue,
+    ) -> Result<()> {
+        (self.lookup(component)?.set_field)(world, entity, field, value)
+    }
+
+    fn lookup(&self, name: &str) -> Result<&TypeRegistration> {
+        self.get(name)
+            .ok_or_else(|| anyhow!("Unknown component `{}`", name))
+    }
+}
+
+impl Default for TypeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "serde")]
+pub(crate) fn save_component<T: Component + serde::Serialize>(
+    world: &World,
+    entity: EntityId,
+) -> Option<Result<serde_json::Value>> {
+    let component = world.get_component::<T>(entity)?;
+    Some(serde_json::to_value(component).map_err(Into::into))
+}
+
+#[cfg(feature = "serde")]
+pub(crate) fn load_component<T: Component + serde::de::DeserializeOwned>(
+    world: &mut World,
+    entity: EntityId,
+    value: serde_json::Value,
+) -> Result<()> {
+    let component: T = serde_json::from_value(value)?;
+    world.add_component(entity, component);
+    Ok(())
+}
@@ -0,0 +1,350 @@
+//! On-screen timeline widget for `Replay`
+//!
+//! A bar along the bottom of the window with step back, play/pause, and step
+//! forward buttons, a track showing the cursor and markers, and (with the
+//! `text` feature) a time readout. Clicking the buttons controls playback and
+//! dragging along the track scrubs.
+
+use super::{Replay, ReplayMode};
+use crate::ecs::{EntityId, World};
+use crate::graphics::{Renderer, Sprite, Texture};
+use crate::input::InputState;
+use crate::time::TimeState;
+use winit::event::MouseButton;
+
+/// Layer the widget is drawn on, above ordinary sprites
+const HUD_LAYER: i32 = 1000;
+/// Side of one icon in the icon sheet, in pixels
+const ICON_SIZE: u32 = 16;
+
+/// Icons in the sheet, left to right
+#[derive(Debug, Clone, Copy)]
+enum Icon {
+    Play,
+    Pause,
+    StepBack,
+    StepForward,
+}
+
+const ICONS: [Icon; 4] = [Icon::Play, Icon::Pause, Icon::StepBack, Icon::StepForward];
+
+/// Pixel rectangle as min x, min y, max x, max y
+type Rect = [f32; 4];
+
+/// Where the widget was last drawn, for hit testing clicks
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    step_back: Rect,
+    toggle: Rect,
+    step_forward: Rect,
+    track: Rect,
+    /// Clickable area around the track, taller than the track itself
+    track_hit: Rect,
+}
+
+/// Widget state kept in the `Replay` resource
+#[derive(Debug, Default)]
+pub(super) struct ReplayHud {
+    layout: Option<Layout>,
+    sprites: Vec<EntityId>,
+    #[cfg(feature = "text")]
+    label: Option<EntityId>,
+    icons: Option<Texture>,
+    dragging: bool,
+}
+
+/// Apply clicks and drags on the widget drawn last frame
+pub(super) fn handle_mouse(replay: &mut Replay, input: &InputState) {
+    let Some(layout) = replay.hud.layout.filter(|_| replay.show_hud) else {
+        replay.hud.dragging = false;
+        return;
+    };
+    let (x, y) = input.cursor_position_physical();
+    let cursor = (x as f32, y as f32);
+
+    if input.mouse_button_just_pressed(MouseButton::Left) {
+        if contains(layout.toggle, cursor) {
+            replay.toggle_play();
+        } else if contains(layout.step_back, cursor) {
+            replay.step(-1);
+        } else if contains(layout.step_forward, cursor) {
+            replay.step(1);
+        } else if contains(layout.track_hit, cursor) {
+            replay.hud.dragging = true;
+        }
+    }
+    if !input.mouse_button_pressed(MouseButton::Left) {
+        replay.hud.dragging = false;
+    }
+    if replay.hud.dragging {
+        let [min_x, _, max_x, _] = layout.track;
+        replay.scrub((cursor.0 - min_x) / (max_x - min_x).max(1.0));
+    }
+}
+
+/// Draw the timeline widget along the bottom of the window
+///
+/// Set `Replay::show_hud` to `false` to hide it; the keyboard controls keep
+/// working either way.
+pub fn replay_hud_system(world: &mut World, renderer: &mut Renderer, _time: &TimeState) {
+    let Some(replay) = world.resource_mut::<Replay>() else {
+        return;
+    };
+    let mut hud = std::mem::take(&mut replay.hud);
+
+    let sprites = if replay.show_hud {
+        if hud.icons.is_none() {
+            hud.icons = icon_sheet(renderer);
+        }
+        let scale = renderer
+            .window
+            .as_ref()
+            .map_or(1.0, |window| window.scale_factor() as f32);
+        let layout = layout(renderer.size(), scale);
+        hud.layout = Some(layout);
+        widget_sprites(replay, &layout, scale, hud.icons.as_ref())
+    } else {
+        hud.layout = None;
+        Vec::new()
+    };
+    #[cfg(feature = "text")]
+    let label = hud
+        .layout
+        .map(|layout| time_label(replay, &layout, renderer.size().1));
+
+    sync_sprites(world, &mut hud.sprites, sprites);
+    #[cfg(feature = "text")]
+    sync_label(world, &mut hud.label, label);
+
+    if let Some(replay) = world.resource_mut::<Replay>() {
+        replay.hud = hud;
+    }
+}
+
+/// Place the buttons and track along the bottom of a `size` window
+fn layout((width, height): (u32, u32), scale: f32) -> Layout {
+    let margin = 16.0 * scale;
+    let button = 28.0 * scale;
+    let gap = 6.0 * scale;
+    let track_height = 6.0 * scale;
+    let center_y = height as f32 - margin - button * 0.5;
+
+    let button_at = |index: usize| {
+        let min_x = margin + index as f32 * (button + gap);
+        [
+            min_x,
+            center_y - button * 0.5,
+            min_x + button,
+            center_y + button * 0.5,
+        ]
+    };
+    let track_min_x = margin + 3.0 * (button + gap) + gap;
+    let track_max_x = (width as f32 - margin).max(track_min_x + 1.0);
+    Layout {
+        step_back: button_at(0),
+        toggle: button_at(1),
+        step_forward: button_at(2),
+        track: [
+            track_min_x,
+            center_y - track_height * 0.5,
+            track_max_x,
+            center_y + track_height * 0.5,
+        ],
+        track_hit: [
+            track_min_x,
+            center_y - button * 0.5,
+            track_max_x,
+            center_y + button * 0.5,
+        ],
+    }
+}
+
+/// Sprites making up the widget, back to front
+fn widget_sprites(
+    replay: &Replay,
+    layout: &Layout,
+    scale: f32,
+    icons: Option<&Texture>,
+) -> Vec<Sprite> {
+    let padding = 8.0 * scale;
+    let panel = [
+        layout.step_back[0] - padding,
+        layout.step_back[1] - padding,
+        layout.track[2] + padding,
+        layout.step_back[3] + padding,
+    ];
+    let button_color = [1.0, 1.0, 1.0, 0.15];
+    let mut sprites = vec![rect_sprite(panel, [0.0, 0.0, 0.0, 0.55], HUD_LAYER)];
+
+    let toggle_icon = match replay.mode() {
+        ReplayMode::Playing => Icon::Pause,
+        ReplayMode::Live | ReplayMode::Paused => Icon::Play,
+    };
+    for (rect, icon) in [
+        (layout.step_back, Icon::StepBack),
+        (layout.toggle, toggle_icon),
+        (layout.step_forward, Icon::StepForward),
+    ] {
+        sprites.push(rect_sprite(rect, button_color, HUD_LAYER + 1));
+        if let Some(icons) = icons {
+            let u = icon as usize as f32 / ICONS.len() as f32;
+            sprites.push(
+                rect_sprite(rect, [1.0, 1.0, 1.0, 1.0], HUD_LAYER + 2)
+                    .with_texture(icons.clone())
+                    .with_region([u, 0.0], [u + 1.0 / ICONS.len() as f32, 1.0]),
+            );
+        }
+    }
+
+    let [min_x, min_y, max_x, max_y] = layout.track;
+    let head_x = min_x + replay.progress() * (max_x - min_x);
+    let fill_color = if replay.is_live() {
+        [0.9, 0.2, 0.2, 1.0]
+    } else {
+        [0.3, 0.6, 1.0, 1.0]
+    };
+    sprites.push(rect_sprite(
+        layout.track,
+        [1.0, 1.0, 1.0, 0.25],
+        HUD_LAYER + 1,
+    ));
+    sprites.push(rect_sprite(
+        [min_x, min_y, head_x, max_y],
+        fill_color,
+        HUD_LAYER + 2,
+    ));
+
+    let center_y = (min_y + max_y) * 0.5;
+    let start = replay.frames.front().map_or(0.0, |frame| frame.time);
+    let end = replay.frames.back().map_or(0.0, |frame| frame.time);
+    if end > start {
+        for (time, _) in replay.markers() {
+            let x = min_x + (time - start) / (end - start) * (max_x - min_x);
+            sprites.push(rect_sprite(
+                centered([x, center_y], [2.0 * scale, 14.0 * scale]),
+                [1.0, 0.8, 0.2, 1.0],
+                HUD_LAYER + 3,
+            ));
+        }
+    }
+    sprites.push(rect_sprite(
+        centered([head_x, center_y], [4.0 * scale, 20.0 * scale]),
+        [1.0, 1.0, 1.0, 1.0],
+        HUD_LAYER + 4,
+    ));
+    sprites
+}
+
+/// Elapsed and total recorded time, right-aligned above the track
+#[cfg(feature = "text")]
+fn time_label(replay: &Replay, layout: &Layout, height: u32) -> crate::graphics::Text {
+    use crate::graphics::{Text, TextAlign};
+
+    let start = replay.frames.front().map_or(0.0, |frame| frame.time);
+    let end = replay.frames.back().map_or(0.0, |frame| frame.time);
+    let now = replay.current_frame().map_or(0.0, |frame| frame.time);
+    let size = (layout.track_hit[3] - layout.track_hit[1]) * 0.5;
+    let top = (layout.track_hit[1] - size * 1.6).clamp(0.0, height as f32);
+    Text::new(
+        format!("{:.1}s / {:.1}s", now - start, end - start),
+        [layout.track[2], top],
+    )
+    .with_size(size)
+    .with_align(TextAlign::Right)
+    .with_layer(HUD_LAYER + 5)
+}
+
+/// Give the widget's entities the new sprites, spawning or despawning as the
+/// count changes
+fn sync_sprites(world: &mut World, entities: &mut Vec<EntityId>, sprites: Vec<Sprite>) {
+    // Something else (such as a scene load) may have despawned them
+    entities.retain(|&entity| world.is_alive(entity));
+    for entity in entities.drain(sprites.len().min(entities.len())..) {
+        world.despawn(entity);
+    }
+    for (index, sprite) in sprites.into_iter().enumerate() {
+        match entities.get(index) {
+            Some(&entity) => world.add_component(entity, sprite),
+            None => entities.push(world.spawn().with(sprite).build()),
+        }
+    }
+}
+
+#[cfg(feature = "text")]
+fn sync_label(
+    world: &mut World,
+    entity: &mut Option<EntityId>,
+    label: Option<crate::graphics::Text>,
+) {
+    let alive = entity.filter(|&entity| world.is_alive(entity));
+    match (alive, label) {
+        (Some(existing), Some(label)) => world.add_component(existing, label),
+        (None, Some(label)) => *entity = Some(world.spawn().with(label).build()),
+        (Some(existing), None) => {
+            world.despawn(existing);
+            *entity = None;
+        }
+        (None, None) => *entity = None,
+    }
+}
+
+fn rect_sprite(rect: Rect, color: [f32; 4], layer: i32) -> Sprite {
+    let size = [rect[2] - rect[0], rect[3] - rect[1]];
+    Sprite::screen([rect[0] + size[0] * 0.5, rect[1] + size[1] * 0.5], size)
+        .with_color(color)
+        .with_layer(layer)
+}
+
+fn centered(center: [f32; 2], size: [f32; 2]) -> Rect {
+    [
+        center[0] - size[0] * 0.5,
+        center[1] - size[1] * 0.5,
+        center[0] + size[0] * 0.5,
+        center[1] + size[1] * 0.5,
+    ]
+}
+
+fn contains(rect: Rect, (x, y): (f32, f32)) -> bool {
+    x >= rect[0] && x <= rect[2] && y >= rect[1] && y <= rect[3]
+}
+
+/// Texture holding the button icons side by side, white on transparent
+fn icon_sheet(renderer: &Renderer) -> Option<Texture> {
+    let width = ICON_SIZE * ICONS.len() as u32;
+    let mut pixels = vec![0u8; (width * ICON_SIZE * 4) as usize];
+    for (index, &icon) in ICONS.iter().enumerate() {
+        for y in 0..ICON_SIZE {
+            for x in 0..ICON_SIZE {
+                if icon_covers(icon, x as f32 + 0.5, y as f32 + 0.5) {
+                    let offset = ((y * width + index as u32 * ICON_SIZE + x) * 4) as usize;
+                    pixels[offset..offset + 4].copy_from_slice(&[255; 4]);
+                }
+            }
+        }
+    }
+    Texture::from_rgba8(
+        renderer.device(),
+        renderer.queue(),
+        width,
+        ICON_SIZE,
+        &pixels,
+    )
+    .inspect_err(|e| log::warn!("Replay HUD icons unavailable: {e:#}"))
+    .ok()
+}
+
+/// Whether the pixel centred at `(x, y)` of a 16-pixel icon is filled
+fn icon_covers(icon: Icon, x: f32, y: f32) -> bool {
+    // Triangle spanning `from..to` horizontally with its point at `to`
+    let triangle = |from: f32, to: f32| {
+        let along = (x - from) / (to - from);
+        (0.0..=1.0).contains(&along) && (y - 8.0).abs() <= (1.0 - along) * 5.0
+    };
+    let bar = |from: f32, to: f32| (from..to).contains(&x) && (3.0..13.0).contains(&y);
+    match icon {
+        Icon::Play => triangle(4.0, 13.0),
+        Icon::Pause => bar(4.0, 7.0) || bar(9.0, 12.0),
+        Icon::StepBack => bar(3.0, 5.0) || triangle(13.0, 5.0),
+        Icon::StepForward => triangle(3.0, 11.0) || bar(11.0, 13.0),
+    }
+}
@@ -0,0 +1,407 @@
+//! Replay recording with a timeline for reviewing simulations
+//!
+//! `replay_system` records every entity's `Transform` each frame while live.
+//! Pausing switches to review mode: the timeline can then be played, paused,
+//! scrubbed, and stepped, and the recorded transforms are written back into the
+//! world. Systems that would fight the playback (physics, AI) can be gated with
+//! `.run_if(replay_is_live)`. `ReplayPlugin` also draws a clickable timeline
+//! widget along the bottom of the window (see `replay_hud_system`).
+//!
+//! ```no_run
+//! use qsi::prelude::*;
+//! use qsi::replay::{ReplayPlugin, replay_is_live};
+//!
+//! # fn simulate(_: &mut World, _: &InputState, _: &TimeState) {}
+//! App::new()
+//!     .add_plugin(ReplayPlugin)
+//!     .add_system(simulate.run_if(replay_is_live));
+//! ```
+
+mod hud;
+
+pub use hud::replay_hud_system;
+
+use crate::App;
+use crate::ecs::{EntityId, World};
+use crate::input::{ActionMap, InputState};
+use crate::math::Transform;
+use crate::time::TimeState;
+use std::collections::VecDeque;
+use winit::keyboard::KeyCode;
+
+/// Transforms of all entities at one recorded frame
+#[derive(Debug, Clone)]
+pub struct ReplayFrame {
+    /// Seconds since the app started
+    pub time: f32,
+    pub transforms: Vec<(EntityId, Transform)>,
+}
+
+/// Whether the world is running live or being reviewed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// Recording new frames
+    Live,
+    /// Playing back recorded frames
+    Playing,
+    /// Holding on one recorded frame
+    Paused,
+}
+
+/// Recorded frames and the playback cursor
+///
+/// Inserted with default settings by `replay_system` when absent.
+#[derive(Debug)]
+pub struct Replay {
+    frames: VecDeque<ReplayFrame>,
+    /// Oldest frames are dropped beyond this count
+    pub max_frames: usize,
+    /// Playback speed multiplier
+    pub speed: f32,
+    mode: ReplayMode,
+    cursor: usize,
+    playback_time: f32,
+    markers: Vec<(f32, String)>,
+    /// Draw the timeline widget
+    pub show_hud: bool,
+    hud: hud::ReplayHud,
+}
+
+impl Default for Replay {
+    fn default() -> Self {
+        Self::new(60 * 60)
+    }
+}
+
+impl Replay {
+    /// Create a replay buffer keeping at most `max_frames` frames
+    pub fn new(max_frames: usize) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            max_frames,
+            speed: 1.0,
+            mode: ReplayMode::Live,
+            cursor: 0,
+            playback_time: 0.0,
+            markers: Vec::new(),
+            show_hud: true,
+            hud: hud::ReplayHud::default(),
+        }
+    }
+
+    /// Current mode
+    pub fn mode(&self) -> ReplayMode {
+        self.mode
+    }
+
+    /// True while recording
+    pub fn is_live(&self) -> bool {
+        self.mode == ReplayMode::Live
+    }
+
+    /// Number of recorded frames
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// True if nothing has been recorded
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Index of the frame being reviewed
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Frame at the cursor
+    pub fn current_frame(&self) -> Option<&ReplayFrame> {
+        self.frames.get(self.cursor)
+    }
+
+    /// Recorded frame by index
+    pub fn frame(&self, index: usize) -> Option<&ReplayFrame> {
+        self.frames.get(index)
+    }
+
+    /// Position of the cursor along the timeline, in `0.0..=1.0`
+    pub fn progress(&self) -> f32 {
+        match self.frames.len() {
+            0 | 1 => 1.0,
+            len => self.cursor as f32 / (len - 1) as f32,
+        }
+    }
+
+    /// Record a frame (ignored unless live)
+    pub fn record(&mut self, frame: ReplayFrame) {
+        if !self.is_live() {
+            return;
+        }
+        self.frames.push_back(frame);
+        while self.frames.len() > self.max_frames.max(1) {
+            self.frames.pop_front();
+        }
+        self.cursor = self.frames.len().saturating_sub(1);
+    }
+
+    /// Start playing from the cursor; from live this starts at the first frame
+    pub fn play(&mut self) {
+        if self.frames.is_empty() {
+            return;
+        }
+        if self.is_live() || self.cursor + 1 >= self.frames.len() {
+            self.cursor = 0;
+        }
+        self.mode = ReplayMode::Playing;
+        self.playback_time = 0.0;
+    }
+
+    /// Hold on the current frame, entering review mode if live
+    pub fn pause(&mut self) {
+        if !self.frames.is_empty() {
+            self.mode = ReplayMode::Paused;
+        }
+    }
+
+    /// Toggle between playing and paused
+    pub fn toggle_play(&mut self) {
+        match self.mode {
+            ReplayMode::Playing => self.pause(),
+            ReplayMode::Paused => self.play(),
+            ReplayMode::Live => self.pause(),
+        }
+    }
+
+    /// Pause and move the cursor by `frames` (negative steps back)
+    pub fn step(&mut self, frames: isize) {
+        self.pause();
+        self.seek(self.cursor.saturating_add_signed(frames));
+    }
+
+    /// Move the cursor to a frame index, clamped to the recording
+    pub fn seek(&mut self, frame: usize) {
+        self.cursor = frame.min(self.frames.len().saturating_sub(1));
+        self.playback_time = 0.0;
+    }
+
+    /// Move the cursor to a fraction of the timeline (`0.0` start, `1.0` end)
+    pub fn scrub(&mut self, fraction: f32) {
+        self.pause();
+        let last = self.frames.len().saturating_sub(1);
+        self.seek((fraction.clamp(0.0, 1.0) * last as f32).round() as usize);
+    }
+
+    /// Leave review mode, discarding frames after the cursor so recording resumes from it
+    pub fn go_live(&mut self) {
+        if !self.is_live() {
+            self.frames.truncate(self.cursor + 1);
+            if let Some(end) = self.frames.back().map(|frame| frame.time) {
+                self.markers.retain(|(time, _)| *time <= end);
+            }
+        }
+        self.mode = ReplayMode::Live;
+    }
+
+    /// Label the most recent frame (or the reviewed frame) on the timeline
+    pub fn mark(&mut self, label: impl Into<String>) {
+        if let Some(frame) = self.current_frame() {
+            self.markers.push((frame.time, label.into()));
+        }
+    }
+
+    /// Timeline markers as `(time, label)`
+    pub fn markers(&self) -> &[(f32, String)] {
+        &self.markers
+    }
+
+    /// Jump to the next marker after the cursor, returning its label
+    pub fn next_marker(&mut self) -> Option<&str> {
+        let now = self.current_frame()?.time;
+        let (time, index) = self
+            .markers
+            .iter()
+            .enumerate()
+            .filter(|(_, (time, _))| *time > now)
+            .min_by(|a, b| a.1.0.total_cmp(&b.1.0))
+            .map(|(index, (time, _))| (*time, index))?;
+        self.pause();
+        let frame = self.frames.iter().position(|frame| frame.time >= time)?;
+        self.seek(frame);
+        Some(&self.markers[index].1)
+    }
+
+    /// Clear the recording and return to live mode
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.markers.clear();
+        self.cursor = 0;
+        self.mode = ReplayMode::Live;
+    }
+
+    /// Advance playback by `dt` seconds of recorded time
+    fn advance(&mut self, dt: f32) {
+        if self.mode != ReplayMode::Playing {
+            return;
+        }
+        self.playback_time += dt * self.speed;
+        while let (Some(current), Some(next)) = (
+            self.frames.get(self.cursor),
+            self.frames.get(self.cursor + 1),
+        ) {
+            let gap = next.time - current.time;
+            if self.playback_time < gap {
+                break;
+            }
+            self.playback_time -= gap;
+            self.cursor += 1;
+        }
+        if self.cursor + 1 >= self.frames.len() {
+            self.mode = ReplayMode::Paused;
+        }
+    }
+
+    /// Text timeline, e.g. `▶ [=====|-----] 2.5s / 5.0s`
+    pub fn timeline_bar(&self, width: usize) -> String {
+        let icon = match self.mode {
+            ReplayMode::Live => "●",
+            ReplayMode::Playing => "▶",
+            ReplayMode::Paused => "⏸",
+        };
+        let start = self.frames.front().map_or(0.0, |frame| frame.time);
+        let end = self.frames.back().map_or(0.0, |frame| frame.time);
+        let now = self.current_frame().map_or(0.0, |frame| frame.time);
+        let head = (self.progress() * width.saturating_sub(1) as f32).round() as usize;
+
+        let mut bar: Vec<char> = (0..width)
+            .map(|i| if i < head { '=' } else { '-' })
+            .collect();
+        if end > start {
+            for (time, _) in &self.markers {
+                let slot = ((time - start) / (end - start) * width.saturating_sub(1) as f32).round()
+                    as usize;
+                if let Some(c) = bar.get_mut(slot) {
+                    *c = '*';
+                }
+            }
+        }
+        if let Some(c) = bar.get_mut(head) {
+            *c = '|';
+        }
+        let bar: String = bar.into_iter().collect();
+        format!("{icon} [{bar}] {:.1}s / {:.1}s", now - start, end - start)
+    }
+}
+
+/// True when there is no replay or it is recording; use with `run_if` to pause
+/// simulation systems during review
+pub fn replay_is_live(world: &World) -> bool {
+    world.resource::<Replay>().is_none_or(Replay::is_live)
+}
+
+/// Action names for the replay controls
+pub mod actions {
+    pub const TOGGLE: &str = "replay_toggle";
+    pub const STEP_BACK: &str = "replay_step_back";
+    pub const STEP_FORWARD: &str = "replay_step_forward";
+    pub const START: &str = "replay_start";
+    pub const END: &str = "replay_end";
+    pub const NEXT_MARKER: &str = "replay_next_marker";
+    pub const LIVE: &str = "replay_live";
+}
+
+/// Default keys: P play/pause, `,`/`.` step, Home/End jump, M next marker, L live
+pub fn default_bindings() -> ActionMap {
+    ActionMap::new()
+        .bind(actions::TOGGLE, KeyCode::KeyP)
+        .bind(actions::STEP_BACK, KeyCode::Comma)
+        .bind(actions::STEP_FORWARD, KeyCode::Period)
+        .bind(actions::START, KeyCode::Home)
+        .bind(actions::END, KeyCode::End)
+        .bind(actions::NEXT_MARKER, KeyCode::KeyM)
+        .bind(actions::LIVE, KeyCode::KeyL)
+}
+
+/// Handle replay controls, then record the frame when live or apply the
+/// recorded transforms when reviewing
+pub fn replay_system(world: &mut World, input: &InputState, time: &TimeState) {
+    if world.resource::<Replay>().is_none() {
+        world.insert_resource(Replay::default());
+    }
+
+    let default_bindings;
+    let actions = match world.resource::<ActionMap>() {
+        Some(actions) => actions,
+        None => {
+            default_bindings = self::default_bindings();
+            &default_bindings
+        }
+    };
+    let toggle = actions.just_pressed(input, actions::TOGGLE);
+    let back = actions.just_pressed(input, actions::STEP_BACK);
+    let forward = actions.just_pressed(input, actions::STEP_FORWARD);
+    let start = actions.just_pressed(input, actions::START);
+    let end = actions.just_pressed(input, actions::END);
+    let next_marker = actions.just_pressed(input, actions::NEXT_MARKER);
+    let live = actions.just_pressed(input, actions::LIVE);
+
+    let replay = world.resource_mut::<Replay>().expect("inserted above");
+    if toggle {
+        replay.toggle_play();
+    }
+    if back {
+        replay.step(-1);
+    }
+    if forward {
+        replay.step(1);
+    }
+    if start {
+        replay.scrub(0.0);
+    }
+    if end {
+        replay.scrub(1.0);
+    }
+    if next_marker {
+        replay.next_marker();
+    }
+    if live {
+        replay.go_live();
+    }
+    hud::handle_mouse(replay, input);
+
+    if replay.is_live() {
+        let transforms = world
+            .query::<Transform>()
+            .map(|(entity, transform)| (entity, transform.clone()))
+            .collect();
+        let frame = ReplayFrame {
+            time: time.elapsed_seconds(),
+            transforms,
+        };
+        if let Some(replay) = world.resource_mut::<Replay>() {
+            replay.record(frame);
+        }
+        return;
+    }
+
+    replay.advance(time.delta_seconds());
+    let Some(frame) = replay.current_frame().cloned() else {
+        return;
+    };
+    for (entity, recorded) in frame.transforms {
+        if let Some(transform) = world.get_component_mut::<Transform>(entity) {
+            *transform = recorded;
+        }
+    }
+}
+
+/// Plugin registering replay recording, controls, and the timeline display
+pub struct ReplayPlugin;
+
+impl crate::plugin::Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        use crate::schedule::IntoSystemDescriptor;
+
+        app.register_render_system(replay_hud_system);
+        app.register_system(replay_system.label("replay"));
+    }
+}
@@ -0,0 +1,568 @@
+//! Scene save and load in RON or JSON
+//!
+//! Only components registered in a `SceneRegistry` are written; everything
+//! else on an entity is skipped, as are entities and components named by a
+//! `SkipSave` component. Unless a `SceneRegistry` resource is inserted, the
+//! registry is built from the world's `TypeRegistry` (see
+//! `TypeRegistry::register_saved`), so registering a component there is
+//! enough to save it. GPU resources are never serialized: give an entity a
+//! `MeshSource` and its `Mesh` is rebuilt after loading.
+//!
+//! Scene files record the version of each component registered with one, so
+//! that when a component struct changes, a migration registered for the old
+//! version upgrades old files as they load:
+//!
+//! ```rust,no_run
+//! use qsi::scene::SceneRegistry;
+//!
+//! // Version 1 renamed `Health::hp` to `current`
+//! # #[derive(serde::Serialize, serde::Deserialize)]
+//! # struct Health { current: f32 }
+//! # impl qsi::ecs::Component for Health {}
+//! let registry = SceneRegistry::new()
+//!     .with_version::<Health>("Health", 1)
+//!     .with_migration("Health", 0, |mut value| {
+//!         if let Some(hp) = value.as_object_mut().and_then(|fields| fields.remove("hp")) {
+//!             value["current"] = hp;
+//!         }
+//!         Ok(value)
+//!     });
+//! ```
+//!
+//! ```rust,no_run
+//! use qsi::prelude::*;
+//!
+//! fn setup(world: &mut World, _renderer: &mut Renderer) {
+//!     world.load_scene("scene.ron").unwrap();
+//! }
+//!
+//! fn save(world: &mut World, input: &InputState, _time: &TimeState) {
+//!     if input.key_just_pressed(KeyCode::F5) {
+//!         world.save_scene("scene.ron").unwrap();
+//!     }
+//! }
+//! ```
+
+use crate::ecs::{Component, EntityId, Prefab, World};
+use crate::reflect::{LoadFn, SaveFn, TypeRegistry, load_component, save_component};
+use anyhow::{Context, Result, bail};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+type MigrateFn = Box<dyn Fn(Value) -> Result<Value> + Send + Sync>;
+
+/// Version of the scene file layout written by this build
+pub const SCENE_FORMAT_VERSION: u32 = 1;
+
+struct Registration {
+    name: String,
+    save: SaveFn,
+    load: LoadFn,
+    /// Schema version of the component's current layout
+    version: u32,
+    /// Upgrades from each older version to the next one
+    migrations: BTreeMap<u32, MigrateFn>,
+}
+
+impl Registration {
+    /// Upgrade `value` saved at `from` to the current version
+    fn migrate(&self, mut value: Value, from: u32) -> Result<Value> {
+        if from > self.version {
+            bail!(
+                "`{}` was saved at version {from}, newer than the supported {}",
+                self.name,
+                self.version
+            );
+        }
+        for version in from..self.version {
+            let migrate = self.migrations.get(&version).with_context(|| {
+                format!("No migration for `{}` from version {version}", self.name)
+            })?;
+            value = migrate(value).with_context(|| {
+                format!("Failed to migrate `{}` from version {version}", self.name)
+            })?;
+        }
+        Ok(value)
+    }
+}
+
+/// Keeps an entity, or some of its components, out of saved scenes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SkipSave {
+    /// Registered names of the components to leave out; empty leaves out
+    /// the whole entity
+    pub components: Vec<String>,
+}
+
+impl Component for SkipSave {}
+
+impl SkipSave {
+    /// Leave the whole entity out
+    pub fn entity() -> Self {
+        Self::default()
+    }
+
+    /// Leave out only the named components
+    pub fn components<S: Into<String>>(names: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            components: names.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    fn skips(&self, name: &str) -> bool {
+        self.components.is_empty() || self.components.iter().any(|skipped| skipped == name)
+    }
+}
+
+/// Components that take part in scene files, keyed by a stable name, with
+/// their schema versions and migrations
+///
+/// Without a `SceneRegistry` resource, scenes use `SceneRegistry::from_types`
+/// on the world's `TypeRegistry`. Insert one (usually built with `from_types`
+/// too) to give components versions and migrations.
+pub struct SceneRegistry {
+    registrations: Vec<Registration>,
+}
+
+impl SceneRegistry {
+    /// Create a registry with the components the built-in `TypeRegistry` saves
+    pub fn new() -> Self {
+        Self::from_types(&TypeRegistry::new())
+    }
+
+    /// Create a registry with every component `types` saves, at version 0
+    pub fn from_types(types: &TypeRegistry) -> Self {
+        let mut registry = Self::empty();
+        for saved in types.saved_types() {
+            registry.insert(saved.name.clone(), saved.save, saved.load, 0);
+        }
+        registry
+    }
+
+    /// Create a registry with no components
+    pub fn empty() -> Self {
+        Self {
+            registrations: Vec::new(),
+        }
+    }
+
+    /// Register a component under `name`, replacing any previous registration
+    pub fn register<T>(&mut self, name: impl Into<String>)
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.register_versioned::<T>(name, 0);
+    }
+
+    /// Register a component whose current layout is schema `version`; files
+    /// saved at older versions are upgraded by migrations from `add_migration`
+    pub fn register_versioned<T>(&mut self, name: impl Into<String>, version: u32)
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.insert(
+            name.into(),
+            save_component::<T>,
+            load_component::<T>,
+            version,
+        );
+    }
+
+    fn insert(&mut self, name: String, save: SaveFn, load: LoadFn, version: u32) {
+        self.registrations.retain(|r| r.name != name);
+        self.registrations.push(Registration {
+            name,
+            save,
+            load,
+            version,
+            migrations: BTreeMap::new(),
+        });
+    }
+
+    /// Upgrade the registered component `name` from version `from` to
+    /// `from + 1`, given its saved data
+    ///
+    /// Register the component first; migrations for an unknown name are dropped.
+    pub fn add_migration(
+        &mut self,
+        name: &str,
+        from: u32,
+        migrate: impl Fn(Value) -> Result<Value> + Send + Sync + 'static,
+    ) {
+        match self.registrations.iter_mut().find(|r| r.name == name) {
+            Some(registration) => {
+                registration.migrations.insert(from, Box::new(migrate));
+            }
+            None => log::warn!("Dropping migration for unregistered scene component `{name}`"),
+        }
+    }
+
+    /// Stop saving and loading the component registered as `name`
+    pub fn unregister(&mut self, name: &str) {
+        self.registrations.retain(|r| r.name != name);
+    }
+
+    /// Builder form of `register`
+    pub fn with<T>(mut self, name: impl Into<String>) -> Self
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.register::<T>(name);
+        self
+    }
+
+    /// Builder form of `register_versioned`
+    pub fn with_version<T>(mut self, name: impl Into<String>, version: u32) -> Self
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.register_versioned::<T>(name, version);
+        self
+    }
+
+    /// Builder form of `add_migration`
+    pub fn with_migration(
+        mut self,
+        name: &str,
+        from: u32,
+        migrate: impl Fn(Value) -> Result<Value> + Send + Sync + 'static,
+    ) -> Self {
+        self.add_migration(name, from, migrate);
+        self
+    }
+
+    /// Builder form of `unregister`
+    pub fn without(mut self, name: &str) -> Self {
+        self.unregister(name);
+        self
+    }
+
+    /// Current schema version of the component registered as `name`
+    pub fn version(&self, name: &str) -> Option<u32> {
+        self.registrations
+            .iter()
+            .find(|r| r.name == name)
+            .map(|r| r.version)
+    }
+
+    /// Names of all registered components
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.registrations.iter().map(|r| r.name.as_str())
+    }
+}
+
+impl Default for SceneRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One saved entity: component name to component data
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SceneEntity {
+    pub components: BTreeMap<String, Value>,
+}
+
+/// A format-independent snapshot of serializable entities
+///
+/// Files written before versioning load as format 0 with every component at
+/// version 0.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Scene {
+    /// Layout version of the file (`SCENE_FORMAT_VERSION` when saved)
+    #[serde(default)]
+    pub format: u32,
+    /// Schema version of each saved component; missing names are version 0
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub versions: BTreeMap<String, u32>,
+    pub entities: Vec<SceneEntity>,
+}
+
+impl Scene {
+    /// Capture every entity that has at least one registered component,
+    /// leaving out what `SkipSave` components name
+    pub fn from_world(world: &World, registry: &SceneRegistry) -> Result<Self> {
+        Ok(Self::capture(world, registry, |_| true)?.0)
+    }
+
+    /// `from_world` limited to entities `include` accepts, also returning the
+    /// saved entities in scene order
+    pub(crate) fn capture(
+        world: &World,
+        registry: &SceneRegistry,
+        include: impl Fn(EntityId) -> bool,
+    ) -> Result<(Self, Vec<EntityId>)> {
+        let mut entities = Vec::new();
+        let mut saved = Vec::new();
+        let mut versions = BTreeMap::new();
+        for &entity in world.entities().iter().filter(|&&entity| include(entity)) {
+            let skip = world.get_component::<SkipSave>(entity);
+            let mut components = BTreeMap::new();
+            for registration in &registry.registrations {
+                if skip.is_some_and(|skip| skip.skips(&registration.name)) {
+                    continue;
+                }
+                if let Some(value) = (registration.save)(world, entity) {
+                    let value = value
+                        .with_context(|| format!("Failed to serialize {}", registration.name))?;
+                    components.insert(registration.name.clone(), value);
+                    if registration.version > 0 {
+                        versions.insert(registration.name.clone(), registration.version);
+                    }
+                }
+            }
+            if !components.is_empty() {
+                entities.push(SceneEntity { components });
+                saved.push(entity);
+            }
+        }
+        let scene = Self {
+            format: SCENE_FORMAT_VERSION,
+            versions,
+            entities,
+        };
+        Ok((scene, saved))
+    }
+
+    /// Spawn the scene's entities into `world`, returning the new entity IDs
+    ///
+    /// Components saved at older versions are migrated as they load.
+    pub fn spawn_into(&self, world: &mut World, registry: &SceneRegistry) -> Result<Vec<EntityId>> {
+        self.check_format()?;
+        let mut spawned = Vec::with_capacity(self.entities.len());
+        for saved in &self.entities {
+            let entity = world.create_entity();
+            spawned.push(entity);
+            saved.apply_at(world, entity, registry, Some(&self.versions))?;
+        }
+        Ok(spawned)
+    }
+
+    /// Upgrade every component to its current registered version in place
+    pub fn migrate(&mut self, registry: &SceneRegistry) -> Result<()> {
+        self.check_format()?;
+        for saved in &mut self.entities {
+            for (name, value) in &mut saved.components {
+                let Some(registration) = registry.registrations.iter().find(|r| &r.name == name)
+                else {
+                    continue;
+                };
+                let from = self.versions.get(name).copied().unwrap_or(0);
+                *value = registration.migrate(std::mem::take(value), from)?;
+            }
+        }
+        for registration in &registry.registrations {
+            if let Some(version) = self.versions.get_mut(&registration.name) {
+                *version = registration.version;
+            } else if registration.version > 0
+                && self
+                    .entities
+                    .iter()
+                    .any(|saved| saved.components.contains_key(&registration.name))
+            {
+                self.versions
+                    .insert(registration.name.clone(), registration.version);
+            }
+        }
+        Ok(())
+    }
+
+    /// Fail on files written by a newer build
+    pub(crate) fn check_format(&self) -> Result<()> {
+        if self.format > SCENE_FORMAT_VERSION {
+            bail!(
+                "Scene format {} is newer than the supported {SCENE_FORMAT_VERSION}",
+                self.format
+            );
+        }
+        Ok(())
+    }
+
+    /// Serialize as pretty-printed RON
+    pub fn to_ron(&self) -> Result<String> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    /// Serialize as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parse a RON scene
+    pub fn from_ron(text: &str) -> Result<Self> {
+        Ok(ron::from_str(text)?)
+    }
+
+    /// Parse a JSON scene
+    pub fn from_json(text: &str) -> Result<Self> {
+        Ok(serde_json::from_str(text)?)
+    }
+
+    /// Write to `path` as RON or JSON, picked by its `.ron` or `.json` extension
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let text = match SceneFormat::from_path(path)? {
+            SceneFormat::Ron => self.to_ron()?,
+            SceneFormat::Json => self.to_json()?,
+        };
+        std::fs::write(path, text)
+            .with_context(|| format!("Failed to write scene {}", path.display()))
+    }
+
+    /// Read from `path`, choosing the format by extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read scene {}", path.display()))?;
+        match SceneFormat::from_path(path)? {
+            SceneFormat::Ron => Self::from_ron(&text),
+            SceneFormat::Json => Self::from_json(&text),
+        }
+        .with_context(|| format!("Failed to parse scene {}", path.display()))
+    }
+}
+
+pub(crate) enum SceneFormat {
+    Ron,
+    Json,
+}
+
+impl SceneFormat {
+    pub(crate) fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("ron") => Ok(Self::Ron),
+            Some("json") => Ok(Self::Json),
+            other => bail!("Unsupported scene extension {:?}", other),
+        }
+    }
+}
+
+impl World {
+    /// Save all entities with registered components to a `.ron` or `.json` file
+    pub fn save_scene(&self, path: impl AsRef<Path>) -> Result<()> {
+        with_registry_ref(self, |world, registry| {
+            Scene::from_world(world, registry)?.save(path)
+        })
+    }
+
+    /// Load a scene file and spawn its entities, returning their IDs
+    pub fn load_scene(&mut self, path: impl AsRef<Path>) -> Result<Vec<EntityId>> {
+        let scene = Scene::load(path)?;
+        with_registry(self, |world, registry| scene.spawn_into(world, registry))
+    }
+}
+
+impl SceneEntity {
+    /// Add this entity's components, taken to be at their current versions,
+    /// to an existing entity
+    pub fn apply(
+        &self,
+        world: &mut World,
+        entity: EntityId,
+        registry: &SceneRegistry,
+    ) -> Result<()> {
+        self.apply_at(world, entity, registry, None)
+    }
+
+    /// `apply`, first migrating components from the saved `versions`
+    pub(crate) fn apply_at(
+        &self,
+        world: &mut World,
+        entity: EntityId,
+        registry: &SceneRegistry,
+        versions: Option<&BTreeMap<String, u32>>,
+    ) -> Result<()> {
+        for (name, value) in &self.components {
+            let Some(registration) = registry.registrations.iter().find(|r| &r.name == name) else {
+                log::warn!("Skipping unregistered scene component `{}`", name);
+                continue;
+            };
+            let value = match versions {
+                Some(versions) => {
+                    registration.migrate(value.clone(), versions.get(name).copied().unwrap_or(0))?
+                }
+                None => value.clone(),
+            };
+            (registration.load)(world, entity, value)
+                .with_context(|| format!("Failed to load {}", name))?;
+        }
+        Ok(())
+    }
+}
+
+impl Prefab {
+    /// Create a prefab that instantiates a saved scene entity
+    ///
+    /// Components are deserialized on each instantiation using the world's
+    /// `SceneRegistry`; failures are logged.
+    pub fn from_scene_entity(saved: SceneEntity) -> Self {
+        Self::from_saved(saved, None)
+    }
+
+    /// Load a prefab from the first entity in a scene file, migrating its
+    /// components from the file's versions on each instantiation
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let scene = Scene::load(path)?;
+        scene.check_format()?;
+        let saved = scene
+            .entities
+            .into_iter()
+            .next()
+            .with_context(|| format!("Scene {} has no entities", path.display()))?;
+        Ok(Self::from_saved(saved, Some(scene.versions)))
+    }
+
+    fn from_saved(saved: SceneEntity, versions: Option<BTreeMap<String, u32>>) -> Self {
+        Prefab::new().with_fn(move |world, entity| {
+            if let Err(e) = with_registry(world, |world, registry| {
+                saved.apply_at(world, entity, registry, versions.as_ref())
+            }) {
+                log::error!("Failed to instantiate prefab: {:#}", e);
+            }
+        })
+    }
+}
+
+/// Run `f` with the world's `SceneRegistry`, or one built from its
+/// `TypeRegistry` if none is inserted
+pub(crate) fn with_registry<R>(
+    world: &mut World,
+    f: impl FnOnce(&mut World, &SceneRegistry) -> R,
+) -> R {
+    match world.remove_resource::<SceneRegistry>() {
+        Some(registry) => {
+            let result = f(world, &registry);
+            world.insert_resource(registry);
+            result
+        }
+        None => {
+            let registry = default_registry(world);
+            f(world, &registry)
+        }
+    }
+}
+
+/// `with_registry` for a shared world
+pub(crate) fn with_registry_ref<R>(
+    world: &World,
+    f: impl FnOnce(&World, &SceneRegistry) -> R,
+) -> R {
+    match world.resource::<SceneRegistry>() {
+        Some(registry) => f(world, registry),
+        None => f(world, &default_registry(world)),
+    }
+}
+
+fn default_registry(world: &World) -> SceneRegistry {
+    match world.resource::<TypeRegistry>() {
+        Some(types) => SceneRegistry::from_types(types),
+        None => SceneRegistry::new(),
+    }
+}
@@ -0,0 +1,364 @@
+//! System ordering via labels and explicit dependencies
+//!
+//! Systems run in registration order unless they declare constraints:
+//!
+//! ```rust,no_run
+//! use qsi::prelude::*;
+//!
+//! # fn physics(_: &mut World, _: &InputState, _: &TimeState) {}
+//! # fn render_prep(_: &mut World, _: &InputState, _: &TimeState) {}
+//! App::new()
+//!     .add_system(render_prep.after("physics"))
+//!     .add_system(physics.label("physics"))
+//!     .run()
+//!     .unwrap();
+//! ```
+//!
+//! The order is resolved once when the app starts; a dependency cycle makes
+//! `App::run` return an error naming the systems involved.
+
+use crate::UpdateSystem;
+use crate::diagnostics::SystemTimings;
+use crate::ecs::{SystemAccess, SystemParam, World};
+use crate::input::InputState;
+use crate::state::{HookKind, StateHook, TransitionDriver};
+use crate::time::TimeState;
+use anyhow::{Result, bail};
+use std::collections::{BTreeSet, HashMap};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::time::Instant;
+
+/// Predicate deciding whether a system runs this frame
+pub type RunCondition = Box<dyn Fn(&World) -> bool + Send>;
+
+/// An update system together with its label and ordering constraints
+pub struct SystemDescriptor {
+    pub(crate) system: UpdateSystem,
+    pub(crate) name: String,
+    pub(crate) labels: Vec<String>,
+    pub(crate) before: Vec<String>,
+    pub(crate) after: Vec<String>,
+    pub(crate) conditions: Vec<RunCondition>,
+    pub(crate) enabled: bool,
+}
+
+impl SystemDescriptor {
+    fn new(name: &str, system: UpdateSystem) -> Self {
+        Self {
+            system,
+            name: name.to_string(),
+            labels: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+            conditions: Vec::new(),
+            enabled: true,
+        }
+    }
+
+    /// Get the system's name (its Rust type name unless overridden)
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Check if the system still runs (panic-safe mode disables systems that panic)
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+/// The app's update systems in execution order
+#[derive(Default)]
+pub(crate) struct Schedule {
+    pub(crate) systems: Vec<SystemDescriptor>,
+    /// Catch panics in systems and disable the offending system instead of aborting
+    pub(crate) panic_safe: bool,
+    /// One driver per registered state type
+    pub(crate) state_transitions: Vec<TransitionDriver>,
+    pub(crate) state_hooks: Vec<StateHook>,
+}
+
+impl Schedule {
+    /// Resolve ordering constraints; must be called before `run`
+    pub(crate) fn sort(&mut self) -> Result<()> {
+        self.systems = sort_systems(std::mem::take(&mut self.systems))?;
+        Ok(())
+    }
+
+    /// Run every enabled system once, recording timings
+    pub(crate) fn run(&mut self, world: &mut World, input: &InputState, time: &TimeState) {
+        self.apply_state_transitions(world, input, time);
+
+        for (index, descriptor) in self.systems.iter_mut().enumerate() {
+            if !descriptor.enabled || !descriptor.conditions.iter().all(|c| c(world)) {
+                continue;
+            }
+
+            let start = Instant::now();
+            if self.panic_safe {
+                let result =
+                    catch_unwind(AssertUnwindSafe(|| (descriptor.system)(world, input, time)));
+                if let Err(payload) = result {
+                    let message = payload
+                        .downcast_ref::<&str>()
+                        .map(|s| s.to_string())
+                        .or_else(|| payload.downcast_ref::<String>().cloned())
+                        .unwrap_or_else(|| "unknown panic".to_string());
+                    log::error!(
+                        "System `{}` panicked and has been disabled: {message}",
+                        descriptor.name
+                    );
+                    descriptor.enabled = false;
+                }
+            } else {
+                (descriptor.system)(world, input, time);
+            }
+            let elapsed = start.elapsed();
+
+            if let Some(timings) = world.resource_mut::<SystemTimings>() {
+                timings.record(index, &descriptor.name, elapsed);
+            }
+        }
+    }
+
+    /// Apply pending state changes, running exit hooks before enter hooks
+    fn apply_state_transitions(&self, world: &mut World, input: &InputState, time: &TimeState) {
+        for driver in &self.state_transitions {
+            let Some((exited, entered)) = driver(world) else {
+                continue;
+            };
+            let hooks = exited
+                .iter()
+                .map(|state| (HookKind::Exit, state))
+                .chain(std::iter::once((HookKind::Enter, &entered)));
+            for (kind, state) in hooks {
+                for hook in &self.state_hooks {
+                    if hook.kind == kind && (hook.matches)(state.as_ref()) {
+                        (hook.system)(world, input, time);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Conversion into a `SystemDescriptor`, with ordering combinators
+///
+/// Implemented for classic `fn(&mut World, &InputState, &TimeState)` systems,
+/// for functions whose parameters are all `SystemParam`s, and for descriptors
+/// themselves. `Marker` only disambiguates those impls and is always inferred.
+pub trait IntoSystemDescriptor<Marker = ()> {
+    /// Convert into a descriptor
+    fn into_descriptor(self) -> SystemDescriptor;
+
+    /// Attach a label other systems can order against
+    fn label(self, label: impl Into<String>) -> SystemDescriptor
+    where
+        Self: Sized,
+    {
+        let mut descriptor = self.into_descriptor();
+        descriptor.labels.push(label.into());
+        descriptor
+    }
+
+    /// Run before every system with the given label
+    fn before(self, label: impl Into<String>) -> SystemDescriptor
+    where
+        Self: Sized,
+    {
+        let mut descriptor = self.into_descriptor();
+        descriptor.before.push(label.into());
+        descriptor
+    }
+
+    /// Run after every system with the given label
+    fn after(self, label: impl Into<String>) -> SystemDescriptor
+    where
+        Self: Sized,
+    {
+        let mut descriptor = self.into_descriptor();
+        descriptor.after.push(label.into());
+        descriptor
+    }
+
+    /// Only run while `condition` returns true
+    fn run_if(self, condition: impl Fn(&World) -> bool + Send + 'static) -> SystemDescriptor
+    where
+        Self: Sized,
+    {
+        let mut descriptor = self.into_descriptor();
+        descriptor.conditions.push(Box::new(condition));
+        descriptor
+    }
+
+    /// Override the name used in diagnostics
+    fn named(self, name: impl Into<String>) -> SystemDescriptor
+    where
+        Self: Sized,
+    {
+        let mut descriptor = self.into_descriptor();
+        descriptor.name = name.into();
+        descriptor
+    }
+}
+
+impl IntoSystemDescriptor for SystemDescriptor {
+    fn into_descriptor(self) -> SystemDescriptor {
+        self
+    }
+}
+
+/// Marker for classic `fn(&mut World, &InputState, &TimeState)` systems
+pub struct WorldSystemMarker;
+
+impl<F> IntoSystemDescriptor<WorldSystemMarker> for F
+where
+    F: Fn(&mut World, &InputState, &TimeState) + Send + 'static,
+{
+    fn into_descriptor(self) -> SystemDescriptor {
+        SystemDescriptor::new(std::any::type_name::<F>(), Box::new(self))
+    }
+}
+
+macro_rules! impl_param_system {
+    ($($param:ident),*) => {
+        #[allow(non_snake_case, unused_variables, unused_mut, unused_unsafe, clippy::unused_unit)]
+        impl<F, $($param: SystemParam),*> IntoSystemDescriptor<fn($($param,)*)> for F
+        where
+            F: Fn($($param),*) + for<'w> Fn($($param::Item<'w>),*) + Send + 'static,
+        {
+            fn into_descriptor(self) -> SystemDescriptor {
+                let name = std::any::type_name::<F>();
+                let mut access = SystemAccess::default();
+                $($param::add_access(&mut access);)*
+                if let Err(conflict) = access.validate() {
+                    panic!("Conflicting parameters in system `{name}`: {conflict}");
+                }
+
+                let system = move |world: &mut World, input: &InputState, time: &TimeState| {
+                    let world = world as *mut World;
+                    // SAFETY: `world` is exclusively borrowed for this call and
+                    // parameter accesses were validated above
+                    let ($($param,)*) = unsafe { ($($param::fetch(world, input, time),)*) };
+                    self($($param),*);
+                };
+                SystemDescriptor::new(name, Box::new(system))
+            }
+        }
+    };
+}
+
+impl_param_system!();
+impl_param_system!(P0);
+impl_param_system!(P0, P1);
+impl_param_system!(P0, P1, P2);
+impl_param_system!(P0, P1, P2, P3);
+impl_param_system!(P0, P1, P2, P3, P4);
+impl_param_system!(P0, P1, P2, P3, P4, P5);
+impl_param_system!(P0, P1, P2, P3, P4, P5, P6);
+impl_param_system!(P0, P1, P2, P3, P4, P5, P6, P7);
+
+/// Sort systems so every `before`/`after` constraint holds
+///
+/// Unconstrained systems keep their registration order.
+pub(crate) fn sort_systems(systems: Vec<SystemDescriptor>) -> Result<Vec<SystemDescriptor>> {
+    let mut labeled: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, system) in systems.iter().enumerate() {
+        for label in &system.labels {
+            labeled.entry(label.as_str()).or_default().push(index);
+        }
+    }
+
+    // edges[a] contains b when a must run before b
+    let mut edges: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); systems.len()];
+    for (index, system) in systems.iter().enumerate() {
+        let constraints = system
+            .after
+            .iter()
+            .map(|label| (label, false))
+            .chain(system.before.iter().map(|label| (label, true)));
+
+        for (label, is_before) in constraints {
+            let Some(others) = labeled.get(label.as_str()) else {
+                log::warn!(
+                    "System `{}` is ordered relative to unknown label `{label}`",
+                    system.name
+                );
+                continue;
+            };
+            for &other in others.iter().filter(|&&other| other != index) {
+                if is_before {
+                    edges[index].insert(other);
+                } else {
+                    edges[other].insert(index);
+                }
+            }
+        }
+    }
+
+    let mut in_degree = vec![0usize; systems.len()];
+    for targets in &edges {
+        for &target in targets {
+            in_degree[target] += 1;
+        }
+    }
+
+    // Kahn's algorithm, always taking the earliest registered ready system
+    let mut ready: BTreeSet<usize> = (0..systems.len()).filter(|&i| in_degree[i] == 0).collect();
+    let mut order = Vec::with_capacity(systems.len());
+    while let Some(index) = ready.pop_first() {
+        order.push(index);
+        for &target in &edges[index] {
+            in_degree[target] -= 1;
+            if in_degree[target] == 0 {
+                ready.insert(target);
+            }
+        }
+    }
+
+    if order.len() < systems.len() {
+        let cycle = find_cycle(&edges, &in_degree);
+        let names: Vec<&str> = cycle.iter().map(|&i| systems[i].name.as_str()).collect();
+        bail!("System ordering cycle detected: {}", names.join(" -> "));
+    }
+
+    let mut slots: Vec<Option<SystemDescriptor>> = systems.into_iter().map(Some).collect();
+    Ok(order
+        .into_iter()
+        .filter_map(|index| slots[index].take())
+        .collect())
+}
+
+/// Find one cycle among the systems Kahn's algorithm could not schedule
+fn find_cycle(edges: &[BTreeSet<usize>], in_degree: &[usize]) -> Vec<usize> {
+    let remaining = |i: usize| in_degree[i] > 0;
+
+    // Every unscheduled system has an unscheduled predecessor, so walking
+    // predecessors must eventually revisit a node.
+    let mut predecessor = vec![None; edges.len()];
+    for (from, targets) in edges.iter().enumerate().filter(|&(i, _)| remaining(i)) {
+        for &to in targets.iter().filter(|&&to| remaining(to)) {
+            predecessor[to].get_or_insert(from);
+        }
+    }
+
+    let Some(start) = (0..edges.len()).find(|&i| remaining(i)) else {
+        return Vec::new();
+    };
+    let mut visited = vec![false; edges.len()];
+    let mut current = start;
+    while !visited[current] {
+        visited[current] = true;
+        current = predecessor[current].unwrap_or(current);
+    }
+
+    // `current` is on the cycle; walk it once more to collect it
+    let mut cycle = vec![current];
+    let mut node = predecessor[current].unwrap_or(current);
+    while node != current {
+        cycle.push(node);
+        node = predecessor[node].unwrap_or(current);
+    }
+    cycle.push(current);
+    cycle.reverse();
+    cycle
+}
@@ -0,0 +1,210 @@
+//! Checkpointing a running application to a file and resuming from it
+//!
+//! A session bundles the scene (every entity's registered components, see
+//! `qsi::scene`), the orbit camera's pose, the clock, and the `Rng` resource.
+//! Loading matches saved entities in creation order with the ones startup
+//! systems created, so meshes and other components a scene cannot store stay
+//! in place; extra saved entities are spawned and surplus ones despawned.
+//! Resources other than `Rng` are not saved.
+//!
+//! ```rust,no_run
+//! use qsi::prelude::*;
+//!
+//! # fn setup(_: &mut World, _: &mut Renderer) {}
+//! // Run a batch of frames and checkpoint at the end
+//! App::new()
+//!     .with_headless(640, 480)
+//!     .with_max_frames(1000)
+//!     .add_startup_system(setup)
+//!     .save_session("checkpoint.ron")
+//!     .run()?;
+//!
+//! // Later: resume where it stopped
+//! App::new()
+//!     .add_startup_system(setup)
+//!     .load_session("checkpoint.ron")
+//!     .run()?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! Systems can save or load while the app runs by inserting a
+//! `SessionRequest`, handled at the end of the frame's update systems.
+
+use crate::AppState;
+use crate::camera::CameraPose;
+use crate::math::Rng;
+use crate::scene::{Scene, SceneFormat, with_registry, with_registry_ref};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Version of the session file layout written by this build
+pub const SESSION_FORMAT_VERSION: u32 = 1;
+
+/// Everything needed to resume an application where it stopped
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Session {
+    /// Layout version of the file (`SESSION_FORMAT_VERSION` when saved)
+    #[serde(default)]
+    pub format: u32,
+    /// Pose of the orbit camera
+    pub camera: Option<CameraPose>,
+    /// Clock time since the app started
+    pub elapsed: Duration,
+    /// Frames run since the app started
+    pub frame_count: u64,
+    /// Random number generator, mid-sequence
+    pub rng: Option<Rng>,
+    pub scene: Scene,
+}
+
+impl Session {
+    /// Serialize as pretty-printed RON
+    pub fn to_ron(&self) -> Result<String> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    /// Serialize as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Write to a `.ron` or `.json` file; any other extension is an error
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let text = match SceneFormat::from_path(path)? {
+            SceneFormat::Ron => self.to_ron()?,
+            SceneFormat::Json => self.to_json()?,
+        };
+        std::fs::write(path, text)
+            .with_context(|| format!("Failed to write session {}", path.display()))
+    }
+
+    /// Read from `path`, choosing the format by extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read session {}", path.display()))?;
+        let session: Self = match SceneFormat::from_path(path)? {
+            SceneFormat::Ron => ron::from_str(&text).map_err(anyhow::Error::from),
+            SceneFormat::Json => serde_json::from_str(&text).map_err(anyhow::Error::from),
+        }
+        .with_context(|| format!("Failed to parse session {}", path.display()))?;
+        if session.format > SESSION_FORMAT_VERSION {
+            bail!(
+                "Session format {} is newer than the supported {SESSION_FORMAT_VERSION}",
+                session.format
+            );
+        }
+        Ok(session)
+    }
+}
+
+/// Resource asking the app to save or load a session after this frame's
+/// update systems
+///
+/// Ignored while update systems run on a simulation thread.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionRequest {
+    Save(PathBuf),
+    Load(PathBuf),
+}
+
+impl SessionRequest {
+    /// Save the session to `path`
+    pub fn save(path: impl Into<PathBuf>) -> Self {
+        Self::Save(path.into())
+    }
+
+    /// Load the session from `path`
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        Self::Load(path.into())
+    }
+}
+
+impl AppState {
+    /// Restore the session in `path`, if given (`App::load_session`)
+    pub(crate) fn resume_session(&mut self, path: Option<&Path>) -> Result<()> {
+        let Some(path) = path else {
+            return Ok(());
+        };
+        let session = Session::load(path)?;
+        self.restore_session(&session)
+            .with_context(|| format!("Failed to resume session {}", path.display()))
+    }
+
+    /// Save the session to `path`, if given (`App::save_session`)
+    pub(crate) fn checkpoint_session(&self, path: Option<&Path>) -> Result<()> {
+        match path {
+            Some(path) => self.capture_session()?.save(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Capture the world, camera, clock, and `Rng`
+    pub(crate) fn capture_session(&self) -> Result<Session> {
+        let camera = self.camera_controller.camera_entity();
+        let scene = with_registry_ref(&self.world, |world, registry| {
+            Scene::capture(world, registry, |entity| Some(entity) != camera)
+        })?
+        .0;
+        Ok(Session {
+            format: SESSION_FORMAT_VERSION,
+            camera: Some(self.camera_controller.pose()),
+            elapsed: self.time.elapsed(),
+            frame_count: self.time.frame_count(),
+            rng: self.world.resource::<Rng>().cloned(),
+            scene,
+        })
+    }
+
+    /// Put the world, camera, clock, and `Rng` back as `session` recorded them
+    pub(crate) fn restore_session(&mut self, session: &Session) -> Result<()> {
+        session.scene.check_format()?;
+        let camera = self.camera_controller.camera_entity();
+        with_registry(&mut self.world, |world, registry| {
+            let (_, current) = Scene::capture(world, registry, |entity| Some(entity) != camera)?;
+            for (index, saved) in session.scene.entities.iter().enumerate() {
+                let entity = match current.get(index) {
+                    Some(&entity) => entity,
+                    None => world.create_entity(),
+                };
+                saved.apply_at(world, entity, registry, Some(&session.scene.versions))?;
+            }
+            for &entity in current.iter().skip(session.scene.entities.len()) {
+                world.despawn(entity);
+            }
+            anyhow::Ok(())
+        })?;
+        if let Some(pose) = session.camera {
+            self.camera_controller.set_pose(pose);
+        }
+        self.time.resume_at(session.elapsed, session.frame_count);
+        if let Some(rng) = &session.rng {
+            self.world.insert_resource(rng.clone());
+        }
+        Ok(())
+    }
+
+    /// Handle a `SessionRequest` inserted by a system this frame
+    pub(crate) fn apply_session_request(&mut self) {
+        let Some(request) = self.world.remove_resource::<SessionRequest>() else {
+            return;
+        };
+        let result = match &request {
+            SessionRequest::Save(path) => self
+                .capture_session()
+                .and_then(|session| session.save(path)),
+            SessionRequest::Load(path) => {
+                Session::load(path).and_then(|session| self.restore_session(&session))
+            }
+        };
+        if let Err(e) = result {
+            log::error!("Session request {request:?} failed: {e:#}");
+        }
+    }
+}
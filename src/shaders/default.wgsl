@@ -1,50 +1,193 @@
 // Default vertex and fragment shader
+//
+// Every color input (vertex colors, material, lights) is linear RGB, and so is
+// the output: the HDR target stays linear and the sRGB output is encoded on
+// write after tonemapping. Custom shaders must not encode colors themselves.
 
-struct Uniforms {
+// Per-view globals shared by every scene shader; custom shaders may declare
+// any prefix of these fields
+struct Globals {
     view_proj: mat4x4<f32>,
+    // xyz: camera position
+    position: vec4<f32>,
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    inverse_view: mat4x4<f32>,
+    inverse_proj: mat4x4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+    // x: elapsed seconds, y: frame seconds, z: frame count
+    time: vec4<f32>,
+    // xy: target size in pixels, zw: 1 / size
+    resolution: vec4<f32>,
+}
+
+struct Object {
     model: mat4x4<f32>,
+    base_color: vec4<f32>,
+    // xyz: emissive color, w: shading mode (0 unlit, 1 flat, 2 shaded)
+    emissive: vec4<f32>,
+    // x: normal map strength (0 without a normal map), y: per-instance scalar
+    params: vec4<f32>,
+}
+
+// Maximum number of point/spot lights; must match `graphics::MAX_LIGHTS`
+const MAX_LIGHTS: u32 = 16u;
+
+struct LocalLight {
+    // xyz: position, w: range
+    position: vec4<f32>,
+    // rgb: color * intensity, w: cos(inner cone angle), -1 for point lights
+    color: vec4<f32>,
+    // xyz: spot direction, w: cos(outer cone angle), -2 for point lights
+    direction: vec4<f32>,
+    // constant, linear, quadratic attenuation
+    attenuation: vec4<f32>,
+}
+
+struct Light {
+    // Directional light: unit vector towards the light
+    to_light: vec4<f32>,
+    // rgb: color * intensity, w: ambient
+    color: vec4<f32>,
+    // x: number of used entries in `lights`
+    count: vec4<u32>,
+    lights: array<LocalLight, MAX_LIGHTS>,
 }
 
 @group(0) @binding(0)
-var<uniform> uniforms: Uniforms;
+var<uniform> globals: Globals;
+
+// One entry per drawn mesh, selected by the draw's instance index
+@group(0) @binding(1)
+var<storage, read> objects: array<Object>;
+
+@group(0) @binding(2)
+var<uniform> light: Light;
+
+// Tangent-space normal map (a flat 1x1 map when the material has none)
+@group(1) @binding(0)
+var normal_map: texture_2d<f32>;
+
+@group(1) @binding(1)
+var normal_sampler: sampler;
 
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) color: vec3<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) uv: vec2<f32>,
+    // xyz: tangent, w: bitangent handedness
+    @location(4) tangent: vec4<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec3<f32>,
     @location(1) world_position: vec3<f32>,
+    @location(2) world_normal: vec3<f32>,
+    @location(3) @interpolate(flat) instance: u32,
+    @location(4) uv: vec2<f32>,
+    @location(5) world_tangent: vec4<f32>,
 }
 
 @vertex
-fn vs_main(vertex: VertexInput) -> VertexOutput {
+fn vs_main(vertex: VertexInput, @builtin(instance_index) instance: u32) -> VertexOutput {
     var out: VertexOutput;
-    
+    let model = objects[instance].model;
+
     // Transform to world space
-    let world_position = uniforms.model * vec4<f32>(vertex.position, 1.0);
-    
+    let world_position = model * vec4<f32>(vertex.position, 1.0);
+
     // Transform to clip space
-    out.clip_position = uniforms.view_proj * world_position;
+    out.clip_position = globals.view_proj * world_position;
     out.color = vertex.color;
     out.world_position = world_position.xyz;
-    
+    // Exact for rotation and uniform scale; zero normals stay zero
+    out.world_normal = (model * vec4<f32>(vertex.normal, 0.0)).xyz;
+    out.instance = instance;
+    out.uv = vertex.uv;
+    out.world_tangent = vec4<f32>((model * vec4<f32>(vertex.tangent.xyz, 0.0)).xyz, vertex.tangent.w);
+
     return out;
 }
 
+// Diffuse plus optional specular contribution of one light
+fn blinn_phong(
+    albedo: vec3<f32>,
+    normal: vec3<f32>,
+    view_dir: vec3<f32>,
+    light_dir: vec3<f32>,
+    radiance: vec3<f32>,
+    specular: bool,
+) -> vec3<f32> {
+    let n_dot_l = dot(normal, light_dir);
+    var color = albedo * radiance * max(n_dot_l, 0.0);
+    if specular && n_dot_l > 0.0 {
+        let half_dir = normalize(light_dir + view_dir);
+        color += radiance * pow(max(dot(normal, half_dir), 0.0), 32.0) * 0.5;
+    }
+    return color;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    // Simple lighting calculation based on world position
-    let light_dir = normalize(vec3<f32>(1.0, 1.0, 1.0));
-    let normal = normalize(cross(
+    let object = objects[in.instance];
+    let albedo = in.color * object.base_color.rgb;
+    let mode = u32(object.emissive.w);
+
+    // Face normal from screen-space derivatives and the normal map sample
+    // (kept outside branches for uniformity)
+    let face_normal = normalize(cross(
         dpdx(in.world_position),
         dpdy(in.world_position)
     ));
-    
-    let light_intensity = max(dot(normal, light_dir), 0.2); // Ambient minimum of 0.2
-    let final_color = in.color * light_intensity;
-    
-    return vec4<f32>(final_color, 1.0);
+    let map_normal = textureSample(normal_map, normal_sampler, in.uv).xyz * 2.0 - 1.0;
+
+    var final_color = albedo;
+    if mode != 0u {
+        // Shaded mode uses the interpolated vertex normal when the mesh has one
+        var normal = face_normal;
+        if mode == 2u && dot(in.world_normal, in.world_normal) > 1e-8 {
+            normal = normalize(in.world_normal);
+        }
+
+        // Normal mapping in the tangent frame around that normal
+        let strength = object.params.x;
+        let tangent = in.world_tangent.xyz - normal * dot(normal, in.world_tangent.xyz);
+        if strength != 0.0 && dot(tangent, tangent) > 1e-8 {
+            let t = normalize(tangent);
+            let b = cross(normal, t) * in.world_tangent.w;
+            let bump = vec3<f32>(map_normal.xy * strength, max(map_normal.z, 1e-3));
+            normal = normalize(t * bump.x + b * bump.y + normal * bump.z);
+        }
+
+        // Blinn-Phong: ambient + diffuse, plus specular in shaded mode
+        let view_dir = normalize(globals.position.xyz - in.world_position);
+        let specular = mode == 2u;
+        var lit = albedo * light.color.w;
+        lit += blinn_phong(albedo, normal, view_dir, light.to_light.xyz, light.color.rgb, specular);
+
+        for (var i = 0u; i < min(light.count.x, MAX_LIGHTS); i++) {
+            let local = light.lights[i];
+            let offset = local.position.xyz - in.world_position;
+            let distance = length(offset);
+            let range = local.position.w;
+            if distance >= range {
+                continue;
+            }
+            let light_dir = offset / max(distance, 1e-4);
+
+            // Classic falloff, windowed so it reaches zero at the range
+            let k = local.attenuation.xyz;
+            let falloff = 1.0 / max(k.x + k.y * distance + k.z * distance * distance, 1e-4);
+            let window = pow(saturate(1.0 - pow(distance / range, 4.0)), 2.0);
+            let cone = smoothstep(local.direction.w, local.color.w, dot(-light_dir, local.direction.xyz));
+
+            let radiance = local.color.rgb * falloff * window * cone;
+            lit += blinn_phong(albedo, normal, view_dir, light_dir, radiance, specular);
+        }
+        final_color = lit;
+    }
+
+    return vec4<f32>(final_color + object.emissive.rgb, object.base_color.a);
 }
@@ -0,0 +1,18 @@
+// Copy of a depth buffer into a half-float color target, which unlike depth
+// can be read back on every backend. Stores `1 - depth`, where half floats are
+// precise for the values near the far plane that most depth takes.
+
+@group(0) @binding(0)
+var depth: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // One triangle covering the screen: (-1,-1), (3,-1), (-1,3)
+    let ndc = vec2<f32>(f32((index << 1u) & 2u) * 2.0 - 1.0, f32(index & 2u) * 2.0 - 1.0);
+    return vec4<f32>(ndc, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0 - textureLoad(depth, vec2<i32>(position.xy), 0).r, 0.0, 0.0, 1.0);
+}
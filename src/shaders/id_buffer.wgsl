@@ -0,0 +1,45 @@
+// Entity IDs of the nearest surface per pixel, for GPU picking
+//
+// Objects are the scene's, selected by instance index like in `default.wgsl`;
+// `ids` holds the entity ID plus one of each object slot, so 0 means nothing.
+
+struct Globals {
+    view_proj: mat4x4<f32>,
+}
+
+struct Object {
+    model: mat4x4<f32>,
+    base_color: vec4<f32>,
+    emissive: vec4<f32>,
+    params: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
+@group(0) @binding(1)
+var<storage, read> objects: array<Object>;
+
+@group(1) @binding(0)
+var<storage, read> ids: array<u32>;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) @interpolate(flat) id: u32,
+}
+
+@vertex
+fn vs_main(
+    @location(0) position: vec3<f32>,
+    @builtin(instance_index) instance: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = globals.view_proj * objects[instance].model * vec4<f32>(position, 1.0);
+    out.id = ids[instance];
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) u32 {
+    return in.id;
+}
@@ -0,0 +1,49 @@
+// Outline around the silhouettes in the outline mask, drawn over the frame
+//
+// Pixels outside every silhouette take the color of the nearest silhouette
+// pixel within its width, fading over the last pixel to smooth the edge.
+
+struct Params {
+    // Widest outline in pixels, bounding the search
+    radius: i32,
+}
+
+@group(0) @binding(0)
+var mask: texture_2d<f32>;
+
+@group(0) @binding(1)
+var<uniform> params: Params;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // One triangle covering the screen: (-1,-1), (3,-1), (-1,3)
+    let ndc = vec2<f32>(f32((index << 1u) & 2u) * 2.0 - 1.0, f32(index & 2u) * 2.0 - 1.0);
+    return vec4<f32>(ndc, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(position.xy);
+    let size = vec2<i32>(textureDimensions(mask));
+    if textureLoad(mask, pixel, 0).a > 0.0 {
+        discard;
+    }
+    var color = vec4<f32>(0.0);
+    for (var y = -params.radius; y <= params.radius; y++) {
+        for (var x = -params.radius; x <= params.radius; x++) {
+            let sample = pixel + vec2<i32>(x, y);
+            if any(sample < vec2<i32>(0)) || any(sample >= size) {
+                continue;
+            }
+            let outline = textureLoad(mask, sample, 0);
+            let coverage = clamp(outline.a - length(vec2<f32>(f32(x), f32(y))) + 0.5, 0.0, 1.0);
+            if outline.a > 0.0 && coverage > color.a {
+                color = vec4<f32>(outline.rgb, coverage);
+            }
+        }
+    }
+    if color.a <= 0.0 {
+        discard;
+    }
+    return color;
+}
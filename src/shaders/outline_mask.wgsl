@@ -0,0 +1,46 @@
+// Silhouettes of outlined meshes, for the outline composite
+//
+// Objects are the scene's, selected by instance index like in `default.wgsl`;
+// `outlines` holds the outline color of each object slot in rgb and its width
+// in pixels in a, so a 0 alpha means no outline.
+
+struct Globals {
+    view_proj: mat4x4<f32>,
+}
+
+struct Object {
+    model: mat4x4<f32>,
+    base_color: vec4<f32>,
+    emissive: vec4<f32>,
+    params: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
+@group(0) @binding(1)
+var<storage, read> objects: array<Object>;
+
+@group(1) @binding(0)
+var<storage, read> outlines: array<vec4<f32>>;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) @interpolate(flat) outline: vec4<f32>,
+}
+
+@vertex
+fn vs_main(
+    @location(0) position: vec3<f32>,
+    @builtin(instance_index) instance: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = globals.view_proj * objects[instance].model * vec4<f32>(position, 1.0);
+    out.outline = outlines[instance];
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.outline;
+}
@@ -0,0 +1,65 @@
+// Point clouds, one screen-space quad per point
+
+// Prefix of the scene globals in default.wgsl
+struct Globals {
+    view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    inverse_view: mat4x4<f32>,
+    inverse_proj: mat4x4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+    time: vec4<f32>,
+    // xy: target size in pixels, zw: 1 / size
+    resolution: vec4<f32>,
+}
+
+struct Cloud {
+    model: mat4x4<f32>,
+    // x: point size in pixels, y: 1 for round points
+    params: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
+@group(1) @binding(0)
+var<uniform> cloud: Cloud;
+
+struct PointInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // Position within the quad, -1..1 on both axes
+    @location(0) corner: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, point: PointInput) -> VertexOutput {
+    let corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0), vec2<f32>(-1.0, 1.0),
+        vec2<f32>(-1.0, 1.0), vec2<f32>(1.0, -1.0), vec2<f32>(1.0, 1.0),
+    );
+    let corner = corners[index];
+    let clip = globals.view_proj * cloud.model * vec4<f32>(point.position, 1.0);
+    // Half the point size in clip space, scaled by w to stay the same on screen
+    let offset = corner * cloud.params.x * globals.resolution.zw * clip.w;
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(clip.xy + offset, clip.zw);
+    out.corner = corner;
+    out.color = point.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    if cloud.params.y > 0.5 && dot(in.corner, in.corner) > 1.0 {
+        discard;
+    }
+    return in.color;
+}
@@ -0,0 +1,123 @@
+// Fullscreen post-processing passes over the HDR scene texture
+//
+// Inputs and outputs are linear; tonemapping writes to an sRGB format (or an
+// sRGB view) that encodes on write.
+
+struct Params {
+    // Meaning depends on the entry point, see each fs_* below
+    a: vec4<f32>,
+    b: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+
+// Second input for passes that combine two textures
+@group(0) @binding(1)
+var extra: texture_2d<f32>;
+
+@group(0) @binding(2)
+var linear_sampler: sampler;
+
+@group(0) @binding(3)
+var<uniform> params: Params;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // One triangle covering the screen: (-1,-1), (3,-1), (-1,3)
+    let ndc = vec2<f32>(f32((index << 1u) & 2u) * 2.0 - 1.0, f32(index & 2u) * 2.0 - 1.0);
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+    out.uv = vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+    return out;
+}
+
+// Fitted ACES filmic curve (Narkowicz 2015)
+fn aces(x: vec3<f32>) -> vec3<f32> {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// a.x: exposure, a.y: operator (0 ACES, 1 Reinhard, 2 clamp)
+@fragment
+fn fs_tonemap(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = textureSample(source, linear_sampler, in.uv);
+    let color = texel.rgb * params.a.x;
+    var mapped: vec3<f32>;
+    if params.a.y < 0.5 {
+        mapped = aces(color);
+    } else if params.a.y < 1.5 {
+        mapped = color / (vec3<f32>(1.0) + color);
+    } else {
+        mapped = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0));
+    }
+    return vec4<f32>(mapped, texel.a);
+}
+
+// a.x: intensity, a.y: radius where darkening starts, a.z: smoothness,
+// b.rgb: vignette color
+@fragment
+fn fs_vignette(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = textureSample(source, linear_sampler, in.uv);
+    // 0 at the center, 1 at the corners
+    let distance = length(in.uv - vec2<f32>(0.5)) * 1.41421356;
+    let amount = smoothstep(params.a.y, params.a.y + params.a.z, distance) * params.a.x;
+    return vec4<f32>(mix(texel.rgb, params.b.rgb, amount), texel.a);
+}
+
+// Average of 16 source texels around `uv`, using 4 bilinear taps;
+// `texel` is the size of one source texel in UV units
+fn box_4(uv: vec2<f32>, texel: vec2<f32>) -> vec3<f32> {
+    let d = texel.xyxy * vec4<f32>(-1.0, -1.0, 1.0, 1.0);
+    return (textureSample(source, linear_sampler, uv + d.xy).rgb
+        + textureSample(source, linear_sampler, uv + d.zy).rgb
+        + textureSample(source, linear_sampler, uv + d.xw).rgb
+        + textureSample(source, linear_sampler, uv + d.zw).rgb) * 0.25;
+}
+
+// a.xy: source texel size, a.z: threshold, a.w: soft knee
+@fragment
+fn fs_bloom_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = box_4(in.uv, params.a.xy);
+    let brightness = max(color.r, max(color.g, color.b));
+    let knee = params.a.w;
+    var soft = clamp(brightness - params.a.z + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 1e-5);
+    let contribution = max(soft, brightness - params.a.z) / max(brightness, 1e-5);
+    return vec4<f32>(color * contribution, 1.0);
+}
+
+// a.xy: source texel size
+@fragment
+fn fs_bloom_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(box_4(in.uv, params.a.xy), 1.0);
+}
+
+// 3x3 tent filter over the smaller mip, added onto the larger one;
+// a.xy: source texel size, a.z: filter radius in texels
+@fragment
+fn fs_bloom_upsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    let d = vec4<f32>(params.a.xy, -params.a.xy) * params.a.z;
+    var sum = textureSample(source, linear_sampler, in.uv).rgb * 4.0;
+    sum += (textureSample(source, linear_sampler, in.uv + vec2<f32>(d.x, 0.0)).rgb
+        + textureSample(source, linear_sampler, in.uv + vec2<f32>(d.z, 0.0)).rgb
+        + textureSample(source, linear_sampler, in.uv + vec2<f32>(0.0, d.y)).rgb
+        + textureSample(source, linear_sampler, in.uv + vec2<f32>(0.0, d.w)).rgb) * 2.0;
+    sum += textureSample(source, linear_sampler, in.uv + d.xy).rgb
+        + textureSample(source, linear_sampler, in.uv + d.zy).rgb
+        + textureSample(source, linear_sampler, in.uv + d.xw).rgb
+        + textureSample(source, linear_sampler, in.uv + d.zw).rgb;
+    return vec4<f32>(sum / 16.0, 1.0);
+}
+
+// Scene plus the blurred highlights in `extra`; a.x: intensity
+@fragment
+fn fs_bloom_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = textureSample(source, linear_sampler, in.uv);
+    let bloom = textureSample(extra, linear_sampler, in.uv).rgb;
+    return vec4<f32>(texel.rgb + bloom * params.a.x, texel.a);
+}
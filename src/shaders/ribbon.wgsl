@@ -0,0 +1,44 @@
+// Camera-facing ribbons, widened sideways from their center line
+
+// Prefix of the scene globals in default.wgsl
+struct Globals {
+    view_proj: mat4x4<f32>,
+    // xyz: camera position
+    position: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    // Signed distance from the center line to this edge
+    @location(1) offset: f32,
+    @location(2) tangent: vec3<f32>,
+    @location(3) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(vertex: VertexInput) -> VertexOutput {
+    // Sideways is across both the ribbon and the line of sight
+    var side = cross(vertex.tangent, globals.position.xyz - vertex.position);
+    if length(side) < 1e-6 {
+        side = cross(vertex.tangent, vec3<f32>(0.0, 1.0, 0.0));
+    }
+    let world = vertex.position + normalize(side) * vertex.offset;
+
+    var out: VertexOutput;
+    out.clip_position = globals.view_proj * vec4<f32>(world, 1.0);
+    out.color = vertex.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
@@ -0,0 +1,58 @@
+// Skybox drawn behind the scene as a fullscreen triangle
+
+struct Sky {
+    // Inverse of projection * view with the camera translation removed
+    inv_view_proj: mat4x4<f32>,
+    // rgb: color straight up, w: 1 to sample the cubemap instead of the gradient
+    zenith: vec4<f32>,
+    // rgb: color at the horizon
+    horizon: vec4<f32>,
+    // rgb: color straight down
+    ground: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> sky: Sky;
+
+@group(0) @binding(1)
+var sky_texture: texture_cube<f32>;
+
+@group(0) @binding(2)
+var sky_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // One triangle covering the screen: (-1,-1), (3,-1), (-1,3)
+    let ndc = vec2<f32>(f32((index << 1u) & 2u) * 2.0 - 1.0, f32(index & 2u) * 2.0 - 1.0);
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc, 1.0, 1.0);
+    out.ndc = ndc;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Any point along the pixel's ray gives the view direction, since the eye is at the origin
+    let point = sky.inv_view_proj * vec4<f32>(in.ndc, 0.5, 1.0);
+    let direction = normalize(point.xyz / point.w);
+
+    // Sampled outside the branch for uniformity
+    let cubemap = textureSample(sky_texture, sky_sampler, direction).rgb;
+    if sky.zenith.w > 0.5 {
+        return vec4<f32>(cubemap, 1.0);
+    }
+
+    let up = direction.y;
+    var color: vec3<f32>;
+    if up >= 0.0 {
+        color = mix(sky.horizon.rgb, sky.zenith.rgb, sqrt(up));
+    } else {
+        color = mix(sky.horizon.rgb, sky.ground.rgb, sqrt(-up));
+    }
+    return vec4<f32>(color, 1.0);
+}
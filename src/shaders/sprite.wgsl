@@ -0,0 +1,45 @@
+// Screen-space sprites, one instanced quad each
+//
+// Drawn after tonemapping onto the sRGB output: tints are linear, textures are
+// decoded to linear when sampled, and the target encodes on write.
+
+@group(0) @binding(0)
+var sprite_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var sprite_sampler: sampler;
+
+struct SpriteInput {
+    // Corners in NDC: min xy, max xy
+    @location(0) rect: vec4<f32>,
+    // Texture region in UVs: min xy, max xy
+    @location(1) region: vec4<f32>,
+    @location(2) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, sprite: SpriteInput) -> VertexOutput {
+    // Two triangles: (0,0), (1,0), (0,1), (0,1), (1,0), (1,1)
+    let corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 0.0), vec2<f32>(0.0, 1.0),
+        vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 0.0), vec2<f32>(1.0, 1.0),
+    );
+    let corner = corners[index];
+    var out: VertexOutput;
+    // NDC y points up while UV v points down the image
+    out.clip_position = vec4<f32>(mix(sprite.rect.xw, sprite.rect.zy, corner), 0.0, 1.0);
+    out.uv = mix(sprite.region.xy, sprite.region.zw, corner);
+    out.color = sprite.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(sprite_texture, sprite_sampler, in.uv) * in.color;
+}
@@ -0,0 +1,70 @@
+// World-space text, one instanced quad per glyph
+
+// Prefix of the scene globals in default.wgsl
+struct Globals {
+    view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    inverse_view: mat4x4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
+@group(1) @binding(0)
+var atlas: texture_2d<f32>;
+
+@group(1) @binding(1)
+var atlas_sampler: sampler;
+
+struct GlyphInput {
+    // xyz: text origin, w: 1 for text facing the camera
+    @location(0) origin: vec4<f32>,
+    // World-space text x and y axes, scale included
+    @location(1) right: vec4<f32>,
+    @location(2) up: vec4<f32>,
+    // Glyph corners along those axes: min xy, max xy
+    @location(3) rect: vec4<f32>,
+    // Atlas region in UVs: min xy, max xy
+    @location(4) region: vec4<f32>,
+    @location(5) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, glyph: GlyphInput) -> VertexOutput {
+    // Two triangles: (0,0), (1,0), (0,1), (0,1), (1,0), (1,1)
+    let corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 0.0), vec2<f32>(0.0, 1.0),
+        vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 0.0), vec2<f32>(1.0, 1.0),
+    );
+    let corner = corners[index];
+    var right = glyph.right.xyz;
+    var up = glyph.up.xyz;
+    if glyph.origin.w > 0.5 {
+        // The camera's own axes, keeping the text's scale
+        right = normalize(globals.inverse_view[0].xyz) * length(right);
+        up = normalize(globals.inverse_view[1].xyz) * length(up);
+    }
+    let local = mix(glyph.rect.xy, glyph.rect.zw, corner);
+    let world = glyph.origin.xyz + right * local.x + up * local.y;
+
+    var out: VertexOutput;
+    out.clip_position = globals.view_proj * vec4<f32>(world, 1.0);
+    // Text y points up while UV v points down the atlas
+    out.uv = mix(glyph.region.xw, glyph.region.zy, corner);
+    out.color = glyph.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coverage = textureSample(atlas, atlas_sampler, in.uv).a;
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
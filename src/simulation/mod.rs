@@ -0,0 +1,341 @@
+//! Running update systems on their own thread
+//!
+//! With `App::with_simulation_thread`, update systems tick at a fixed rate on
+//! a dedicated thread while the main thread keeps rendering at the display
+//! rate. After every tick the simulation copies what rendering needs into a
+//! snapshot world, and the main thread draws the newest snapshot, so a slow
+//! tick never holds up a frame or the camera.
+//!
+//! ```rust,no_run
+//! use qsi::prelude::*;
+//!
+//! # fn heavy_physics(_: &mut World, _: &InputState, _: &TimeState) {}
+//! App::new()
+//!     .with_simulation_thread(30.0)
+//!     .add_system(heavy_physics)
+//!     .run()
+//!     .unwrap();
+//! ```
+//!
+//! Update systems and their run conditions must therefore be `Send`. Render
+//! systems still run on the main thread, against the simulation world between
+//! ticks, and may hold non-`Send` state. Components only rendering needs, such as custom materials,
+//! must be added with `App::extract_component` to appear in snapshots.
+
+use crate::assets::{Assets, Handle};
+use crate::camera::{AutoOrbit, Camera, CameraPresets};
+use crate::ecs::{Component, World};
+use crate::frame_export::FrameRecorder;
+use crate::graphics::{
+    DebugDraw, DirectionalLight, Exposure, Instances, Material, Mesh, PointCloud, PointLight,
+    PostEffects, Ribbon, SpotLight, Sprite, Visibility,
+};
+use crate::input::InputState;
+use crate::math::Transform;
+use crate::schedule::Schedule;
+use crate::time::{Paused, TimeState};
+use crate::{AppExit, tasks};
+use std::any::TypeId;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+type ExtractFn = fn(&World, &mut World);
+
+/// Components and resources copied from the simulation world into each
+/// render snapshot
+///
+/// `Extractor::new` covers everything the built-in renderer draws; add
+/// components read by custom materials or render systems on top.
+#[derive(Clone)]
+pub struct Extractor {
+    components: Vec<(TypeId, ExtractFn)>,
+    resources: Vec<ExtractFn>,
+}
+
+impl Default for Extractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Extractor {
+    /// Create an extractor with the built-in render components
+    pub fn new() -> Self {
+        let mut extractor = Self::empty();
+        extractor
+            .component::<Transform>()
+            .component::<Camera>()
+            .component::<Mesh>()
+            .component::<Handle<Mesh>>()
+            .component::<Material>()
+            .component::<Instances>()
+            .component::<DirectionalLight>()
+            .component::<PointLight>()
+            .component::<SpotLight>()
+            .component::<Sprite>()
+            .component::<Ribbon>()
+            .component::<PointCloud>()
+            .component::<Visibility>()
+            .resource::<Assets<Mesh>>()
+            .resource::<DebugDraw>()
+            .resource::<Exposure>();
+        #[cfg(feature = "text")]
+        extractor
+            .component::<crate::graphics::Text>()
+            .component::<crate::graphics::Text3d>();
+        extractor
+    }
+
+    /// Create an extractor that copies nothing
+    pub fn empty() -> Self {
+        Self {
+            components: Vec::new(),
+            resources: Vec::new(),
+        }
+    }
+
+    /// Copy every `T` component into snapshots
+    pub fn component<T: Component + Clone>(&mut self) -> &mut Self {
+        if self
+            .components
+            .iter()
+            .all(|(id, _)| *id != TypeId::of::<T>())
+        {
+            self.components.push((TypeId::of::<T>(), |source, target| {
+                for (entity, component) in source.query::<T>() {
+                    target.add_component(entity, component.clone());
+                }
+            }));
+        }
+        self
+    }
+
+    /// Copy the `T` resource into snapshots
+    pub fn resource<T: Clone + Send + Sync + 'static>(&mut self) -> &mut Self {
+        self.resources.push(|source, target| {
+            if let Some(resource) = source.resource::<T>() {
+                target.insert_resource(resource.clone());
+            }
+        });
+        self
+    }
+
+    /// Snapshot of `world` holding its entities and the extracted data
+    pub fn extract(&self, world: &World) -> World {
+        let mut snapshot = world.with_same_entities();
+        for (_, extract) in &self.components {
+            extract(world, &mut snapshot);
+        }
+        for extract in &self.resources {
+            extract(world, &mut snapshot);
+        }
+        snapshot
+    }
+
+    /// Component types replaced by each snapshot
+    pub(crate) fn component_types(&self) -> Vec<TypeId> {
+        self.components.iter().map(|(id, _)| *id).collect()
+    }
+}
+
+/// State shared between the main thread and the simulation thread
+struct Shared {
+    world: Mutex<World>,
+    /// Input gathered by the main thread since the last tick
+    input: Mutex<InputState>,
+    /// Newest snapshot the main thread has not taken yet
+    snapshot: Mutex<Option<World>>,
+    running: AtomicBool,
+    exit_requested: AtomicBool,
+    /// The main thread missed the world and wants it before the next tick
+    main_waiting: AtomicBool,
+}
+
+/// Handle to the thread running update systems
+pub(crate) struct SimulationThread {
+    shared: Arc<Shared>,
+    handle: Option<JoinHandle<Schedule>>,
+    types: Vec<TypeId>,
+}
+
+impl SimulationThread {
+    /// Move `world` and the update systems of `schedule` onto a new thread
+    /// ticking `tick_rate` times per second
+    pub(crate) fn spawn(
+        world: World,
+        schedule: Schedule,
+        extractor: Extractor,
+        tick_rate: f64,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            world: Mutex::new(world),
+            input: Mutex::new(InputState::new()),
+            snapshot: Mutex::new(None),
+            running: AtomicBool::new(true),
+            exit_requested: AtomicBool::new(false),
+            main_waiting: AtomicBool::new(false),
+        });
+        let types = extractor.component_types();
+        let period = Duration::from_secs_f64(1.0 / tick_rate.max(1e-3));
+        let thread_shared = shared.clone();
+        let handle = std::thread::Builder::new()
+            .name("qsi-simulation".to_string())
+            .spawn(move || run(&thread_shared, schedule, &extractor, period))
+            .expect("Failed to spawn simulation thread");
+        Self {
+            shared,
+            handle: Some(handle),
+            types,
+        }
+    }
+
+    /// Add one frame of input for the next tick
+    pub(crate) fn send_input(&self, frame: &InputState) {
+        lock(&self.shared.input).accumulate(frame);
+    }
+
+    /// Move the newest snapshot into `render_world`, if a tick finished since
+    /// the last call
+    pub(crate) fn update_render_world(&self, render_world: &mut World) {
+        if let Some(snapshot) = lock(&self.shared.snapshot).take() {
+            render_world.absorb(snapshot, &self.types);
+        }
+    }
+
+    /// The simulation world, unless a tick is running; the simulation then
+    /// waits for the main thread after that tick
+    pub(crate) fn try_world(&self) -> Option<MutexGuard<'_, World>> {
+        match self.shared.world.try_lock() {
+            Ok(world) => Some(world),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => {
+                self.shared.main_waiting.store(true, Ordering::Release);
+                None
+            }
+        }
+    }
+
+    /// Let the simulation continue after `try_world`
+    pub(crate) fn release_world(&self, world: MutexGuard<'_, World>) {
+        drop(world);
+        self.shared.main_waiting.store(false, Ordering::Release);
+        if let Some(handle) = &self.handle {
+            handle.thread().unpark();
+        }
+    }
+
+    /// True once an update system inserted `AppExit` or the thread died
+    pub(crate) fn exit_requested(&self) -> bool {
+        self.shared.exit_requested.load(Ordering::Acquire)
+            || self.handle.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    /// Stop after the current tick and hand back the world and update systems
+    pub(crate) fn join(mut self) -> Option<(World, Schedule)> {
+        self.shared.running.store(false, Ordering::Release);
+        let handle = self.handle.take()?;
+        handle.thread().unpark();
+        let schedule = match handle.join() {
+            Ok(schedule) => schedule,
+            Err(_) => {
+                log::error!("Simulation thread panicked");
+                return None;
+            }
+        };
+        let shared = Arc::into_inner(self.shared)?;
+        let world = shared
+            .world
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        Some((world, schedule))
+    }
+}
+
+/// Tick loop of the simulation thread
+fn run(
+    shared: &Shared,
+    mut schedule: Schedule,
+    extractor: &Extractor,
+    period: Duration,
+) -> Schedule {
+    let mut time = TimeState::new();
+    let mut next_tick = Instant::now();
+    while shared.running.load(Ordering::Acquire) {
+        {
+            let mut world = lock(&shared.world);
+            let input = {
+                let mut pending = lock(&shared.input);
+                let input = pending.clone();
+                pending.update();
+                input
+            };
+            time.set_paused(world.has_resource::<Paused>());
+            time.update();
+            world.update_events();
+            tasks::apply_completed(&mut world);
+            schedule.run(&mut world, &input, &time);
+
+            let snapshot = extractor.extract(&world);
+            *lock(&shared.snapshot) = Some(snapshot);
+            if let Some(debug) = world.resource_mut::<DebugDraw>() {
+                debug.clear();
+            }
+            if world.has_resource::<AppExit>() {
+                shared.exit_requested.store(true, Ordering::Release);
+            }
+        }
+
+        // Give the main thread its turn with the world before ticking again
+        while shared.main_waiting.load(Ordering::Acquire) && shared.running.load(Ordering::Acquire)
+        {
+            std::thread::park_timeout(Duration::from_millis(1));
+        }
+
+        next_tick += period;
+        let now = Instant::now();
+        if next_tick < now {
+            // Running behind: start the next tick now instead of catching up
+            next_tick = now;
+        }
+        while shared.running.load(Ordering::Acquire) {
+            let now = Instant::now();
+            if now >= next_tick {
+                break;
+            }
+            std::thread::park_timeout(next_tick - now);
+        }
+    }
+    schedule
+}
+
+/// Move what belongs to the main thread from `from` to `to`: camera presets,
+/// the idle orbit, frame recording, and per-camera post stacks, which hold GPU
+/// state and cannot be copied each tick
+pub(crate) fn hand_over(from: &mut World, to: &mut World) {
+    move_resource::<CameraPresets>(from, to);
+    move_resource::<AutoOrbit>(from, to);
+    move_resource::<FrameRecorder>(from, to);
+    let stacks: Vec<_> = from
+        .query::<PostEffects>()
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in stacks {
+        if let Some(post) = from.remove_component::<PostEffects>(entity) {
+            to.add_component(entity, post);
+        }
+    }
+}
+
+fn move_resource<T: Send + Sync + 'static>(from: &mut World, to: &mut World) {
+    if let Some(resource) = from.remove_resource::<T>() {
+        to.insert_resource(resource);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
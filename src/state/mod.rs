@@ -0,0 +1,134 @@
+//! App states with state-gated systems and enter/exit hooks
+//!
+//! ```rust,no_run
+//! use qsi::prelude::*;
+//! use qsi::state::State;
+//!
+//! #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+//! enum Mode {
+//!     Running,
+//!     Paused,
+//! }
+//!
+//! fn toggle_pause(world: &mut World, input: &InputState, _time: &TimeState) {
+//!     if input.key_just_pressed(KeyCode::KeyP) {
+//!         let state = world.resource_mut::<State<Mode>>().unwrap();
+//!         let next = match state.get() {
+//!             Mode::Running => Mode::Paused,
+//!             Mode::Paused => Mode::Running,
+//!         };
+//!         state.set(next);
+//!     }
+//! }
+//!
+//! # fn simulate(_: &mut World, _: &InputState, _: &TimeState) {}
+//! # fn show_pause_menu(_: &mut World, _: &InputState, _: &TimeState) {}
+//! App::new()
+//!     .add_state(Mode::Running)
+//!     .add_system(toggle_pause)
+//!     .add_system_in_state(Mode::Running, simulate)
+//!     .add_on_enter(Mode::Paused, show_pause_menu)
+//!     .run()
+//!     .unwrap();
+//! ```
+
+use crate::ecs::World;
+use std::any::Any;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// Types usable as app states, typically fieldless enums
+pub trait States: Copy + Eq + Hash + Debug + Send + Sync + 'static {}
+
+impl<T: Copy + Eq + Hash + Debug + Send + Sync + 'static> States for T {}
+
+/// Resource holding the current state of type `S`
+///
+/// Changes requested with `set` take effect at the start of the next frame,
+/// before any update system runs.
+#[derive(Debug)]
+pub struct State<S: States> {
+    current: S,
+    next: Option<S>,
+    entered: bool,
+}
+
+impl<S: States> State<S> {
+    pub fn new(initial: S) -> Self {
+        Self {
+            current: initial,
+            next: None,
+            entered: false,
+        }
+    }
+
+    /// Get the current state
+    pub fn get(&self) -> S {
+        self.current
+    }
+
+    /// Request a transition to `next`
+    pub fn set(&mut self, next: S) {
+        self.next = Some(next);
+    }
+
+    /// Get the pending transition, if any
+    pub fn pending(&self) -> Option<S> {
+        self.next
+    }
+}
+
+/// Run condition that passes while `State<S>` equals `state`
+pub fn in_state<S: States>(state: S) -> impl Fn(&World) -> bool + Send + 'static {
+    move |world| {
+        world
+            .resource::<State<S>>()
+            .is_some_and(|current| current.current == state)
+    }
+}
+
+/// A state change: the exited state (none on the first frame) and the entered one
+pub(crate) type Transition = (Option<Box<dyn Any>>, Box<dyn Any>);
+
+/// Applies the pending transition of one state type
+pub(crate) type TransitionDriver = Box<dyn Fn(&mut World) -> Option<Transition> + Send>;
+
+pub(crate) fn transition_driver<S: States>() -> TransitionDriver {
+    Box::new(|world| {
+        let state = world.resource_mut::<State<S>>()?;
+        if !state.entered {
+            state.entered = true;
+            state.next = None;
+            return Some((None, Box::new(state.current)));
+        }
+        let next = state.next.take().filter(|&next| next != state.current)?;
+        let previous = std::mem::replace(&mut state.current, next);
+        Some((Some(Box::new(previous)), Box::new(next)))
+    })
+}
+
+/// Whether a hook runs when its state is entered or exited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HookKind {
+    Enter,
+    Exit,
+}
+
+type StateMatcher = Box<dyn Fn(&dyn Any) -> bool + Send>;
+
+/// A system run when a specific state value is entered or exited
+pub(crate) struct StateHook {
+    pub(crate) kind: HookKind,
+    pub(crate) matches: StateMatcher,
+    pub(crate) system: crate::UpdateSystem,
+}
+
+impl StateHook {
+    pub(crate) fn new<S: States>(kind: HookKind, state: S, system: crate::UpdateSystem) -> Self {
+        Self {
+            kind,
+            matches: Box::new(move |value| value.downcast_ref::<S>() == Some(&state)),
+            system,
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Chunked world streaming around the camera
+//!
+//! The world is split into square chunks on the XZ plane. Chunks within
+//! `load_radius` of the camera are generated on the `TaskPool` by a user
+//! loader, uploaded to the GPU a few at a time, and despawned again once the
+//! camera moves beyond `unload_radius`.
+//!
+//! ```rust,no_run
+//! use qsi::prelude::*;
+//! use qsi::streaming::{ChunkData, ChunkStreamer};
+//!
+//! App::new()
+//!     .insert_resource(ChunkStreamer::new(32.0, 3, |coord| {
+//!         // Build terrain for `coord` here (runs on a worker thread)
+//!         ChunkData::default()
+//!     }))
+//!     .run()
+//!     .unwrap();
+//! ```
+
+use crate::ecs::{Component, EntityId, World};
+use crate::graphics::{Mesh, Renderer, Vertex};
+use crate::math::{Point3, Transform, Vector3};
+use crate::tasks::TaskPool;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+/// Integer chunk coordinate on the XZ plane
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChunkCoord {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkCoord {
+    /// Create a chunk coordinate
+    pub fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    /// Get the chunk containing a world position
+    pub fn from_position(position: Point3<f32>, chunk_size: f32) -> Self {
+        Self {
+            x: (position.x / chunk_size).floor() as i32,
+            z: (position.z / chunk_size).floor() as i32,
+        }
+    }
+
+    /// World-space position of the chunk's minimum corner
+    pub fn origin(&self, chunk_size: f32) -> Vector3<f32> {
+        Vector3::new(self.x as f32 * chunk_size, 0.0, self.z as f32 * chunk_size)
+    }
+
+    /// Chebyshev distance in chunks
+    pub fn distance(&self, other: ChunkCoord) -> u32 {
+        (self.x - other.x)
+            .unsigned_abs()
+            .max((self.z - other.z).unsigned_abs())
+    }
+}
+
+/// CPU-side geometry for one mesh in a chunk
+#[derive(Debug, Clone)]
+pub struct ChunkMesh {
+    pub transform: Transform,
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u16>,
+    pub topology: wgpu::PrimitiveTopology,
+}
+
+/// Everything a loader produces for one chunk
+#[derive(Debug, Clone, Default)]
+pub struct ChunkData {
+    pub meshes: Vec<ChunkMesh>,
+}
+
+/// Marker component on entities owned by a streamed chunk
+#[derive(Debug, Clone, Copy)]
+pub struct Chunk {
+    pub coord: ChunkCoord,
+}
+
+impl Component for Chunk {}
+
+type ChunkLoader = Arc<dyn Fn(ChunkCoord) -> ChunkData + Send + Sync>;
+
+/// Resource that streams chunks in and out around the camera
+pub struct ChunkStreamer {
+    /// Edge length of a chunk in world units
+    pub chunk_size: f32,
+    /// Chunks within this many chunks of the camera are loaded
+    pub load_radius: u32,
+    /// Chunks further than this are unloaded (kept above `load_radius` to avoid thrashing)
+    pub unload_radius: u32,
+    /// Maximum chunks uploaded to the GPU per frame
+    pub max_uploads_per_frame: usize,
+    loader: ChunkLoader,
+    loaded: HashMap<ChunkCoord, Vec<EntityId>>,
+    in_flight: HashSet<ChunkCoord>,
+    ready: VecDeque<(ChunkCoord, ChunkData)>,
+}
+
+impl ChunkStreamer {
+    /// Create a streamer with the given chunk size, load radius and loader
+    pub fn new<F>(chunk_size: f32, load_radius: u32, loader: F) -> Self
+    where
+        F: Fn(ChunkCoord) -> ChunkData + Send + Sync + 'static,
+    {
+        Self {
+            chunk_size,
+            load_radius,
+            unload_radius: load_radius + 1,
+            max_uploads_per_frame: 2,
+            loader: Arc::new(loader),
+            loaded: HashMap::new(),
+            in_flight: HashSet::new(),
+            ready: VecDeque::new(),
+        }
+    }
+
+    /// Check if a chunk is currently loaded
+    pub fn is_loaded(&self, coord: ChunkCoord) -> bool {
+        self.loaded.contains_key(&coord)
+    }
+
+    /// Number of loaded chunks
+    pub fn loaded_count(&self) -> usize {
+        self.loaded.len()
+    }
+
+    /// Number of chunks being generated or waiting for upload
+    pub fn pending_count(&self) -> usize {
+        self.in_flight.len()
+    }
+}
+
+/// Stream chunks around `center`; called by `App` once per frame
+pub fn update_streaming(world: &mut World, renderer: &Renderer, center: Point3<f32>) {
+    let Some(mut streamer) = world.remove_resource::<ChunkStreamer>() else {
+        return;
+    };
+
+    let center_chunk = ChunkCoord::from_position(center, streamer.chunk_size);
+
+    // Unload chunks that drifted out of range
+    let unload_radius = streamer.unload_radius.max(streamer.load_radius);
+    let far: Vec<ChunkCoord> = streamer
+        .loaded
+        .keys()
+        .filter(|coord| coord.distance(center_chunk) > unload_radius)
+        .copied()
+        .collect();
+    for coord in far {
+        for entity in streamer.loaded.remove(&coord).unwrap_or_default() {
+            world.despawn(entity);
+        }
+    }
+
+    // Request chunks that came into range
+    let radius = streamer.load_radius as i32;
+    for dz in -radius..=radius {
+        for dx in -radius..=radius {
+            let coord = ChunkCoord::new(center_chunk.x + dx, center_chunk.z + dz);
+            if streamer.loaded.contains_key(&coord) || streamer.in_flight.contains(&coord) {
+                continue;
+            }
+            let Some(pool) = world.resource::<TaskPool>() else {
+                continue;
+            };
+            let loader = streamer.loader.clone();
+            pool.spawn(
+                move || loader(coord),
+                move |world, data| {
+                    if let Some(streamer) = world.resource_mut::<ChunkStreamer>() {
+                        streamer.ready.push_back((coord, data));
+                    }
+                },
+            );
+            streamer.in_flight.insert(coord);
+        }
+    }
+
+    // Upload a bounded number of generated chunks
+    for _ in 0..streamer.max_uploads_per_frame.max(1) {
+        let Some((coord, data)) = streamer.ready.pop_front() else {
+            break;
+        };
+        streamer.in_flight.remove(&coord);
+        if coord.distance(center_chunk) > unload_radius {
+            continue;
+        }
+
+        let entities = data
+            .meshes
+            .into_iter()
+            .map(|chunk_mesh| {
+                let mesh = Mesh::new_with_topology(
+                    renderer.device(),
+                    &chunk_mesh.vertices,
+                    &chunk_mesh.indices,
+                    chunk_mesh.topology,
+                );
+                world
+                    .spawn()
+                    .with(chunk_mesh.transform)
+                    .with(mesh)
+                    .with(Chunk { coord })
+                    .build()
+            })
+            .collect();
+        streamer.loaded.insert(coord, entities);
+    }
+
+    world.insert_resource(streamer);
+}
@@ -0,0 +1,164 @@
+//! Background task pool for work that should not stall the frame
+//!
+//! Jobs run on worker threads; their results are handed back to the `World`
+//! at a sync point at the start of each frame, before update systems run.
+//!
+//! ```rust,no_run
+//! use qsi::prelude::*;
+//! use qsi::tasks::TaskPool;
+//!
+//! struct PathFound(Vec<Vector3<f32>>);
+//! impl Event for PathFound {}
+//!
+//! fn request_path(world: &mut World, _input: &InputState, _time: &TimeState) {
+//!     if let Some(pool) = world.resource::<TaskPool>() {
+//!         pool.spawn_event(|| PathFound(Vec::new()));
+//!     }
+//! }
+//! ```
+
+use crate::ecs::{Event, World};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+type Job = Box<dyn FnOnce() + Send>;
+type Completion = Box<dyn FnOnce(&mut World) + Send>;
+
+/// Worker thread pool available to systems as a `World` resource
+pub struct TaskPool {
+    job_sender: Option<Sender<Job>>,
+    completion_sender: Sender<Completion>,
+    completions: Mutex<Receiver<Completion>>,
+    pending: Arc<AtomicUsize>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl TaskPool {
+    /// Create a pool with the given number of worker threads (at least one)
+    pub fn new(threads: usize) -> Self {
+        let (job_sender, job_receiver) = channel::<Job>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let (completion_sender, completions) = channel();
+        let pending = Arc::new(AtomicUsize::new(0));
+
+        let workers = (0..threads.max(1))
+            .map(|index| {
+                let job_receiver = job_receiver.clone();
+                let pending = pending.clone();
+                std::thread::Builder::new()
+                    .name(format!("qsi-worker-{index}"))
+                    .spawn(move || {
+                        loop {
+                            // Hold the lock only while waiting for the next job
+                            let job = job_receiver.lock().ok().and_then(|rx| rx.recv().ok());
+                            let Some(job) = job else {
+                                break;
+                            };
+                            // A panicking job never sends its completion, so
+                            // uncount it here and keep the worker alive
+                            if catch_unwind(AssertUnwindSafe(job)).is_err() {
+                                pending.fetch_sub(1, Ordering::SeqCst);
+                                log::error!("Task panicked; its result was dropped");
+                            }
+                        }
+                    })
+                    .expect("Failed to spawn worker thread")
+            })
+            .collect();
+
+        Self {
+            job_sender: Some(job_sender),
+            completion_sender,
+            completions: Mutex::new(completions),
+            pending,
+            workers,
+        }
+    }
+
+    /// Run `job` on a worker and pass its result to `on_complete` at the next sync point
+    pub fn spawn<T, F, C>(&self, job: F, on_complete: C)
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+        C: FnOnce(&mut World, T) + Send + 'static,
+    {
+        let completion_sender = self.completion_sender.clone();
+        let pending = self.pending.clone();
+        pending.fetch_add(1, Ordering::SeqCst);
+
+        let job: Job = Box::new(move || {
+            let result = job();
+            let completion: Completion = Box::new(move |world| on_complete(world, result));
+            if completion_sender.send(completion).is_err() {
+                pending.fetch_sub(1, Ordering::SeqCst);
+            }
+        });
+
+        if let Some(sender) = &self.job_sender
+            && sender.send(job).is_err()
+        {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            log::error!("Task pool is shut down; dropping job");
+        }
+    }
+
+    /// Run `job` on a worker and send its result as an event at the next sync point
+    pub fn spawn_event<E, F>(&self, job: F)
+    where
+        E: Event,
+        F: FnOnce() -> E + Send + 'static,
+    {
+        self.spawn(job, |world, event| world.send(event));
+    }
+
+    /// Number of jobs spawned but not yet applied to the world
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    /// Number of worker threads
+    pub fn thread_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    fn take_completed(&self) -> Vec<Completion> {
+        let Ok(completions) = self.completions.lock() else {
+            return Vec::new();
+        };
+        let completed: Vec<Completion> = completions.try_iter().collect();
+        self.pending.fetch_sub(completed.len(), Ordering::SeqCst);
+        completed
+    }
+}
+
+impl Default for TaskPool {
+    /// Create a pool with one thread per core, leaving one for the main thread
+    fn default() -> Self {
+        let cores = std::thread::available_parallelism().map_or(2, |n| n.get());
+        Self::new(cores.saturating_sub(1))
+    }
+}
+
+impl Drop for TaskPool {
+    fn drop(&mut self) {
+        // Closing the job channel lets workers exit once the queue drains
+        self.job_sender.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Apply results of finished jobs to the world (the per-frame sync point)
+pub fn apply_completed(world: &mut World) {
+    let completed = match world.resource::<TaskPool>() {
+        Some(pool) => pool.take_completed(),
+        None => return,
+    };
+    for completion in completed {
+        completion(world);
+    }
+}
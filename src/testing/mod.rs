@@ -0,0 +1,255 @@
+//! Randomized scenes for benchmarks and stress tests
+//!
+//! `spawn_stress_scene` fills a box with cubes and spheres of random size,
+//! color, and velocity. All lengths are in meters and speeds in meters per
+//! second, so scenes from different settings stay comparable. Add
+//! `stress_motion_system` to keep the objects moving and bouncing off the box.
+//!
+//! ```rust,no_run
+//! use qsi::prelude::*;
+//! use qsi::testing::{spawn_stress_scene, stress_motion_system};
+//!
+//! App::new()
+//!     .add_startup_system(|world: &mut World, renderer: &mut Renderer| {
+//!         spawn_stress_scene(world, renderer, 10_000);
+//!     })
+//!     .add_system(stress_motion_system)
+//!     .run()
+//!     .unwrap();
+//! ```
+
+use crate::ecs::{Component, EntityId, World};
+use crate::graphics::{Material, Mesh, MeshSource, Renderer, ShadingMode, Vertex};
+use crate::input::InputState;
+use crate::math::{Transform, Vector3, Velocity};
+use crate::time::TimeState;
+use cgmath::InnerSpace;
+use std::f32::consts::{PI, TAU};
+
+/// Settings for `spawn_stress_scene_with`
+#[derive(Debug, Clone, PartialEq)]
+pub struct StressScene {
+    /// Half the edge length of the box objects spawn and bounce in, in meters
+    pub half_extent: f32,
+    /// Smallest and largest object size (cube edge or sphere diameter), in meters
+    pub size_range: (f32, f32),
+    /// Fastest initial speed, in meters per second
+    pub max_speed: f32,
+    /// Fastest initial spin, in radians per second
+    pub max_spin: f32,
+    /// Share of spheres, 0–1; the rest are cubes
+    pub sphere_fraction: f32,
+    /// Share of alpha-blended objects, 0–1
+    pub transparent_fraction: f32,
+    /// Same seed, same scene
+    pub seed: u32,
+}
+
+impl Default for StressScene {
+    fn default() -> Self {
+        Self {
+            half_extent: 20.0,
+            size_range: (0.2, 1.0),
+            max_speed: 2.0,
+            max_spin: 1.0,
+            sphere_fraction: 0.5,
+            transparent_fraction: 0.1,
+            seed: 1,
+        }
+    }
+}
+
+impl StressScene {
+    /// Set the box half extent in meters
+    pub fn with_half_extent(mut self, meters: f32) -> Self {
+        self.half_extent = meters;
+        self
+    }
+
+    /// Set the object size range in meters
+    pub fn with_size_range(mut self, min: f32, max: f32) -> Self {
+        self.size_range = (min, max);
+        self
+    }
+
+    /// Set the fastest initial speed in meters per second
+    pub fn with_max_speed(mut self, meters_per_second: f32) -> Self {
+        self.max_speed = meters_per_second;
+        self
+    }
+
+    /// Set the random seed
+    pub fn with_seed(mut self, seed: u32) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// Object spawned by a stress scene, kept inside its box by `stress_motion_system`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StressBody {
+    /// Half the edge length of the box, in meters
+    pub half_extent: f32,
+    /// Radius of the object's bounds, in meters
+    pub radius: f32,
+}
+
+impl Component for StressBody {}
+
+/// Spawn `count` random cubes and spheres with the default `StressScene`
+pub fn spawn_stress_scene(world: &mut World, renderer: &Renderer, count: usize) -> Vec<EntityId> {
+    spawn_stress_scene_with(world, renderer, count, &StressScene::default())
+}
+
+/// Spawn `count` random cubes and spheres with `Transform`, `Mesh`,
+/// `Material`, `Velocity`, and `StressBody` components; all share two meshes
+pub fn spawn_stress_scene_with(
+    world: &mut World,
+    renderer: &Renderer,
+    count: usize,
+    scene: &StressScene,
+) -> Vec<EntityId> {
+    let cube = MeshSource::Cube {
+        size: 1.0,
+        color: [1.0; 3],
+    }
+    .build(renderer.device());
+    let sphere = unit_sphere(renderer, 16, 12);
+    let mut random = Random::new(scene.seed);
+    let (min_size, max_size) = scene.size_range;
+    let extent = scene.half_extent.max(0.0);
+
+    (0..count)
+        .map(|_| {
+            let is_sphere = random.next() < scene.sphere_fraction;
+            let size = min_size + (max_size - min_size) * random.next();
+            let mut transform = Transform::at_position(random.vector(extent));
+            transform.scale = Vector3::new(size, size, size);
+            transform.rotation = random.vector(PI);
+
+            let color = [
+                0.2 + 0.8 * random.next(),
+                0.2 + 0.8 * random.next(),
+                0.2 + 0.8 * random.next(),
+            ];
+            let mut material = Material::new(color);
+            if is_sphere {
+                material = material.with_shading(ShadingMode::Shaded);
+            }
+            if random.next() < scene.transparent_fraction {
+                material = material.with_alpha(0.5).with_transparent(true);
+            }
+
+            let direction = random.vector(1.0);
+            let speed = scene.max_speed * random.next();
+            let velocity = Velocity {
+                linear: if direction.magnitude2() > 1e-6 {
+                    direction.normalize_to(speed)
+                } else {
+                    direction
+                },
+                angular: random.vector(scene.max_spin),
+            };
+            // A cube's corners reach √3/2 of its edge from the center
+            let radius = if is_sphere { 0.5 } else { 0.866 } * size;
+            let mesh = if is_sphere { &sphere } else { &cube };
+
+            world
+                .spawn()
+                .with(transform)
+                .with(mesh.clone())
+                .with(material)
+                .with(velocity)
+                .with(StressBody {
+                    half_extent: extent,
+                    radius,
+                })
+                .id()
+        })
+        .collect()
+}
+
+/// Move and spin every `StressBody` by its `Velocity`, bouncing off its box
+pub fn stress_motion_system(world: &mut World, _input: &InputState, time: &TimeState) {
+    let dt = time.delta_seconds();
+    let bodies: Vec<_> = world
+        .query::<StressBody>()
+        .map(|(entity, body)| (entity, *body))
+        .collect();
+    for (entity, body) in bodies {
+        let Some(mut velocity) = world.get_component::<Velocity>(entity).cloned() else {
+            continue;
+        };
+        let Some(transform) = world.get_component_mut::<Transform>(entity) else {
+            continue;
+        };
+        transform.position += velocity.linear * dt;
+        transform.rotation += velocity.angular * dt;
+        let limit = (body.half_extent - body.radius).max(0.0);
+        for axis in 0..3 {
+            if transform.position[axis].abs() > limit {
+                transform.position[axis] = transform.position[axis].clamp(-limit, limit);
+                velocity.linear[axis] = -velocity.linear[axis];
+            }
+        }
+        world.add_component(entity, velocity);
+    }
+}
+
+/// UV sphere of diameter 1 with smooth normals, white vertices
+fn unit_sphere(renderer: &Renderer, segments: u16, rings: u16) -> Mesh {
+    let mut vertices = Vec::new();
+    for ring in 0..=rings {
+        let polar = PI * ring as f32 / rings as f32;
+        for segment in 0..=segments {
+            let azimuth = TAU * segment as f32 / segments as f32;
+            let normal = [
+                polar.sin() * azimuth.cos(),
+                polar.cos(),
+                polar.sin() * azimuth.sin(),
+            ];
+            vertices.push(
+                Vertex::new(normal.map(|n| n * 0.5), [1.0; 3])
+                    .with_normal(normal)
+                    .with_uv([segment as f32 / segments as f32, ring as f32 / rings as f32]),
+            );
+        }
+    }
+    let mut indices = Vec::new();
+    let stride = segments + 1;
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let a = ring * stride + segment;
+            let b = a + stride;
+            indices.extend_from_slice(&[a, a + 1, b, a + 1, b + 1, b]);
+        }
+    }
+    renderer.create_mesh(&vertices, &indices)
+}
+
+/// Small xorshift generator, so scenes need no extra dependency
+struct Random(u32);
+
+impl Random {
+    fn new(seed: u32) -> Self {
+        // Zero would stay zero forever
+        Self(seed.wrapping_mul(0x9e37_79b9) | 1)
+    }
+
+    /// Uniform value in 0..1
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    /// Vector with each component uniform in -extent..extent
+    fn vector(&mut self, extent: f32) -> Vector3<f32> {
+        Vector3::new(
+            (self.next() * 2.0 - 1.0) * extent,
+            (self.next() * 2.0 - 1.0) * extent,
+            (self.next() * 2.0 - 1.0) * extent,
+        )
+    }
+}
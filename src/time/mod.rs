@@ -18,8 +18,19 @@ pub struct TimeState {
     frame_time_history: Vec<Duration>,
     /// Maximum number of frames to keep in history
     max_history: usize,
+    /// Step used instead of the measured frame time, if set
+    fixed_delta: Option<Duration>,
+    /// Whether the clock is stopped
+    paused: bool,
 }
 
+/// Resource that stops the app clock while present
+///
+/// Frames keep running, but `TimeState::delta` is zero and elapsed time
+/// stands still, so anything driven by the clock holds its state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Paused;
+
 impl TimeState {
     /// Create a new time state
     pub fn new() -> Self {
@@ -32,14 +43,25 @@ impl TimeState {
             frame_count: 0,
             frame_time_history: Vec::new(),
             max_history: 60, // Keep 60 frames of history for smooth FPS
+            fixed_delta: None,
+            paused: false,
         }
     }
 
     /// Update the time state - call this once per frame
     pub fn update(&mut self) {
         let now = Instant::now();
-        self.delta_time = now.duration_since(self.last_frame_time);
-        self.elapsed_time = now.duration_since(self.startup_time);
+        match self.fixed_delta {
+            _ if self.paused => self.delta_time = Duration::ZERO,
+            Some(step) => {
+                self.delta_time = step;
+                self.elapsed_time += step;
+            }
+            None => {
+                self.delta_time = now.duration_since(self.last_frame_time);
+                self.elapsed_time = now.duration_since(self.startup_time);
+            }
+        }
         self.last_frame_time = now;
         self.frame_count += 1;
 
@@ -112,6 +134,52 @@ impl TimeState {
         self.frame_time_history.clear();
     }
 
+    /// Continue counting from `elapsed` and `frame_count`, e.g. when resuming
+    /// a saved session
+    pub fn resume_at(&mut self, elapsed: Duration, frame_count: u64) {
+        let now = Instant::now();
+        self.startup_time = now.checked_sub(elapsed).unwrap_or(now);
+        self.last_frame_time = now;
+        self.delta_time = Duration::ZERO;
+        self.elapsed_time = elapsed;
+        self.frame_count = frame_count;
+        self.frame_time_history.clear();
+    }
+
+    /// Advance by exactly `step` each frame instead of the measured frame time
+    /// (`None` returns to real time), e.g. for deterministic frame export
+    ///
+    /// Elapsed time keeps counting from its current value either way.
+    pub fn set_fixed_delta(&mut self, step: Option<Duration>) {
+        if step.is_none() && self.fixed_delta.is_some() {
+            // Continue real-time elapsed from where the fixed clock got to
+            let now = Instant::now();
+            self.startup_time = now.checked_sub(self.elapsed_time).unwrap_or(now);
+        }
+        self.fixed_delta = step;
+    }
+
+    /// The fixed step set with `set_fixed_delta`, if any
+    pub fn fixed_delta(&self) -> Option<Duration> {
+        self.fixed_delta
+    }
+
+    /// Stop or restart the clock; while stopped, frames advance with a zero
+    /// delta and elapsed time holds
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused && !paused {
+            // Continue real-time elapsed from where the clock stopped
+            let now = Instant::now();
+            self.startup_time = now.checked_sub(self.elapsed_time).unwrap_or(now);
+        }
+        self.paused = paused;
+    }
+
+    /// Whether the clock is stopped (see `set_paused`)
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
     /// Check if we're in the first frame
     pub fn is_first_frame(&self) -> bool {
         self.frame_count <= 1
@@ -0,0 +1,284 @@
+//! Time-of-day lighting: a sun (and faint moon) that follows the clock
+//!
+//! A `TimeOfDay` resource holds the hour and how fast it advances. Entities
+//! with a `SunLight` get a `DirectionalLight` aimed, colored, and dimmed to
+//! match the sun's position, and the skybox gradient follows along.
+//!
+//! ```no_run
+//! use qsi::prelude::*;
+//! use qsi::time_of_day::{SunLight, TimeOfDay, TimeOfDayPlugin};
+//!
+//! App::new()
+//!     .insert_resource(TimeOfDay::new(7.0).with_speed(0.5))
+//!     .add_plugin(TimeOfDayPlugin)
+//!     .add_startup_system(|world, _renderer| {
+//!         world.spawn().with(SunLight::default());
+//!     });
+//! ```
+
+use crate::App;
+use crate::ecs::{Component, World};
+use crate::graphics::{DirectionalLight, Renderer, Skybox};
+use crate::input::InputState;
+use crate::math::Vector3;
+use crate::time::TimeState;
+
+/// Clock and location driving the sun
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeOfDay {
+    /// Local solar time in hours (0..24, 12 is noon)
+    pub hour: f32,
+    /// In-world hours that pass per real second (0 freezes the clock)
+    pub speed: f32,
+    /// Latitude in degrees (positive north), which sets how high the sun climbs
+    pub latitude: f32,
+    /// Day of the year (1..365), which sets the season
+    pub day_of_year: u32,
+    /// Whether the skybox gradient follows the sun
+    pub drive_sky: bool,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self {
+            hour: 12.0,
+            speed: 0.0,
+            latitude: 45.0,
+            day_of_year: 172,
+            drive_sky: true,
+        }
+    }
+}
+
+impl TimeOfDay {
+    /// Clock stopped at `hour`
+    pub fn new(hour: f32) -> Self {
+        Self {
+            hour: hour.rem_euclid(24.0),
+            ..Default::default()
+        }
+    }
+
+    /// Set how many in-world hours pass per real second
+    pub fn with_speed(mut self, hours_per_second: f32) -> Self {
+        self.speed = hours_per_second;
+        self
+    }
+
+    /// Set the latitude in degrees
+    pub fn with_latitude(mut self, latitude: f32) -> Self {
+        self.latitude = latitude.clamp(-90.0, 90.0);
+        self
+    }
+
+    /// Set the day of the year
+    pub fn with_day_of_year(mut self, day: u32) -> Self {
+        self.day_of_year = day.clamp(1, 365);
+        self
+    }
+
+    /// Advance the clock, wrapping at midnight
+    pub fn advance(&mut self, seconds: f32) {
+        self.hour = (self.hour + self.speed * seconds).rem_euclid(24.0);
+    }
+
+    /// Sun `(azimuth, elevation)` in radians; azimuth is measured clockwise
+    /// from north (-Z) towards east (+X)
+    pub fn sun_angles(&self) -> (f32, f32) {
+        use std::f32::consts::{PI, TAU};
+
+        let latitude = self.latitude.to_radians();
+        let declination =
+            -23.44f32.to_radians() * (TAU / 365.0 * (self.day_of_year as f32 + 10.0)).cos();
+        let hour_angle = (self.hour - 12.0) * 15f32.to_radians();
+
+        let sin_elevation = latitude.sin() * declination.sin()
+            + latitude.cos() * declination.cos() * hour_angle.cos();
+        let elevation = sin_elevation.clamp(-1.0, 1.0).asin();
+
+        // Azimuth from north; mirrored in the afternoon
+        let cos_azimuth = (declination.sin() - sin_elevation * latitude.sin())
+            / (elevation.cos() * latitude.cos()).max(1e-4);
+        let azimuth = cos_azimuth.clamp(-1.0, 1.0).acos();
+        let azimuth = if hour_angle > 0.0 {
+            TAU - azimuth
+        } else {
+            azimuth
+        };
+        (azimuth % TAU, elevation.clamp(-PI / 2.0, PI / 2.0))
+    }
+
+    /// Unit vector from the ground towards the sun (+Y up, -Z north, +X east)
+    pub fn sun_direction(&self) -> Vector3<f32> {
+        let (azimuth, elevation) = self.sun_angles();
+        Vector3::new(
+            azimuth.sin() * elevation.cos(),
+            elevation.sin(),
+            -azimuth.cos() * elevation.cos(),
+        )
+    }
+}
+
+/// Makes an entity's `DirectionalLight` follow the `TimeOfDay` sun
+///
+/// Below the horizon the light switches to a dim, bluish moon opposite the sun.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SunLight {
+    /// Intensity with the sun high in the sky
+    pub intensity: f32,
+    /// Ambient fraction at noon
+    pub day_ambient: f32,
+    /// Moonlight intensity at night
+    pub moon_intensity: f32,
+    /// Ambient fraction at night
+    pub night_ambient: f32,
+}
+
+impl Component for SunLight {}
+
+impl Default for SunLight {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            day_ambient: 0.25,
+            moon_intensity: 0.08,
+            night_ambient: 0.05,
+        }
+    }
+}
+
+impl SunLight {
+    /// Directional light for the given time of day
+    pub fn light(&self, time_of_day: &TimeOfDay) -> DirectionalLight {
+        let to_sun = time_of_day.sun_direction();
+        let (_, elevation) = time_of_day.sun_angles();
+        let elevation = elevation.to_degrees();
+
+        // 0 once the sun is 6° below the horizon (civil twilight), 1 above 6°
+        let day = smoothstep(-6.0, 6.0, elevation);
+        let sun_color = color_temperature(sun_temperature(elevation));
+        let moon_color = [0.6, 0.7, 1.0];
+        let sun_up = smoothstep(-1.0, 2.0, elevation);
+
+        let (direction, color, intensity) = if sun_up > 0.0 {
+            // Dims as the sun sinks, since its light crosses more atmosphere
+            let strength = smoothstep(-1.0, 20.0, elevation).max(0.05 * sun_up);
+            (-to_sun, sun_color, self.intensity * strength)
+        } else {
+            (to_sun, moon_color, self.moon_intensity)
+        };
+        DirectionalLight::new(direction)
+            .with_color(color)
+            .with_intensity(intensity)
+            .with_ambient(self.night_ambient + (self.day_ambient - self.night_ambient) * day)
+    }
+}
+
+/// Sky gradient for the given time of day: day blue, sunset orange, night dark
+pub fn sky_gradient(time_of_day: &TimeOfDay) -> Skybox {
+    let (_, elevation) = time_of_day.sun_angles();
+    let elevation = elevation.to_degrees();
+    let day = smoothstep(-6.0, 10.0, elevation);
+    // Strongest with the sun just at the horizon
+    let sunset = (1.0 - (elevation / 8.0).abs()).max(0.0);
+
+    let mix3 = |a: [f32; 3], b: [f32; 3], t: f32| [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t);
+    let zenith = mix3([0.004, 0.006, 0.02], [0.15, 0.35, 0.8], day);
+    let horizon = mix3([0.02, 0.03, 0.06], [0.7, 0.8, 0.95], day);
+    let horizon = mix3(horizon, [0.95, 0.45, 0.2], sunset * 0.8);
+    let ground = mix3([0.01, 0.01, 0.015], [0.25, 0.22, 0.2], day);
+    Skybox::gradient(zenith, horizon, ground)
+}
+
+/// Approximate sunlight color temperature in kelvin for a sun elevation in degrees
+fn sun_temperature(elevation: f32) -> f32 {
+    2000.0 + 4500.0 * smoothstep(0.0, 30.0, elevation)
+}
+
+/// Linear RGB of a black body at `kelvin`, normalized to a maximum of 1
+///
+/// Fit by Tanner Helland, valid from about 1000 K to 40000 K.
+pub fn color_temperature(kelvin: f32) -> [f32; 3] {
+    let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (t - 60.0).powf(-0.133_204_76)
+    };
+    let green = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_16 * (t - 60.0).powf(-0.075_514_846)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+
+    let linear = [red, green, blue].map(|c| {
+        let c = (c / 255.0).clamp(0.0, 1.0);
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    let max = linear.iter().copied().fold(1e-6, f32::max);
+    linear.map(|c| c / max)
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Advance the clock and update every `SunLight`'s `DirectionalLight`
+pub fn time_of_day_system(world: &mut World, _input: &InputState, time: &TimeState) {
+    let Some(time_of_day) = world.resource_mut::<TimeOfDay>() else {
+        world.insert_resource(TimeOfDay::default());
+        return;
+    };
+    time_of_day.advance(time.delta_seconds());
+    let time_of_day = *time_of_day;
+
+    let suns: Vec<_> = world
+        .query::<SunLight>()
+        .map(|(entity, sun)| (entity, sun.light(&time_of_day)))
+        .collect();
+    for (entity, light) in suns {
+        match world.get_component_mut::<DirectionalLight>(entity) {
+            Some(existing) => *existing = light,
+            None => world.add_component(entity, light),
+        }
+    }
+}
+
+/// Match the skybox to the sun and keep frames coming while the clock runs
+pub fn time_of_day_sky_system(world: &mut World, renderer: &mut Renderer, _time: &TimeState) {
+    let Some(time_of_day) = world.resource::<TimeOfDay>() else {
+        return;
+    };
+    if time_of_day.drive_sky {
+        renderer.set_skybox(sky_gradient(time_of_day));
+    }
+    if time_of_day.speed != 0.0 {
+        renderer.request_redraw();
+    }
+}
+
+/// Plugin advancing the `TimeOfDay` clock and driving sun lights and the sky
+pub struct TimeOfDayPlugin;
+
+impl crate::plugin::Plugin for TimeOfDayPlugin {
+    fn build(&self, app: &mut App) {
+        use crate::schedule::IntoSystemDescriptor;
+
+        app.register_system(time_of_day_system.label("time_of_day"));
+        app.register_render_system(time_of_day_sky_system);
+    }
+}
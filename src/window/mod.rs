@@ -0,0 +1,241 @@
+//! Window creation settings and runtime window changes
+//!
+//! `WindowConfig` describes the window the app opens with; the
+//! `WindowControl` resource lets systems change its title and fullscreen
+//! state while the app runs.
+//!
+//! ```no_run
+//! use qsi::prelude::*;
+//! use qsi::window::{WindowConfig, WindowControl};
+//! use winit::keyboard::KeyCode;
+//!
+//! App::new()
+//!     .with_window(
+//!         WindowConfig::default()
+//!             .with_title("Viewer")
+//!             .with_size(1280, 720)
+//!             .with_min_size(640, 360),
+//!     )
+//!     .add_system(|world: &mut World, input: &InputState, _: &TimeState| {
+//!         if input.key_just_pressed(KeyCode::F11)
+//!             && let Some(window) = world.resource_mut::<WindowControl>()
+//!         {
+//!             window.toggle_fullscreen();
+//!         }
+//!     });
+//! ```
+
+use crate::ecs::World;
+use crate::graphics::{Renderer, read_png_rgba8};
+use anyhow::{Context, Result};
+use std::path::Path;
+use winit::dpi::{LogicalPosition, LogicalSize};
+use winit::window::{Fullscreen, Icon, WindowAttributes};
+
+/// Inner size most platforms open a window at when none is set, in logical
+/// pixels
+pub const DEFAULT_WINDOW_SIZE: (u32, u32) = (800, 600);
+
+/// Settings of the window an app opens, in logical pixels
+#[derive(Debug, Clone)]
+pub struct WindowConfig {
+    pub title: String,
+    /// Initial inner size, or `None` for the platform default
+    pub inner_size: Option<(u32, u32)>,
+    /// Smallest inner size the user can resize to
+    pub min_size: Option<(u32, u32)>,
+    /// Position of the top left corner on the desktop, or `None` to let the
+    /// platform place the window
+    pub position: Option<(i32, i32)>,
+    /// Start borderless fullscreen on the current monitor
+    pub fullscreen: bool,
+    /// Start maximized
+    pub maximized: bool,
+    pub resizable: bool,
+    /// Show the title bar and borders
+    pub decorations: bool,
+    /// Let the desktop show through where the clear color is transparent,
+    /// where the platform supports it
+    pub transparent: bool,
+    /// Icon in the title bar and task bar
+    pub icon: Option<Icon>,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: "QSi App".to_string(),
+            inner_size: None,
+            min_size: None,
+            position: None,
+            fullscreen: false,
+            maximized: false,
+            resizable: true,
+            decorations: true,
+            transparent: false,
+            icon: None,
+        }
+    }
+}
+
+impl WindowConfig {
+    /// Set the title
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Set the initial inner size
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.inner_size = Some((width, height));
+        self
+    }
+
+    /// Set the smallest inner size
+    pub fn with_min_size(mut self, width: u32, height: u32) -> Self {
+        self.min_size = Some((width, height));
+        self
+    }
+
+    /// Set where the window opens
+    pub fn with_position(mut self, x: i32, y: i32) -> Self {
+        self.position = Some((x, y));
+        self
+    }
+
+    /// Set whether to start fullscreen
+    pub fn with_fullscreen(mut self, fullscreen: bool) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
+    /// Set whether to start maximized
+    pub fn with_maximized(mut self, maximized: bool) -> Self {
+        self.maximized = maximized;
+        self
+    }
+
+    /// Set whether the user can resize the window
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    /// Set whether to show the title bar and borders
+    pub fn with_decorations(mut self, decorations: bool) -> Self {
+        self.decorations = decorations;
+        self
+    }
+
+    /// Set whether the window background can be transparent
+    pub fn with_transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    /// Set the icon
+    pub fn with_icon(mut self, icon: Icon) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    /// Set the icon from a PNG file
+    pub fn with_icon_png(self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let (width, height, pixels) = read_png_rgba8(path)?;
+        let icon = Icon::from_rgba(pixels, width, height)
+            .with_context(|| format!("Invalid window icon {}", path.display()))?;
+        Ok(self.with_icon(icon))
+    }
+
+    /// winit attributes of a window with these settings
+    pub(crate) fn attributes(&self) -> WindowAttributes {
+        let mut attributes = WindowAttributes::default()
+            .with_title(&self.title)
+            .with_maximized(self.maximized)
+            .with_resizable(self.resizable)
+            .with_decorations(self.decorations)
+            .with_transparent(self.transparent)
+            .with_window_icon(self.icon.clone());
+        if let Some((width, height)) = self.inner_size {
+            attributes = attributes.with_inner_size(LogicalSize::new(width, height));
+        }
+        if let Some((width, height)) = self.min_size {
+            attributes = attributes.with_min_inner_size(LogicalSize::new(width, height));
+        }
+        if let Some((x, y)) = self.position {
+            attributes = attributes.with_position(LogicalPosition::new(x, y));
+        }
+        if self.fullscreen {
+            attributes = attributes.with_fullscreen(Some(Fullscreen::Borderless(None)));
+        }
+        attributes
+    }
+}
+
+/// Resource for changing the window from systems
+///
+/// Changes apply at the end of the frame's render systems. Headless apps
+/// keep the values without a window to apply them to.
+#[derive(Debug, Clone)]
+pub struct WindowControl {
+    title: String,
+    fullscreen: bool,
+    changed: bool,
+}
+
+impl WindowControl {
+    /// Control of a window opened with `config`
+    pub fn new(config: &WindowConfig) -> Self {
+        Self {
+            title: config.title.clone(),
+            fullscreen: config.fullscreen,
+            changed: false,
+        }
+    }
+
+    /// Window title
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Change the window title
+    pub fn set_title(&mut self, title: impl Into<String>) {
+        self.title = title.into();
+        self.changed = true;
+    }
+
+    /// Whether the window is fullscreen
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    /// Switch between borderless fullscreen and a normal window
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.fullscreen = fullscreen;
+        self.changed = true;
+    }
+
+    /// Leave fullscreen if in it, otherwise enter it
+    pub fn toggle_fullscreen(&mut self) {
+        self.set_fullscreen(!self.fullscreen);
+    }
+}
+
+/// Apply changes made through `WindowControl` to the renderer's window
+pub(crate) fn apply_window_control(world: &mut World, renderer: &Renderer) {
+    let (Some(control), Some(window)) = (world.resource_mut::<WindowControl>(), &renderer.window)
+    else {
+        return;
+    };
+    if std::mem::take(&mut control.changed) {
+        window.set_title(&control.title);
+        let fullscreen = control.fullscreen.then_some(Fullscreen::Borderless(None));
+        if window.fullscreen().is_some() != control.fullscreen {
+            window.set_fullscreen(fullscreen);
+        }
+    } else {
+        // The user may have left fullscreen through the platform
+        control.fullscreen = window.fullscreen().is_some();
+    }
+}
@@ -0,0 +1,385 @@
+//! VR headset support (behind the `xr` feature)
+//!
+//! Head and controller pose components, per-eye view math, and a plugin that
+//! renders each eye into the headset's swapchain images. `OpenXrPlugin` runs
+//! all of it on the system's OpenXR runtime over Vulkan, creating the device
+//! the runtime picks and rendering the whole app on it:
+//!
+//! ```rust,no_run
+//! use qsi::prelude::*;
+//! use qsi::xr::{Hand, HeadPose, ControllerPose, OpenXrPlugin};
+//!
+//! App::new()
+//!     .with_headless(1280, 720)
+//!     .add_plugin(OpenXrPlugin)
+//!     .add_startup_system(|world: &mut World, _: &mut Renderer| {
+//!         world.spawn().with(HeadPose::default()).build();
+//!         world.spawn().with(ControllerPose::new(Hand::Left)).build();
+//!         world.spawn().with(ControllerPose::new(Hand::Right)).build();
+//!     })
+//!     .run()
+//!     .unwrap();
+//! ```
+//!
+//! Other backends implement [`XrRuntime`] and insert it as the
+//! `XrRuntimeResource` resource next to `XrPlugin`; the swapchain images they
+//! expose must be texture views on the renderer's device.
+
+mod openxr_runtime;
+
+pub use openxr_runtime::{OpenXrPlugin, OpenXrRuntime};
+
+use crate::ecs::{Component, World};
+use crate::graphics::{Renderer, ViewTarget};
+use crate::input::InputState;
+use crate::math::{Matrix4, Transform, Vector3};
+use crate::time::TimeState;
+use crate::{App, AppExit};
+use anyhow::Result;
+use cgmath::{InnerSpace, Quaternion, Rad, SquareMatrix};
+
+/// Which hand a controller is held in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+/// Position and orientation in the tracking space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pose {
+    pub position: Vector3<f32>,
+    pub orientation: Quaternion<f32>,
+}
+
+impl Default for Pose {
+    fn default() -> Self {
+        Self {
+            position: Vector3::new(0.0, 0.0, 0.0),
+            orientation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+        }
+    }
+}
+
+impl Pose {
+    /// Pose-to-tracking-space matrix
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.position) * Matrix4::from(self.orientation.normalize())
+    }
+
+    /// Copy into a `Transform`, keeping its scale
+    pub fn apply_to(&self, transform: &mut Transform) {
+        transform.position = self.position;
+        transform.set_rotation_from_quaternion(self.orientation);
+    }
+}
+
+/// Field of view of one eye as four half-angles (left and down are negative)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fov {
+    pub left: Rad<f32>,
+    pub right: Rad<f32>,
+    pub up: Rad<f32>,
+    pub down: Rad<f32>,
+}
+
+/// One eye's pose and field of view for the current frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EyeView {
+    pub pose: Pose,
+    pub fov: Fov,
+}
+
+impl EyeView {
+    /// World-to-eye matrix, given the tracking space's placement in the world
+    pub fn view_matrix(&self, origin: Matrix4<f32>) -> Matrix4<f32> {
+        (origin * self.pose.matrix())
+            .invert()
+            .unwrap_or_else(Matrix4::identity)
+    }
+
+    /// Asymmetric perspective projection (wgpu depth range 0..1)
+    pub fn projection(&self, near: f32, far: f32) -> Matrix4<f32> {
+        let left = self.fov.left.0.tan() * near;
+        let right = self.fov.right.0.tan() * near;
+        let down = self.fov.down.0.tan() * near;
+        let up = self.fov.up.0.tan() * near;
+        let mut proj = Matrix4::from_scale(0.0);
+        proj.x.x = 2.0 * near / (right - left);
+        proj.y.y = 2.0 * near / (up - down);
+        proj.z.x = (right + left) / (right - left);
+        proj.z.y = (up + down) / (up - down);
+        proj.z.z = far / (near - far);
+        proj.z.w = -1.0;
+        proj.w.z = near * far / (near - far);
+        proj.w.w = 0.0;
+        proj
+    }
+}
+
+/// Lifecycle of the XR session, mirroring OpenXR's session states
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XrSessionState {
+    /// Waiting for the runtime (headset not ready or not worn)
+    Idle,
+    /// Frames are submitted but the app is not visible yet
+    Ready,
+    /// Visible in the headset
+    Visible,
+    /// Visible and receiving input
+    Focused,
+    /// The runtime asked the session to end
+    Stopping,
+    /// The app should quit
+    Exiting,
+}
+
+impl XrSessionState {
+    /// True when frames should be rendered
+    pub fn is_running(self) -> bool {
+        matches!(
+            self,
+            XrSessionState::Ready | XrSessionState::Visible | XrSessionState::Focused
+        )
+    }
+}
+
+/// Input state of one tracked controller
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ControllerState {
+    pub hand: Hand,
+    pub pose: Pose,
+    pub trigger: f32,
+    pub grip: f32,
+    pub thumbstick: [f32; 2],
+    pub primary_button: bool,
+}
+
+/// Everything the runtime predicts for the frame being rendered
+#[derive(Debug, Clone, PartialEq)]
+pub struct XrFrame {
+    pub head: Pose,
+    pub eyes: [EyeView; 2],
+    pub controllers: Vec<ControllerState>,
+}
+
+/// Session management and swapchains for a headset runtime, such as
+/// `OpenXrRuntime`
+///
+/// Frames follow the OpenXR loop: `poll_events`, then `begin_frame` (wait +
+/// begin, acquiring swapchain images), rendering into `eye_target`s, then
+/// `end_frame` (release + submit layers).
+pub trait XrRuntime: Send + Sync {
+    /// Process runtime events and return the current session state
+    fn poll_events(&mut self) -> Result<XrSessionState>;
+
+    /// Wait for the next frame; `None` if the runtime says not to render it
+    fn begin_frame(&mut self) -> Result<Option<XrFrame>>;
+
+    /// Swapchain image for eye `index` (0 left, 1 right) acquired by `begin_frame`
+    fn eye_target(&self, index: usize) -> Option<&wgpu::TextureView>;
+
+    /// Format and size of the eye swapchain images
+    fn eye_format(&self) -> wgpu::TextureFormat;
+    fn eye_size(&self) -> (u32, u32);
+
+    /// Release the swapchain images and submit the frame
+    fn end_frame(&mut self) -> Result<()>;
+}
+
+/// Resource holding the active runtime and the latest session state and frame
+pub struct XrRuntimeResource {
+    runtime: Box<dyn XrRuntime>,
+    state: XrSessionState,
+    frame: Option<XrFrame>,
+    /// Placement of the tracking space in the world
+    pub origin: Transform,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl XrRuntimeResource {
+    /// Wrap a runtime
+    pub fn new(runtime: impl XrRuntime + 'static) -> Self {
+        Self {
+            runtime: Box::new(runtime),
+            state: XrSessionState::Idle,
+            frame: None,
+            origin: Transform::default(),
+            near: 0.05,
+            far: 100.0,
+        }
+    }
+
+    /// Latest session state
+    pub fn state(&self) -> XrSessionState {
+        self.state
+    }
+
+    /// Frame most recently begun, if any
+    pub fn frame(&self) -> Option<&XrFrame> {
+        self.frame.as_ref()
+    }
+}
+
+/// Component tracking the headset; its `Transform` follows the head
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct HeadPose {
+    pub pose: Pose,
+}
+
+impl Component for HeadPose {}
+
+/// Component tracking one controller; its `Transform` follows the controller
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ControllerPose {
+    pub hand: Hand,
+    pub pose: Pose,
+    pub trigger: f32,
+    pub grip: f32,
+    pub thumbstick: [f32; 2],
+    pub primary_button: bool,
+    /// Whether the runtime reported this controller last frame
+    pub tracked: bool,
+}
+
+impl Component for ControllerPose {}
+
+impl ControllerPose {
+    /// Untracked controller for `hand`
+    pub fn new(hand: Hand) -> Self {
+        Self {
+            hand,
+            pose: Pose::default(),
+            trigger: 0.0,
+            grip: 0.0,
+            thumbstick: [0.0; 2],
+            primary_button: false,
+            tracked: false,
+        }
+    }
+}
+
+/// Poll the runtime, begin a frame, and copy head/controller poses into components
+pub fn xr_pose_system(world: &mut World, _input: &InputState, _time: &TimeState) {
+    let Some(xr) = world.resource_mut::<XrRuntimeResource>() else {
+        return;
+    };
+    xr.frame = None;
+    match xr.runtime.poll_events() {
+        Ok(state) => xr.state = state,
+        Err(err) => log::error!("XR event polling failed: {err:#}"),
+    }
+    if xr.state == XrSessionState::Exiting {
+        world.insert_resource(AppExit);
+        return;
+    }
+    if xr.state.is_running() {
+        match xr.runtime.begin_frame() {
+            Ok(frame) => xr.frame = frame,
+            Err(err) => log::error!("XR begin_frame failed: {err:#}"),
+        }
+    }
+    let Some(frame) = xr.frame.clone() else {
+        return;
+    };
+    let origin = xr.origin.matrix();
+    let to_world = |pose: &Pose| {
+        let matrix = origin * pose.matrix();
+        Pose {
+            position: matrix.w.truncate(),
+            orientation: Quaternion::from(cgmath::Matrix3::from_cols(
+                matrix.x.truncate().normalize(),
+                matrix.y.truncate().normalize(),
+                matrix.z.truncate().normalize(),
+            )),
+        }
+    };
+
+    let head = to_world(&frame.head);
+    let heads: Vec<_> = world
+        .query::<HeadPose>()
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in heads {
+        if let Some(component) = world.get_component_mut::<HeadPose>(entity) {
+            component.pose = head;
+        }
+        if let Some(transform) = world.get_component_mut::<Transform>(entity) {
+            head.apply_to(transform);
+        }
+    }
+
+    let controllers: Vec<_> = world
+        .query::<ControllerPose>()
+        .map(|(entity, controller)| (entity, controller.hand))
+        .collect();
+    for (entity, hand) in controllers {
+        let state = frame.controllers.iter().find(|c| c.hand == hand);
+        let pose = state.map(|state| to_world(&state.pose));
+        if let Some(component) = world.get_component_mut::<ControllerPose>(entity) {
+            component.tracked = state.is_some();
+            if let (Some(state), Some(pose)) = (state, pose) {
+                component.pose = pose;
+                component.trigger = state.trigger;
+                component.grip = state.grip;
+                component.thumbstick = state.thumbstick;
+                component.primary_button = state.primary_button;
+            }
+        }
+        if let (Some(pose), Some(transform)) = (pose, world.get_component_mut::<Transform>(entity))
+        {
+            pose.apply_to(transform);
+        }
+    }
+}
+
+/// Render both eyes into the runtime's swapchain images and submit the frame
+pub fn xr_render_system(world: &mut World, renderer: &mut Renderer, time: &TimeState) {
+    let Some(mut xr) = world.remove_resource::<XrRuntimeResource>() else {
+        return;
+    };
+    if let Some(frame) = xr.frame.take() {
+        let origin = xr.origin.matrix();
+        let format = xr.runtime.eye_format();
+        let (width, height) = xr.runtime.eye_size();
+        let targets: Vec<ViewTarget> = frame
+            .eyes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, eye)| {
+                Some(ViewTarget {
+                    view: eye.view_matrix(origin),
+                    projection: eye.projection(xr.near, xr.far),
+                    target: xr.runtime.eye_target(index)?,
+                    format,
+                    width,
+                    height,
+                    viewport: None,
+                })
+            })
+            .collect();
+        renderer.update_time(time);
+        renderer.render_to_targets(world, &targets);
+        if let Err(err) = xr.runtime.end_frame() {
+            log::error!("XR end_frame failed: {err:#}");
+        }
+    }
+    // The headset needs frames whether or not the window does
+    if xr.state != XrSessionState::Exiting {
+        renderer.request_redraw();
+    }
+    world.insert_resource(xr);
+}
+
+/// Plugin syncing headset poses and rendering to the headset each frame
+pub struct XrPlugin;
+
+impl crate::plugin::Plugin for XrPlugin {
+    fn build(&self, app: &mut App) {
+        use crate::schedule::IntoSystemDescriptor;
+
+        app.register_system(xr_pose_system.label("xr"));
+        app.register_render_system(xr_render_system);
+    }
+}
@@ -0,0 +1,702 @@
+//! `XrRuntime` over the system's OpenXR runtime, rendering on a Vulkan
+//! device shared with the renderer
+//!
+//! OpenXR decides which GPU drives the headset and must create the Vulkan
+//! instance and device itself, so `OpenXrRuntime::new` creates them through
+//! the runtime and wraps them for wgpu; the renderer is then built on that
+//! device (`RendererConfig::with_gpu`), and the swapchain images it draws into
+//! are wgpu textures over the runtime's `VkImage`s.
+
+use super::{
+    ControllerState, EyeView, Fov, Hand, Pose, XrFrame, XrPlugin, XrRuntime, XrRuntimeResource,
+    XrSessionState,
+};
+use crate::App;
+use crate::graphics::{RendererConfig, SharedGpu};
+use anyhow::{Context, Result, bail};
+use ash::vk::{self, Handle};
+use cgmath::{Quaternion, Rad, Vector3};
+use openxr as xr;
+use wgpu::hal::api::Vulkan;
+
+const VIEW_TYPE: xr::ViewConfigurationType = xr::ViewConfigurationType::PRIMARY_STEREO;
+
+/// Vulkan version the instance is created for; 1.1 is the lowest the common
+/// runtimes accept
+const VULKAN_VERSION: u32 = vk::API_VERSION_1_1;
+
+/// Swapchain formats the renderer can draw into, best first
+const FORMATS: [(vk::Format, wgpu::TextureFormat); 2] = [
+    (
+        vk::Format::R8G8B8A8_SRGB,
+        wgpu::TextureFormat::Rgba8UnormSrgb,
+    ),
+    (
+        vk::Format::B8G8R8A8_SRGB,
+        wgpu::TextureFormat::Bgra8UnormSrgb,
+    ),
+];
+
+/// Controller inputs suggested for one interaction profile
+struct Profile {
+    path: &'static str,
+    trigger: &'static str,
+    grip: Option<&'static str>,
+    thumbstick: Option<&'static str>,
+    /// Primary button of the left and right hand
+    primary: [&'static str; 2],
+}
+
+const PROFILES: [Profile; 3] = [
+    Profile {
+        path: "/interaction_profiles/khr/simple_controller",
+        trigger: "select/click",
+        grip: None,
+        thumbstick: None,
+        primary: ["menu/click"; 2],
+    },
+    Profile {
+        path: "/interaction_profiles/oculus/touch_controller",
+        trigger: "trigger/value",
+        grip: Some("squeeze/value"),
+        thumbstick: Some("thumbstick"),
+        primary: ["x/click", "a/click"],
+    },
+    Profile {
+        path: "/interaction_profiles/valve/index_controller",
+        trigger: "trigger/value",
+        grip: Some("squeeze/value"),
+        thumbstick: Some("thumbstick"),
+        primary: ["a/click"; 2],
+    },
+];
+
+/// Controller actions, each with a left and a right hand subaction
+struct Controls {
+    set: xr::ActionSet,
+    pose: xr::Action<xr::Posef>,
+    trigger: xr::Action<f32>,
+    grip: xr::Action<f32>,
+    thumbstick: xr::Action<xr::Vector2f>,
+    primary: xr::Action<bool>,
+    /// Subaction path and grip space of each hand
+    hands: Vec<(Hand, xr::Path, xr::Space)>,
+}
+
+/// The eye swapchain: one two-layer image per buffer, a layer per eye
+struct Swapchain {
+    /// Views of each image's layers; declared first so they go before the
+    /// swapchain owning the images
+    views: Vec<[wgpu::TextureView; 2]>,
+    handle: xr::Swapchain<xr::Vulkan>,
+    format: wgpu::TextureFormat,
+    size: (u32, u32),
+}
+
+/// Frame begun by `begin_frame`, submitted by `end_frame`
+struct PendingFrame {
+    time: xr::Time,
+    /// Eye poses the frame is rendered from, submitted with it
+    views: Vec<xr::View>,
+    image: usize,
+}
+
+/// OpenXR session on the system's runtime (SteamVR, Monado, Meta Quest Link, ...)
+///
+/// Usually added through `OpenXrPlugin`; created directly, the returned
+/// `SharedGpu` must go to the renderer's config before the app runs.
+pub struct OpenXrRuntime {
+    // Declared in drop order: everything made from the session goes first
+    swapchain: Swapchain,
+    controls: Controls,
+    stage: xr::Space,
+    head: xr::Space,
+    frame_stream: xr::FrameStream<xr::Vulkan>,
+    frame_waiter: xr::FrameWaiter,
+    session: xr::Session<xr::Vulkan>,
+    instance: xr::Instance,
+    blend_mode: xr::EnvironmentBlendMode,
+    state: XrSessionState,
+    pending: Option<PendingFrame>,
+}
+
+impl OpenXrRuntime {
+    /// Connect to the runtime and start a session for its headset, returning
+    /// it with the Vulkan device it renders on
+    ///
+    /// `config` supplies the limits the device is created with.
+    pub fn new(config: &RendererConfig) -> Result<(Self, SharedGpu)> {
+        // SAFETY: loads the OpenXR loader installed on the system, which only
+        // runs its initializers
+        let entry = unsafe { xr::Entry::load(&()) }.context("Failed to load the OpenXR loader")?;
+        if !entry.enumerate_extensions()?.khr_vulkan_enable2 {
+            bail!("The OpenXR runtime does not support Vulkan");
+        }
+        let mut extensions = xr::ExtensionSet::default();
+        extensions.khr_vulkan_enable2 = true;
+        let instance = entry
+            .create_instance(
+                &xr::ApplicationInfo {
+                    application_name: "qsi",
+                    engine_name: "qsi",
+                    ..Default::default()
+                },
+                &extensions,
+                &[],
+                &(),
+            )
+            .context("Failed to create the OpenXR instance")?;
+        let system = instance
+            .system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+            .context("No headset found")?;
+        let blend_mode = *instance
+            .enumerate_environment_blend_modes(system, VIEW_TYPE)?
+            .first()
+            .context("The headset has no blend mode for stereo views")?;
+
+        let (gpu, session_info) = create_vulkan(&instance, system, config)?;
+        // SAFETY: `session_info` holds the instance and device just created
+        // through this runtime for `system`
+        let (session, frame_waiter, frame_stream) =
+            unsafe { instance.create_session::<xr::Vulkan>(system, &session_info) }
+                .context("Failed to create the OpenXR session")?;
+
+        let spaces = session.enumerate_reference_spaces()?;
+        // Standing scale when the runtime knows the play area, else seated
+        let stage_type = if spaces.contains(&xr::ReferenceSpaceType::STAGE) {
+            xr::ReferenceSpaceType::STAGE
+        } else {
+            xr::ReferenceSpaceType::LOCAL
+        };
+        let stage = session.create_reference_space(stage_type, xr::Posef::IDENTITY)?;
+        let head =
+            session.create_reference_space(xr::ReferenceSpaceType::VIEW, xr::Posef::IDENTITY)?;
+        let controls = create_controls(&instance, &session)?;
+        let swapchain = create_swapchain(&instance, system, &session, &gpu.device)?;
+
+        let runtime = Self {
+            swapchain,
+            controls,
+            stage,
+            head,
+            frame_stream,
+            frame_waiter,
+            session,
+            instance,
+            blend_mode,
+            state: XrSessionState::Idle,
+            pending: None,
+        };
+        Ok((runtime, gpu))
+    }
+
+    /// Poses and controller input predicted for `time`
+    fn locate(&self, time: xr::Time) -> Result<(XrFrame, Vec<xr::View>)> {
+        let (_, views) = self.session.locate_views(VIEW_TYPE, time, &self.stage)?;
+        if views.len() != 2 {
+            bail!("Expected 2 stereo views, the runtime gave {}", views.len());
+        }
+        let eyes = [0, 1].map(|index| EyeView {
+            pose: pose(views[index].pose),
+            fov: fov(views[index].fov),
+        });
+        let head = pose(self.head.locate(&self.stage, time)?.pose);
+
+        let controls = &self.controls;
+        self.session
+            .sync_actions(&[xr::ActiveActionSet::new(&controls.set)])?;
+        let mut controllers = Vec::new();
+        for (hand, path, space) in &controls.hands {
+            let location = space.locate(&self.stage, time)?;
+            let tracked =
+                xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID;
+            if !controls.pose.is_active(&self.session, *path)?
+                || !location.location_flags.contains(tracked)
+            {
+                continue;
+            }
+            let thumbstick = controls
+                .thumbstick
+                .state(&self.session, *path)?
+                .current_state;
+            controllers.push(ControllerState {
+                hand: *hand,
+                pose: pose(location.pose),
+                trigger: controls.trigger.state(&self.session, *path)?.current_state,
+                grip: controls.grip.state(&self.session, *path)?.current_state,
+                thumbstick: [thumbstick.x, thumbstick.y],
+                primary_button: controls.primary.state(&self.session, *path)?.current_state,
+            });
+        }
+        let frame = XrFrame {
+            head,
+            eyes,
+            controllers,
+        };
+        Ok((frame, views))
+    }
+
+    /// Locate the frame and acquire the swapchain image it is drawn into
+    fn prepare(&mut self, time: xr::Time) -> Result<XrFrame> {
+        let (frame, views) = self.locate(time)?;
+        let image = self.swapchain.handle.acquire_image()? as usize;
+        self.swapchain.handle.wait_image(xr::Duration::INFINITE)?;
+        self.pending = Some(PendingFrame { time, views, image });
+        Ok(frame)
+    }
+}
+
+impl XrRuntime for OpenXrRuntime {
+    fn poll_events(&mut self) -> Result<XrSessionState> {
+        let mut buffer = xr::EventDataBuffer::new();
+        while let Some(event) = self.instance.poll_event(&mut buffer)? {
+            match event {
+                xr::Event::SessionStateChanged(change) => {
+                    self.state = match change.state() {
+                        xr::SessionState::READY => {
+                            self.session.begin(VIEW_TYPE)?;
+                            XrSessionState::Ready
+                        }
+                        xr::SessionState::SYNCHRONIZED => XrSessionState::Ready,
+                        xr::SessionState::VISIBLE => XrSessionState::Visible,
+                        xr::SessionState::FOCUSED => XrSessionState::Focused,
+                        xr::SessionState::STOPPING => {
+                            self.session.end()?;
+                            XrSessionState::Stopping
+                        }
+                        xr::SessionState::EXITING | xr::SessionState::LOSS_PENDING => {
+                            XrSessionState::Exiting
+                        }
+                        _ => XrSessionState::Idle,
+                    };
+                }
+                xr::Event::InstanceLossPending(_) => self.state = XrSessionState::Exiting,
+                xr::Event::EventsLost(lost) => {
+                    log::warn!("OpenXR dropped {} events", lost.lost_event_count());
+                }
+                _ => {}
+            }
+        }
+        Ok(self.state)
+    }
+
+    fn begin_frame(&mut self) -> Result<Option<XrFrame>> {
+        let state = self.frame_waiter.wait()?;
+        self.frame_stream.begin()?;
+        let time = state.predicted_display_time;
+        let prepared = if state.should_render {
+            self.prepare(time).map(Some)
+        } else {
+            Ok(None)
+        };
+        // A frame that will not be drawn is submitted empty, as OpenXR
+        // requires every begun frame to end
+        if !matches!(prepared, Ok(Some(_))) {
+            self.frame_stream.end(time, self.blend_mode, &[])?;
+        }
+        prepared
+    }
+
+    fn eye_target(&self, index: usize) -> Option<&wgpu::TextureView> {
+        let image = self.pending.as_ref()?.image;
+        self.swapchain.views.get(image)?.get(index)
+    }
+
+    fn eye_format(&self) -> wgpu::TextureFormat {
+        self.swapchain.format
+    }
+
+    fn eye_size(&self) -> (u32, u32) {
+        self.swapchain.size
+    }
+
+    fn end_frame(&mut self) -> Result<()> {
+        let Some(frame) = self.pending.take() else {
+            return Ok(());
+        };
+        self.swapchain.handle.release_image()?;
+        let (width, height) = self.swapchain.size;
+        let rect = xr::Rect2Di {
+            offset: xr::Offset2Di { x: 0, y: 0 },
+            extent: xr::Extent2Di {
+                width: width as i32,
+                height: height as i32,
+            },
+        };
+        let views: Vec<_> = frame
+            .views
+            .iter()
+            .enumerate()
+            .map(|(index, view)| {
+                xr::CompositionLayerProjectionView::new()
+                    .pose(view.pose)
+                    .fov(view.fov)
+                    .sub_image(
+                        xr::SwapchainSubImage::new()
+                            .swapchain(&self.swapchain.handle)
+                            .image_array_index(index as u32)
+                            .image_rect(rect),
+                    )
+            })
+            .collect();
+        let layer = xr::CompositionLayerProjection::new()
+            .space(&self.stage)
+            .views(&views);
+        self.frame_stream
+            .end(frame.time, self.blend_mode, &[&layer])?;
+        Ok(())
+    }
+}
+
+/// Create the Vulkan instance and device the runtime asks for and wrap them
+/// for wgpu, returning them with the handles the session is created from
+fn create_vulkan(
+    instance: &xr::Instance,
+    system: xr::SystemId,
+    config: &RendererConfig,
+) -> Result<(SharedGpu, xr::vulkan::SessionCreateInfo)> {
+    let requirements = instance.graphics_requirements::<xr::Vulkan>(system)?;
+    let version = xr::Version::new(1, 1, 0);
+    if version < requirements.min_api_version_supported
+        || version.major() > requirements.max_api_version_supported.major()
+    {
+        bail!(
+            "The OpenXR runtime needs Vulkan {} to {}.x",
+            requirements.min_api_version_supported,
+            requirements.max_api_version_supported.major()
+        );
+    }
+    let flags = wgpu::InstanceFlags::from_build_config().with_env();
+
+    // SAFETY: every Vulkan object is created through the runtime from the
+    // loaded entry, with the extensions and features wgpu-hal lists for it,
+    // and handed to wgpu-hal, which owns and destroys them
+    unsafe {
+        let entry = ash::Entry::load().context("Failed to load the Vulkan library")?;
+        let get_instance_proc_addr = std::mem::transmute::<
+            vk::PFN_vkGetInstanceProcAddr,
+            xr::sys::platform::VkGetInstanceProcAddr,
+        >(entry.static_fn().get_instance_proc_addr);
+        let extensions =
+            wgpu::hal::vulkan::Instance::desired_extensions(&entry, VULKAN_VERSION, flags)?;
+        let extension_names: Vec<_> = extensions.iter().map(|name| name.as_ptr()).collect();
+        let app_info = vk::ApplicationInfo::default()
+            .application_name(c"qsi")
+            .engine_name(c"qsi")
+            .api_version(VULKAN_VERSION);
+        let create_info = vk::InstanceCreateInfo::default()
+            .application_info(&app_info)
+            .enabled_extension_names(&extension_names);
+        let raw_instance = instance
+            .create_vulkan_instance(
+                system,
+                get_instance_proc_addr,
+                &create_info as *const _ as *const _,
+            )?
+            .map_err(vk::Result::from_raw)
+            .context("Failed to create the Vulkan instance")?;
+        let vk_instance =
+            ash::Instance::load(entry.static_fn(), vk::Instance::from_raw(raw_instance as _));
+        let physical_device = vk::PhysicalDevice::from_raw(
+            instance.vulkan_graphics_device(system, raw_instance)? as _,
+        );
+        if vk_instance
+            .get_physical_device_properties(physical_device)
+            .api_version
+            < VULKAN_VERSION
+        {
+            bail!("The headset's GPU does not support Vulkan 1.1");
+        }
+        let queue_family_index = vk_instance
+            .get_physical_device_queue_family_properties(physical_device)
+            .iter()
+            .position(|family| family.queue_flags.contains(vk::QueueFlags::GRAPHICS))
+            .context("The headset's GPU has no graphics queue")?
+            as u32;
+
+        let hal_instance = wgpu::hal::vulkan::Instance::from_raw(
+            entry,
+            vk_instance.clone(),
+            VULKAN_VERSION,
+            0,
+            None,
+            extensions,
+            flags,
+            Default::default(),
+            false,
+            None,
+        )?;
+        let exposed = hal_instance
+            .expose_adapter(physical_device)
+            .context("wgpu cannot use the headset's GPU")?;
+        let wgpu_instance = wgpu::Instance::from_hal::<Vulkan>(hal_instance);
+        let adapter = wgpu_instance.create_adapter_from_hal(exposed);
+        let features = config.device_features(&adapter);
+
+        let (hal_device, raw_device) = {
+            let hal_adapter = adapter
+                .as_hal::<Vulkan>()
+                .context("The headset's adapter is not a Vulkan adapter")?;
+            let extensions = hal_adapter.required_device_extensions(features);
+            let extension_names: Vec<_> = extensions.iter().map(|name| name.as_ptr()).collect();
+            let mut enabled_features = hal_adapter.physical_device_features(&extensions, features);
+            let priorities = [1.0];
+            let queues = [vk::DeviceQueueCreateInfo::default()
+                .queue_family_index(queue_family_index)
+                .queue_priorities(&priorities)];
+            let create_info = enabled_features.add_to_device_create(
+                vk::DeviceCreateInfo::default()
+                    .queue_create_infos(&queues)
+                    .enabled_extension_names(&extension_names),
+            );
+            let raw_device = instance
+                .create_vulkan_device(
+                    system,
+                    get_instance_proc_addr,
+                    physical_device.as_raw() as _,
+                    &create_info as *const _ as *const _,
+                )?
+                .map_err(vk::Result::from_raw)
+                .context("Failed to create the Vulkan device")?;
+            let vk_device =
+                ash::Device::load(vk_instance.fp_v1_0(), vk::Device::from_raw(raw_device as _));
+            let hal_device = hal_adapter.device_from_raw(
+                vk_device,
+                None,
+                &extensions,
+                features,
+                &wgpu::MemoryHints::Performance,
+                queue_family_index,
+                0,
+            )?;
+            (hal_device, raw_device)
+        };
+        let (device, queue) = adapter
+            .create_device_from_hal(
+                hal_device,
+                &wgpu::DeviceDescriptor {
+                    label: Some("Main Device"),
+                    required_features: features,
+                    required_limits: config.limits.clone(),
+                    memory_hints: Default::default(),
+                    trace: Default::default(),
+                },
+            )
+            .context("Failed to create logical device and command queue")?;
+
+        let session_info = xr::vulkan::SessionCreateInfo {
+            instance: raw_instance,
+            physical_device: physical_device.as_raw() as _,
+            device: raw_device,
+            queue_family_index,
+            queue_index: 0,
+        };
+        let gpu = SharedGpu {
+            instance: wgpu_instance,
+            adapter,
+            device,
+            queue,
+        };
+        Ok((gpu, session_info))
+    }
+}
+
+/// Create the controller actions, suggest bindings for the common
+/// controllers, and attach them to `session`
+fn create_controls(instance: &xr::Instance, session: &xr::Session<xr::Vulkan>) -> Result<Controls> {
+    let sides = [(Hand::Left, "left"), (Hand::Right, "right")];
+    let paths = sides
+        .iter()
+        .map(|(_, side)| instance.string_to_path(&format!("/user/hand/{side}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    let set = instance.create_action_set("qsi", "qsi controllers", 0)?;
+    let pose = set.create_action::<xr::Posef>("grip_pose", "Grip pose", &paths)?;
+    let trigger = set.create_action::<f32>("trigger", "Trigger", &paths)?;
+    let grip = set.create_action::<f32>("grip", "Grip", &paths)?;
+    let thumbstick = set.create_action::<xr::Vector2f>("thumbstick", "Thumbstick", &paths)?;
+    let primary = set.create_action::<bool>("primary_button", "Primary button", &paths)?;
+
+    for profile in &PROFILES {
+        let mut bindings = Vec::new();
+        for (index, (_, side)) in sides.iter().enumerate() {
+            let input =
+                |name: &str| instance.string_to_path(&format!("/user/hand/{side}/input/{name}"));
+            bindings.push(xr::Binding::new(&pose, input("grip/pose")?));
+            bindings.push(xr::Binding::new(&trigger, input(profile.trigger)?));
+            bindings.push(xr::Binding::new(&primary, input(profile.primary[index])?));
+            if let Some(name) = profile.grip {
+                bindings.push(xr::Binding::new(&grip, input(name)?));
+            }
+            if let Some(name) = profile.thumbstick {
+                bindings.push(xr::Binding::new(&thumbstick, input(name)?));
+            }
+        }
+        // Runtimes may not know every profile; the others still apply
+        if let Err(err) = instance
+            .string_to_path(profile.path)
+            .and_then(|path| instance.suggest_interaction_profile_bindings(path, &bindings))
+        {
+            log::warn!("OpenXR rejected bindings for {}: {err}", profile.path);
+        }
+    }
+    session.attach_action_sets(&[&set])?;
+
+    let hands = sides
+        .iter()
+        .zip(paths)
+        .map(|(&(hand, _), path)| {
+            let space = pose.create_space(session, path, xr::Posef::IDENTITY)?;
+            Ok((hand, path, space))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Controls {
+        set,
+        pose,
+        trigger,
+        grip,
+        thumbstick,
+        primary,
+        hands,
+    })
+}
+
+/// Create the eye swapchain at the headset's recommended size and wrap its
+/// images as wgpu textures on `device`
+fn create_swapchain(
+    instance: &xr::Instance,
+    system: xr::SystemId,
+    session: &xr::Session<xr::Vulkan>,
+    device: &wgpu::Device,
+) -> Result<Swapchain> {
+    let supported = session.enumerate_swapchain_formats()?;
+    let (vk_format, format) = FORMATS
+        .into_iter()
+        .find(|(vk_format, _)| supported.contains(&(vk_format.as_raw() as _)))
+        .context("The OpenXR runtime offers no sRGB RGBA8 swapchain format")?;
+    let views = instance.enumerate_view_configuration_views(system, VIEW_TYPE)?;
+    let width = views
+        .iter()
+        .map(|view| view.recommended_image_rect_width)
+        .max();
+    let height = views
+        .iter()
+        .map(|view| view.recommended_image_rect_height)
+        .max();
+    let (Some(width), Some(height)) = (width, height) else {
+        bail!("The headset reports no views");
+    };
+    let handle = session.create_swapchain(&xr::SwapchainCreateInfo {
+        create_flags: xr::SwapchainCreateFlags::EMPTY,
+        usage_flags: xr::SwapchainUsageFlags::COLOR_ATTACHMENT | xr::SwapchainUsageFlags::SAMPLED,
+        format: vk_format.as_raw() as _,
+        sample_count: 1,
+        width,
+        height,
+        face_count: 1,
+        array_size: 2,
+        mip_count: 1,
+    })?;
+
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 2,
+    };
+    let views = handle
+        .enumerate_images()?
+        .into_iter()
+        .map(|image| {
+            // SAFETY: the image was created by the runtime on `device` with
+            // this size, format, and usage; the no-op drop callback leaves
+            // destroying it to the runtime
+            let texture = unsafe {
+                let raw = device
+                    .as_hal::<Vulkan>()
+                    .context("The renderer's device is not a Vulkan device")?
+                    .texture_from_raw(
+                        vk::Image::from_raw(image),
+                        &wgpu::hal::TextureDescriptor {
+                            label: Some("XR Eye Image"),
+                            size,
+                            mip_level_count: 1,
+                            sample_count: 1,
+                            dimension: wgpu::TextureDimension::D2,
+                            format,
+                            usage: wgpu::TextureUses::COLOR_TARGET | wgpu::TextureUses::RESOURCE,
+                            memory_flags: wgpu::hal::MemoryFlags::empty(),
+                            view_formats: Vec::new(),
+                        },
+                        Some(Box::new(|| {})),
+                    );
+                device.create_texture_from_hal::<Vulkan>(
+                    raw,
+                    &wgpu::TextureDescriptor {
+                        label: Some("XR Eye Image"),
+                        size,
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format,
+                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                            | wgpu::TextureUsages::TEXTURE_BINDING,
+                        view_formats: &[],
+                    },
+                )
+            };
+            Ok([0, 1].map(|layer| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("XR Eye View"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Swapchain {
+        views,
+        handle,
+        format,
+        size: (width, height),
+    })
+}
+
+fn pose(pose: xr::Posef) -> Pose {
+    let (position, orientation) = (pose.position, pose.orientation);
+    Pose {
+        position: Vector3::new(position.x, position.y, position.z),
+        orientation: Quaternion::new(orientation.w, orientation.x, orientation.y, orientation.z),
+    }
+}
+
+fn fov(fov: xr::Fovf) -> Fov {
+    Fov {
+        left: Rad(fov.angle_left),
+        right: Rad(fov.angle_right),
+        up: Rad(fov.angle_up),
+        down: Rad(fov.angle_down),
+    }
+}
+
+/// Plugin showing the app in the headset of the system's OpenXR runtime
+///
+/// Starts the session and the Vulkan device the runtime picks, has the
+/// renderer draw on that device, and adds `XrPlugin`. Without a runtime or
+/// headset it logs why and the app runs as usual. The runtime paces frames,
+/// so pair it with `with_headless` or vsync off (`RendererConfig::with_vsync`)
+/// to keep a mirror window from halving the headset's frame rate.
+pub struct OpenXrPlugin;
+
+impl crate::plugin::Plugin for OpenXrPlugin {
+    fn build(&self, app: &mut App) {
+        match OpenXrRuntime::new(&app.renderer_config) {
+            Ok((runtime, gpu)) => {
+                app.renderer_config.gpu = Some(gpu);
+                app.register_resource(XrRuntimeResource::new(runtime));
+                app.register_plugin(XrPlugin);
+            }
+            Err(err) => log::warn!("Running without a headset: {err:#}"),
+        }
+    }
+}